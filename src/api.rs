@@ -0,0 +1,469 @@
+//! Interprets responses from the chat API and monitors its availability.
+
+use std::{net::IpAddr, time::Duration};
+
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+use serde_json::{json, Value};
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
+
+/// How long a health check waits for the endpoint to answer.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Builds the HTTP client for the API. It sends the API key, if there is
+/// one, as a bearer token with every request: prompts, health checks,
+/// warm-ups, model management and embeddings alike.
+///
+/// # Errors
+///
+/// Returns a `reqwest::Error` if the client cannot be built.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::api;
+///
+/// assert!(api::client(Some("sk-test")).is_ok());
+/// assert!(api::client(None).is_ok());
+/// ```
+pub fn client(api_key: Option<&str>) -> Result<reqwest::Client, reqwest::Error> {
+    let mut headers = HeaderMap::new();
+    if let Some(key) = api_key {
+        match HeaderValue::from_str(&format!("Bearer {key}")) {
+            Ok(mut value) => {
+                value.set_sensitive(true);
+                headers.insert(AUTHORIZATION, value);
+            }
+            Err(err) => warn!(%err, "the API key cannot be sent in a header"),
+        }
+    }
+    reqwest::Client::builder().default_headers(headers).build()
+}
+
+/// Returns `true` if the server behind `endpoint` answers HTTP requests.
+///
+/// Any response counts, including error statuses; only connection failures
+/// and timeouts mean the endpoint is unreachable.
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client.
+/// * `endpoint` - The configured API endpoint; its origin is requested.
+pub async fn check_endpoint(client: &reqwest::Client, endpoint: &str) -> bool {
+    let Ok(mut url) = reqwest::Url::parse(endpoint).and_then(|url| url.join("/")) else {
+        return false;
+    };
+    // A WebSocket endpoint is checked over HTTP on the same host.
+    let scheme = match url.scheme() {
+        "ws" => "http",
+        "wss" => "https",
+        _ => "",
+    };
+    if !scheme.is_empty() {
+        let _ = url.set_scheme(scheme);
+    }
+
+    match client.get(url).timeout(HEALTH_CHECK_TIMEOUT).send().await {
+        Ok(_) => true,
+        Err(err) => {
+            debug!(?err, "endpoint health check failed");
+            false
+        }
+    }
+}
+
+/// Returns `true` if `endpoint` is on this machine: its host is `localhost`
+/// or a loopback address. An endpoint that cannot be parsed is not.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::api::is_local_endpoint;
+///
+/// assert!(is_local_endpoint("http://localhost:11434/api/chat"));
+/// assert!(is_local_endpoint("http://127.0.0.1:8080/completion"));
+/// assert!(is_local_endpoint("http://[::1]:1234/v1/chat/completions"));
+/// assert!(!is_local_endpoint("https://api.openai.com/v1/chat/completions"));
+/// ```
+#[must_use]
+pub fn is_local_endpoint(endpoint: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(endpoint) else {
+        return false;
+    };
+    let Some(host) = url.host_str() else {
+        return false;
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host.parse::<IpAddr>() {
+        Ok(address) => address.is_loopback(),
+        Err(_) => host.eq_ignore_ascii_case("localhost") || host.ends_with(".localhost"),
+    }
+}
+
+/// The readiness of the endpoint, as shown in the status bar.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EndpointStatus {
+    /// The endpoint has not been checked yet.
+    #[default]
+    Checking,
+    /// The endpoint is reachable and the model is being loaded.
+    WarmingUp,
+    /// The endpoint is reachable and ready for prompts.
+    Ready,
+    /// The endpoint could not be reached.
+    Unreachable,
+}
+
+/// Asks an Ollama endpoint to load `model` into memory, so the first real
+/// prompt does not wait for it.
+///
+/// Ollama loads the model and returns immediately for a chat request
+/// without messages.
+///
+/// # Errors
+///
+/// Returns a `reqwest::Error` if the request fails or the endpoint answers
+/// with an error status.
+pub async fn warm_up(
+    client: &reqwest::Client,
+    endpoint: &str,
+    model: &str,
+) -> Result<(), reqwest::Error> {
+    client
+        .post(endpoint)
+        .json(&json!({"model": model, "messages": [], "stream": false}))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Checks the endpoint on startup and optionally warms up the model in the
+/// background.
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client.
+/// * `endpoint` - The configured API endpoint.
+/// * `model` - The configured model.
+/// * `load_model` - Whether to warm up the model once the endpoint answers.
+///
+/// # Returns
+///
+/// A receiver holding the current readiness of the endpoint.
+#[must_use]
+pub fn spawn_startup_check(
+    client: reqwest::Client,
+    endpoint: String,
+    model: String,
+    load_model: bool,
+) -> watch::Receiver<EndpointStatus> {
+    let (tx, rx) = watch::channel(EndpointStatus::Checking);
+    tokio::spawn(async move {
+        if !check_endpoint(&client, &endpoint).await {
+            warn!(endpoint, "endpoint is unreachable");
+            let _ = tx.send(EndpointStatus::Unreachable);
+            return;
+        }
+        if load_model {
+            let _ = tx.send(EndpointStatus::WarmingUp);
+            match warm_up(&client, &endpoint, &model).await {
+                Ok(()) => info!(model, "model warmed up"),
+                Err(err) => warn!(?err, model, "model warm-up failed"),
+            }
+        }
+        let _ = tx.send(EndpointStatus::Ready);
+    });
+    rx
+}
+
+/// Checks the endpoint every `interval` in the background.
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client.
+/// * `endpoint` - The configured API endpoint.
+/// * `interval` - The time between checks.
+///
+/// # Returns
+///
+/// A receiver holding the result of the latest check. It starts out `true`
+/// until the first check completes.
+#[must_use]
+pub fn spawn_health_monitor(
+    client: reqwest::Client,
+    endpoint: String,
+    interval: Duration,
+) -> watch::Receiver<bool> {
+    let (tx, rx) = watch::channel(true);
+    tokio::spawn(async move {
+        loop {
+            let online = check_endpoint(&client, &endpoint).await;
+            if tx.send(online).is_err() {
+                break;
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+    rx
+}
+
+/// Extracts a readable error message from an API error response body.
+///
+/// Understands the shapes used by Ollama (`{"error": "..."}`), OpenAI
+/// (`{"error": {"message", "type", "code"}}`), Anthropic
+/// (`{"type": "error", "error": {"type", "message"}}`) and common
+/// `{"message"}` / `{"detail"}` bodies. The error type or code, if present,
+/// is appended in brackets.
+///
+/// # Arguments
+///
+/// * `body` - The response body.
+///
+/// # Returns
+///
+/// The error message, or `None` if the body has no recognisable message.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::api::parse_error_message;
+///
+/// assert_eq!(
+///     parse_error_message(r#"{"error": "model 'x' not found"}"#).as_deref(),
+///     Some("model 'x' not found")
+/// );
+/// assert_eq!(
+///     parse_error_message(
+///         r#"{"error": {"message": "Incorrect API key provided", "type": "invalid_request_error", "code": "invalid_api_key"}}"#
+///     )
+///     .as_deref(),
+///     Some("Incorrect API key provided [invalid_api_key]")
+/// );
+/// assert_eq!(parse_error_message("<html>Bad Gateway</html>"), None);
+/// ```
+#[must_use]
+pub fn parse_error_message(body: &str) -> Option<String> {
+    let json: Value = serde_json::from_str(body).ok()?;
+
+    let error = &json["error"];
+    if let Some(message) = non_empty(error) {
+        return Some(message.to_string());
+    }
+
+    let (message, kind) = if error.is_object() {
+        (
+            non_empty(&error["message"]),
+            non_empty(&error["code"]).or_else(|| non_empty(&error["type"])),
+        )
+    } else if let Some(first) = json["errors"].get(0) {
+        (
+            non_empty(&first["message"]).or_else(|| non_empty(first)),
+            None,
+        )
+    } else {
+        (
+            non_empty(&json["message"]).or_else(|| non_empty(&json["detail"])),
+            non_empty(&json["code"]),
+        )
+    };
+
+    match (message, kind) {
+        (Some(message), Some(kind)) => Some(format!("{message} [{kind}]")),
+        (Some(message), None) => Some(message.to_string()),
+        (None, _) => None,
+    }
+}
+
+fn non_empty(value: &Value) -> Option<&str> {
+    value.as_str().map(str::trim).filter(|s| !s.is_empty())
+}
+
+/// Decodes a stream of newline-delimited JSON values, however its bytes are
+/// split into network chunks.
+///
+/// Values are parsed as soon as they are complete, so an object split
+/// across chunks, or a multi-byte character split between them, is kept
+/// until the rest arrives. Values spread over several lines are accepted as
+/// well. A malformed line is reported as an error and skipped, without
+/// losing the values around it.
+#[derive(Debug, Default)]
+pub struct JsonLines {
+    buffer: Vec<u8>,
+}
+
+impl JsonLines {
+    /// Creates an empty decoder.
+    #[must_use]
+    pub fn new() -> Self {
+        JsonLines::default()
+    }
+
+    /// Adds the bytes of a chunk.
+    ///
+    /// # Returns
+    ///
+    /// The values completed by the chunk, and an error for each malformed
+    /// line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::api::JsonLines;
+    ///
+    /// let mut lines = JsonLines::new();
+    /// assert!(lines.push(br#"{"message":{"content":"Hel"#).is_empty());
+    /// let values = lines.push(b"lo\"}}\n{\"done\":true}\n");
+    /// assert_eq!(values.len(), 2);
+    /// assert_eq!(values[0].as_ref().unwrap()["message"]["content"], "Hello");
+    /// ```
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Result<Value, serde_json::Error>> {
+        self.buffer.extend_from_slice(chunk);
+        let mut values = Vec::new();
+        let mut start = 0;
+        loop {
+            let mut stream =
+                serde_json::Deserializer::from_slice(&self.buffer[start..]).into_iter::<Value>();
+            let error = loop {
+                match stream.next() {
+                    Some(Ok(value)) => values.push(Ok(value)),
+                    Some(Err(err)) => break Some(err),
+                    None => break None,
+                }
+            };
+            let parsed = start + stream.byte_offset();
+            match error {
+                None => {
+                    start = self.buffer.len();
+                    break;
+                }
+                Some(err) if err.is_eof() => {
+                    start = parsed;
+                    break;
+                }
+                Some(err) => {
+                    let rest = &self.buffer[parsed..];
+                    let line = rest
+                        .iter()
+                        .position(|b| !b.is_ascii_whitespace())
+                        .unwrap_or_default();
+                    match rest[line..].iter().position(|&b| b == b'\n') {
+                        Some(end) => {
+                            values.push(Err(err));
+                            start = parsed + line + end + 1;
+                        }
+                        // The rest of the line may still arrive.
+                        None => {
+                            start = parsed;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        self.buffer.drain(..start);
+        values
+    }
+
+    /// Ends the stream.
+    ///
+    /// # Returns
+    ///
+    /// The value left without a trailing newline, an error if the stream
+    /// ended in the middle of a value, or `None` if nothing was left.
+    pub fn finish(self) -> Option<Result<Value, serde_json::Error>> {
+        if self.buffer.iter().all(u8::is_ascii_whitespace) {
+            return None;
+        }
+        Some(serde_json::from_slice(&self.buffer))
+    }
+}
+
+/// Decodes a stream of server-sent events whose data are JSON values, as
+/// sent by llama.cpp and OpenAI-compatible servers, from bytes split into
+/// network chunks.
+///
+/// Only complete lines are decoded, so a multi-byte character split between
+/// chunks is kept until the rest arrives. The `[DONE]` marker some servers
+/// end the stream with is not decoded but noted, and comments and other
+/// fields are skipped.
+#[derive(Debug, Default)]
+pub struct ServerSentEvents {
+    buffer: Vec<u8>,
+    data: Vec<String>,
+    done: bool,
+}
+
+impl ServerSentEvents {
+    /// Creates an empty decoder.
+    #[must_use]
+    pub fn new() -> Self {
+        ServerSentEvents::default()
+    }
+
+    /// Adds the bytes of a chunk.
+    ///
+    /// # Returns
+    ///
+    /// The data of the events completed by the chunk, and an error for each
+    /// event whose data is not valid JSON.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::api::ServerSentEvents;
+    ///
+    /// let mut events = ServerSentEvents::new();
+    /// assert!(events.push(b"data: {\"content\":\"Hel").is_empty());
+    /// let values = events.push(b"lo\"}\n\ndata: [DONE]\n\n");
+    /// assert_eq!(values.len(), 1);
+    /// assert_eq!(values[0].as_ref().unwrap()["content"], "Hello");
+    /// assert!(events.is_done());
+    /// ```
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<Result<Value, serde_json::Error>> {
+        self.buffer.extend_from_slice(chunk);
+        let mut values = Vec::new();
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            values.extend(self.line(line.trim_end_matches(['\n', '\r'])));
+        }
+        values
+    }
+
+    /// Returns `true` once the `[DONE]` marker arrived.
+    #[must_use]
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    /// Ends the stream.
+    ///
+    /// # Returns
+    ///
+    /// The data of an event the stream ended in without a blank line, if
+    /// any.
+    pub fn finish(mut self) -> Option<Result<Value, serde_json::Error>> {
+        let rest = String::from_utf8_lossy(&std::mem::take(&mut self.buffer)).into_owned();
+        self.line(rest.trim_end()).or_else(|| self.line(""))
+    }
+
+    /// Handles a line of the stream, returning the event it completes.
+    fn line(&mut self, line: &str) -> Option<Result<Value, serde_json::Error>> {
+        if line.is_empty() {
+            let data = std::mem::take(&mut self.data).join("\n");
+            if data == "[DONE]" {
+                self.done = true;
+            }
+            if data.is_empty() || self.done {
+                return None;
+            }
+            return Some(serde_json::from_str(&data));
+        }
+        if let Some(data) = line.strip_prefix("data:") {
+            self.data
+                .push(data.strip_prefix(' ').unwrap_or(data).to_string());
+        }
+        None
+    }
+}
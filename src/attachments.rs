@@ -0,0 +1,679 @@
+//! Turns files into context attached to the next message.
+//!
+//! Dropping a file onto most terminals pastes its path, quoted or with its
+//! spaces escaped the way a shell would need it. A paste made only of
+//! absolute paths to existing files is offered as attachments instead of
+//! being typed into the input.
+//!
+//! PDF and DOCX documents are attached as their text, with page and
+//! section markers, and CSV and TSV tables as a sample of their rows. Other
+//! binary files are refused, and files too large to attach whole keep only
+//! their first and last lines, which are all that is read of them. Before
+//! a dropped file is attached, its size and the tokens it adds to the
+//! prompt are shown for confirmation.
+//!
+//! `/attach` takes glob patterns or directories instead, and attaches every
+//! file they match that git does not ignore, each under its path, until a
+//! total size budget is used up. Hidden, `target` and `node_modules`
+//! directories are not searched, unless a pattern names them.
+
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use glob::{MatchOptions, Pattern};
+
+use crate::{
+    commands::expand_home,
+    documents::{self, DocumentError, Kind},
+    git,
+    models::format_size,
+    tables::{self, Sample, Sampling},
+    tokens::estimate_tokens,
+};
+
+/// Returns the files a paste names, if it is nothing but absolute paths to
+/// existing files, as terminals paste them when files are dropped onto
+/// them: separated by spaces or lines, quoted or with escaped spaces, or as
+/// `file://` URLs.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::attachments::dropped_files;
+///
+/// let cargo = std::fs::canonicalize("Cargo.toml").unwrap();
+/// assert_eq!(dropped_files(&format!("'{}' ", cargo.display())), vec![cargo]);
+/// assert!(dropped_files("Cargo.toml").is_empty());
+/// assert!(dropped_files("/no/such/file.txt").is_empty());
+/// ```
+#[must_use]
+pub fn dropped_files(pasted: &str) -> Vec<PathBuf> {
+    let pasted = pasted.trim();
+    if pasted.is_empty() {
+        return Vec::new();
+    }
+    // One path per line, possibly with unescaped spaces.
+    let lines: Option<Vec<PathBuf>> = pasted
+        .lines()
+        .map(|line| existing_file(line.trim()))
+        .collect();
+    if let Some(files) = lines {
+        return files;
+    }
+    let files: Option<Vec<PathBuf>> = split_paths(pasted)
+        .iter()
+        .map(|word| existing_file(word))
+        .collect();
+    files.unwrap_or_default()
+}
+
+/// Returns the path a pasted word names if it is an absolute path to an
+/// existing file.
+fn existing_file(word: &str) -> Option<PathBuf> {
+    let path = match word.strip_prefix("file://") {
+        Some(url) => PathBuf::from(percent_decode(url)),
+        None => PathBuf::from(word),
+    };
+    (path.is_absolute() && path.is_file()).then_some(path)
+}
+
+/// Splits pasted text into words the way a shell would, honouring single
+/// and double quotes and backslash escapes.
+fn split_paths(text: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quote = None;
+    let mut in_word = false;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some('"') | None, '\\') => {
+                word.extend(chars.next());
+                in_word = true;
+            }
+            (Some(_), c) => word.push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (None, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+/// Decodes the `%XX` escapes of a URL path.
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| text.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Represents errors that can occur while attaching a file.
+#[derive(Debug, thiserror::Error)]
+pub enum AttachmentError {
+    /// The file could not be read.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// The file is not text, so it is not attached.
+    #[error("{name} is a binary file ({size}), not text")]
+    Binary { name: String, size: String },
+    /// The text of a PDF or DOCX document could not be extracted.
+    #[error("{name}: {source}")]
+    Document { name: String, source: DocumentError },
+    /// A document or table is too large to be read whole.
+    #[error("{name} is too large to attach ({size}, at most {max})")]
+    TooLarge {
+        name: String,
+        size: String,
+        max: String,
+    },
+    /// A pattern given to `/attach` is not a valid glob pattern.
+    #[error("invalid pattern: {0}")]
+    Pattern(#[from] glob::PatternError),
+}
+
+/// A file read to be attached to a message, cut to its head and tail if it
+/// is too large to attach whole, or sampled if it is a table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    /// The file.
+    pub path: PathBuf,
+    /// The size of the file in bytes.
+    pub size: u64,
+    /// The text attached.
+    pub text: String,
+    /// The estimated tokens of the whole file.
+    pub full_tokens: usize,
+    /// Whether the middle of the file was left out.
+    pub truncated: bool,
+    /// The sample attached of a CSV or TSV table.
+    pub table: Option<Sample>,
+}
+
+impl Attachment {
+    /// Reads a file to attach it, extracting the text of PDF and DOCX
+    /// documents. CSV and TSV tables with more rows than the sampling keeps
+    /// are attached as a sample. Other files larger than [`MAX_BYTES`] keep
+    /// only their first and last lines, with a note of what was left out,
+    /// and only those are read. Documents and tables, which are read whole,
+    /// are refused above [`MAX_WHOLE_BYTES`].
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file.
+    /// * `sampling` - How tables are sampled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `AttachmentError` if the file cannot be read, is binary,
+    /// is too large, or is a document whose text cannot be extracted.
+    pub fn load(path: &Path, sampling: Sampling) -> Result<Self, AttachmentError> {
+        let size = fs::metadata(path)?.len();
+        let binary = || AttachmentError::Binary {
+            name: file_name(path),
+            size: format_size(size),
+        };
+        let read_whole = Kind::of(path).is_some() || tables::delimiter(path).is_some();
+        if read_whole && size > MAX_WHOLE_BYTES {
+            return Err(AttachmentError::TooLarge {
+                name: file_name(path),
+                size: format_size(size),
+                max: format_size(MAX_WHOLE_BYTES),
+            });
+        }
+        if !read_whole && size > MAX_BYTES as u64 {
+            let text = read_head_and_tail(path, size)?.ok_or_else(binary)?;
+            return Ok(Attachment {
+                path: path.to_path_buf(),
+                size,
+                truncated: true,
+                // Roughly four bytes per token, since most of it is unread.
+                full_tokens: usize::try_from(size.div_ceil(4)).unwrap_or(usize::MAX),
+                text,
+                table: None,
+            });
+        }
+
+        let bytes = fs::read(path)?;
+        let content = if let Some(kind) = Kind::of(path) {
+            documents::extract(kind, &bytes).map_err(|source| AttachmentError::Document {
+                name: file_name(path),
+                source,
+            })?
+        } else if looks_binary(&bytes) {
+            return Err(binary());
+        } else {
+            String::from_utf8(bytes).map_err(|_| binary())?
+        };
+        let table =
+            tables::delimiter(path).and_then(|delimiter| Sample::of(&content, delimiter, sampling));
+        let sampled = match &table {
+            Some(sample) if !sample.is_complete() => Some(sample.text()),
+            _ => None,
+        };
+        let attached = sampled.as_deref().unwrap_or(&content);
+        let text = head_and_tail(attached, MAX_BYTES);
+        Ok(Attachment {
+            path: path.to_path_buf(),
+            size,
+            truncated: text.len() < attached.len(),
+            full_tokens: estimate_tokens(&content),
+            text: text.into_owned(),
+            table,
+        })
+    }
+
+    /// Returns the estimated tokens the attachment adds to the prompt.
+    #[must_use]
+    pub fn tokens(&self) -> usize {
+        estimate_tokens(&self.text)
+    }
+
+    /// Describes the attachment and its token impact for confirming it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::attachments::Attachment;
+    /// use std::path::PathBuf;
+    ///
+    /// let attachment = Attachment {
+    ///     path: PathBuf::from("/tmp/notes.txt"),
+    ///     size: 2300,
+    ///     text: "a".repeat(2300),
+    ///     full_tokens: 575,
+    ///     truncated: false,
+    ///     table: None,
+    /// };
+    /// assert_eq!(attachment.summary(), "notes.txt: 2.3 KB, ~575 tokens");
+    /// ```
+    #[must_use]
+    pub fn summary(&self) -> String {
+        let name = file_name(&self.path);
+        if let Some(sample) = self.table.as_ref().filter(|sample| !sample.is_complete()) {
+            format!(
+                "{name}: {} (~{} tokens), {} would be attached with statistics of each column, ~{} tokens",
+                format_size(self.size),
+                self.full_tokens,
+                sample.strategy_note(),
+                self.tokens()
+            )
+        } else if self.truncated {
+            format!(
+                "{name}: {} (~{} tokens), too large to attach whole; its first and last lines would be attached, ~{} tokens",
+                format_size(self.size),
+                self.full_tokens,
+                self.tokens()
+            )
+        } else {
+            format!(
+                "{name}: {}, ~{} tokens",
+                format_size(self.size),
+                self.tokens()
+            )
+        }
+    }
+
+    /// Returns the context attached to the message: the text under the
+    /// file's path.
+    #[must_use]
+    pub fn context(&self) -> String {
+        format!("File `{}`:\n{}", self.path.display(), self.text.trim_end())
+    }
+}
+
+/// The files matched by the patterns given to `/attach`, read within a size
+/// budget.
+#[derive(Debug, Default)]
+pub struct Batch {
+    /// The files attached, with their paths as matched.
+    pub attached: Vec<Attachment>,
+    /// The number of matching files git ignores.
+    pub ignored: usize,
+    /// The files left out because they did not fit in the budget.
+    pub over_budget: Vec<PathBuf>,
+    /// Whether the search stopped at [`MAX_FILES`] matching files.
+    pub too_many: bool,
+    /// The files that could not be attached, and why.
+    pub failed: Vec<(PathBuf, AttachmentError)>,
+}
+
+impl Batch {
+    /// Reads the files matching glob patterns, such as `src/**/*.rs`. A
+    /// directory stands for every file below it. Hidden files, files in
+    /// [`SKIPPED_DIRS`] and files ignored by git are skipped, and files that
+    /// would take the attached text over `budget` bytes are left out. Once
+    /// the budget is used up, no more files are read.
+    ///
+    /// # Arguments
+    ///
+    /// * `patterns` - The patterns, relative to `dir` or absolute.
+    /// * `dir` - The directory relative patterns are resolved in.
+    /// * `budget` - The most bytes of text attached in all.
+    /// * `sampling` - How tables are sampled.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `AttachmentError` if a pattern is not valid.
+    pub fn collect(
+        patterns: &[String],
+        dir: &Path,
+        budget: usize,
+        sampling: Sampling,
+    ) -> Result<Self, AttachmentError> {
+        let options = MatchOptions {
+            require_literal_leading_dot: true,
+            ..MatchOptions::default()
+        };
+        let mut batch = Batch::default();
+        let mut seen = HashSet::new();
+        let mut files = Vec::new();
+        for pattern in patterns {
+            let mut full = dir.join(expand_home(pattern));
+            if full.is_dir() {
+                full.push("**/*");
+            }
+            let text = full.to_string_lossy();
+            let matcher = Pattern::new(&text)?;
+            let mut found = Vec::new();
+            batch.too_many |= !walk(&literal_prefix(&full), &matcher, options, &mut found);
+            for path in found {
+                let path = path
+                    .strip_prefix(dir)
+                    .map_or(path.clone(), Path::to_path_buf);
+                if seen.insert(path.clone()) {
+                    files.push(path);
+                }
+            }
+            if files.len() >= MAX_FILES {
+                batch.too_many = true;
+                files.truncate(MAX_FILES);
+                break;
+            }
+        }
+
+        let ignored: HashSet<PathBuf> = git::ignored(dir, &files)
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+        let mut used = 0;
+        for path in files {
+            if ignored.contains(&path) {
+                batch.ignored += 1;
+                continue;
+            }
+            if used >= budget {
+                batch.over_budget.push(path);
+                continue;
+            }
+            match Attachment::load(&dir.join(&path), sampling) {
+                Ok(mut attachment) if used + attachment.text.len() <= budget => {
+                    used += attachment.text.len();
+                    attachment.path = path;
+                    batch.attached.push(attachment);
+                }
+                Ok(_) => batch.over_budget.push(path),
+                Err(err) => batch.failed.push((path, err)),
+            }
+        }
+        Ok(batch)
+    }
+
+    /// Returns `true` if the patterns matched no file.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.attached.is_empty()
+            && self.over_budget.is_empty()
+            && self.failed.is_empty()
+            && self.ignored == 0
+    }
+
+    /// Returns the context attached to the message: each file under its
+    /// path.
+    #[must_use]
+    pub fn context(&self) -> String {
+        self.attached
+            .iter()
+            .map(Attachment::context)
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    /// Describes what was attached and what was left out, as the text of a
+    /// system message.
+    ///
+    /// # Arguments
+    ///
+    /// * `budget` - The budget the files were read within, in bytes.
+    #[must_use]
+    pub fn describe(&self, budget: usize) -> String {
+        let bytes: usize = self.attached.iter().map(|a| a.text.len()).sum();
+        let tokens: usize = self.attached.iter().map(Attachment::tokens).sum();
+        let mut text = if self.attached.is_empty() {
+            String::from("No files attached")
+        } else {
+            format!(
+                "Attached {} file(s) to the next message, {}, ~{tokens} tokens:",
+                self.attached.len(),
+                format_size(bytes as u64)
+            )
+        };
+        for attachment in &self.attached {
+            text.push_str(&format!("\n- {}", attachment.path.display()));
+        }
+        if !self.over_budget.is_empty() {
+            let paths: Vec<String> = self
+                .over_budget
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            text.push_str(&format!(
+                "\n\nLeft out to stay within {}: {}",
+                format_size(budget as u64),
+                paths.join(", ")
+            ));
+        }
+        if self.ignored > 0 {
+            text.push_str(&format!(
+                "\n\nSkipped {} file(s) ignored by git",
+                self.ignored
+            ));
+        }
+        if self.too_many {
+            text.push_str(&format!(
+                "\n\nStopped looking after {MAX_FILES} matching files"
+            ));
+        }
+        for (path, err) in &self.failed {
+            text.push_str(&format!("\n\nNot attaching {}: {err}", path.display()));
+        }
+        text
+    }
+}
+
+/// The most files `/attach` looks at, so a pattern matching a huge tree
+/// does not run for long.
+pub const MAX_FILES: usize = 2000;
+
+/// Directories `/attach` does not search, besides hidden ones, unless a
+/// pattern names them.
+pub const SKIPPED_DIRS: [&str; 2] = ["target", "node_modules"];
+
+/// Returns the part of a pattern before its first component with a
+/// wildcard: the directory to search for its matches.
+fn literal_prefix(pattern: &Path) -> PathBuf {
+    pattern
+        .components()
+        .take_while(|part| !part.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+        .collect()
+}
+
+/// Adds the files below `path`, or `path` itself, that match a pattern, in
+/// sorted order, without entering hidden directories or [`SKIPPED_DIRS`].
+///
+/// # Returns
+///
+/// `false` if the search stopped at [`MAX_FILES`] files.
+fn walk(path: &Path, pattern: &Pattern, options: MatchOptions, found: &mut Vec<PathBuf>) -> bool {
+    if path.is_file() {
+        if pattern.matches_path_with(path, options) {
+            found.push(path.to_path_buf());
+        }
+        return found.len() < MAX_FILES;
+    }
+    let Ok(entries) = fs::read_dir(path) else {
+        return true;
+    };
+    let mut entries: Vec<_> = entries.flatten().collect();
+    entries.sort_by_key(fs::DirEntry::file_name);
+    for entry in entries {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        let path = entry.path();
+        let searched = match entry.file_type() {
+            Ok(kind) if kind.is_dir() => {
+                name.starts_with('.')
+                    || SKIPPED_DIRS.contains(&name.as_ref())
+                    || walk(&path, pattern, options, found)
+            }
+            Ok(_) => {
+                if pattern.matches_path_with(&path, options) {
+                    found.push(path);
+                }
+                found.len() < MAX_FILES
+            }
+            Err(_) => true,
+        };
+        if !searched {
+            return false;
+        }
+    }
+    true
+}
+
+/// The most bytes of a file attached; larger files are cut to their head and
+/// tail.
+pub const MAX_BYTES: usize = 100 * 1024;
+
+/// The largest document or table attached, since they are read whole to
+/// extract their text or sample their rows.
+pub const MAX_WHOLE_BYTES: u64 = 50 * 1024 * 1024;
+
+/// How many bytes at the start of a file are checked for NUL bytes.
+const SNIFF_BYTES: usize = 8 * 1024;
+
+/// Returns `true` if the bytes look like a binary file rather than text: a
+/// NUL byte near the start, as text files never have one.
+fn looks_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(SNIFF_BYTES)].contains(&0)
+}
+
+/// Reads the first and last lines of a file too large to attach whole,
+/// about half of [`MAX_BYTES`] each, joined by a note of how much was left
+/// out. Nothing in between is read.
+///
+/// # Returns
+///
+/// The text, or `None` if the file looks binary.
+fn read_head_and_tail(path: &Path, size: u64) -> io::Result<Option<String>> {
+    let half = (MAX_BYTES / 2) as u64;
+    let mut file = fs::File::open(path)?;
+    let mut head = Vec::new();
+    (&mut file).take(half).read_to_end(&mut head)?;
+    file.seek(SeekFrom::End(-i64::try_from(half).unwrap_or(i64::MAX)))?;
+    let mut tail = Vec::new();
+    file.take(half).read_to_end(&mut tail)?;
+    if looks_binary(&head) {
+        return Ok(None);
+    }
+
+    // Keep whole lines, unless a single line is longer than the budget.
+    if let Some(end) = head.iter().rposition(|&b| b == b'\n') {
+        head.truncate(end + 1);
+    }
+    let start = tail
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(0, |start| start + 1);
+    let tail = &tail[start..];
+    let (Some(head), Some(tail)) = (utf8_prefix(&head), utf8_suffix(tail)) else {
+        return Ok(None);
+    };
+    let omitted = size - (head.len() + tail.len()) as u64;
+    Ok(Some(format!(
+        "{head}[... {} left out ...]\n{tail}",
+        format_size(omitted)
+    )))
+}
+
+/// Returns the bytes as text, without a character cut off at the end, or
+/// `None` if they are not UTF-8.
+fn utf8_prefix(bytes: &[u8]) -> Option<&str> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => Some(text),
+        Err(err) if err.error_len().is_none() => {
+            std::str::from_utf8(&bytes[..err.valid_up_to()]).ok()
+        }
+        Err(_) => None,
+    }
+}
+
+/// Returns the bytes as text, without a character cut off at the start, or
+/// `None` if they are not UTF-8.
+fn utf8_suffix(bytes: &[u8]) -> Option<&str> {
+    let continuation = bytes
+        .iter()
+        .take(3)
+        .take_while(|&&b| b & 0xC0 == 0x80)
+        .count();
+    std::str::from_utf8(&bytes[continuation..]).ok()
+}
+
+/// Returns the file name of a path, for messages.
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Cuts text longer than `max` bytes to its first and last lines, about
+/// half of `max` each, joined by a note of how much was left out.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::attachments::head_and_tail;
+///
+/// let log: String = (1..=100).map(|n| format!("line {n}\n")).collect();
+/// let cut = head_and_tail(&log, 40);
+/// assert!(cut.starts_with("line 1\nline 2\n"));
+/// assert!(cut.contains("[... 96 line(s), 761 B left out ...]"));
+/// assert!(cut.ends_with("line 99\nline 100\n"));
+/// assert_eq!(head_and_tail("short", 40), "short");
+/// ```
+#[must_use]
+pub fn head_and_tail(text: &str, max: usize) -> Cow<'_, str> {
+    if text.len() <= max {
+        return Cow::Borrowed(text);
+    }
+    let half = max / 2;
+    let mut head_end = half;
+    while !text.is_char_boundary(head_end) {
+        head_end -= 1;
+    }
+    // Keep whole lines, unless a single line is longer than the budget.
+    head_end = text[..head_end].rfind('\n').map_or(head_end, |end| end + 1);
+    let mut tail_start = text.len() - half;
+    while !text.is_char_boundary(tail_start) {
+        tail_start += 1;
+    }
+    tail_start = text[tail_start..]
+        .find('\n')
+        .map_or(tail_start, |start| tail_start + start + 1);
+    let tail_start = tail_start.max(head_end);
+    let omitted = &text[head_end..tail_start];
+    Cow::Owned(format!(
+        "{}[... {} line(s), {} left out ...]\n{}",
+        &text[..head_end],
+        omitted.lines().count(),
+        format_size(omitted.len() as u64),
+        &text[tail_start..]
+    ))
+}
@@ -0,0 +1,172 @@
+//! Stores API keys in the operating system's keyring.
+//!
+//! Keys are stored per profile with `chatti auth set [profile]`, so they
+//! never need to be written to `config.toml`. When the keyring is not
+//! available, the key is read from the `CHATTI_API_KEY` environment variable
+//! or the `api_key` configuration value instead.
+
+use std::io::{self, BufRead, IsTerminal};
+
+use tracing::warn;
+
+/// The keyring service the keys are stored under.
+const SERVICE: &str = "chatti";
+
+/// The environment variable holding the API key.
+pub const API_KEY_ENV: &str = "CHATTI_API_KEY";
+
+/// The profile used when none is given.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Represents errors that can occur while managing API keys.
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    /// The keyring could not be accessed.
+    #[error("keyring error: {0}")]
+    Keyring(#[from] keyring::Error),
+
+    /// The key could not be read from the terminal.
+    #[error("could not read the key: {0}")]
+    Io(#[from] io::Error),
+
+    /// The command line was not understood.
+    #[error("{0}")]
+    Usage(String),
+}
+
+/// Stores the API key of a profile in the keyring.
+///
+/// # Errors
+///
+/// Returns `AuthError::Keyring` if the keyring is not available.
+pub fn set_key(profile: &str, key: &str) -> Result<(), AuthError> {
+    Ok(keyring::Entry::new(SERVICE, profile)?.set_password(key)?)
+}
+
+/// Removes the API key of a profile from the keyring.
+///
+/// # Errors
+///
+/// Returns `AuthError::Keyring` if the keyring is not available or holds no
+/// key for the profile.
+pub fn delete_key(profile: &str) -> Result<(), AuthError> {
+    Ok(keyring::Entry::new(SERVICE, profile)?.delete_credential()?)
+}
+
+/// Returns the API key of a profile stored in the keyring, if any.
+///
+/// # Errors
+///
+/// Returns `AuthError::Keyring` if the keyring is not available.
+pub fn keyring_key(profile: &str) -> Result<Option<String>, AuthError> {
+    match keyring::Entry::new(SERVICE, profile)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Picks the API key from the available sources, in order of preference:
+/// the keyring, the environment, then the configuration.
+///
+/// # Arguments
+///
+/// * `keyring` - The result of looking up the key in the keyring.
+/// * `env` - The value of the `CHATTI_API_KEY` environment variable.
+/// * `config` - The `api_key` configuration value.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::auth::{choose_key, AuthError};
+///
+/// let unavailable = Err(AuthError::Usage("no keyring".to_string()));
+/// assert_eq!(
+///     choose_key(unavailable, Some("from-env".to_string()), Some("from-config")),
+///     Some("from-env".to_string())
+/// );
+/// assert_eq!(
+///     choose_key(Ok(Some("from-keyring".to_string())), None, Some("from-config")),
+///     Some("from-keyring".to_string())
+/// );
+/// ```
+#[must_use]
+pub fn choose_key(
+    keyring: Result<Option<String>, AuthError>,
+    env: Option<String>,
+    config: Option<&str>,
+) -> Option<String> {
+    let keyring = keyring.unwrap_or_else(|err| {
+        warn!(%err, "keyring unavailable, falling back to the environment and config");
+        None
+    });
+    keyring
+        .or(env)
+        .or_else(|| config.map(str::to_string))
+        .filter(|key| !key.trim().is_empty())
+}
+
+/// Resolves the API key of a profile from the keyring, the `CHATTI_API_KEY`
+/// environment variable or the configuration.
+#[must_use]
+pub fn resolve_key(profile: &str, config: Option<&str>) -> Option<String> {
+    choose_key(
+        keyring_key(profile),
+        std::env::var(API_KEY_ENV).ok(),
+        config,
+    )
+}
+
+/// Runs `chatti auth <set|delete> [profile]`.
+///
+/// `set` reads the key from the terminal without echoing it, or from the
+/// first line of standard input when it is not a terminal.
+///
+/// # Arguments
+///
+/// * `args` - The arguments after `auth`.
+///
+/// # Errors
+///
+/// Returns an `AuthError` if the arguments are invalid, the key cannot be
+/// read, or the keyring is not available.
+pub fn run_command(args: &[String]) -> Result<String, AuthError> {
+    let usage = || AuthError::Usage(String::from("usage: chatti auth <set|delete> [profile]"));
+    let (action, rest) = args.split_first().ok_or_else(usage)?;
+    let profile = match rest {
+        [] => DEFAULT_PROFILE,
+        [profile] => profile.as_str(),
+        _ => return Err(usage()),
+    };
+
+    match action.as_str() {
+        "set" => {
+            let key = read_key(profile)?;
+            if key.is_empty() {
+                return Err(AuthError::Usage(String::from("no key entered")));
+            }
+            set_key(profile, &key)?;
+            Ok(format!(
+                "Stored the API key for profile \"{profile}\" in the keyring"
+            ))
+        }
+        "delete" => {
+            delete_key(profile)?;
+            Ok(format!(
+                "Removed the API key for profile \"{profile}\" from the keyring"
+            ))
+        }
+        _ => Err(usage()),
+    }
+}
+
+fn read_key(profile: &str) -> Result<String, AuthError> {
+    let key = if io::stdin().is_terminal() {
+        rpassword::prompt_password(format!("API key for profile \"{profile}\": "))?
+    } else {
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        line
+    };
+    Ok(key.trim().to_string())
+}
@@ -0,0 +1,226 @@
+//! Compares models on a set of prompts.
+//!
+//! `chatti bench --models llama3,mistral --prompts prompts.txt` sends every
+//! prompt to every model, each on its own rather than as a conversation, and
+//! measures the time to the first token, the total time and the speed of
+//! each answer. A summary table is printed to the terminal, and a markdown
+//! report with the table and all answers side by side is written to a file.
+//! The prompts file is read like a script, so it can be plain text or YAML.
+
+use std::{path::PathBuf, time::Duration};
+
+use crate::script::Answer;
+
+/// The report file written when `--report` is not given.
+pub const DEFAULT_REPORT: &str = "bench-report.md";
+
+/// The command line options of a benchmark, parsed by
+/// [`Mode::parse`](crate::cli::Mode::parse).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Options {
+    /// The models to compare, in the order they are listed.
+    pub models: Vec<String>,
+    /// The prompts to send to each model.
+    pub prompts: PathBuf,
+    /// The file the markdown report is written to.
+    pub report: PathBuf,
+}
+
+/// How one model did over all prompts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    /// The model.
+    pub model: String,
+    /// The number of prompts it answered.
+    pub answered: usize,
+    /// The number of prompts sent to it.
+    pub prompts: usize,
+    /// The mean time to the first token of its answers.
+    pub first_token: Option<Duration>,
+    /// The mean total time of its answers.
+    pub total: Option<Duration>,
+    /// The mean estimated tokens per second of its answers.
+    pub tokens_per_second: Option<f64>,
+}
+
+impl Summary {
+    /// Summarizes the answers of one model.
+    ///
+    /// Failed requests count against the answered prompts but are left out
+    /// of the means.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::bench::Summary;
+    /// use chatti::script::Answer;
+    /// use std::time::Duration;
+    ///
+    /// let answer = |response: Result<&str, &str>, millis| Answer {
+    ///     prompt: "Hi".to_string(),
+    ///     model: "llama3".to_string(),
+    ///     response: response.map(str::to_string).map_err(str::to_string),
+    ///     prompt_tokens: 1,
+    ///     first_token: Some(Duration::from_millis(millis / 4)),
+    ///     elapsed: Duration::from_millis(millis),
+    /// };
+    /// let answers = [answer(Ok("Hello!"), 1000), answer(Ok("Hey"), 3000), answer(Err("timeout"), 0)];
+    /// let summary = Summary::of("llama3", &answers);
+    /// assert_eq!(summary.answered, 2);
+    /// assert_eq!(summary.prompts, 3);
+    /// assert_eq!(summary.total, Some(Duration::from_millis(2000)));
+    /// assert_eq!(summary.first_token, Some(Duration::from_millis(500)));
+    /// ```
+    #[must_use]
+    pub fn of(model: &str, answers: &[Answer]) -> Self {
+        let answered: Vec<&Answer> = answers
+            .iter()
+            .filter(|answer| answer.model == model && answer.response.is_ok())
+            .collect();
+        let first_tokens: Vec<Duration> = answered
+            .iter()
+            .filter_map(|answer| answer.first_token)
+            .collect();
+        let totals: Vec<Duration> = answered.iter().map(|answer| answer.elapsed).collect();
+        let speeds: Vec<f64> = answered
+            .iter()
+            .map(|answer| answer.tokens_per_second())
+            .collect();
+        Summary {
+            model: model.to_string(),
+            answered: answered.len(),
+            prompts: answers
+                .iter()
+                .filter(|answer| answer.model == model)
+                .count(),
+            first_token: mean_duration(&first_tokens),
+            total: mean_duration(&totals),
+            tokens_per_second: mean(&speeds),
+        }
+    }
+
+    /// Returns the cells of the summary's row in a table.
+    fn cells(&self) -> [String; 5] {
+        [
+            self.model.clone(),
+            format!("{}/{}", self.answered, self.prompts),
+            seconds(self.first_token),
+            seconds(self.total),
+            self.tokens_per_second
+                .map_or_else(|| String::from("-"), |speed| format!("{speed:.1}")),
+        ]
+    }
+}
+
+/// The column headers of the summary table.
+const HEADERS: [&str; 5] = ["Model", "Answered", "First token", "Total", "Tok/s"];
+
+/// Formats the summaries as a table aligned for the terminal.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::bench::{table, Summary};
+/// use std::time::Duration;
+///
+/// let summary = Summary {
+///     model: "llama3".to_string(),
+///     answered: 2,
+///     prompts: 2,
+///     first_token: Some(Duration::from_millis(410)),
+///     total: Some(Duration::from_millis(2300)),
+///     tokens_per_second: Some(35.24),
+/// };
+/// assert_eq!(
+///     table(&[summary]),
+///     "Model   Answered  First token  Total  Tok/s\nllama3  2/2       0.41s        2.30s  35.2\n"
+/// );
+/// ```
+#[must_use]
+pub fn table(summaries: &[Summary]) -> String {
+    let rows: Vec<[String; 5]> = std::iter::once(HEADERS.map(str::to_string))
+        .chain(summaries.iter().map(Summary::cells))
+        .collect();
+    let widths: Vec<usize> = (0..HEADERS.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    rows.iter()
+        .map(|row| {
+            let line: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, width)| format!("{cell:width$}"))
+                .collect();
+            format!("{}\n", line.join("  ").trim_end())
+        })
+        .collect()
+}
+
+/// Formats the benchmark as a markdown report: the summary table, then
+/// each prompt with the answer of every model.
+///
+/// # Arguments
+///
+/// * `prompts` - The prompts, in the order they were sent.
+/// * `summaries` - The summary of each model.
+/// * `answers` - The answers of all models.
+#[must_use]
+pub fn report(prompts: &[String], summaries: &[Summary], answers: &[Answer]) -> String {
+    let mut report = String::from("# Benchmark\n\n");
+    report.push_str(&format!("| {} |\n", HEADERS.join(" | ")));
+    report.push_str(&format!("|{}\n", "---|".repeat(HEADERS.len())));
+    for summary in summaries {
+        report.push_str(&format!("| {} |\n", summary.cells().join(" | ")));
+    }
+
+    for (number, prompt) in prompts.iter().enumerate() {
+        report.push_str(&format!(
+            "\n## {}. {}\n",
+            number + 1,
+            prompt.lines().next().unwrap_or_default()
+        ));
+        for summary in summaries {
+            let Some(answer) = answers
+                .iter()
+                .filter(|answer| answer.model == summary.model)
+                .nth(number)
+            else {
+                continue;
+            };
+            let body = match &answer.response {
+                Ok(response) => format!(
+                    "_{} to first token, {:.2}s total, {:.1} tok/s_\n\n{}",
+                    seconds(answer.first_token),
+                    answer.elapsed.as_secs_f64(),
+                    answer.tokens_per_second(),
+                    response.trim_end()
+                ),
+                Err(err) => format!("**Error:** {err}"),
+            };
+            report.push_str(&format!("\n### {}\n\n{body}\n", summary.model));
+        }
+    }
+    report
+}
+
+/// Formats a duration in seconds, or `-` if there is none.
+fn seconds(duration: Option<Duration>) -> String {
+    duration.map_or_else(|| String::from("-"), |d| format!("{:.2}s", d.as_secs_f64()))
+}
+
+/// Returns the mean of durations, or `None` if there are none.
+fn mean_duration(durations: &[Duration]) -> Option<Duration> {
+    let count = u32::try_from(durations.len()).ok().filter(|n| *n > 0)?;
+    Some(durations.iter().sum::<Duration>() / count)
+}
+
+/// Returns the mean of values, or `None` if there are none.
+#[allow(clippy::cast_precision_loss)]
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
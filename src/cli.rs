@@ -0,0 +1,221 @@
+//! Parses the command line.
+//!
+//! Without arguments, the interactive interface starts; `--plain` draws it in
+//! the terminal's normal buffer instead of the alternate screen, and
+//! `--print-on-exit` prints the conversation once it closes. The other
+//! modes run without it: managing API keys, running a script of prompts,
+//! acting as a filter that applies a template to standard input, and
+//! benchmarking models on a set of prompts. `--trace-requests` goes with
+//! any of them.
+
+use std::path::PathBuf;
+
+use crate::bench;
+use crate::script::{Options, OutputFormat};
+use crate::session::TranscriptFormat;
+
+/// The usage text shown for invalid arguments.
+pub const USAGE: &str = "usage: chatti [--plain] [--print-on-exit plain|markdown]
+       chatti --script <path> [--output <path>] [--format markdown|json]
+       chatti --template <name> < input
+       chatti auth <set|delete> [profile]
+       chatti bench --models <a,b,...> --prompts <path> [--report <path>]
+Every mode also takes --trace-requests, which logs each step of chat requests.";
+
+/// The option that logs each step of chat requests, in any mode.
+pub const TRACE_REQUESTS: &str = "--trace-requests";
+
+/// The command line was not understood.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+#[error("{0}\n{USAGE}")]
+pub struct UsageError(pub String);
+
+/// The command line options of the interactive interface.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct InteractiveOptions {
+    /// Draw the interface in the normal buffer rather than the alternate
+    /// screen (`--plain`), so the terminal's own selection and scrollback
+    /// keep working.
+    pub plain: bool,
+    /// Print the conversation in this format to standard output when the
+    /// interface exits (`--print-on-exit`).
+    pub print_on_exit: Option<TranscriptFormat>,
+}
+
+/// Takes `--trace-requests` out of the arguments, since it goes with every
+/// mode.
+///
+/// # Returns
+///
+/// `true` if it was given.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::cli::take_trace_requests;
+///
+/// let mut args = vec!["--plain".to_string(), "--trace-requests".to_string()];
+/// assert!(take_trace_requests(&mut args));
+/// assert_eq!(args, ["--plain"]);
+/// assert!(!take_trace_requests(&mut args));
+/// ```
+pub fn take_trace_requests(args: &mut Vec<String>) -> bool {
+    let before = args.len();
+    args.retain(|arg| arg != TRACE_REQUESTS);
+    args.len() != before
+}
+
+/// What the application was started to do.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Mode {
+    /// Start the interactive interface.
+    Interactive(InteractiveOptions),
+    /// Manage API keys (`chatti auth ...`), with the arguments after `auth`.
+    Auth(Vec<String>),
+    /// Run a script of prompts (`--script`).
+    Script(Options),
+    /// Apply a template to standard input and print the answer
+    /// (`--template`).
+    Filter(String),
+    /// Compare models on a set of prompts (`chatti bench ...`).
+    Bench(bench::Options),
+}
+
+impl Mode {
+    /// Parses the command line arguments, without the program name.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `UsageError` if an option is unknown, is missing its value,
+    /// or does not go with the other options.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::cli::{InteractiveOptions, Mode};
+    /// use chatti::script::{Options, OutputFormat};
+    /// use std::path::PathBuf;
+    ///
+    /// let args = |args: &[&str]| -> Vec<String> { args.iter().map(|a| a.to_string()).collect() };
+    /// assert_eq!(
+    ///     Mode::parse(&[]),
+    ///     Ok(Mode::Interactive(InteractiveOptions::default()))
+    /// );
+    /// assert_eq!(
+    ///     Mode::parse(&args(&["--script", "prompts.txt", "-o", "answers.md"])),
+    ///     Ok(Mode::Script(Options {
+    ///         script: PathBuf::from("prompts.txt"),
+    ///         output: Some(PathBuf::from("answers.md")),
+    ///         format: OutputFormat::Markdown,
+    ///     }))
+    /// );
+    /// assert_eq!(
+    ///     Mode::parse(&args(&["-t", "fix-grammar"])),
+    ///     Ok(Mode::Filter("fix-grammar".to_string()))
+    /// );
+    /// ```
+    pub fn parse(args: &[String]) -> Result<Self, UsageError> {
+        if args.first().is_some_and(|arg| arg == "auth") {
+            return Ok(Mode::Auth(args[1..].to_vec()));
+        }
+        if args.first().is_some_and(|arg| arg == "bench") {
+            return parse_bench(&args[1..]).map(Mode::Bench);
+        }
+
+        let mut script = None;
+        let mut output = None;
+        let mut format = None;
+        let mut template = None;
+        let mut interactive = InteractiveOptions::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .cloned()
+                    .ok_or_else(|| UsageError(format!("{arg} needs a value")))
+            };
+            match arg.as_str() {
+                "--script" | "-s" => script = Some(PathBuf::from(value()?)),
+                "--output" | "-o" => output = Some(PathBuf::from(value()?)),
+                "--format" | "-f" => {
+                    let value = value()?;
+                    format = Some(match value.as_str() {
+                        "markdown" => OutputFormat::Markdown,
+                        "json" => OutputFormat::Json,
+                        _ => return Err(UsageError(format!("unknown format: {value}"))),
+                    });
+                }
+                "--template" | "-t" => template = Some(value()?),
+                "--plain" => interactive.plain = true,
+                "--print-on-exit" => {
+                    let value = value()?;
+                    interactive.print_on_exit = Some(match value.as_str() {
+                        "plain" => TranscriptFormat::Plain,
+                        "markdown" => TranscriptFormat::Markdown,
+                        _ => return Err(UsageError(format!("unknown transcript format: {value}"))),
+                    });
+                }
+                _ => return Err(UsageError(format!("unknown option: {arg}"))),
+            }
+        }
+
+        let batch = script.is_some() || template.is_some();
+        if batch && interactive != InteractiveOptions::default() {
+            return Err(UsageError(String::from(
+                "--plain and --print-on-exit only apply to the interactive interface",
+            )));
+        }
+        match (script, template) {
+            (Some(_), Some(_)) => Err(UsageError(String::from(
+                "--script and --template cannot be combined",
+            ))),
+            (Some(script), None) => Ok(Mode::Script(Options {
+                script,
+                output,
+                format: format.unwrap_or_default(),
+            })),
+            (None, _) if output.is_some() || format.is_some() => Err(UsageError(String::from(
+                "--output and --format need --script",
+            ))),
+            (None, Some(template)) => Ok(Mode::Filter(template)),
+            (None, None) => Ok(Mode::Interactive(interactive)),
+        }
+    }
+}
+
+/// Parses the arguments of `chatti bench`.
+fn parse_bench(args: &[String]) -> Result<bench::Options, UsageError> {
+    let mut models = Vec::new();
+    let mut prompts = None;
+    let mut report = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| UsageError(format!("{arg} needs a value")))
+        };
+        match arg.as_str() {
+            "--models" | "-m" => models.extend(
+                value()?
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|model| !model.is_empty())
+                    .map(str::to_string),
+            ),
+            "--prompts" | "-p" => prompts = Some(PathBuf::from(value()?)),
+            "--report" | "-r" => report = Some(PathBuf::from(value()?)),
+            _ => return Err(UsageError(format!("unknown option: {arg}"))),
+        }
+    }
+
+    if models.is_empty() {
+        return Err(UsageError(String::from("bench needs --models")));
+    }
+    let prompts = prompts.ok_or_else(|| UsageError(String::from("bench needs --prompts")))?;
+    Ok(bench::Options {
+        models,
+        prompts,
+        report: report.unwrap_or_else(|| PathBuf::from(bench::DEFAULT_REPORT)),
+    })
+}
@@ -0,0 +1,37 @@
+//! Reads and writes text on the system clipboard.
+
+/// Represents errors that can occur while reading the clipboard.
+#[derive(Debug, thiserror::Error)]
+pub enum ClipboardError {
+    /// The clipboard could not be accessed, or holds no text.
+    #[error("could not access the clipboard: {0}")]
+    Unavailable(#[from] arboard::Error),
+
+    /// The clipboard holds only whitespace.
+    #[error("the clipboard is empty")]
+    Empty,
+}
+
+/// Returns the text currently on the system clipboard.
+///
+/// # Errors
+///
+/// Returns a `ClipboardError` if the clipboard cannot be accessed or holds no
+/// text.
+pub fn read_text() -> Result<String, ClipboardError> {
+    let text = arboard::Clipboard::new()?.get_text()?;
+    if text.trim().is_empty() {
+        return Err(ClipboardError::Empty);
+    }
+    Ok(text)
+}
+
+/// Puts text on the system clipboard.
+///
+/// # Errors
+///
+/// Returns a `ClipboardError` if the clipboard cannot be accessed.
+pub fn write_text(text: &str) -> Result<(), ClipboardError> {
+    arboard::Clipboard::new()?.set_text(text)?;
+    Ok(())
+}
@@ -0,0 +1,359 @@
+//! Parses slash commands entered in the input box.
+
+use std::path::PathBuf;
+
+use crate::dataset::DatasetFormat;
+use crate::git::GitPrompt;
+use crate::message::{Mark, MarkFilter};
+use crate::models::ModelCommand;
+
+/// A slash command as offered for completion.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct CommandInfo {
+    /// The name, without the slash.
+    pub name: &'static str,
+    /// The arguments, as shown in the usage.
+    pub args: &'static str,
+    /// What the command does, in a few words.
+    pub summary: &'static str,
+    /// Whether the argument is a file path.
+    pub takes_path: bool,
+}
+
+impl CommandInfo {
+    const fn new(name: &'static str, args: &'static str, summary: &'static str) -> Self {
+        CommandInfo {
+            name,
+            args,
+            summary,
+            takes_path: false,
+        }
+    }
+
+    const fn with_path(name: &'static str, args: &'static str, summary: &'static str) -> Self {
+        CommandInfo {
+            name,
+            args,
+            summary,
+            takes_path: true,
+        }
+    }
+}
+
+/// The slash commands, in the order they are offered.
+pub const COMMANDS: &[CommandInfo] = &[
+    CommandInfo::new("archive", "[days]", "archive sessions not modified lately"),
+    CommandInfo::with_path("attach", "<glob>...", "attach the files matching patterns"),
+    CommandInfo::new(
+        "clear",
+        "[all|undo]",
+        "empty the transcript, or bring it back",
+    ),
+    CommandInfo::new("compare", "", "toggle comparing two models"),
+    CommandInfo::new("copy", "<n>", "copy block n of the selected answer"),
+    CommandInfo::new(
+        "context",
+        "[prompt]",
+        "show what the next request would send",
+    ),
+    CommandInfo::new(
+        "continue",
+        "",
+        "continue an answer cut off at the token limit",
+    ),
+    CommandInfo::new("commitmsg", "", "write a commit message for staged changes"),
+    CommandInfo::with_path(
+        "dataset",
+        "[openai|sharegpt] [+mark|-mark]... [path]",
+        "write marked exchanges as a fine-tuning dataset",
+    ),
+    CommandInfo::new("diagrams", "", "render the diagrams of the selected answer"),
+    CommandInfo::new("diff", "", "review the working tree changes"),
+    CommandInfo::new(
+        "dryrun",
+        "",
+        "toggle showing requests instead of sending them",
+    ),
+    CommandInfo::new("explain-staged", "", "explain the staged changes"),
+    CommandInfo::with_path(
+        "export",
+        "[+mark|-mark]... [path]",
+        "write the conversation as markdown",
+    ),
+    CommandInfo::with_path("import", "<path>", "import a conversation"),
+    CommandInfo::new("json", "", "toggle requesting answers as JSON"),
+    CommandInfo::new(
+        "marked",
+        "[good|bad|favorite]...",
+        "read the exchanges with marks",
+    ),
+    CommandInfo::new("model", "<name>", "switch to another model"),
+    CommandInfo::new("models", "pull|rm|ps", "manage local models"),
+    CommandInfo::new("new", "[template]", "start a new conversation"),
+    CommandInfo::new(
+        "paste-context",
+        "",
+        "attach the clipboard to the next message",
+    ),
+    CommandInfo::new("plugins", "[reload]", "list or reload the plugins"),
+    CommandInfo::new("retry", "[model]", "regenerate the latest answer"),
+    CommandInfo::with_path(
+        "save",
+        "<n> <path>",
+        "write block n of the selected answer to a file",
+    ),
+    CommandInfo::new("scripts", "[reload]", "list or reload the scripts"),
+    CommandInfo::new("search", "<query>", "search all saved sessions"),
+    CommandInfo::new("sessions", "", "switch to a saved session"),
+    CommandInfo::new("speak", "", "toggle reading answers aloud"),
+    CommandInfo::new("summary-stats", "", "show statistics of the conversation"),
+    CommandInfo::new("translate", "<language>", "translate the selected answer"),
+];
+
+/// A slash command entered by the user.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Command {
+    /// Import a conversation from another client (`/import <path>`).
+    Import(PathBuf),
+    /// Archive the saved sessions not modified for the given number of
+    /// days, or for the configured retention period (`/archive [days]`).
+    Archive(Option<u64>),
+    /// Attach the files matching glob patterns, or below directories, to
+    /// the next message (`/attach <glob>...`).
+    Attach(Vec<String>),
+    /// Empty the transcript, keeping the system prompt and the saved
+    /// session; with `all`, reset the system prompt and delete the saved
+    /// session too (`/clear [all]`).
+    Clear(bool),
+    /// Bring back the conversation the last `/clear` removed, shortly after
+    /// it (`/clear undo`).
+    UndoClear,
+    /// Toggle sending prompts to two models side by side (`/compare`).
+    Compare,
+    /// Copy a code block or table of the selected answer, or of the latest
+    /// answer with any, to the clipboard, by the number shown above it
+    /// (`/copy <n>`).
+    Copy(usize),
+    /// Continue the selected or latest answer where the token limit cut it
+    /// off (`/continue`).
+    Continue,
+    /// Show the request that sending the prompt, or the draft, would make
+    /// (`/context [prompt]`).
+    Context(String),
+    /// Render the Mermaid and Graphviz diagrams of the selected answer, or
+    /// of the latest answer with diagrams (`/diagrams`).
+    Diagrams,
+    /// Start a new conversation, optionally from a template
+    /// (`/new [template]`).
+    New(Option<String>),
+    /// Send a prompt built from the git repository in the working directory
+    /// (`/diff`, `/commitmsg`, `/explain-staged`).
+    Git(GitPrompt),
+    /// Attach the clipboard to the next message as context
+    /// (`/paste-context`).
+    PasteContext,
+    /// Toggle reading responses aloud (`/speak`).
+    Speak,
+    /// Show the turns, tokens and response times of the conversation
+    /// (`/summary-stats`).
+    SummaryStats,
+    /// Toggle showing the request a prompt would be sent with instead of
+    /// sending it (`/dryrun`).
+    DryRun,
+    /// Toggle requesting answers as JSON (`/json`).
+    Json,
+    /// Write the conversation to a markdown file, keeping only the
+    /// exchanges with the `+` marks and leaving out those with the `-`
+    /// marks (`/export [+mark|-mark]... [path]`).
+    Export(Option<PathBuf>, MarkFilter),
+    /// Write the exchanges with the `+` marks, or the favorites, as a JSONL
+    /// fine-tuning dataset in the given format
+    /// (`/dataset [openai|sharegpt] [+mark|-mark]... [path]`).
+    Dataset(DatasetFormat, MarkFilter, Option<PathBuf>),
+    /// Open the transcript on the exchanges with any of the given marks,
+    /// or with any mark at all (`/marked [mark]...`).
+    Marked(MarkFilter),
+    /// List the loaded plugins and their commands, or load them again
+    /// (`/plugins [reload]`).
+    Plugins(bool),
+    /// List the loaded scripts and their commands, or load them again
+    /// (`/scripts [reload]`).
+    Scripts(bool),
+    /// Send the following prompts to another model (`/model <name>`).
+    Model(String),
+    /// Send the prompt of an answer again, inserting the new answer below
+    /// it: the answer chosen in the retry popup, or the latest answer with
+    /// the current parameters and the given model (`/retry [model]`).
+    Retry(Option<String>),
+    /// Write a code block or table of the selected answer, or of the latest
+    /// answer with any, to a file, by the number shown above it
+    /// (`/save <n> <path>`).
+    Save(usize, PathBuf),
+    /// Search the messages of all saved sessions and list the matches
+    /// (`/search <query>`). Without a query, open the session of the result
+    /// chosen in the search popup.
+    Search(String),
+    /// Choose a saved session to switch to (`/sessions`). Once one was
+    /// chosen in the switcher, open it.
+    Sessions,
+    /// Translate the selected answer, or the latest one, inserting the
+    /// translation below it (`/translate <language>`).
+    Translate(String),
+    /// Manage local models (`/models pull <name>`, `/models rm <name>`,
+    /// `/models ps`).
+    Models(ModelCommand),
+    /// A command that is not recognised, or is missing its arguments.
+    Invalid(String),
+}
+
+impl Command {
+    /// Parses a slash command from the user's input.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The raw input text.
+    ///
+    /// # Returns
+    ///
+    /// `Some(Command)` if the input starts with `/`, `None` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::commands::Command;
+    /// use std::path::PathBuf;
+    ///
+    /// assert_eq!(
+    ///     Command::parse("/import chat.json"),
+    ///     Some(Command::Import(PathBuf::from("chat.json")))
+    /// );
+    /// assert_eq!(Command::parse("hello"), None);
+    /// ```
+    #[must_use]
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        let rest = input.strip_prefix('/')?;
+        let (name, args) = rest
+            .split_once(char::is_whitespace)
+            .map_or((rest, ""), |(name, args)| (name, args.trim()));
+
+        let command = match name {
+            "import" if !args.is_empty() => Command::Import(expand_home(args)),
+            "import" => Command::Invalid(String::from("Usage: /import <path>")),
+            "archive" if args.is_empty() => Command::Archive(None),
+            "archive" => match args.parse() {
+                Ok(days) => Command::Archive(Some(days)),
+                Err(_) => Command::Invalid(String::from("Usage: /archive [days]")),
+            },
+            "attach" if !args.is_empty() => {
+                Command::Attach(args.split_whitespace().map(String::from).collect())
+            }
+            "attach" => Command::Invalid(String::from("Usage: /attach <glob>...")),
+            "clear" => match args {
+                "" => Command::Clear(false),
+                "all" => Command::Clear(true),
+                "undo" => Command::UndoClear,
+                _ => Command::Invalid(String::from("Usage: /clear [all|undo]")),
+            },
+            "compare" => Command::Compare,
+            "context" => Command::Context(args.to_string()),
+            "continue" => Command::Continue,
+            "copy" => match args.parse() {
+                Ok(number) if number > 0 => Command::Copy(number),
+                _ => Command::Invalid(String::from("Usage: /copy <n>")),
+            },
+            "diagrams" => Command::Diagrams,
+            "new" => Command::New((!args.is_empty()).then(|| args.to_string())),
+            "diff" => Command::Git(GitPrompt::Diff),
+            "commitmsg" => Command::Git(GitPrompt::CommitMessage),
+            "explain-staged" => Command::Git(GitPrompt::ExplainStaged),
+            "paste-context" => Command::PasteContext,
+            "speak" => Command::Speak,
+            "summary-stats" => Command::SummaryStats,
+            "dryrun" => Command::DryRun,
+            "json" => Command::Json,
+            "export" => match MarkFilter::parse(args, false) {
+                Ok((marks, path)) => {
+                    Command::Export((!path.is_empty()).then(|| expand_home(path)), marks)
+                }
+                Err(message) => Command::Invalid(message),
+            },
+            "dataset" => parse_dataset(args),
+            "marked" => match MarkFilter::parse(args, true) {
+                Ok((mut filter, _)) => {
+                    if filter.only.is_empty() {
+                        filter.only = Mark::ALL.to_vec();
+                    }
+                    Command::Marked(filter)
+                }
+                Err(message) => Command::Invalid(message),
+            },
+            "model" if !args.is_empty() => Command::Model(args.to_string()),
+            "model" => Command::Invalid(String::from("Usage: /model <name>")),
+            "models" => parse_models(args),
+            "plugins" if args.is_empty() => Command::Plugins(false),
+            "plugins" if args == "reload" => Command::Plugins(true),
+            "plugins" => Command::Invalid(String::from("Usage: /plugins [reload]")),
+            "retry" => Command::Retry((!args.is_empty()).then(|| args.to_string())),
+            "save" => match args.split_once(char::is_whitespace) {
+                Some((number, path)) => match number.parse() {
+                    Ok(number) if number > 0 => Command::Save(number, expand_home(path.trim())),
+                    _ => Command::Invalid(String::from("Usage: /save <n> <path>")),
+                },
+                None => Command::Invalid(String::from("Usage: /save <n> <path>")),
+            },
+            "scripts" if args.is_empty() => Command::Scripts(false),
+            "scripts" if args == "reload" => Command::Scripts(true),
+            "scripts" => Command::Invalid(String::from("Usage: /scripts [reload]")),
+            "search" => Command::Search(args.to_string()),
+            "sessions" => Command::Sessions,
+            "translate" if !args.is_empty() => Command::Translate(args.to_string()),
+            "translate" => Command::Invalid(String::from("Usage: /translate <language>")),
+            _ => Command::Invalid(format!("Unknown command: /{name}")),
+        };
+        Some(command)
+    }
+}
+
+fn parse_models(args: &str) -> Command {
+    let mut words = args.split_whitespace();
+    let command = match (words.next(), words.next(), words.next()) {
+        (Some("pull"), Some(name), None) => ModelCommand::Pull(name.to_string()),
+        (Some("rm"), Some(name), None) => ModelCommand::Remove(name.to_string()),
+        (Some("ps"), None, None) => ModelCommand::Running,
+        _ => {
+            return Command::Invalid(String::from(
+                "Usage: /models pull <name> | /models rm <name> | /models ps",
+            ))
+        }
+    };
+    Command::Models(command)
+}
+
+fn parse_dataset(args: &str) -> Command {
+    let (first, rest) = args
+        .split_once(char::is_whitespace)
+        .map_or((args, ""), |(first, rest)| (first, rest.trim_start()));
+    let (format, args) = match DatasetFormat::named(first) {
+        Some(format) => (format, rest),
+        None => (DatasetFormat::default(), args),
+    };
+    match MarkFilter::parse(args, false) {
+        Ok((mut marks, path)) => {
+            if marks.only.is_empty() {
+                marks.only = vec![Mark::Favorite];
+            }
+            Command::Dataset(format, marks, (!path.is_empty()).then(|| expand_home(path)))
+        }
+        Err(message) => Command::Invalid(message),
+    }
+}
+
+/// Expands a leading `~` to the home directory.
+#[must_use]
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+        None => PathBuf::from(path),
+    }
+}
@@ -1,5 +1,15 @@
 use serde::{Deserialize, Serialize};
-use std::{fs, path::PathBuf};
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use crate::error::Application;
+use crate::message::Message;
+use crate::provider::{ChatTemplate, Provider, TemplateName};
+use crate::retention::Action;
+use crate::session::TranscriptFormat;
+use crate::store::Backend;
+use crate::structured::JsonOutput;
+use crate::tables::{Sampling, Strategy};
+use crate::transport::Transport;
 
 /// Configuration structure for the Chatti application.
 ///
@@ -7,14 +17,176 @@ use std::{fs, path::PathBuf};
 /// including API endpoint, model name, streaming flag, and temperature.
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
-    /// The API endpoint for the chat service.
+    /// The hosts requests may go to in local-only mode besides this
+    /// machine, such as a server on the local network.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed_hosts: Vec<String>,
+    /// The API endpoint for the chat service. It can be left out for
+    /// providers with a well-known local address.
+    #[serde(default)]
     pub api_endpoint: String,
+    /// The API key, used when it is neither in the keyring nor in
+    /// `CHATTI_API_KEY`. Prefer `chatti auth set` over storing it here.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// The model's context window in tokens, used for budget warnings.
+    #[serde(default = "default_context_tokens")]
+    pub context_tokens: usize,
+    /// Whether prompts are shown as the request they would be sent with,
+    /// instead of being sent. Toggled with `/dryrun`.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dry_run: bool,
+    /// The language every answer is requested in, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    /// Whether requests may only go to this machine and `allowed_hosts`.
+    /// The API key of the credentials profile is not loaded either; no
+    /// profiles are listed in the interface, so there are none to hide.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub local_only: bool,
+    /// How many messages the transcript keeps in memory; older ones are
+    /// paged out to disk. Unlimited when not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_scrollback: Option<usize>,
+    /// The maximum number of tokens of a response, sent only when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
     /// The name of the model to use for chat.
     pub model: String,
+    /// The format the conversation is printed in when the interface exits,
+    /// if it is printed at all.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub print_on_exit: Option<TranscriptFormat>,
+    /// The credentials profile; its API key is looked up in the keyring.
+    #[serde(default = "default_profile")]
+    pub profile: String,
+    /// The kind of server behind the endpoint.
+    #[serde(default)]
+    pub provider: Provider,
+    /// Where sessions are kept.
+    #[serde(default)]
+    pub session_store: Backend,
     /// Whether to use streaming for responses.
     pub stream: bool,
     /// The temperature parameter for text generation.
     pub temperature: f32,
+    /// The nucleus sampling threshold, sent only when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    // The sections come after the plain values, which TOML needs before
+    // any table.
+    /// How attached files are read.
+    #[serde(default)]
+    pub attachments: AttachmentsConfig,
+    /// Models used by the compare mode.
+    #[serde(default)]
+    pub compare: CompareConfig,
+    /// The socket other programs use to drive the running instance.
+    #[serde(default)]
+    pub control: ControlConfig,
+    /// Prompt cost preview and confirmation.
+    #[serde(default)]
+    pub cost: CostConfig,
+    /// The commands Mermaid and Graphviz diagrams are rendered with.
+    #[serde(default)]
+    pub diagrams: DiagramsConfig,
+    /// The history of sent prompts.
+    #[serde(default)]
+    pub history: HistoryConfig,
+    /// External commands run on completed responses.
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    /// JSON mode: answers are requested as JSON, matching a schema if one
+    /// is set. Toggled with `/json`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub json_output: Option<JsonOutput>,
+    /// The chat template used with llama.cpp's server.
+    #[serde(default)]
+    pub llamacpp: LlamaCppConfig,
+    /// Queueing of messages while the endpoint is unreachable.
+    #[serde(default)]
+    pub offline: OfflineConfig,
+    /// Executables run on events.
+    #[serde(default)]
+    pub plugins: PluginsConfig,
+    /// Client-side limits on outgoing requests.
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// Redaction of secrets in logs and exports.
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    /// How long saved sessions are kept.
+    #[serde(default)]
+    pub retention: RetentionConfig,
+    /// Retrieval of excerpts from local files.
+    #[serde(default)]
+    pub retrieval: RetrievalConfig,
+    /// User scripts run on prompts, answers and commands.
+    #[serde(default)]
+    pub scripting: ScriptingConfig,
+    /// Shell command substitution in prompts.
+    #[serde(default)]
+    pub shell: ShellConfig,
+    /// Reading responses aloud.
+    #[serde(default)]
+    pub speech: SpeechConfig,
+    /// The spinner shown while a response is generated.
+    #[serde(default)]
+    pub spinner: SpinnerConfig,
+    /// Checks performed when the application starts.
+    #[serde(default)]
+    pub startup: StartupConfig,
+    /// Exporting traces and metrics of requests with OpenTelemetry.
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    /// Conversation templates started with `/new <name>`.
+    #[serde(default)]
+    pub templates: HashMap<String, TemplateConfig>,
+    /// Overrides for terminal capability detection.
+    #[serde(default)]
+    pub terminal: TerminalConfig,
+    /// Role prefixes and colors.
+    #[serde(default)]
+    pub theme: ThemeConfig,
+    /// How requests reach the server.
+    #[serde(default)]
+    pub transport: TransportConfig,
+}
+
+fn default_context_tokens() -> usize {
+    4096
+}
+
+fn default_profile() -> String {
+    String::from(crate::auth::DEFAULT_PROFILE)
+}
+
+/// Configuration for redacting secrets from logs and exports.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RedactionConfig {
+    /// Whether redaction is applied at all.
+    pub enabled: bool,
+    /// Whether prompt contents are written to the log file.
+    pub log_prompts: bool,
+    /// Additional regular expressions to redact. A `secret` capture group,
+    /// if present, limits redaction to that group.
+    pub patterns: Vec<String>,
+    /// Whether prompts sent to an endpoint on another machine are scanned,
+    /// with their attachments, for credentials, and need confirming before
+    /// they are sent if one is found.
+    pub scan_prompts: bool,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        RedactionConfig {
+            enabled: true,
+            log_prompts: true,
+            patterns: Vec::new(),
+            scan_prompts: false,
+        }
+    }
 }
 
 impl Config {
@@ -65,11 +237,65 @@ impl Config {
         }
 
         let config_content = fs::read_to_string(&config_path).map_err(FSError::IoError)?;
-        let config: Config = toml::from_str(&config_content).map_err(FSError::TomlParseError)?;
+        let mut config: Config =
+            toml::from_str(&config_content).map_err(FSError::TomlParseError)?;
+        config.apply_provider_defaults();
 
         Ok(config)
     }
 
+    /// Fills in the endpoint of the provider when none is configured.
+    pub fn apply_provider_defaults(&mut self) {
+        if self.api_endpoint.is_empty() {
+            if let Some(endpoint) = self.provider.default_endpoint() {
+                self.api_endpoint = endpoint.to_string();
+            }
+        }
+    }
+
+    /// Checks that a request may be sent to `endpoint`: in local-only mode,
+    /// its host must be this machine or one of `allowed_hosts`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Application::LocalOnly`, naming the host, if the request is
+    /// refused.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::config::Config;
+    ///
+    /// let mut config: Config =
+    ///     toml::from_str("model = \"llama3\"\nstream = true\ntemperature = 0.7\nlocal_only = true").unwrap();
+    /// config.allowed_hosts = vec!["gpu-box.lan".to_string()];
+    /// assert!(config.check_endpoint("http://localhost:11434/api/chat").is_ok());
+    /// assert!(config.check_endpoint("http://GPU-box.lan:8080/completion").is_ok());
+    /// assert!(config.check_endpoint("https://api.openai.com/v1/chat/completions").is_err());
+    /// ```
+    pub fn check_endpoint(&self, endpoint: &str) -> Result<(), Application> {
+        if !self.local_only || crate::api::is_local_endpoint(endpoint) {
+            return Ok(());
+        }
+        let host = reqwest::Url::parse(endpoint)
+            .ok()
+            .and_then(|url| url.host_str().map(str::to_string));
+        match host {
+            Some(host)
+                if self
+                    .allowed_hosts
+                    .iter()
+                    .any(|allowed| allowed.eq_ignore_ascii_case(&host)) =>
+            {
+                Ok(())
+            }
+            host => Err(Application::LocalOnly(format!(
+                "Local-only mode: not sending a request to {}, which is neither this machine nor in `allowed_hosts`",
+                host.as_deref().unwrap_or(endpoint)
+            ))),
+        }
+    }
+
     /// Creates a default configuration file.
     ///
     /// This function is called when the configuration file doesn't exist.
@@ -84,10 +310,48 @@ impl Config {
     /// Returns a `Result` indicating success or a `FSError`.
     fn create_default_config(path: &PathBuf) -> Result<(), FSError> {
         let default_config = Config {
+            allowed_hosts: Vec::new(),
             api_endpoint: String::new(),
+            api_key: None,
+            context_tokens: default_context_tokens(),
+            dry_run: false,
+            language: None,
+            local_only: false,
+            max_scrollback: None,
+            max_tokens: None,
             model: String::new(),
+            print_on_exit: None,
+            profile: default_profile(),
+            provider: Provider::default(),
+            session_store: Backend::default(),
             stream: false,
             temperature: 0.7,
+            top_p: None,
+            attachments: AttachmentsConfig::default(),
+            compare: CompareConfig::default(),
+            control: ControlConfig::default(),
+            cost: CostConfig::default(),
+            diagrams: DiagramsConfig::default(),
+            history: HistoryConfig::default(),
+            hooks: HooksConfig::default(),
+            json_output: None,
+            llamacpp: LlamaCppConfig::default(),
+            offline: OfflineConfig::default(),
+            plugins: PluginsConfig::default(),
+            rate_limit: RateLimitConfig::default(),
+            redaction: RedactionConfig::default(),
+            retention: RetentionConfig::default(),
+            retrieval: RetrievalConfig::default(),
+            scripting: ScriptingConfig::default(),
+            shell: ShellConfig::default(),
+            speech: SpeechConfig::default(),
+            spinner: SpinnerConfig::default(),
+            startup: StartupConfig::default(),
+            telemetry: TelemetryConfig::default(),
+            templates: HashMap::new(),
+            terminal: TerminalConfig::default(),
+            theme: ThemeConfig::default(),
+            transport: TransportConfig::default(),
         };
 
         let toml_string = toml::to_string(&default_config).map_err(FSError::TomlSerializeError)?;
@@ -97,6 +361,581 @@ impl Config {
     }
 }
 
+/// Configuration for the control socket.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ControlConfig {
+    /// Whether the control socket is opened (Unix only).
+    pub enabled: bool,
+    /// The path of the socket. Defaults to `chatti.sock` in
+    /// `$XDG_RUNTIME_DIR`.
+    pub socket: Option<PathBuf>,
+}
+
+/// Configuration for prompt cost estimates.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CostConfig {
+    /// Whether the estimated prompt cost is shown while editing.
+    pub show_preview: bool,
+    /// Sending a prompt estimated above this cost requires confirmation.
+    pub confirm_above: Option<f64>,
+    /// Prices per model name.
+    pub prices: HashMap<String, ModelPrice>,
+}
+
+impl CostConfig {
+    /// Returns the price of the given model, if configured.
+    #[must_use]
+    pub fn price_for(&self, model: &str) -> Option<ModelPrice> {
+        self.prices.get(model).copied()
+    }
+}
+
+/// Configuration for limiting the rate of outgoing requests.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// The most requests sent in any minute, or `None` or 0 for no limit.
+    pub requests_per_minute: Option<u32>,
+    /// The most requests in flight at once, or `None` for no limit.
+    pub max_concurrent: Option<usize>,
+    /// Limits for credentials profiles, overriding the ones above.
+    pub profiles: HashMap<String, RateLimit>,
+}
+
+impl RateLimitConfig {
+    /// Returns the limits of a credentials profile: its own where set, and
+    /// the general ones otherwise.
+    #[must_use]
+    pub fn for_profile(&self, profile: &str) -> RateLimit {
+        let own = self.profiles.get(profile).copied().unwrap_or_default();
+        RateLimit {
+            requests_per_minute: own.requests_per_minute.or(self.requests_per_minute),
+            max_concurrent: own.max_concurrent.or(self.max_concurrent),
+        }
+    }
+}
+
+/// Limits on the requests sent with one credentials profile.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct RateLimit {
+    /// The most requests sent in any minute, or `None` or 0 for no limit.
+    pub requests_per_minute: Option<u32>,
+    /// The most requests in flight at once, or `None` for no limit.
+    pub max_concurrent: Option<usize>,
+}
+
+/// Configuration for how requests reach the server.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TransportConfig {
+    /// The transport of every credentials profile without its own.
+    pub protocol: Transport,
+    /// Transports for credentials profiles, overriding the one above.
+    pub profiles: HashMap<String, Transport>,
+}
+
+impl TransportConfig {
+    /// Returns the transport of a credentials profile: its own if set, and
+    /// the general one otherwise.
+    #[must_use]
+    pub fn for_profile(&self, profile: &str) -> Transport {
+        self.profiles.get(profile).copied().unwrap_or(self.protocol)
+    }
+}
+
+/// The price of a model in currency units per million tokens.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(default)]
+pub struct ModelPrice {
+    /// Price per million prompt tokens.
+    pub input: f64,
+    /// Price per million completion tokens.
+    pub output: f64,
+}
+
+/// Configuration for how message roles are displayed.
+///
+/// Colors accept names such as `blue`, hex values such as `#00ff00`, or
+/// terminal palette indices.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ThemeConfig {
+    /// Prefix of user messages.
+    pub user_prefix: String,
+    /// Prefix of assistant messages; `{model}` is replaced with the name of
+    /// the model that wrote the answer.
+    pub assistant_prefix: String,
+    /// Prefix of system notices.
+    pub system_prefix: String,
+    /// Color of the user prefix.
+    pub user_color: String,
+    /// Color of the assistant prefix.
+    pub assistant_color: String,
+    /// Color of system notices.
+    pub system_color: String,
+}
+
+impl Default for ThemeConfig {
+    fn default() -> Self {
+        ThemeConfig {
+            user_prefix: "You: ".to_string(),
+            assistant_prefix: "AI: ".to_string(),
+            system_prefix: String::new(),
+            user_color: "blue".to_string(),
+            assistant_color: "green".to_string(),
+            system_color: "yellow".to_string(),
+        }
+    }
+}
+
+/// Configuration for queueing messages while offline.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct OfflineConfig {
+    /// Whether messages are queued instead of failing when the endpoint is
+    /// unreachable.
+    pub queue: bool,
+    /// Seconds between checks of whether the endpoint is reachable again.
+    pub retry_interval_secs: u64,
+}
+
+impl Default for OfflineConfig {
+    fn default() -> Self {
+        OfflineConfig {
+            queue: false,
+            retry_interval_secs: 15,
+        }
+    }
+}
+
+/// Configuration for llama.cpp's server, which needs the conversation
+/// rendered into a single prompt.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct LlamaCppConfig {
+    /// The chat template of the model.
+    pub template: TemplateName,
+    /// The template used when `template = "custom"`.
+    pub custom: ChatTemplate,
+}
+
+impl LlamaCppConfig {
+    /// Returns the configured chat template.
+    #[must_use]
+    pub fn chat_template(&self) -> ChatTemplate {
+        ChatTemplate::preset(self.template).unwrap_or_else(|| self.custom.clone())
+    }
+}
+
+/// Configuration for the checks performed on startup.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct StartupConfig {
+    /// Whether the endpoint is pinged on startup.
+    pub health_check: bool,
+    /// Whether the model is loaded on startup (Ollama only).
+    pub warm_up: bool,
+}
+
+impl Default for StartupConfig {
+    fn default() -> Self {
+        StartupConfig {
+            health_check: true,
+            warm_up: false,
+        }
+    }
+}
+
+/// Configuration for comparing the responses of two models.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct CompareConfig {
+    /// The two models that answer each prompt in compare mode.
+    pub models: Vec<String>,
+}
+
+impl CompareConfig {
+    /// Returns the two models to compare, if at least two are configured.
+    #[must_use]
+    pub fn pair(&self) -> Option<[String; 2]> {
+        match self.models.as_slice() {
+            [left, right, ..] => Some([left.clone(), right.clone()]),
+            _ => None,
+        }
+    }
+}
+
+/// Configuration for `$(command)` substitution in prompts.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct ShellConfig {
+    /// Programs that substitutions may run. Substitution is disabled while
+    /// this is empty.
+    pub allowed: Vec<String>,
+}
+
+/// Configuration for commands run on completed responses.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct HooksConfig {
+    /// Shell commands that receive each completed response on standard
+    /// input.
+    pub on_response: Vec<String>,
+}
+
+/// Configuration for reading responses aloud.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct SpeechConfig {
+    /// The text-to-speech command, such as `espeak` or `say`, which reads
+    /// each sentence from standard input.
+    pub command: Option<String>,
+    /// Whether responses are spoken from startup, rather than after `/speak`.
+    pub enabled: bool,
+}
+
+/// Configuration for retrieving excerpts of local files as context.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct RetrievalConfig {
+    /// The directory whose files are indexed; retrieval is off when unset.
+    pub directory: Option<PathBuf>,
+    /// The embeddings endpoint. Defaults to `/api/embed` on the host of
+    /// `api_endpoint`.
+    pub endpoint: Option<String>,
+    /// The embedding model.
+    pub model: String,
+    /// The number of excerpts sent with each prompt.
+    pub top_k: usize,
+    /// The maximum size of an excerpt in characters.
+    pub chunk_chars: usize,
+}
+
+impl Default for RetrievalConfig {
+    fn default() -> Self {
+        RetrievalConfig {
+            directory: None,
+            endpoint: None,
+            model: String::from("nomic-embed-text"),
+            top_k: 4,
+            chunk_chars: 1500,
+        }
+    }
+}
+
+/// A saved conversation template.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct TemplateConfig {
+    /// The system prompt of the conversation.
+    pub system: Option<String>,
+    /// Messages the conversation starts with.
+    pub messages: Vec<Message>,
+    /// Glob patterns of files whose contents are attached.
+    pub attach: Vec<String>,
+    /// A shell command whose output is attached, such as `git diff`.
+    pub command: Option<String>,
+    /// A JSON Schema; the conversation is started in JSON mode with it.
+    pub schema: Option<serde_json::Value>,
+}
+
+/// The settings of a project, read from `.chatti.toml` at the root of its
+/// git repository.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProjectConfig {
+    /// The model used in the project, instead of the configured one.
+    pub model: Option<String>,
+    /// The system prompt conversations in the project start with.
+    pub system: Option<String>,
+    /// Glob patterns of files, relative to the repository root, whose
+    /// contents are attached to the system prompt.
+    pub attach: Vec<String>,
+}
+
+/// Configuration for terminal color depth, glyph and image support.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TerminalConfig {
+    /// The color depth to render with.
+    pub colors: ColorMode,
+    /// Whether unicode glyphs are used; detected from the locale if unset.
+    pub unicode: Option<bool>,
+    /// The graphics protocol images are drawn with.
+    pub images: ImageMode,
+    /// Whether each frame is wrapped in a synchronized update, so terminals
+    /// that support it show it at once instead of as it is written.
+    pub synchronized_output: bool,
+    /// The most frames drawn per second while an answer streams in; the
+    /// chunks received in between are shown together.
+    pub max_fps: u32,
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        TerminalConfig {
+            colors: ColorMode::default(),
+            unicode: None,
+            images: ImageMode::default(),
+            synchronized_output: true,
+            max_fps: 30,
+        }
+    }
+}
+
+/// Configuration for the spinner shown while a response is generated.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct SpinnerConfig {
+    /// The built-in frame set.
+    pub style: SpinnerStyle,
+    /// Custom frames, used instead of `style` if not empty.
+    pub frames: Vec<String>,
+    /// Whether the elapsed time, received tokens and token speed are shown
+    /// next to the spinner.
+    pub progress: bool,
+}
+
+impl Default for SpinnerConfig {
+    fn default() -> Self {
+        SpinnerConfig {
+            style: SpinnerStyle::default(),
+            frames: Vec::new(),
+            progress: true,
+        }
+    }
+}
+
+/// Configuration for the history of sent prompts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HistoryConfig {
+    /// Whether sent prompts are saved for later runs.
+    pub save: bool,
+    /// How many prompts are kept; older ones are dropped.
+    pub max_entries: usize,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        HistoryConfig {
+            save: true,
+            max_entries: 1000,
+        }
+    }
+}
+
+/// Configuration for sweeping out old sessions.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
+pub struct RetentionConfig {
+    /// Sessions not modified for this many days are swept out on startup,
+    /// or `None` to keep every session.
+    pub days: Option<u64>,
+    /// Whether old sessions are archived or deleted.
+    pub action: Action,
+}
+
+/// Configuration for attached files.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct AttachmentsConfig {
+    /// How many rows of a CSV or TSV table are attached, besides the
+    /// header.
+    pub sample_rows: usize,
+    /// Whether the first rows are attached or rows spread through the
+    /// table.
+    pub sampling: Strategy,
+    /// The most text `/attach` attaches in all, in kilobytes.
+    pub budget_kb: usize,
+}
+
+impl Default for AttachmentsConfig {
+    fn default() -> Self {
+        let sampling = Sampling::default();
+        AttachmentsConfig {
+            sample_rows: sampling.rows,
+            sampling: sampling.strategy,
+            budget_kb: 256,
+        }
+    }
+}
+
+impl AttachmentsConfig {
+    /// Returns how tables are sampled.
+    #[must_use]
+    pub fn sampling(&self) -> Sampling {
+        Sampling {
+            rows: self.sample_rows,
+            strategy: self.sampling,
+        }
+    }
+
+    /// Returns the most bytes of text `/attach` attaches in all.
+    #[must_use]
+    pub fn budget(&self) -> usize {
+        self.budget_kb * 1024
+    }
+}
+
+/// Configuration for plugins.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct PluginsConfig {
+    /// Whether plugins are loaded. Off unless turned on, since plugins run
+    /// with every prompt and answer.
+    pub enabled: bool,
+    /// The directory of the plugins. Defaults to `~/.config/chatti/plugins`.
+    pub directory: Option<PathBuf>,
+    /// How many seconds a plugin may run before it is stopped.
+    pub timeout_secs: u64,
+}
+
+impl Default for PluginsConfig {
+    fn default() -> Self {
+        PluginsConfig {
+            enabled: false,
+            directory: None,
+            timeout_secs: 5,
+        }
+    }
+}
+
+/// Configuration for user scripts, which need chatti built with the
+/// `scripting` feature.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct ScriptingConfig {
+    /// Whether scripts are loaded.
+    pub enabled: bool,
+    /// The directory of the scripts. Defaults to `~/.config/chatti/scripts`.
+    pub directory: Option<PathBuf>,
+}
+
+impl Default for ScriptingConfig {
+    fn default() -> Self {
+        ScriptingConfig {
+            enabled: true,
+            directory: None,
+        }
+    }
+}
+
+/// Configuration for rendering Mermaid and Graphviz diagrams. The
+/// commands are run with `sh -c`, with `{input}` replaced by the path of
+/// the diagram's source and `{output}` by the path of the PNG image to
+/// write.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct DiagramsConfig {
+    /// The command that renders Mermaid diagrams.
+    pub mermaid: String,
+    /// The command that renders Graphviz graphs.
+    pub graphviz: String,
+    /// The directory rendered diagrams are kept in. Defaults to
+    /// `~/.config/chatti/diagrams`.
+    pub directory: Option<PathBuf>,
+    /// The command that opens images on terminals without a graphics
+    /// protocol, given the path of the image. Defaults to `open` on macOS
+    /// and `xdg-open` elsewhere.
+    pub open: Option<String>,
+    /// How many seconds a render may run before it is stopped.
+    pub timeout_secs: u64,
+}
+
+impl Default for DiagramsConfig {
+    fn default() -> Self {
+        DiagramsConfig {
+            mermaid: String::from("mmdc --quiet -i {input} -o {output}"),
+            graphviz: String::from("dot -Tpng {input} -o {output}"),
+            directory: None,
+            open: None,
+            timeout_secs: 60,
+        }
+    }
+}
+
+/// Configuration for exporting traces and metrics of requests to an
+/// OpenTelemetry collector over OTLP/HTTP. Exporting needs chatti built
+/// with the `telemetry` feature.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct TelemetryConfig {
+    /// Whether traces and metrics are exported.
+    pub enabled: bool,
+    /// The base URL of the collector; traces go to `/v1/traces` and
+    /// metrics to `/v1/metrics` below it.
+    pub endpoint: String,
+    /// The service name the traces and metrics are reported under.
+    pub service_name: String,
+    /// Headers sent with every export, such as an authorization header.
+    pub headers: HashMap<String, String>,
+    /// How often metrics are exported, in seconds.
+    pub export_interval_secs: u64,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig {
+            enabled: false,
+            endpoint: String::from("http://localhost:4318"),
+            service_name: String::from("chatti"),
+            headers: HashMap::new(),
+            export_interval_secs: 60,
+        }
+    }
+}
+
+/// A built-in spinner frame set.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SpinnerStyle {
+    /// Rotating braille dots.
+    #[default]
+    Braille,
+    /// Growing dots.
+    Dots,
+    /// A rotating line, for terminals without unicode glyphs.
+    Ascii,
+}
+
+/// The color depth to render with.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Detect from `COLORTERM` and `TERM`.
+    #[default]
+    Auto,
+    /// 24-bit RGB colors.
+    TrueColor,
+    /// The 256-color palette.
+    #[serde(rename = "256")]
+    Ansi256,
+    /// The 16 basic ANSI colors.
+    #[serde(rename = "16")]
+    Ansi16,
+}
+
+/// The graphics protocol images are drawn inline with.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageMode {
+    /// Detect from the environment and by asking the terminal.
+    #[default]
+    Auto,
+    /// The kitty graphics protocol.
+    Kitty,
+    /// iTerm2's inline images.
+    Iterm2,
+    /// Sixel graphics.
+    Sixel,
+    /// Show images by their name and dimensions only.
+    Off,
+}
+
 /// Represents errors that can occur during configuration operations.
 #[derive(Debug, thiserror::Error)]
 pub enum FSError {
@@ -115,4 +954,17 @@ pub enum FSError {
     /// Error when serializing to TOML.
     #[error("Toml serialize error: {0}")]
     TomlSerializeError(#[from] toml::ser::Error),
+
+    /// Error when reading or writing JSON data files.
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+
+    /// Error from the SQLite session store.
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    SqliteError(#[from] rusqlite::Error),
+
+    /// Error when the configuration asks for something this build lacks.
+    #[error("not supported: {0}")]
+    Unsupported(String),
 }
@@ -0,0 +1,109 @@
+//! Stitches the continuation of a cut-off answer onto it.
+//!
+//! Asked to continue an answer that stopped inside a code block, models
+//! tend to open a new code fence and to write the last line again, so
+//! appending their output as it is would leave a stray fence and a
+//! duplicated line in the middle of the code. The continuation is joined
+//! to the answer without them, so the block stays one valid block.
+
+/// Returns the text to append to an answer to continue it, without the
+/// fence the continuation reopens and the line it repeats when the answer
+/// stopped inside a code block.
+///
+/// # Arguments
+///
+/// * `earlier` - The answer as it was cut off.
+/// * `continuation` - What the model wrote when asked to continue it.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::continuation::stitch;
+///
+/// let earlier = "Here it is:\n\n```rust\nfn main() {\n    let total = sum(";
+/// let continuation = "```rust\n    let total = sum(&values);\n}\n```";
+/// assert_eq!(stitch(earlier, continuation), "&values);\n}\n```");
+///
+/// // Outside of a code block, the continuation is appended as it is.
+/// assert_eq!(stitch("The first part", " and the rest."), " and the rest.");
+/// ```
+#[must_use]
+pub fn stitch<'a>(earlier: &str, continuation: &'a str) -> &'a str {
+    let Some(fence) = open_fence(earlier) else {
+        return continuation;
+    };
+    let mut rest = continuation;
+
+    // A fence reopening the block, ahead of the code.
+    let start = rest.trim_start_matches(['\n', '\r']);
+    let first_line = start.lines().next().unwrap_or_default();
+    if let Some(info) = fence_line(first_line).filter(|line| line.marker.starts_with(fence.marker))
+    {
+        let after = &start[first_line.len()..];
+        let after = after
+            .strip_prefix("\r\n")
+            .or_else(|| after.strip_prefix('\n'));
+        // A bare fence closes the block, unless a later one closes it.
+        let reopens = !info.info.is_empty()
+            || after.is_some_and(|after| after.lines().any(|line| fence_line(line).is_some()));
+        if reopens {
+            rest = after.unwrap_or_default();
+        }
+    }
+
+    // The last line, written again in full or completed.
+    let last_line = earlier.rsplit('\n').next().unwrap_or_default();
+    let complete = earlier.ends_with('\n');
+    let repeated = if complete {
+        earlier.trim_end_matches('\n').rsplit('\n').next()
+    } else {
+        Some(last_line)
+    };
+    if let Some(line) = repeated.filter(|line| !line.trim().is_empty()) {
+        if let Some(after) = rest.strip_prefix(line) {
+            rest = if complete {
+                after.strip_prefix('\n').unwrap_or(after)
+            } else {
+                after
+            };
+        }
+    }
+    rest
+}
+
+/// A line opening or closing a code fence.
+struct Fence<'a> {
+    /// The backticks or tildes of the fence.
+    marker: &'a str,
+    /// What follows them, such as the language.
+    info: &'a str,
+}
+
+/// Returns the fence a line is, if it is one.
+fn fence_line(line: &str) -> Option<Fence<'_>> {
+    let line = line.trim_start();
+    let first = line.chars().next().filter(|c| matches!(c, '`' | '~'))?;
+    let length = line.len() - line.trim_start_matches(first).len();
+    (length >= 3).then(|| Fence {
+        marker: &line[..length],
+        info: line[length..].trim(),
+    })
+}
+
+/// Returns the fence of the code block the text ends inside of, if any.
+fn open_fence(text: &str) -> Option<Fence<'_>> {
+    let mut open: Option<Fence> = None;
+    for line in text.lines() {
+        let Some(fence) = fence_line(line) else {
+            continue;
+        };
+        open = match open {
+            None => Some(fence),
+            Some(opening) if fence.info.is_empty() && fence.marker.starts_with(opening.marker) => {
+                None
+            }
+            Some(opening) => Some(opening),
+        };
+    }
+    open
+}
@@ -0,0 +1,252 @@
+//! Lets other programs drive a running instance through a Unix socket.
+//!
+//! Each line written to the socket is a command, answered with a line of
+//! `ok` or `error: <reason>`:
+//!
+//! - `send <text>` or `send "<text>"` sends a prompt, as if it was typed.
+//!   Quoted text may contain `\n`, `\"` and `\\` escapes.
+//! - `export [path]` writes the conversation to a markdown file.
+//! - `switch-model <name>` sends the following prompts to another model.
+//!
+//! Commands are turned into the input they stand for and handled once the
+//! interface is idle, so they queue up while a response is streaming.
+
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UnixListener, UnixStream},
+    sync::mpsc,
+};
+use tracing::{debug, warn};
+
+/// A command received on the control socket.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ControlCommand {
+    /// Send a prompt.
+    Send(String),
+    /// Export the conversation, optionally to the given path.
+    Export(Option<String>),
+    /// Switch to another model.
+    SwitchModel(String),
+}
+
+impl ControlCommand {
+    /// Parses a line received on the control socket.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the command is unknown or its
+    /// arguments are missing or malformed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::control::ControlCommand;
+    ///
+    /// assert_eq!(
+    ///     ControlCommand::parse(r#"send "Explain:\n\"this\"""#),
+    ///     Ok(ControlCommand::Send("Explain:\n\"this\"".to_string()))
+    /// );
+    /// assert_eq!(
+    ///     ControlCommand::parse("switch-model mistral"),
+    ///     Ok(ControlCommand::SwitchModel("mistral".to_string()))
+    /// );
+    /// assert!(ControlCommand::parse("reboot").is_err());
+    /// ```
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim();
+        let (name, args) = line
+            .split_once(char::is_whitespace)
+            .map_or((line, ""), |(name, args)| (name, args.trim()));
+        match name {
+            "send" => {
+                let text = unquote(args)?;
+                if text.trim().is_empty() {
+                    return Err(String::from("usage: send <text>"));
+                }
+                Ok(ControlCommand::Send(text))
+            }
+            "export" if args.is_empty() => Ok(ControlCommand::Export(None)),
+            "export" => Ok(ControlCommand::Export(Some(unquote(args)?))),
+            "switch-model" if !args.is_empty() => Ok(ControlCommand::SwitchModel(unquote(args)?)),
+            "switch-model" => Err(String::from("usage: switch-model <name>")),
+            _ => Err(format!("unknown command: {name}")),
+        }
+    }
+
+    /// Returns the input line the command stands for.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::control::ControlCommand;
+    ///
+    /// assert_eq!(ControlCommand::Export(None).input(), "/export");
+    /// assert_eq!(
+    ///     ControlCommand::SwitchModel("mistral".to_string()).input(),
+    ///     "/model mistral"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn input(&self) -> String {
+        match self {
+            ControlCommand::Send(text) => text.clone(),
+            ControlCommand::Export(None) => String::from("/export"),
+            ControlCommand::Export(Some(path)) => format!("/export {path}"),
+            ControlCommand::SwitchModel(model) => format!("/model {model}"),
+        }
+    }
+}
+
+/// Removes the surrounding quotes and escapes of a quoted argument; other
+/// arguments are returned as they are.
+fn unquote(text: &str) -> Result<String, String> {
+    let Some(inner) = text.strip_prefix('"') else {
+        return Ok(text.to_string());
+    };
+    let mut result = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some(c) => result.push(c),
+                None => break,
+            },
+            '"' if chars.as_str().trim().is_empty() => return Ok(result),
+            '"' => return Err(String::from("unexpected text after the closing quote")),
+            c => result.push(c),
+        }
+    }
+    Err(String::from("unclosed quote"))
+}
+
+/// Returns the default socket path: `chatti.sock` in `$XDG_RUNTIME_DIR`, or
+/// in the temporary directory if it is not set.
+#[must_use]
+pub fn default_socket_path() -> PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map_or_else(std::env::temp_dir, PathBuf::from)
+        .join("chatti.sock")
+}
+
+/// The listening control socket. The socket file is removed when it is
+/// dropped.
+#[derive(Debug)]
+pub struct ControlSocket {
+    path: PathBuf,
+}
+
+impl ControlSocket {
+    /// Returns the path of the socket.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ControlSocket {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Starts listening for commands on a Unix socket.
+///
+/// If another instance is already listening on `path`, the process id is
+/// added to the file name. A stale socket left by a crashed instance is
+/// replaced. The socket is only accessible to the current user.
+///
+/// # Arguments
+///
+/// * `path` - The path of the socket.
+///
+/// # Returns
+///
+/// The socket and a receiver of the input lines the commands stand for.
+///
+/// # Errors
+///
+/// Returns an `io::Error` if the socket cannot be created.
+pub async fn listen(path: &Path) -> io::Result<(ControlSocket, mpsc::Receiver<String>)> {
+    let mut path = path.to_path_buf();
+    if UnixStream::connect(&path).await.is_ok() {
+        let name = format!(
+            "{}-{}.sock",
+            path.file_stem().unwrap_or_default().to_string_lossy(),
+            std::process::id()
+        );
+        path.set_file_name(name);
+    }
+    let _ = std::fs::remove_file(&path);
+
+    let listener = bind_private(&path)?;
+    debug!(path = %path.display(), "control socket listening");
+
+    let (tx, rx) = mpsc::channel(16);
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(serve(stream, tx.clone()));
+                }
+                Err(err) => {
+                    warn!(?err, "control socket accept failed");
+                    break;
+                }
+            }
+        }
+    });
+    Ok((ControlSocket { path }, rx))
+}
+
+/// Binds a socket at `path` that only the current user can connect to.
+///
+/// The socket is bound in a directory only the user can enter, made
+/// private there and then moved to `path`, so it is never reachable by
+/// others before its permissions are set.
+fn bind_private(path: &Path) -> io::Result<UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let dir = path.with_file_name(format!(
+        ".{}-{}",
+        path.file_name().unwrap_or_default().to_string_lossy(),
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::DirBuilder::new().mode(0o700).create(&dir)?;
+    let staged = dir.join("socket");
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_dir_all(&dir);
+    bound
+}
+
+/// Answers the commands of one connection.
+async fn serve(stream: UnixStream, tx: mpsc::Sender<String>) {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match ControlCommand::parse(&line) {
+            Ok(command) => match tx.send(command.input()).await {
+                Ok(()) => String::from("ok\n"),
+                Err(_) => String::from("error: chatti is shutting down\n"),
+            },
+            Err(err) => format!("error: {err}\n"),
+        };
+        if writer.write_all(reply.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+}
@@ -0,0 +1,142 @@
+//! Writes marked prompts and answers as a fine-tuning dataset.
+//!
+//! Each exchange a mark filter keeps becomes one JSON line: the system
+//! prompt, if there is one, the prompt as it was sent, with its attached
+//! context, and the answer. Two layouts are supported: OpenAI's chat
+//! fine-tuning format, with a `messages` array of `role` and `content`,
+//! and the ShareGPT format, with a `conversations` array of `from` and
+//! `value`.
+
+use serde_json::{json, Value};
+
+use crate::message::{MarkFilter, Message};
+
+/// The layout of the lines of a dataset.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DatasetFormat {
+    /// `{"messages": [{"role": "user", "content": ...}, ...]}`.
+    #[default]
+    OpenAi,
+    /// `{"conversations": [{"from": "human", "value": ...}, ...]}`.
+    ShareGpt,
+}
+
+impl DatasetFormat {
+    /// Returns the format with the given name, `openai` or `sharegpt`.
+    #[must_use]
+    pub fn named(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "openai" => Some(DatasetFormat::OpenAi),
+            "sharegpt" => Some(DatasetFormat::ShareGpt),
+            _ => None,
+        }
+    }
+
+    /// Returns one line of the dataset.
+    fn line(self, system: Option<&str>, prompt: &str, answer: &str) -> Value {
+        let turns = system
+            .map(|system| ("system", system))
+            .into_iter()
+            .chain([("user", prompt), ("assistant", answer)]);
+        match self {
+            DatasetFormat::OpenAi => {
+                let messages: Vec<Value> = turns
+                    .map(|(role, content)| json!({"role": role, "content": content}))
+                    .collect();
+                json!({ "messages": messages })
+            }
+            DatasetFormat::ShareGpt => {
+                let conversations: Vec<Value> = turns
+                    .map(|(role, value)| {
+                        let from = match role {
+                            "user" => "human",
+                            "assistant" => "gpt",
+                            other => other,
+                        };
+                        json!({"from": from, "value": value})
+                    })
+                    .collect();
+                json!({ "conversations": conversations })
+            }
+        }
+    }
+}
+
+/// Returns the prompts and answers of the exchanges a filter keeps. The
+/// prompt is the text that was sent, with its attached context, and the
+/// answer is the one carrying a mark the filter asks for, or else the last
+/// answer of the exchange. Exchanges without an answer are left out.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::dataset::pairs;
+/// use chatti::message::{Mark, MarkFilter, Message};
+///
+/// let mut messages = vec![
+///     Message::new("user", "Capital of France?"),
+///     Message::new("assistant", "Paris."),
+///     Message::new("user", "And of Peru?"),
+///     Message::new("assistant", "Lima."),
+/// ];
+/// messages[1].toggle_mark(Mark::Favorite);
+/// let favorites = MarkFilter { only: vec![Mark::Favorite], skip: Vec::new() };
+/// assert_eq!(
+///     pairs(&messages, &favorites),
+///     vec![("Capital of France?".to_string(), "Paris.".to_string())]
+/// );
+/// ```
+#[must_use]
+pub fn pairs(messages: &[Message], filter: &MarkFilter) -> Vec<(String, String)> {
+    let kept = filter.apply(messages);
+    let mut pairs = Vec::new();
+    let mut rest = kept.as_slice();
+    while let Some(start) = rest.iter().position(|message| message.role == "user") {
+        let prompt = &rest[start];
+        rest = &rest[start + 1..];
+        let end = rest
+            .iter()
+            .position(|message| message.role == "user")
+            .unwrap_or(rest.len());
+        let answers: Vec<&Message> = rest[..end]
+            .iter()
+            .filter(|message| message.role == "assistant" && message.is_sent())
+            .collect();
+        let answer = answers
+            .iter()
+            .find(|answer| answer.marks.iter().any(|mark| filter.only.contains(mark)))
+            .or_else(|| answers.last());
+        if let Some(answer) = answer {
+            pairs.push((prompt.request_content(), answer.content.clone()));
+        }
+        rest = &rest[end..];
+    }
+    pairs
+}
+
+/// Writes prompts and answers as JSON lines.
+///
+/// # Arguments
+///
+/// * `format` - The layout of the lines.
+/// * `system` - The system prompt sent before each prompt, if any.
+/// * `pairs` - The prompts and their answers.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::dataset::{to_jsonl, DatasetFormat};
+///
+/// let pairs = vec![("Hi".to_string(), "Hello!".to_string())];
+/// assert_eq!(
+///     to_jsonl(DatasetFormat::ShareGpt, None, &pairs),
+///     "{\"conversations\":[{\"from\":\"human\",\"value\":\"Hi\"},{\"from\":\"gpt\",\"value\":\"Hello!\"}]}\n"
+/// );
+/// ```
+#[must_use]
+pub fn to_jsonl(format: DatasetFormat, system: Option<&str>, pairs: &[(String, String)]) -> String {
+    pairs
+        .iter()
+        .map(|(prompt, answer)| format!("{}\n", format.line(system, prompt, answer)))
+        .collect()
+}
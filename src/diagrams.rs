@@ -0,0 +1,309 @@
+//! Renders Mermaid and Graphviz diagrams of answers into images.
+//!
+//! A fenced code block tagged `mermaid`, or `dot` or `graphviz`, holds the
+//! source of a diagram. Pressing `g` on an answer, or `/diagrams`, renders
+//! its diagrams with the external command configured for their kind in the
+//! `[diagrams]` section. The images are shown below the answer on
+//! terminals with a graphics protocol, and opened with the system viewer
+//! on others. Rendered images are kept by the hash of their source, so a
+//! diagram is only rendered once. A command that runs longer than the
+//! configured timeout is stopped.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs,
+    hash::{Hash, Hasher},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::AtomicBool,
+    thread,
+    time::Duration,
+};
+
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag, TagEnd};
+
+use crate::commands::expand_home;
+use crate::config::DiagramsConfig;
+use crate::process;
+
+/// The language of a diagram.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Kind {
+    /// A Mermaid diagram, in a `mermaid` block.
+    Mermaid,
+    /// A Graphviz graph, in a `dot` or `graphviz` block.
+    Graphviz,
+}
+
+impl Kind {
+    /// Returns the kind of diagram a code block holds, judging by the
+    /// language of its fence, or `None` if it holds no diagram.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::diagrams::Kind;
+    ///
+    /// assert_eq!(Kind::from_language("mermaid"), Some(Kind::Mermaid));
+    /// assert_eq!(Kind::from_language("dot {layout=neato}"), Some(Kind::Graphviz));
+    /// assert_eq!(Kind::from_language("rust"), None);
+    /// ```
+    #[must_use]
+    pub fn from_language(language: &str) -> Option<Self> {
+        let language = language.split_whitespace().next()?.to_ascii_lowercase();
+        match language.as_str() {
+            "mermaid" => Some(Kind::Mermaid),
+            "dot" | "graphviz" => Some(Kind::Graphviz),
+            _ => None,
+        }
+    }
+
+    /// Returns the extension of the source file given to the command.
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Kind::Mermaid => "mmd",
+            Kind::Graphviz => "dot",
+        }
+    }
+
+    /// Returns the name of the kind, as shown in the transcript.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Kind::Mermaid => "Mermaid",
+            Kind::Graphviz => "Graphviz",
+        }
+    }
+}
+
+/// A diagram written in an answer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Diagram {
+    /// The language it is written in.
+    pub kind: Kind,
+    /// Its source, as in the code block.
+    pub source: String,
+}
+
+/// Represents errors that can occur while rendering a diagram.
+#[derive(Debug, thiserror::Error)]
+pub enum DiagramError {
+    /// The source could not be written, or the command could not be
+    /// started.
+    #[error("could not render with `{command}`: {source}")]
+    Io {
+        /// The command.
+        command: String,
+        /// The underlying error.
+        source: io::Error,
+    },
+
+    /// The command exited unsuccessfully.
+    #[error("`{command}` failed ({status}): {stderr}")]
+    Failed {
+        /// The command.
+        command: String,
+        /// The exit status of the command.
+        status: String,
+        /// What the command wrote to standard error.
+        stderr: String,
+    },
+
+    /// The command ran out of time and was stopped.
+    #[error("`{command}` did not finish within {seconds} seconds")]
+    TimedOut {
+        /// The command.
+        command: String,
+        /// How long it was given.
+        seconds: u64,
+    },
+
+    /// The render was cancelled and its command stopped.
+    #[error("`{command}` was cancelled")]
+    Cancelled {
+        /// The command.
+        command: String,
+    },
+
+    /// The command succeeded without writing the image.
+    #[error("`{command}` wrote no image")]
+    NoOutput {
+        /// The command.
+        command: String,
+    },
+}
+
+/// Returns the diagrams written in markdown, in order.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::diagrams::{find, Kind};
+///
+/// let answer = "Flow:\n\n```mermaid\ngraph TD; A-->B;\n```\n\n```rust\nfn main() {}\n```\n";
+/// let diagrams = find(answer);
+/// assert_eq!(diagrams.len(), 1);
+/// assert_eq!(diagrams[0].kind, Kind::Mermaid);
+/// assert_eq!(diagrams[0].source, "graph TD; A-->B;\n");
+/// ```
+#[must_use]
+pub fn find(markdown: &str) -> Vec<Diagram> {
+    let mut diagrams = Vec::new();
+    let mut current: Option<Diagram> = None;
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(language))) => {
+                current = Kind::from_language(&language).map(|kind| Diagram {
+                    kind,
+                    source: String::new(),
+                });
+            }
+            Event::Text(text) => {
+                if let Some(diagram) = &mut current {
+                    diagram.source.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                diagrams.extend(current.take().filter(|d| !d.source.trim().is_empty()));
+            }
+            _ => {}
+        }
+    }
+    diagrams
+}
+
+/// Returns the directory rendered diagrams are kept in by default,
+/// `~/.config/chatti/diagrams`.
+#[must_use]
+pub fn default_directory() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".config")
+        .join("chatti")
+        .join("diagrams")
+}
+
+/// Builds the command that renders a diagram, putting the quoted paths of
+/// the source and the image in place of `{input}` and `{output}`.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::diagrams::command_line;
+/// use std::path::Path;
+///
+/// assert_eq!(
+///     command_line("dot -Tpng {input} -o {output}", Path::new("/tmp/a.dot"), Path::new("/tmp/a.png")),
+///     "dot -Tpng '/tmp/a.dot' -o '/tmp/a.png'"
+/// );
+/// ```
+#[must_use]
+pub fn command_line(template: &str, input: &Path, output: &Path) -> String {
+    template
+        .replace("{input}", &quote(input))
+        .replace("{output}", &quote(output))
+}
+
+fn quote(path: &Path) -> String {
+    format!("'{}'", path.display().to_string().replace('\'', r"'\''"))
+}
+
+/// Renders a diagram into a PNG image with the command configured for its
+/// kind, unless it was rendered before. The command is killed once
+/// `cancelled` is set.
+///
+/// # Returns
+///
+/// The path of the image.
+///
+/// # Errors
+///
+/// Returns a `DiagramError` if the source cannot be written, or the
+/// command cannot be started, fails, runs out of time, is cancelled or
+/// writes no image.
+pub fn render(
+    diagram: &Diagram,
+    config: &DiagramsConfig,
+    cancelled: &AtomicBool,
+) -> Result<PathBuf, DiagramError> {
+    let template = match diagram.kind {
+        Kind::Mermaid => &config.mermaid,
+        Kind::Graphviz => &config.graphviz,
+    };
+    let directory = config
+        .directory
+        .as_deref()
+        .map_or_else(default_directory, |dir| expand_home(&dir.to_string_lossy()));
+    let mut hasher = DefaultHasher::new();
+    (template, diagram).hash(&mut hasher);
+    let name = format!("{:016x}", hasher.finish());
+    let input = directory.join(format!("{name}.{}", diagram.kind.extension()));
+    let output = directory.join(format!("{name}.png"));
+    if output.is_file() {
+        return Ok(output);
+    }
+
+    let command = command_line(template, &input, &output);
+    let io_error = |source| DiagramError::Io {
+        command: command.clone(),
+        source,
+    };
+    fs::create_dir_all(&directory).map_err(io_error)?;
+    fs::write(&input, &diagram.source).map_err(io_error)?;
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let mut sh = Command::new("sh");
+    sh.arg("-c").arg(&command);
+    let result = match process::run_cancellable(&mut sh, None, timeout, cancelled) {
+        Ok(result) => result,
+        Err(err) if err.kind() == ErrorKind::TimedOut => {
+            return Err(DiagramError::TimedOut {
+                command,
+                seconds: config.timeout_secs,
+            })
+        }
+        Err(err) if err.kind() == ErrorKind::Interrupted => {
+            return Err(DiagramError::Cancelled { command })
+        }
+        Err(err) => return Err(io_error(err)),
+    };
+    if !result.status.success() {
+        return Err(DiagramError::Failed {
+            command,
+            status: result.status.to_string(),
+            stderr: result.stderr.trim().to_string(),
+        });
+    }
+    if output.is_file() {
+        Ok(output)
+    } else {
+        Err(DiagramError::NoOutput { command })
+    }
+}
+
+/// Opens an image with the configured viewer, or the system's, without
+/// waiting for it to close.
+///
+/// # Errors
+///
+/// Returns an error if the viewer cannot be started.
+pub fn open(image: &Path, config: &DiagramsConfig) -> io::Result<()> {
+    let viewer = config
+        .open
+        .as_deref()
+        .unwrap_or(if cfg!(target_os = "macos") {
+            "open"
+        } else {
+            "xdg-open"
+        });
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{viewer} {}", quote(image)))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    thread::spawn(move || child.wait());
+    Ok(())
+}
@@ -0,0 +1,179 @@
+//! Extracts the text of PDF and DOCX documents to attach them.
+//!
+//! A PDF is read page by page, each page under a `[Page N]` marker. A DOCX
+//! file is a zip archive around `word/document.xml`, whose paragraphs are
+//! read in order, with headings kept as markdown headings so the sections
+//! of the document stay recognisable. Both need chatti built with the
+//! `documents` feature, which is on by default.
+
+use std::path::Path;
+
+/// A kind of document whose text can be extracted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A PDF document.
+    Pdf,
+    /// A Word document in the Office Open XML format.
+    Docx,
+}
+
+impl Kind {
+    /// Returns the kind of document a file is, from its extension.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::documents::Kind;
+    /// use std::path::Path;
+    ///
+    /// assert_eq!(Kind::of(Path::new("report.PDF")), Some(Kind::Pdf));
+    /// assert_eq!(Kind::of(Path::new("notes.docx")), Some(Kind::Docx));
+    /// assert_eq!(Kind::of(Path::new("main.rs")), None);
+    /// ```
+    #[must_use]
+    pub fn of(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "pdf" => Some(Kind::Pdf),
+            "docx" => Some(Kind::Docx),
+            _ => None,
+        }
+    }
+}
+
+/// Represents errors that can occur while extracting the text of a
+/// document.
+#[derive(Debug, thiserror::Error)]
+pub enum DocumentError {
+    /// The PDF could not be parsed.
+    #[cfg(feature = "documents")]
+    #[error("cannot read the PDF: {0}")]
+    Pdf(#[from] lopdf::Error),
+    /// The DOCX archive could not be opened.
+    #[cfg(feature = "documents")]
+    #[error("cannot read the DOCX archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+    /// The text of the DOCX file could not be parsed.
+    #[cfg(feature = "documents")]
+    #[error("cannot read the DOCX text: {0}")]
+    Xml(#[from] roxmltree::Error),
+    /// The document could not be read.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    /// The document is encrypted.
+    #[error("the document is encrypted")]
+    Encrypted,
+    /// The document has no text, such as a scanned PDF.
+    #[error("the document has no text to extract")]
+    NoText,
+    /// Chatti was built without the `documents` feature.
+    #[error("reading documents needs chatti built with the documents feature")]
+    Unsupported,
+}
+
+/// Extracts the text of a document.
+///
+/// # Arguments
+///
+/// * `kind` - The kind of document.
+/// * `bytes` - The contents of the file.
+///
+/// # Errors
+///
+/// Returns a `DocumentError` if the document cannot be parsed, is
+/// encrypted or has no text.
+pub fn extract(kind: Kind, bytes: &[u8]) -> Result<String, DocumentError> {
+    let text = match kind {
+        Kind::Pdf => pdf_text(bytes)?,
+        Kind::Docx => docx_text(bytes)?,
+    };
+    if text.trim().is_empty() {
+        return Err(DocumentError::NoText);
+    }
+    Ok(text)
+}
+
+/// Extracts the text of each page of a PDF under a page marker.
+#[cfg(feature = "documents")]
+fn pdf_text(bytes: &[u8]) -> Result<String, DocumentError> {
+    let document = lopdf::Document::load_mem(bytes)?;
+    if document.is_encrypted() {
+        return Err(DocumentError::Encrypted);
+    }
+    let mut pages = Vec::new();
+    let mut any_text = false;
+    for number in document.get_pages().into_keys() {
+        let text = document.extract_text(&[number]).unwrap_or_default();
+        any_text |= !text.trim().is_empty();
+        pages.push(format!("[Page {number}]\n{}", text.trim()));
+    }
+    if !any_text {
+        return Err(DocumentError::NoText);
+    }
+    Ok(pages.join("\n\n"))
+}
+
+/// The namespace of the elements of a Word document.
+#[cfg(feature = "documents")]
+const WORD_NAMESPACE: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+
+/// Extracts the paragraphs of a DOCX file, with headings as markdown
+/// headings.
+#[cfg(feature = "documents")]
+fn docx_text(bytes: &[u8]) -> Result<String, DocumentError> {
+    use std::io::{Cursor, Read};
+
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes))?;
+    let mut xml = String::new();
+    archive
+        .by_name("word/document.xml")?
+        .read_to_string(&mut xml)?;
+    let document = roxmltree::Document::parse(&xml)?;
+
+    let is = |node: &roxmltree::Node, name: &str| {
+        node.tag_name().namespace() == Some(WORD_NAMESPACE) && node.tag_name().name() == name
+    };
+    let mut paragraphs = Vec::new();
+    for paragraph in document.descendants().filter(|node| is(node, "p")) {
+        let mut text = String::new();
+        for node in paragraph.descendants() {
+            if is(&node, "t") {
+                text.push_str(node.text().unwrap_or_default());
+            } else if is(&node, "tab") {
+                text.push('\t');
+            } else if is(&node, "br") || is(&node, "cr") {
+                text.push('\n');
+            }
+        }
+        if text.trim().is_empty() {
+            continue;
+        }
+        let style = paragraph
+            .descendants()
+            .find(|node| is(node, "pStyle"))
+            .and_then(|node| node.attribute((WORD_NAMESPACE, "val")))
+            .unwrap_or_default();
+        let level = match style {
+            "Title" => Some(1),
+            _ => style
+                .strip_prefix("Heading")
+                .and_then(|level| level.parse::<usize>().ok())
+                .map(|level| (level + 1).min(6)),
+        };
+        match level {
+            Some(level) => paragraphs.push(format!("{} {}", "#".repeat(level), text.trim())),
+            None => paragraphs.push(text),
+        }
+    }
+    Ok(paragraphs.join("\n\n"))
+}
+
+#[cfg(not(feature = "documents"))]
+fn pdf_text(_: &[u8]) -> Result<String, DocumentError> {
+    Err(DocumentError::Unsupported)
+}
+
+#[cfg(not(feature = "documents"))]
+fn docx_text(_: &[u8]) -> Result<String, DocumentError> {
+    Err(DocumentError::Unsupported)
+}
@@ -1,6 +1,10 @@
+use serde::{Deserialize, Serialize};
 use std::error::Error as StdError;
 use thiserror::Error;
 
+/// The maximum number of characters of a response body kept in error details.
+const BODY_EXCERPT_CHARS: usize = 500;
+
 /// Represents application-wide errors.
 #[derive(Debug, Error)]
 pub enum Application {
@@ -16,12 +20,35 @@ pub enum Application {
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
 
+    /// WebSocket errors.
+    #[error("WebSocket error: {0}")]
+    WebSocket(Box<tokio_tungstenite::tungstenite::Error>),
+
     /// JSON parsing errors.
     #[error("JSON parsing error: {0}")]
     JsonParse(#[from] serde_json::Error),
 
-    #[error("API error: {0}")]
-    ApiError(String),
+    /// Invalid redaction pattern in the configuration.
+    #[error("Invalid redaction pattern: {0}")]
+    Redaction(#[from] regex::Error),
+
+    /// The API answered with an unsuccessful status.
+    #[error("API error ({status}): {message}")]
+    ApiError {
+        /// The error message reported by the API.
+        message: String,
+        /// The HTTP status code.
+        status: u16,
+        /// The response body.
+        body: String,
+        /// The request id reported by the server, if any.
+        request_id: Option<String>,
+    },
+
+    /// A request was refused because local-only mode is on and the
+    /// endpoint is on another machine.
+    #[error("{0}")]
+    LocalOnly(String),
 
     /// Unexpected errors.
     #[error("Unexpected error: {0}")]
@@ -34,6 +61,12 @@ impl From<Box<dyn StdError>> for Application {
     }
 }
 
+impl From<tokio_tungstenite::tungstenite::Error> for Application {
+    fn from(error: tokio_tungstenite::tungstenite::Error) -> Self {
+        Application::WebSocket(Box::new(error))
+    }
+}
+
 impl Application {
     /// Display an error message.
     ///
@@ -56,12 +89,126 @@ impl Application {
         match self {
             Application::Config(_) => "There was an issue with the application configuration",
             Application::Ui(_) => "An error occurred in the user interface",
-            Application::Network(_) => "There was a problem connecting to the server",
+            Application::Network(_) | Application::WebSocket(_) => {
+                "There was a problem connecting to the server"
+            }
             Application::JsonParse(_) => "There was an issue processing the server response",
-            Application::ApiError(msg) => msg,
+            Application::Redaction(_) => "A redaction pattern in the configuration is invalid",
+            Application::ApiError { message, .. } | Application::LocalOnly(message) => message,
             Application::Unexpected(_) => "An unexpected error occurred",
         }
     }
+
+    /// Returns `true` if the error means the endpoint could not be reached.
+    #[must_use]
+    pub fn is_connection_error(&self) -> bool {
+        match self {
+            Application::Network(err) => err.is_connect() || err.is_timeout(),
+            Application::WebSocket(err) => {
+                matches!(**err, tokio_tungstenite::tungstenite::Error::Io(_))
+            }
+            _ => false,
+        }
+    }
+
+    /// Collects the technical details of the error for display in the
+    /// transcript.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::error::Application;
+    ///
+    /// let error = Application::ApiError {
+    ///     message: "model not found".to_string(),
+    ///     status: 404,
+    ///     body: r#"{"error":"model not found"}"#.to_string(),
+    ///     request_id: Some("req-1".to_string()),
+    /// };
+    /// let details = error.details();
+    /// assert_eq!(details.status, Some(404));
+    /// assert_eq!(details.request_id.as_deref(), Some("req-1"));
+    /// ```
+    #[must_use]
+    pub fn details(&self) -> ErrorDetails {
+        match self {
+            Application::ApiError {
+                status,
+                body,
+                request_id,
+                ..
+            } => ErrorDetails {
+                status: Some(*status),
+                request_id: request_id.clone(),
+                body: Some(excerpt(body)),
+                source: self.to_string(),
+            },
+            Application::Network(err) => ErrorDetails {
+                status: err.status().map(|status| status.as_u16()),
+                source: err.to_string(),
+                ..ErrorDetails::default()
+            },
+            _ => ErrorDetails {
+                source: self.to_string(),
+                ..ErrorDetails::default()
+            },
+        }
+    }
+}
+
+/// Technical details of an error, shown when an error card is expanded.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ErrorDetails {
+    /// The HTTP status code, if the error came from a response.
+    pub status: Option<u16>,
+    /// The request id reported by the server, if any.
+    pub request_id: Option<String>,
+    /// An excerpt of the response body.
+    pub body: Option<String>,
+    /// The full error message.
+    pub source: String,
+}
+
+impl ErrorDetails {
+    /// Returns the details as `label: value` lines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::error::ErrorDetails;
+    ///
+    /// let details = ErrorDetails {
+    ///     status: Some(500),
+    ///     source: "boom".to_string(),
+    ///     ..ErrorDetails::default()
+    /// };
+    /// assert_eq!(details.lines(), vec!["status: 500", "error: boom"]);
+    /// ```
+    #[must_use]
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(status) = self.status {
+            lines.push(format!("status: {status}"));
+        }
+        if let Some(request_id) = &self.request_id {
+            lines.push(format!("request id: {request_id}"));
+        }
+        lines.push(format!("error: {}", self.source));
+        if let Some(body) = self.body.as_deref().filter(|body| !body.is_empty()) {
+            lines.push(format!("body: {body}"));
+        }
+        lines
+    }
+}
+
+fn excerpt(body: &str) -> String {
+    let body = body.trim();
+    if body.chars().count() > BODY_EXCERPT_CHARS {
+        let cut: String = body.chars().take(BODY_EXCERPT_CHARS).collect();
+        format!("{cut}…")
+    } else {
+        body.to_string()
+    }
 }
 
 /// A type alias for Results that use the Application error type.
@@ -0,0 +1,220 @@
+//! Builds prompts from the state of the git repository in a directory.
+//!
+//! The helpers shell out to `git`, so they work wherever git is installed and
+//! follow the user's git configuration.
+
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+    process::{self, Stdio},
+};
+
+/// The maximum number of bytes of a diff included in a prompt.
+const MAX_DIFF_BYTES: usize = 60 * 1024;
+
+/// The number of recent commits shown to the model when writing a commit
+/// message.
+const LOG_ENTRIES: usize = 10;
+
+/// Represents errors that can occur while gathering git state.
+#[derive(Debug, thiserror::Error)]
+pub enum GitError {
+    /// `git` could not be started.
+    #[error("could not run git: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// `git` exited unsuccessfully, for example outside a repository.
+    #[error("git {command} failed: {stderr}")]
+    Failed {
+        /// The git subcommand that was run.
+        command: String,
+        /// What git wrote to standard error.
+        stderr: String,
+    },
+
+    /// There are no changes to send.
+    #[error("no {0} to send")]
+    NoChanges(&'static str),
+}
+
+/// A prompt built from the repository state.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GitPrompt {
+    /// Review the uncommitted changes in the working tree.
+    Diff,
+    /// Write a commit message for the staged changes.
+    CommitMessage,
+    /// Explain the staged changes.
+    ExplainStaged,
+}
+
+impl GitPrompt {
+    /// Builds the prompt from the repository in `dir`.
+    ///
+    /// # Arguments
+    ///
+    /// * `dir` - A directory inside the repository.
+    ///
+    /// # Returns
+    ///
+    /// The prompt to send, including the relevant diff.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `GitError` if git fails or there are no changes to send.
+    pub fn build(self, dir: &Path) -> Result<String, GitError> {
+        match self {
+            GitPrompt::Diff => {
+                let diff = working_tree_diff(dir)?;
+                Ok(format!(
+                    "Review the following uncommitted changes. Point out bugs, risky \
+                     changes and anything that looks unfinished.\n\n```diff\n{diff}\n```"
+                ))
+            }
+            GitPrompt::CommitMessage => {
+                let diff = staged_diff(dir)?;
+                let log = recent_log(dir).unwrap_or_default();
+                let mut prompt = String::from(
+                    "Write a commit message for the staged changes below: a summary line \
+                     of at most 72 characters, a blank line, then a short body explaining \
+                     why the change was made. Reply with the message only.",
+                );
+                if !log.is_empty() {
+                    prompt.push_str(&format!(
+                        " Match the style of the recent commits.\n\nRecent commits:\n```\n{log}\n```"
+                    ));
+                }
+                prompt.push_str(&format!("\n\nStaged changes:\n```diff\n{diff}\n```"));
+                Ok(prompt)
+            }
+            GitPrompt::ExplainStaged => {
+                let diff = staged_diff(dir)?;
+                Ok(format!(
+                    "Explain what the following staged changes do, file by file, and \
+                     summarize their overall effect.\n\n```diff\n{diff}\n```"
+                ))
+            }
+        }
+    }
+}
+
+/// Returns the staged and unstaged changes against `HEAD`.
+///
+/// In a repository without commits, the staged changes are followed by the
+/// unstaged ones instead.
+///
+/// # Errors
+///
+/// Returns a `GitError` if git fails or the working tree is clean.
+pub fn working_tree_diff(dir: &Path) -> Result<String, GitError> {
+    let diff = match git(dir, &["diff", "HEAD"]) {
+        Ok(diff) => diff,
+        Err(GitError::Failed { .. }) => {
+            let staged = git(dir, &["diff", "--cached"])?;
+            let unstaged = git(dir, &["diff"])?;
+            format!("{staged}{unstaged}")
+        }
+        Err(err) => return Err(err),
+    };
+    non_empty(diff, "uncommitted changes")
+}
+
+/// Returns the staged changes.
+///
+/// # Errors
+///
+/// Returns a `GitError` if git fails or nothing is staged.
+pub fn staged_diff(dir: &Path) -> Result<String, GitError> {
+    non_empty(git(dir, &["diff", "--cached"])?, "staged changes")
+}
+
+/// Returns the summary lines of the most recent commits.
+///
+/// # Errors
+///
+/// Returns a `GitError` if git fails, for example before the first commit.
+pub fn recent_log(dir: &Path) -> Result<String, GitError> {
+    let count = LOG_ENTRIES.to_string();
+    Ok(git(dir, &["log", "--oneline", "-n", &count])?
+        .trim_end()
+        .to_string())
+}
+
+/// Returns the paths git ignores, through `.gitignore` files or the
+/// user's exclude settings.
+///
+/// # Arguments
+///
+/// * `dir` - A directory inside the repository.
+/// * `paths` - The paths to check, relative to `dir` or absolute.
+///
+/// # Errors
+///
+/// Returns a `GitError` if git cannot be run, or `dir` is not in a
+/// repository.
+pub fn ignored(dir: &Path, paths: &[PathBuf]) -> Result<Vec<PathBuf>, GitError> {
+    let mut child = process::Command::new("git")
+        .args(["check-ignore", "--stdin", "-z"])
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut input = Vec::new();
+    for path in paths {
+        input.extend_from_slice(path.to_string_lossy().as_bytes());
+        input.push(0);
+    }
+    // Written from another thread, so that git never waits on a full
+    // output pipe while the input is still being written.
+    let writer = child
+        .stdin
+        .take()
+        .map(|mut stdin| std::thread::spawn(move || stdin.write_all(&input)));
+    let output = child.wait_with_output()?;
+    if let Some(writer) = writer {
+        writer.join().ok();
+    }
+    // git exits with 1 when none of the paths is ignored.
+    if !output.status.success() && output.status.code() != Some(1) {
+        return Err(GitError::Failed {
+            command: String::from("check-ignore"),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String, GitError> {
+    let output = process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()?;
+    if !output.status.success() {
+        return Err(GitError::Failed {
+            command: args.join(" "),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn non_empty(diff: String, what: &'static str) -> Result<String, GitError> {
+    let mut diff = diff.trim_end().to_string();
+    if diff.is_empty() {
+        return Err(GitError::NoChanges(what));
+    }
+    if diff.len() > MAX_DIFF_BYTES {
+        let mut end = MAX_DIFF_BYTES;
+        while !diff.is_char_boundary(end) {
+            end -= 1;
+        }
+        diff.truncate(end);
+        diff.push_str("\n[diff truncated]");
+    }
+    Ok(diff)
+}
@@ -0,0 +1,202 @@
+//! Remembers the prompts sent from the input line, like a shell history.
+//!
+//! The history is kept apart from sessions in `~/.local/share/chatti/history`,
+//! so `Up` recalls earlier prompts and `Ctrl+R` searches them whichever
+//! session is open. Each entry is one line of the file, with newlines and
+//! backslashes escaped.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::config::FSError;
+
+/// Returns the path of the input history file.
+///
+/// # Errors
+///
+/// Returns `FSError::HomeDirNotFound` if the home directory cannot be found.
+pub fn path() -> Result<PathBuf, FSError> {
+    Ok(dirs::home_dir()
+        .ok_or(FSError::HomeDirNotFound)?
+        .join(".local")
+        .join("share")
+        .join("chatti")
+        .join("history"))
+}
+
+/// The sent prompts, oldest first, without duplicates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputHistory {
+    entries: Vec<String>,
+    max_entries: usize,
+}
+
+impl Default for InputHistory {
+    fn default() -> Self {
+        InputHistory::new(1000)
+    }
+}
+
+impl InputHistory {
+    /// Creates an empty history keeping at most `max_entries` prompts.
+    #[must_use]
+    pub fn new(max_entries: usize) -> Self {
+        InputHistory {
+            entries: Vec::new(),
+            max_entries,
+        }
+    }
+
+    /// Loads a history file. A missing file is an empty history.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The history file.
+    /// * `max_entries` - How many prompts to keep; older ones are dropped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the file exists but cannot be read.
+    pub fn load(path: &Path, max_entries: usize) -> Result<Self, FSError> {
+        let mut history = InputHistory::new(max_entries);
+        match fs::read_to_string(path) {
+            Ok(text) => text.lines().map(unescape).for_each(|entry| {
+                history.push(&entry);
+            }),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+        Ok(history)
+    }
+
+    /// Writes the history file, creating its directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<(), FSError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let text: String = self
+            .entries
+            .iter()
+            .map(|entry| format!("{}\n", escape(entry)))
+            .collect();
+        fs::write(path, text)?;
+        Ok(())
+    }
+
+    /// Adds a sent prompt as the newest entry. An earlier copy of the same
+    /// prompt is removed, and the oldest entries are dropped beyond the
+    /// maximum size.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the prompt is blank and was not added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::history::InputHistory;
+    ///
+    /// let mut history = InputHistory::new(2);
+    /// history.push("first");
+    /// history.push("second");
+    /// history.push("first");
+    /// history.push("third");
+    /// assert_eq!(history.entries(), ["first", "third"]);
+    /// ```
+    pub fn push(&mut self, prompt: &str) -> bool {
+        let prompt = prompt.trim();
+        if prompt.is_empty() {
+            return false;
+        }
+        self.entries.retain(|entry| entry != prompt);
+        self.entries.push(prompt.to_string());
+        let excess = self.entries.len().saturating_sub(self.max_entries);
+        self.entries.drain(..excess);
+        true
+    }
+
+    /// Returns the prompts, oldest first.
+    #[must_use]
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Returns the prompt at `index`, counting from the oldest.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// Returns the number of prompts.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if no prompts were sent yet.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Finds the newest prompt before `before` that contains `query`,
+    /// ignoring case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::history::InputHistory;
+    ///
+    /// let mut history = InputHistory::new(10);
+    /// history.push("Explain monads");
+    /// history.push("Fix this test");
+    /// history.push("explain lifetimes");
+    /// assert_eq!(history.search("explain", history.len()), Some(2));
+    /// assert_eq!(history.search("explain", 2), Some(0));
+    /// assert_eq!(history.search("explain", 0), None);
+    /// ```
+    #[must_use]
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        let query = query.to_lowercase();
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|entry| entry.to_lowercase().contains(&query))
+    }
+}
+
+/// A `Ctrl+R` search through the history in progress.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HistorySearch {
+    /// The text searched for.
+    pub query: String,
+    /// The index of the matching prompt, or `None` if nothing matches.
+    pub found: Option<usize>,
+}
+
+/// Escapes newlines and backslashes so an entry fits on one line.
+fn escape(entry: &str) -> String {
+    entry.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+/// Reverses [`escape`].
+fn unescape(line: &str) -> String {
+    let mut entry = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => entry.push('\n'),
+                Some(c) => entry.push(c),
+                None => entry.push('\\'),
+            },
+            c => entry.push(c),
+        }
+    }
+    entry
+}
@@ -0,0 +1,121 @@
+//! Runs external commands on completed responses.
+//!
+//! Each hook is run with `sh -c` and receives the response on standard input,
+//! so answers can be piped into note-taking tools, speech synthesizers and
+//! the like. Hooks run in the background and their failures are logged
+//! without affecting the chat.
+
+use std::{
+    io::{ErrorKind, Write},
+    process::{Command, Stdio},
+    thread,
+};
+
+use tracing::{error, info};
+
+/// Represents errors that can occur while running a hook.
+#[derive(Debug, thiserror::Error)]
+pub enum HookError {
+    /// The hook could not be started or fed its input.
+    #[error("could not run hook `{command}`: {source}")]
+    Io {
+        /// The hook command.
+        command: String,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+
+    /// The hook exited unsuccessfully.
+    #[error("hook `{command}` failed ({status}): {stderr}")]
+    Failed {
+        /// The hook command.
+        command: String,
+        /// The exit status of the hook.
+        status: String,
+        /// What the hook wrote to standard error.
+        stderr: String,
+    },
+}
+
+/// Runs a hook, writing the response to its standard input and waiting for
+/// it to exit.
+///
+/// The model that produced the response is available to the hook in the
+/// `CHATTI_MODEL` environment variable.
+///
+/// # Arguments
+///
+/// * `command` - The shell command to run.
+/// * `response` - The completed response.
+/// * `model` - The model that produced the response.
+///
+/// # Errors
+///
+/// Returns a `HookError` if the hook cannot be started or exits
+/// unsuccessfully.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::hooks::run;
+///
+/// assert!(run("grep -q Hello", "Hello, world", "llama3.2").is_ok());
+/// assert!(run("grep -q Goodbye", "Hello, world", "llama3.2").is_err());
+/// ```
+pub fn run(command: &str, response: &str, model: &str) -> Result<(), HookError> {
+    let io_error = |source| HookError::Io {
+        command: command.to_string(),
+        source,
+    };
+
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .env("CHATTI_MODEL", model)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(io_error)?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // A hook that exits without reading its input is not an error.
+        match stdin.write_all(response.as_bytes()) {
+            Err(err) if err.kind() != ErrorKind::BrokenPipe => return Err(io_error(err)),
+            _ => {}
+        }
+    }
+
+    let output = child.wait_with_output().map_err(io_error)?;
+    if !output.status.success() {
+        return Err(HookError::Failed {
+            command: command.to_string(),
+            status: output.status.to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Runs the hooks on a completed response in a background thread, one after
+/// the other, logging any failures.
+///
+/// # Arguments
+///
+/// * `commands` - The hook commands.
+/// * `response` - The completed response.
+/// * `model` - The model that produced the response.
+pub fn spawn(commands: &[String], response: String, model: String) {
+    if commands.is_empty() {
+        return;
+    }
+    let commands = commands.to_vec();
+    thread::spawn(move || {
+        for command in &commands {
+            match run(command, &response, &model) {
+                Ok(()) => info!(command, "response hook finished"),
+                Err(err) => error!(%err, "response hook failed"),
+            }
+        }
+    });
+}
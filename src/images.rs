@@ -0,0 +1,295 @@
+//! Finds the images of messages and reads what is needed to show and send
+//! them.
+//!
+//! A user message carries the image files attached to it, and an answer
+//! can show images with markdown image syntax, as local paths, `file://`
+//! URLs or `data:` URLs. Each image is shown in the transcript by its name
+//! and dimensions, and drawn inline on terminals with a graphics protocol
+//! when chatti is built with the `images` feature. Images on the web are
+//! not downloaded, and an answer cannot show files outside the working
+//! directory.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+
+use crate::commands::expand_home;
+use crate::message::Message;
+
+/// The extensions of the image files that can be attached.
+pub const EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "webp"];
+
+/// Returns `true` if the file is an image that can be attached, judging by
+/// its extension.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::images::is_image;
+/// use std::path::Path;
+///
+/// assert!(is_image(Path::new("/tmp/Screenshot.PNG")));
+/// assert!(!is_image(Path::new("notes.txt")));
+/// ```
+#[must_use]
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Returns the media type of an image file, judging by its extension.
+#[must_use]
+pub fn media_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match ext.as_deref() {
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        _ => "image/png",
+    }
+}
+
+/// Reads an image file as base64, as Ollama takes images.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read.
+pub fn encode(path: &Path) -> io::Result<String> {
+    Ok(STANDARD.encode(fs::read(path)?))
+}
+
+/// Reads an image file as a `data:` URL, as OpenAI-compatible servers take
+/// images.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read.
+pub fn data_url(path: &Path) -> io::Result<String> {
+    Ok(format!(
+        "data:{};base64,{}",
+        media_type(path),
+        encode(path)?
+    ))
+}
+
+/// Where the data of an image is.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// A local file.
+    File(PathBuf),
+    /// The bytes of a `data:` URL.
+    Data(Vec<u8>),
+    /// An image on the web, which is not downloaded.
+    Remote(String),
+    /// A local file outside the working directory, which an answer may not
+    /// show.
+    Outside(PathBuf),
+}
+
+/// An image of a message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Image {
+    /// What the image is called in the transcript: its file name, or the
+    /// alt text an answer gave it.
+    pub label: String,
+    /// Where its data is.
+    pub source: Source,
+}
+
+impl Image {
+    /// Returns an image file attached to a message.
+    #[must_use]
+    pub fn file(path: &Path) -> Self {
+        Image {
+            label: path.file_name().map_or_else(
+                || path.display().to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            ),
+            source: Source::File(path.to_path_buf()),
+        }
+    }
+
+    /// Returns the image a markdown image refers to.
+    ///
+    /// # Arguments
+    ///
+    /// * `alt` - The alt text, used as the label unless it is empty.
+    /// * `url` - A local path, `file://` URL, `data:` URL or web URL.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::images::{Image, Source};
+    ///
+    /// let image = Image::reference("", "data:image/png;base64,aGk=");
+    /// assert_eq!(image.source, Source::Data(b"hi".to_vec()));
+    /// assert_eq!(image.label, "image");
+    /// let image = Image::reference("A cat", "https://example.com/cat.png");
+    /// assert_eq!(image.label, "A cat");
+    /// ```
+    #[must_use]
+    pub fn reference(alt: &str, url: &str) -> Self {
+        let source = if let Some(data) = url.strip_prefix("data:") {
+            let bytes = data
+                .split_once(";base64,")
+                .and_then(|(_, encoded)| STANDARD.decode(encoded.trim()).ok())
+                .unwrap_or_default();
+            Source::Data(bytes)
+        } else if url.starts_with("http://") || url.starts_with("https://") {
+            Source::Remote(url.to_string())
+        } else {
+            let path = expand_home(url.strip_prefix("file://").unwrap_or(url));
+            if in_working_dir(&path) {
+                Source::File(path)
+            } else {
+                Source::Outside(path)
+            }
+        };
+        let label = match (&source, alt.trim()) {
+            (_, alt) if !alt.is_empty() => alt.to_string(),
+            (Source::File(path) | Source::Outside(path), _) => Image::file(path).label,
+            (Source::Data(_), _) => String::from("image"),
+            (Source::Remote(url), _) => url.clone(),
+        };
+        Image { label, source }
+    }
+
+    /// Returns the data of the image, or `None` if it is on the web or
+    /// cannot be read.
+    #[must_use]
+    pub fn bytes(&self) -> Option<Vec<u8>> {
+        match &self.source {
+            Source::File(path) => fs::read(path).ok(),
+            Source::Data(bytes) => Some(bytes.clone()),
+            Source::Remote(_) | Source::Outside(_) => None,
+        }
+    }
+
+    /// Returns the width and height of the image in pixels, read from its
+    /// header, or `None` if they cannot be read.
+    #[must_use]
+    pub fn dimensions(&self) -> Option<(usize, usize)> {
+        let size = match &self.source {
+            Source::File(path) => imagesize::size(path).ok()?,
+            Source::Data(bytes) => imagesize::blob_size(bytes).ok()?,
+            Source::Remote(_) | Source::Outside(_) => return None,
+        };
+        Some((size.width, size.height))
+    }
+
+    /// Describes the image in the transcript, in place of or above the
+    /// image itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::images::Image;
+    ///
+    /// let image = Image::reference("Logo", "https://example.com/logo.png");
+    /// assert_eq!(image.placeholder(), "[image: Logo]");
+    /// let image = Image::reference("", "no/such/chart.png");
+    /// assert_eq!(image.placeholder(), "[image: chart.png, not found]");
+    /// let image = Image::reference("", "/etc/secret.png");
+    /// assert_eq!(
+    ///     image.placeholder(),
+    ///     "[image: secret.png, outside the working directory]"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn placeholder(&self) -> String {
+        match (&self.source, self.dimensions()) {
+            (_, Some((width, height))) => format!("[image: {}, {width}x{height}]", self.label),
+            (Source::File(path), None) if !path.is_file() => {
+                format!("[image: {}, not found]", self.label)
+            }
+            (Source::Outside(_), _) => {
+                format!("[image: {}, outside the working directory]", self.label)
+            }
+            _ => format!("[image: {}]", self.label),
+        }
+    }
+}
+
+/// Returns the images of a message: the files attached to it, the images
+/// an answer shows, then the diagrams rendered from it, in order.
+#[must_use]
+pub fn of(message: &Message) -> Vec<Image> {
+    let mut images: Vec<Image> = message
+        .images
+        .iter()
+        .map(|path| Image::file(path))
+        .collect();
+    if message.role == "assistant" {
+        images.extend(references(&message.content));
+    }
+    images.extend(message.diagrams.iter().enumerate().map(|(i, path)| Image {
+        label: format!("diagram {}", i + 1),
+        source: Source::File(path.clone()),
+    }));
+    images
+}
+
+/// Returns `true` if a file an answer refers to is in the working
+/// directory. A file that does not exist counts if its relative path stays
+/// inside it.
+fn in_working_dir(path: &Path) -> bool {
+    let Ok(dir) = std::env::current_dir().and_then(fs::canonicalize) else {
+        return false;
+    };
+    match fs::canonicalize(path) {
+        Ok(real) => real.starts_with(dir),
+        Err(_) => {
+            path.is_relative()
+                && !path
+                    .components()
+                    .any(|part| part == std::path::Component::ParentDir)
+        }
+    }
+}
+
+/// Returns the images markdown shows, with their alt text.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::images::{references, Source};
+/// use std::path::PathBuf;
+///
+/// let images = references("Here it is:\n\n![A *red* bar chart](charts/sales.png)");
+/// assert_eq!(images.len(), 1);
+/// assert_eq!(images[0].label, "A red bar chart");
+/// assert_eq!(images[0].source, Source::File(PathBuf::from("charts/sales.png")));
+/// assert!(references("`![not](an-image.png)`").is_empty());
+/// ```
+#[must_use]
+pub fn references(markdown: &str) -> Vec<Image> {
+    let mut images = Vec::new();
+    let mut current: Option<(String, String)> = None;
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Start(Tag::Image { dest_url, .. }) => {
+                current = Some((dest_url.into_string(), String::new()));
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, alt)) = &mut current {
+                    alt.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Image) => {
+                if let Some((url, alt)) = current.take() {
+                    images.push(Image::reference(&alt, &url));
+                }
+            }
+            _ => {}
+        }
+    }
+    images
+}
@@ -0,0 +1,278 @@
+//! Imports conversations exported from other chat clients.
+//!
+//! Supported formats are detected automatically:
+//! - OpenAI-style JSON: a `[{"role", "content"}]` array, an object with a
+//!   `messages` array, or a ChatGPT data export (`conversations.json`) with a
+//!   `mapping` tree.
+//! - Plain markdown transcripts with role headings (`## User`) or role
+//!   prefixes (`**Assistant:**`, `You:`).
+
+use serde_json::Value;
+use std::{fs, path::Path};
+
+use crate::{message::Message, session::Session};
+
+/// Represents errors that can occur while importing a conversation.
+#[derive(Debug, thiserror::Error)]
+pub enum ImportError {
+    /// The file could not be read.
+    #[error("could not read file: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The file looked like JSON but could not be parsed.
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    /// The JSON structure is not a recognised export format.
+    #[error("unrecognised JSON export format")]
+    UnknownFormat,
+
+    /// No messages could be extracted from the file.
+    #[error("no messages found in the file")]
+    Empty,
+}
+
+/// The detected format of an import file.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Format {
+    /// OpenAI-style JSON export.
+    Json,
+    /// Markdown transcript.
+    Markdown,
+}
+
+/// Detects the format of the given content.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file, used for its extension.
+/// * `content` - The file content.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::import::{detect_format, Format};
+/// use std::path::Path;
+///
+/// assert_eq!(detect_format(Path::new("chat.txt"), "[]"), Format::Json);
+/// assert_eq!(detect_format(Path::new("chat.md"), "## User\nhi"), Format::Markdown);
+/// ```
+#[must_use]
+pub fn detect_format(path: &Path, content: &str) -> Format {
+    let is_json_ext = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let trimmed = content.trim_start();
+
+    if is_json_ext || trimmed.starts_with('{') || trimmed.starts_with('[') {
+        Format::Json
+    } else {
+        Format::Markdown
+    }
+}
+
+/// Imports one or more sessions from a file.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to import.
+///
+/// # Returns
+///
+/// The imported sessions. ChatGPT exports may contain several conversations.
+///
+/// # Errors
+///
+/// Returns an `ImportError` if the file cannot be read, is not a recognised
+/// format, or contains no messages.
+pub fn import_file(path: &Path) -> Result<Vec<Session>, ImportError> {
+    let content = fs::read_to_string(path)?;
+    import_str(path, &content)
+}
+
+/// Imports one or more sessions from already loaded content.
+///
+/// # Errors
+///
+/// Returns an `ImportError` if the content is not a recognised format or
+/// contains no messages.
+pub fn import_str(path: &Path, content: &str) -> Result<Vec<Session>, ImportError> {
+    let sessions = match detect_format(path, content) {
+        Format::Json => parse_json(&serde_json::from_str(content)?)?,
+        Format::Markdown => vec![Session::from_messages(parse_markdown(content))],
+    };
+
+    let sessions: Vec<Session> = sessions
+        .into_iter()
+        .filter(|s| !s.messages.is_empty())
+        .collect();
+
+    if sessions.is_empty() {
+        return Err(ImportError::Empty);
+    }
+    Ok(sessions)
+}
+
+fn parse_json(value: &Value) -> Result<Vec<Session>, ImportError> {
+    match value {
+        Value::Array(items) if items.iter().all(|i| i.get("mapping").is_some()) => {
+            items.iter().map(parse_chatgpt_conversation).collect()
+        }
+        Value::Array(items) => Ok(vec![Session::from_messages(parse_message_list(items))]),
+        Value::Object(_) if value.get("mapping").is_some() => {
+            Ok(vec![parse_chatgpt_conversation(value)?])
+        }
+        Value::Object(_) => {
+            let messages = value
+                .get("messages")
+                .and_then(Value::as_array)
+                .ok_or(ImportError::UnknownFormat)?;
+            let mut session = Session::from_messages(parse_message_list(messages));
+            if let Some(title) = value.get("title").and_then(Value::as_str) {
+                session.title = title.to_string();
+            }
+            Ok(vec![session])
+        }
+        _ => Err(ImportError::UnknownFormat),
+    }
+}
+
+fn parse_message_list(items: &[Value]) -> Vec<Message> {
+    items
+        .iter()
+        .filter_map(|item| {
+            let role = normalize_role(item.get("role")?.as_str()?)?;
+            let content = content_text(item.get("content")?);
+            (!content.trim().is_empty()).then(|| Message::new(role, content))
+        })
+        .collect()
+}
+
+/// Walks a ChatGPT export `mapping` tree from `current_node` back to the root.
+fn parse_chatgpt_conversation(value: &Value) -> Result<Session, ImportError> {
+    let mapping = value
+        .get("mapping")
+        .and_then(Value::as_object)
+        .ok_or(ImportError::UnknownFormat)?;
+
+    let mut node_id = value
+        .get("current_node")
+        .and_then(Value::as_str)
+        .map(String::from)
+        .or_else(|| {
+            mapping
+                .iter()
+                .find(|(_, node)| {
+                    node.get("children")
+                        .and_then(Value::as_array)
+                        .is_none_or(Vec::is_empty)
+                })
+                .map(|(id, _)| id.clone())
+        });
+
+    let mut messages = Vec::new();
+    while let Some(node) = node_id.as_deref().and_then(|id| mapping.get(id)) {
+        if let Some(message) = node.get("message").filter(|m| !m.is_null()) {
+            let role = message
+                .pointer("/author/role")
+                .and_then(Value::as_str)
+                .and_then(normalize_role);
+            let content = message
+                .pointer("/content/parts")
+                .or_else(|| message.get("content"))
+                .map(content_text)
+                .unwrap_or_default();
+
+            if let Some(role) = role {
+                if !content.trim().is_empty() {
+                    messages.push(Message::new(role, content));
+                }
+            }
+        }
+        node_id = node.get("parent").and_then(Value::as_str).map(String::from);
+    }
+    messages.reverse();
+
+    let mut session = Session::from_messages(messages);
+    if let Some(title) = value.get("title").and_then(Value::as_str) {
+        session.title = title.to_string();
+    }
+    Ok(session)
+}
+
+/// Extracts text from a string, an array of strings, or an array of
+/// `{"type": "text", "text": ...}` parts.
+fn content_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .filter_map(|part| match part {
+                Value::String(s) => Some(s.as_str()),
+                _ => part.get("text").and_then(Value::as_str),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn normalize_role(role: &str) -> Option<&'static str> {
+    match role.trim().to_lowercase().as_str() {
+        "user" | "you" | "human" | "me" => Some("user"),
+        "assistant" | "ai" | "model" | "bot" | "chatgpt" | "gpt" | "claude" => Some("assistant"),
+        "system" => Some("system"),
+        _ => None,
+    }
+}
+
+/// Parses a markdown transcript into messages.
+///
+/// A new message starts at a heading naming a role (`## Assistant`), a bold
+/// role prefix (`**User:** text`) or a plain role prefix (`AI: text`).
+#[must_use]
+pub fn parse_markdown(content: &str) -> Vec<Message> {
+    let mut messages: Vec<(String, String)> = Vec::new();
+    let mut current: Option<(String, Vec<&str>)> = None;
+
+    for line in content.lines() {
+        if let Some((role, rest)) = markdown_role_marker(line) {
+            if let Some((role, body)) = current.take() {
+                messages.push((role, body.join("\n")));
+            }
+            current = Some((role.to_string(), vec![rest]));
+        } else if let Some((_, body)) = current.as_mut() {
+            body.push(line);
+        }
+    }
+    if let Some((role, body)) = current {
+        messages.push((role, body.join("\n")));
+    }
+
+    messages
+        .into_iter()
+        .filter(|(_, body)| !body.trim().is_empty())
+        .map(|(role, body)| Message::new(role, body.trim()))
+        .collect()
+}
+
+fn markdown_role_marker(line: &str) -> Option<(&'static str, &str)> {
+    let trimmed = line.trim();
+
+    if let Some(heading) = trimmed.strip_prefix('#') {
+        let name = heading.trim_start_matches('#').trim().trim_end_matches(':');
+        return normalize_role(name).map(|role| (role, ""));
+    }
+
+    let (name, rest) = if let Some(bold) = trimmed.strip_prefix("**") {
+        let end = bold.find("**")?;
+        (&bold[..end], &bold[end + 2..])
+    } else {
+        let colon = trimmed.find(':')?;
+        (&trimmed[..colon], &trimmed[colon..])
+    };
+
+    let name = name.trim().trim_end_matches(':');
+    let rest = rest.trim_start_matches(':').trim_start();
+    normalize_role(name).map(|role| (role, rest))
+}
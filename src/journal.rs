@@ -0,0 +1,225 @@
+//! Journals responses as they stream, so a crash does not lose them.
+//!
+//! The periodic recovery file can be several seconds behind a streaming
+//! response. While a response streams, its prompt and then every chunk are
+//! appended to a journal next to the sessions directory, one JSON entry per
+//! line, as soon as they arrive. The journal is removed once the response
+//! is saved with its session; if chatti crashes first, the journal is
+//! replayed into the conversation offered for restoring on the next start.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+};
+use tracing::error;
+
+use crate::{
+    config::FSError,
+    message::Message,
+    session::{Recovery, Session},
+};
+
+/// An entry of the journal.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Entry {
+    /// The prompt being answered, always the first entry.
+    Prompt {
+        session_id: Option<String>,
+        message: Box<Message>,
+    },
+    /// A streamed chunk of the answer.
+    Chunk(String),
+}
+
+/// The journal of the response being streamed.
+#[derive(Debug, Default)]
+pub struct Journal {
+    path: PathBuf,
+    file: Option<File>,
+}
+
+impl Journal {
+    /// Returns the path of the journal.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FSError::HomeDirNotFound` if the home directory cannot be found.
+    pub fn path() -> Result<PathBuf, FSError> {
+        Ok(Session::sessions_dir()?.with_file_name("journal.jsonl"))
+    }
+
+    /// Starts the journal of a response, replacing any previous one.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Where the journal is written.
+    /// * `session_id` - The session the prompt belongs to, if it was saved.
+    /// * `prompt` - The prompt being answered.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the journal cannot be created.
+    pub fn begin(path: PathBuf, session_id: Option<String>, prompt: &Message) -> io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = File::create(&path)?;
+        write_entry(
+            &mut file,
+            &Entry::Prompt {
+                session_id,
+                message: Box::new(prompt.clone()),
+            },
+        )?;
+        Ok(Journal {
+            path,
+            file: Some(file),
+        })
+    }
+
+    /// Appends a streamed chunk. If it cannot be written, the error is
+    /// logged and journaling stops for this response.
+    pub fn append(&mut self, chunk: &str) {
+        let Some(file) = self.file.as_mut() else {
+            return;
+        };
+        if let Err(err) = write_entry(file, &Entry::Chunk(chunk.to_string())) {
+            error!(?err, "failed to journal the response");
+            self.file = None;
+        }
+    }
+
+    /// Removes the journal once the response is saved or discarded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the journal exists but cannot be removed.
+    pub fn finish(self) -> io::Result<()> {
+        if self.file.is_none() {
+            return Ok(());
+        }
+        match fs::remove_file(&self.path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Writes an entry as one line.
+fn write_entry(file: &mut File, entry: &Entry) -> io::Result<()> {
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    file.write_all(line.as_bytes())
+}
+
+/// A response that was streaming when chatti stopped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Partial {
+    /// The session the prompt belongs to, if it was saved.
+    pub session_id: Option<String>,
+    /// The prompt being answered.
+    pub prompt: Message,
+    /// The part of the answer that arrived.
+    pub response: String,
+}
+
+impl Partial {
+    /// Adds the partial answer to the conversation to restore, replacing an
+    /// older copy of it and adding its prompt if that is missing. The answer
+    /// is marked as stopped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::journal::Partial;
+    /// use chatti::message::Message;
+    /// use chatti::session::Recovery;
+    ///
+    /// let mut recovery = Recovery {
+    ///     messages: vec![Message::new("user", "Hi"), Message::new("assistant", "Hel")],
+    ///     ..Recovery::default()
+    /// };
+    /// let partial = Partial {
+    ///     session_id: None,
+    ///     prompt: Message::new("user", "Hi"),
+    ///     response: String::from("Hello the"),
+    /// };
+    /// partial.restore(&mut recovery);
+    /// assert_eq!(recovery.messages.len(), 2);
+    /// assert_eq!(recovery.messages[1].content, "Hello the");
+    /// assert!(recovery.messages[1].stopped);
+    /// ```
+    pub fn restore(self, recovery: &mut Recovery) {
+        let same = |a: &Message, b: &Message| a.role == b.role && a.content == b.content;
+        let messages = &mut recovery.messages;
+        let count = messages.len();
+        if count >= 2
+            && messages[count - 1].role == "assistant"
+            && same(&messages[count - 2], &self.prompt)
+        {
+            messages.pop();
+        }
+        if !messages.last().is_some_and(|last| same(last, &self.prompt)) {
+            messages.push(self.prompt);
+        }
+        if !self.response.is_empty() {
+            let mut answer = Message::new("assistant", self.response);
+            answer.stopped = true;
+            messages.push(answer);
+        }
+        if recovery.session_id.is_none() {
+            recovery.session_id = self.session_id;
+        }
+    }
+}
+
+/// Reads the journal left by a run that stopped while a response was
+/// streaming, if there is one. A last line cut short by the crash is
+/// ignored.
+///
+/// # Errors
+///
+/// Returns an `FSError` if the journal exists but cannot be read.
+pub fn replay(path: &Path) -> Result<Option<Partial>, FSError> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let mut entries = BufReader::new(file)
+        .lines()
+        .map_while(|line| serde_json::from_str::<Entry>(&line.ok()?).ok());
+    let Some(Entry::Prompt {
+        session_id,
+        message,
+    }) = entries.next()
+    else {
+        return Ok(None);
+    };
+    let response = entries
+        .filter_map(|entry| match entry {
+            Entry::Chunk(chunk) => Some(chunk),
+            Entry::Prompt { .. } => None,
+        })
+        .collect();
+    Ok(Some(Partial {
+        session_id,
+        prompt: *message,
+        response,
+    }))
+}
+
+/// Removes a journal after it was replayed or declined.
+///
+/// # Errors
+///
+/// Returns an `FSError` if the journal exists but cannot be removed.
+pub fn clear(path: &Path) -> Result<(), FSError> {
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
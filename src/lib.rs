@@ -1,4 +1,51 @@
+pub mod api;
+pub mod attachments;
+pub mod auth;
+pub mod bench;
+pub mod cli;
+pub mod clipboard;
+pub mod commands;
 pub mod config;
+pub mod continuation;
+#[cfg(unix)]
+pub mod control;
+pub mod dataset;
+pub mod diagrams;
+pub mod documents;
 pub mod error;
+pub mod git;
+pub mod history;
+pub mod hooks;
+pub mod images;
+pub mod import;
+pub mod journal;
+pub mod lint;
 pub mod logging;
+pub mod mentions;
+pub mod message;
+pub mod models;
+pub mod plugins;
+pub mod process;
+pub mod project;
+pub mod provider;
+pub mod ratelimit;
+pub mod redact;
+pub mod retention;
+pub mod retrieval;
+pub mod script;
+pub mod scripting;
+pub mod search;
+pub mod session;
+pub mod shell;
+pub mod speech;
+pub mod stats;
+pub mod store;
+pub mod stream;
+pub mod structured;
+pub mod tables;
+pub mod telemetry;
+pub mod templates;
+pub mod tokens;
+pub mod translate;
+pub mod transport;
 pub mod ui;
@@ -0,0 +1,111 @@
+//! Checks a prompt for likely mistakes before it is sent.
+//!
+//! The checks are cheap and only warn: a `{{placeholder}}` left from a
+//! template, an `@path` mention naming no file, so nothing would be
+//! attached, and what looks like an API key or other secret. The warnings
+//! are shown above the input, and pressing Enter again sends the prompt
+//! anyway. With `scan_prompts` set in the `[redaction]` section, prompts
+//! sent to another machine are also scanned, with their attachments, for
+//! the credentials the redaction patterns find.
+
+use std::{fmt, path::Path, sync::LazyLock};
+
+use regex::Regex;
+
+use crate::{mentions, redact::Redactor};
+
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\{\{\s*([^{}\s][^{}]*?)\s*\}\}").expect("valid placeholder pattern")
+});
+
+/// A likely mistake in a prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// A `{{placeholder}}` that was never filled in.
+    Placeholder(String),
+    /// An `@path` mention of a file that does not exist.
+    MissingFile(String),
+    /// Text that looks like an API key, token or password.
+    Secret,
+    /// A credential found by the scan of a prompt sent to another machine.
+    Credential {
+        /// The kind of credential, such as `AWS access key`.
+        kind: &'static str,
+        /// Where it was found: the prompt or its attachments.
+        place: &'static str,
+    },
+}
+
+impl Warning {
+    /// Returns `true` for a credential found by the scan of the prompt.
+    #[must_use]
+    pub fn is_credential(&self) -> bool {
+        matches!(self, Warning::Credential { .. })
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::Placeholder(name) => write!(f, "`{{{{{name}}}}}` was never filled in"),
+            Warning::MissingFile(path) => {
+                write!(f, "@{path} names no file, so nothing is attached for it")
+            }
+            Warning::Secret => write!(f, "the prompt seems to contain an API key or password"),
+            Warning::Credential { kind, place } => {
+                write!(
+                    f,
+                    "{kind} in {place}, which would be sent to another machine"
+                )
+            }
+        }
+    }
+}
+
+/// Checks a prompt for unfilled placeholders, mentions of missing files and
+/// secrets.
+///
+/// # Arguments
+///
+/// * `prompt` - The text of the message.
+/// * `dir` - The directory mentioned paths are resolved in.
+///
+/// # Returns
+///
+/// The warnings, in that order, or an empty list if the prompt looks fine.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::lint::{check, Warning};
+/// use std::path::Path;
+///
+/// let warnings = check("Summarise {{ topic }} using @missing.md", Path::new("."));
+/// assert_eq!(
+///     warnings,
+///     vec![
+///         Warning::Placeholder("topic".to_string()),
+///         Warning::MissingFile("missing.md".to_string()),
+///     ]
+/// );
+/// assert!(check("Summarise @Cargo.toml, @alice", Path::new(".")).is_empty());
+/// ```
+#[must_use]
+pub fn check(prompt: &str, dir: &Path) -> Vec<Warning> {
+    let mut warnings: Vec<Warning> = Vec::new();
+    for captures in PLACEHOLDER.captures_iter(prompt) {
+        let warning = Warning::Placeholder(captures[1].to_string());
+        if !warnings.contains(&warning) {
+            warnings.push(warning);
+        }
+    }
+    warnings.extend(
+        mentions::missing(prompt, dir)
+            .into_iter()
+            .map(Warning::MissingFile),
+    );
+    if Redactor::builtin().finds_secret(prompt) {
+        warnings.push(Warning::Secret);
+    }
+    warnings
+}
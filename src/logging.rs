@@ -1,8 +1,16 @@
-use std::path::PathBuf;
+use std::{io::Write, path::PathBuf, sync::Arc};
 use tracing_appender::rolling::{RollingFileAppender, Rotation};
-use tracing_subscriber::{fmt::format::FmtSpan, prelude::*, EnvFilter};
+use tracing_subscriber::{
+    filter::filter_fn,
+    fmt::{format::FmtSpan, MakeWriter},
+    prelude::*,
+    EnvFilter,
+};
 
-/// Sets up logging for the application.
+use crate::redact::Redactor;
+use crate::telemetry::{self, Telemetry};
+
+/// Sets up logging for the application with the built-in redaction patterns.
 ///
 /// This function initializes a daily rolling file appender for logging,
 /// configures the logging format, and sets up the tracing subscriber.
@@ -33,6 +41,38 @@ use tracing_subscriber::{fmt::format::FmtSpan, prelude::*, EnvFilter};
 /// # }
 /// ```
 pub fn setup() -> Result<tracing_appender::non_blocking::WorkerGuard, Box<dyn std::error::Error>> {
+    setup_with_redactor(Redactor::builtin(), false, None)
+}
+
+/// The target of the spans and events of chat requests: sending them,
+/// parsing their streamed responses and showing the answer.
+pub const API_TARGET: &str = "chatti::api";
+
+/// Sets up logging for the application, redacting secrets from every line
+/// written to the log file.
+///
+/// # Arguments
+///
+/// * `redactor` - The redactor applied to the log output.
+/// * `trace_requests` - Whether every span and event of chat requests is
+///   logged, down to each streamed chunk, whatever the level of the rest.
+/// * `telemetry` - The exporter the spans of chat requests are also sent
+///   with, if any. Events are not exported, since they are only redacted
+///   when written to the log file.
+///
+/// # Returns
+///
+/// Returns a `Result` containing the `WorkerGuard` for the non-blocking writer,
+/// or a boxed error if setup fails.
+///
+/// # Errors
+///
+/// Returns the same errors as [`setup`].
+pub fn setup_with_redactor(
+    redactor: Redactor,
+    trace_requests: bool,
+    telemetry: Option<&Telemetry>,
+) -> Result<tracing_appender::non_blocking::WorkerGuard, Box<dyn std::error::Error>> {
     let config_dir = dirs::home_dir()
         .ok_or("home directory not found")?
         .join(".config")
@@ -45,14 +85,26 @@ pub fn setup() -> Result<tracing_appender::non_blocking::WorkerGuard, Box<dyn st
     let file_appender = RollingFileAppender::new(Rotation::DAILY, log_dir, "chatti.log");
 
     let (non_blocking, guard) = tracing_appender::non_blocking(file_appender);
+    let mut filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    if trace_requests {
+        filter = filter.add_directive(format!("{API_TARGET}=trace").parse()?);
+    }
     tracing_subscriber::registry()
-        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with(filter)
         .with(
             tracing_subscriber::fmt::layer()
-                .with_writer(non_blocking)
+                .with_writer(RedactingMakeWriter {
+                    inner: non_blocking,
+                    redactor: Arc::new(redactor),
+                })
                 .with_ansi(false)
                 .with_span_events(FmtSpan::FULL),
         )
+        .with(telemetry.map(|telemetry| {
+            telemetry
+                .layer()
+                .with_filter(filter_fn(telemetry::is_exported))
+        }))
         .init();
     Ok(guard)
 }
@@ -79,3 +131,39 @@ pub fn get_log_file_path() -> PathBuf {
         .join("chatti")
         .join("logs")
 }
+
+/// A `MakeWriter` that redacts secrets before passing output to the inner writer.
+pub struct RedactingMakeWriter<M> {
+    inner: M,
+    redactor: Arc<Redactor>,
+}
+
+impl<'a, M: MakeWriter<'a>> MakeWriter<'a> for RedactingMakeWriter<M> {
+    type Writer = RedactingWriter<M::Writer>;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        RedactingWriter {
+            inner: self.inner.make_writer(),
+            redactor: Arc::clone(&self.redactor),
+        }
+    }
+}
+
+/// A writer that redacts secrets from each formatted log record.
+pub struct RedactingWriter<W> {
+    inner: W,
+    redactor: Arc<Redactor>,
+}
+
+impl<W: Write> Write for RedactingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let text = String::from_utf8_lossy(buf);
+        self.inner
+            .write_all(self.redactor.redact(&text).as_bytes())?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
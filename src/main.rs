@@ -1,12 +1,54 @@
-use chatti::{config, error, logging, ui};
+use chatti::{
+    api, attachments, auth, bench, cli, clipboard, commands, config, dataset, diagrams, error,
+    history, hooks, import, journal, logging, message, models, plugins, project, provider,
+    ratelimit, redact, retention, retrieval, script, scripting, search, session, speech, stats,
+    store, stream, structured, telemetry, templates, tokens, transport, ui,
+};
 
-use config::Config;
+use attachments::Batch;
+use bench::Summary;
+use cli::{InteractiveOptions, Mode};
+use commands::Command;
+use config::{Config, RedactionConfig};
 use error::{AppResult, Application};
-use futures_util::StreamExt;
-use serde_json::json;
-use tokio::sync::mpsc;
-use tokio::time::{sleep, Duration};
-use tracing::{error, instrument};
+use futures_util::future::{self, FutureExt};
+use history::InputHistory;
+use journal::Journal;
+use logging::API_TARGET;
+use message::{Generation, Message, Metadata};
+use models::ModelCommand;
+use plugins::Plugins;
+use project::Project;
+use ratelimit::RateLimiter;
+use redact::Redactor;
+use script::{OutputFormat, Script, ScriptError};
+use scripting::{Outcome, Scripts, Snapshot};
+use search::{Hit, Results};
+use session::{Recovery, Session, TranscriptFormat};
+use speech::Speaker;
+use std::fs::{self, File};
+use std::future::Future;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use structured::JsonOutput;
+use telemetry::Telemetry;
+use templates::TemplateError;
+use tokio::sync::{mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio::time::{sleep, Duration, Instant};
+use tracing::{debug, error, field, info, instrument, trace, Span};
+use transport::Transport;
+use ui::capabilities::Capabilities;
+use ui::context_view::Section;
+use ui::graphics::Graphics;
+use ui::params::{Params, Retry};
+use ui::scrollback::Scrollback;
+use ui::spinner::Spinner;
+use ui::state::{InputMode, CLEAR_UNDO_WINDOW};
+use ui::switcher::Switcher;
+use ui::theme::Theme;
 use ui::Action;
 use ui::Interface;
 
@@ -17,130 +59,2219 @@ use ui::Interface;
 #[tokio::main]
 async fn main() -> AppResult<()> {
     color_eyre::install()?;
-    let _guard = logging::setup()?;
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let trace_requests = cli::take_trace_requests(&mut args);
+    let mode = Mode::parse(&args).unwrap_or_else(|err| {
+        eprintln!("{err}");
+        std::process::exit(1);
+    });
+    if let Mode::Auth(args) = &mode {
+        match auth::run_command(args) {
+            Ok(message) => println!("{message}"),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    let mut config = Config::load()?;
+    let telemetry = Telemetry::start(&config.telemetry);
+    let _guard = logging::setup_with_redactor(
+        Redactor::new(&config.redaction)?,
+        trace_requests,
+        telemetry.as_ref().ok().and_then(Option::as_ref),
+    )?;
+    if let Err(err) = &telemetry {
+        error!(%err, "telemetry is not exported");
+    }
+    // Resolved once logging is set up, so a keyring failure is logged.
+    config.api_key = if config.local_only {
+        None
+    } else {
+        auth::resolve_key(&config.profile, config.api_key.as_deref())
+    };
+    let client = api::client(config.api_key.as_deref())?;
+    // Broken project settings are reported once the interface is up, and
+    // the session goes on without them.
+    let (project, project_error) = match Project::detect(Path::new(".")) {
+        Ok(project) => (project, None),
+        Err(err) => {
+            error!(?err, "failed to read the project settings");
+            (None, Some(err))
+        }
+    };
+    if let Some(project) = &project {
+        project.apply(&mut config);
+        session::use_project_dir(project.sessions_dir()?);
+    }
+    store::open(config.session_store)?;
+    sweep_old_sessions(&config);
+
+    let limiter = RateLimiter::new(config.rate_limit.for_profile(&config.profile));
+    match mode {
+        Mode::Script(options) => match run_script(&client, &limiter, &config, &options).await {
+            Ok(true) => return Ok(()),
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        },
+        Mode::Filter(template) => {
+            if let Err(err) = run_filter(&client, &limiter, &config, &template).await {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+        Mode::Bench(options) => match run_bench(&client, &limiter, &config, &options).await {
+            Ok(true) => return Ok(()),
+            Ok(false) => std::process::exit(1),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        },
+        Mode::Interactive(_) | Mode::Auth(_) => {}
+    }
+
+    let options = match mode {
+        Mode::Interactive(options) => options,
+        _ => InteractiveOptions::default(),
+    };
+    let mut chat_ui = Interface::new(!options.plain)?;
+    chat_ui.ui_state.context_budget = config.context_tokens;
+    chat_ui.ui_state.params = Params::from_config(&config);
+    chat_ui
+        .ui_state
+        .answer_language
+        .clone_from(&config.language);
+    chat_ui.ui_state.compare_models = config.compare.pair();
+    chat_ui.ui_state.sampling = config.attachments.sampling();
+    chat_ui.ui_state.dry_run = config.dry_run;
+    chat_ui.ui_state.scrollback = Scrollback::new(config.max_scrollback);
+    chat_ui.ui_state.json_output.clone_from(&config.json_output);
+    chat_ui
+        .ui_state
+        .shell_allowed
+        .clone_from(&config.shell.allowed);
+    chat_ui.ui_state.theme = Theme::from_config(&config.theme, &config.model);
+    chat_ui.ui_state.capabilities = Capabilities::detect(&config.terminal);
+    chat_ui.ui_state.graphics = Graphics::detect(config.terminal.images);
+    chat_ui.synchronized_output = config.terminal.synchronized_output;
+    chat_ui.frame_interval = Duration::from_secs(1) / config.terminal.max_fps.max(1);
+    chat_ui.ui_state.limiter = Some(limiter.clone());
+    chat_ui.ui_state.spinner =
+        Spinner::from_config(&config.spinner, chat_ui.ui_state.capabilities.unicode);
+    chat_ui.ui_state.show_progress = config.spinner.progress;
+    if config.redaction.scan_prompts && !api::is_local_endpoint(&config.api_endpoint) {
+        let scanning = RedactionConfig {
+            enabled: true,
+            ..config.redaction.clone()
+        };
+        chat_ui.ui_state.secret_scan =
+            Some(Redactor::new(&scanning).unwrap_or_else(|_| Redactor::builtin()));
+    }
+    if let Some(price) = config.cost.price_for(&config.model) {
+        chat_ui.ui_state.cost_confirm_threshold = config.cost.confirm_above;
+        if config.cost.show_preview || config.cost.confirm_above.is_some() {
+            chat_ui.ui_state.prompt_price = Some(price.input);
+        }
+    }
+    if let Some(command) = &config.speech.command {
+        chat_ui.ui_state.speaker = Some(Speaker::spawn(
+            command.clone(),
+            config.model.clone(),
+            config.speech.enabled,
+        ));
+    }
+    let endpoint_allowed = config.check_endpoint(&config.api_endpoint).is_ok();
+    if config.offline.queue && endpoint_allowed {
+        chat_ui.ui_state.queue_offline = true;
+        chat_ui.ui_state.connectivity = Some(api::spawn_health_monitor(
+            client.clone(),
+            config.api_endpoint.clone(),
+            Duration::from_secs(config.offline.retry_interval_secs.max(1)),
+        ));
+    }
+    if (config.startup.health_check || config.startup.warm_up) && endpoint_allowed {
+        chat_ui.ui_state.endpoint = Some(api::spawn_startup_check(
+            client.clone(),
+            config.api_endpoint.clone(),
+            config.model.clone(),
+            config.startup.warm_up && config.provider.can_warm_up(),
+        ));
+    }
+    let embeddings = retrieval::embeddings_endpoint(&config.retrieval, &config.api_endpoint);
+    let index = match config.check_endpoint(&embeddings) {
+        _ if config.retrieval.directory.is_none() => None,
+        Ok(()) => Some(retrieval::spawn_indexer(
+            client.clone(),
+            config.retrieval.clone(),
+            &config.api_endpoint,
+        )),
+        Err(err) => {
+            chat_ui.add_system_message(format!("Not indexing files for retrieval. {err}"));
+            None
+        }
+    };
+    load_history(&mut chat_ui, &config);
+    if let Some(project) = &project {
+        start_project(&mut chat_ui, project);
+    }
+    if let Some(err) = project_error {
+        chat_ui.add_system_message(format!(
+            "Not using the project settings in {}: {err}",
+            project::FILE_NAME
+        ));
+    }
+    match session::load_draft() {
+        Ok(Some(draft)) => {
+            chat_ui.ui_state.input = draft;
+            chat_ui.add_system_message(String::from(
+                "Restored your unsent draft; press e to continue editing",
+            ));
+        }
+        Ok(None) => {}
+        Err(err) => error!(?err, "failed to load the saved draft"),
+    }
+    #[cfg(unix)]
+    let _control = open_control_socket(&mut chat_ui, &config).await;
+    let mut plugins = load_plugins(&mut chat_ui, &config);
+    let mut scripts = load_scripts(&mut chat_ui, &config);
+    let mut session = Session::new();
+    restore_recovery(&mut chat_ui, &mut session)?;
+
+    while let Some(message) = chat_ui.run()? {
+        chat_ui.ui_state.params.apply(&mut config);
+        config.json_output.clone_from(&chat_ui.ui_state.json_output);
+        if plugins
+            .as_ref()
+            .is_some_and(|plugins| plugins.invoke(&message))
+        {
+            continue;
+        }
+        if let Some(outcome) = scripts
+            .as_ref()
+            .and_then(|scripts| scripts.command(&snapshot(&chat_ui, &config), &message))
+        {
+            apply_scripts(&mut chat_ui, outcome);
+            continue;
+        }
+        if let Some(command) = Command::parse(&message) {
+            if let Command::Plugins(reload) = command {
+                manage_plugins(&mut chat_ui, &config, plugins.as_mut(), reload);
+                continue;
+            }
+            if let Command::Scripts(reload) = command {
+                manage_scripts(&mut chat_ui, &config, &mut scripts, reload);
+                continue;
+            }
+            if let Command::Models(command) = command {
+                manage_models(&mut chat_ui, &client, &config, command).await?;
+                continue;
+            }
+            if let Command::Retry(model) = command {
+                let retry = chat_ui.ui_state.retry.take();
+                match retry.or_else(|| chat_ui.ui_state.retry_latest(model)) {
+                    Some(retry) => {
+                        retry_answer(&mut chat_ui, &client, &limiter, &config, retry).await?
+                    }
+                    None => chat_ui.add_system_message(String::from("There is no answer to retry")),
+                }
+                save_session(&mut chat_ui, &mut session);
+                continue;
+            }
+            if command == Command::Continue {
+                continue_answer(&mut chat_ui, &client, &limiter, &config).await?;
+                save_session(&mut chat_ui, &mut session);
+                continue;
+            }
+            if command == Command::Diagrams {
+                render_diagrams(&mut chat_ui, &config).await?;
+                save_session(&mut chat_ui, &mut session);
+                continue;
+            }
+            if let Command::Attach(patterns) = command {
+                attach_files(&mut chat_ui, &config, patterns).await?;
+                continue;
+            }
+            if let Command::Search(query) = command {
+                search_sessions(&mut chat_ui, &mut session, query).await?;
+                continue;
+            }
+            if let Command::New(Some(name)) = command {
+                start_template(&mut chat_ui, &mut session, &config, name).await?;
+                continue;
+            }
+            if let Command::Context(prompt) = command {
+                show_context(&mut chat_ui, &client, &config, index.as_ref(), prompt).await?;
+                continue;
+            }
+            if let Command::Translate(language) = command {
+                translate_answer(&mut chat_ui, &client, &limiter, &config, &language).await?;
+                save_session(&mut chat_ui, &mut session);
+                continue;
+            }
+            if !handle_command(&mut chat_ui, &mut session, &mut config, command)? {
+                continue;
+            }
+        }
+
+        // A dry run only shows the request, so nothing that reacts to a
+        // prompt being sent runs for it.
+        if chat_ui.ui_state.dry_run {
+            let history = chat_ui.request_messages();
+            chat_ui
+                .ui_state
+                .finish_dry_run(provider::dry_run(&config, &history));
+            continue;
+        }
+
+        let mut message = message;
+        if let Some(scripts) = &scripts {
+            let mut outcome = scripts.on_prompt(&snapshot(&chat_ui, &config), &message);
+            if let Some(prompt) = outcome.text.take() {
+                chat_ui.ui_state.rewrite_latest("user", prompt.clone());
+                message = prompt;
+            }
+            apply_scripts(&mut chat_ui, outcome);
+        }
+        if let Some(plugins) = &plugins {
+            plugins.notify(plugins::Event::MessageSent {
+                content: message.clone(),
+                model: config.model.clone(),
+            });
+        }
+
+        if chat_ui.ui_state.queue_offline && !chat_ui.ui_state.is_online() {
+            chat_ui.mark_pending();
+            continue;
+        }
+
+        // Retrieval runs in the request task, so it can be cancelled, and
+        // once for both requests of a comparison.
+        let retrieved = add_retrieved_context(
+            client.clone(),
+            config.clone(),
+            index.as_ref().and_then(|index| index.borrow().clone()),
+            chat_ui.request_messages(),
+        )
+        .boxed()
+        .shared();
+        let history = || retrieved.clone().map(|(history, _)| history);
+
+        let mut journal = None;
+        if let Some(models) = chat_ui.ui_state.start_comparison() {
+            let [(left, mut left_rx), (right, mut right_rx)] = models.map(|model| {
+                let config = Config {
+                    model,
+                    ..config.clone()
+                };
+                spawn_request_after(&client, &limiter, config, history())
+            });
+            process_comparison(&mut chat_ui, &mut left_rx, &mut right_rx).await?;
+            left.abort();
+            right.abort();
+        } else {
+            let (request, mut rx) =
+                spawn_request_after(&client, &limiter, config.clone(), history());
+            let journal = journal.insert(begin_journal(&chat_ui));
+            chat_ui.start_new_response();
+            let completed = process_response(&mut chat_ui, &mut rx, journal).await?;
+            if let Some(mut response) = completed {
+                // The stream has ended, so the request is done as well.
+                let metadata = request.await.unwrap_or_default();
+                chat_ui
+                    .ui_state
+                    .annotate_generation(Generation::from_config(&config));
+                chat_ui.ui_state.annotate_metadata(metadata);
+                // The request task waited for the retrieval, so it is done.
+                let (_, sources) = retrieved.await;
+                if !sources.is_empty() {
+                    chat_ui.ui_state.annotate_sources(sources);
+                }
+                chat_ui.ui_state.check_json_answer();
+                if let Some(scripts) = &scripts {
+                    let mut outcome = scripts.on_response(&snapshot(&chat_ui, &config), &response);
+                    if let Some(answer) = outcome.text.take() {
+                        chat_ui.ui_state.rewrite_latest("assistant", answer.clone());
+                        response = answer;
+                    }
+                    apply_scripts(&mut chat_ui, outcome);
+                }
+                if let Some(plugins) = &plugins {
+                    plugins.notify(plugins::Event::MessageReceived {
+                        content: response.clone(),
+                        model: config.model.clone(),
+                    });
+                }
+                hooks::spawn(&config.hooks.on_response, response, config.model.clone());
+            } else {
+                request.abort();
+            }
+        }
+
+        save_session(&mut chat_ui, &mut session);
+        if let Err(err) = journal.map_or(Ok(()), Journal::finish) {
+            error!(?err, "failed to remove the response journal");
+        }
+
+        if chat_ui.should_quit() {
+            break;
+        }
+    }
+
+    save_session(&mut chat_ui, &mut session);
+    if let Err(err) = Recovery::clear() {
+        error!(?err, "failed to remove the recovery file");
+    }
+
+    // Leave the alternate screen first, so the transcript stays visible.
+    drop(chat_ui);
+    if let Some(format) = options.print_on_exit.or(config.print_on_exit) {
+        print_transcript(&session, &config, format);
+    }
+
+    Ok(())
+}
+
+/// Prints the conversation to standard output, with the same redaction as
+/// `/export`.
+fn print_transcript(session: &Session, config: &Config, format: TranscriptFormat) {
+    if session.messages.is_empty() {
+        return;
+    }
+    let redactor = Redactor::new(&config.redaction).unwrap_or_else(|_| Redactor::builtin());
+    print!("{}", redactor.redact(&session.transcript(format)));
+}
+
+/// Sends the prompts of a script in turn, as one conversation, and writes
+/// the answers in the requested format. The conversation is saved as a
+/// session.
+///
+/// # Returns
+///
+/// `true` if every prompt was answered.
+///
+/// # Errors
+///
+/// Returns a `ScriptError` if the script cannot be loaded or the answers
+/// cannot be written.
+async fn run_script(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    config: &Config,
+    options: &script::Options,
+) -> Result<bool, ScriptError> {
+    let script = Script::load(&options.script)?;
+    let mut output: Box<dyn Write> = match &options.output {
+        Some(path) => Box::new(File::create(path)?),
+        None => Box::new(io::stdout()),
+    };
+
+    let mut history = Vec::new();
+    let mut answered = true;
+    let total = script.steps.len();
+    for (index, step) in script.steps.iter().enumerate() {
+        let config = Config {
+            model: script.model_for(step, &config.model).to_string(),
+            ..config.clone()
+        };
+        eprintln!("[{}/{total}] {}", index + 1, config.model);
+
+        let request = script.request(step, &history);
+        let prompt_tokens = request
+            .iter()
+            .map(|message| tokens::estimate_tokens(&message.content))
+            .sum();
+        let started = Instant::now();
+        let (response, first_token) =
+            collect_response(client, limiter, config.clone(), request).await;
+        let answer = script::Answer {
+            prompt: step.prompt.clone(),
+            model: config.model,
+            response,
+            prompt_tokens,
+            first_token: first_token.map(|at| at - started),
+            elapsed: started.elapsed(),
+        };
+        match &answer.response {
+            Ok(response) => {
+                history.push(Message::new("user", step.prompt.clone()));
+                history.push(Message::new("assistant", response.clone()));
+            }
+            Err(err) => {
+                error!(err, step = index + 1, "script step failed");
+                answered = false;
+            }
+        }
+        match options.format {
+            OutputFormat::Markdown => writeln!(output, "{}", answer.to_markdown(index + 1))?,
+            OutputFormat::Json => writeln!(output, "{}", answer.to_json_line(index + 1))?,
+        }
+        output.flush()?;
+    }
+
+    if !history.is_empty() {
+        let mut session = Session::from_messages(history);
+        session.system_prompt.clone_from(&script.system);
+        session.title = session.derive_title();
+        if let Err(err) = session.save() {
+            error!(?err, "failed to save the script session");
+        }
+    }
+    Ok(answered)
+}
+
+/// Sends every prompt of a benchmark to every model, prints the summary
+/// table and writes the markdown report.
+///
+/// # Returns
+///
+/// `true` if every prompt was answered by every model.
+///
+/// # Errors
+///
+/// Returns a `ScriptError` if the prompts cannot be loaded or the report
+/// cannot be written.
+async fn run_bench(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    config: &Config,
+    options: &bench::Options,
+) -> Result<bool, ScriptError> {
+    let script = Script::load(&options.prompts)?;
+    let total = script.steps.len() * options.models.len();
+    let mut answers = Vec::new();
+    for model in &options.models {
+        let config = Config {
+            model: model.clone(),
+            ..config.clone()
+        };
+        for step in &script.steps {
+            eprintln!("[{}/{total}] {model}", answers.len() + 1);
+            let request = script.request(step, &[]);
+            let prompt_tokens = request
+                .iter()
+                .map(|message| tokens::estimate_tokens(&message.content))
+                .sum();
+            let started = Instant::now();
+            let (response, first_token) =
+                collect_response(client, limiter, config.clone(), request).await;
+            if let Err(err) = &response {
+                error!(err, model, "benchmark prompt failed");
+            }
+            answers.push(script::Answer {
+                prompt: step.prompt.clone(),
+                model: model.clone(),
+                response,
+                prompt_tokens,
+                first_token: first_token.map(|at| at - started),
+                elapsed: started.elapsed(),
+            });
+        }
+    }
+
+    let summaries: Vec<Summary> = options
+        .models
+        .iter()
+        .map(|model| Summary::of(model, &answers))
+        .collect();
+    print!("{}", bench::table(&summaries));
+    let prompts: Vec<String> = script
+        .steps
+        .iter()
+        .map(|step| step.prompt.clone())
+        .collect();
+    fs::write(
+        &options.report,
+        bench::report(&prompts, &summaries, &answers),
+    )?;
+    eprintln!("Report written to {}", options.report.display());
+    Ok(answers.iter().all(|answer| answer.response.is_ok()))
+}
+
+/// Applies a template to standard input and prints only the answer, so
+/// chatti can be used as an editor filter.
+///
+/// If the request fails, the input is printed back unchanged, so an editor
+/// replacing the selection with the output does not lose it.
+///
+/// # Errors
+///
+/// Returns a description of the problem if the template cannot be started
+/// or the request fails.
+async fn run_filter(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    config: &Config,
+    name: &str,
+) -> Result<(), String> {
+    let mut input = String::new();
+    io::stdin()
+        .read_to_string(&mut input)
+        .map_err(|err| format!("could not read standard input: {err}"))?;
+
+    let started = config
+        .templates
+        .get(name)
+        .ok_or_else(|| TemplateError::Unknown(name.to_string()))
+        .and_then(|template| templates::start(template, Path::new(".")));
+    let response = match started {
+        Ok(started) => {
+            let mut config = config.clone();
+            if let Some(schema) = started.schema.clone() {
+                config.json_output = Some(JsonOutput {
+                    schema: Some(schema),
+                });
+            }
+            let request = templates::apply_to(started, &input);
+            collect_response(client, limiter, config, request).await.0
+        }
+        Err(err) => Err(err.to_string()),
+    };
+
+    let mut stdout = io::stdout().lock();
+    let written = match &response {
+        Ok(answer) => write_filtered(&mut stdout, answer, &input),
+        Err(_) => stdout.write_all(input.as_bytes()),
+    };
+    written.map_err(|err| format!("could not write the answer: {err}"))?;
+    response.map(|_| ())
+}
+
+/// Writes a filter answer, ending it with a newline if the input ended with
+/// one.
+fn write_filtered(out: &mut impl Write, answer: &str, input: &str) -> io::Result<()> {
+    let answer = answer.trim_end_matches('\n');
+    if input.ends_with('\n') {
+        writeln!(out, "{answer}")
+    } else {
+        write!(out, "{answer}")
+    }
+}
+
+/// Sends the conversation and waits for the whole response.
+///
+/// # Returns
+///
+/// The response, or the error message if the request failed, and when the
+/// first part of the response arrived.
+async fn collect_response(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    config: Config,
+    history: Vec<Message>,
+) -> (Result<String, String>, Option<Instant>) {
+    let (_request, mut rx) = spawn_request(client, limiter, config, history);
+    let mut response = String::new();
+    let mut first_token = None;
+    while let Some(chunk) = rx.recv().await {
+        first_token.get_or_insert_with(Instant::now);
+        match chunk {
+            Ok(chunk) => response.push_str(&chunk),
+            Err(err) => return (Err(err.to_string()), first_token),
+        }
+    }
+    (Ok(response), first_token)
+}
+
+/// Opens the control socket if it is enabled, so other programs can send
+/// commands to this instance.
+///
+/// # Returns
+///
+/// The socket, which is removed when it is dropped.
+#[cfg(unix)]
+async fn open_control_socket(
+    chat_ui: &mut Interface,
+    config: &Config,
+) -> Option<chatti::control::ControlSocket> {
+    if !config.control.enabled {
+        return None;
+    }
+    let path = config
+        .control
+        .socket
+        .as_deref()
+        .map(|path| commands::expand_home(&path.to_string_lossy()))
+        .unwrap_or_else(chatti::control::default_socket_path);
+    match chatti::control::listen(&path).await {
+        Ok((socket, rx)) => {
+            info!(path = %socket.path().display(), "control socket opened");
+            chat_ui.ui_state.control = Some(rx);
+            Some(socket)
+        }
+        Err(err) => {
+            error!(?err, path = %path.display(), "failed to open the control socket");
+            chat_ui.add_system_message(format!(
+                "Could not open the control socket at {}: {err}",
+                path.display()
+            ));
+            None
+        }
+    }
+}
+
+/// Returns the directory plugins are loaded from.
+fn plugin_directory(config: &Config) -> PathBuf {
+    config
+        .plugins
+        .directory
+        .as_deref()
+        .map_or_else(plugins::default_directory, |dir| {
+            commands::expand_home(&dir.to_string_lossy())
+        })
+}
+
+/// Loads the plugins, unless they are disabled, showing those that failed
+/// to load.
+fn load_plugins(chat_ui: &mut Interface, config: &Config) -> Option<Plugins> {
+    if !config.plugins.enabled {
+        return None;
+    }
+    let (mut plugins, rx) = Plugins::new(Duration::from_secs(config.plugins.timeout_secs.max(1)));
+    for err in plugins.load(&plugin_directory(config)) {
+        error!(%err, "failed to load a plugin");
+        chat_ui.add_system_message(err.to_string());
+    }
+    chat_ui.ui_state.plugin_replies = Some(rx);
+    Some(plugins)
+}
+
+/// Lists the loaded plugins and their commands, or loads them again.
+fn manage_plugins(
+    chat_ui: &mut Interface,
+    config: &Config,
+    plugins: Option<&mut Plugins>,
+    reload: bool,
+) {
+    let Some(plugins) = plugins else {
+        chat_ui.add_system_message(String::from(
+            "Plugins are disabled; set `enabled = true` in the `[plugins]` section to load them",
+        ));
+        return;
+    };
+    let dir = plugin_directory(config);
+    if reload {
+        for err in plugins.load(&dir) {
+            error!(%err, "failed to load a plugin");
+            chat_ui.add_system_message(err.to_string());
+        }
+    }
+    chat_ui.add_system_message(plugins.describe(&dir));
+}
+
+/// Returns the directory scripts are loaded from.
+fn script_directory(config: &Config) -> PathBuf {
+    config
+        .scripting
+        .directory
+        .as_deref()
+        .map_or_else(scripting::default_directory, |dir| {
+            commands::expand_home(&dir.to_string_lossy())
+        })
+}
+
+/// Loads the scripts, unless they are disabled, showing those that failed
+/// to load.
+fn load_scripts(chat_ui: &mut Interface, config: &Config) -> Option<Scripts> {
+    if !config.scripting.enabled {
+        return None;
+    }
+    let (scripts, errors) = Scripts::load(&script_directory(config));
+    for err in errors {
+        error!(%err, "failed to load a script");
+        chat_ui.add_system_message(err.to_string());
+    }
+    Some(scripts)
+}
+
+/// Lists the loaded scripts and their commands, or loads them again.
+fn manage_scripts(
+    chat_ui: &mut Interface,
+    config: &Config,
+    scripts: &mut Option<Scripts>,
+    reload: bool,
+) {
+    if !config.scripting.enabled {
+        chat_ui.add_system_message(String::from(
+            "Scripts are disabled; set `enabled = true` in the `[scripting]` section to load them",
+        ));
+        return;
+    }
+    if reload {
+        *scripts = load_scripts(chat_ui, config);
+    }
+    if let Some(scripts) = scripts {
+        chat_ui.add_system_message(scripts.describe(&script_directory(config)));
+    }
+}
+
+/// Returns what scripts can read of the chat.
+fn snapshot(chat_ui: &Interface, config: &Config) -> Snapshot {
+    Snapshot {
+        model: config.model.clone(),
+        messages: chat_ui.conversation(),
+        input: chat_ui.ui_state.input.clone(),
+    }
+}
+
+/// Shows the notices and failures of scripts, and carries out what they
+/// asked for.
+fn apply_scripts(chat_ui: &mut Interface, outcome: Outcome) {
+    for err in outcome.errors {
+        error!(%err, "script failed");
+        chat_ui.add_system_message(err.to_string());
+    }
+    let effects = outcome.effects;
+    for notice in effects.notices {
+        chat_ui.add_system_message(notice);
+    }
+    if let Some(input) = effects.input {
+        chat_ui.ui_state.set_input(input);
+    }
+    if let Some(prompt) = effects.prompt {
+        chat_ui.ui_state.interjection = Some(prompt);
+    }
+}
+
+/// Starts the conversation with the project's system prompt and attached
+/// files, which later new conversations start with too.
+fn start_project(chat_ui: &mut Interface, project: &Project) {
+    match project.start() {
+        Ok(started) => {
+            chat_ui
+                .ui_state
+                .default_system_prompt
+                .clone_from(&started.system_prompt);
+            chat_ui.ui_state.system_prompt = started.system_prompt;
+            chat_ui.add_system_message(format!(
+                "Project \"{}\": using {} ({} file(s) attached)",
+                project.name(),
+                project::FILE_NAME,
+                started.files
+            ));
+        }
+        Err(err) => {
+            error!(?err, root = %project.root.display(), "failed to start the project");
+            chat_ui.add_system_message(format!(
+                "Project \"{}\": could not attach files: {err}",
+                project.name()
+            ));
+        }
+    }
+}
+
+/// Loads the prompts sent in earlier runs for recall, unless the history is
+/// not saved.
+fn load_history(chat_ui: &mut Interface, config: &Config) {
+    let max_entries = config.history.max_entries;
+    chat_ui.ui_state.history = InputHistory::new(max_entries);
+    if !config.history.save {
+        return;
+    }
+    let loaded = history::path().and_then(|path| {
+        chat_ui.ui_state.history = InputHistory::load(&path, max_entries)?;
+        chat_ui.ui_state.history_file = Some(path);
+        Ok(())
+    });
+    if let Err(err) = loaded {
+        error!(?err, "failed to load the input history");
+    }
+}
+
+/// Starts the journal of the response to the latest prompt.
+fn begin_journal(chat_ui: &Interface) -> Journal {
+    let Some(prompt) = chat_ui.conversation().pop() else {
+        return Journal::default();
+    };
+    Journal::path()
+        .and_then(|path| {
+            Ok(Journal::begin(
+                path,
+                chat_ui.ui_state.session_id.clone(),
+                &prompt,
+            )?)
+        })
+        .unwrap_or_else(|err| {
+            error!(?err, "failed to start the response journal");
+            Journal::default()
+        })
+}
+
+/// Offers to restore the conversation and draft left by a run that did not
+/// exit cleanly, including a response that was still streaming.
+fn restore_recovery(chat_ui: &mut Interface, session: &mut Session) -> AppResult<()> {
+    let recovery = Recovery::load().unwrap_or_else(|err| {
+        error!(?err, "failed to load the recovery file");
+        None
+    });
+    let journal_path = Journal::path().ok();
+    let partial = journal_path.as_deref().and_then(|path| {
+        journal::replay(path).unwrap_or_else(|err| {
+            error!(?err, "failed to replay the response journal");
+            None
+        })
+    });
+    let mut recovery = recovery.unwrap_or_default();
+    let interrupted = partial.is_some();
+    if let Some(partial) = partial {
+        partial.restore(&mut recovery);
+    }
+    if recovery.is_empty() {
+        return Ok(());
+    }
+
+    let restore = chat_ui.confirm(format!(
+        "Chatti did not exit cleanly last time{}. Restore the unsent draft and the \
+         conversation ({} message(s))?",
+        if interrupted {
+            ", while an answer was streaming"
+        } else {
+            ""
+        },
+        recovery.messages.len()
+    ))?;
+    if restore {
+        *session = recovery
+            .session_id
+            .as_deref()
+            .and_then(|id| Session::load(id).ok())
+            .unwrap_or_else(|| Session::from_messages(recovery.messages.clone()));
+        chat_ui.ui_state.session_id = recovery.session_id;
+        chat_ui.load_messages(recovery.messages);
+        chat_ui.ui_state.system_prompt = recovery.system_prompt;
+        if !recovery.draft.trim().is_empty() {
+            chat_ui.ui_state.input = recovery.draft;
+        }
+        chat_ui.add_system_message(String::from("Restored the previous conversation"));
+    }
+    if let Err(err) = Recovery::clear() {
+        error!(?err, "failed to remove the recovery file");
+    }
+    if let Some(path) = journal_path {
+        if let Err(err) = journal::clear(&path) {
+            error!(?err, "failed to remove the response journal");
+        }
+    }
+    Ok(())
+}
+
+/// Prepends the excerpts of the retrieval index most relevant to the last
+/// message, if the index is ready.
+///
+/// # Returns
+///
+/// The conversation with the excerpts added, and the files and lines of the
+/// excerpts.
+async fn add_retrieved_context(
+    client: reqwest::Client,
+    config: Config,
+    index: Option<Arc<retrieval::Index>>,
+    mut history: Vec<Message>,
+) -> (Vec<Message>, Vec<String>) {
+    let Some(index) = index else {
+        return (history, Vec::new());
+    };
+    let Some(last) = history.last_mut().filter(|m| m.role == "user") else {
+        return (history, Vec::new());
+    };
+    let chunks = retrieve_chunks(&client, &config, &index, &last.content).await;
+    last.content = retrieval::with_context(&chunks, &last.content);
+    let sources = chunks.iter().map(retrieval::Chunk::label).collect();
+    (history, sources)
+}
+
+/// Retrieves the excerpts of the index relevant to a prompt, or none if
+/// retrieval fails.
+async fn retrieve_chunks(
+    client: &reqwest::Client,
+    config: &Config,
+    index: &retrieval::Index,
+    prompt: &str,
+) -> Vec<retrieval::Chunk> {
+    let endpoint = retrieval::embeddings_endpoint(&config.retrieval, &config.api_endpoint);
+    match retrieval::retrieve(client, &endpoint, index, &config.retrieval, prompt).await {
+        Ok(chunks) => chunks,
+        Err(err) => {
+            error!(?err, "retrieval failed; sending without context");
+            Vec::new()
+        }
+    }
+}
+
+/// Opens the context viewer on the request that sending `prompt`, or the
+/// draft if it is empty, would make, with the excerpts the index would add
+/// to it.
+async fn show_context(
+    chat_ui: &mut Interface,
+    client: &reqwest::Client,
+    config: &Config,
+    index: Option<&watch::Receiver<Option<Arc<retrieval::Index>>>>,
+    prompt: String,
+) -> AppResult<()> {
+    let prompt = if prompt.is_empty() {
+        chat_ui.ui_state.input.clone()
+    } else {
+        prompt
+    };
+    let mut view = chat_ui.ui_state.next_request(&prompt);
+    let index = index.and_then(|index| index.borrow().clone());
+    if let Some(index) = index.filter(|_| !prompt.trim().is_empty()) {
+        let (client, config, query) = (client.clone(), config.clone(), prompt.clone());
+        let retrieval = async move { retrieve_chunks(&client, &config, &index, &query).await };
+        let label = String::from("Retrieving excerpts for the context");
+        let Some(chunks) = run_async_as_task(chat_ui, label, retrieval).await? else {
+            return Ok(());
+        };
+        if !chunks.is_empty() {
+            let excerpts: Vec<String> = chunks
+                .iter()
+                .map(|chunk| format!("{}\n```\n{}\n```", chunk.label(), chunk.text))
+                .collect();
+            view.insert_before_message(Section::new(
+                format!("Retrieved excerpts, {} chunk(s)", chunks.len()),
+                excerpts.join("\n\n"),
+            ));
+        }
+    }
+    chat_ui.ui_state.open_context(view);
+    Ok(())
+}
+
+/// Sends the conversation to the API in the background, once the rate
+/// limiter allows it.
+///
+/// # Returns
+///
+/// The handle of the request task, which ends with what the server reported
+/// about the response, and the receiver of the streamed response.
+fn spawn_request(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    config: Config,
+    history: Vec<Message>,
+) -> (
+    JoinHandle<Metadata>,
+    mpsc::Receiver<Result<String, Application>>,
+) {
+    spawn_request_after(client, limiter, config, future::ready(history))
+}
+
+/// Sends the conversation to the API in the background like
+/// [`spawn_request`], once `history` has produced it.
+fn spawn_request_after(
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    config: Config,
+    history: impl Future<Output = Vec<Message>> + Send + 'static,
+) -> (
+    JoinHandle<Metadata>,
+    mpsc::Receiver<Result<String, Application>>,
+) {
+    let (tx, rx) = mpsc::channel(100);
+    let client = client.clone();
+    let limiter = limiter.clone();
+    let request = tokio::spawn(async move {
+        let history = history.await;
+        let _permit = limiter.acquire().await;
+        process_message(&client, &config, &history, tx)
+            .await
+            .unwrap_or_else(|err| {
+                error!(?err, "error occurred in process_message");
+                Metadata::default()
+            })
+    });
+    (request, rx)
+}
+
+/// Saves the conversation shown in the UI to the current session, after
+/// paging out the messages beyond the scrollback limit.
+fn save_session(chat_ui: &mut Interface, session: &mut Session) {
+    chat_ui.ui_state.trim_scrollback();
+    let messages = chat_ui.conversation();
+    if messages.is_empty() {
+        return;
+    }
+    let last_read = Some(chat_ui.ui_state.last_read.min(messages.len()));
+    if messages == session.messages {
+        // Reading is no modification, so the time it was modified stays.
+        if last_read != session.last_read {
+            session.last_read = last_read;
+            if let Err(err) = store::with_store(|store| store.save(session)) {
+                error!(?err, "failed to save how much of the session was read");
+            }
+        }
+        return;
+    }
+    session.last_read = last_read;
+    session.messages = messages;
+    session
+        .system_prompt
+        .clone_from(&chat_ui.ui_state.system_prompt);
+    if session.title == "New chat" {
+        session.title = session.derive_title();
+    }
+    match session.save() {
+        Ok(()) => chat_ui.ui_state.session_id = Some(session.id.clone()),
+        Err(err) => error!(?err, "failed to save session"),
+    }
+}
+
+/// Executes a slash command entered by the user.
+///
+/// # Arguments
+///
+/// * `chat_ui` - A mutable reference to the `Interface` instance.
+/// * `session` - The session currently shown in the UI.
+/// * `config` - The application configuration.
+/// * `command` - The parsed command.
+///
+/// # Returns
+///
+/// `true` if the command submitted a prompt that should be sent.
+///
+/// # Errors
+///
+/// Returns an error if a dialog the command shows cannot be drawn.
+fn handle_command(
+    chat_ui: &mut Interface,
+    session: &mut Session,
+    config: &mut Config,
+    command: Command,
+) -> AppResult<bool> {
+    match command {
+        Command::Import(path) => match import::import_file(&path) {
+            Ok(mut sessions) => {
+                for imported in &mut sessions {
+                    if let Err(err) = imported.save() {
+                        error!(?err, "failed to save imported session");
+                    }
+                }
+                let count = sessions.len();
+                if let Some(last) = sessions.pop() {
+                    chat_ui.load_messages(last.messages.clone());
+                    chat_ui
+                        .ui_state
+                        .system_prompt
+                        .clone_from(&last.system_prompt);
+                    chat_ui.add_system_message(format!(
+                        "Imported {count} session(s) from {}; continuing \"{}\"",
+                        path.display(),
+                        last.title
+                    ));
+                    chat_ui.ui_state.session_id = Some(last.id.clone());
+                    *session = last;
+                }
+            }
+            Err(err) => {
+                error!(?err, path = %path.display(), "import failed");
+                chat_ui.add_system_message(format!("Import of {} failed: {err}", path.display()));
+            }
+        },
+        Command::Compare => {
+            let state = &mut chat_ui.ui_state;
+            match state.compare_models.clone() {
+                Some([left, right]) => {
+                    state.compare_mode = !state.compare_mode;
+                    let notice = if state.compare_mode {
+                        format!("Compare mode on: prompts are sent to {left} and {right}")
+                    } else {
+                        String::from("Compare mode off")
+                    };
+                    chat_ui.add_system_message(notice);
+                }
+                None => chat_ui.add_system_message(String::from(
+                    "Configure two models in the [compare] section to use /compare",
+                )),
+            }
+        }
+        Command::Clear(all) => {
+            if chat_ui.conversation().is_empty() {
+                chat_ui.add_system_message(String::from("There is nothing to clear"));
+                return Ok(false);
+            }
+            if all
+                && !chat_ui.confirm(String::from(
+                    "Clear the conversation, reset the system prompt and delete the saved session?",
+                ))?
+            {
+                return Ok(false);
+            }
+            save_session(chat_ui, session);
+            let mut cleared = std::mem::replace(session, Session::new());
+            cleared
+                .system_prompt
+                .clone_from(&chat_ui.ui_state.system_prompt);
+            if all {
+                if let Err(err) = store::with_store(|store| store.delete(&cleared.id)) {
+                    error!(?err, id = %cleared.id, "failed to delete the cleared session");
+                    chat_ui.add_system_message(format!("Deleting the saved session failed: {err}"));
+                }
+                chat_ui.ui_state.system_prompt = chat_ui.ui_state.default_system_prompt.clone();
+            }
+            chat_ui.ui_state.clear_conversation(cleared);
+            chat_ui.add_system_message(format!(
+                "Cleared the conversation; press u or run /clear undo within {}s to bring it back",
+                CLEAR_UNDO_WINDOW.as_secs()
+            ));
+        }
+        Command::UndoClear => match chat_ui.ui_state.take_cleared() {
+            Some(mut cleared) => {
+                save_session(chat_ui, session);
+                chat_ui.ui_state.session_id = Some(cleared.id.clone());
+                chat_ui.load_messages(cleared.messages.clone());
+                chat_ui
+                    .ui_state
+                    .system_prompt
+                    .clone_from(&cleared.system_prompt);
+                // `/clear all` deleted the saved session.
+                if let Err(err) = cleared.save() {
+                    error!(?err, id = %cleared.id, "failed to save the restored session");
+                }
+                *session = cleared;
+                chat_ui.add_system_message(String::from("Brought back the cleared conversation"));
+            }
+            None => chat_ui.add_system_message(String::from(
+                "There is no recently cleared conversation to bring back",
+            )),
+        },
+        Command::New(None) => {
+            save_session(chat_ui, session);
+            *session = Session::new();
+            chat_ui.ui_state.session_id = None;
+            chat_ui.load_messages(Vec::new());
+            chat_ui.ui_state.system_prompt = chat_ui.ui_state.default_system_prompt.clone();
+            chat_ui.add_system_message(String::from("Started a new conversation"));
+        }
+        Command::Git(prompt) => match prompt.build(Path::new(".")) {
+            Ok(prompt) => {
+                chat_ui.ui_state.submit_prompt(prompt);
+                return Ok(true);
+            }
+            Err(err) => {
+                error!(?err, "failed to gather git state");
+                chat_ui.add_system_message(format!("Git command failed: {err}"));
+            }
+        },
+        Command::PasteContext => match clipboard::read_text() {
+            Ok(text) => {
+                let lines = text.lines().count();
+                chat_ui.ui_state.pending_context = Some(text);
+                chat_ui.add_system_message(format!(
+                    "Attached {lines} line(s) from the clipboard to the next message"
+                ));
+            }
+            Err(err) => {
+                error!(?err, "failed to read the clipboard");
+                chat_ui.add_system_message(format!("Paste failed: {err}"));
+            }
+        },
+        Command::Copy(number) => {
+            let Some(code) = code_block(chat_ui, number) else {
+                return Ok(false);
+            };
+            match clipboard::write_text(&code) {
+                Ok(()) => chat_ui.add_system_message(format!(
+                    "Copied block {number} ({} line(s)) to the clipboard",
+                    code.lines().count()
+                )),
+                Err(err) => {
+                    error!(?err, "failed to copy a block");
+                    chat_ui.add_system_message(format!("Copy failed: {err}"));
+                }
+            }
+        }
+        Command::Save(number, path) => {
+            let Some(code) = code_block(chat_ui, number) else {
+                return Ok(false);
+            };
+            let Some(path) = confirm_overwrite(chat_ui, path)? else {
+                return Ok(false);
+            };
+            match fs::write(&path, code) {
+                Ok(()) => chat_ui
+                    .add_system_message(format!("Saved block {number} to {}", path.display())),
+                Err(err) => {
+                    error!(?err, path = %path.display(), "failed to save a block");
+                    chat_ui
+                        .add_system_message(format!("Saving to {} failed: {err}", path.display()));
+                }
+            }
+        }
+        Command::DryRun => {
+            let state = &mut chat_ui.ui_state;
+            state.dry_run = !state.dry_run;
+            chat_ui.add_system_message(String::from(if chat_ui.ui_state.dry_run {
+                "Dry run on: prompts show the request they would be sent with, without sending it"
+            } else {
+                "Dry run off"
+            }));
+        }
+        Command::Json => {
+            let state = &mut chat_ui.ui_state;
+            state.json_output = match state.json_output {
+                Some(_) => None,
+                None => Some(JsonOutput::default()),
+            };
+            chat_ui.add_system_message(String::from(if chat_ui.ui_state.json_output.is_some() {
+                "JSON mode on: answers are requested as JSON"
+            } else {
+                "JSON mode off"
+            }));
+        }
+        Command::SummaryStats => {
+            let stats = stats::Stats::of(&chat_ui.conversation());
+            chat_ui.add_system_message(stats.describe());
+        }
+        Command::Speak => match chat_ui.ui_state.speaker.as_mut() {
+            Some(speaker) => {
+                let enabled = !speaker.is_enabled();
+                speaker.set_enabled(enabled);
+                chat_ui.add_system_message(String::from(if enabled {
+                    "Speech on: responses are read aloud"
+                } else {
+                    "Speech off"
+                }));
+            }
+            None => chat_ui.add_system_message(String::from(
+                "Configure a command in the [speech] section to use /speak",
+            )),
+        },
+        Command::Export(path, marks) => {
+            save_session(chat_ui, session);
+            if session.messages.is_empty() {
+                chat_ui.add_system_message(String::from("Nothing to export yet"));
+                return Ok(false);
+            }
+            let exported = Session {
+                messages: marks.apply(&session.messages),
+                ..session.clone()
+            };
+            if exported.messages.is_empty() {
+                chat_ui.add_system_message(format!(
+                    "No exchange is {}; nothing was exported",
+                    marks.describe()
+                ));
+                return Ok(false);
+            }
+            let path = path.unwrap_or_else(|| PathBuf::from(format!("chatti-{}.md", session.id)));
+            let Some(path) = confirm_overwrite(chat_ui, path)? else {
+                return Ok(false);
+            };
+            let redactor = Redactor::new(&config.redaction).unwrap_or_else(|_| Redactor::builtin());
+            let what = if marks.is_empty() {
+                String::from("the conversation")
+            } else {
+                format!("the exchanges {}", marks.describe())
+            };
+            match fs::write(&path, redactor.redact(&exported.to_markdown()).as_ref()) {
+                Ok(()) => {
+                    chat_ui.add_system_message(format!("Exported {what} to {}", path.display()))
+                }
+                Err(err) => {
+                    error!(?err, path = %path.display(), "export failed");
+                    chat_ui
+                        .add_system_message(format!("Export to {} failed: {err}", path.display()));
+                }
+            }
+        }
+        Command::Dataset(format, marks, path) => {
+            save_session(chat_ui, session);
+            let pairs = dataset::pairs(&session.messages, &marks);
+            if pairs.is_empty() {
+                chat_ui.add_system_message(format!(
+                    "No answered exchange is {}; nothing was written",
+                    marks.describe()
+                ));
+                return Ok(false);
+            }
+            let path =
+                path.unwrap_or_else(|| PathBuf::from(format!("chatti-{}.jsonl", session.id)));
+            let Some(path) = confirm_overwrite(chat_ui, path)? else {
+                return Ok(false);
+            };
+            let redactor = Redactor::new(&config.redaction).unwrap_or_else(|_| Redactor::builtin());
+            let pairs: Vec<(String, String)> = pairs
+                .iter()
+                .map(|(prompt, answer)| {
+                    (
+                        redactor.redact(prompt).into_owned(),
+                        redactor.redact(answer).into_owned(),
+                    )
+                })
+                .collect();
+            let system = session
+                .system_prompt
+                .as_deref()
+                .map(|prompt| redactor.redact(prompt).into_owned());
+            let lines = dataset::to_jsonl(format, system.as_deref(), &pairs);
+            match fs::write(&path, lines) {
+                Ok(()) => chat_ui.add_system_message(format!(
+                    "Wrote {} exchange(s) {} to {}",
+                    pairs.len(),
+                    marks.describe(),
+                    path.display()
+                )),
+                Err(err) => {
+                    error!(?err, path = %path.display(), "dataset export failed");
+                    chat_ui.add_system_message(format!(
+                        "Writing the dataset to {} failed: {err}",
+                        path.display()
+                    ));
+                }
+            }
+        }
+        Command::Marked(filter) => chat_ui.ui_state.open_marked(filter),
+        Command::Model(model) => {
+            chat_ui.ui_state.theme = Theme::from_config(&config.theme, &model);
+            chat_ui.add_system_message(format!("Switched to model {model}"));
+            config.model = model;
+        }
+        Command::Sessions => match chat_ui.ui_state.switch_to.take() {
+            Some(chosen) if chosen.id == session.id => {}
+            Some(chosen) => {
+                open_session(chat_ui, session, &chosen.id, &chosen.title);
+            }
+            None => {
+                save_session(chat_ui, session);
+                match store::with_store(|store| store.list()) {
+                    Ok(sessions) if sessions.is_empty() => {
+                        chat_ui.add_system_message(String::from("There are no saved sessions yet"))
+                    }
+                    Ok(sessions) => chat_ui.ui_state.open_switcher(Switcher::new(sessions)),
+                    Err(err) => {
+                        error!(?err, "listing sessions failed");
+                        chat_ui.add_system_message(format!("Listing the sessions failed: {err}"));
+                    }
+                }
+            }
+        },
+        Command::Archive(days) => {
+            let Some(days) = days.or(config.retention.days) else {
+                chat_ui.add_system_message(String::from(
+                    "Usage: /archive <days>, or set days in [retention]",
+                ));
+                return Ok(false);
+            };
+            save_session(chat_ui, session);
+            let swept = retention::Archive::open().and_then(|archive| {
+                let swept = store::with_store(|store| {
+                    retention::sweep(
+                        store,
+                        &archive,
+                        retention::cutoff(days),
+                        retention::Action::Archive,
+                        Some(&session.id),
+                    )
+                })?;
+                Ok((swept, archive))
+            });
+            match swept {
+                Ok((swept, archive)) => chat_ui.add_system_message(format!(
+                    "Archived {swept} session(s) not modified for {days} day(s) to {}",
+                    archive.dir().display()
+                )),
+                Err(err) => {
+                    error!(?err, "failed to archive sessions");
+                    chat_ui.add_system_message(format!("Archiving failed: {err}"));
+                }
+            }
+        }
+        // Handled asynchronously before getting here.
+        Command::New(Some(_))
+        | Command::Models(_)
+        | Command::Search(_)
+        | Command::Plugins(_)
+        | Command::Scripts(_)
+        | Command::Retry(_)
+        | Command::Translate(_)
+        | Command::Context(_)
+        | Command::Attach(_)
+        | Command::Diagrams
+        | Command::Continue => {}
+        Command::Invalid(message) => chat_ui.add_system_message(message),
+    }
+    Ok(false)
+}
+
+/// Returns code block or table `number` of the selected answer, or of the
+/// latest answer with any, or says why there is none.
+fn code_block(chat_ui: &mut Interface, number: usize) -> Option<String> {
+    let Some(blocks) = chat_ui.ui_state.code_block_answer() else {
+        chat_ui.add_system_message(String::from(
+            "There is no answer with code blocks or tables",
+        ));
+        return None;
+    };
+    let code = blocks.get(number - 1).cloned();
+    if code.is_none() {
+        chat_ui.add_system_message(format!(
+            "There is no block {number}; the answer has {}",
+            blocks.len()
+        ));
+    }
+    code
+}
+
+/// Asks before an export overwrites a file, offering to write to another
+/// path instead. Another path that exists too is asked about in turn.
+///
+/// # Returns
+///
+/// The path to write to, or `None` if the user cancelled the export.
+///
+/// # Errors
+///
+/// Returns an error if the dialog cannot be drawn.
+fn confirm_overwrite(chat_ui: &mut Interface, mut path: PathBuf) -> AppResult<Option<PathBuf>> {
+    while path.exists() {
+        let question = format!(
+            "{} already exists. Press Enter to overwrite it, or change the path.",
+            path.display()
+        );
+        let Some(answer) = chat_ui.prompt(question, path.display().to_string())? else {
+            chat_ui.add_system_message(String::from("Cancelled the export"));
+            return Ok(None);
+        };
+        let chosen = commands::expand_home(&answer);
+        if chosen == path {
+            break;
+        }
+        path = chosen;
+    }
+    Ok(Some(path))
+}
+
+/// Archives or deletes the sessions older than the configured retention
+/// period.
+fn sweep_old_sessions(config: &Config) {
+    let Some(days) = config.retention.days else {
+        return;
+    };
+    let swept = retention::Archive::open().and_then(|archive| {
+        store::with_store(|store| {
+            retention::sweep(
+                store,
+                &archive,
+                retention::cutoff(days),
+                config.retention.action,
+                None,
+            )
+        })
+    });
+    match swept {
+        Ok(0) => {}
+        Ok(swept) => info!(swept, days, action = ?config.retention.action, "swept old sessions"),
+        Err(err) => error!(?err, "failed to sweep old sessions"),
+    }
+}
+
+/// Opens the session of a search result with the matching message
+/// selected, saving the current one first.
+fn open_search_hit(chat_ui: &mut Interface, session: &mut Session, hit: &Hit) {
+    if open_session(chat_ui, session, &hit.session, &hit.title) {
+        chat_ui.ui_state.jump_to_message(hit.message);
+    }
+}
+
+/// Opens a saved session in the UI, saving the current one first.
+///
+/// # Arguments
+///
+/// * `chat_ui` - A mutable reference to the `Interface` instance.
+/// * `session` - The session currently shown, replaced by the opened one.
+/// * `id` - The identifier of the session to open.
+/// * `title` - Its title, for the notice if it cannot be opened.
+///
+/// # Returns
+///
+/// `true` if the session was opened.
+fn open_session(chat_ui: &mut Interface, session: &mut Session, id: &str, title: &str) -> bool {
+    save_session(chat_ui, session);
+    let loaded = match Session::load(id) {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            error!(?err, id, "failed to load session");
+            chat_ui.add_system_message(format!("Could not open \"{title}\": {err}"));
+            return false;
+        }
+    };
+    chat_ui.ui_state.session_id = Some(loaded.id.clone());
+    chat_ui.load_messages(loaded.messages.clone());
+    chat_ui
+        .ui_state
+        .system_prompt
+        .clone_from(&loaded.system_prompt);
+    chat_ui.add_system_message(format!("Opened \"{}\"", loaded.title));
+    let unread = chat_ui.ui_state.mark_unread(loaded.last_read);
+    if unread > 0 {
+        chat_ui.add_system_message(format!(
+            "{unread} message(s) are new since you last read it; press n to jump to them"
+        ));
+    }
+    *session = loaded;
+    true
+}
+
+/// Runs a `/models` command against the Ollama management API, showing the
+/// progress of a pull as a progress bar in the transcript.
+///
+/// # Arguments
+///
+/// * `chat_ui` - A mutable reference to the `Interface` instance.
+/// * `client` - The HTTP client.
+/// * `config` - The application configuration.
+/// * `command` - The `/models` subcommand.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or an `Application` error.
+async fn manage_models(
+    chat_ui: &mut Interface,
+    client: &reqwest::Client,
+    config: &Config,
+    command: ModelCommand,
+) -> AppResult<()> {
+    let endpoint = &config.api_endpoint;
+    if let Err(err) = config.check_endpoint(endpoint) {
+        chat_ui.add_system_message(err.to_string());
+        return Ok(());
+    }
+    match command {
+        ModelCommand::Pull(name) => {
+            chat_ui.add_system_message(format!("Pulling {name}..."));
+            chat_ui.ui_state.input_mode = InputMode::Waiting;
+            let task = chat_ui.ui_state.tasks.start(format!("Pulling {name}"));
+            let (tx, mut rx) = mpsc::channel(100);
+            let mut pull = {
+                let (client, endpoint, name) = (client.clone(), endpoint.clone(), name.clone());
+                tokio::spawn(async move { models::pull(&client, &endpoint, &name, tx).await })
+            };
+            let result = loop {
+                tokio::select! {
+                    Some(progress) = rx.recv() => {
+                        chat_ui
+                            .ui_state
+                            .tasks
+                            .update(task, progress.describe(), progress.fraction());
+                    }
+                    result = &mut pull => {
+                        break result.map_err(|err| Application::Unexpected(err.to_string()));
+                    }
+                    () = sleep(Duration::from_millis(100)) => {}
+                }
+                if chat_ui.update()?.is_some() || chat_ui.ui_state.tasks.is_cancelled(task) {
+                    pull.abort();
+                    chat_ui.ui_state.tasks.finish(task);
+                    chat_ui
+                        .ui_state
+                        .update_system_message(format!("Cancelled pulling {name}"));
+                    chat_ui.ui_state.input_mode = InputMode::Normal;
+                    return Ok(());
+                }
+            };
+            chat_ui.ui_state.tasks.finish(task);
+            chat_ui.ui_state.input_mode = InputMode::Normal;
+            let result = result?;
+            match result {
+                Ok(()) => chat_ui
+                    .ui_state
+                    .update_system_message(format!("Pulled {name}")),
+                Err(err) => {
+                    error!(?err, model = name, "model pull failed");
+                    chat_ui
+                        .ui_state
+                        .update_system_message(format!("Pulling {name} failed: {err}"));
+                }
+            }
+        }
+        ModelCommand::Remove(name) => {
+            let question = format!(
+                "Delete the model {name} from the server? It has to be pulled again to be used."
+            );
+            if !chat_ui.confirm(question)? {
+                return Ok(());
+            }
+            match models::delete(client, endpoint, &name).await {
+                Ok(()) => chat_ui.add_system_message(format!("Deleted {name}")),
+                Err(err) => {
+                    error!(?err, model = name, "model delete failed");
+                    chat_ui.add_system_message(format!("Deleting {name} failed: {err}"));
+                }
+            }
+        }
+        ModelCommand::Running => match models::running(client, endpoint).await {
+            Ok(running) => chat_ui.add_system_message(models::describe_running(&running)),
+            Err(err) => {
+                error!(?err, "listing running models failed");
+                chat_ui.add_system_message(format!("Listing running models failed: {err}"));
+            }
+        },
+    }
+    Ok(())
+}
 
-    let config = Config::load()?;
-    let mut chat_ui = Interface::new()?;
-    let client = reqwest::Client::new();
+/// Sends the prompt of an answer again with another model or other
+/// parameters, streaming the new answer below the original one.
+///
+/// # Arguments
+///
+/// * `chat_ui` - A mutable reference to the `Interface` instance.
+/// * `client` - The HTTP client.
+/// * `config` - The application configuration.
+/// * `retry` - The answer to retry and how.
+async fn retry_answer(
+    chat_ui: &mut Interface,
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    config: &Config,
+    retry: Retry,
+) -> AppResult<()> {
+    let mut config = config.clone();
+    retry.params.apply(&mut config);
+    if !retry.model.is_empty() {
+        config.model = retry.model;
+    }
+    let Some((history, slot)) = chat_ui
+        .ui_state
+        .start_retry(retry.message, Generation::from_config(&config))
+    else {
+        chat_ui.add_system_message(String::from("Only answers to a prompt can be retried"));
+        return Ok(());
+    };
 
-    while let Some(message) = chat_ui.run()? {
-        let (tx, mut rx) = mpsc::channel(100);
-        let client_clone = client.clone();
-        let config_clone = config.clone();
+    match stream_into(chat_ui, client, limiter, config, history, slot).await? {
+        Streamed::Cancelled => chat_ui.ui_state.cancel_retry(slot),
+        streamed => chat_ui.ui_state.finish_retry(slot, streamed.error()),
+    }
+    Ok(())
+}
 
-        tokio::spawn(async move {
-            if let Err(err) = process_message(&client_clone, &config_clone, &message, tx).await {
-                error!(?err, "error occurred in process_message");
+/// Continues the answer cut off at the token limit, streaming the rest onto
+/// the end of it and stitching it to the answer once it is done.
+///
+/// # Arguments
+///
+/// * `chat_ui` - A mutable reference to the `Interface` instance.
+/// * `client` - The HTTP client.
+/// * `config` - The application configuration.
+async fn continue_answer(
+    chat_ui: &mut Interface,
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    config: &Config,
+) -> AppResult<()> {
+    let Some((history, continuation)) = chat_ui.ui_state.start_continuation() else {
+        chat_ui.add_system_message(String::from(
+            "There is no answer cut off at the token limit to continue",
+        ));
+        return Ok(());
+    };
+    let slot = continuation.slot;
+    let streamed = stream_into(chat_ui, client, limiter, config.clone(), history, slot).await?;
+    chat_ui
+        .ui_state
+        .finish_continuation(continuation, streamed.error());
+    Ok(())
+}
+
+/// Translates the selected answer, or the latest one, streaming the
+/// translation below it.
+///
+/// # Arguments
+///
+/// * `chat_ui` - A mutable reference to the `Interface` instance.
+/// * `client` - The HTTP client.
+/// * `config` - The application configuration.
+/// * `language` - The language to translate into.
+async fn translate_answer(
+    chat_ui: &mut Interface,
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    config: &Config,
+    language: &str,
+) -> AppResult<()> {
+    let Some((request, slot)) = chat_ui.ui_state.start_translation(language) else {
+        chat_ui.add_system_message(String::from("There is no answer to translate"));
+        return Ok(());
+    };
+    let streamed = stream_into(chat_ui, client, limiter, config.clone(), request, slot).await?;
+    chat_ui.ui_state.finish_translation(slot, streamed.error());
+    Ok(())
+}
+
+/// Opens the chosen hit of a session search, or searches the saved
+/// sessions for `query` in the background and lists the hits.
+async fn search_sessions(
+    chat_ui: &mut Interface,
+    session: &mut Session,
+    query: String,
+) -> AppResult<()> {
+    if let Some(hit) = chat_ui.ui_state.search_hit.take() {
+        open_search_hit(chat_ui, session, &hit);
+        return Ok(());
+    }
+    if query.is_empty() {
+        chat_ui.add_system_message(String::from("Usage: /search <query>"));
+        return Ok(());
+    }
+    let label = format!("Searching the sessions for \"{query}\"");
+    let search = {
+        let query = query.clone();
+        move || store::with_store(|store| search::find(store, &query))
+    };
+    match run_as_task(chat_ui, label, search).await? {
+        None => chat_ui.add_system_message(String::from("Search cancelled")),
+        Some(Ok(hits)) if hits.is_empty() => {
+            chat_ui.add_system_message(format!("No saved session mentions \"{query}\""));
+        }
+        Some(Ok(hits)) => chat_ui.ui_state.open_search(Results::new(query, hits)),
+        Some(Err(err)) => {
+            error!(?err, "session search failed");
+            chat_ui.add_system_message(format!("Search failed: {err}"));
+        }
+    }
+    Ok(())
+}
+
+/// Starts a new conversation from a template, preparing it in the
+/// background so its command can be cancelled.
+async fn start_template(
+    chat_ui: &mut Interface,
+    session: &mut Session,
+    config: &Config,
+    name: String,
+) -> AppResult<()> {
+    let Some(template) = config.templates.get(&name).cloned() else {
+        let err = TemplateError::Unknown(name.clone());
+        chat_ui.add_system_message(format!("Template \"{name}\" failed: {err}"));
+        return Ok(());
+    };
+    let label = format!("Starting the conversation from template \"{name}\"");
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let start = {
+        let cancelled = Arc::clone(&cancelled);
+        move || templates::start_cancellable(&template, Path::new("."), &cancelled)
+    };
+    let Some(started) = run_as_task(chat_ui, label, start).await? else {
+        // The command is killed rather than left running.
+        cancelled.store(true, Ordering::Relaxed);
+        chat_ui.add_system_message(format!("Template \"{name}\" cancelled"));
+        return Ok(());
+    };
+    match started {
+        Ok(started) => {
+            save_session(chat_ui, session);
+            *session = Session::new();
+            chat_ui.ui_state.session_id = None;
+            chat_ui.load_messages(started.messages);
+            chat_ui.ui_state.system_prompt = started.system_prompt;
+            chat_ui.add_system_message(format!(
+                "Started a new conversation from template \"{name}\" ({} file(s) attached)",
+                started.files
+            ));
+            if let Some(schema) = started.schema {
+                chat_ui.ui_state.json_output = Some(JsonOutput {
+                    schema: Some(schema),
+                });
+                chat_ui.add_system_message(String::from(
+                    "JSON mode on: answers are requested as JSON matching the template's schema",
+                ));
             }
-        });
+        }
+        Err(err) => {
+            error!(?err, template = name, "failed to start template");
+            chat_ui.add_system_message(format!("Template \"{name}\" failed: {err}"));
+        }
+    }
+    Ok(())
+}
+
+/// Attaches the files matching glob patterns to the next message, reading
+/// them in the background.
+async fn attach_files(
+    chat_ui: &mut Interface,
+    config: &Config,
+    patterns: Vec<String>,
+) -> AppResult<()> {
+    let budget = config.attachments.budget();
+    let sampling = config.attachments.sampling();
+    let label = format!("Attaching {}", patterns.join(" "));
+    let walk = {
+        let patterns = patterns.clone();
+        move || Batch::collect(&patterns, Path::new("."), budget, sampling)
+    };
+    let Some(result) = run_as_task(chat_ui, label, walk).await? else {
+        chat_ui.add_system_message(String::from("Attaching cancelled"));
+        return Ok(());
+    };
+    match result {
+        Ok(batch) if batch.is_empty() => {
+            chat_ui.add_system_message(format!("No files match {}", patterns.join(" ")))
+        }
+        Ok(batch) => {
+            if !batch.attached.is_empty() {
+                chat_ui.ui_state.attach_context(batch.context());
+            }
+            chat_ui.add_system_message(batch.describe(budget));
+        }
+        Err(err) => chat_ui.add_system_message(format!("Attach failed: {err}")),
+    }
+    Ok(())
+}
 
-        chat_ui.start_new_response();
-        process_response(&mut chat_ui, &mut rx).await?;
+/// Runs blocking work, such as reading many files, in the background as a
+/// task that can be cancelled, keeping the interface responsive meanwhile.
+///
+/// # Returns
+///
+/// What the work returned, or `None` if the task was cancelled. Cancelled
+/// work is left to finish, and what it returns unused.
+async fn run_as_task<T: Send + 'static>(
+    chat_ui: &mut Interface,
+    label: String,
+    work: impl FnOnce() -> T + Send + 'static,
+) -> AppResult<Option<T>> {
+    let mut handle = tokio::task::spawn_blocking(work);
+    wait_for_task(chat_ui, label, &mut handle).await
+}
 
-        if chat_ui.should_quit() {
-            break;
+/// Runs asynchronous work, such as retrieving excerpts, in the background
+/// as a task that can be cancelled, like [`run_as_task`].
+///
+/// # Returns
+///
+/// What the work returned, or `None` if the task was cancelled. Cancelled
+/// work is stopped.
+async fn run_async_as_task<T: Send + 'static>(
+    chat_ui: &mut Interface,
+    label: String,
+    work: impl Future<Output = T> + Send + 'static,
+) -> AppResult<Option<T>> {
+    let mut handle = tokio::spawn(work);
+    let joined = wait_for_task(chat_ui, label, &mut handle).await;
+    handle.abort();
+    joined
+}
+
+/// Shows a task while `handle` runs, until it ends or the task is
+/// cancelled.
+async fn wait_for_task<T>(
+    chat_ui: &mut Interface,
+    label: String,
+    handle: &mut JoinHandle<T>,
+) -> AppResult<Option<T>> {
+    chat_ui.ui_state.input_mode = InputMode::Waiting;
+    let task = chat_ui.ui_state.tasks.start(label);
+    let joined = loop {
+        if chat_ui.ui_state.tasks.is_cancelled(task) {
+            break None;
+        }
+        tokio::select! {
+            joined = &mut *handle => break Some(joined),
+            () = sleep(Duration::from_millis(100)) => {}
+        }
+        if chat_ui.update()?.is_some() {
+            chat_ui.ui_state.tasks.cancel_all();
+        }
+    };
+    chat_ui.ui_state.tasks.finish(task);
+    chat_ui.ui_state.input_mode = InputMode::Normal;
+    joined
+        .transpose()
+        .map_err(|err| Application::Unexpected(err.to_string()))
+}
+
+/// Renders the diagrams of the selected answer, or of the latest answer
+/// with diagrams, each as a task that can be cancelled, and shows them
+/// below it. Without a graphics protocol to draw them with, the images are
+/// also opened with the viewer.
+async fn render_diagrams(chat_ui: &mut Interface, config: &Config) -> AppResult<()> {
+    let Some((index, found)) = chat_ui.ui_state.diagram_answer() else {
+        chat_ui.add_system_message(String::from(
+            "There is no answer with a Mermaid or Graphviz diagram",
+        ));
+        return Ok(());
+    };
+    chat_ui.ui_state.input_mode = InputMode::Waiting;
+    let mut renders = tokio::task::JoinSet::new();
+    let mut tasks = Vec::new();
+    let mut cancels = Vec::new();
+    for (position, diagram) in found.into_iter().enumerate() {
+        let label = format!("Rendering {} diagram {}", diagram.kind.name(), position + 1);
+        tasks.push(chat_ui.ui_state.tasks.start(label));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        cancels.push(Arc::clone(&cancelled));
+        let settings = config.diagrams.clone();
+        renders.spawn_blocking(move || {
+            (
+                position,
+                diagram.kind,
+                diagrams::render(&diagram, &settings, &cancelled),
+            )
+        });
+    }
+
+    // A cancelled render's command is killed, and its image unused.
+    let mut rendered = vec![None; tasks.len()];
+    while !renders.is_empty()
+        && tasks
+            .iter()
+            .any(|&task| !chat_ui.ui_state.tasks.is_cancelled(task))
+    {
+        tokio::select! {
+            Some(joined) = renders.join_next() => {
+                let Ok((position, kind, result)) = joined else {
+                    continue;
+                };
+                if chat_ui.ui_state.tasks.is_cancelled(tasks[position]) {
+                    continue;
+                }
+                chat_ui.ui_state.tasks.finish(tasks[position]);
+                match result {
+                    Ok(image) => rendered[position] = Some(image),
+                    Err(err) => {
+                        error!(%err, "failed to render a diagram");
+                        chat_ui.add_system_message(format!(
+                            "Could not render {} diagram {}: {err}",
+                            kind.name(),
+                            position + 1
+                        ));
+                    }
+                }
+            }
+            () = sleep(Duration::from_millis(100)) => {}
+        }
+        if chat_ui.update()?.is_some() {
+            chat_ui.ui_state.tasks.cancel_all();
+        }
+        for (&task, cancelled) in tasks.iter().zip(&cancels) {
+            if chat_ui.ui_state.tasks.is_cancelled(task) {
+                cancelled.store(true, Ordering::Relaxed);
+            }
         }
     }
+    for (task, cancelled) in tasks.into_iter().zip(&cancels) {
+        cancelled.store(true, Ordering::Relaxed);
+        chat_ui.ui_state.tasks.finish(task);
+    }
+    chat_ui.ui_state.input_mode = InputMode::Normal;
 
+    let images: Vec<PathBuf> = rendered.into_iter().flatten().collect();
+    if chat_ui.ui_state.graphics.is_none() {
+        for image in &images {
+            if let Err(err) = diagrams::open(image, &config.diagrams) {
+                error!(?err, "failed to open a diagram");
+                chat_ui.add_system_message(format!("Could not open {}: {err}", image.display()));
+            }
+        }
+    }
+    chat_ui.ui_state.show_diagrams(index, images);
     Ok(())
 }
 
+/// How streaming a response into a message ended.
+enum Streamed {
+    /// The response was streamed to its end, or stopped with Ctrl-C.
+    Finished,
+    /// The request failed, for the given reason.
+    Failed(String),
+    /// The user cancelled the request with Esc.
+    Cancelled,
+}
+
+impl Streamed {
+    /// Returns why the request failed, if it did. What was streamed before
+    /// a cancel is kept like a stopped response.
+    fn error(self) -> Option<String> {
+        match self {
+            Streamed::Failed(error) => Some(error),
+            Streamed::Finished | Streamed::Cancelled => None,
+        }
+    }
+}
+
+/// Sends a request and streams the response into the message at `slot`,
+/// until it ends or the user stops or cancels it.
+async fn stream_into(
+    chat_ui: &mut Interface,
+    client: &reqwest::Client,
+    limiter: &RateLimiter,
+    config: Config,
+    history: Vec<Message>,
+    slot: usize,
+) -> AppResult<Streamed> {
+    let (request, mut rx) = spawn_request(client, limiter, config, history);
+    let streamed = loop {
+        tokio::select! {
+            chunk = rx.recv() => match chunk {
+                Some(Ok(content)) => chat_ui.ui_state.update_retry(slot, &content),
+                Some(Err(err)) => {
+                    error!(?err, "request failed");
+                    break Streamed::Failed(err.to_string());
+                }
+                None => {
+                    let metadata = request.await.unwrap_or_default();
+                    chat_ui.ui_state.annotate_metadata_at(slot, metadata);
+                    return Ok(Streamed::Finished);
+                }
+            },
+            () = sleep(Duration::from_millis(100)) => {}
+        }
+        match chat_ui.update()? {
+            Some(Action::CancelRequest) => break Streamed::Cancelled,
+            Some(_) => break Streamed::Finished,
+            None => {}
+        }
+    };
+    request.abort();
+    Ok(streamed)
+}
+
 /// Processes a user message by sending it to the API and streaming the response.
 ///
 /// # Arguments
 ///
 /// * `client` - The HTTP client for making API requests.
 /// * `config` - The application configuration.
-/// * `message` - The user's message to be processed.
+/// * `messages` - The conversation so far, ending with the user's message.
 /// * `tx` - A channel sender for streaming the response.
 ///
 /// # Returns
 ///
-/// Returns a `Result` indicating success or an `Application` error.
-#[instrument]
+/// Returns what the server reported about the response, or an
+/// `Application` error.
+#[instrument(
+    target = API_TARGET,
+    skip_all,
+    fields(
+        model = %config.model,
+        provider = ?config.provider,
+        messages = messages.len(),
+        status = field::Empty,
+        request_id = field::Empty,
+    )
+)]
 async fn process_message(
     client: &reqwest::Client,
     config: &Config,
-    message: &str,
+    messages: &[Message],
     tx: mpsc::Sender<Result<String, Application>>,
-) -> AppResult<()> {
-    let response = match client
-        .post(&config.api_endpoint)
-        .json(&json!({
-            "model": config.model,
-            "messages": [{"role": "user", "content": message}],
-            "stream": config.stream,
-            "temperature": config.temperature,
-        }))
-        .send()
-        .await
-    {
+) -> AppResult<Metadata> {
+    if let Err(err) = config.check_endpoint(&config.api_endpoint) {
+        tx.send(Err(err))
+            .await
+            .map_err(|e| Application::Unexpected(e.to_string()))?;
+        return Ok(Metadata::default());
+    }
+    if config.redaction.log_prompts {
+        info!(?messages, "sending prompt");
+    }
+
+    let provider = format!("{:?}", config.provider).to_lowercase();
+    let body = config.provider.request_body(config, messages);
+    let started = Instant::now();
+    if config.transport.for_profile(&config.profile) == Transport::WebSocket {
+        let endpoint = &config.api_endpoint;
+        let key = config.api_key.as_deref();
+        return match transport::send_websocket(endpoint, key, &body, config.provider, &tx).await {
+            Ok(metadata) => {
+                telemetry::record_request(
+                    &config.model,
+                    &provider,
+                    0,
+                    started.elapsed(),
+                    &metadata,
+                );
+                Ok(metadata)
+            }
+            Err(err) => {
+                tx.send(Err(err))
+                    .await
+                    .map_err(|e| Application::Unexpected(e.to_string()))?;
+                Ok(Metadata::default())
+            }
+        };
+    }
+
+    let response = match client.post(&config.api_endpoint).json(&body).send().await {
         Ok(resp) => resp,
         Err(e) => {
             tx.send(Err(Application::Network(e)))
                 .await
                 .map_err(|e| Application::Unexpected(e.to_string()))?;
-            return Ok(());
+            return Ok(Metadata::default());
         }
     };
 
-    if !response.status().is_success() {
+    let request_id = ["x-request-id", "request-id"]
+        .iter()
+        .find_map(|name| response.headers().get(*name))
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let status = response.status();
+    let span = Span::current();
+    span.record("status", status.as_u16());
+    if let Some(id) = &request_id {
+        span.record("request_id", id.as_str());
+    }
+    debug!(
+        target: API_TARGET,
+        elapsed_ms = started.elapsed().as_millis(),
+        "received the response headers"
+    );
+    if !status.is_success() {
         let error_body = response.text().await?;
-        let error_json: serde_json::Value =
-            serde_json::from_str(&error_body).unwrap_or_else(|_| json!({"error": error_body}));
-
-        if let Some(error_message) = error_json.get("error").and_then(|e| e.as_str()) {
-            tx.send(Err(Application::ApiError(error_message.to_string())))
-                .await
-                .map_err(|e| Application::Unexpected(e.to_string()))?;
-        } else {
-            tx.send(Err(Application::Unexpected(
-                "Unknown API error".to_string(),
-            )))
-            .await
-            .map_err(|e| Application::Unexpected(e.to_string()))?;
-        }
-        return Ok(());
+        let message = api::parse_error_message(&error_body)
+            .unwrap_or_else(|| format!("The server responded with {status}"));
+        telemetry::record_request(
+            &config.model,
+            &provider,
+            status.as_u16(),
+            started.elapsed(),
+            &Metadata::default(),
+        );
+        tx.send(Err(Application::ApiError {
+            message,
+            status: status.as_u16(),
+            body: error_body,
+            request_id,
+        }))
+        .await
+        .map_err(|e| Application::Unexpected(e.to_string()))?;
+        return Ok(Metadata::default());
     }
 
-    if !config.stream {
+    let mut metadata = if config.stream {
+        stream::forward(response, config.provider, &tx).await?
+    } else {
         // Handle regular (non-streaming) response
         let json: serde_json::Value = response.json().await?;
-        if let Some(content) = json["message"]["content"].as_str() {
+        if let Some(content) = config.provider.content(&json) {
             tx.send(Ok(content.to_string()))
                 .await
                 .map_err(|e| Application::Unexpected(e.to_string()))?;
         }
-        return Ok(());
-    }
-
-    // Handle streaming response
-    let mut stream = response.bytes_stream();
-    let mut buffer = Vec::new();
-
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk?;
-        buffer.extend_from_slice(&chunk);
-
-        while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
-            let line = String::from_utf8_lossy(&buffer[..pos]).to_string();
-            buffer.drain(..=pos);
-
-            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&line) {
-                if let Some(content) = json["message"]["content"].as_str() {
-                    tx.send(Ok(content.to_string()))
-                        .await
-                        .map_err(|e| Application::Unexpected(e.to_string()))?;
-                }
-
-                if json["done"].as_bool().unwrap_or(false) {
-                    return Ok(());
-                }
-            }
-        }
+        let mut metadata = Metadata::default();
+        config.provider.record(&json, &mut metadata);
+        metadata
+    };
+    if request_id.is_some() {
+        metadata.request_id = request_id;
     }
-
-    Ok(())
+    telemetry::record_request(
+        &config.model,
+        &provider,
+        status.as_u16(),
+        started.elapsed(),
+        &metadata,
+    );
+    Ok(metadata)
 }
 
 /// Processes the streamed response and updates the UI.
@@ -149,63 +2280,145 @@ async fn process_message(
 ///
 /// * `chatti` - A mutable reference to the `ChatUI` instance.
 /// * `rx` - A mutable reference to the receiver channel for the streamed response.
+/// * `journal` - The journal each streamed chunk is appended to.
 ///
 /// # Returns
 ///
-/// Returns a `Result` indicating success or an `Application` error.
+/// Returns the response if it completed, `None` if it failed or was stopped
+/// or cancelled, or an `Application` error.
+#[instrument(target = API_TARGET, skip_all, fields(chunks = field::Empty, bytes = field::Empty))]
 async fn process_response(
     chat_ui: &mut Interface,
     rx: &mut mpsc::Receiver<Result<String, Application>>,
-) -> AppResult<()> {
+    journal: &mut Journal,
+) -> AppResult<Option<String>> {
     let mut full_response = String::new();
-    let mut error_occurred = false;
+    let mut chunks = 0_usize;
 
     loop {
         tokio::select! {
             result = rx.recv() => {
                 match result {
                     Some(Ok(content)) => {
+                        let started = Instant::now();
                         full_response.push_str(&content);
+                        journal.append(&content);
                         chat_ui.update_response(&content);
-                        if let Some(action) = chat_ui.update()? {
-                            if action == Action::CancelRequest {
-                                chat_ui.add_response("Request cancelled".to_string());
-                                return Ok(());
-                            }
+                        let action = chat_ui.update()?;
+                        chunks += 1;
+                        trace!(
+                            target: API_TARGET,
+                            bytes = content.len(),
+                            update_us = started.elapsed().as_micros(),
+                            "showed a chunk"
+                        );
+                        if let Some(action) = action {
+                            apply_action(chat_ui, &action);
+                            return Ok(None);
                         }
                     }
                     Some(Err(err)) => {
                         error!(?err, "Error occurred while receiving response");
-                        chat_ui.add_response(format!(
-                            "Error: {}, For more details, please check the log file at: {}",
-                            err.display_message(), logging::get_log_file_path().display()
-                        ));
-                        error_occurred = true;
+                        if chat_ui.ui_state.queue_offline && err.is_connection_error() {
+                            chat_ui.mark_pending();
+                        } else {
+                            chat_ui.add_error(&err);
+                        }
                         break;
                     }
                     None => {
-                        if !full_response.is_empty() {
-                            chat_ui.add_response(full_response);
+                        let span = Span::current();
+                        span.record("chunks", chunks);
+                        span.record("bytes", full_response.len());
+                        if full_response.is_empty() {
+                            return Ok(None);
                         }
-                        break;
+                        chat_ui.add_response(full_response.clone());
+                        return Ok(Some(full_response));
                     }
                 }
             }
             () = sleep(Duration::from_millis(100)) => {
                 if let Some(action) = chat_ui.update()? {
-                    if action == Action::CancelRequest {
-                        chat_ui.add_response("Request cancelled".to_string());
-                        return Ok(());
-                    }
+                    apply_action(chat_ui, &action);
+                    return Ok(None);
                 }
             }
         }
     }
 
-    if error_occurred {
-        // Add a delay to prevent rapid spinning
-        sleep(Duration::from_millis(300)).await;
+    // Add a delay after an error to prevent rapid spinning
+    sleep(Duration::from_millis(300)).await;
+
+    Ok(None)
+}
+
+/// Streams the responses of two models side by side.
+///
+/// # Arguments
+///
+/// * `chat_ui` - A mutable reference to the `Interface` instance.
+/// * `left` - The receiver of the left model's response.
+/// * `right` - The receiver of the right model's response.
+///
+/// # Returns
+///
+/// Returns a `Result` indicating success or an `Application` error.
+async fn process_comparison(
+    chat_ui: &mut Interface,
+    left: &mut mpsc::Receiver<Result<String, Application>>,
+    right: &mut mpsc::Receiver<Result<String, Application>>,
+) -> AppResult<()> {
+    let mut open = [true, true];
+
+    while open.iter().any(|open| *open) {
+        let (side, result) = tokio::select! {
+            result = left.recv(), if open[0] => (0, result),
+            result = right.recv(), if open[1] => (1, result),
+            () = sleep(Duration::from_millis(100)) => {
+                if let Some(action) = chat_ui.update()? {
+                    apply_action(chat_ui, &action);
+                    return Ok(());
+                }
+                continue;
+            }
+        };
+
+        match result {
+            Some(Ok(content)) => chat_ui.ui_state.update_comparison(side, &content),
+            Some(Err(err)) => {
+                error!(?err, side, "Error occurred while comparing responses");
+                chat_ui
+                    .ui_state
+                    .update_comparison(side, &format!("\n\n**Error:** {}", err.display_message()));
+                open[side] = false;
+                chat_ui.ui_state.finish_comparison(side);
+            }
+            None => {
+                open[side] = false;
+                chat_ui.ui_state.finish_comparison(side);
+            }
+        }
+
+        if let Some(action) = chat_ui.update()? {
+            apply_action(chat_ui, &action);
+            return Ok(());
+        }
     }
 
     Ok(())
 }
+
+/// Applies a user action to the in-flight request.
+///
+/// # Arguments
+///
+/// * `chat_ui` - A mutable reference to the `Interface` instance.
+/// * `action` - The action requested by the user.
+fn apply_action(chat_ui: &mut Interface, action: &Action) {
+    match action {
+        Action::CancelRequest => chat_ui.cancel_response(),
+        Action::StopGeneration => chat_ui.stop_response(),
+        Action::Interject => chat_ui.interject(),
+    }
+}
@@ -0,0 +1,121 @@
+//! Attaches the files mentioned in a prompt as `@path`.
+//!
+//! A word starting with `@` that names an existing file, relative to the
+//! working directory or `~`, is read and attached to the message as context
+//! when it is sent. Other words starting with `@`, such as handles, are left
+//! alone.
+
+use std::path::{Path, PathBuf};
+
+use crate::attachments::Attachment;
+use crate::commands::expand_home;
+use crate::tables::Sampling;
+
+/// Returns the files mentioned in a prompt, in order and without
+/// duplicates, as written after the `@`.
+///
+/// Punctuation ending a sentence after a mention is ignored.
+///
+/// # Arguments
+///
+/// * `prompt` - The text of the message.
+/// * `dir` - The directory relative paths are resolved in.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::mentions::find;
+/// use std::path::Path;
+///
+/// let found = find("Compare @Cargo.toml, @nowhere.txt and @Cargo.toml", Path::new("."));
+/// assert_eq!(found, vec!["Cargo.toml".to_string()]);
+/// ```
+#[must_use]
+pub fn find(prompt: &str, dir: &Path) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+    for word in prompt.split_whitespace() {
+        let Some(mention) = word.strip_prefix('@') else {
+            continue;
+        };
+        let trimmed = mention.trim_end_matches([',', '.', ';', ':', '!', '?', ')']);
+        let Some(path) = [mention, trimmed]
+            .into_iter()
+            .find(|path| !path.is_empty() && resolve(path, dir).is_file())
+        else {
+            continue;
+        };
+        if !found.iter().any(|known| known == path) {
+            found.push(path.to_string());
+        }
+    }
+    found
+}
+
+/// Returns the mentions in a prompt that look like paths but name no
+/// file, in order and without duplicates. A mention looks like a path if
+/// it has a `/`, a `.` or a leading `~`, so handles such as `@alice` are
+/// not counted.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::mentions::missing;
+/// use std::path::Path;
+///
+/// let missing = missing("Ask @alice about @Cargo.toml and @docs/nowhere.md.", Path::new("."));
+/// assert_eq!(missing, vec!["docs/nowhere.md".to_string()]);
+/// ```
+#[must_use]
+pub fn missing(prompt: &str, dir: &Path) -> Vec<String> {
+    let mut missing: Vec<String> = Vec::new();
+    for word in prompt.split_whitespace() {
+        let Some(mention) = word.strip_prefix('@') else {
+            continue;
+        };
+        let trimmed = mention.trim_end_matches([',', '.', ';', ':', '!', '?', ')']);
+        let looks_like_path = trimmed.contains(['/', '.']) || trimmed.starts_with('~');
+        if !looks_like_path
+            || [mention, trimmed]
+                .iter()
+                .any(|path| resolve(path, dir).is_file())
+        {
+            continue;
+        }
+        if !missing.iter().any(|known| known == trimmed) {
+            missing.push(trimmed.to_string());
+        }
+    }
+    missing
+}
+
+/// Reads the files mentioned in a prompt into the context attached to it.
+///
+/// Binary files are skipped, long files keep only their first and last
+/// lines, and CSV and TSV tables are sampled.
+///
+/// # Arguments
+///
+/// * `prompt` - The text of the message.
+/// * `dir` - The directory relative paths are resolved in.
+/// * `sampling` - How tables are sampled.
+///
+/// # Returns
+///
+/// The contents of the files, each under its path, or `None` if no file is
+/// mentioned.
+#[must_use]
+pub fn attach(prompt: &str, dir: &Path, sampling: Sampling) -> Option<String> {
+    let sections: Vec<String> = find(prompt, dir)
+        .into_iter()
+        .filter_map(|path| {
+            let attachment = Attachment::load(&resolve(&path, dir), sampling).ok()?;
+            Some(format!("File `{path}`:\n{}", attachment.text.trim_end()))
+        })
+        .collect();
+    (!sections.is_empty()).then(|| sections.join("\n\n"))
+}
+
+/// Resolves a mentioned path against `dir`, expanding a leading `~`.
+fn resolve(path: &str, dir: &Path) -> PathBuf {
+    dir.join(expand_home(path))
+}
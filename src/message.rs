@@ -0,0 +1,614 @@
+//! Defines the chat message type shared by the UI, sessions and the client.
+
+use serde::{Deserialize, Serialize};
+use std::{borrow::Borrow, path::PathBuf, time::Duration};
+
+use crate::config::Config;
+use crate::error::ErrorDetails;
+
+/// A single message in the transcript.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Message {
+    /// The author of the message: `user`, `assistant`, `system` or `error`.
+    pub role: String,
+    /// The message text.
+    pub content: String,
+    /// Whether generation was stopped before the response was complete.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub stopped: bool,
+    /// Whether the message is queued until the endpoint is reachable again.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub pending: bool,
+    /// Whether the request a `user` message started was cancelled before
+    /// it was answered. The prompt is then left out of later requests.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cancelled: bool,
+    /// Context attached to a user message, sent after its text and shown
+    /// collapsed in the transcript.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attachment: Option<String>,
+    /// Image files attached to a user message, sent with it to servers
+    /// that read images and shown in the transcript.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<PathBuf>,
+    /// The images the diagrams of an `assistant` message were rendered
+    /// into, shown below it and not sent.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub diagrams: Vec<PathBuf>,
+    /// The files and lines of the excerpts retrieved as context for an
+    /// `assistant` message.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    /// The model and parameters that produced an `assistant` message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation: Option<Generation>,
+    /// What the server reported about the request that produced an
+    /// `assistant` message, and how long it took.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Metadata>,
+    /// Whether the `assistant` message is shown next to a retry of the same
+    /// prompt, with the parameters that produced it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub compared: bool,
+    /// Whether an `assistant` message is a JSON answer, shown pretty-printed
+    /// as a code block. Its content is kept as the server sent it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub json: bool,
+    /// The language an `assistant` message was translated into, from the
+    /// answer above it. Translations are not sent with later prompts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation: Option<String>,
+    /// Technical details of an `error` message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<ErrorDetails>,
+    /// How the user marked the message, in the order of [`Mark`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub marks: Vec<Mark>,
+    /// Whether the details of an `error` message are shown.
+    #[serde(skip)]
+    pub expanded: bool,
+    /// The column from which long code lines are shown when they are cut
+    /// off instead of wrapped, or `None` to wrap them.
+    #[serde(skip)]
+    pub code_scroll: Option<usize>,
+    /// Whether an answer is shown as its markdown source rather than
+    /// rendered.
+    #[serde(skip)]
+    pub raw: bool,
+    /// Whether the message is the first one added since the session was
+    /// last read, shown below a `── new ──` separator.
+    #[serde(skip)]
+    pub first_unread: bool,
+}
+
+impl Message {
+    /// Creates a new message.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The author of the message.
+    /// * `content` - The message text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::Message;
+    ///
+    /// let message = Message::new("user", "Hello");
+    /// assert_eq!(message.role, "user");
+    /// assert_eq!(message.content, "Hello");
+    /// ```
+    #[must_use]
+    pub fn new(role: impl Into<String>, content: impl Into<String>) -> Self {
+        Message {
+            role: role.into(),
+            content: content.into(),
+            ..Message::default()
+        }
+    }
+
+    /// Returns the text sent to the API: the content, followed by the
+    /// attached context if there is any.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::Message;
+    ///
+    /// let mut message = Message::new("user", "What does this do?");
+    /// message.attachment = Some("fn main() {}".to_string());
+    /// assert_eq!(
+    ///     message.request_content(),
+    ///     "What does this do?\n\nContext:\n```\nfn main() {}\n```"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn request_content(&self) -> String {
+        match &self.attachment {
+            Some(attachment) => format!(
+                "{}\n\nContext:\n```\n{}\n```",
+                self.content,
+                attachment.trim_end()
+            ),
+            None => self.content.clone(),
+        }
+    }
+
+    /// Lists what is known about how the message was generated, as label
+    /// and value pairs with `-` for what was not recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::{Message, Metadata};
+    ///
+    /// let mut message = Message::new("assistant", "Hi");
+    /// message.metadata = Some(Metadata {
+    ///     completion_tokens: Some(2),
+    ///     total_ms: Some(1250),
+    ///     ..Metadata::default()
+    /// });
+    /// let details = message.details();
+    /// assert!(details.contains(&("Completion tokens", "2".to_string())));
+    /// assert!(details.contains(&("Total time", "1.25s".to_string())));
+    /// assert!(details.contains(&("Model", "-".to_string())));
+    /// ```
+    #[must_use]
+    pub fn details(&self) -> Vec<(&'static str, String)> {
+        let generation = self.generation.as_ref();
+        let metadata = self.metadata.clone().unwrap_or_default();
+        let known = |value: Option<String>| value.unwrap_or_else(|| String::from("-"));
+        let seconds = |ms: Option<u64>| {
+            known(ms.map(|ms| format!("{:.2}s", Duration::from_millis(ms).as_secs_f64())))
+        };
+        let finish_reason = metadata
+            .finish_reason
+            .or_else(|| self.stopped.then(|| String::from("stopped by you")));
+        vec![
+            ("Model", known(generation.map(|g| g.model.clone()))),
+            (
+                "Temperature",
+                known(generation.map(|g| format!("{:.2}", g.temperature))),
+            ),
+            (
+                "top_p",
+                known(generation.and_then(|g| g.top_p).map(|p| format!("{p:.2}"))),
+            ),
+            (
+                "max_tokens",
+                known(generation.and_then(|g| g.max_tokens).map(|n| n.to_string())),
+            ),
+            (
+                "Prompt tokens",
+                known(metadata.prompt_tokens.map(|n| n.to_string())),
+            ),
+            (
+                "Completion tokens",
+                known(metadata.completion_tokens.map(|n| n.to_string())),
+            ),
+            ("First token", seconds(metadata.first_token_ms)),
+            ("Total time", seconds(metadata.total_ms)),
+            ("Finish reason", known(finish_reason)),
+            ("Request id", known(metadata.request_id)),
+        ]
+    }
+
+    /// Returns `true` if the message is an answer the server cut off at the
+    /// token limit, rather than one the user stopped.
+    #[must_use]
+    pub fn is_cut_off(&self) -> bool {
+        self.role == "assistant"
+            && !self.stopped
+            && self
+                .metadata
+                .as_ref()
+                .is_some_and(Metadata::hit_token_limit)
+    }
+
+    /// Marks the message, or removes the mark if it has it. A message is
+    /// not both good and bad: marking it one removes the other.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::{Mark, Message};
+    ///
+    /// let mut message = Message::new("assistant", "42");
+    /// message.toggle_mark(Mark::Favorite);
+    /// message.toggle_mark(Mark::Bad);
+    /// message.toggle_mark(Mark::Good);
+    /// assert_eq!(message.marks, vec![Mark::Good, Mark::Favorite]);
+    /// message.toggle_mark(Mark::Favorite);
+    /// assert_eq!(message.marks, vec![Mark::Good]);
+    /// ```
+    pub fn toggle_mark(&mut self, mark: Mark) {
+        if let Some(index) = self.marks.iter().position(|known| *known == mark) {
+            self.marks.remove(index);
+            return;
+        }
+        self.marks.retain(|known| match mark {
+            Mark::Good => *known != Mark::Bad,
+            Mark::Bad => *known != Mark::Good,
+            Mark::Favorite => true,
+        });
+        self.marks.push(mark);
+        self.marks.sort_unstable();
+    }
+
+    /// Returns `true` if the message is part of the conversation sent with
+    /// prompts: a user message or an answer that is not empty, not a
+    /// translation and not a prompt whose request was cancelled.
+    #[must_use]
+    pub fn is_sent(&self) -> bool {
+        (self.role == "user" || self.role == "assistant")
+            && !self.content.is_empty()
+            && self.translation.is_none()
+            && !self.cancelled
+    }
+}
+
+/// A mark the user put on a message, to curate prompts and answers.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Mark {
+    /// A good prompt or answer, marked with `+`.
+    Good,
+    /// A bad prompt or answer, marked with `-`.
+    Bad,
+    /// A favorite, marked with `*`.
+    Favorite,
+}
+
+impl Mark {
+    /// Every mark, in order.
+    pub const ALL: [Mark; 3] = [Mark::Good, Mark::Bad, Mark::Favorite];
+
+    /// Returns the mark with the given name, `good`, `bad` or `favorite`,
+    /// which may be shortened to `fav`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::Mark;
+    ///
+    /// assert_eq!(Mark::named("Good"), Some(Mark::Good));
+    /// assert_eq!(Mark::named("fav"), Some(Mark::Favorite));
+    /// assert_eq!(Mark::named("meh"), None);
+    /// ```
+    #[must_use]
+    pub fn named(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "good" => Some(Mark::Good),
+            "bad" => Some(Mark::Bad),
+            "favorite" | "fav" => Some(Mark::Favorite),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the mark.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Mark::Good => "good",
+            Mark::Bad => "bad",
+            Mark::Favorite => "favorite",
+        }
+    }
+
+    /// Returns the symbol shown for the mark.
+    #[must_use]
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Mark::Good => "✓",
+            Mark::Bad => "✗",
+            Mark::Favorite => "★",
+        }
+    }
+}
+
+/// Which exchanges of a conversation to keep by their marks. An exchange is
+/// a prompt with the messages after it, up to the next prompt, and it has
+/// the marks of any of its messages.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MarkFilter {
+    /// Exchanges are kept only if they have one of these marks, unless it
+    /// is empty.
+    pub only: Vec<Mark>,
+    /// Exchanges with one of these marks are left out.
+    pub skip: Vec<Mark>,
+}
+
+impl MarkFilter {
+    /// Parses a filter from words such as `+good` or `-bad`: `+` keeps only
+    /// exchanges with the mark and `-` leaves them out. With `bare` set, a
+    /// name without a sign keeps the exchanges with the mark. A `--` word
+    /// ends the filter, so the words after it can start with a sign.
+    ///
+    /// # Returns
+    ///
+    /// The filter and the words after the leading filter words.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message naming the word if a mark is unknown.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::{Mark, MarkFilter};
+    ///
+    /// let (filter, rest) = MarkFilter::parse("+good -bad notes.md", false).unwrap();
+    /// assert_eq!(filter.only, vec![Mark::Good]);
+    /// assert_eq!(filter.skip, vec![Mark::Bad]);
+    /// assert_eq!(rest, "notes.md");
+    /// assert!(MarkFilter::parse("+great", false).is_err());
+    ///
+    /// let (filter, rest) = MarkFilter::parse("+good -- -draft.md", false).unwrap();
+    /// assert_eq!(filter.only, vec![Mark::Good]);
+    /// assert_eq!(rest, "-draft.md");
+    /// ```
+    pub fn parse(args: &str, bare: bool) -> Result<(Self, &str), String> {
+        let mut filter = MarkFilter::default();
+        let mut rest = args.trim_start();
+        while let Some(word) = rest.split_whitespace().next() {
+            if word == "--" {
+                rest = rest[word.len()..].trim_start();
+                break;
+            }
+            let (list, name) = if let Some(name) = word.strip_prefix('+') {
+                (&mut filter.only, name)
+            } else if let Some(name) = word.strip_prefix('-') {
+                (&mut filter.skip, name)
+            } else if bare {
+                (&mut filter.only, word)
+            } else {
+                break;
+            };
+            let mark = Mark::named(name).ok_or_else(|| {
+                format!(
+                    "Unknown mark: {word}; the marks are good, bad and favorite \
+                     (put -- before a path starting with + or -)"
+                )
+            })?;
+            if !list.contains(&mark) {
+                list.push(mark);
+            }
+            rest = rest[word.len()..].trim_start();
+        }
+        Ok((filter, rest))
+    }
+
+    /// Returns `true` if the filter keeps every exchange.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.skip.is_empty()
+    }
+
+    /// Returns the messages of the exchanges the filter keeps, as they are
+    /// given: owned or borrowed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::{Mark, MarkFilter, Message};
+    ///
+    /// let mut messages = vec![
+    ///     Message::new("user", "1 + 1?"),
+    ///     Message::new("assistant", "2"),
+    ///     Message::new("user", "2 + 2?"),
+    ///     Message::new("assistant", "5"),
+    /// ];
+    /// messages[1].toggle_mark(Mark::Good);
+    /// let filter = MarkFilter { only: vec![Mark::Good], skip: Vec::new() };
+    /// let kept = filter.apply(&messages);
+    /// assert_eq!(kept.len(), 2);
+    /// assert_eq!(kept[1].content, "2");
+    /// ```
+    #[must_use]
+    pub fn apply<T: Borrow<Message> + Clone>(&self, messages: &[T]) -> Vec<T> {
+        let mut kept = Vec::new();
+        let mut start = 0;
+        while start < messages.len() {
+            let end = messages[start + 1..]
+                .iter()
+                .position(|message| message.borrow().role == "user")
+                .map_or(messages.len(), |offset| start + 1 + offset);
+            let exchange = &messages[start..end];
+            let has = |marks: &[Mark]| {
+                exchange.iter().any(|message| {
+                    message
+                        .borrow()
+                        .marks
+                        .iter()
+                        .any(|mark| marks.contains(mark))
+                })
+            };
+            if (self.only.is_empty() || has(&self.only)) && !has(&self.skip) {
+                kept.extend_from_slice(exchange);
+            }
+            start = end;
+        }
+        kept
+    }
+
+    /// Describes the filter, such as `marked ✓ good, without ✗ bad`.
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let list = |marks: &[Mark]| {
+            marks
+                .iter()
+                .map(|mark| format!("{} {}", mark.symbol(), mark.name()))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        };
+        let mut parts = Vec::new();
+        if !self.only.is_empty() {
+            parts.push(format!("marked {}", list(&self.only)));
+        }
+        if !self.skip.is_empty() {
+            parts.push(format!("without {}", list(&self.skip)));
+        }
+        parts.join(", ")
+    }
+}
+
+/// The model and sampling parameters that produced an answer.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Generation {
+    /// The model that answered.
+    pub model: String,
+    /// The temperature.
+    pub temperature: f32,
+    /// The nucleus sampling threshold, if one was sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f32>,
+    /// The response token limit, if one was sent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+}
+
+impl Generation {
+    /// Returns the model and parameters requests are sent with.
+    #[must_use]
+    pub fn from_config(config: &Config) -> Self {
+        Generation {
+            model: config.model.clone(),
+            temperature: config.temperature,
+            top_p: config.top_p,
+            max_tokens: config.max_tokens,
+        }
+    }
+
+    /// Describes the model and the parameters that were sent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::Generation;
+    ///
+    /// let generation = Generation {
+    ///     model: "llama3".to_string(),
+    ///     temperature: 0.9,
+    ///     top_p: None,
+    ///     max_tokens: Some(512),
+    /// };
+    /// assert_eq!(
+    ///     generation.describe(),
+    ///     "llama3, temperature 0.90, max_tokens 512"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let mut text = format!("{}, temperature {:.2}", self.model, self.temperature);
+        if let Some(top_p) = self.top_p {
+            text.push_str(&format!(", top_p {top_p:.2}"));
+        }
+        if let Some(max_tokens) = self.max_tokens {
+            text.push_str(&format!(", max_tokens {max_tokens}"));
+        }
+        text
+    }
+}
+
+/// What the server reported about the request that produced an answer, and
+/// how long the answer took. Servers report different subsets, so every
+/// field is optional.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct Metadata {
+    /// The number of tokens of the prompt, including the conversation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_tokens: Option<u64>,
+    /// The number of tokens of the answer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completion_tokens: Option<u64>,
+    /// The time from sending the request to the first part of the answer,
+    /// in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_token_ms: Option<u64>,
+    /// The time from sending the request to the end of the answer, in
+    /// milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_ms: Option<u64>,
+    /// Why the server ended the answer, such as `stop` or `length`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<String>,
+    /// The identifier the server gave the request.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+impl Metadata {
+    /// Takes the fields `other` has, keeping the others.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::Metadata;
+    ///
+    /// let mut metadata = Metadata {
+    ///     total_ms: Some(900),
+    ///     ..Metadata::default()
+    /// };
+    /// metadata.merge(Metadata {
+    ///     finish_reason: Some("stop".to_string()),
+    ///     ..Metadata::default()
+    /// });
+    /// assert_eq!(metadata.total_ms, Some(900));
+    /// assert_eq!(metadata.finish_reason.as_deref(), Some("stop"));
+    /// ```
+    pub fn merge(&mut self, other: Metadata) {
+        self.prompt_tokens = other.prompt_tokens.or(self.prompt_tokens);
+        self.completion_tokens = other.completion_tokens.or(self.completion_tokens);
+        self.first_token_ms = other.first_token_ms.or(self.first_token_ms);
+        self.total_ms = other.total_ms.or(self.total_ms);
+        self.finish_reason = other.finish_reason.or(self.finish_reason.take());
+        self.request_id = other.request_id.or(self.request_id.take());
+    }
+
+    /// Returns `true` if the answer ended because it reached the token
+    /// limit: `length` from Ollama and OpenAI-compatible servers, `limit`
+    /// from llama.cpp.
+    #[must_use]
+    pub fn hit_token_limit(&self) -> bool {
+        matches!(self.finish_reason.as_deref(), Some("length" | "limit"))
+    }
+
+    /// Adds what the server reported about the continuation of an answer:
+    /// the completion tokens and total time add up, and the finish reason
+    /// and request id become the continuation's.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::Metadata;
+    ///
+    /// let mut metadata = Metadata {
+    ///     completion_tokens: Some(256),
+    ///     total_ms: Some(4000),
+    ///     finish_reason: Some("length".to_string()),
+    ///     ..Metadata::default()
+    /// };
+    /// assert!(metadata.hit_token_limit());
+    /// metadata.extend(Metadata {
+    ///     completion_tokens: Some(80),
+    ///     total_ms: Some(1500),
+    ///     finish_reason: Some("stop".to_string()),
+    ///     ..Metadata::default()
+    /// });
+    /// assert_eq!(metadata.completion_tokens, Some(336));
+    /// assert_eq!(metadata.total_ms, Some(5500));
+    /// assert!(!metadata.hit_token_limit());
+    /// ```
+    pub fn extend(&mut self, continuation: Metadata) {
+        let add = |earlier: Option<u64>, later: Option<u64>| match (earlier, later) {
+            (Some(earlier), Some(later)) => Some(earlier + later),
+            (earlier, later) => earlier.or(later),
+        };
+        self.completion_tokens = add(self.completion_tokens, continuation.completion_tokens);
+        self.total_ms = add(self.total_ms, continuation.total_ms);
+        self.prompt_tokens = self.prompt_tokens.or(continuation.prompt_tokens);
+        self.first_token_ms = self.first_token_ms.or(continuation.first_token_ms);
+        self.finish_reason = continuation.finish_reason;
+        self.request_id = continuation.request_id.or(self.request_id.take());
+    }
+}
@@ -0,0 +1,300 @@
+//! Manages local models through the Ollama management API.
+//!
+//! The management endpoints live next to the chat endpoint, so their URLs
+//! are derived from the origin of the configured `api_endpoint`.
+
+use futures_util::StreamExt;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::mpsc;
+
+use crate::api;
+
+/// Represents errors that can occur while managing models.
+#[derive(Debug, thiserror::Error)]
+pub enum ModelError {
+    /// The configured endpoint is not a valid URL.
+    #[error("invalid API endpoint: {0}")]
+    Endpoint(String),
+
+    /// The request could not be sent or its response could not be read.
+    #[error("request failed: {0}")]
+    Network(#[from] reqwest::Error),
+
+    /// The server answered with an error.
+    #[error("{0}")]
+    Server(String),
+}
+
+/// A `/models` subcommand.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ModelCommand {
+    /// Download a model (`/models pull <name>`).
+    Pull(String),
+    /// Delete a model (`/models rm <name>`).
+    Remove(String),
+    /// List the models loaded into memory (`/models ps`).
+    Running,
+}
+
+/// A progress update streamed while a model is pulled.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+pub struct PullProgress {
+    /// What the server is doing, such as `pulling manifest` or `success`.
+    #[serde(default)]
+    pub status: String,
+    /// The size of the layer being downloaded in bytes.
+    pub total: Option<u64>,
+    /// The number of bytes of the layer downloaded so far.
+    pub completed: Option<u64>,
+}
+
+impl PullProgress {
+    /// Returns how much of the current layer has been downloaded, if known.
+    #[must_use]
+    pub fn fraction(&self) -> Option<f64> {
+        match (self.completed, self.total) {
+            #[allow(clippy::cast_precision_loss)]
+            (Some(completed), Some(total)) if total > 0 => {
+                Some((completed as f64 / total as f64).min(1.0))
+            }
+            _ => None,
+        }
+    }
+
+    /// Describes the progress of a model pull task: what the server is
+    /// doing, and how much of the layer has been downloaded while one is.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::models::PullProgress;
+    ///
+    /// let progress = PullProgress {
+    ///     status: "pulling 6a0746a1ec1a".to_string(),
+    ///     total: Some(4_000_000_000),
+    ///     completed: Some(1_000_000_000),
+    /// };
+    /// assert_eq!(progress.describe(), "pulling 6a0746a1ec1a · 1.0 GB / 4.0 GB");
+    /// ```
+    #[must_use]
+    pub fn describe(&self) -> String {
+        match (self.fraction(), self.completed, self.total) {
+            (Some(_), Some(completed), Some(total)) => format!(
+                "{} · {} / {}",
+                self.status,
+                format_size(completed),
+                format_size(total)
+            ),
+            _ => self.status.clone(),
+        }
+    }
+}
+
+/// A model loaded into memory, as listed by `/api/ps`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct RunningModel {
+    /// The name of the model.
+    pub name: String,
+    /// The memory the model takes up in bytes.
+    #[serde(default)]
+    pub size: u64,
+    /// The part of `size` held in video memory.
+    #[serde(default)]
+    pub size_vram: u64,
+    /// When the model will be unloaded, as an RFC 3339 timestamp.
+    #[serde(default)]
+    pub expires_at: String,
+}
+
+/// Pulls a model, sending progress updates as they are streamed.
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client.
+/// * `endpoint` - The configured API endpoint.
+/// * `name` - The model to pull.
+/// * `progress` - Receives the progress updates.
+///
+/// # Errors
+///
+/// Returns a `ModelError` if the request fails or the server reports an
+/// error, for example for an unknown model.
+pub async fn pull(
+    client: &reqwest::Client,
+    endpoint: &str,
+    name: &str,
+    progress: mpsc::Sender<PullProgress>,
+) -> Result<(), ModelError> {
+    let response = client
+        .post(management_url(endpoint, "api/pull")?)
+        .json(&json!({"model": name, "stream": true}))
+        .send()
+        .await?;
+    let response = check_status(response).await?;
+
+    let mut stream = response.bytes_stream();
+    let mut lines = api::JsonLines::new();
+    while let Some(chunk) = stream.next().await {
+        for json in lines.push(&chunk?).into_iter().flatten() {
+            report_progress(json, &progress).await?;
+        }
+    }
+    if let Some(Ok(json)) = lines.finish() {
+        report_progress(json, &progress).await?;
+    }
+    Ok(())
+}
+
+/// Sends a line of the pull stream as a progress update.
+///
+/// # Errors
+///
+/// Returns a `ModelError` if the line reports an error.
+async fn report_progress(
+    json: serde_json::Value,
+    progress: &mpsc::Sender<PullProgress>,
+) -> Result<(), ModelError> {
+    if let Some(error) = json["error"].as_str() {
+        return Err(ModelError::Server(error.to_string()));
+    }
+    if let Ok(update) = serde_json::from_value::<PullProgress>(json) {
+        let _ = progress.send(update).await;
+    }
+    Ok(())
+}
+
+/// Deletes a model from the server.
+///
+/// # Errors
+///
+/// Returns a `ModelError` if the request fails or the model does not exist.
+pub async fn delete(
+    client: &reqwest::Client,
+    endpoint: &str,
+    name: &str,
+) -> Result<(), ModelError> {
+    let response = client
+        .delete(management_url(endpoint, "api/delete")?)
+        .json(&json!({"model": name}))
+        .send()
+        .await?;
+    check_status(response).await?;
+    Ok(())
+}
+
+/// Lists the models currently loaded into memory.
+///
+/// # Errors
+///
+/// Returns a `ModelError` if the request fails or the response cannot be
+/// parsed.
+pub async fn running(
+    client: &reqwest::Client,
+    endpoint: &str,
+) -> Result<Vec<RunningModel>, ModelError> {
+    #[derive(Deserialize)]
+    struct Running {
+        #[serde(default)]
+        models: Vec<RunningModel>,
+    }
+
+    let response = client
+        .get(management_url(endpoint, "api/ps")?)
+        .send()
+        .await?;
+    Ok(check_status(response)
+        .await?
+        .json::<Running>()
+        .await?
+        .models)
+}
+
+/// Formats the running models as a markdown list.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::models::{describe_running, RunningModel};
+///
+/// assert_eq!(describe_running(&[]), "No models are loaded");
+/// let model = RunningModel {
+///     name: "llama3:8b".to_string(),
+///     size: 6_000_000_000,
+///     size_vram: 3_000_000_000,
+///     expires_at: "2024-06-04T14:38:31Z".to_string(),
+/// };
+/// assert_eq!(
+///     describe_running(&[model]),
+///     "Loaded models:\n\n- **llama3:8b**: 6.0 GB, 50% GPU, until 2024-06-04T14:38:31Z"
+/// );
+/// ```
+#[must_use]
+pub fn describe_running(models: &[RunningModel]) -> String {
+    if models.is_empty() {
+        return String::from("No models are loaded");
+    }
+    let mut text = String::from("Loaded models:\n");
+    for model in models {
+        #[allow(clippy::cast_precision_loss)]
+        let gpu = if model.size > 0 {
+            model.size_vram as f64 / model.size as f64 * 100.0
+        } else {
+            0.0
+        };
+        text.push_str(&format!(
+            "\n- **{}**: {}, {gpu:.0}% GPU",
+            model.name,
+            format_size(model.size)
+        ));
+        if !model.expires_at.is_empty() {
+            text.push_str(&format!(", until {}", model.expires_at));
+        }
+    }
+    text
+}
+
+/// Formats a number of bytes with a decimal unit.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::models::format_size;
+///
+/// assert_eq!(format_size(512), "512 B");
+/// assert_eq!(format_size(1_500_000), "1.5 MB");
+/// ```
+#[must_use]
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let mut size = bytes as f64 / 1000.0;
+    let mut unit = 0;
+    while size >= 1000.0 && unit < UNITS.len() - 1 {
+        size /= 1000.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+fn management_url(endpoint: &str, path: &str) -> Result<reqwest::Url, ModelError> {
+    reqwest::Url::parse(endpoint)
+        .and_then(|url| url.join("/"))
+        .and_then(|origin| origin.join(path))
+        .map_err(|_| ModelError::Endpoint(endpoint.to_string()))
+}
+
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, ModelError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(ModelError::Server(
+        api::parse_error_message(&body)
+            .unwrap_or_else(|| format!("the server responded with {status}")),
+    ))
+}
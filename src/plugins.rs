@@ -0,0 +1,503 @@
+//! Runs plugins: executables that react to what happens in chatti.
+//!
+//! Every executable file in `~/.config/chatti/plugins`, or the configured
+//! directory, is a plugin. Chatti runs it once for each event, with the
+//! event as a JSON object on standard input, and reads the actions it asks
+//! for from its standard output, one JSON object per line.
+//!
+//! The events are:
+//!
+//! - `{"event": "load"}`, when chatti starts or the plugins are reloaded,
+//! - `{"event": "message_sent", "content": ..., "model": ...}`, when a
+//!   prompt is sent,
+//! - `{"event": "message_received", "content": ..., "model": ...}`, when an
+//!   answer is complete,
+//! - `{"event": "command", "name": ..., "args": ...}`, when one of the
+//!   plugin's commands is entered.
+//!
+//! The actions are:
+//!
+//! - `{"action": "insert_text", "text": ...}`, which adds text to the input,
+//! - `{"action": "add_message", "content": ...}`, which shows a notice in
+//!   the transcript,
+//! - `{"action": "register_command", "name": ..., "description": ...}`,
+//!   which adds a slash command, in reply to the `load` event.
+//!
+//! Plugins run in the background, so a slow one does not hold up the chat;
+//! one that fails, or takes longer than the timeout, is logged and stopped.
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    process::Command,
+    thread,
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+use tracing::{error, info};
+
+use crate::commands::COMMANDS;
+use crate::process;
+
+/// Something that happened in chatti, sent to plugins.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    /// The plugins were loaded.
+    Load,
+    /// A prompt was sent.
+    MessageSent {
+        /// The prompt.
+        content: String,
+        /// The model it was sent to.
+        model: String,
+    },
+    /// An answer is complete.
+    MessageReceived {
+        /// The answer.
+        content: String,
+        /// The model that wrote it.
+        model: String,
+    },
+    /// A command registered by the plugin was entered.
+    Command {
+        /// The name of the command, without the `/`.
+        name: String,
+        /// What follows the name.
+        args: String,
+    },
+}
+
+/// Something a plugin asks chatti to do.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    /// Add text to the input.
+    InsertText {
+        /// The text to add.
+        text: String,
+    },
+    /// Show a notice in the transcript.
+    AddMessage {
+        /// The text of the notice.
+        content: String,
+    },
+    /// Add a slash command, sent back to the plugin as a `command` event.
+    RegisterCommand {
+        /// The name of the command, without the `/`.
+        name: String,
+        /// What the command does, shown by `/plugins`.
+        #[serde(default)]
+        description: String,
+    },
+}
+
+/// An action asked for by a plugin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    /// The name of the plugin.
+    pub plugin: String,
+    /// The action.
+    pub action: Action,
+}
+
+/// Represents errors that can occur while running a plugin.
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    /// The plugin could not be started or fed its input.
+    #[error("could not run plugin `{plugin}`: {source}")]
+    Io {
+        /// The name of the plugin.
+        plugin: String,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+
+    /// The plugin exited unsuccessfully.
+    #[error("plugin `{plugin}` failed ({status}): {stderr}")]
+    Failed {
+        /// The name of the plugin.
+        plugin: String,
+        /// The exit status of the plugin.
+        status: String,
+        /// What the plugin wrote to standard error.
+        stderr: String,
+    },
+
+    /// The plugin did not exit in time and was stopped.
+    #[error("plugin `{plugin}` took longer than {seconds}s and was stopped")]
+    TimedOut {
+        /// The name of the plugin.
+        plugin: String,
+        /// The seconds it was given.
+        seconds: u64,
+    },
+
+    /// The plugin wrote a line that is not an action.
+    #[error("plugin `{plugin}` wrote an invalid action `{line}`: {source}")]
+    Action {
+        /// The name of the plugin.
+        plugin: String,
+        /// The line it wrote.
+        line: String,
+        /// The underlying error.
+        source: serde_json::Error,
+    },
+
+    /// The plugin registered a command chatti already has.
+    #[error("plugin `{plugin}` cannot register /{name}, which is already a command")]
+    Command {
+        /// The name of the plugin.
+        plugin: String,
+        /// The name of the command.
+        name: String,
+    },
+}
+
+/// A slash command registered by a plugin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PluginCommand {
+    /// The name of the command, without the `/`.
+    pub name: String,
+    /// What the command does.
+    pub description: String,
+}
+
+/// An executable run on events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plugin {
+    /// The file name of the executable.
+    pub name: String,
+    /// The path of the executable.
+    pub path: PathBuf,
+    /// The commands it registered.
+    pub commands: Vec<PluginCommand>,
+}
+
+impl Plugin {
+    /// Runs the plugin with an event, waiting for it to exit.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event written to its standard input.
+    /// * `timeout` - How long the plugin may run before it is stopped.
+    ///
+    /// # Returns
+    ///
+    /// The actions the plugin asked for.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `PluginError` if the plugin cannot be started, exits
+    /// unsuccessfully, runs out of time or writes something other than
+    /// actions.
+    pub fn run(&self, event: &Event, timeout: Duration) -> Result<Vec<Action>, PluginError> {
+        let mut input = serde_json::to_string(event).unwrap_or_default();
+        input.push('\n');
+        let output = process::run(
+            &mut Command::new(&self.path),
+            Some(input.into_bytes()),
+            timeout,
+        )
+        .map_err(|source| match source.kind() {
+            ErrorKind::TimedOut => PluginError::TimedOut {
+                plugin: self.name.clone(),
+                seconds: timeout.as_secs(),
+            },
+            _ => PluginError::Io {
+                plugin: self.name.clone(),
+                source,
+            },
+        })?;
+        let process::Output {
+            status,
+            stdout,
+            stderr,
+        } = output;
+
+        if !status.success() {
+            return Err(PluginError::Failed {
+                plugin: self.name.clone(),
+                status: status.to_string(),
+                stderr: stderr.trim().to_string(),
+            });
+        }
+        stdout
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|source| PluginError::Action {
+                    plugin: self.name.clone(),
+                    line: line.to_string(),
+                    source,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Returns the directory plugins are loaded from by default,
+/// `~/.config/chatti/plugins`.
+#[must_use]
+pub fn default_directory() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".config")
+        .join("chatti")
+        .join("plugins")
+}
+
+/// Returns the executable files in a directory, sorted by name. A missing
+/// directory has none.
+///
+/// # Errors
+///
+/// Returns an error if the directory exists but cannot be read.
+pub fn find(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| is_executable(path))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Returns `true` if the path is a file that may be run.
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+/// Returns `true` if the path is a file that may be run.
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+/// The loaded plugins, and the channel their actions are sent through.
+#[derive(Debug)]
+pub struct Plugins {
+    /// The plugins, sorted by name.
+    pub plugins: Vec<Plugin>,
+    /// How long a plugin may run before it is stopped.
+    timeout: Duration,
+    /// Where the actions of plugins, and the failures of their commands,
+    /// are sent.
+    tx: mpsc::UnboundedSender<Result<Reply, PluginError>>,
+}
+
+impl Plugins {
+    /// Creates a manager without plugins.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - How long a plugin may run before it is stopped.
+    ///
+    /// # Returns
+    ///
+    /// The manager, and the receiver of the actions plugins ask for.
+    #[must_use]
+    pub fn new(timeout: Duration) -> (Self, mpsc::UnboundedReceiver<Result<Reply, PluginError>>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let plugins = Plugins {
+            plugins: Vec::new(),
+            timeout,
+            tx,
+        };
+        (plugins, rx)
+    }
+
+    /// Loads the plugins of a directory, replacing those loaded before, and
+    /// runs each with the `load` event to register its commands. Other
+    /// actions asked for are sent on as usual.
+    ///
+    /// # Returns
+    ///
+    /// The errors of the plugins that could not be loaded, which are left
+    /// out.
+    pub fn load(&mut self, dir: &Path) -> Vec<PluginError> {
+        let paths = match find(dir) {
+            Ok(paths) => paths,
+            Err(source) => {
+                self.plugins.clear();
+                return vec![PluginError::Io {
+                    plugin: dir.display().to_string(),
+                    source,
+                }];
+            }
+        };
+        let timeout = self.timeout;
+        let loading: Vec<_> = paths
+            .into_iter()
+            .map(|path| {
+                let plugin = Plugin {
+                    name: path
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                        .into_owned(),
+                    path,
+                    commands: Vec::new(),
+                };
+                thread::spawn(move || {
+                    let actions = plugin.run(&Event::Load, timeout);
+                    (plugin, actions)
+                })
+            })
+            .collect();
+
+        let mut plugins: Vec<Plugin> = Vec::new();
+        let mut errors = Vec::new();
+        for loaded in loading {
+            let Ok((mut plugin, actions)) = loaded.join() else {
+                continue;
+            };
+            let actions = match actions {
+                Ok(actions) => actions,
+                Err(err) => {
+                    errors.push(err);
+                    continue;
+                }
+            };
+            for action in actions {
+                let Action::RegisterCommand { name, description } = action else {
+                    self.send(&plugin.name, action);
+                    continue;
+                };
+                let taken = COMMANDS.iter().any(|command| command.name == name)
+                    || plugins
+                        .iter()
+                        .chain([&plugin])
+                        .any(|other| other.commands.iter().any(|c| c.name == name));
+                if taken || name.is_empty() || name.contains(char::is_whitespace) {
+                    errors.push(PluginError::Command {
+                        plugin: plugin.name.clone(),
+                        name,
+                    });
+                } else {
+                    plugin.commands.push(PluginCommand { name, description });
+                }
+            }
+            info!(plugin = plugin.name, "plugin loaded");
+            plugins.push(plugin);
+        }
+        self.plugins = plugins;
+        errors
+    }
+
+    /// Runs every plugin with an event in the background, sending on the
+    /// actions they ask for and logging their failures.
+    pub fn notify(&self, event: Event) {
+        if self.plugins.is_empty() {
+            return;
+        }
+        let plugins = self.plugins.clone();
+        let timeout = self.timeout;
+        let tx = self.tx.clone();
+        thread::spawn(move || {
+            for plugin in &plugins {
+                match plugin.run(&event, timeout) {
+                    Ok(actions) => {
+                        for action in actions {
+                            send(&tx, &plugin.name, action);
+                        }
+                    }
+                    Err(err) => error!(%err, "plugin failed"),
+                }
+            }
+        });
+    }
+
+    /// Runs the plugin that registered the command entered, if any, in the
+    /// background. Its failure is sent on, to be shown.
+    ///
+    /// # Arguments
+    ///
+    /// * `input` - The input, such as `/weather Oslo`.
+    ///
+    /// # Returns
+    ///
+    /// `true` if a plugin registered the command.
+    pub fn invoke(&self, input: &str) -> bool {
+        let Some(rest) = input.trim().strip_prefix('/') else {
+            return false;
+        };
+        let (name, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let Some(plugin) = self
+            .plugins
+            .iter()
+            .find(|plugin| plugin.commands.iter().any(|command| command.name == name))
+        else {
+            return false;
+        };
+        let plugin = plugin.clone();
+        let event = Event::Command {
+            name: name.to_string(),
+            args: args.trim().to_string(),
+        };
+        let timeout = self.timeout;
+        let tx = self.tx.clone();
+        thread::spawn(move || match plugin.run(&event, timeout) {
+            Ok(actions) => {
+                for action in actions {
+                    send(&tx, &plugin.name, action);
+                }
+            }
+            Err(err) => {
+                let _ = tx.send(Err(err));
+            }
+        });
+        true
+    }
+
+    /// Describes the loaded plugins and their commands, for `/plugins`.
+    #[must_use]
+    pub fn describe(&self, dir: &Path) -> String {
+        if self.plugins.is_empty() {
+            return format!(
+                "No plugins are loaded; executables in {} are run as plugins",
+                dir.display()
+            );
+        }
+        let mut text = format!("Plugins in {}:", dir.display());
+        for plugin in &self.plugins {
+            text.push_str(&format!("\n- {}", plugin.name));
+            for command in &plugin.commands {
+                let line = format!("\n  /{} {}", command.name, command.description);
+                text.push_str(line.trim_end());
+            }
+        }
+        text
+    }
+
+    /// Sends on an action asked for by a plugin.
+    fn send(&self, plugin: &str, action: Action) {
+        send(&self.tx, plugin, action);
+    }
+}
+
+/// Sends on an action, ignoring commands registered outside of the `load`
+/// event.
+fn send(tx: &mpsc::UnboundedSender<Result<Reply, PluginError>>, plugin: &str, action: Action) {
+    if let Action::RegisterCommand { name, .. } = &action {
+        error!(plugin, name, "commands can only be registered on load");
+        return;
+    }
+    let _ = tx.send(Ok(Reply {
+        plugin: plugin.to_string(),
+        action,
+    }));
+}
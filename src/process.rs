@@ -0,0 +1,149 @@
+//! Runs external programs with a deadline.
+//!
+//! The output pipes are drained and the input written on their own threads
+//! while the deadline runs, so a program that fills a pipe, or never reads
+//! its input, cannot block past it. A program still running when the
+//! deadline passes, or when it is cancelled, is killed.
+
+use std::io::{self, ErrorKind, Read, Write};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// What a program that finished in time wrote and how it exited.
+#[derive(Debug)]
+pub struct Output {
+    /// How the program exited.
+    pub status: ExitStatus,
+    /// What it wrote to its standard output.
+    pub stdout: String,
+    /// What it wrote to its standard error.
+    pub stderr: String,
+}
+
+/// Runs a command to completion, killing it if it runs out of time.
+///
+/// # Arguments
+///
+/// * `command` - The command to run; its standard streams are replaced.
+/// * `input` - What to write to its standard input, or `None` to give it
+///   none.
+/// * `timeout` - How long it may run.
+///
+/// # Errors
+///
+/// Returns an error of kind `ErrorKind::TimedOut` if the command ran out
+/// of time, or the error starting or waiting for it.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::process;
+/// use std::io::ErrorKind;
+/// use std::process::Command;
+/// use std::time::Duration;
+///
+/// let mut cat = Command::new("cat");
+/// let output = process::run(&mut cat, Some(b"hi".to_vec()), Duration::from_secs(5)).unwrap();
+/// assert_eq!(output.stdout, "hi");
+///
+/// let mut sleep = Command::new("sleep");
+/// sleep.arg("5");
+/// let err = process::run(&mut sleep, None, Duration::from_millis(50)).unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::TimedOut);
+/// ```
+pub fn run(command: &mut Command, input: Option<Vec<u8>>, timeout: Duration) -> io::Result<Output> {
+    run_cancellable(command, input, timeout, &AtomicBool::new(false))
+}
+
+/// Runs a command to completion like [`run`], also killing it once
+/// `cancelled` is set.
+///
+/// # Errors
+///
+/// Returns an error of kind `ErrorKind::Interrupted` if the command was
+/// cancelled, besides the errors of [`run`].
+///
+/// # Examples
+///
+/// ```
+/// use chatti::process;
+/// use std::io::ErrorKind;
+/// use std::process::Command;
+/// use std::sync::atomic::AtomicBool;
+/// use std::time::Duration;
+///
+/// let mut sleep = Command::new("sleep");
+/// sleep.arg("5");
+/// let cancelled = AtomicBool::new(true);
+/// let err = process::run_cancellable(&mut sleep, None, Duration::from_secs(5), &cancelled)
+///     .unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::Interrupted);
+/// ```
+pub fn run_cancellable(
+    command: &mut Command,
+    input: Option<Vec<u8>>,
+    timeout: Duration,
+    cancelled: &AtomicBool,
+) -> io::Result<Output> {
+    let mut child = command
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let read = |pipe: Option<Box<dyn Read + Send>>| {
+        thread::spawn(move || {
+            let mut bytes = Vec::new();
+            if let Some(mut pipe) = pipe {
+                let _ = pipe.read_to_end(&mut bytes);
+            }
+            String::from_utf8_lossy(&bytes).into_owned()
+        })
+    };
+    let stdout = read(child.stdout.take().map(|pipe| Box::new(pipe) as _));
+    let stderr = read(child.stderr.take().map(|pipe| Box::new(pipe) as _));
+    let writer = child.stdin.take().zip(input).map(|(mut stdin, input)| {
+        thread::spawn(move || match stdin.write_all(&input) {
+            // A program that exits without reading its input is not an error.
+            Err(err) if err.kind() != ErrorKind::BrokenPipe => Err(err),
+            _ => Ok(()),
+        })
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        let stopped = if cancelled.load(Ordering::Relaxed) {
+            Some(io::Error::new(ErrorKind::Interrupted, "cancelled"))
+        } else if Instant::now() >= deadline {
+            Some(io::Error::new(
+                ErrorKind::TimedOut,
+                format!("did not finish within {} second(s)", timeout.as_secs()),
+            ))
+        } else {
+            None
+        };
+        if let Some(err) = stopped {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(err);
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    if let Some(writer) = writer {
+        writer.join().unwrap_or(Ok(()))?;
+    }
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
@@ -0,0 +1,137 @@
+//! Project mode: settings for the git repository chatti is started in.
+//!
+//! When the repository root holds a `.chatti.toml`, its model, system prompt
+//! and attachments are used, and sessions are stored apart from the global
+//! ones:
+//!
+//! ```toml
+//! model = "qwen2.5-coder"
+//! system = "You are working on a Rust TUI chat client."
+//! attach = ["README.md", "src/lib.rs"]
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    config::{Config, FSError, ProjectConfig, TemplateConfig},
+    templates::{self, Started, TemplateError},
+};
+
+/// The name of the project settings file at the repository root.
+pub const FILE_NAME: &str = ".chatti.toml";
+
+/// Returns the root of the git repository containing `dir`, if any.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::project::find_root;
+///
+/// assert_eq!(find_root(std::path::Path::new("/")), None);
+/// ```
+#[must_use]
+pub fn find_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|ancestor| ancestor.join(".git").exists())
+        .map(Path::to_path_buf)
+}
+
+/// A git repository with project settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Project {
+    /// The root of the repository.
+    pub root: PathBuf,
+    /// The settings read from `.chatti.toml`.
+    pub config: ProjectConfig,
+}
+
+impl Project {
+    /// Looks for project settings in the repository containing `dir`.
+    ///
+    /// # Returns
+    ///
+    /// The project, or `None` outside a repository or if the repository has
+    /// no `.chatti.toml`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the settings file cannot be read or parsed.
+    pub fn detect(dir: &Path) -> Result<Option<Self>, FSError> {
+        let Some(root) = find_root(&dir.canonicalize()?) else {
+            return Ok(None);
+        };
+        let text = match fs::read_to_string(root.join(FILE_NAME)) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        Ok(Some(Project {
+            root,
+            config: toml::from_str(&text)?,
+        }))
+    }
+
+    /// Returns the name of the project: the name of the repository root.
+    #[must_use]
+    pub fn name(&self) -> String {
+        self.root.file_name().map_or_else(
+            || self.root.display().to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        )
+    }
+
+    /// Returns the directory the sessions of the project are stored in:
+    /// `~/.config/chatti/projects/<root>/sessions`, with the path of the
+    /// root flattened into one directory name.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FSError::HomeDirNotFound` if the home directory cannot be found.
+    pub fn sessions_dir(&self) -> Result<PathBuf, FSError> {
+        let key: String = self
+            .root
+            .to_string_lossy()
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '.' || c == '_' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        Ok(dirs::home_dir()
+            .ok_or(FSError::HomeDirNotFound)?
+            .join(".config")
+            .join("chatti")
+            .join("projects")
+            .join(key)
+            .join("sessions"))
+    }
+
+    /// Uses the project's model instead of the configured one.
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(model) = &self.config.model {
+            config.model.clone_from(model);
+        }
+    }
+
+    /// Prepares the system prompt conversations in the project start with,
+    /// including the attached files.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TemplateError` if a pattern is invalid or a file cannot be
+    /// read.
+    pub fn start(&self) -> Result<Started, TemplateError> {
+        let template = TemplateConfig {
+            system: self.config.system.clone(),
+            attach: self.config.attach.clone(),
+            ..TemplateConfig::default()
+        };
+        templates::start(&template, &self.root)
+    }
+}
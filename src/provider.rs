@@ -0,0 +1,431 @@
+//! Speaks the request and response formats of the supported servers.
+//!
+//! Ollama's `/api/chat` takes the messages as they are and streams one JSON
+//! object per line. OpenAI-compatible servers such as LM Studio take the
+//! same messages and stream server-sent events. llama.cpp's own server has
+//! no chat endpoint: its `/completion` takes a single prompt, so the
+//! messages are rendered with a chat template, and it streams server-sent
+//! events as well.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::warn;
+
+use crate::config::Config;
+use crate::images;
+use crate::message::{Message, Metadata};
+
+/// The server the chat endpoint belongs to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// Ollama's `/api/chat`.
+    #[default]
+    Ollama,
+    /// llama.cpp's server, through its native `/completion`.
+    #[serde(rename = "llamacpp")]
+    LlamaCpp,
+    /// A server with an OpenAI-compatible `/v1/chat/completions`.
+    #[serde(rename = "openai")]
+    OpenAi,
+    /// LM Studio's local server, which is OpenAI-compatible and needs no
+    /// API key.
+    #[serde(rename = "lmstudio")]
+    LmStudio,
+}
+
+impl Provider {
+    /// Returns the endpoint used when `api_endpoint` is not set, for
+    /// servers that listen on a well-known local address.
+    #[must_use]
+    pub fn default_endpoint(self) -> Option<&'static str> {
+        match self {
+            Provider::LmStudio => Some("http://localhost:1234/v1/chat/completions"),
+            Provider::Ollama | Provider::LlamaCpp | Provider::OpenAi => None,
+        }
+    }
+
+    /// Returns `true` if the server streams server-sent events rather than
+    /// one JSON object per line.
+    #[must_use]
+    pub fn streams_events(self) -> bool {
+        self != Provider::Ollama
+    }
+
+    /// Returns `true` if the server ends a stream with a `[DONE]` marker,
+    /// after the part that reports the finish reason: OpenAI-compatible
+    /// servers send the usage statistics in a part of their own between
+    /// them.
+    #[must_use]
+    pub fn ends_with_marker(self) -> bool {
+        matches!(self, Provider::OpenAi | Provider::LmStudio)
+    }
+
+    /// Returns `true` if the server can be asked to load the model ahead of
+    /// the first prompt.
+    #[must_use]
+    pub fn can_warm_up(self) -> bool {
+        self == Provider::Ollama
+    }
+
+    /// Builds the body of a chat request.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The model, sampling parameters and chat template.
+    /// * `messages` - The conversation, ending with the prompt to answer.
+    #[must_use]
+    pub fn request_body(self, config: &Config, messages: &[Message]) -> Value {
+        match self {
+            Provider::Ollama | Provider::OpenAi | Provider::LmStudio => {
+                let messages: Vec<_> = messages.iter().map(|m| self.message(m)).collect();
+                let mut body = json!({
+                    "model": config.model,
+                    "messages": messages,
+                    "stream": config.stream,
+                    "temperature": config.temperature,
+                });
+                if let Some(top_p) = config.top_p {
+                    body["top_p"] = json!(top_p);
+                }
+                if let Some(max_tokens) = config.max_tokens {
+                    body["max_tokens"] = json!(max_tokens);
+                }
+                if config.stream && self.ends_with_marker() {
+                    body["stream_options"] = json!({"include_usage": true});
+                }
+                match (&config.json_output, self) {
+                    (Some(output), Provider::Ollama) => body["format"] = output.ollama_format(),
+                    (Some(output), _) => body["response_format"] = output.response_format(),
+                    (None, _) => {}
+                }
+                body
+            }
+            Provider::LlamaCpp => {
+                let template = config.llamacpp.chat_template();
+                let mut body = json!({
+                    "prompt": template.render(messages),
+                    "stop": template.stop,
+                    "stream": config.stream,
+                    "temperature": config.temperature,
+                    "cache_prompt": true,
+                });
+                if let Some(top_p) = config.top_p {
+                    body["top_p"] = json!(top_p);
+                }
+                if let Some(max_tokens) = config.max_tokens {
+                    body["n_predict"] = json!(max_tokens);
+                }
+                if let Some(output) = &config.json_output {
+                    body["json_schema"] = output.json_schema();
+                }
+                body
+            }
+        }
+    }
+
+    /// Builds a message of a chat request, with its images as Ollama or
+    /// OpenAI-compatible servers take them. Images that cannot be read are
+    /// left out.
+    fn message(self, message: &Message) -> Value {
+        let images = message.images.iter().filter_map(|path| {
+            let encoded = match self {
+                Provider::Ollama => images::encode(path),
+                _ => images::data_url(path),
+            };
+            encoded
+                .map_err(|err| warn!(?err, path = %path.display(), "cannot send an image"))
+                .ok()
+        });
+        match self {
+            _ if message.images.is_empty() => {
+                json!({"role": message.role, "content": message.content})
+            }
+            Provider::Ollama => json!({
+                "role": message.role,
+                "content": message.content,
+                "images": images.collect::<Vec<_>>(),
+            }),
+            _ => {
+                let mut parts = vec![json!({"type": "text", "text": message.content})];
+                parts.extend(
+                    images.map(|url| json!({"type": "image_url", "image_url": {"url": url}})),
+                );
+                json!({"role": message.role, "content": parts})
+            }
+        }
+    }
+
+    /// Returns the text of a response, or of a streamed part of one.
+    ///
+    /// OpenAI-compatible servers differ in where they put it: the `delta` of
+    /// a streamed choice, its `message`, or the `text` of older completion
+    /// servers. Parts without content, such as a final part that only holds
+    /// usage statistics, return `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::provider::Provider;
+    /// use serde_json::json;
+    ///
+    /// let ollama = json!({"message": {"content": "Hi"}, "done": false});
+    /// assert_eq!(Provider::Ollama.content(&ollama), Some("Hi"));
+    /// let llamacpp = json!({"content": "Hi", "stop": false});
+    /// assert_eq!(Provider::LlamaCpp.content(&llamacpp), Some("Hi"));
+    /// let openai = json!({"choices": [{"delta": {"content": "Hi"}, "finish_reason": null}]});
+    /// assert_eq!(Provider::LmStudio.content(&openai), Some("Hi"));
+    /// ```
+    #[must_use]
+    pub fn content(self, json: &Value) -> Option<&str> {
+        match self {
+            Provider::Ollama => json["message"]["content"].as_str(),
+            Provider::LlamaCpp => json["content"].as_str(),
+            Provider::OpenAi | Provider::LmStudio => {
+                let choice = &json["choices"][0];
+                choice["delta"]["content"]
+                    .as_str()
+                    .or_else(|| choice["message"]["content"].as_str())
+                    .or_else(|| choice["text"].as_str())
+            }
+        }
+    }
+
+    /// Returns `true` if a streamed part is the last one.
+    ///
+    /// For OpenAI-compatible servers, any `finish_reason` counts, including
+    /// nonstandard ones such as `eos`; an empty string or `"null"` does not.
+    #[must_use]
+    pub fn is_done(self, json: &Value) -> bool {
+        match self {
+            Provider::Ollama => json["done"].as_bool().unwrap_or(false),
+            Provider::LlamaCpp => json["stop"].as_bool().unwrap_or(false),
+            Provider::OpenAi | Provider::LmStudio => json["choices"][0]["finish_reason"]
+                .as_str()
+                .is_some_and(|reason| !reason.is_empty() && reason != "null"),
+        }
+    }
+
+    /// Records the token counts, finish reason and request id a response,
+    /// or a streamed part of one, reports. Ollama and llama.cpp report them
+    /// in the last part; OpenAI-compatible servers name the request in
+    /// every part and may add usage statistics at the end.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::Metadata;
+    /// use chatti::provider::Provider;
+    /// use serde_json::json;
+    ///
+    /// let mut metadata = Metadata::default();
+    /// let last = json!({"done": true, "done_reason": "stop", "prompt_eval_count": 26, "eval_count": 298});
+    /// Provider::Ollama.record(&last, &mut metadata);
+    /// assert_eq!(metadata.prompt_tokens, Some(26));
+    /// assert_eq!(metadata.completion_tokens, Some(298));
+    /// assert_eq!(metadata.finish_reason.as_deref(), Some("stop"));
+    /// ```
+    pub fn record(self, json: &Value, metadata: &mut Metadata) {
+        let (prompt_tokens, completion_tokens, finish_reason) = match self {
+            Provider::Ollama => (
+                &json["prompt_eval_count"],
+                &json["eval_count"],
+                json["done_reason"].as_str(),
+            ),
+            Provider::LlamaCpp => {
+                let finish_reason = json["stop_type"].as_str().or_else(|| {
+                    [
+                        ("stopped_limit", "limit"),
+                        ("stopped_word", "word"),
+                        ("stopped_eos", "eos"),
+                    ]
+                    .into_iter()
+                    .find(|(flag, _)| json[*flag].as_bool() == Some(true))
+                    .map(|(_, reason)| reason)
+                });
+                (
+                    &json["tokens_evaluated"],
+                    &json["tokens_predicted"],
+                    finish_reason,
+                )
+            }
+            Provider::OpenAi | Provider::LmStudio => {
+                if let Some(id) = json["id"].as_str() {
+                    metadata.request_id.get_or_insert_with(|| id.to_string());
+                }
+                (
+                    &json["usage"]["prompt_tokens"],
+                    &json["usage"]["completion_tokens"],
+                    json["choices"][0]["finish_reason"].as_str(),
+                )
+            }
+        };
+        if let Some(tokens) = prompt_tokens.as_u64() {
+            metadata.prompt_tokens = Some(tokens);
+        }
+        if let Some(tokens) = completion_tokens.as_u64() {
+            metadata.completion_tokens = Some(tokens);
+        }
+        if let Some(reason) =
+            finish_reason.filter(|r| !r.is_empty() && *r != "null" && *r != "none")
+        {
+            metadata.finish_reason = Some(reason.to_string());
+        }
+    }
+}
+
+/// Describes the request a prompt would be sent with, for a dry run: the
+/// method, endpoint and headers, followed by the JSON body. The API key is
+/// never shown.
+///
+/// # Arguments
+///
+/// * `config` - The endpoint, provider and request parameters.
+/// * `messages` - The conversation, ending with the prompt to answer.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::config::Config;
+/// use chatti::message::Message;
+/// use chatti::provider::dry_run;
+///
+/// let config: Config = toml::from_str(
+///     "api_endpoint = \"http://localhost:11434/api/chat\"\nmodel = \"llama3\"\nstream = true\ntemperature = 0.7\n",
+/// )
+/// .unwrap();
+/// let preview = dry_run(&config, &[Message::new("user", "Hi")]);
+/// assert!(preview.contains("POST http://localhost:11434/api/chat"));
+/// assert!(preview.contains("\"content\": \"Hi\""));
+/// ```
+#[must_use]
+pub fn dry_run(config: &Config, messages: &[Message]) -> String {
+    let body = config.provider.request_body(config, messages);
+    let body = serde_json::to_string_pretty(&body).unwrap_or_default();
+    let auth = if config.api_key.is_some() {
+        "\nAuthorization: Bearer <hidden>"
+    } else {
+        ""
+    };
+    format!(
+        "Dry run, nothing was sent:\n\n```\nPOST {}\nContent-Type: application/json{auth}\n```\n\n```json\n{body}\n```",
+        config.api_endpoint
+    )
+}
+
+/// The chat templates llama.cpp's server can be configured with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplateName {
+    /// ChatML, used by Qwen, Yi, Hermes and many fine-tunes.
+    #[default]
+    Chatml,
+    /// Llama 3 and later.
+    Llama3,
+    /// Mistral and Mixtral instruct models.
+    Mistral,
+    /// The template set in `[llamacpp.custom]`.
+    Custom,
+}
+
+/// How a conversation is turned into a single prompt.
+///
+/// The role templates contain `{content}`, which is replaced with the text
+/// of each message.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChatTemplate {
+    /// Text at the very start of the prompt.
+    pub prefix: String,
+    /// The template of a system message.
+    pub system: String,
+    /// The template of a user message.
+    pub user: String,
+    /// The template of an assistant message.
+    pub assistant: String,
+    /// Text that opens the answer to generate.
+    pub generation: String,
+    /// Strings that end the generation.
+    pub stop: Vec<String>,
+}
+
+impl ChatTemplate {
+    /// Returns a built-in template, or `None` for [`TemplateName::Custom`].
+    #[must_use]
+    pub fn preset(name: TemplateName) -> Option<Self> {
+        let template = |prefix: &str, roles: [&str; 3], generation: &str, stop: &[&str]| {
+            let [system, user, assistant] = roles.map(str::to_string);
+            ChatTemplate {
+                prefix: prefix.to_string(),
+                system,
+                user,
+                assistant,
+                generation: generation.to_string(),
+                stop: stop.iter().map(|s| (*s).to_string()).collect(),
+            }
+        };
+        match name {
+            TemplateName::Chatml => Some(template(
+                "",
+                [
+                    "<|im_start|>system\n{content}<|im_end|>\n",
+                    "<|im_start|>user\n{content}<|im_end|>\n",
+                    "<|im_start|>assistant\n{content}<|im_end|>\n",
+                ],
+                "<|im_start|>assistant\n",
+                &["<|im_end|>"],
+            )),
+            TemplateName::Llama3 => Some(template(
+                "<|begin_of_text|>",
+                [
+                    "<|start_header_id|>system<|end_header_id|>\n\n{content}<|eot_id|>",
+                    "<|start_header_id|>user<|end_header_id|>\n\n{content}<|eot_id|>",
+                    "<|start_header_id|>assistant<|end_header_id|>\n\n{content}<|eot_id|>",
+                ],
+                "<|start_header_id|>assistant<|end_header_id|>\n\n",
+                &["<|eot_id|>"],
+            )),
+            TemplateName::Mistral => Some(template(
+                "<s>",
+                [
+                    "[INST] {content} [/INST]",
+                    "[INST] {content} [/INST]",
+                    "{content}</s>",
+                ],
+                "",
+                &["</s>"],
+            )),
+            TemplateName::Custom => None,
+        }
+    }
+
+    /// Renders a conversation into a prompt that ends where the answer
+    /// starts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::Message;
+    /// use chatti::provider::{ChatTemplate, TemplateName};
+    ///
+    /// let template = ChatTemplate::preset(TemplateName::Chatml).unwrap();
+    /// assert_eq!(
+    ///     template.render(&[Message::new("user", "Hi")]),
+    ///     "<|im_start|>user\nHi<|im_end|>\n<|im_start|>assistant\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn render(&self, messages: &[Message]) -> String {
+        let mut prompt = self.prefix.clone();
+        for message in messages {
+            let template = match message.role.as_str() {
+                "system" => &self.system,
+                "assistant" => &self.assistant,
+                _ => &self.user,
+            };
+            prompt.push_str(&template.replace("{content}", &message.content));
+        }
+        prompt.push_str(&self.generation);
+        prompt
+    }
+}
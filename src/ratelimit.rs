@@ -0,0 +1,174 @@
+//! Limits the rate of outgoing chat requests.
+//!
+//! Requests wait for their turn instead of failing, so regenerating quickly
+//! or running a script cannot exceed the configured quota. Cancelling a
+//! request that is still waiting drops it without sending anything. The
+//! limiter counts the requests waiting and in flight, which the interface
+//! shows while a response is awaited.
+
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tracing::info;
+
+use crate::config::RateLimit;
+
+/// The window `requests_per_minute` is counted over.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Limits how many requests are sent per minute and at once.
+///
+/// Clones share the same limits.
+#[derive(Debug, Clone, Default)]
+pub struct RateLimiter {
+    requests_per_minute: Option<usize>,
+    sent: Arc<Mutex<VecDeque<Instant>>>,
+    concurrent: Option<Arc<Semaphore>>,
+    max_concurrent: Option<usize>,
+    queued: Arc<AtomicUsize>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+/// Allows a request to be in flight until it is dropped.
+#[derive(Debug)]
+pub struct Permit {
+    _concurrent: Option<OwnedSemaphorePermit>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Counts a request as queued until it gets its turn or is dropped.
+struct Queued(Arc<AtomicUsize>);
+
+impl Queued {
+    fn new(queued: &Arc<AtomicUsize>) -> Self {
+        queued.fetch_add(1, Ordering::Relaxed);
+        Queued(Arc::clone(queued))
+    }
+}
+
+impl Drop for Queued {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl RateLimiter {
+    /// Creates a limiter with the given limits.
+    ///
+    /// A `requests_per_minute` of 0 means no limit, as no request could
+    /// ever be sent otherwise.
+    #[must_use]
+    pub fn new(limit: RateLimit) -> Self {
+        RateLimiter {
+            requests_per_minute: limit
+                .requests_per_minute
+                .and_then(|limit| usize::try_from(limit).ok())
+                .filter(|&limit| limit > 0),
+            sent: Arc::default(),
+            concurrent: limit
+                .max_concurrent
+                .map(|max| Arc::new(Semaphore::new(max.max(1)))),
+            max_concurrent: limit.max_concurrent.map(|max| max.max(1)),
+            queued: Arc::default(),
+            in_flight: Arc::default(),
+        }
+    }
+
+    /// Returns the number of requests waiting for their turn.
+    #[must_use]
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of requests in flight.
+    #[must_use]
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Returns the most requests in flight at once, or `None` for no limit.
+    #[must_use]
+    pub fn max_concurrent(&self) -> Option<usize> {
+        self.max_concurrent
+    }
+
+    /// Waits until a request may be sent.
+    ///
+    /// # Returns
+    ///
+    /// A permit to keep while the request is in flight.
+    pub async fn acquire(&self) -> Permit {
+        let queued = Queued::new(&self.queued);
+        let permit = match &self.concurrent {
+            Some(semaphore) => Arc::clone(semaphore).acquire_owned().await.ok(),
+            None => None,
+        };
+        loop {
+            let Some(wait) = self.reserve() else {
+                drop(queued);
+                self.in_flight.fetch_add(1, Ordering::Relaxed);
+                return Permit {
+                    _concurrent: permit,
+                    in_flight: Arc::clone(&self.in_flight),
+                };
+            };
+            info!(
+                wait_secs = wait.as_secs(),
+                "request rate limit reached; waiting"
+            );
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Returns how long a request sent now would wait for the per-minute
+    /// limit, or `None` if it would be sent right away.
+    #[must_use]
+    pub fn delay(&self) -> Option<Duration> {
+        let limit = self.requests_per_minute?;
+        let mut sent = self.sent.lock().unwrap_or_else(|e| e.into_inner());
+        Self::expire(&mut sent);
+        (sent.len() >= limit).then(|| Self::until_free(&sent))
+    }
+
+    /// Records a request as sent if the per-minute limit allows it.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the request was recorded, or how long to wait otherwise.
+    fn reserve(&self) -> Option<Duration> {
+        let limit = self.requests_per_minute?;
+        let mut sent = self.sent.lock().unwrap_or_else(|e| e.into_inner());
+        Self::expire(&mut sent);
+        if sent.len() < limit {
+            sent.push_back(Instant::now());
+            return None;
+        }
+        Some(Self::until_free(&sent))
+    }
+
+    /// Forgets the requests sent before the window.
+    fn expire(sent: &mut VecDeque<Instant>) {
+        while sent.front().is_some_and(|at| at.elapsed() >= WINDOW) {
+            sent.pop_front();
+        }
+    }
+
+    /// Returns how long until the oldest request leaves the window.
+    fn until_free(sent: &VecDeque<Instant>) -> Duration {
+        sent.front()
+            .map_or(Duration::ZERO, |at| WINDOW.saturating_sub(at.elapsed()))
+    }
+}
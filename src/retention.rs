@@ -0,0 +1,142 @@
+//! Keeps the sessions directory from growing forever.
+//!
+//! With `[retention] days` set, sessions that were not modified for that
+//! many days are swept out of the session store on startup: by default
+//! they are moved to the archive directory as gzip-compressed JSON, or,
+//! with `action = "delete"`, removed. `/archive` sweeps on demand, always
+//! archiving.
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{config::FSError, session::Session, store::SessionStore};
+
+/// The number of seconds in a day.
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// What happens to sessions older than the retention period.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    /// Compress them into the archive directory.
+    #[default]
+    Archive,
+    /// Delete them.
+    Delete,
+}
+
+/// Returns the time, in seconds since the Unix epoch, before which a
+/// session is older than `days`.
+#[must_use]
+pub fn cutoff(days: u64) -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        .saturating_sub(days.saturating_mul(DAY_SECS))
+}
+
+/// Sessions kept as gzip-compressed `<id>.json.gz` files in a directory.
+#[derive(Debug, Clone)]
+pub struct Archive {
+    dir: PathBuf,
+}
+
+impl Archive {
+    /// Creates an archive on `dir`, which is created on the first write.
+    #[must_use]
+    pub fn new(dir: PathBuf) -> Self {
+        Archive { dir }
+    }
+
+    /// Opens the archive next to the sessions directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FSError::HomeDirNotFound` if the home directory cannot be
+    /// found.
+    pub fn open() -> Result<Self, FSError> {
+        Ok(Archive::new(
+            Session::sessions_dir()?.with_file_name("archive"),
+        ))
+    }
+
+    /// Returns the directory the archived sessions are kept in.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Writes a compressed copy of the session.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the file cannot be written.
+    pub fn save(&self, session: &Session) -> Result<(), FSError> {
+        fs::create_dir_all(&self.dir)?;
+        let file = File::create(self.dir.join(format!("{}.json.gz", session.id)))?;
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        encoder.write_all(serde_json::to_string(session)?.as_bytes())?;
+        encoder.finish()?;
+        Ok(())
+    }
+
+    /// Reads an archived session back.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the session is not archived or cannot be
+    /// read.
+    pub fn load(&self, id: &str) -> Result<Session, FSError> {
+        let file = File::open(self.dir.join(format!("{id}.json.gz")))?;
+        let mut json = String::new();
+        GzDecoder::new(file).read_to_string(&mut json)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// Archives or deletes the sessions last modified before `cutoff`.
+///
+/// # Arguments
+///
+/// * `store` - The store the sessions are swept out of.
+/// * `archive` - Where archived sessions go.
+/// * `cutoff` - The time, in seconds since the Unix epoch, before which
+///   sessions are swept; see [`cutoff`].
+/// * `action` - Whether old sessions are archived or deleted.
+/// * `keep` - A session that is never swept, such as the open one.
+///
+/// # Returns
+///
+/// The number of sessions swept.
+///
+/// # Errors
+///
+/// Returns an `FSError` if a session cannot be read, archived or removed.
+/// A session is only removed from the store once it is archived.
+pub fn sweep(
+    store: &dyn SessionStore,
+    archive: &Archive,
+    cutoff: u64,
+    action: Action,
+    keep: Option<&str>,
+) -> Result<usize, FSError> {
+    let mut swept = 0;
+    for info in store.list()? {
+        if info.updated_at >= cutoff || keep == Some(info.id.as_str()) {
+            continue;
+        }
+        if action == Action::Archive {
+            archive.save(&store.load(&info.id)?)?;
+        }
+        store.delete(&info.id)?;
+        swept += 1;
+    }
+    Ok(swept)
+}
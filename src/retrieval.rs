@@ -0,0 +1,530 @@
+//! Retrieves excerpts of local files relevant to a prompt.
+//!
+//! The files of the configured directory are split into chunks, which are
+//! embedded with the provider's embeddings endpoint. The chunks and their
+//! embeddings are kept in `~/.config/chatti/index.json`, so only files that
+//! changed are embedded again on the next start. For each prompt, the chunks
+//! closest to it are sent along as context.
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::config::{FSError, RetrievalConfig};
+use crate::session::Session;
+
+/// The number of chunks embedded per request while indexing.
+const EMBED_BATCH: usize = 32;
+
+/// Files larger than this are not indexed.
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+
+/// How long embedding a prompt may take before it is sent without context.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Represents errors that can occur while indexing or retrieving.
+#[derive(Debug, thiserror::Error)]
+pub enum RetrievalError {
+    /// The index or the indexed files could not be read or written.
+    #[error("{0}")]
+    Storage(#[from] FSError),
+
+    /// The embeddings request could not be sent or its response read.
+    #[error("embeddings request failed: {0}")]
+    Network(#[from] reqwest::Error),
+
+    /// The embeddings endpoint answered with an error or an unexpected
+    /// response.
+    #[error("embeddings endpoint: {0}")]
+    Server(String),
+}
+
+/// An excerpt of an indexed file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Chunk {
+    /// The path of the file, relative to the indexed directory.
+    pub source: String,
+    /// The first line of the excerpt, counting from 1.
+    pub start_line: usize,
+    /// The last line of the excerpt.
+    pub end_line: usize,
+    /// The text of the excerpt.
+    pub text: String,
+    /// The embedding of the text.
+    pub embedding: Vec<f32>,
+}
+
+impl Chunk {
+    /// Returns the file and lines of the excerpt, such as `src/main.rs:1-40`.
+    #[must_use]
+    pub fn label(&self) -> String {
+        format!("{}:{}-{}", self.source, self.start_line, self.end_line)
+    }
+}
+
+/// The size and modification time of an indexed file, used to notice
+/// changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct FileStamp {
+    size: u64,
+    modified: u64,
+}
+
+/// The embedded chunks of a directory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Index {
+    /// The indexed directory.
+    pub directory: PathBuf,
+    /// The model the chunks were embedded with.
+    pub model: String,
+    /// The embedded chunks.
+    pub chunks: Vec<Chunk>,
+    files: HashMap<String, FileStamp>,
+}
+
+impl Index {
+    /// Returns the path of the index file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the home directory cannot be found.
+    pub fn path() -> Result<PathBuf, FSError> {
+        Ok(Session::sessions_dir()?.with_file_name("index.json"))
+    }
+
+    /// Loads the index from `path`, if it exists.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the file cannot be read or parsed.
+    pub fn load(path: &Path) -> Result<Option<Self>, FSError> {
+        match fs::read_to_string(path) {
+            Ok(json) => Ok(Some(serde_json::from_str(&json)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the index to `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the file cannot be written.
+    pub fn save(&self, path: &Path) -> Result<(), FSError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Brings the index up to date with the configured directory, embedding
+    /// the chunks of new and changed files. Everything is embedded again if
+    /// the directory or model changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - The HTTP client.
+    /// * `endpoint` - The embeddings endpoint.
+    /// * `directory` - The directory to index.
+    /// * `config` - The retrieval configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `RetrievalError` if the directory cannot be read or
+    /// embedding fails.
+    pub async fn refresh(
+        mut self,
+        client: &reqwest::Client,
+        endpoint: &str,
+        directory: &Path,
+        config: &RetrievalConfig,
+    ) -> Result<Self, RetrievalError> {
+        if self.directory != directory || self.model != config.model {
+            self = Index {
+                directory: directory.to_path_buf(),
+                model: config.model.clone(),
+                ..Index::default()
+            };
+        }
+
+        let mut files = Vec::new();
+        collect_files(directory, directory, &mut files)?;
+        let current: HashMap<String, FileStamp> = files
+            .iter()
+            .map(|(source, _, stamp)| (source.clone(), *stamp))
+            .collect();
+
+        let old_files = std::mem::take(&mut self.files);
+        self.chunks
+            .retain(|chunk| old_files.get(&chunk.source) == current.get(&chunk.source));
+
+        let mut fresh = Vec::new();
+        for (source, path, stamp) in &files {
+            if old_files.get(source) == Some(stamp) {
+                continue;
+            }
+            let Ok(text) = fs::read_to_string(path) else {
+                continue;
+            };
+            for (start_line, end_line, text) in split(&text, config.chunk_chars) {
+                fresh.push(Chunk {
+                    source: source.clone(),
+                    start_line,
+                    end_line,
+                    text,
+                    embedding: Vec::new(),
+                });
+            }
+        }
+
+        for batch in fresh.chunks_mut(EMBED_BATCH) {
+            let inputs: Vec<String> = batch.iter().map(|chunk| chunk.text.clone()).collect();
+            let embeddings = embed(client, endpoint, &config.model, &inputs).await?;
+            for (chunk, embedding) in batch.iter_mut().zip(embeddings) {
+                chunk.embedding = embedding;
+            }
+        }
+        info!(
+            files = current.len(),
+            embedded = fresh.len(),
+            "retrieval index refreshed"
+        );
+
+        self.chunks.extend(fresh);
+        self.files = current;
+        Ok(self)
+    }
+
+    /// Returns the chunks most similar to `query`, best first.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The embedding of the prompt.
+    /// * `top_k` - The maximum number of chunks to return.
+    #[must_use]
+    pub fn search(&self, query: &[f32], top_k: usize) -> Vec<&Chunk> {
+        let mut scored: Vec<(f32, &Chunk)> = self
+            .chunks
+            .iter()
+            .map(|chunk| (cosine_similarity(query, &chunk.embedding), chunk))
+            .collect();
+        scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+        scored
+            .into_iter()
+            .take(top_k)
+            .map(|(_, chunk)| chunk)
+            .collect()
+    }
+}
+
+/// Splits text into chunks of whole lines of at most `max_chars`
+/// characters, unless a single line is longer.
+///
+/// # Returns
+///
+/// The first and last line number and the text of each chunk.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::retrieval::split;
+///
+/// let chunks = split("one\ntwo\n\nthree", 8);
+/// assert_eq!(
+///     chunks,
+///     vec![(1, 2, "one\ntwo".to_string()), (4, 4, "three".to_string())]
+/// );
+/// ```
+#[must_use]
+pub fn split(text: &str, max_chars: usize) -> Vec<(usize, usize, String)> {
+    let mut chunks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut start = 1;
+    let mut length = 0;
+
+    let mut flush = |current: &mut Vec<&str>, start: usize, end: usize| {
+        let text = current.join("\n").trim().to_string();
+        if !text.is_empty() {
+            chunks.push((start, end, text));
+        }
+        current.clear();
+    };
+
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let chars = line.chars().count();
+        if !current.is_empty() && length + chars + 1 > max_chars {
+            flush(&mut current, start, number - 1);
+            length = 0;
+        }
+        if current.is_empty() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            start = number;
+        }
+        current.push(line);
+        length += chars + 1;
+    }
+    let end = start + current.len().saturating_sub(1);
+    flush(&mut current, start, end);
+    chunks
+}
+
+/// Returns the cosine similarity of two vectors, or 0 if either is empty or
+/// their lengths differ.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::retrieval::cosine_similarity;
+///
+/// assert_eq!(cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]), 1.0);
+/// assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]), 0.0);
+/// ```
+#[must_use]
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.is_empty() || a.len() != b.len() {
+        return 0.0;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+/// Embeds texts with the embeddings endpoint, which takes
+/// `{"model", "input": [...]}` and answers with `{"embeddings": [...]}`.
+///
+/// # Errors
+///
+/// Returns a `RetrievalError` if the request fails or the response does not
+/// hold one embedding per input.
+pub async fn embed(
+    client: &reqwest::Client,
+    endpoint: &str,
+    model: &str,
+    inputs: &[String],
+) -> Result<Vec<Vec<f32>>, RetrievalError> {
+    #[derive(Deserialize)]
+    struct Embeddings {
+        embeddings: Vec<Vec<f32>>,
+    }
+
+    let response = client
+        .post(endpoint)
+        .json(&json!({"model": model, "input": inputs}))
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(RetrievalError::Server(
+            crate::api::parse_error_message(&body)
+                .unwrap_or_else(|| format!("the server responded with {status}")),
+        ));
+    }
+    let embeddings = response.json::<Embeddings>().await?.embeddings;
+    if embeddings.len() != inputs.len() {
+        return Err(RetrievalError::Server(format!(
+            "expected {} embeddings, got {}",
+            inputs.len(),
+            embeddings.len()
+        )));
+    }
+    Ok(embeddings)
+}
+
+/// Finds the chunks of the index most relevant to a prompt.
+///
+/// # Errors
+///
+/// Returns a `RetrievalError` if the prompt cannot be embedded.
+pub async fn retrieve(
+    client: &reqwest::Client,
+    endpoint: &str,
+    index: &Index,
+    config: &RetrievalConfig,
+    prompt: &str,
+) -> Result<Vec<Chunk>, RetrievalError> {
+    let query = tokio::time::timeout(
+        QUERY_TIMEOUT,
+        embed(client, endpoint, &index.model, &[prompt.to_string()]),
+    )
+    .await
+    .map_err(|_| RetrievalError::Server(String::from("timed out embedding the prompt")))??;
+    Ok(index
+        .search(&query[0], config.top_k)
+        .into_iter()
+        .cloned()
+        .collect())
+}
+
+/// Prepends the retrieved excerpts to a prompt, numbered so the model can
+/// cite them.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::retrieval::{with_context, Chunk};
+///
+/// let chunk = Chunk {
+///     source: "notes.md".to_string(),
+///     start_line: 3,
+///     end_line: 4,
+///     text: "The deploy key rotates monthly.".to_string(),
+///     embedding: Vec::new(),
+/// };
+/// let prompt = with_context(&[chunk], "When does the key rotate?");
+/// assert!(prompt.starts_with("Use the following excerpts"));
+/// assert!(prompt.contains("[1] notes.md:3-4\n```\nThe deploy key rotates monthly.\n```"));
+/// assert!(prompt.ends_with("Question: When does the key rotate?"));
+/// ```
+#[must_use]
+pub fn with_context(chunks: &[Chunk], prompt: &str) -> String {
+    if chunks.is_empty() {
+        return prompt.to_string();
+    }
+    let mut text = String::from(
+        "Use the following excerpts from local files if they are relevant, and cite \
+         them by number.\n",
+    );
+    for (number, chunk) in chunks.iter().enumerate() {
+        text.push_str(&format!(
+            "\n[{}] {}\n```\n{}\n```\n",
+            number + 1,
+            chunk.label(),
+            chunk.text
+        ));
+    }
+    text.push_str(&format!("\nQuestion: {prompt}"));
+    text
+}
+
+/// Returns the embeddings endpoint: the configured one, or `/api/embed` next
+/// to the chat endpoint.
+#[must_use]
+pub fn embeddings_endpoint(config: &RetrievalConfig, api_endpoint: &str) -> String {
+    config.endpoint.clone().unwrap_or_else(|| {
+        reqwest::Url::parse(api_endpoint)
+            .and_then(|url| url.join("/api/embed"))
+            .map_or_else(|_| api_endpoint.to_string(), String::from)
+    })
+}
+
+/// Loads and refreshes the index of the configured directory in the
+/// background.
+///
+/// # Arguments
+///
+/// * `client` - The HTTP client.
+/// * `config` - The retrieval configuration.
+/// * `api_endpoint` - The configured chat endpoint.
+///
+/// # Returns
+///
+/// A receiver holding the index once it is ready. If refreshing fails, the
+/// index saved by an earlier run is used.
+#[must_use]
+pub fn spawn_indexer(
+    client: reqwest::Client,
+    config: RetrievalConfig,
+    api_endpoint: &str,
+) -> watch::Receiver<Option<Arc<Index>>> {
+    let (tx, rx) = watch::channel(None);
+    let endpoint = embeddings_endpoint(&config, api_endpoint);
+    tokio::spawn(async move {
+        let Some(directory) = &config.directory else {
+            return;
+        };
+        let directory = crate::commands::expand_home(&directory.to_string_lossy());
+        let path = match Index::path() {
+            Ok(path) => path,
+            Err(err) => {
+                warn!(%err, "cannot locate the retrieval index");
+                return;
+            }
+        };
+        let saved = Index::load(&path).unwrap_or_else(|err| {
+            warn!(%err, "ignoring unreadable retrieval index");
+            None
+        });
+        let index = match saved
+            .clone()
+            .unwrap_or_default()
+            .refresh(&client, &endpoint, &directory, &config)
+            .await
+        {
+            Ok(index) => {
+                if let Err(err) = index.save(&path) {
+                    warn!(%err, "failed to save the retrieval index");
+                }
+                index
+            }
+            Err(err) => {
+                warn!(%err, directory = %directory.display(), "indexing failed");
+                match saved {
+                    Some(saved) => saved,
+                    None => return,
+                }
+            }
+        };
+        let _ = tx.send(Some(Arc::new(index)));
+    });
+    rx
+}
+
+/// Lists the text files below `dir`, skipping hidden entries and large
+/// files.
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    files: &mut Vec<(String, PathBuf, FileStamp)>,
+) -> Result<(), FSError> {
+    let mut entries: Vec<_> = fs::read_dir(dir)?.filter_map(Result::ok).collect();
+    entries.sort_by_key(fs::DirEntry::file_name);
+    for entry in entries {
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            collect_files(root, &path, files)?;
+        } else if metadata.is_file() && metadata.len() <= MAX_FILE_BYTES {
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |since| since.as_secs());
+            let source = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .into_owned();
+            files.push((
+                source,
+                path,
+                FileStamp {
+                    size: metadata.len(),
+                    modified,
+                },
+            ));
+        }
+    }
+    Ok(())
+}
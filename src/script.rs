@@ -0,0 +1,287 @@
+//! Runs a batch of prompts without the interactive UI.
+//!
+//! `chatti --script prompts.txt` sends each prompt of the file in turn, as
+//! one conversation, and writes the answers as markdown, or with
+//! `--format json` as JSON Lines for other programs. A plain text script
+//! holds one prompt per line; a YAML script (`.yaml` or `.yml`) can also
+//! override the model and system prompt of each step:
+//!
+//! ```yaml
+//! model: llama3
+//! system: Answer in one sentence.
+//! steps:
+//!   - prompt: What is a monad?
+//!   - prompt: What is a monad?
+//!     model: mistral
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::message::Message;
+use crate::tokens::estimate_tokens;
+
+/// Represents errors that can occur while loading a script.
+#[derive(Debug, thiserror::Error)]
+pub enum ScriptError {
+    /// The script or output file could not be read or written.
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    /// The YAML script is malformed.
+    #[error("invalid script: {0}")]
+    Yaml(#[from] serde_yml::Error),
+
+    /// The script holds no prompts.
+    #[error("the script holds no prompts")]
+    Empty,
+}
+
+/// How the answers of a scripted run are written.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum OutputFormat {
+    /// A markdown section per answer, for reading.
+    #[default]
+    Markdown,
+    /// A JSON object per line with the answer and its metadata, for other
+    /// programs.
+    Json,
+}
+
+/// The command line options of a scripted run, parsed by
+/// [`Mode::parse`](crate::cli::Mode::parse).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Options {
+    /// The script to run.
+    pub script: PathBuf,
+    /// The file the answers are written to, or `None` for standard output.
+    pub output: Option<PathBuf>,
+    /// How the answers are written.
+    pub format: OutputFormat,
+}
+
+/// One prompt of a script.
+#[derive(Debug, PartialEq, Eq, Clone, Deserialize)]
+pub struct Step {
+    /// The prompt to send.
+    pub prompt: String,
+    /// The model to send it to, instead of the script's model.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// The system prompt to send it with, instead of the script's.
+    #[serde(default)]
+    pub system: Option<String>,
+}
+
+/// A batch of prompts sent as one conversation.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Deserialize)]
+pub struct Script {
+    /// The model of every step, instead of the configured one.
+    #[serde(default)]
+    pub model: Option<String>,
+    /// The system prompt of every step.
+    #[serde(default)]
+    pub system: Option<String>,
+    /// The prompts, in order.
+    pub steps: Vec<Step>,
+}
+
+impl Script {
+    /// Loads a script, as YAML if the file ends in `.yaml` or `.yml` and as
+    /// plain text otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ScriptError` if the file cannot be read or parsed, or holds
+    /// no prompts.
+    pub fn load(path: &Path) -> Result<Self, ScriptError> {
+        let text = fs::read_to_string(path)?;
+        let yaml = path
+            .extension()
+            .is_some_and(|ext| ext == "yaml" || ext == "yml");
+        let script = if yaml {
+            serde_yml::from_str(&text)?
+        } else {
+            Script::from_lines(&text)
+        };
+        if script.steps.is_empty() {
+            return Err(ScriptError::Empty);
+        }
+        Ok(script)
+    }
+
+    /// Reads a plain text script: every non-empty line is a prompt, and
+    /// lines starting with `#` are comments.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::script::Script;
+    ///
+    /// let script = Script::from_lines("# warm-up\nHello\n\nWhat is 2 + 2?\n");
+    /// let prompts: Vec<_> = script.steps.iter().map(|s| s.prompt.as_str()).collect();
+    /// assert_eq!(prompts, vec!["Hello", "What is 2 + 2?"]);
+    /// ```
+    #[must_use]
+    pub fn from_lines(text: &str) -> Self {
+        let steps = text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|prompt| Step {
+                prompt: prompt.to_string(),
+                model: None,
+                system: None,
+            })
+            .collect();
+        Script {
+            steps,
+            ..Script::default()
+        }
+    }
+
+    /// Returns the model a step is sent to.
+    #[must_use]
+    pub fn model_for<'a>(&'a self, step: &'a Step, default: &'a str) -> &'a str {
+        step.model
+            .as_deref()
+            .or(self.model.as_deref())
+            .unwrap_or(default)
+    }
+
+    /// Returns the messages to send for a step: its system prompt, the
+    /// conversation so far and the step's prompt.
+    ///
+    /// # Arguments
+    ///
+    /// * `step` - The step to send.
+    /// * `history` - The prompts and answers of the earlier steps.
+    #[must_use]
+    pub fn request(&self, step: &Step, history: &[Message]) -> Vec<Message> {
+        step.system
+            .as_ref()
+            .or(self.system.as_ref())
+            .map(|system| Message::new("system", system.clone()))
+            .into_iter()
+            .chain(history.iter().cloned())
+            .chain(std::iter::once(Message::new("user", step.prompt.clone())))
+            .collect()
+    }
+}
+
+/// The outcome of one step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Answer {
+    /// The prompt that was sent.
+    pub prompt: String,
+    /// The model it was sent to.
+    pub model: String,
+    /// The response, or the error message if the request failed.
+    pub response: Result<String, String>,
+    /// The estimated number of tokens of the request, including the
+    /// conversation so far.
+    pub prompt_tokens: usize,
+    /// How long it took until the first part of the response arrived.
+    pub first_token: Option<Duration>,
+    /// How long the response took.
+    pub elapsed: Duration,
+}
+
+impl Answer {
+    /// Returns the estimated number of tokens of the response, or 0 if the
+    /// request failed.
+    #[must_use]
+    pub fn response_tokens(&self) -> usize {
+        self.response.as_deref().map_or(0, estimate_tokens)
+    }
+
+    /// Returns the estimated number of tokens received per second, over the
+    /// whole time the response took.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn tokens_per_second(&self) -> f64 {
+        let elapsed = self.elapsed.as_secs_f64();
+        if elapsed > 0.0 {
+            self.response_tokens() as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    /// Formats the answer as a markdown section.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The number of the step, counting from 1.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::script::Answer;
+    /// use std::time::Duration;
+    ///
+    /// let answer = Answer {
+    ///     prompt: "Hello".to_string(),
+    ///     model: "llama3".to_string(),
+    ///     response: Ok("Hi!".to_string()),
+    ///     prompt_tokens: 2,
+    ///     first_token: Some(Duration::from_millis(400)),
+    ///     elapsed: Duration::from_millis(1300),
+    /// };
+    /// assert_eq!(
+    ///     answer.to_markdown(1),
+    ///     "## 1. Hello\n\n_llama3, 1.3s_\n\nHi!\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn to_markdown(&self, number: usize) -> String {
+        let body = match &self.response {
+            Ok(response) => response.trim_end().to_string(),
+            Err(err) => format!("**Error:** {err}"),
+        };
+        format!(
+            "## {number}. {}\n\n_{}, {:.1}s_\n\n{body}\n",
+            self.prompt.lines().next().unwrap_or_default(),
+            self.model,
+            self.elapsed.as_secs_f64()
+        )
+    }
+
+    /// Formats the answer as a line of JSON.
+    ///
+    /// The object holds the step number, prompt, model, response (or
+    /// error), estimated token counts and timings in milliseconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `number` - The number of the step, counting from 1.
+    #[must_use]
+    pub fn to_json_line(&self, number: usize) -> String {
+        let (response, error) = match &self.response {
+            Ok(response) => (Some(response.as_str()), None),
+            Err(err) => (None, Some(err.as_str())),
+        };
+        json!({
+            "step": number,
+            "prompt": self.prompt,
+            "model": self.model,
+            "response": response,
+            "error": error,
+            "tokens": {
+                "prompt": self.prompt_tokens,
+                "response": self.response_tokens(),
+            },
+            "timings": {
+                "first_token_ms": self.first_token.map(|d| d.as_millis()),
+                "total_ms": self.elapsed.as_millis(),
+            },
+        })
+        .to_string()
+    }
+}
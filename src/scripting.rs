@@ -0,0 +1,511 @@
+//! Runs user scripts written in Rhai.
+//!
+//! Every `.rhai` file in `~/.config/chatti/scripts`, or the configured
+//! directory, is a script. A script changes chatti by defining functions:
+//!
+//! - `on_prompt(prompt)` returns the prompt to send instead,
+//! - `on_response(response)` returns the answer to keep instead,
+//! - `command_<name>(args)` adds the slash command `/<name>`, and whatever
+//!   text it returns is shown as a notice.
+//!
+//! Returning nothing leaves the text as it is. Scripts may call `model()`,
+//! `messages()` (an array of `#{role, content}` maps) and `input()` to read
+//! the chat, and `notify(text)`, `set_input(text)` and `send(prompt)` to
+//! show a notice, replace the input and send a prompt to the model. They
+//! cannot reach files or the network, nor import modules, and a script
+//! running too long or building too large a string, array or map is
+//! stopped. Scripts need chatti built with the `scripting` feature.
+
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use crate::message::Message;
+
+#[cfg(feature = "scripting")]
+use crate::commands::COMMANDS;
+#[cfg(feature = "scripting")]
+use std::{cell::RefCell, rc::Rc};
+
+/// The most operations a script may run in one call.
+#[cfg(feature = "scripting")]
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// The longest string a script may build, in bytes.
+#[cfg(feature = "scripting")]
+const MAX_STRING_SIZE: usize = 1 << 20;
+
+/// The most items an array or object map built by a script may hold.
+#[cfg(feature = "scripting")]
+const MAX_COLLECTION_SIZE: usize = 10_000;
+
+/// The prefix of the functions defining slash commands.
+#[cfg(feature = "scripting")]
+const COMMAND_PREFIX: &str = "command_";
+
+/// Represents errors that can occur while loading or running a script.
+#[derive(Debug, thiserror::Error)]
+pub enum ScriptingError {
+    /// The scripts could not be read.
+    #[error("could not read the scripts in {path}: {source}")]
+    Io {
+        /// The file or directory.
+        path: PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+
+    /// A script does not compile.
+    #[error("script `{script}` does not compile: {message}")]
+    Compile {
+        /// The file name of the script.
+        script: String,
+        /// What is wrong with it.
+        message: String,
+    },
+
+    /// A function of a script failed.
+    #[error("script `{script}` failed: {message}")]
+    Run {
+        /// The file name of the script.
+        script: String,
+        /// What went wrong.
+        message: String,
+    },
+
+    /// A script defined a command chatti already has.
+    #[error("script `{script}` cannot define /{name}, which is already a command")]
+    Command {
+        /// The file name of the script.
+        script: String,
+        /// The name of the command.
+        name: String,
+    },
+
+    /// Chatti was built without the `scripting` feature.
+    #[error("running scripts needs chatti built with the scripting feature")]
+    Unsupported,
+}
+
+/// What scripts can read of the chat.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    /// The model prompts are sent to.
+    pub model: String,
+    /// The user messages and answers of the conversation.
+    pub messages: Vec<Message>,
+    /// The text being edited.
+    pub input: String,
+}
+
+/// What scripts asked chatti to do while they ran.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Effects {
+    /// Notices to show in the transcript.
+    pub notices: Vec<String>,
+    /// The text to replace the input with.
+    pub input: Option<String>,
+    /// A prompt to send to the model.
+    pub prompt: Option<String>,
+}
+
+/// The result of running the scripts.
+#[derive(Debug, Default)]
+pub struct Outcome {
+    /// The text the scripts returned in place of the prompt or answer, if
+    /// they changed it.
+    pub text: Option<String>,
+    /// What the scripts asked for.
+    pub effects: Effects,
+    /// The failures of scripts, which are skipped.
+    pub errors: Vec<ScriptingError>,
+}
+
+/// A slash command defined by a script.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptCommand {
+    /// The name of the command, without the `/`.
+    pub name: String,
+    /// The file name of the script defining it.
+    pub script: String,
+}
+
+/// Returns the directory scripts are loaded from by default,
+/// `~/.config/chatti/scripts`.
+#[must_use]
+pub fn default_directory() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".config")
+        .join("chatti")
+        .join("scripts")
+}
+
+/// Returns the `.rhai` files in a directory, sorted by name. A missing
+/// directory has none.
+///
+/// # Errors
+///
+/// Returns an error if the directory exists but cannot be read.
+pub fn find(dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "rhai"))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// What the functions registered with the engine share with the caller.
+#[cfg(feature = "scripting")]
+#[derive(Default)]
+struct Shared {
+    snapshot: Snapshot,
+    effects: Effects,
+}
+
+/// The loaded scripts.
+#[derive(Default)]
+pub struct Scripts {
+    /// The slash commands the scripts define.
+    pub commands: Vec<ScriptCommand>,
+    #[cfg(feature = "scripting")]
+    engine: rhai::Engine,
+    #[cfg(feature = "scripting")]
+    scripts: Vec<(String, rhai::AST)>,
+    #[cfg(feature = "scripting")]
+    shared: Rc<RefCell<Shared>>,
+}
+
+impl Scripts {
+    /// Returns `true` if no script is loaded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        #[cfg(feature = "scripting")]
+        return self.scripts.is_empty();
+        #[cfg(not(feature = "scripting"))]
+        return true;
+    }
+
+    /// Returns the file names of the loaded scripts.
+    #[must_use]
+    pub fn names(&self) -> Vec<&str> {
+        #[cfg(feature = "scripting")]
+        return self.scripts.iter().map(|(name, _)| name.as_str()).collect();
+        #[cfg(not(feature = "scripting"))]
+        return Vec::new();
+    }
+
+    /// Runs `on_prompt` of each script defining it, each given the prompt
+    /// the previous one returned.
+    #[must_use]
+    pub fn on_prompt(&self, snapshot: &Snapshot, prompt: &str) -> Outcome {
+        self.transform("on_prompt", snapshot, prompt)
+    }
+
+    /// Runs `on_response` of each script defining it, each given the
+    /// answer the previous one returned.
+    #[must_use]
+    pub fn on_response(&self, snapshot: &Snapshot, response: &str) -> Outcome {
+        self.transform("on_response", snapshot, response)
+    }
+
+    /// Describes the loaded scripts and their commands, for `/scripts`.
+    #[must_use]
+    pub fn describe(&self, dir: &Path) -> String {
+        if self.is_empty() {
+            return format!(
+                "No scripts are loaded; .rhai files in {} are run as scripts",
+                dir.display()
+            );
+        }
+        let mut text = format!("Scripts in {}:", dir.display());
+        for name in self.names() {
+            text.push_str(&format!("\n- {name}"));
+            for command in self.commands.iter().filter(|c| c.script == name) {
+                text.push_str(&format!("\n  /{}", command.name));
+            }
+        }
+        text
+    }
+}
+
+#[cfg(feature = "scripting")]
+impl Scripts {
+    /// Loads the scripts of a directory.
+    ///
+    /// # Returns
+    ///
+    /// The scripts, and the errors of those that could not be loaded,
+    /// which are left out.
+    #[must_use]
+    pub fn load(dir: &Path) -> (Self, Vec<ScriptingError>) {
+        let mut scripts = Scripts::new();
+        let paths = match find(dir) {
+            Ok(paths) => paths,
+            Err(source) => {
+                let path = dir.to_path_buf();
+                return (scripts, vec![ScriptingError::Io { path, source }]);
+            }
+        };
+        let mut errors = Vec::new();
+        for path in paths {
+            let name = path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            let source = match fs::read_to_string(&path) {
+                Ok(source) => source,
+                Err(source) => {
+                    errors.push(ScriptingError::Io { path, source });
+                    continue;
+                }
+            };
+            match scripts.add(name, &source) {
+                Ok(mut refused) => errors.append(&mut refused),
+                Err(err) => errors.push(err),
+            }
+        }
+        (scripts, errors)
+    }
+
+    /// Creates an engine with the functions scripts may call, and without
+    /// scripts.
+    fn new() -> Self {
+        let shared = Rc::new(RefCell::new(Shared::default()));
+        let mut engine = rhai::Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        engine.set_max_string_size(MAX_STRING_SIZE);
+        engine.set_max_array_size(MAX_COLLECTION_SIZE);
+        engine.set_max_map_size(MAX_COLLECTION_SIZE);
+        engine.set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new());
+
+        let state = Rc::clone(&shared);
+        engine.register_fn("model", move || state.borrow().snapshot.model.clone());
+        let state = Rc::clone(&shared);
+        engine.register_fn("input", move || state.borrow().snapshot.input.clone());
+        let state = Rc::clone(&shared);
+        engine.register_fn("messages", move || {
+            state
+                .borrow()
+                .snapshot
+                .messages
+                .iter()
+                .map(|message| {
+                    let mut map = rhai::Map::new();
+                    map.insert("role".into(), message.role.clone().into());
+                    map.insert("content".into(), message.content.clone().into());
+                    rhai::Dynamic::from_map(map)
+                })
+                .collect::<rhai::Array>()
+        });
+        let state = Rc::clone(&shared);
+        engine.register_fn("notify", move |text: &str| {
+            state.borrow_mut().effects.notices.push(text.to_string());
+        });
+        let state = Rc::clone(&shared);
+        engine.register_fn("set_input", move |text: &str| {
+            state.borrow_mut().effects.input = Some(text.to_string());
+        });
+        let state = Rc::clone(&shared);
+        engine.register_fn("send", move |prompt: &str| {
+            state.borrow_mut().effects.prompt = Some(prompt.to_string());
+        });
+
+        Scripts {
+            commands: Vec::new(),
+            engine,
+            scripts: Vec::new(),
+            shared,
+        }
+    }
+
+    /// Compiles a script and adds it with its commands.
+    ///
+    /// # Returns
+    ///
+    /// The errors of the commands that were refused.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `ScriptingError` if the script does not compile.
+    fn add(&mut self, name: String, source: &str) -> Result<Vec<ScriptingError>, ScriptingError> {
+        let ast = self
+            .engine
+            .compile(source)
+            .map_err(|err| ScriptingError::Compile {
+                script: name.clone(),
+                message: err.to_string(),
+            })?;
+        let mut refused = Vec::new();
+        for function in ast.iter_functions() {
+            let Some(command) = function.name.strip_prefix(COMMAND_PREFIX) else {
+                continue;
+            };
+            let taken = COMMANDS.iter().any(|builtin| builtin.name == command)
+                || self.commands.iter().any(|other| other.name == command);
+            if taken || function.params.len() != 1 {
+                refused.push(ScriptingError::Command {
+                    script: name.clone(),
+                    name: command.to_string(),
+                });
+            } else {
+                self.commands.push(ScriptCommand {
+                    name: command.to_string(),
+                    script: name.clone(),
+                });
+            }
+        }
+        self.commands.sort_by(|a, b| a.name.cmp(&b.name));
+        self.scripts.push((name, ast));
+        Ok(refused)
+    }
+
+    /// Runs the command entered, if a script defines it.
+    ///
+    /// # Arguments
+    ///
+    /// * `snapshot` - What the script can read of the chat.
+    /// * `input` - The input, such as `/shout hello`.
+    ///
+    /// # Returns
+    ///
+    /// What the script asked for, or `None` if no script defines the
+    /// command.
+    #[must_use]
+    pub fn command(&self, snapshot: &Snapshot, input: &str) -> Option<Outcome> {
+        let rest = input.trim().strip_prefix('/')?;
+        let (name, args) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let command = self.commands.iter().find(|command| command.name == name)?;
+        let (script, ast) = self
+            .scripts
+            .iter()
+            .find(|(script, _)| *script == command.script)?;
+
+        self.begin(snapshot);
+        let function = format!("{COMMAND_PREFIX}{name}");
+        let result = self.call(script, ast, &function, args.trim());
+        let mut outcome = self.finish();
+        match result {
+            Ok(Some(text)) if !text.is_empty() => outcome.effects.notices.push(text),
+            Ok(_) => {}
+            Err(err) => outcome.errors.push(err),
+        }
+        Some(outcome)
+    }
+
+    /// Runs a function of each script defining it, passing the text along.
+    fn transform(&self, function: &str, snapshot: &Snapshot, text: &str) -> Outcome {
+        self.begin(snapshot);
+        let mut current = text.to_string();
+        let mut errors = Vec::new();
+        for (script, ast) in &self.scripts {
+            let defined = ast
+                .iter_functions()
+                .any(|f| f.name == function && f.params.len() == 1);
+            if !defined {
+                continue;
+            }
+            match self.call(script, ast, function, &current) {
+                Ok(Some(text)) => current = text,
+                Ok(None) => {}
+                Err(err) => errors.push(err),
+            }
+        }
+        let mut outcome = self.finish();
+        outcome.text = (current != text).then_some(current);
+        outcome.errors = errors;
+        outcome
+    }
+
+    /// Calls a function of a script with a string.
+    ///
+    /// # Returns
+    ///
+    /// The string the function returned, or `None` if it returned nothing.
+    fn call(
+        &self,
+        script: &str,
+        ast: &rhai::AST,
+        function: &str,
+        arg: &str,
+    ) -> Result<Option<String>, ScriptingError> {
+        let options = rhai::CallFnOptions::new().eval_ast(false);
+        let value: rhai::Dynamic = self
+            .engine
+            .call_fn_with_options(
+                options,
+                &mut rhai::Scope::new(),
+                ast,
+                function,
+                (arg.to_string(),),
+            )
+            .map_err(|err| ScriptingError::Run {
+                script: script.to_string(),
+                message: err.to_string(),
+            })?;
+        if value.is_unit() {
+            return Ok(None);
+        }
+        value
+            .into_string()
+            .map(Some)
+            .map_err(|kind| ScriptingError::Run {
+                script: script.to_string(),
+                message: format!("{function} returned {kind}, not a string"),
+            })
+    }
+
+    /// Gives the scripts a snapshot of the chat to read.
+    fn begin(&self, snapshot: &Snapshot) {
+        let mut shared = self.shared.borrow_mut();
+        shared.snapshot = snapshot.clone();
+        shared.effects = Effects::default();
+    }
+
+    /// Takes what the scripts asked for.
+    fn finish(&self) -> Outcome {
+        Outcome {
+            effects: std::mem::take(&mut self.shared.borrow_mut().effects),
+            ..Outcome::default()
+        }
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+impl Scripts {
+    /// Loads the scripts of a directory, which fails if there are any,
+    /// since chatti was built without the `scripting` feature.
+    #[must_use]
+    pub fn load(dir: &Path) -> (Self, Vec<ScriptingError>) {
+        let errors = match find(dir) {
+            Ok(paths) if paths.is_empty() => Vec::new(),
+            Ok(_) => vec![ScriptingError::Unsupported],
+            Err(source) => vec![ScriptingError::Io {
+                path: dir.to_path_buf(),
+                source,
+            }],
+        };
+        (Scripts::default(), errors)
+    }
+
+    /// Runs nothing, since no script is loaded.
+    #[must_use]
+    pub fn command(&self, _: &Snapshot, _: &str) -> Option<Outcome> {
+        None
+    }
+
+    /// Leaves the text as it is, since no script is loaded.
+    fn transform(&self, _: &str, _: &Snapshot, _: &str) -> Outcome {
+        Outcome::default()
+    }
+}
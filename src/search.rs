@@ -0,0 +1,178 @@
+//! Searches the messages of all saved sessions.
+//!
+//! `/search <query>` asks the session store for the sessions that mention
+//! the query, then finds the matching messages in each of them and cuts a
+//! snippet around every match. The results are listed in a popup, and
+//! choosing one opens its session with the matching message selected.
+
+use std::ops::Range;
+
+use crate::{config::FSError, store::SessionStore};
+
+/// The most results listed for one search.
+pub const MAX_HITS: usize = 50;
+
+/// The most results listed for one session.
+const MAX_HITS_PER_SESSION: usize = 3;
+
+/// How many characters of context a snippet shows on each side of the
+/// match.
+const CONTEXT: usize = 30;
+
+/// A message of a saved session that matches a search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    /// The identifier of the session.
+    pub session: String,
+    /// The title of the session.
+    pub title: String,
+    /// The position of the message in the session.
+    pub message: usize,
+    /// The text around the match, on one line.
+    pub snippet: String,
+    /// Where the match is in the snippet, in bytes.
+    pub matched: Range<usize>,
+}
+
+/// The results of a search, as listed in the popup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Results {
+    /// The text searched for.
+    pub query: String,
+    /// The matching messages, most recently modified session first.
+    pub hits: Vec<Hit>,
+    /// The position of the highlighted result.
+    pub selected: usize,
+}
+
+impl Results {
+    /// Lists the results of a search with the first one highlighted.
+    #[must_use]
+    pub fn new(query: String, hits: Vec<Hit>) -> Self {
+        Results {
+            query,
+            hits,
+            selected: 0,
+        }
+    }
+
+    /// Highlights the previous result.
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Highlights the next result.
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.hits.len().saturating_sub(1));
+    }
+
+    /// Returns the highlighted result.
+    #[must_use]
+    pub fn selected(&self) -> Option<&Hit> {
+        self.hits.get(self.selected)
+    }
+}
+
+/// Finds the messages of the stored sessions that contain `query`,
+/// ignoring case.
+///
+/// At most [`MAX_HITS`] results are returned, and a few per session, so
+/// one long conversation does not crowd out the others.
+///
+/// # Errors
+///
+/// Returns an `FSError` if the sessions cannot be searched or read.
+pub fn find(store: &dyn SessionStore, query: &str) -> Result<Vec<Hit>, FSError> {
+    let query = query.trim();
+    let mut hits = Vec::new();
+    if query.is_empty() {
+        return Ok(hits);
+    }
+    for info in store.search(query)? {
+        let session = store.load(&info.id)?;
+        let found = session
+            .messages
+            .iter()
+            .enumerate()
+            .filter_map(|(index, message)| {
+                let (snippet, matched) = snippet(&message.content, query)?;
+                Some(Hit {
+                    session: session.id.clone(),
+                    title: session.title.clone(),
+                    message: index,
+                    snippet,
+                    matched,
+                })
+            })
+            .take(MAX_HITS_PER_SESSION);
+        hits.extend(found);
+        if hits.len() >= MAX_HITS {
+            hits.truncate(MAX_HITS);
+            break;
+        }
+    }
+    Ok(hits)
+}
+
+/// Cuts a one-line snippet around the first match of `query` in `text`,
+/// ignoring case, and returns it with the position of the match in it.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::search::snippet;
+///
+/// let (text, matched) = snippet("Borrowing rules:\nOne mutable REFERENCE at a time", "reference").unwrap();
+/// assert_eq!(text, "Borrowing rules: One mutable REFERENCE at a time");
+/// assert_eq!(&text[matched], "REFERENCE");
+/// assert_eq!(snippet("Hello", "bye"), None);
+/// ```
+#[must_use]
+pub fn snippet(text: &str, query: &str) -> Option<(String, Range<usize>)> {
+    let found = find_ignoring_case(text, query)?;
+    let before = &text[..found.start];
+    let after = &text[found.end..];
+
+    let skipped = before.chars().count().saturating_sub(CONTEXT);
+    let mut snippet = if skipped > 0 {
+        String::from("…")
+    } else {
+        String::new()
+    };
+    snippet.extend(before.chars().skip(skipped).map(one_line));
+    let start = snippet.len();
+    snippet.extend(text[found].chars().map(one_line));
+    let end = snippet.len();
+    snippet.extend(after.chars().take(CONTEXT).map(one_line));
+    if after.chars().nth(CONTEXT).is_some() {
+        snippet.push('…');
+    }
+    Some((snippet, start..end))
+}
+
+/// Returns the byte range of the first occurrence of `query` in `text`,
+/// comparing characters without regard to case.
+fn find_ignoring_case(text: &str, query: &str) -> Option<Range<usize>> {
+    if query.is_empty() {
+        return None;
+    }
+    text.char_indices().find_map(|(start, _)| {
+        let mut rest = text[start..].char_indices();
+        for wanted in query.chars() {
+            let (_, found) = rest.next()?;
+            if !found.to_lowercase().eq(wanted.to_lowercase()) {
+                return None;
+            }
+        }
+        Some(start..start + rest.offset())
+    })
+}
+
+/// Replaces line breaks and tabs with spaces.
+fn one_line(c: char) -> char {
+    if c.is_whitespace() {
+        ' '
+    } else {
+        c
+    }
+}
@@ -0,0 +1,347 @@
+//! Persists chat sessions to disk.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::PathBuf,
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{config::FSError, message::Message, store};
+
+/// The sessions directory of the project chatti was started in, if any.
+static PROJECT_SESSIONS_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Stores sessions, and the draft and recovery state next to them, in `dir`
+/// instead of the global sessions directory, for project mode. Only the
+/// first call has an effect.
+pub fn use_project_dir(dir: PathBuf) {
+    let _ = PROJECT_SESSIONS_DIR.set(dir);
+}
+
+/// How a conversation is written out as text.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TranscriptFormat {
+    /// Each message under a `Role:` line, without markup.
+    Plain,
+    /// A markdown document, as `/export` writes it.
+    #[default]
+    Markdown,
+}
+
+/// A saved conversation.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Session {
+    /// Unique identifier, also used as the file name.
+    pub id: String,
+    /// Human-readable title.
+    pub title: String,
+    /// Creation time as seconds since the Unix epoch.
+    pub created_at: u64,
+    /// Last modification time as seconds since the Unix epoch.
+    pub updated_at: u64,
+    /// The system prompt sent before the conversation, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// The conversation messages.
+    pub messages: Vec<Message>,
+    /// How many of the messages had been read when the session was last
+    /// open, so the ones added since are marked as new when it is opened
+    /// again.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_read: Option<usize>,
+}
+
+impl Session {
+    /// Creates a new, empty session with a fresh identifier.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::session::Session;
+    ///
+    /// let session = Session::new();
+    /// assert!(session.messages.is_empty());
+    /// assert!(!session.id.is_empty());
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        Session {
+            id: format!("{}", now.as_millis()),
+            title: String::from("New chat"),
+            created_at: now.as_secs(),
+            updated_at: now.as_secs(),
+            system_prompt: None,
+            messages: Vec::new(),
+            last_read: None,
+        }
+    }
+
+    /// Creates a session from existing messages, deriving the title from the
+    /// first user message.
+    ///
+    /// # Arguments
+    ///
+    /// * `messages` - The conversation messages.
+    #[must_use]
+    pub fn from_messages(messages: Vec<Message>) -> Self {
+        let mut session = Session::new();
+        session.messages = messages;
+        session.title = session.derive_title();
+        session
+    }
+
+    /// Derives a short title from the first user message.
+    #[must_use]
+    pub fn derive_title(&self) -> String {
+        self.messages
+            .iter()
+            .find(|m| m.role == "user")
+            .and_then(|m| m.content.lines().find(|l| !l.trim().is_empty()))
+            .map_or_else(
+                || String::from("New chat"),
+                |line| line.trim().chars().take(60).collect(),
+            )
+    }
+
+    /// Formats the session as a markdown transcript with `## User` and
+    /// `## Assistant` headings, which `/import` reads back.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::Message;
+    /// use chatti::session::Session;
+    ///
+    /// let session = Session::from_messages(vec![
+    ///     Message::new("user", "Hello"),
+    ///     Message::new("assistant", "Hi!"),
+    /// ]);
+    /// assert_eq!(
+    ///     session.to_markdown(),
+    ///     "# Hello\n\n## User\n\nHello\n\n## Assistant\n\nHi!\n"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn to_markdown(&self) -> String {
+        let mut text = format!("# {}\n", self.title);
+        for (role, content) in self.entries() {
+            text.push_str(&format!("\n## {role}\n\n{}\n", content.trim_end()));
+        }
+        text
+    }
+
+    /// Formats the session as plain text, each message under a `Role:`
+    /// line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::Message;
+    /// use chatti::session::Session;
+    ///
+    /// let session = Session::from_messages(vec![
+    ///     Message::new("user", "Hello"),
+    ///     Message::new("assistant", "Hi!"),
+    /// ]);
+    /// assert_eq!(session.to_plain_text(), "User:\nHello\n\nAssistant:\nHi!\n");
+    /// ```
+    #[must_use]
+    pub fn to_plain_text(&self) -> String {
+        self.entries()
+            .map(|(role, content)| format!("{role}:\n{}\n", content.trim_end()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Formats the session in the given format.
+    #[must_use]
+    pub fn transcript(&self, format: TranscriptFormat) -> String {
+        match format {
+            TranscriptFormat::Plain => self.to_plain_text(),
+            TranscriptFormat::Markdown => self.to_markdown(),
+        }
+    }
+
+    /// Returns the system prompt and messages with the heading of their
+    /// role.
+    fn entries(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        let system = self
+            .system_prompt
+            .iter()
+            .map(|prompt| ("System", prompt.as_str()));
+        let messages = self.messages.iter().map(|message| {
+            let role = if message.role == "user" {
+                "User"
+            } else {
+                "Assistant"
+            };
+            (role, message.content.as_str())
+        });
+        system.chain(messages)
+    }
+
+    /// Returns the directory where sessions are stored: the project's, if
+    /// one was set with [`use_project_dir`], or the global one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FSError::HomeDirNotFound` if the home directory cannot be found.
+    pub fn sessions_dir() -> Result<PathBuf, FSError> {
+        if let Some(dir) = PROJECT_SESSIONS_DIR.get() {
+            return Ok(dir.clone());
+        }
+        Ok(dirs::home_dir()
+            .ok_or(FSError::HomeDirNotFound)?
+            .join(".config")
+            .join("chatti")
+            .join("sessions"))
+    }
+
+    /// Writes the session to the session store, by default the sessions
+    /// directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the session cannot be written.
+    pub fn save(&mut self) -> Result<(), FSError> {
+        self.updated_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        store::with_store(|store| store.save(self))
+    }
+
+    /// Loads a session by identifier from the session store.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the session cannot be read or parsed.
+    pub fn load(id: &str) -> Result<Self, FSError> {
+        store::with_store(|store| store.load(id))
+    }
+}
+
+/// Returns the path of the file holding the unsent draft.
+///
+/// # Errors
+///
+/// Returns `FSError::HomeDirNotFound` if the home directory cannot be found.
+pub fn draft_path() -> Result<PathBuf, FSError> {
+    Ok(Session::sessions_dir()?.with_file_name("draft.txt"))
+}
+
+/// Saves the unsent draft so it survives a restart.
+///
+/// # Errors
+///
+/// Returns an `FSError` if the file cannot be written.
+pub fn save_draft(draft: &str) -> Result<(), FSError> {
+    let path = draft_path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, draft)?;
+    Ok(())
+}
+
+/// Loads the unsent draft, if one was saved.
+///
+/// # Errors
+///
+/// Returns an `FSError` if the file exists but cannot be read.
+pub fn load_draft() -> Result<Option<String>, FSError> {
+    match fs::read_to_string(draft_path()?) {
+        Ok(draft) if !draft.trim().is_empty() => Ok(Some(draft)),
+        Ok(_) => Ok(None),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Removes the saved draft once it has been sent.
+///
+/// # Errors
+///
+/// Returns an `FSError` if the file exists but cannot be removed.
+pub fn clear_draft() -> Result<(), FSError> {
+    match fs::remove_file(draft_path()?) {
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    }
+}
+
+/// The state written periodically so it can be restored after a crash.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Recovery {
+    /// The identifier of the session being shown, if it was saved.
+    pub session_id: Option<String>,
+    /// The system prompt of the conversation.
+    pub system_prompt: Option<String>,
+    /// The conversation, including any partial response.
+    pub messages: Vec<Message>,
+    /// The unsent input.
+    pub draft: String,
+}
+
+impl Recovery {
+    /// Returns the path of the recovery file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `FSError::HomeDirNotFound` if the home directory cannot be found.
+    pub fn path() -> Result<PathBuf, FSError> {
+        Ok(Session::sessions_dir()?.with_file_name("recovery.json"))
+    }
+
+    /// Returns `true` if there is nothing worth restoring.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty() && self.draft.trim().is_empty()
+    }
+
+    /// Writes the recovery file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the file cannot be written.
+    pub fn save(&self) -> Result<(), FSError> {
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Loads the recovery file left by a previous run, if there is one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the file exists but cannot be read or parsed.
+    pub fn load() -> Result<Option<Self>, FSError> {
+        match fs::read_to_string(Self::path()?) {
+            Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Removes the recovery file on a clean exit.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the file exists but cannot be removed.
+    pub fn clear() -> Result<(), FSError> {
+        match fs::remove_file(Self::path()?) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Expands `$(command)` substitutions in prompts.
+//!
+//! Only commands whose programs are on the configured allow-list are run.
+//! Every stage of a pipeline or command list must be allowed, and nested
+//! substitutions, output redirection to files, environment assignments and
+//! wrappers that run another program are rejected.
+
+use std::io::ErrorKind;
+use std::process::Command;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+use crate::process;
+
+/// The maximum number of bytes of command output inlined into a prompt.
+const MAX_OUTPUT_BYTES: usize = 20 * 1024;
+
+/// How long a command may run before it is killed.
+pub const RUN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Programs that run the program named in their arguments, which would
+/// count as themselves on the allow-list.
+const WRAPPERS: &[&str] = &["builtin", "command", "env", "exec"];
+
+/// Represents errors that can occur while expanding substitutions.
+#[derive(Debug, thiserror::Error, PartialEq)]
+pub enum ShellError {
+    /// The command uses a program that is not on the allow-list.
+    #[error("`{0}` is not an allowed command")]
+    NotAllowed(String),
+
+    /// The command uses a construct that could bypass the allow-list.
+    #[error("unsupported shell syntax in `{0}`")]
+    Unsupported(String),
+
+    /// The command did not finish in time and was killed.
+    #[error("`{0}` did not finish within {secs} seconds", secs = RUN_TIMEOUT.as_secs())]
+    TimedOut(String),
+
+    /// The command was cancelled and killed.
+    #[error("`{0}` was cancelled")]
+    Cancelled(String),
+
+    /// A `$(` is never closed.
+    #[error("unclosed $( in prompt")]
+    Unclosed,
+
+    /// The command could not be started.
+    #[error("could not run `{command}`: {message}")]
+    Io {
+        /// The command that was run.
+        command: String,
+        /// The underlying error.
+        message: String,
+    },
+}
+
+/// Returns the commands of all `$(...)` substitutions in `text`, in order.
+///
+/// # Errors
+///
+/// Returns `ShellError::Unclosed` if a substitution is not closed.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::shell::substitutions;
+///
+/// assert_eq!(
+///     substitutions("Explain: $(cargo build 2>&1 | tail -40)").unwrap(),
+///     vec!["cargo build 2>&1 | tail -40"]
+/// );
+/// assert!(substitutions("costs $5 (maybe)").unwrap().is_empty());
+/// ```
+pub fn substitutions(text: &str) -> Result<Vec<&str>, ShellError> {
+    Ok(spans(text)?
+        .into_iter()
+        .map(|(start, end)| &text[start + 2..end - 1])
+        .collect())
+}
+
+/// Checks that every program in `command` is on the allow-list.
+///
+/// # Errors
+///
+/// Returns `ShellError::NotAllowed` for a program that is not allowed, and
+/// `ShellError::Unsupported` for nested substitutions, backticks, output
+/// redirection to files, and stages starting with an environment assignment
+/// such as `LD_PRELOAD=...` or a wrapper such as `env`, which could run
+/// other code as an allowed program.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::shell::check_allowed;
+///
+/// let allowed = vec!["cargo".to_string(), "tail".to_string()];
+/// assert!(check_allowed("cargo build 2>&1 | tail -40", &allowed).is_ok());
+/// assert!(check_allowed("cargo build; rm -rf target", &allowed).is_err());
+/// assert!(check_allowed("cargo build > out.txt", &allowed).is_err());
+/// assert!(check_allowed("RUSTC_WRAPPER=/tmp/x cargo build", &allowed).is_err());
+/// ```
+pub fn check_allowed(command: &str, allowed: &[String]) -> Result<(), ShellError> {
+    let without_dup = command.replace("2>&1", "").replace(">&2", "");
+    if command.contains("$(") || command.contains('`') || without_dup.contains('>') {
+        return Err(ShellError::Unsupported(command.to_string()));
+    }
+
+    for stage in without_dup.split(['|', ';', '&', '\n']) {
+        let Some(program) = stage.split_whitespace().next() else {
+            continue;
+        };
+        let name = program.rsplit('/').next().unwrap_or(program);
+        if program.contains('=') || WRAPPERS.contains(&name) {
+            return Err(ShellError::Unsupported(command.to_string()));
+        }
+        if !allowed.iter().any(|allowed| allowed == name) {
+            return Err(ShellError::NotAllowed(name.to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// Runs `command` with `sh` and returns its standard output, truncated to a
+/// reasonable size. A failing exit status is not an error, since the output
+/// of failing commands is often what the prompt is about. A command still
+/// running after [`RUN_TIMEOUT`] is killed.
+///
+/// # Errors
+///
+/// Returns `ShellError::Io` if the shell cannot be started, and
+/// `ShellError::TimedOut` if the command ran out of time.
+pub fn run(command: &str) -> Result<String, ShellError> {
+    run_cancellable(command, &AtomicBool::new(false))
+}
+
+/// Runs `command` like [`run`], killing it once `cancelled` is set.
+fn run_cancellable(command: &str, cancelled: &AtomicBool) -> Result<String, ShellError> {
+    let mut sh = Command::new("sh");
+    sh.arg("-c").arg(command);
+    let output =
+        process::run_cancellable(&mut sh, None, RUN_TIMEOUT, cancelled).map_err(|err| match err
+            .kind()
+        {
+            ErrorKind::TimedOut => ShellError::TimedOut(command.to_string()),
+            ErrorKind::Interrupted => ShellError::Cancelled(command.to_string()),
+            _ => ShellError::Io {
+                command: command.to_string(),
+                message: err.to_string(),
+            },
+        })?;
+
+    let mut text = output.stdout;
+    if text.len() > MAX_OUTPUT_BYTES {
+        let mut end = MAX_OUTPUT_BYTES;
+        while !text.is_char_boundary(end) {
+            end -= 1;
+        }
+        text.truncate(end);
+    }
+    Ok(text.trim_end().to_string())
+}
+
+/// Replaces every `$(command)` in `text` with the command's output.
+///
+/// # Arguments
+///
+/// * `text` - The prompt to expand.
+/// * `allowed` - The programs that may be run.
+///
+/// # Errors
+///
+/// Returns a `ShellError` if a command is not allowed or cannot be run; no
+/// command is run unless all of them are allowed.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::shell::expand;
+///
+/// let allowed = vec!["echo".to_string()];
+/// assert_eq!(expand("Say $(echo hi)!", &allowed).unwrap(), "Say hi!");
+/// ```
+pub fn expand(text: &str, allowed: &[String]) -> Result<String, ShellError> {
+    expand_cancellable(text, allowed, &AtomicBool::new(false))
+}
+
+/// Replaces every `$(command)` in `text` like [`expand`], killing the
+/// running command once `cancelled` is set.
+///
+/// # Errors
+///
+/// Returns the errors of [`expand`], and `ShellError::Cancelled` if the
+/// expansion was cancelled.
+pub fn expand_cancellable(
+    text: &str,
+    allowed: &[String],
+    cancelled: &AtomicBool,
+) -> Result<String, ShellError> {
+    let spans = spans(text)?;
+    for &(start, end) in &spans {
+        check_allowed(&text[start + 2..end - 1], allowed)?;
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for (start, end) in spans {
+        result.push_str(&text[last..start]);
+        result.push_str(&run_cancellable(&text[start + 2..end - 1], cancelled)?);
+        last = end;
+    }
+    result.push_str(&text[last..]);
+    Ok(result)
+}
+
+/// Finds the byte ranges of `$(...)` substitutions, balancing nested
+/// parentheses.
+fn spans(text: &str) -> Result<Vec<(usize, usize)>, ShellError> {
+    let bytes = text.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i + 1 < bytes.len() {
+        if bytes[i] != b'$' || bytes[i + 1] != b'(' {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        let mut depth = 0;
+        let mut end = None;
+        for (offset, &byte) in bytes[i + 1..].iter().enumerate() {
+            match byte {
+                b'(' => depth += 1,
+                b')' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        end = Some(i + 1 + offset + 1);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        let end = end.ok_or(ShellError::Unclosed)?;
+        spans.push((start, end));
+        i = end;
+    }
+
+    Ok(spans)
+}
@@ -0,0 +1,190 @@
+//! Reads responses aloud with an external text-to-speech command.
+//!
+//! Streamed responses are split into sentences as they arrive, and each
+//! complete sentence is passed to the configured command (such as `espeak`
+//! or `say`) on standard input. Code blocks and markdown markup are skipped.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+use tracing::error;
+
+use crate::hooks;
+
+/// Splits streamed text into sentences suitable for speaking.
+#[derive(Debug, Default)]
+pub struct SentenceSplitter {
+    buffer: String,
+    in_code: bool,
+}
+
+impl SentenceSplitter {
+    /// Creates an empty splitter.
+    #[must_use]
+    pub fn new() -> Self {
+        SentenceSplitter::default()
+    }
+
+    /// Adds streamed content and returns the sentences it completes.
+    ///
+    /// A sentence ends at a line break, or at `.`, `!` or `?` followed by
+    /// whitespace.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::speech::SentenceSplitter;
+    ///
+    /// let mut splitter = SentenceSplitter::new();
+    /// assert_eq!(splitter.push("Hello there. How"), vec!["Hello there."]);
+    /// assert_eq!(splitter.push(" are **you**?\n"), vec!["How are you?"]);
+    /// assert_eq!(splitter.finish(), None);
+    /// ```
+    pub fn push(&mut self, content: &str) -> Vec<String> {
+        self.buffer.push_str(content);
+        let mut sentences = Vec::new();
+        while let Some(end) = sentence_end(&self.buffer) {
+            let piece: String = self.buffer.drain(..end).collect();
+            if let Some(sentence) = self.clean(&piece) {
+                sentences.push(sentence);
+            }
+        }
+        sentences
+    }
+
+    /// Returns the incomplete sentence left at the end of a response, and
+    /// resets the splitter.
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.buffer);
+        let sentence = self.clean(&rest);
+        self.in_code = false;
+        sentence
+    }
+
+    /// Discards buffered content, for example when a response is cancelled.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+        self.in_code = false;
+    }
+
+    fn clean(&mut self, piece: &str) -> Option<String> {
+        let piece = piece.trim();
+        if piece.starts_with("```") {
+            self.in_code = !self.in_code;
+            return None;
+        }
+        if self.in_code {
+            return None;
+        }
+        let piece = piece.trim_start_matches(['#', '>', '-', '*', '+', ' ']);
+        let sentence: String = piece.chars().filter(|c| !matches!(c, '*' | '`')).collect();
+        sentence
+            .chars()
+            .any(char::is_alphanumeric)
+            .then(|| sentence.trim().to_string())
+    }
+}
+
+fn sentence_end(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\n' => return Some(i + 1),
+            '.' | '!' | '?' if chars.peek().is_some_and(|(_, next)| next.is_whitespace()) => {
+                return Some(i + 1);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Speaks sentences in the background, one after the other.
+#[derive(Debug)]
+pub struct Speaker {
+    sender: mpsc::Sender<String>,
+    enabled: Arc<AtomicBool>,
+    splitter: SentenceSplitter,
+}
+
+impl Speaker {
+    /// Starts a speaker that runs `command` for each sentence.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The text-to-speech shell command, which reads the
+    ///   sentence from standard input.
+    /// * `model` - The model whose responses are spoken.
+    /// * `enabled` - Whether speaking starts enabled.
+    #[must_use]
+    pub fn spawn(command: String, model: String, enabled: bool) -> Self {
+        let (sender, receiver) = mpsc::channel::<String>();
+        let enabled = Arc::new(AtomicBool::new(enabled));
+        let worker_enabled = Arc::clone(&enabled);
+        thread::spawn(move || {
+            for sentence in receiver {
+                if !worker_enabled.load(Ordering::Relaxed) {
+                    continue;
+                }
+                if let Err(err) = hooks::run(&command, &sentence, &model) {
+                    error!(%err, "text-to-speech failed");
+                }
+            }
+        });
+        Speaker {
+            sender,
+            enabled,
+            splitter: SentenceSplitter::new(),
+        }
+    }
+
+    /// Returns `true` if responses are being spoken.
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Turns speaking on or off. Turning it off also skips the sentences
+    /// that are still queued.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        if !enabled {
+            self.splitter.clear();
+        }
+    }
+
+    /// Speaks the sentences completed by new streamed content.
+    pub fn push(&mut self, content: &str) {
+        if !self.is_enabled() {
+            return;
+        }
+        for sentence in self.splitter.push(content) {
+            self.say(sentence);
+        }
+    }
+
+    /// Speaks the rest of a completed response.
+    pub fn finish(&mut self) {
+        if let Some(sentence) = self.splitter.finish() {
+            if self.is_enabled() {
+                self.say(sentence);
+            }
+        }
+    }
+
+    /// Drops the unfinished sentence of a cancelled or stopped response.
+    pub fn discard(&mut self) {
+        self.splitter.clear();
+    }
+
+    fn say(&self, sentence: String) {
+        if self.sender.send(sentence).is_err() {
+            error!("text-to-speech worker stopped");
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Summarizes a conversation for `/summary-stats`.
+//!
+//! The token counts and response times are the ones recorded on each
+//! answer when it arrived. Servers that do not report token counts are
+//! estimated from the text, and the summary says so.
+
+use std::time::Duration;
+
+use crate::{message::Message, tokens::estimate_tokens};
+
+/// The statistics of a conversation.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Stats {
+    /// The number of prompts.
+    pub turns: usize,
+    /// The number of answers.
+    pub answers: usize,
+    /// The tokens of the prompts, including the conversation sent with
+    /// them.
+    pub prompt_tokens: usize,
+    /// The tokens of the answers.
+    pub completion_tokens: usize,
+    /// Whether any of the token counts were estimated from the text.
+    pub estimated: bool,
+    /// The mean time the timed answers took.
+    pub mean_response: Option<Duration>,
+    /// The longest answer: its turn, its tokens, and how long it took if
+    /// that was recorded.
+    pub longest: Option<(usize, usize, Option<Duration>)>,
+}
+
+impl Stats {
+    /// Computes the statistics of a conversation.
+    ///
+    /// Translations are not answers to a prompt and are left out.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::{Message, Metadata};
+    /// use chatti::stats::Stats;
+    ///
+    /// let mut answer = Message::new("assistant", "Hello!");
+    /// answer.metadata = Some(Metadata {
+    ///     prompt_tokens: Some(12),
+    ///     completion_tokens: Some(3),
+    ///     total_ms: Some(1500),
+    ///     ..Metadata::default()
+    /// });
+    /// let stats = Stats::of(&[Message::new("user", "Hi"), answer]);
+    /// assert_eq!(stats.turns, 1);
+    /// assert_eq!(stats.prompt_tokens + stats.completion_tokens, 15);
+    /// assert!(!stats.estimated);
+    /// ```
+    #[must_use]
+    pub fn of(messages: &[Message]) -> Self {
+        let mut stats = Stats::default();
+        let mut times = Vec::new();
+        let mut sent = 0;
+        for message in messages.iter().filter(|m| m.is_sent()) {
+            if message.role == "user" {
+                stats.turns += 1;
+                sent += estimate_tokens(&message.request_content());
+                continue;
+            }
+            stats.answers += 1;
+            let metadata = message.metadata.clone().unwrap_or_default();
+            let reported = |tokens: Option<u64>| tokens.and_then(|n| usize::try_from(n).ok());
+            let prompt = reported(metadata.prompt_tokens);
+            let completion = reported(metadata.completion_tokens);
+            stats.estimated |= prompt.is_none() || completion.is_none();
+            let completion = completion.unwrap_or_else(|| estimate_tokens(&message.content));
+            stats.prompt_tokens += prompt.unwrap_or(sent);
+            stats.completion_tokens += completion;
+            sent += completion;
+
+            let time = metadata.total_ms.map(Duration::from_millis);
+            times.extend(time);
+            if stats
+                .longest
+                .is_none_or(|(_, tokens, _)| completion > tokens)
+            {
+                stats.longest = Some((stats.turns, completion, time));
+            }
+        }
+        stats.mean_response = u32::try_from(times.len())
+            .ok()
+            .filter(|count| *count > 0)
+            .map(|count| times.iter().sum::<Duration>() / count);
+        stats
+    }
+
+    /// Describes the statistics as the text of a system message.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::stats::Stats;
+    /// use std::time::Duration;
+    ///
+    /// let stats = Stats {
+    ///     turns: 2,
+    ///     answers: 2,
+    ///     prompt_tokens: 120,
+    ///     completion_tokens: 300,
+    ///     estimated: false,
+    ///     mean_response: Some(Duration::from_millis(2500)),
+    ///     longest: Some((2, 200, Some(Duration::from_millis(3100)))),
+    /// };
+    /// assert_eq!(
+    ///     stats.describe(),
+    ///     "Conversation statistics:\n\
+    ///      - Turns: 2 (2 answer(s))\n\
+    ///      - Tokens: 420 (120 in prompts, 300 in answers)\n\
+    ///      - Average response time: 2.50s\n\
+    ///      - Longest answer: 200 tokens, in turn 2, took 3.10s"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let approximately = if self.estimated { "~" } else { "" };
+        let mut text = format!(
+            "Conversation statistics:\n- Turns: {} ({} answer(s))\n- Tokens: {approximately}{} ({} in prompts, {} in answers)",
+            self.turns,
+            self.answers,
+            self.prompt_tokens + self.completion_tokens,
+            self.prompt_tokens,
+            self.completion_tokens,
+        );
+        if let Some(mean) = self.mean_response {
+            text.push_str(&format!(
+                "\n- Average response time: {:.2}s",
+                mean.as_secs_f64()
+            ));
+        }
+        if let Some((turn, tokens, time)) = self.longest {
+            text.push_str(&format!(
+                "\n- Longest answer: {approximately}{tokens} tokens, in turn {turn}"
+            ));
+            if let Some(time) = time {
+                text.push_str(&format!(", took {:.2}s", time.as_secs_f64()));
+            }
+        }
+        if self.estimated {
+            text.push_str("\n\n~ Some token counts were not reported by the server and are estimated from the text");
+        }
+        text
+    }
+}
@@ -0,0 +1,414 @@
+//! Where sessions are kept.
+//!
+//! [`SessionStore`] is the interface between sessions and their storage,
+//! so other backends, such as one syncing to a server, can be plugged in
+//! with [`use_store`]. Sessions are kept as JSON files by default
+//! ([`JsonStore`]); with the `sqlite` cargo feature, `session_store =
+//! "sqlite"` keeps them in one SQLite database instead ([`SqliteStore`]).
+
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use crate::{config::FSError, session::Session};
+
+/// The store set with [`use_store`], if any.
+static STORE: OnceLock<Box<dyn SessionStore>> = OnceLock::new();
+
+/// Keeps sessions in `store` instead of the JSON files of the sessions
+/// directory. Only the first call has an effect.
+pub fn use_store(store: Box<dyn SessionStore>) {
+    let _ = STORE.set(store);
+}
+
+/// Runs `f` with the store set with [`use_store`], or with a [`JsonStore`]
+/// on the sessions directory.
+///
+/// # Errors
+///
+/// Returns the error of `f`, or `FSError::HomeDirNotFound` if the sessions
+/// directory cannot be found.
+pub fn with_store<T>(
+    f: impl FnOnce(&dyn SessionStore) -> Result<T, FSError>,
+) -> Result<T, FSError> {
+    match STORE.get() {
+        Some(store) => f(store.as_ref()),
+        None => f(&JsonStore::new(Session::sessions_dir()?)),
+    }
+}
+
+/// The storage backends that can be configured.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// A JSON file per session in the sessions directory.
+    #[default]
+    Json,
+    /// An SQLite database next to the sessions directory; needs the
+    /// `sqlite` cargo feature.
+    Sqlite,
+}
+
+/// Opens the configured backend and makes it the store of sessions.
+///
+/// # Errors
+///
+/// Returns an `FSError` if the database cannot be opened, or if SQLite is
+/// configured but chatti was built without the `sqlite` feature.
+pub fn open(backend: Backend) -> Result<(), FSError> {
+    match backend {
+        Backend::Json => Ok(()),
+        #[cfg(feature = "sqlite")]
+        Backend::Sqlite => {
+            let path = Session::sessions_dir()?.with_file_name("sessions.db");
+            use_store(Box::new(SqliteStore::open(&path)?));
+            Ok(())
+        }
+        #[cfg(not(feature = "sqlite"))]
+        Backend::Sqlite => Err(FSError::Unsupported(String::from(
+            "session_store = \"sqlite\" needs chatti built with the sqlite feature",
+        ))),
+    }
+}
+
+/// What a listing shows of a session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// The identifier the session is loaded by.
+    pub id: String,
+    /// The title.
+    pub title: String,
+    /// Last modification time as seconds since the Unix epoch.
+    pub updated_at: u64,
+    /// The number of messages.
+    pub messages: usize,
+}
+
+impl From<&Session> for SessionInfo {
+    fn from(session: &Session) -> Self {
+        SessionInfo {
+            id: session.id.clone(),
+            title: session.title.clone(),
+            updated_at: session.updated_at,
+            messages: session.messages.len(),
+        }
+    }
+}
+
+/// A place sessions are saved to and loaded from.
+pub trait SessionStore: Send + Sync {
+    /// Saves a session, replacing the one with the same identifier.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the session cannot be written.
+    fn save(&self, session: &Session) -> Result<(), FSError>;
+
+    /// Loads a session by identifier.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the session does not exist or cannot be read.
+    fn load(&self, id: &str) -> Result<Session, FSError>;
+
+    /// Deletes a session by identifier; deleting a missing session is not
+    /// an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the session cannot be removed.
+    fn delete(&self, id: &str) -> Result<(), FSError>;
+
+    /// Lists the sessions, most recently modified first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the sessions cannot be listed.
+    fn list(&self) -> Result<Vec<SessionInfo>, FSError>;
+
+    /// Lists the sessions whose title or messages contain `query`, ignoring
+    /// case, most recently modified first.
+    ///
+    /// The default implementation loads every listed session; backends
+    /// that can search their storage directly should override it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the sessions cannot be listed or read.
+    fn search(&self, query: &str) -> Result<Vec<SessionInfo>, FSError> {
+        let query = query.to_lowercase();
+        let mut found = Vec::new();
+        for info in self.list()? {
+            let session = self.load(&info.id)?;
+            if matches(&session, &query) {
+                found.push(info);
+            }
+        }
+        Ok(found)
+    }
+}
+
+/// Returns `true` if the title or a message of `session` contains the
+/// lowercase `query`.
+fn matches(session: &Session, query: &str) -> bool {
+    session.title.to_lowercase().contains(query)
+        || session
+            .messages
+            .iter()
+            .any(|message| message.content.to_lowercase().contains(query))
+}
+
+/// Keeps each session as a `<id>.json` file in a directory.
+#[derive(Debug, Clone)]
+pub struct JsonStore {
+    dir: PathBuf,
+}
+
+impl JsonStore {
+    /// Creates a store on `dir`, which is created on the first save.
+    #[must_use]
+    pub fn new(dir: PathBuf) -> Self {
+        JsonStore { dir }
+    }
+
+    /// Returns the directory the sessions are kept in.
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Reads every session file, skipping files that are not sessions.
+    fn sessions(&self) -> Result<Vec<Session>, FSError> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut sessions: Vec<Session> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .filter_map(|content| serde_json::from_str(&content).ok())
+            .collect();
+        sessions.sort_by_key(|session| Reverse(session.updated_at));
+        Ok(sessions)
+    }
+}
+
+impl SessionStore for JsonStore {
+    fn save(&self, session: &Session) -> Result<(), FSError> {
+        fs::create_dir_all(&self.dir)?;
+        let json = serde_json::to_string_pretty(session)?;
+        fs::write(self.dir.join(format!("{}.json", session.id)), json)?;
+        Ok(())
+    }
+
+    fn load(&self, id: &str) -> Result<Session, FSError> {
+        let content = fs::read_to_string(self.dir.join(format!("{id}.json")))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), FSError> {
+        match fs::remove_file(self.dir.join(format!("{id}.json"))) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn list(&self) -> Result<Vec<SessionInfo>, FSError> {
+        Ok(self.sessions()?.iter().map(SessionInfo::from).collect())
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<SessionInfo>, FSError> {
+        let query = query.to_lowercase();
+        Ok(self
+            .sessions()?
+            .iter()
+            .filter(|session| matches(session, &query))
+            .map(SessionInfo::from)
+            .collect())
+    }
+}
+
+/// Keeps sessions in an SQLite database, one row per session.
+///
+/// When SQLite was built with FTS5, the title and messages are also kept
+/// in a full-text index, and searches match words starting with the
+/// query's words there instead of scanning every message.
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteStore {
+    connection: std::sync::Mutex<rusqlite::Connection>,
+    full_text: bool,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStore {
+    /// Opens the database at `path`, creating it and its directory if
+    /// needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an `FSError` if the directory cannot be created or the
+    /// database cannot be opened.
+    pub fn open(path: &Path) -> Result<Self, FSError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let connection = rusqlite::Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS sessions (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                updated_at INTEGER NOT NULL,
+                messages INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                data TEXT NOT NULL
+            )",
+        )?;
+        let indexed: bool = connection.query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE name = 'sessions_fts')",
+            [],
+            |row| row.get(0),
+        )?;
+        let full_text = connection
+            .execute_batch(
+                "CREATE VIRTUAL TABLE IF NOT EXISTS sessions_fts USING fts5(id UNINDEXED, title, content)",
+            )
+            .is_ok();
+        if full_text && !indexed {
+            connection.execute_batch(
+                "INSERT INTO sessions_fts (id, title, content) SELECT id, title, content FROM sessions",
+            )?;
+        }
+        Ok(SqliteStore {
+            connection: std::sync::Mutex::new(connection),
+            full_text,
+        })
+    }
+
+    /// Returns `true` if searches use the FTS5 full-text index.
+    #[must_use]
+    pub fn has_full_text(&self) -> bool {
+        self.full_text
+    }
+
+    /// Lists the sessions matching a `WHERE` clause, newest first.
+    fn select(
+        &self,
+        condition: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<SessionInfo>, FSError> {
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let mut statement = connection.prepare(&format!(
+            "SELECT id, title, updated_at, messages FROM sessions {condition} ORDER BY updated_at DESC"
+        ))?;
+        let rows = statement.query_map(params, |row| {
+            Ok(SessionInfo {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                updated_at: row.get(2)?,
+                messages: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl SessionStore for SqliteStore {
+    fn save(&self, session: &Session) -> Result<(), FSError> {
+        let content: Vec<&str> = session
+            .messages
+            .iter()
+            .map(|message| message.content.as_str())
+            .collect();
+        let content = content.join("\n");
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        connection.execute(
+            "INSERT OR REPLACE INTO sessions (id, title, updated_at, messages, content, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                session.id,
+                session.title,
+                session.updated_at,
+                session.messages.len(),
+                content,
+                serde_json::to_string(session)?,
+            ],
+        )?;
+        if self.full_text {
+            connection.execute("DELETE FROM sessions_fts WHERE id = ?1", [&session.id])?;
+            connection.execute(
+                "INSERT INTO sessions_fts (id, title, content) VALUES (?1, ?2, ?3)",
+                [&session.id, &session.title, &content],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn load(&self, id: &str) -> Result<Session, FSError> {
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        let data: String =
+            connection.query_row("SELECT data FROM sessions WHERE id = ?1", [id], |row| {
+                row.get(0)
+            })?;
+        Ok(serde_json::from_str(&data)?)
+    }
+
+    fn delete(&self, id: &str) -> Result<(), FSError> {
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(|err| err.into_inner());
+        connection.execute("DELETE FROM sessions WHERE id = ?1", [id])?;
+        if self.full_text {
+            connection.execute("DELETE FROM sessions_fts WHERE id = ?1", [id])?;
+        }
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<SessionInfo>, FSError> {
+        self.select("", [])
+    }
+
+    fn search(&self, query: &str) -> Result<Vec<SessionInfo>, FSError> {
+        if self.full_text {
+            // The query as one phrase whose last word may be a prefix; a
+            // query without words is rejected by FTS5 and searched below.
+            let phrase = format!("\"{}\"*", query.replace('"', "\"\""));
+            let found = self.select(
+                "WHERE id IN (SELECT id FROM sessions_fts WHERE sessions_fts MATCH ?1)",
+                [phrase],
+            );
+            if found.is_ok() {
+                return found;
+            }
+        }
+        let pattern = format!(
+            "%{}%",
+            query
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+        self.select(
+            "WHERE title LIKE ?1 ESCAPE '\\' OR content LIKE ?1 ESCAPE '\\'",
+            [pattern],
+        )
+    }
+}
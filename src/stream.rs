@@ -0,0 +1,173 @@
+//! Reads streamed chat responses.
+//!
+//! The body of a streamed response arrives in network chunks that can end
+//! anywhere, including in the middle of a multi-byte character. The bytes
+//! are buffered and only decoded once a whole JSON line or event has
+//! arrived, so CJK text and emoji split between chunks come through intact.
+//! Each chunk is traced with its size and the parts it completed, and the
+//! span of a response records the totals and the malformed parts skipped.
+
+use futures_util::StreamExt;
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tracing::{field, instrument, trace, warn, Span};
+
+use crate::api::{JsonLines, ServerSentEvents};
+use crate::error::{AppResult, Application};
+use crate::logging::API_TARGET;
+use crate::message::Metadata;
+use crate::provider::Provider;
+
+/// Splits a response body into JSON values, in the provider's format.
+enum Decoder {
+    Lines(JsonLines),
+    Events(ServerSentEvents),
+}
+
+impl Decoder {
+    fn for_provider(provider: Provider) -> Self {
+        if provider.streams_events() {
+            Decoder::Events(ServerSentEvents::new())
+        } else {
+            Decoder::Lines(JsonLines::new())
+        }
+    }
+
+    fn push(&mut self, chunk: &[u8]) -> Vec<Result<Value, serde_json::Error>> {
+        match self {
+            Decoder::Lines(lines) => lines.push(chunk),
+            Decoder::Events(events) => events.push(chunk),
+        }
+    }
+
+    /// Returns `true` once the stream's end marker arrived.
+    fn is_done(&self) -> bool {
+        match self {
+            Decoder::Lines(_) => false,
+            Decoder::Events(events) => events.is_done(),
+        }
+    }
+
+    fn finish(self) -> Option<Result<Value, serde_json::Error>> {
+        match self {
+            Decoder::Lines(lines) => lines.finish(),
+            Decoder::Events(events) => events.finish(),
+        }
+    }
+}
+
+/// Sends the content of a streamed chat response to `tx` as it arrives.
+///
+/// # Arguments
+///
+/// * `response` - The response, streaming one JSON object per line or per
+///   server-sent event, depending on the provider.
+/// * `provider` - The server the response comes from.
+/// * `tx` - Receives each piece of the content.
+///
+/// # Returns
+///
+/// The token counts and finish reason the response reported.
+///
+/// # Errors
+///
+/// Returns an `Application` error if the body cannot be read or the
+/// receiver is gone.
+#[instrument(
+    target = API_TARGET,
+    skip_all,
+    fields(?provider, chunks = field::Empty, bytes = field::Empty, parts = field::Empty, malformed = field::Empty)
+)]
+pub async fn forward(
+    response: reqwest::Response,
+    provider: Provider,
+    tx: &mpsc::Sender<Result<String, Application>>,
+) -> AppResult<Metadata> {
+    let mut stream = response.bytes_stream();
+    let mut decoder = Decoder::for_provider(provider);
+    let mut metadata = Metadata::default();
+    let mut counts = Counts::default();
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        let parts = decoder.push(&chunk);
+        counts.chunk(chunk.len(), parts.len());
+        for json in parts {
+            let finished = forward_value(json, provider, tx, &mut metadata, &mut counts).await?;
+            if finished && !provider.ends_with_marker() {
+                counts.record();
+                return Ok(metadata);
+            }
+        }
+        if decoder.is_done() {
+            counts.record();
+            return Ok(metadata);
+        }
+    }
+    if let Some(json) = decoder.finish() {
+        counts.parts += 1;
+        forward_value(json, provider, tx, &mut metadata, &mut counts).await?;
+    }
+    counts.record();
+    Ok(metadata)
+}
+
+/// How much of a streamed response arrived, recorded on its span.
+#[derive(Default)]
+pub(crate) struct Counts {
+    chunks: usize,
+    bytes: usize,
+    parts: usize,
+    malformed: usize,
+}
+
+impl Counts {
+    /// Counts a chunk of the response and the parts it completed.
+    pub(crate) fn chunk(&mut self, bytes: usize, parts: usize) {
+        self.chunks += 1;
+        self.bytes += bytes;
+        self.parts += parts;
+        trace!(target: API_TARGET, bytes, parts, "received a chunk");
+    }
+
+    /// Records the counts on the current span.
+    pub(crate) fn record(&self) {
+        let span = Span::current();
+        span.record("chunks", self.chunks);
+        span.record("bytes", self.bytes);
+        span.record("parts", self.parts);
+        span.record("malformed", self.malformed);
+    }
+}
+
+/// Sends the content of a streamed value and records what it reports,
+/// counting it as malformed if it could not be parsed.
+///
+/// # Returns
+///
+/// `true` if the value reports the finish reason, which ends the response
+/// unless the provider [ends it with a marker](Provider::ends_with_marker).
+pub(crate) async fn forward_value(
+    json: Result<Value, serde_json::Error>,
+    provider: Provider,
+    tx: &mpsc::Sender<Result<String, Application>>,
+    metadata: &mut Metadata,
+    counts: &mut Counts,
+) -> AppResult<bool> {
+    let json = match json {
+        Ok(json) => json,
+        Err(err) => {
+            warn!(target: API_TARGET, ?err, "skipping a malformed part of the response");
+            counts.malformed += 1;
+            return Ok(false);
+        }
+    };
+    provider.record(&json, metadata);
+    if let Some(content) = provider.content(&json) {
+        trace!(target: API_TARGET, bytes = content.len(), "forwarding content");
+        tx.send(Ok(content.to_string()))
+            .await
+            .map_err(|e| Application::Unexpected(e.to_string()))?;
+    }
+    Ok(provider.is_done(&json))
+}
@@ -0,0 +1,328 @@
+//! Requests answers as JSON and checks them against a schema.
+//!
+//! JSON mode is toggled with `/json`, or started by a template with a
+//! `schema`. The server is asked to constrain its output: Ollama through
+//! `format`, OpenAI-compatible servers through `response_format` and
+//! llama.cpp through `json_schema`. The answer is then pretty-printed as a
+//! JSON code block, and any place where it does not match the schema is
+//! reported below it.
+//!
+//! The validation covers the common part of JSON Schema: `type`, `enum`,
+//! `const`, `properties`, `required`, `additionalProperties`, `items`, and
+//! the length and range bounds. Other keywords are ignored.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+/// How answers are requested while JSON mode is on.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JsonOutput {
+    /// The JSON Schema answers must match, or `None` for any JSON.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+}
+
+impl JsonOutput {
+    /// Returns the value of Ollama's `format` field: the schema, or `"json"`
+    /// without one.
+    #[must_use]
+    pub fn ollama_format(&self) -> Value {
+        self.schema.clone().unwrap_or_else(|| json!("json"))
+    }
+
+    /// Returns the value of the `response_format` field of OpenAI-compatible
+    /// servers. The schema is only enforced strictly if it qualifies, see
+    /// [`is_strict`].
+    #[must_use]
+    pub fn response_format(&self) -> Value {
+        match &self.schema {
+            Some(schema) => json!({
+                "type": "json_schema",
+                "json_schema": {"name": "answer", "schema": schema, "strict": is_strict(schema)},
+            }),
+            None => json!({"type": "json_object"}),
+        }
+    }
+
+    /// Returns the value of llama.cpp's `json_schema` field; an empty schema
+    /// allows any JSON.
+    #[must_use]
+    pub fn json_schema(&self) -> Value {
+        self.schema.clone().unwrap_or_else(|| json!({}))
+    }
+}
+
+/// Returns `true` if a schema can be enforced in strict mode by
+/// OpenAI-compatible servers: every object in it lists all its properties
+/// as required and allows no others.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::structured::is_strict;
+/// use serde_json::json;
+///
+/// let strict = json!({
+///     "type": "object",
+///     "properties": {"name": {"type": "string"}},
+///     "required": ["name"],
+///     "additionalProperties": false,
+/// });
+/// assert!(is_strict(&strict));
+/// assert!(!is_strict(&json!({"type": "object", "properties": {"name": {}}})));
+/// assert!(is_strict(&json!({"type": "array", "items": strict})));
+/// ```
+#[must_use]
+pub fn is_strict(schema: &Value) -> bool {
+    let Some(schema) = schema.as_object() else {
+        return true;
+    };
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let is_object = properties.is_some() || schema.get("type") == Some(&json!("object"));
+    if is_object {
+        let required: Vec<&str> = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .map(|required| required.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+        let properties = properties.into_iter().flatten();
+        if schema.get("additionalProperties") != Some(&Value::Bool(false))
+            || !properties
+                .clone()
+                .all(|(name, _)| required.contains(&name.as_str()))
+            || !properties.clone().all(|(_, property)| is_strict(property))
+        {
+            return false;
+        }
+    }
+    schema.get("items").is_none_or(is_strict)
+}
+
+/// A JSON answer, pretty-printed and checked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checked {
+    /// The answer as a JSON code block.
+    pub content: String,
+    /// Where the answer does not match the schema, one problem per entry.
+    pub problems: Vec<String>,
+}
+
+/// Pretty-prints a JSON answer and validates it against a schema.
+///
+/// A code fence around the answer, which some models add anyway, is
+/// removed first.
+///
+/// # Errors
+///
+/// Returns the parse error if the answer is not valid JSON.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::structured::check;
+/// use serde_json::json;
+///
+/// let schema = json!({
+///     "type": "object",
+///     "properties": {"age": {"type": "integer"}},
+///     "required": ["name", "age"],
+/// });
+/// let checked = check("{\"age\": \"ten\"}", Some(&schema)).unwrap();
+/// assert!(checked.content.starts_with("```json\n{\n  \"age\": \"ten\"\n}"));
+/// assert_eq!(
+///     checked.problems,
+///     vec![
+///         "`$`: missing required property \"name\"",
+///         "`$.age`: expected integer, found string",
+///     ]
+/// );
+/// assert!(check("not json", None).is_err());
+/// ```
+pub fn check(answer: &str, schema: Option<&Value>) -> Result<Checked, serde_json::Error> {
+    let value: Value = serde_json::from_str(strip_fence(answer))?;
+    let pretty = serde_json::to_string_pretty(&value)?;
+    let mut problems = Vec::new();
+    if let Some(schema) = schema {
+        validate(schema, &value, "$", &mut problems);
+    }
+    Ok(Checked {
+        content: format!("```json\n{pretty}\n```"),
+        problems,
+    })
+}
+
+/// Removes a Markdown code fence around the answer, if there is one.
+fn strip_fence(answer: &str) -> &str {
+    let trimmed = answer.trim();
+    let Some(rest) = trimmed.strip_prefix("```") else {
+        return trimmed;
+    };
+    let body = rest.split_once('\n').map_or("", |(_, body)| body);
+    body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+}
+
+/// Checks `value` against `schema`, adding a problem for each mismatch.
+fn validate(schema: &Value, value: &Value, path: &str, problems: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        if schema == &Value::Bool(false) {
+            problems.push(format!("`{path}`: no value is allowed here"));
+        }
+        return;
+    };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|name| has_type(value, name)) {
+            problems.push(format!(
+                "`{path}`: expected {}, found {}",
+                types.join(" or "),
+                type_name(value)
+            ));
+            return;
+        }
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            problems.push(format!(
+                "`{path}`: {value} is not one of {}",
+                allowed.join(", ")
+            ));
+        }
+    }
+    if let Some(constant) = schema.get("const") {
+        if constant != value {
+            problems.push(format!("`{path}`: expected {constant}, found {value}"));
+        }
+    }
+
+    match value {
+        Value::Object(object) => validate_object(schema, object, path, problems),
+        Value::Array(items) => {
+            check_count(
+                schema,
+                "minItems",
+                "maxItems",
+                items.len(),
+                "item",
+                path,
+                problems,
+            );
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate(item_schema, item, &format!("{path}[{index}]"), problems);
+                }
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count();
+            check_count(
+                schema,
+                "minLength",
+                "maxLength",
+                length,
+                "character",
+                path,
+                problems,
+            );
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            let bound = |key| schema.get(key).and_then(Value::as_f64);
+            if let Some(minimum) = bound("minimum").filter(|minimum| number < *minimum) {
+                problems.push(format!("`{path}`: {number} is less than {minimum}"));
+            }
+            if let Some(maximum) = bound("maximum").filter(|maximum| number > *maximum) {
+                problems.push(format!("`{path}`: {number} is greater than {maximum}"));
+            }
+        }
+        Value::Bool(_) | Value::Null => {}
+    }
+}
+
+/// Checks the properties of an object.
+fn validate_object(
+    schema: &Map<String, Value>,
+    object: &Map<String, Value>,
+    path: &str,
+    problems: &mut Vec<String>,
+) {
+    for name in schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+    {
+        if !object.contains_key(name) {
+            problems.push(format!("`{path}`: missing required property \"{name}\""));
+        }
+    }
+
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let additional = schema.get("additionalProperties");
+    for (name, property) in object {
+        let property_path = format!("{path}.{name}");
+        match properties.and_then(|properties| properties.get(name)) {
+            Some(property_schema) => validate(property_schema, property, &property_path, problems),
+            None => match additional {
+                Some(Value::Bool(false)) => {
+                    problems.push(format!("`{path}`: unexpected property \"{name}\""));
+                }
+                Some(additional) => validate(additional, property, &property_path, problems),
+                None => {}
+            },
+        }
+    }
+}
+
+/// Checks a length or count against the `min` and `max` keywords.
+fn check_count(
+    schema: &Map<String, Value>,
+    min: &str,
+    max: &str,
+    count: usize,
+    unit: &str,
+    path: &str,
+    problems: &mut Vec<String>,
+) {
+    let bound = |key| {
+        schema
+            .get(key)
+            .and_then(Value::as_u64)
+            .and_then(|bound| usize::try_from(bound).ok())
+    };
+    if let Some(min) = bound(min).filter(|min| count < *min) {
+        problems.push(format!("`{path}`: {count} {unit}(s), fewer than {min}"));
+    }
+    if let Some(max) = bound(max).filter(|max| count > *max) {
+        problems.push(format!("`{path}`: {count} {unit}(s), more than {max}"));
+    }
+}
+
+/// Returns `true` if `value` has the JSON Schema type `name`.
+fn has_type(value: &Value, name: &str) -> bool {
+    match name {
+        "integer" => value.as_f64().is_some_and(|number| number.fract() == 0.0),
+        "number" => value.is_number(),
+        name => type_name(value) == name,
+    }
+}
+
+/// Returns the JSON Schema type of a value.
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
@@ -0,0 +1,327 @@
+//! Samples CSV and TSV files to attach them.
+//!
+//! A table is usually too long to send whole and too regular to need it: a
+//! header, a sample of the rows and statistics of each column tell the
+//! model what the data looks like. How many rows are kept, and whether they
+//! are the first rows or rows spread through the file, is configured in the
+//! `[attachments]` section, and the sample says how it was taken.
+
+use std::{collections::HashSet, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+/// Which rows of a table are kept in a sample.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// The first rows.
+    Head,
+    /// Rows spread evenly from the first to the last.
+    #[default]
+    Even,
+}
+
+/// How a table is sampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sampling {
+    /// The most rows kept, besides the header.
+    pub rows: usize,
+    /// Which rows are kept.
+    pub strategy: Strategy,
+}
+
+impl Default for Sampling {
+    fn default() -> Self {
+        Sampling {
+            rows: 20,
+            strategy: Strategy::Even,
+        }
+    }
+}
+
+/// Returns the delimiter of a table file, from its extension: a comma for
+/// CSV files and a tab for TSV files.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::tables::delimiter;
+/// use std::path::Path;
+///
+/// assert_eq!(delimiter(Path::new("sales.CSV")), Some(','));
+/// assert_eq!(delimiter(Path::new("genes.tsv")), Some('\t'));
+/// assert_eq!(delimiter(Path::new("notes.txt")), None);
+/// ```
+#[must_use]
+pub fn delimiter(path: &Path) -> Option<char> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "csv" => Some(','),
+        "tsv" | "tab" => Some('\t'),
+        _ => None,
+    }
+}
+
+/// Splits delimited text into records of fields. Fields may be quoted with
+/// `"`, with `""` for a quote inside them, and quoted fields may span
+/// lines. Blank lines are skipped.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::tables::parse;
+///
+/// let records = parse("name,quote\nAda,\"Hello, \"\"world\"\"\"\n\n", ',');
+/// assert_eq!(records, vec![vec!["name", "quote"], vec!["Ada", "Hello, \"world\""]]);
+/// ```
+#[must_use]
+pub fn parse(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            if c != '"' {
+                field.push(c);
+            } else if chars.next_if_eq(&'"').is_some() {
+                field.push('"');
+            } else {
+                quoted = false;
+            }
+            continue;
+        }
+        match c {
+            '"' if field.is_empty() => quoted = true,
+            '\r' => {}
+            '\n' => {
+                record.push(std::mem::take(&mut field));
+                if record.len() > 1 || !record[0].is_empty() {
+                    records.push(std::mem::take(&mut record));
+                }
+                record.clear();
+            }
+            c if c == delimiter => record.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// A sample of a table: its header, some of its rows, and statistics of
+/// every column over all the rows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sample {
+    /// The delimiter of the fields.
+    pub delimiter: char,
+    /// The names of the columns.
+    pub header: Vec<String>,
+    /// The rows kept, each with its number in the table, starting at 1.
+    pub rows: Vec<(usize, Vec<String>)>,
+    /// The number of rows in the table, besides the header.
+    pub total_rows: usize,
+    /// Which rows were kept.
+    pub strategy: Strategy,
+    /// A description of each column.
+    pub columns: Vec<String>,
+}
+
+impl Sample {
+    /// Samples delimited text whose first record is the header.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The contents of the table.
+    /// * `delimiter` - The delimiter of the fields.
+    /// * `sampling` - How the rows are sampled.
+    ///
+    /// # Returns
+    ///
+    /// The sample, or `None` if the text has no records.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::tables::{Sample, Sampling, Strategy};
+    ///
+    /// let text: String = std::iter::once("n,square\n".to_string())
+    ///     .chain((1..=10).map(|n| format!("{n},{}\n", n * n)))
+    ///     .collect();
+    /// let sampling = Sampling { rows: 3, strategy: Strategy::Even };
+    /// let sample = Sample::of(&text, ',', sampling).unwrap();
+    /// let numbers: Vec<usize> = sample.rows.iter().map(|(number, _)| *number).collect();
+    /// assert_eq!(numbers, vec![1, 5, 10]);
+    /// assert_eq!(sample.columns[1], "square: numbers from 1 to 100, mean 38.50");
+    /// ```
+    #[must_use]
+    pub fn of(text: &str, delimiter: char, sampling: Sampling) -> Option<Self> {
+        let mut records = parse(text, delimiter).into_iter();
+        let header = records.next()?;
+        let records: Vec<Vec<String>> = records.collect();
+        let columns = header
+            .iter()
+            .enumerate()
+            .map(|(index, name)| {
+                let values = records
+                    .iter()
+                    .map(|record| record.get(index).map_or("", |value| value.trim()));
+                describe_column(name, values)
+            })
+            .collect();
+        let rows = pick(records.len(), sampling)
+            .into_iter()
+            .map(|index| (index + 1, records[index].clone()))
+            .collect();
+        Some(Sample {
+            delimiter,
+            header,
+            rows,
+            total_rows: records.len(),
+            strategy: sampling.strategy,
+            columns,
+        })
+    }
+
+    /// Returns `true` if the sample holds every row of the table.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.rows.len() == self.total_rows
+    }
+
+    /// Returns the kind of table, `CSV` or `TSV`.
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        if self.delimiter == '\t' {
+            "TSV"
+        } else {
+            "CSV"
+        }
+    }
+
+    /// Describes which rows the sample holds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::tables::{Sample, Sampling, Strategy};
+    ///
+    /// let text = "a\n1\n2\n3\n4\n";
+    /// let head = Sample::of(text, ',', Sampling { rows: 2, strategy: Strategy::Head }).unwrap();
+    /// assert_eq!(head.strategy_note(), "the header and the first 2 of 4 rows");
+    /// let even = Sample::of(text, ',', Sampling { rows: 2, strategy: Strategy::Even }).unwrap();
+    /// assert_eq!(even.strategy_note(), "the header and 2 of 4 rows, spread evenly from the first to the last");
+    /// ```
+    #[must_use]
+    pub fn strategy_note(&self) -> String {
+        let kept = self.rows.len();
+        let total = self.total_rows;
+        if self.is_complete() {
+            return format!("the header and all {total} row(s)");
+        }
+        match self.strategy {
+            Strategy::Head => format!("the header and the first {kept} of {total} rows"),
+            Strategy::Even => format!(
+                "the header and {kept} of {total} rows, spread evenly from the first to the last"
+            ),
+        }
+    }
+
+    /// Returns the text attached for the sample: a note of how it was
+    /// taken, the header and rows kept, each row led by its number, and the
+    /// statistics of the columns.
+    #[must_use]
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "{} sample: {}. The first column, `row`, is the number of the row in the file.\n",
+            self.kind(),
+            self.strategy_note()
+        );
+        let header = std::iter::once("row").chain(self.header.iter().map(String::as_str));
+        text.push_str(&self.line(header));
+        for (number, row) in &self.rows {
+            let number = number.to_string();
+            let fields = std::iter::once(number.as_str()).chain(row.iter().map(String::as_str));
+            text.push_str(&self.line(fields));
+        }
+        text.push_str(&format!(
+            "\nColumns, over all {} row(s):\n",
+            self.total_rows
+        ));
+        for column in &self.columns {
+            text.push_str(&format!("- {column}\n"));
+        }
+        text
+    }
+
+    /// Joins fields into a line, quoting those that need it.
+    fn line<'a>(&self, fields: impl Iterator<Item = &'a str>) -> String {
+        let fields: Vec<String> = fields
+            .map(|field| {
+                if field.contains(self.delimiter) || field.contains(['"', '\n']) {
+                    format!("\"{}\"", field.replace('"', "\"\""))
+                } else {
+                    field.to_string()
+                }
+            })
+            .collect();
+        let mut line = fields.join(&self.delimiter.to_string());
+        line.push('\n');
+        line
+    }
+}
+
+/// Returns the indices of the rows a sample keeps.
+fn pick(total: usize, sampling: Sampling) -> Vec<usize> {
+    let rows = sampling.rows;
+    if total <= rows {
+        return (0..total).collect();
+    }
+    match (sampling.strategy, rows) {
+        (Strategy::Head, _) | (Strategy::Even, 0 | 1) => (0..rows).collect(),
+        (Strategy::Even, _) => (0..rows).map(|i| i * (total - 1) / (rows - 1)).collect(),
+    }
+}
+
+/// Describes a column from its values: the range and mean of a numeric
+/// column, the number of distinct values of any other, and how many values
+/// are empty.
+fn describe_column<'a>(name: &str, values: impl Iterator<Item = &'a str>) -> String {
+    let mut empty = 0;
+    let mut distinct = HashSet::new();
+    let mut numbers = Some(Vec::new());
+    for value in values {
+        if value.is_empty() {
+            empty += 1;
+            continue;
+        }
+        distinct.insert(value);
+        let number = value
+            .parse::<f64>()
+            .ok()
+            .filter(|number| number.is_finite());
+        if let (Some(numbers), Some(number)) = (&mut numbers, number) {
+            numbers.push(number);
+        } else {
+            numbers = None;
+        }
+    }
+    let mut description = match numbers {
+        _ if distinct.is_empty() => format!("{name}: empty"),
+        Some(numbers) => {
+            let min = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+            let max = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let mean = numbers.iter().sum::<f64>() / numbers.len() as f64;
+            format!("{name}: numbers from {min} to {max}, mean {mean:.2}")
+        }
+        None => format!("{name}: text, {} distinct value(s)", distinct.len()),
+    };
+    if empty > 0 && !distinct.is_empty() {
+        description.push_str(&format!(", {empty} empty"));
+    }
+    description
+}
@@ -0,0 +1,302 @@
+//! Exports traces and metrics of chat requests with OpenTelemetry.
+//!
+//! When the `[telemetry]` section enables it, the spans of chat requests
+//! are sent to an OTLP collector over HTTP, with metrics of how long
+//! requests take and how many tokens answers have, so chatti can be
+//! watched in Grafana next to the inference server. The collector takes
+//! traces at `{endpoint}/v1/traces` and metrics at `{endpoint}/v1/metrics`.
+//! Exporting needs chatti built with the `telemetry` feature.
+//!
+//! Only the spans of chat requests are exported, never events: events can
+//! hold prompts and answers, and the redactor only cleans the lines written
+//! to the log file.
+
+use std::time::Duration;
+
+use tracing::Subscriber;
+use tracing_subscriber::{registry::LookupSpan, Layer};
+
+use crate::config::TelemetryConfig;
+use crate::logging::API_TARGET;
+use crate::message::Metadata;
+
+#[cfg(feature = "telemetry")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "telemetry")]
+use opentelemetry::{
+    metrics::{Counter, Histogram},
+    trace::TracerProvider as _,
+    KeyValue,
+};
+#[cfg(feature = "telemetry")]
+use opentelemetry_otlp::{MetricExporter, SpanExporter, WithExportConfig, WithHttpConfig};
+#[cfg(feature = "telemetry")]
+use opentelemetry_sdk::{
+    metrics::{PeriodicReader, SdkMeterProvider},
+    runtime,
+    trace::TracerProvider,
+    Resource,
+};
+
+/// The instruments requests are recorded with, once exporting started.
+#[cfg(feature = "telemetry")]
+static INSTRUMENTS: OnceLock<Instruments> = OnceLock::new();
+
+/// Represents errors that can occur while starting to export telemetry.
+#[derive(Debug, thiserror::Error)]
+pub enum TelemetryError {
+    /// Chatti was built without the `telemetry` feature.
+    #[error("exporting telemetry needs chatti built with the telemetry feature")]
+    Unsupported,
+
+    /// An exporter could not be set up.
+    #[error("could not export {signal} to {endpoint}: {message}")]
+    Exporter {
+        /// What was to be exported, `traces` or `metrics`.
+        signal: &'static str,
+        /// The URL it was to be exported to.
+        endpoint: String,
+        /// What went wrong.
+        message: String,
+    },
+}
+
+/// Returns the URL the collector takes a signal at, below its base
+/// endpoint.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::telemetry::signal_endpoint;
+///
+/// assert_eq!(
+///     signal_endpoint("http://localhost:4318/", "traces"),
+///     "http://localhost:4318/v1/traces"
+/// );
+/// ```
+#[must_use]
+pub fn signal_endpoint(endpoint: &str, signal: &str) -> String {
+    format!("{}/v1/{signal}", endpoint.trim_end_matches('/'))
+}
+
+/// Returns `true` if a span or event is sent to the collector: only the
+/// spans of chat requests, whose fields are models, statuses and counts.
+#[must_use]
+pub fn is_exported(metadata: &tracing::Metadata<'_>) -> bool {
+    metadata.is_span() && metadata.target() == API_TARGET
+}
+
+/// Exports traces and metrics until it is dropped, when what is left is
+/// sent.
+pub struct Telemetry {
+    #[cfg(feature = "telemetry")]
+    tracer_provider: TracerProvider,
+    #[cfg(feature = "telemetry")]
+    meter_provider: SdkMeterProvider,
+}
+
+impl std::fmt::Debug for Telemetry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Telemetry").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "telemetry")]
+impl Telemetry {
+    /// Starts exporting traces and metrics if the configuration enables
+    /// it. It is called from the async runtime, which sends the batches.
+    ///
+    /// # Returns
+    ///
+    /// The exporter, or `None` if telemetry is turned off.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `TelemetryError` if an exporter cannot be set up.
+    pub fn start(config: &TelemetryConfig) -> Result<Option<Self>, TelemetryError> {
+        if !config.enabled {
+            return Ok(None);
+        }
+        let resource = Resource::new([KeyValue::new("service.name", config.service_name.clone())]);
+
+        let endpoint = signal_endpoint(&config.endpoint, "traces");
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(&endpoint)
+            .with_headers(config.headers.clone())
+            .build()
+            .map_err(|err| TelemetryError::Exporter {
+                signal: "traces",
+                endpoint,
+                message: err.to_string(),
+            })?;
+        let tracer_provider = TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(resource.clone())
+            .build();
+
+        let endpoint = signal_endpoint(&config.endpoint, "metrics");
+        let exporter = MetricExporter::builder()
+            .with_http()
+            .with_endpoint(&endpoint)
+            .with_headers(config.headers.clone())
+            .build()
+            .map_err(|err| TelemetryError::Exporter {
+                signal: "metrics",
+                endpoint,
+                message: err.to_string(),
+            })?;
+        let reader = PeriodicReader::builder(exporter, runtime::Tokio)
+            .with_interval(Duration::from_secs(config.export_interval_secs.max(1)))
+            .build();
+        let meter_provider = SdkMeterProvider::builder()
+            .with_reader(reader)
+            .with_resource(resource)
+            .build();
+        let _ = INSTRUMENTS.set(Instruments::new(&meter_provider));
+
+        Ok(Some(Telemetry {
+            tracer_provider,
+            meter_provider,
+        }))
+    }
+
+    /// Returns the layer that sends the spans logging records to the
+    /// collector.
+    #[must_use]
+    pub fn layer<S>(&self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(self.tracer_provider.tracer("chatti"))
+    }
+}
+
+#[cfg(feature = "telemetry")]
+impl Drop for Telemetry {
+    fn drop(&mut self) {
+        if let Err(err) = self.tracer_provider.shutdown() {
+            eprintln!("Could not send the last traces: {err}");
+        }
+        if let Err(err) = self.meter_provider.shutdown() {
+            eprintln!("Could not send the last metrics: {err}");
+        }
+    }
+}
+
+#[cfg(not(feature = "telemetry"))]
+impl Telemetry {
+    /// Starts nothing, since chatti was built without the `telemetry`
+    /// feature.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TelemetryError::Unsupported` if the configuration enables
+    /// telemetry.
+    pub fn start(config: &TelemetryConfig) -> Result<Option<Self>, TelemetryError> {
+        if config.enabled {
+            Err(TelemetryError::Unsupported)
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Returns a layer that does nothing, since there is no collector.
+    #[must_use]
+    pub fn layer<S>(&self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_subscriber::layer::Identity::new()
+    }
+}
+
+/// The metrics of chat requests.
+#[cfg(feature = "telemetry")]
+struct Instruments {
+    duration: Histogram<f64>,
+    tokens: Counter<u64>,
+    throughput: Histogram<f64>,
+}
+
+#[cfg(feature = "telemetry")]
+impl Instruments {
+    fn new(provider: &SdkMeterProvider) -> Self {
+        use opentelemetry::metrics::MeterProvider as _;
+
+        let meter = provider.meter("chatti");
+        Instruments {
+            duration: meter
+                .f64_histogram("chatti.request.duration")
+                .with_unit("s")
+                .with_description("The time from sending a chat request to the end of its answer")
+                .build(),
+            tokens: meter
+                .u64_counter("chatti.tokens")
+                .with_unit("{token}")
+                .with_description("The tokens of prompts and answers, as servers report them")
+                .build(),
+            throughput: meter
+                .f64_histogram("chatti.answer.throughput")
+                .with_unit("{token}/s")
+                .with_description("The tokens of an answer per second of its request")
+                .build(),
+        }
+    }
+}
+
+/// Records a finished chat request in the metrics, if they are exported.
+///
+/// # Arguments
+///
+/// * `model` - The model the request went to.
+/// * `provider` - The kind of server it went to.
+/// * `status` - The HTTP status of the response, or 0 if there was none.
+/// * `elapsed` - The time from sending the request to the end of the
+///   answer.
+/// * `metadata` - What the server reported, for the token counts.
+#[cfg(feature = "telemetry")]
+pub fn record_request(
+    model: &str,
+    provider: &str,
+    status: u16,
+    elapsed: Duration,
+    metadata: &Metadata,
+) {
+    let Some(instruments) = INSTRUMENTS.get() else {
+        return;
+    };
+    let attributes = [
+        KeyValue::new("model", model.to_string()),
+        KeyValue::new("provider", provider.to_string()),
+    ];
+    let mut with_status = attributes.to_vec();
+    with_status.push(KeyValue::new("status", i64::from(status)));
+    instruments
+        .duration
+        .record(elapsed.as_secs_f64(), &with_status);
+
+    for (kind, count) in [
+        ("prompt", metadata.prompt_tokens),
+        ("completion", metadata.completion_tokens),
+    ] {
+        if let Some(count) = count {
+            let mut with_kind = attributes.to_vec();
+            with_kind.push(KeyValue::new("kind", kind));
+            instruments.tokens.add(count, &with_kind);
+        }
+    }
+    if let Some(count) = metadata.completion_tokens {
+        if !elapsed.is_zero() {
+            #[allow(clippy::cast_precision_loss)]
+            let per_second = count as f64 / elapsed.as_secs_f64();
+            instruments.throughput.record(per_second, &attributes);
+        }
+    }
+}
+
+/// Records nothing, since chatti was built without the `telemetry`
+/// feature.
+#[cfg(not(feature = "telemetry"))]
+pub fn record_request(_: &str, _: &str, _: u16, _: Duration, _: &Metadata) {}
@@ -0,0 +1,215 @@
+//! Starts conversations from saved templates.
+//!
+//! A template seeds a new conversation with a system prompt and messages, and
+//! can attach the contents of files matching glob patterns and the output of
+//! a shell command (for example `git diff` for a code review). The command
+//! is killed if it runs longer than the `$(command)` substitutions may.
+
+use std::{fs, io::ErrorKind, path::Path, process::Command, sync::atomic::AtomicBool};
+
+use crate::{config::TemplateConfig, message::Message, process, shell::RUN_TIMEOUT};
+
+/// The maximum number of bytes kept from each attached file or command output.
+const MAX_ATTACHMENT_BYTES: usize = 100 * 1024;
+
+/// Represents errors that can occur while starting a template.
+#[derive(Debug, thiserror::Error)]
+pub enum TemplateError {
+    /// No template with the given name is configured.
+    #[error("unknown template: {0}")]
+    Unknown(String),
+
+    /// An attachment pattern is not a valid glob.
+    #[error("invalid attachment pattern: {0}")]
+    Pattern(#[from] glob::PatternError),
+
+    /// An attached file could not be read, or the command could not be run.
+    #[error("could not read attachment: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// The attachment command exited unsuccessfully.
+    #[error("command `{command}` failed: {stderr}")]
+    Command {
+        /// The command that was run.
+        command: String,
+        /// What the command wrote to standard error.
+        stderr: String,
+    },
+
+    /// The attachment command did not finish in time and was killed.
+    #[error("command `{0}` did not finish within {secs} seconds", secs = RUN_TIMEOUT.as_secs())]
+    TimedOut(String),
+
+    /// The attachment command was cancelled and killed.
+    #[error("command `{0}` was cancelled")]
+    Cancelled(String),
+}
+
+/// A conversation prepared from a template.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Started {
+    /// The system prompt, including any attachments.
+    pub system_prompt: Option<String>,
+    /// The seeded messages.
+    pub messages: Vec<Message>,
+    /// The number of attached files.
+    pub files: usize,
+    /// The JSON Schema answers must match, if the template has one.
+    pub schema: Option<serde_json::Value>,
+}
+
+/// Prepares a conversation from a template.
+///
+/// Attachment patterns and the command are resolved relative to `dir`.
+///
+/// # Arguments
+///
+/// * `template` - The template configuration.
+/// * `dir` - The directory to resolve attachments in.
+///
+/// # Returns
+///
+/// The system prompt and seeded messages of the new conversation.
+///
+/// # Errors
+///
+/// Returns a `TemplateError` if a pattern is invalid, a file cannot be read,
+/// or the command fails or runs out of time.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::config::TemplateConfig;
+/// use chatti::templates::start;
+///
+/// let template = TemplateConfig {
+///     system: Some("You are a careful reviewer.".to_string()),
+///     command: Some("echo '+ added line'".to_string()),
+///     ..TemplateConfig::default()
+/// };
+/// let started = start(&template, std::path::Path::new(".")).unwrap();
+/// let prompt = started.system_prompt.unwrap();
+/// assert!(prompt.starts_with("You are a careful reviewer."));
+/// assert!(prompt.contains("+ added line"));
+/// ```
+pub fn start(template: &TemplateConfig, dir: &Path) -> Result<Started, TemplateError> {
+    start_cancellable(template, dir, &AtomicBool::new(false))
+}
+
+/// Prepares a conversation from a template like [`start`], killing the
+/// command once `cancelled` is set.
+///
+/// # Errors
+///
+/// Returns a `TemplateError` like [`start`], or `TemplateError::Cancelled`
+/// if the command was cancelled.
+pub fn start_cancellable(
+    template: &TemplateConfig,
+    dir: &Path,
+    cancelled: &AtomicBool,
+) -> Result<Started, TemplateError> {
+    let mut sections: Vec<String> = template.system.iter().cloned().collect();
+    let mut files = 0;
+
+    for pattern in &template.attach {
+        let full_pattern = dir.join(pattern);
+        for path in glob::glob(&full_pattern.to_string_lossy())?.flatten() {
+            if !path.is_file() {
+                continue;
+            }
+            let bytes = fs::read(&path)?;
+            let Ok(content) = String::from_utf8(bytes) else {
+                continue;
+            };
+            let name = path.strip_prefix(dir).unwrap_or(&path);
+            sections.push(format!(
+                "File `{}`:\n```\n{}\n```",
+                name.display(),
+                truncate(&content)
+            ));
+            files += 1;
+        }
+    }
+
+    if let Some(command) = &template.command {
+        let mut sh = Command::new("sh");
+        sh.arg("-c").arg(command).current_dir(dir);
+        let output = process::run_cancellable(&mut sh, None, RUN_TIMEOUT, cancelled).map_err(
+            |err| match err.kind() {
+                ErrorKind::TimedOut => TemplateError::TimedOut(command.clone()),
+                ErrorKind::Interrupted => TemplateError::Cancelled(command.clone()),
+                _ => err.into(),
+            },
+        )?;
+        if !output.status.success() {
+            return Err(TemplateError::Command {
+                command: command.clone(),
+                stderr: output.stderr.trim().to_string(),
+            });
+        }
+        sections.push(format!(
+            "Output of `{command}`:\n```\n{}\n```",
+            truncate(output.stdout.trim_end())
+        ));
+    }
+
+    Ok(Started {
+        system_prompt: (!sections.is_empty()).then(|| sections.join("\n\n")),
+        messages: template.messages.clone(),
+        files,
+        schema: template.schema.clone(),
+    })
+}
+
+/// Builds the request that applies a template to a piece of text, as when
+/// chatti is used as an editor filter.
+///
+/// The text is appended to the template's last message if that is a user
+/// message, and sent as a new user message otherwise.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::message::Message;
+/// use chatti::templates::{apply_to, Started};
+///
+/// let started = Started {
+///     system_prompt: Some("Reply with the corrected text only.".to_string()),
+///     messages: vec![Message::new("user", "Fix the grammar:")],
+///     files: 0,
+///     schema: None,
+/// };
+/// let request = apply_to(started, "he go home\n");
+/// assert_eq!(request.len(), 2);
+/// assert_eq!(request[0].role, "system");
+/// assert_eq!(request[1].content, "Fix the grammar:\n\nhe go home");
+/// ```
+#[must_use]
+pub fn apply_to(started: Started, input: &str) -> Vec<Message> {
+    let mut messages: Vec<Message> = started
+        .system_prompt
+        .map(|prompt| Message::new("system", prompt))
+        .into_iter()
+        .chain(started.messages)
+        .collect();
+    let input = input.trim_end();
+    match messages.last_mut() {
+        Some(last) if last.role == "user" => {
+            last.content = format!("{}\n\n{input}", last.content.trim_end());
+        }
+        _ => messages.push(Message::new("user", input)),
+    }
+    messages
+}
+
+/// Cuts an attached file down to the size sent as context.
+pub(crate) fn truncate(text: &str) -> &str {
+    if text.len() <= MAX_ATTACHMENT_BYTES {
+        return text;
+    }
+    let mut end = MAX_ATTACHMENT_BYTES;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
@@ -0,0 +1,68 @@
+//! Provides a lightweight token count estimate for prompts.
+//!
+//! Exact counts depend on the model's tokenizer; the heuristic here is meant
+//! for budget warnings, not billing.
+
+/// Estimates the number of tokens in `text`.
+///
+/// The estimate takes the larger of roughly four characters per token and
+/// four tokens per three words, which tracks common BPE tokenizers closely
+/// enough for both prose and code.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::tokens::estimate_tokens;
+///
+/// assert_eq!(estimate_tokens(""), 0);
+/// assert!(estimate_tokens("Hello there, how are you today?") >= 6);
+/// ```
+#[must_use]
+pub fn estimate_tokens(text: &str) -> usize {
+    let chars = text.chars().count();
+    let words = text.split_whitespace().count();
+    (chars.div_ceil(4)).max((words * 4).div_ceil(3))
+}
+
+/// Estimates the cost of sending `tokens` prompt tokens.
+///
+/// # Arguments
+///
+/// * `tokens` - The number of prompt tokens.
+/// * `price_per_million` - The price per million prompt tokens.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::tokens::estimate_cost;
+///
+/// assert!((estimate_cost(500_000, 2.0) - 1.0).abs() < f64::EPSILON);
+/// ```
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn estimate_cost(tokens: usize, price_per_million: f64) -> f64 {
+    tokens as f64 * price_per_million / 1_000_000.0
+}
+
+/// Counts of a draft message shown next to the input box.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct DraftStats {
+    /// Number of characters.
+    pub chars: usize,
+    /// Number of whitespace-separated words.
+    pub words: usize,
+    /// Estimated number of tokens.
+    pub tokens: usize,
+}
+
+impl DraftStats {
+    /// Computes the statistics for `text`.
+    #[must_use]
+    pub fn of(text: &str) -> Self {
+        DraftStats {
+            chars: text.chars().count(),
+            words: text.split_whitespace().count(),
+            tokens: estimate_tokens(text),
+        }
+    }
+}
@@ -0,0 +1,54 @@
+//! Translates answers, and asks for every answer in a given language.
+
+/// Returns the prompt asking the model to translate `text` into `language`.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::translate::prompt;
+///
+/// let prompt = prompt("French", "Good morning");
+/// assert!(prompt.starts_with("Translate the following text into French."));
+/// assert!(prompt.ends_with("\n\nGood morning"));
+/// ```
+#[must_use]
+pub fn prompt(language: &str, text: &str) -> String {
+    format!(
+        "Translate the following text into {language}. Keep the formatting, code and names \
+         unchanged, and reply with the translation only.\n\n{text}"
+    )
+}
+
+/// Adds the instruction to answer in `language` to a system prompt.
+///
+/// # Arguments
+///
+/// * `system_prompt` - The system prompt of the conversation, if any.
+/// * `language` - The language answers are requested in, if any.
+///
+/// # Returns
+///
+/// The system prompt to send, or `None` if there is neither.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::translate::with_language;
+///
+/// assert_eq!(
+///     with_language(Some("Be brief."), Some("German")).as_deref(),
+///     Some("Be brief.\n\nAlways answer in German, whatever language the question is asked in.")
+/// );
+/// assert_eq!(with_language(Some("Be brief."), None).as_deref(), Some("Be brief."));
+/// assert_eq!(with_language(None, None), None);
+/// ```
+#[must_use]
+pub fn with_language(system_prompt: Option<&str>, language: Option<&str>) -> Option<String> {
+    let instruction = language.map(|language| {
+        format!("Always answer in {language}, whatever language the question is asked in.")
+    });
+    match (system_prompt, instruction) {
+        (Some(prompt), Some(instruction)) => Some(format!("{prompt}\n\n{instruction}")),
+        (prompt, instruction) => prompt.map(str::to_string).or(instruction),
+    }
+}
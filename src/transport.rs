@@ -0,0 +1,179 @@
+//! Carries chat requests to the server.
+//!
+//! Most servers take a request over HTTP and stream the answer in the body
+//! of the response. Some self-hosted gateways stream over a WebSocket
+//! instead: the request body is sent as a text message, and each message
+//! the server sends back holds one JSON part of the answer in the
+//! provider's format, until a part marks the end or the server closes the
+//! connection. The transport is chosen in the `[transport]` section, for
+//! all credentials profiles or for each one.
+
+use std::{future::Future, io, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{sync::mpsc, time};
+use tokio_tungstenite::tungstenite::{
+    self, client::IntoClientRequest, http::header::AUTHORIZATION, http::HeaderValue,
+    Message as Frame,
+};
+use tracing::{field, instrument};
+
+use crate::api;
+use crate::error::{AppResult, Application};
+use crate::logging::API_TARGET;
+use crate::message::Metadata;
+use crate::provider::Provider;
+use crate::stream::{self, Counts};
+
+/// How long opening a WebSocket may take.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the server may stay silent while the answer streams.
+const READ_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How requests reach the server.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transport {
+    /// An HTTP request, whose response streams the answer.
+    #[default]
+    Http,
+    /// A WebSocket, whose messages stream the answer.
+    #[serde(rename = "websocket")]
+    WebSocket,
+}
+
+/// Returns the WebSocket URL of an endpoint, so `http` and `https`
+/// endpoints can be used as they are configured for other transports.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::transport::websocket_url;
+///
+/// assert_eq!(websocket_url("http://localhost:8080/chat"), "ws://localhost:8080/chat");
+/// assert_eq!(websocket_url("https://gateway.lan/chat"), "wss://gateway.lan/chat");
+/// assert_eq!(websocket_url("wss://gateway.lan/chat"), "wss://gateway.lan/chat");
+/// ```
+#[must_use]
+pub fn websocket_url(endpoint: &str) -> String {
+    if let Some(rest) = endpoint.strip_prefix("http://") {
+        format!("ws://{rest}")
+    } else if let Some(rest) = endpoint.strip_prefix("https://") {
+        format!("wss://{rest}")
+    } else {
+        endpoint.to_string()
+    }
+}
+
+/// Sends a chat request over a WebSocket and sends the content of the
+/// answer to `tx` as it arrives.
+///
+/// # Arguments
+///
+/// * `endpoint` - The endpoint, as an `http`, `https`, `ws` or `wss` URL.
+/// * `api_key` - The API key, sent as a bearer token when opening the
+///   connection.
+/// * `body` - The body of the request, in the provider's format.
+/// * `provider` - The server the answer comes from.
+/// * `tx` - Receives each piece of the content.
+///
+/// # Returns
+///
+/// The token counts and finish reason the answer reported.
+///
+/// # Errors
+///
+/// Returns an `Application` error if the connection cannot be opened in
+/// time, the server refuses it, it fails or stays silent too long while
+/// the answer streams, or the receiver is gone.
+#[instrument(
+    target = API_TARGET,
+    skip_all,
+    fields(?provider, chunks = field::Empty, bytes = field::Empty, parts = field::Empty, malformed = field::Empty)
+)]
+pub async fn send_websocket(
+    endpoint: &str,
+    api_key: Option<&str>,
+    body: &Value,
+    provider: Provider,
+    tx: &mpsc::Sender<Result<String, Application>>,
+) -> AppResult<Metadata> {
+    let mut request = websocket_url(endpoint).into_client_request()?;
+    if let Some(key) = api_key {
+        let value = HeaderValue::from_str(&format!("Bearer {key}"))
+            .map_err(|err| Application::Unexpected(err.to_string()))?;
+        request.headers_mut().insert(AUTHORIZATION, value);
+    }
+    let connection = within(CONNECT_TIMEOUT, tokio_tungstenite::connect_async(request)).await?;
+    let (mut socket, _) = match connection {
+        Ok(connection) => connection,
+        Err(tungstenite::Error::Http(response)) => {
+            let status = response.status();
+            let body = response
+                .body()
+                .as_deref()
+                .map(|body| String::from_utf8_lossy(body).into_owned())
+                .unwrap_or_default();
+            let message = api::parse_error_message(&body)
+                .unwrap_or_else(|| format!("The server responded with {status}"));
+            return Err(Application::ApiError {
+                message,
+                status: status.as_u16(),
+                body,
+                request_id: None,
+            });
+        }
+        Err(err) => return Err(err.into()),
+    };
+    socket.send(Frame::text(body.to_string())).await?;
+
+    let mut metadata = Metadata::default();
+    let mut counts = Counts::default();
+    while let Some(frame) = within(READ_TIMEOUT, socket.next()).await? {
+        let text = match frame? {
+            Frame::Text(text) => text,
+            Frame::Binary(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Frame::Close(_) => break,
+            Frame::Ping(_) | Frame::Pong(_) | Frame::Frame(_) => continue,
+        };
+        let text = text.trim();
+        counts.chunk(
+            text.len(),
+            usize::from(!text.is_empty() && text != "[DONE]"),
+        );
+        if text == "[DONE]" {
+            break;
+        }
+        if text.is_empty() {
+            continue;
+        }
+        let json = serde_json::from_str(text);
+        let finished =
+            stream::forward_value(json, provider, tx, &mut metadata, &mut counts).await?;
+        if finished && !provider.ends_with_marker() {
+            break;
+        }
+    }
+    counts.record();
+    // The answer is complete, so a failure to close cleanly does not matter.
+    let _ = socket.close(None).await;
+    Ok(metadata)
+}
+
+/// Waits for `future`, failing with a timed-out WebSocket error if it takes
+/// longer than `limit`.
+async fn within<T>(limit: Duration, future: impl Future<Output = T>) -> AppResult<T> {
+    time::timeout(limit, future).await.map_err(|_| {
+        tungstenite::Error::Io(io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!(
+                "the server did not answer within {} seconds",
+                limit.as_secs()
+            ),
+        ))
+        .into()
+    })
+}
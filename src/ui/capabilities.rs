@@ -0,0 +1,241 @@
+//! Detects what the terminal can display and degrades colors and glyphs to
+//! match.
+
+use std::env;
+
+use ratatui::{buffer::Buffer, style::Color};
+
+use crate::config::{ColorMode, TerminalConfig};
+
+/// The color depth supported by the terminal.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSupport {
+    /// 24-bit RGB colors.
+    #[default]
+    TrueColor,
+    /// The 256-color xterm palette.
+    Ansi256,
+    /// The 16 basic ANSI colors.
+    Ansi16,
+}
+
+/// The 16 basic ANSI colors with their usual xterm RGB values.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// The channel values of the 6x6x6 color cube in the 256-color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+impl ColorSupport {
+    /// Detects color support from the `COLORTERM` and `TERM` environment
+    /// variables.
+    #[must_use]
+    pub fn detect() -> Self {
+        Self::from_env(
+            env::var("COLORTERM").ok().as_deref(),
+            env::var("TERM").ok().as_deref(),
+        )
+    }
+
+    /// Determines color support from the given `COLORTERM` and `TERM` values.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::capabilities::ColorSupport;
+    ///
+    /// assert_eq!(ColorSupport::from_env(Some("truecolor"), None), ColorSupport::TrueColor);
+    /// assert_eq!(ColorSupport::from_env(None, Some("xterm-256color")), ColorSupport::Ansi256);
+    /// assert_eq!(ColorSupport::from_env(None, Some("linux")), ColorSupport::Ansi16);
+    /// ```
+    #[must_use]
+    pub fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            ColorSupport::TrueColor
+        } else if term.is_some_and(|term| term.contains("256color")) {
+            ColorSupport::Ansi256
+        } else {
+            ColorSupport::Ansi16
+        }
+    }
+
+    /// Maps `color` to the nearest color this terminal can display.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::capabilities::ColorSupport;
+    /// use ratatui::style::Color;
+    ///
+    /// assert_eq!(ColorSupport::Ansi256.adapt(Color::Rgb(255, 0, 0)), Color::Indexed(196));
+    /// assert_eq!(ColorSupport::Ansi16.adapt(Color::Rgb(250, 10, 10)), Color::LightRed);
+    /// assert_eq!(ColorSupport::Ansi16.adapt(Color::Blue), Color::Blue);
+    /// ```
+    #[must_use]
+    pub fn adapt(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorSupport::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_256(r, g, b)),
+            (ColorSupport::Ansi16, Color::Rgb(r, g, b)) => nearest_16(r, g, b),
+            (ColorSupport::Ansi16, Color::Indexed(index)) if index >= 16 => {
+                let (r, g, b) = indexed_rgb(index);
+                nearest_16(r, g, b)
+            }
+            _ => color,
+        }
+    }
+}
+
+/// What the terminal can display.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capabilities {
+    /// The supported color depth.
+    pub colors: ColorSupport,
+    /// Whether non-ASCII glyphs such as braille spinners can be displayed.
+    pub unicode: bool,
+}
+
+impl Default for Capabilities {
+    fn default() -> Self {
+        Capabilities {
+            colors: ColorSupport::TrueColor,
+            unicode: true,
+        }
+    }
+}
+
+impl Capabilities {
+    /// Detects the terminal capabilities, applying the overrides from the
+    /// configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The terminal section of the configuration.
+    #[must_use]
+    pub fn detect(config: &TerminalConfig) -> Self {
+        let colors = match config.colors {
+            ColorMode::Auto => ColorSupport::detect(),
+            ColorMode::TrueColor => ColorSupport::TrueColor,
+            ColorMode::Ansi256 => ColorSupport::Ansi256,
+            ColorMode::Ansi16 => ColorSupport::Ansi16,
+        };
+        let unicode = config.unicode.unwrap_or_else(|| {
+            unicode_from_env(
+                env::var("LC_ALL").ok().as_deref(),
+                env::var("LC_CTYPE").ok().as_deref(),
+                env::var("LANG").ok().as_deref(),
+            )
+        });
+
+        Capabilities { colors, unicode }
+    }
+
+    /// Maps every color in `buffer` to one the terminal can display.
+    pub fn apply(&self, buffer: &mut Buffer) {
+        if self.colors == ColorSupport::TrueColor {
+            return;
+        }
+        for cell in &mut buffer.content {
+            cell.fg = self.colors.adapt(cell.fg);
+            cell.bg = self.colors.adapt(cell.bg);
+        }
+    }
+}
+
+/// Returns `true` if the effective locale uses UTF-8.
+///
+/// The first non-empty value of `LC_ALL`, `LC_CTYPE` and `LANG` wins, as in
+/// the C library.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::ui::capabilities::unicode_from_env;
+///
+/// assert!(unicode_from_env(None, None, Some("en_US.UTF-8")));
+/// assert!(!unicode_from_env(Some("C"), None, Some("en_US.UTF-8")));
+/// ```
+#[must_use]
+pub fn unicode_from_env(lc_all: Option<&str>, lc_ctype: Option<&str>, lang: Option<&str>) -> bool {
+    [lc_all, lc_ctype, lang]
+        .into_iter()
+        .flatten()
+        .find(|value| !value.is_empty())
+        .is_some_and(|locale| {
+            let locale = locale.to_ascii_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+}
+
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let cube_index = |v: u8| match v {
+        0..=47 => 0,
+        48..=114 => 1,
+        _ => (v - 35) / 40,
+    };
+    let (ri, gi, bi) = (cube_index(r), cube_index(g), cube_index(b));
+    let cube = (
+        CUBE_LEVELS[ri as usize],
+        CUBE_LEVELS[gi as usize],
+        CUBE_LEVELS[bi as usize],
+    );
+
+    let average = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+    let gray_index = if average > 238 {
+        23
+    } else {
+        u8::try_from(average.saturating_sub(3) / 10).unwrap_or(23)
+    };
+    let gray_level = 8 + 10 * gray_index;
+
+    if distance((r, g, b), cube) <= distance((r, g, b), (gray_level, gray_level, gray_level)) {
+        16 + 36 * ri + 6 * gi + bi
+    } else {
+        232 + gray_index
+    }
+}
+
+fn nearest_16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, rgb)| distance((r, g, b), *rgb))
+        .map_or(Color::Reset, |(color, _)| *color)
+}
+
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI16[index as usize].1,
+        16..=231 => {
+            let i = index - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        }
+    }
+}
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let d = |x: u8, y: u8| u32::from(x.abs_diff(y)).pow(2);
+    d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
@@ -2,19 +2,48 @@
 
 use color_eyre::Result;
 use crossterm::{
-    event::{self, Event, KeyCode},
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    event::{self, DisableBracketedPaste, EnableBracketedPaste, Event, KeyCode, KeyEventKind},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, BeginSynchronizedUpdate, EndSynchronizedUpdate,
+        EnterAlternateScreen, LeaveAlternateScreen,
+    },
     ExecutableCommand,
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::{
-    io::{stdout, Stdout},
+    io::{stdout, Stdout, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
     time::{Duration, Instant},
 };
 
+use super::dialog::{Answer, Dialog};
 use super::input_handler::InputHandler;
 use super::renderer::Renderer;
 use super::state::{InputMode, State};
+use crate::{
+    attachments::{self, Attachment},
+    clipboard,
+    error::Application,
+    images::{self, Image},
+    logging,
+    message::Message,
+    session::{self, Recovery},
+    shell,
+};
+use tracing::error;
+
+/// How often the conversation and draft are saved for crash recovery.
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How often frames are drawn at most while an answer streams in, unless
+/// the configuration says otherwise.
+const DEFAULT_FRAME_INTERVAL: Duration = Duration::from_millis(33);
 
 /// The main structure for the chat user interface.
 pub struct Interface {
@@ -22,11 +51,22 @@ pub struct Interface {
     pub ui_state: State,
     pub input_handler: InputHandler,
     pub ui_renderer: Renderer,
+    pub last_autosave: Instant,
+    pub saved_recovery: Recovery,
+    pub alternate_screen: bool,
+    pub synchronized_output: bool,
+    pub frame_interval: Duration,
+    pub last_frame: Option<Instant>,
 }
 
 impl Interface {
     /// Creates a new `ChatUI` instance.
     ///
+    /// # Arguments
+    ///
+    /// * `alternate_screen` - Whether to draw on the alternate screen, or in
+    ///   the terminal's normal buffer.
+    ///
     /// # Returns
     ///
     /// A `Result` containing the new `ChatUI` instance or an error.
@@ -34,9 +74,12 @@ impl Interface {
     /// # Errors
     ///
     /// This function will return an error if it fails to set up the terminal or create the UI components.
-    pub fn new() -> Result<Self> {
+    pub fn new(alternate_screen: bool) -> Result<Self> {
         enable_raw_mode()?;
-        stdout().execute(EnterAlternateScreen)?;
+        if alternate_screen {
+            stdout().execute(EnterAlternateScreen)?;
+        }
+        stdout().execute(EnableBracketedPaste)?;
 
         let backend = CrosstermBackend::new(stdout());
         let terminal = Terminal::new(backend)?;
@@ -50,6 +93,12 @@ impl Interface {
             ui_state,
             input_handler,
             ui_renderer,
+            last_autosave: Instant::now(),
+            saved_recovery: Recovery::default(),
+            alternate_screen,
+            synchronized_output: true,
+            frame_interval: DEFAULT_FRAME_INTERVAL,
+            last_frame: None,
         })
     }
 
@@ -77,28 +126,174 @@ impl Interface {
             if self.ui_state.input_mode == InputMode::Waiting {
                 if event::poll(timeout)? {
                     if let Event::Key(key) = event::read()? {
-                        if key.code == KeyCode::Esc {
-                            self.ui_state.input_mode = InputMode::Normal;
-                            self.ui_state.messages.pop();
+                        if key.kind == KeyEventKind::Press && key.code == KeyCode::Esc {
+                            self.ui_state.abandon_response();
                         }
                     }
                 }
                 continue;
             }
 
-            if let Event::Key(key) = event::read()? {
-                match self.ui_state.input_mode {
-                    InputMode::Normal => self
+            if self.ui_state.input_mode == InputMode::Normal && self.ui_state.should_resend() {
+                if let Some(message) = self.ui_state.resend_pending() {
+                    return Ok(Some(message));
+                }
+            }
+
+            if self.ui_state.input_mode == InputMode::Normal {
+                if let Some(prompt) = self.ui_state.take_interjection() {
+                    self.keep_draft();
+                    self.keep_history();
+                    return Ok(Some(prompt));
+                }
+            }
+
+            if self.ui_state.input_mode == InputMode::Normal && self.ui_state.retry.is_some() {
+                return Ok(Some(String::from("/retry")));
+            }
+
+            if self.ui_state.input_mode == InputMode::Normal && self.ui_state.search_hit.is_some() {
+                return Ok(Some(String::from("/search")));
+            }
+
+            if self.ui_state.input_mode == InputMode::Normal && self.ui_state.switch_to.is_some() {
+                return Ok(Some(String::from("/sessions")));
+            }
+
+            if self.ui_state.input_mode == InputMode::Normal
+                && self.ui_state.take_clear_undo_request()
+            {
+                return Ok(Some(String::from("/clear undo")));
+            }
+
+            if self.ui_state.input_mode == InputMode::Normal && self.ui_state.take_context_request()
+            {
+                return Ok(Some(String::from("/context")));
+            }
+
+            if self.ui_state.input_mode == InputMode::Normal
+                && self.ui_state.take_diagrams_request()
+            {
+                return Ok(Some(String::from("/diagrams")));
+            }
+
+            if self.ui_state.input_mode == InputMode::Normal
+                && self.ui_state.take_continuation_request()
+            {
+                return Ok(Some(String::from("/continue")));
+            }
+
+            if matches!(
+                self.ui_state.input_mode,
+                InputMode::Normal | InputMode::Editing
+            ) {
+                self.ui_state.apply_plugin_replies();
+                if let Some(input) = self.ui_state.take_control_input() {
+                    return Ok(Some(input));
+                }
+            }
+
+            if !event::poll(timeout)? {
+                last_tick = Instant::now();
+                continue;
+            }
+
+            let event = event::read()?;
+            if let Event::Paste(text) = &event {
+                self.paste(text)?;
+            }
+            if let Event::Key(key) = event {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                if matches!(
+                    self.ui_state.input_mode,
+                    InputMode::Normal | InputMode::Editing
+                ) {
+                    if self
                         .input_handler
-                        .handle_normal_mode(&mut self.ui_state, key.code),
+                        .handle_palette_shortcut(&mut self.ui_state, key)
+                    {
+                        continue;
+                    }
+                    if let Some(command) = self.input_handler.handle_session_shortcut(key) {
+                        self.keep_draft();
+                        return Ok(Some(command));
+                    }
+                }
+                match self.ui_state.input_mode {
+                    InputMode::Normal => {
+                        if let Some(command) = self.input_handler.handle_normal_shortcut(key) {
+                            return Ok(Some(command));
+                        }
+                        self.input_handler
+                            .handle_normal_mode(&mut self.ui_state, key.code);
+                        if self.ui_state.plain_view {
+                            self.print_plain()?;
+                        }
+                    }
                     InputMode::Editing => {
+                        if self
+                            .input_handler
+                            .handle_editing_shortcut(&mut self.ui_state, key)
+                        {
+                            continue;
+                        }
+                        if key.code == KeyCode::Enter && !self.confirm_substitutions()? {
+                            continue;
+                        }
                         if let Some(message) = self
                             .input_handler
                             .handle_editing_mode(&mut self.ui_state, key.code)?
                         {
+                            self.keep_draft();
+                            self.keep_history();
                             return Ok(Some(message));
                         }
+                        if std::mem::take(&mut self.ui_state.substitutions_requested) {
+                            self.expand_substitutions()?;
+                        }
+                        if self.ui_state.input_mode == InputMode::Normal {
+                            self.keep_draft();
+                        }
                     }
+                    InputMode::Pager => {
+                        self.input_handler
+                            .handle_pager_mode(&mut self.ui_state, key.code);
+                        self.copy_selection();
+                    }
+                    InputMode::Compare => self
+                        .input_handler
+                        .handle_compare_mode(&mut self.ui_state, key.code),
+                    InputMode::Params => self
+                        .input_handler
+                        .handle_params_mode(&mut self.ui_state, key.code),
+                    InputMode::Search => self
+                        .input_handler
+                        .handle_search_mode(&mut self.ui_state, key.code),
+                    InputMode::Diff => self
+                        .input_handler
+                        .handle_diff_mode(&mut self.ui_state, key.code),
+                    InputMode::Details => self
+                        .input_handler
+                        .handle_details_mode(&mut self.ui_state, key.code),
+                    InputMode::Context => self
+                        .input_handler
+                        .handle_context_mode(&mut self.ui_state, key.code),
+                    InputMode::Palette => {
+                        if let Some(command) = self
+                            .input_handler
+                            .handle_palette_mode(&mut self.ui_state, key.code)
+                        {
+                            return Ok(Some(command));
+                        }
+                        if self.ui_state.plain_view {
+                            self.print_plain()?;
+                        }
+                    }
+                    InputMode::Switcher => self
+                        .input_handler
+                        .handle_switcher_mode(&mut self.ui_state, key.code),
                     InputMode::Waiting => {}
                 }
             }
@@ -119,14 +314,318 @@ impl Interface {
         self.ui_state.quit
     }
 
+    /// Copies the lines selected in the pager to the clipboard, if a
+    /// selection was just copied, and says how that went in the pager.
+    fn copy_selection(&mut self) {
+        let pager = &mut self.ui_state.pager;
+        let Some(text) = pager.copied.take() else {
+            return;
+        };
+        pager.notice = Some(match clipboard::write_text(&text) {
+            Ok(()) => format!("copied {} line(s)", text.lines().count()),
+            Err(err) => {
+                error!(?err, "failed to copy the selection");
+                err.to_string()
+            }
+        });
+    }
+
+    /// Prints the selected message as plain text in the terminal's normal
+    /// buffer, where it can be selected and searched with the terminal's own
+    /// tools, and returns to the interface once Enter is pressed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the terminal cannot be switched
+    /// back and forth.
+    pub fn print_plain(&mut self) -> Result<()> {
+        self.ui_state.plain_view = false;
+        let Some(text) = self.ui_state.plain_text().map(str::to_string) else {
+            return Ok(());
+        };
+
+        disable_raw_mode()?;
+        if self.alternate_screen {
+            stdout().execute(LeaveAlternateScreen)?;
+        }
+        let mut out = stdout();
+        writeln!(out, "\n{}\n", text.trim_end())?;
+        write!(out, "── Press Enter to return to chatti ──")?;
+        out.flush()?;
+        // The key is read as an event like any other, so it is not left
+        // behind for the interface, or taken from a read of standard input.
+        enable_raw_mode()?;
+        loop {
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press && key.code == KeyCode::Enter {
+                    break;
+                }
+            }
+        }
+
+        if self.alternate_screen {
+            stdout().execute(EnterAlternateScreen)?;
+        }
+        self.terminal.clear()?;
+        Ok(())
+    }
+
+    /// Asks before running the `$(command)` substitutions of the draft,
+    /// which sending expands for a preview.
+    ///
+    /// # Returns
+    ///
+    /// `false` if the draft has substitutions the user declined to run.
+    fn confirm_substitutions(&mut self) -> Result<bool> {
+        let state = &self.ui_state;
+        if state.shell_allowed.is_empty()
+            || state.shell_preview.is_some()
+            || state.history_search.is_some()
+            || state.input.trim_start().starts_with('/')
+        {
+            return Ok(true);
+        }
+        let commands = match shell::substitutions(&state.input) {
+            Ok(commands) if !commands.is_empty() => commands
+                .iter()
+                .map(|command| format!("  {command}"))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            _ => return Ok(true),
+        };
+        self.confirm(format!(
+            "Run these commands to fill in the prompt?\n\n{commands}\n\nTheir output is shown before sending."
+        ))
+    }
+
+    /// Runs the `$(command)` substitutions of the draft as a task that can
+    /// be cancelled, keeping the interface responsive, and shows the
+    /// expanded prompt for preview.
+    fn expand_substitutions(&mut self) -> Result<()> {
+        let input = self.ui_state.input.trim().to_string();
+        let allowed = self.ui_state.shell_allowed.clone();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (tx, rx) = mpsc::channel();
+        thread::spawn({
+            let cancelled = Arc::clone(&cancelled);
+            move || tx.send(shell::expand_cancellable(&input, &allowed, &cancelled))
+        });
+
+        let task = self.ui_state.tasks.start("Running the prompt's commands");
+        self.ui_state.input_mode = InputMode::Waiting;
+        let result = loop {
+            if self.ui_state.tasks.is_cancelled(task) {
+                cancelled.store(true, Ordering::Relaxed);
+                break None;
+            }
+            match rx.recv_timeout(Duration::from_millis(50)) {
+                Ok(result) => break Some(result),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => break None,
+            }
+            if self.update()?.is_some() {
+                self.ui_state.tasks.cancel_all();
+            }
+        };
+        self.ui_state.tasks.finish(task);
+        self.ui_state.input_mode = InputMode::Editing;
+
+        match result {
+            Some(Ok(expanded)) => self.ui_state.shell_preview = Some(expanded),
+            Some(Err(err)) => {
+                self.ui_state
+                    .add_system_message(format!("Substitution failed: {err}"));
+                self.ui_state.flash_input();
+            }
+            None => self
+                .ui_state
+                .add_system_message(String::from("Substitution cancelled")),
+        }
+        Ok(())
+    }
+
+    /// Saves the unsent draft to disk, or removes the saved one if the input
+    /// is empty.
+    fn keep_draft(&self) {
+        let result = if self.ui_state.input.trim().is_empty() {
+            session::clear_draft()
+        } else {
+            session::save_draft(&self.ui_state.input)
+        };
+        if let Err(err) = result {
+            error!(?err, "failed to save the draft");
+        }
+    }
+
+    /// Saves the history of sent prompts, if it is kept between runs.
+    fn keep_history(&self) {
+        let Some(path) = &self.ui_state.history_file else {
+            return;
+        };
+        if let Err(err) = self.ui_state.history.save(path) {
+            error!(?err, "failed to save the input history");
+        }
+    }
+
+    /// Draws a frame. Terminals that support synchronized updates show it
+    /// at once, so a slow connection does not show it half drawn; ratatui
+    /// only writes the cells that changed since the last frame.
     fn draw(&mut self) -> Result<()> {
+        if self.synchronized_output {
+            self.terminal
+                .backend_mut()
+                .execute(BeginSynchronizedUpdate)?;
+        }
         self.terminal.draw(|f| {
             self.ui_renderer.render(f, &mut self.ui_state);
         })?;
+        if self.synchronized_output {
+            self.terminal.backend_mut().execute(EndSynchronizedUpdate)?;
+        }
+        self.last_frame = Some(Instant::now());
+        self.autosave();
 
         Ok(())
     }
 
+    /// Writes the conversation and draft for crash recovery when they have
+    /// changed since the last save.
+    fn autosave(&mut self) {
+        if self.last_autosave.elapsed() < AUTOSAVE_INTERVAL {
+            return;
+        }
+        self.last_autosave = Instant::now();
+        let recovery = self.ui_state.recovery();
+        if recovery == self.saved_recovery {
+            return;
+        }
+        match recovery.save() {
+            Ok(()) => self.saved_recovery = recovery,
+            Err(err) => error!(?err, "failed to save recovery state"),
+        }
+    }
+
+    /// Handles text pasted while editing. A paste of paths to existing
+    /// files, as terminals paste files dropped onto them, is offered as
+    /// attachments, with a preview of the first table among them; anything
+    /// else goes into the input.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if there are issues with event polling or drawing the UI.
+    fn paste(&mut self, text: &str) -> Result<()> {
+        if self.ui_state.input_mode != InputMode::Editing {
+            return Ok(());
+        }
+        let files = attachments::dropped_files(text);
+        if files.is_empty() {
+            self.input_handler.handle_paste(&mut self.ui_state, text);
+            return Ok(());
+        }
+        let (images, files): (Vec<PathBuf>, Vec<PathBuf>) =
+            files.into_iter().partition(|file| images::is_image(file));
+        let mut loaded = Vec::new();
+        for file in &files {
+            match Attachment::load(file, self.ui_state.sampling) {
+                Ok(attachment) => loaded.push(attachment),
+                Err(err) => self
+                    .ui_state
+                    .add_system_message(format!("Not attaching {}: {err}", file.display())),
+            }
+        }
+        if loaded.is_empty() && images.is_empty() {
+            self.input_handler.handle_paste(&mut self.ui_state, text);
+            return Ok(());
+        }
+        let mut summaries: Vec<String> = loaded.iter().map(Attachment::summary).collect();
+        summaries.extend(images.iter().map(|path| Image::file(path).placeholder()));
+        let tokens: usize = loaded.iter().map(Attachment::tokens).sum();
+        let question = format!(
+            "Attach to the next message instead of pasting the path?\n\n{}\n\nThis adds ~{tokens} tokens to the prompt.",
+            summaries.join("\n")
+        );
+        self.ui_state.table_preview = loaded.iter().find_map(|attachment| {
+            let name = attachment.path.file_name()?.to_string_lossy().into_owned();
+            Some((name, attachment.table.clone()?))
+        });
+        let confirmed = self.confirm(question)?;
+        self.ui_state.table_preview = None;
+        if !confirmed {
+            self.input_handler.handle_paste(&mut self.ui_state, text);
+            return Ok(());
+        }
+        for attachment in &loaded {
+            self.ui_state.attach_context(attachment.context());
+        }
+        for image in images {
+            self.ui_state.attach_image(image);
+        }
+        Ok(())
+    }
+
+    /// Shows a dialog and waits until the user answers it.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if there are issues with event polling or drawing the UI.
+    pub fn ask(&mut self, dialog: Dialog) -> Result<Answer> {
+        self.ui_state.dialog = Some(dialog);
+        let answer = loop {
+            self.draw()?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                let Some(dialog) = self.ui_state.dialog.as_mut() else {
+                    break Answer::No;
+                };
+                if let Some(answer) = dialog.handle_key(key.code) {
+                    break answer;
+                }
+            }
+        };
+        self.ui_state.dialog = None;
+        Ok(answer)
+    }
+
+    /// Asks the user a yes/no question in a popup.
+    ///
+    /// # Arguments
+    ///
+    /// * `question` - The question to ask.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the user answered with `y` or Enter, `false` for `n` or Esc.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if there are issues with event polling or drawing the UI.
+    pub fn confirm(&mut self, question: String) -> Result<bool> {
+        Ok(self.ask(Dialog::confirm(question))? == Answer::Yes)
+    }
+
+    /// Asks the user for a line of text in a popup.
+    ///
+    /// # Arguments
+    ///
+    /// * `question` - What to enter.
+    /// * `initial` - The text to start with, edited in place.
+    ///
+    /// # Returns
+    ///
+    /// The text entered with Enter, or `None` if the user pressed Esc.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if there are issues with event polling or drawing the UI.
+    pub fn prompt(&mut self, question: String, initial: String) -> Result<Option<String>> {
+        Ok(match self.ask(Dialog::prompt(question, initial))? {
+            Answer::Text(text) => Some(text),
+            Answer::Yes | Answer::No => None,
+        })
+    }
+
     /// Updates the UI and checks for user input.
     ///
     /// # Returns
@@ -137,13 +636,21 @@ impl Interface {
     ///
     /// This function will return an error if there are issues with event polling or drawing the UI.
     pub fn update(&mut self) -> Result<Option<super::state::Action>> {
-        self.draw()?;
+        // Chunks arriving faster than the frame rate are drawn together.
+        if self
+            .last_frame
+            .is_none_or(|drawn| drawn.elapsed() >= self.frame_interval)
+        {
+            self.draw()?;
+        }
 
         if event::poll(Duration::from_millis(1))? {
             if let Event::Key(key) = event::read()? {
-                if key.code == KeyCode::Esc && self.ui_state.input_mode == InputMode::Waiting {
-                    self.ui_state.input_mode = InputMode::Normal;
-                    return Ok(Some(super::state::Action::CancelRequest));
+                if key.kind == KeyEventKind::Press && self.ui_state.input_mode == InputMode::Waiting
+                {
+                    return Ok(self
+                        .input_handler
+                        .handle_waiting_mode(&mut self.ui_state, key));
                 }
             }
         }
@@ -157,18 +664,101 @@ impl Interface {
     ///
     /// * `new_content` - A string slice containing the new content to be added to the response.
     pub fn update_response(&mut self, new_content: &str) {
+        if let Some(speaker) = self.ui_state.speaker.as_mut() {
+            speaker.push(new_content);
+        }
         self.ui_state.update_response(new_content);
     }
 
+    /// Returns the system prompt and conversation to send to the API.
+    #[must_use]
+    pub fn request_messages(&self) -> Vec<Message> {
+        self.ui_state.request_messages()
+    }
+
+    /// Queues the message being sent until the endpoint is reachable again.
+    pub fn mark_pending(&mut self) {
+        self.ui_state.mark_pending();
+    }
+
+    /// Ends the current request with an error card.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The error that ended the request.
+    pub fn add_error(&mut self, error: &Application) {
+        let mut details = error.details();
+        details.source = format!(
+            "{} (log: {})",
+            details.source,
+            logging::get_log_file_path().display()
+        );
+        self.discard_speech();
+        self.ui_state
+            .add_error(error.display_message().to_string(), details);
+    }
+
     /// Adds a complete response to the UI.
     ///
     /// # Arguments
     ///
     /// * `response` - A string containing the complete response to be added.
     pub fn add_response(&mut self, response: String) {
+        if let Some(speaker) = self.ui_state.speaker.as_mut() {
+            speaker.finish();
+        }
         self.ui_state.add_response(response);
     }
 
+    /// Replaces the transcript with the given messages.
+    ///
+    /// # Arguments
+    ///
+    /// * `messages` - The messages to show.
+    pub fn load_messages(&mut self, messages: Vec<Message>) {
+        self.ui_state.load_messages(messages);
+    }
+
+    /// Adds a system notice to the transcript.
+    ///
+    /// # Arguments
+    ///
+    /// * `notice` - The text of the notice.
+    pub fn add_system_message(&mut self, notice: String) {
+        self.ui_state.add_system_message(notice);
+    }
+
+    /// Returns the user and assistant messages that make up the conversation.
+    #[must_use]
+    pub fn conversation(&self) -> Vec<Message> {
+        self.ui_state.conversation()
+    }
+
+    /// Stops the current generation, keeping the partial response.
+    pub fn stop_response(&mut self) {
+        self.discard_speech();
+        self.ui_state.stop_response();
+    }
+
+    /// Stops the current generation, keeping the partial response, and
+    /// queues the follow-up typed meanwhile to be sent next.
+    pub fn interject(&mut self) {
+        self.stop_response();
+        self.ui_state.queue_interjection();
+    }
+
+    /// Cancels the current request, discarding the partial response.
+    pub fn cancel_response(&mut self) {
+        self.discard_speech();
+        self.ui_state.cancel_response();
+    }
+
+    fn discard_speech(&mut self) {
+        if let Some(speaker) = self.ui_state.speaker.as_mut() {
+            speaker.discard();
+        }
+    }
+
     /// Prepares the UI for a new response.
     pub fn start_new_response(&mut self) {
         self.ui_state.start_new_response();
@@ -177,7 +767,10 @@ impl Interface {
 
 impl Drop for Interface {
     fn drop(&mut self) {
+        let _ = stdout().execute(DisableBracketedPaste);
         disable_raw_mode().unwrap();
-        stdout().execute(LeaveAlternateScreen).unwrap();
+        if self.alternate_screen {
+            stdout().execute(LeaveAlternateScreen).unwrap();
+        }
     }
 }
@@ -0,0 +1,223 @@
+//! Guesses the language of code blocks that were not labeled with one.
+//!
+//! Models often open a fence with a bare ```` ``` ````. The guess looks at
+//! the first line (a shebang, `<?php`, a doctype or a JSON document) and
+//! otherwise counts keywords and idioms typical of each language. A guess
+//! needs at least [`MIN_SIGNALS`] of them, so short or ambiguous snippets
+//! stay plain text rather than being highlighted wrongly.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// How many distinct signals of a language a block needs for it to be
+/// guessed.
+pub const MIN_SIGNALS: usize = 2;
+
+/// The signals of each language, in order of preference when several
+/// languages score the same. The names are understood by the highlighter.
+static SIGNALS: LazyLock<Vec<(&'static str, Vec<Regex>)>> = LazyLock::new(|| {
+    let languages: [(&str, &[&str]); 12] = [
+        (
+            "rust",
+            &[
+                r"(?m)^\s*(pub(\(crate\))? )?(async )?fn \w+",
+                r"(?m)^\s*let (mut )?\w+(: [\w<>&]+)? =",
+                r"(?m)^\s*(impl|trait|mod|enum|struct) \w+",
+                r"(?m)^\s*use \w+::",
+                r"#\[(derive|test|cfg)",
+                r"\w+!\(",
+                r"&(mut )?self\b",
+                r"\) -> [\w<>&()]+",
+                r"\b(Some|Ok|Err)\(",
+            ],
+        ),
+        (
+            "python",
+            &[
+                r"(?m)^\s*def \w+\(.*\):",
+                r"(?m)^\s*(from [\w.]+ )?import \w+",
+                r"(?m)^\s*(if|elif|for|while|with|try|class)\b.*:\s*$",
+                r"\bself\.\w+",
+                r"\bprint\(",
+                r"\b(None|True|False)\b",
+                r"__\w+__",
+            ],
+        ),
+        (
+            "javascript",
+            &[
+                r"(?m)^\s*(export )?(const|let|var) \w+ =",
+                r"\bfunction\s*\w*\(",
+                r"=>",
+                r"\bconsole\.\w+\(",
+                r"\brequire\(|(?m)^\s*import .* from ",
+                r"===|!==",
+                r"\b(document|window)\.",
+            ],
+        ),
+        (
+            "go",
+            &[
+                r"(?m)^package \w+",
+                r"(?m)^func ",
+                r":=",
+                r"\bfmt\.\w+\(",
+                r"(?m)^import \(",
+                r"err != nil",
+            ],
+        ),
+        (
+            "c",
+            &[
+                r"(?m)^#include <\w+\.h>",
+                r"(?m)^\s*(int|void|char|static) \**\w+\(",
+                r"\b(printf|malloc|free|sizeof)\(",
+                r"\bNULL\b",
+                r"\w->\w",
+            ],
+        ),
+        (
+            "cpp",
+            &[
+                r"(?m)^#include <\w+>",
+                r"\bstd::",
+                r"\b(cout|cin|endl)\b",
+                r"\btemplate\s*<",
+                r"(?m)^\s*(namespace|class) \w+",
+                r"\bnullptr\b",
+            ],
+        ),
+        (
+            "java",
+            &[
+                r"\b(public|private|protected) (static )?(final )?(class|void|\w+) \w+",
+                r"\bSystem\.(out|err)\.",
+                r"(?m)^import java\.",
+                r"@Override",
+                r"\bnew \w+(<.*>)?\(",
+                r"\bString\[\]",
+            ],
+        ),
+        (
+            "bash",
+            &[
+                r"(?m)^\s*(echo|export|cd|sudo|mkdir|rm|cp|mv|chmod|curl) ",
+                r"(?m)^\s*(if|while|for) .*; (then|do)$",
+                r"(?m)^\s*(fi|done|esac)$",
+                r"\$\(|\$\{\w+",
+                r"(?m)^\s*(apt|apt-get|brew|npm|pip|cargo|git|docker) \w+",
+                r" \| (grep|sed|awk|xargs|sort)\b",
+            ],
+        ),
+        (
+            "sql",
+            &[
+                r"(?mi)^\s*select\b",
+                r"(?i)\bfrom \w+",
+                r"(?i)\bwhere\b",
+                r"(?i)\b(insert into|update \w+ set|delete from)\b",
+                r"(?i)\bcreate (table|index|view)\b",
+                r"(?i)\b(group|order) by\b",
+                r"(?i)\b(inner |left |right )?join\b",
+            ],
+        ),
+        (
+            "html",
+            &[
+                r"(?i)<(html|head|body|div|span|p|ul|li|a|script|style)[\s>]",
+                r"</\w+>",
+                r#"\b(class|href|src|id)=""#,
+            ],
+        ),
+        (
+            "css",
+            &[
+                r"(?m)^[\w.#:\-\[\]=, >]+\s*\{\s*$",
+                r"(?m)^\s*[\w-]+:\s*[^;]+;\s*$",
+                r"\b\d+(px|em|rem|%)\b",
+                r"@media\b",
+            ],
+        ),
+        (
+            "yaml",
+            &[
+                r"(?m)^[\w-]+:\s*$",
+                r"(?m)^\s+[\w-]+: \S",
+                r"(?m)^\s*- [\w-]+: ",
+                r"(?m)^---$",
+            ],
+        ),
+    ];
+    languages
+        .into_iter()
+        .map(|(name, patterns)| {
+            let patterns = patterns
+                .iter()
+                .map(|pattern| Regex::new(pattern).expect("valid language signal"))
+                .collect();
+            (name, patterns)
+        })
+        .collect()
+});
+
+/// Guesses the language of a code block, or returns `None` if it does not
+/// look like any language clearly enough.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::ui::code_language::guess;
+///
+/// assert_eq!(guess("fn main() {\n    println!(\"hi\");\n}\n"), Some("rust"));
+/// assert_eq!(guess("#!/usr/bin/env python3\nprint('hi')\n"), Some("python"));
+/// assert_eq!(guess("{\"name\": \"chatti\"}"), Some("json"));
+/// assert_eq!(guess("let answer = 42;"), None);
+/// ```
+#[must_use]
+pub fn guess(code: &str) -> Option<&'static str> {
+    if let Some(language) = guess_from_first_line(code) {
+        return Some(language);
+    }
+
+    let mut best = None;
+    let mut best_score = MIN_SIGNALS - 1;
+    for (name, patterns) in SIGNALS.iter() {
+        let score = patterns
+            .iter()
+            .filter(|pattern| pattern.is_match(code))
+            .count();
+        if score > best_score {
+            best = Some(*name);
+            best_score = score;
+        }
+    }
+    best
+}
+
+/// Recognises a language from the first line alone.
+fn guess_from_first_line(code: &str) -> Option<&'static str> {
+    let trimmed = code.trim_start();
+    let first = trimmed.lines().next()?;
+    if let Some(shebang) = first.strip_prefix("#!") {
+        let interpreter = shebang
+            .split_whitespace()
+            .map(|word| word.rsplit('/').next().unwrap_or(word))
+            .find(|word| *word != "env" && !word.starts_with('-'))?
+            .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        return match interpreter {
+            "sh" | "bash" | "zsh" => Some("bash"),
+            "python" => Some("python"),
+            "node" => Some("javascript"),
+            _ => None,
+        };
+    }
+    if first.starts_with("<?php") {
+        return Some("php");
+    }
+    if first.to_ascii_lowercase().starts_with("<!doctype html") {
+        return Some("html");
+    }
+    let is_json = (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok();
+    is_json.then_some("json")
+}
@@ -0,0 +1,60 @@
+//! Holds the two responses of a side-by-side model comparison.
+
+/// Two responses to the same prompt from different models.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Comparison {
+    /// The model answering on each side.
+    pub models: [String; 2],
+    /// The response received so far on each side.
+    pub responses: [String; 2],
+    /// Whether each side has finished streaming.
+    pub done: [bool; 2],
+}
+
+impl Comparison {
+    /// Creates an empty comparison between two models.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::compare::Comparison;
+    ///
+    /// let mut comparison = Comparison::new(["llama3.2".to_string(), "mistral".to_string()]);
+    /// comparison.push(0, "Hello");
+    /// comparison.finish(0);
+    /// assert_eq!(comparison.responses[0], "Hello");
+    /// assert!(!comparison.is_done());
+    /// ```
+    #[must_use]
+    pub fn new(models: [String; 2]) -> Self {
+        Comparison {
+            models,
+            ..Comparison::default()
+        }
+    }
+
+    /// Appends streamed content to one side.
+    ///
+    /// # Arguments
+    ///
+    /// * `side` - `0` for the left model, `1` for the right one.
+    /// * `content` - The new content.
+    pub fn push(&mut self, side: usize, content: &str) {
+        if let Some(response) = self.responses.get_mut(side) {
+            response.push_str(content);
+        }
+    }
+
+    /// Marks one side as finished.
+    pub fn finish(&mut self, side: usize) {
+        if let Some(done) = self.done.get_mut(side) {
+            *done = true;
+        }
+    }
+
+    /// Returns `true` once both sides have finished.
+    #[must_use]
+    pub fn is_done(&self) -> bool {
+        self.done.iter().all(|done| *done)
+    }
+}
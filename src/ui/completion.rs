@@ -0,0 +1,320 @@
+//! Completes slash commands and file paths in the input.
+//!
+//! While the input starts with `/`, a popup above it lists the commands
+//! matching what was typed. After a command that takes a path, such as
+//! `/export`, it lists the matching files instead. A word starting with `@`
+//! anywhere in a prompt is completed with the files of the working directory
+//! matching it fuzzily. `Up`/`Down` select a suggestion, `Tab` accepts it
+//! and `Esc` hides the popup until the next edit.
+
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::commands::{expand_home, COMMANDS};
+
+/// The most suggestions listed at once.
+pub const MAX_SUGGESTIONS: usize = 50;
+
+/// The most files looked at when completing a mention, so large trees do
+/// not slow down typing.
+pub const MAX_MENTION_FILES: usize = 5000;
+
+/// Directories skipped when completing a mention, besides hidden ones.
+const SKIPPED_DIRS: [&str; 2] = ["target", "node_modules"];
+
+/// How long the files listed under a directory are reused for mentions,
+/// so the tree is not walked again on every keystroke.
+const MENTION_FILES_TTL: Duration = Duration::from_secs(5);
+
+/// The files listed under a directory, and when they were listed.
+type Listing = (Instant, Arc<Vec<String>>);
+
+/// The files last listed under each directory.
+static MENTION_FILES: LazyLock<Mutex<HashMap<PathBuf, Listing>>> = LazyLock::new(Mutex::default);
+
+/// A suggested completion.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Suggestion {
+    /// The text that replaces the completed part of the input.
+    pub text: String,
+    /// A short description shown next to it.
+    pub detail: String,
+}
+
+/// The suggestions for the current input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// The byte offset in the input where the completed part starts.
+    pub start: usize,
+    /// The suggestions, in the order they are listed.
+    pub suggestions: Vec<Suggestion>,
+    /// The index of the selected suggestion.
+    pub selected: usize,
+}
+
+impl Completion {
+    /// Returns the suggestions for an input, or `None` if there is nothing
+    /// to complete.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::completion::Completion;
+    ///
+    /// let completion = Completion::for_input("/ex").unwrap();
+    /// let texts: Vec<_> = completion.suggestions.iter().map(|s| s.text.as_str()).collect();
+    /// assert_eq!(texts, vec!["/explain-staged", "/export "]);
+    /// assert!(Completion::for_input("hello").is_none());
+    /// ```
+    #[must_use]
+    pub fn for_input(input: &str) -> Option<Self> {
+        let (start, suggestions) = match input.strip_prefix('/') {
+            Some(rest) => Self::for_command(input, rest)?,
+            None => Self::for_mention(input)?,
+        };
+        let unchanged = matches!(suggestions.as_slice(), [only] if only.text == input[start..]);
+        if suggestions.is_empty() || unchanged {
+            return None;
+        }
+        Some(Completion {
+            start,
+            suggestions,
+            selected: 0,
+        })
+    }
+
+    /// Completes the name of a slash command, or the path it takes.
+    fn for_command(input: &str, rest: &str) -> Option<(usize, Vec<Suggestion>)> {
+        Some(match rest.split_once(' ') {
+            None => (0, complete_command(rest)),
+            Some((name, arg)) => {
+                COMMANDS
+                    .iter()
+                    .find(|command| command.name == name && command.takes_path)?;
+                (input.len() - arg.len(), complete_path(arg))
+            }
+        })
+    }
+
+    /// Completes an `@` mention in the last word of the input.
+    fn for_mention(input: &str) -> Option<(usize, Vec<Suggestion>)> {
+        let start = input
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map_or(0, |(index, c)| index + c.len_utf8());
+        let query = input[start..].strip_prefix('@')?;
+        Some((start, complete_mention(query, Path::new("."))))
+    }
+
+    /// Selects the previous suggestion, wrapping around.
+    pub fn select_previous(&mut self) {
+        let len = self.suggestions.len();
+        self.selected = (self.selected + len - 1) % len;
+    }
+
+    /// Selects the next suggestion, wrapping around.
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % self.suggestions.len();
+    }
+
+    /// Returns the input with the completed part replaced by the selected
+    /// suggestion.
+    #[must_use]
+    pub fn apply(&self, input: &str) -> String {
+        let suggestion = &self.suggestions[self.selected];
+        format!("{}{}", &input[..self.start], suggestion.text)
+    }
+}
+
+/// Returns the slash commands starting with `prefix`. Commands that take
+/// arguments are completed with a trailing space.
+#[must_use]
+pub fn complete_command(prefix: &str) -> Vec<Suggestion> {
+    COMMANDS
+        .iter()
+        .filter(|command| command.name.starts_with(prefix))
+        .map(|command| Suggestion {
+            text: if command.args.is_empty() {
+                format!("/{}", command.name)
+            } else {
+                format!("/{} ", command.name)
+            },
+            detail: format!("{} {}", command.args, command.summary)
+                .trim_start()
+                .to_string(),
+        })
+        .collect()
+}
+
+/// Returns the files and directories whose path starts with `partial`,
+/// sorted by name. Directories end with `/` so they can be completed
+/// further. Hidden files are only listed once the name starts with `.`.
+#[must_use]
+pub fn complete_path(partial: &str) -> Vec<Suggestion> {
+    let (dir, prefix) = partial
+        .rfind('/')
+        .map_or(("", partial), |slash| partial.split_at(slash + 1));
+    let path = if dir.is_empty() {
+        PathBuf::from(".")
+    } else {
+        expand_home(dir)
+    };
+    let Ok(entries) = fs::read_dir(path) else {
+        return Vec::new();
+    };
+
+    let mut suggestions: Vec<Suggestion> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.file_type().is_ok_and(|kind| kind.is_dir());
+            Some(Suggestion {
+                text: format!("{dir}{name}{}", if is_dir { "/" } else { "" }),
+                detail: String::from(if is_dir { "directory" } else { "" }),
+            })
+        })
+        .collect();
+    suggestions.sort_by(|a, b| a.text.cmp(&b.text));
+    suggestions.truncate(MAX_SUGGESTIONS);
+    suggestions
+}
+
+/// Returns the files under `dir` whose path matches `query` fuzzily, best
+/// matches first, as `@path ` mentions.
+///
+/// Hidden files and build directories are skipped. The files of a
+/// directory are listed once every few seconds at most, so files added
+/// since may be missing for a moment.
+#[must_use]
+pub fn complete_mention(query: &str, dir: &Path) -> Vec<Suggestion> {
+    let mut matches: Vec<(usize, String)> = cached_files_under(dir)
+        .iter()
+        .filter_map(|path| fuzzy_score(query, path).map(|score| (score, path.clone())))
+        .collect();
+    matches.sort_by(|(a_score, a), (b_score, b)| {
+        (Reverse(a_score), a.len(), a).cmp(&(Reverse(b_score), b.len(), b))
+    });
+    matches
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, path)| Suggestion {
+            text: format!("@{path} "),
+            detail: String::new(),
+        })
+        .collect()
+}
+
+/// Scores how well `candidate` matches `query` when the characters of the
+/// query appear in it in order, ignoring case, or returns `None` if they do
+/// not. Runs of consecutive characters and characters starting a path
+/// segment or word score higher; the best placement of the query counts.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::ui::completion::fuzzy_score;
+///
+/// assert!(fuzzy_score("uist", "src/ui/state.rs").is_some());
+/// assert!(fuzzy_score("main", "src/main.rs") > fuzzy_score("main", "src/markdown_input.rs"));
+/// assert_eq!(fuzzy_score("xyz", "src/ui/state.rs"), None);
+/// ```
+#[must_use]
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<usize> {
+    let query: Vec<char> = query.chars().collect();
+    let chars: Vec<char> = candidate.chars().collect();
+    let Some((first, rest)) = query.split_first() else {
+        return Some(0);
+    };
+    let bonus = |index: usize| {
+        let boundary = index == 0 || matches!(chars[index - 1], '/' | '_' | '-' | '.' | ' ');
+        if boundary {
+            3
+        } else {
+            1
+        }
+    };
+
+    // best[j] is the best score of the query so far with its last character
+    // matched at index j of the candidate.
+    let mut best: Vec<Option<usize>> = (0..chars.len())
+        .map(|index| {
+            chars[index]
+                .eq_ignore_ascii_case(first)
+                .then(|| bonus(index))
+        })
+        .collect();
+    for wanted in rest {
+        let mut next = vec![None; chars.len()];
+        let mut before = None;
+        for index in 1..chars.len() {
+            if index >= 2 {
+                before = before.max(best[index - 2]);
+            }
+            if chars[index].eq_ignore_ascii_case(wanted) {
+                let consecutive = best[index - 1].map(|score| score + 4);
+                next[index] = consecutive.max(before).map(|score| score + bonus(index));
+            }
+        }
+        best = next;
+    }
+    best.into_iter().flatten().max()
+}
+
+/// Returns the files under `dir` like [`files_under`], listing them again
+/// only once the last listing is older than [`MENTION_FILES_TTL`].
+fn cached_files_under(dir: &Path) -> Arc<Vec<String>> {
+    let mut cache = MENTION_FILES
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    if let Some((listed, files)) = cache.get(dir) {
+        if listed.elapsed() < MENTION_FILES_TTL {
+            return Arc::clone(files);
+        }
+    }
+    let files = Arc::new(files_under(dir));
+    cache.insert(dir.to_path_buf(), (Instant::now(), Arc::clone(&files)));
+    files
+}
+
+/// Lists the files under `dir`, relative to it, up to
+/// [`MAX_MENTION_FILES`].
+fn files_under(dir: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let Ok(entries) = fs::read_dir(dir.join(&relative)) else {
+            continue;
+        };
+        let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+        entries.sort_by_key(fs::DirEntry::file_name);
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+            let path = relative.join(&name);
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => {
+                    if !SKIPPED_DIRS.contains(&name.as_str()) {
+                        pending.push(path);
+                    }
+                }
+                Ok(_) => files.push(path.to_string_lossy().into_owned()),
+                Err(_) => {}
+            }
+            if files.len() >= MAX_MENTION_FILES {
+                return files;
+            }
+        }
+    }
+    files
+}
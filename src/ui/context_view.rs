@@ -0,0 +1,102 @@
+//! Shows what the next request would send.
+//!
+//! When an answer misses something, it helps to see the context the model
+//! was given: the system prompt, the history, the attached files and the
+//! excerpts retrieved from the index. Pressing `c` in normal mode, or
+//! entering `/context`, opens a popup with the request that sending the
+//! draft would make, one section at a time, each with its estimated
+//! tokens.
+
+use crate::tokens::estimate_tokens;
+
+/// A part of the request, such as the system prompt or the history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// What the part is.
+    pub title: String,
+    /// The text sent for it.
+    pub text: String,
+    /// The estimated tokens of the text.
+    pub tokens: usize,
+}
+
+impl Section {
+    /// Creates a section, estimating its tokens from the text.
+    #[must_use]
+    pub fn new(title: impl Into<String>, text: String) -> Self {
+        Section {
+            title: title.into(),
+            tokens: estimate_tokens(&text),
+            text,
+        }
+    }
+}
+
+/// The request shown in the popup.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ContextView {
+    /// The parts of the request, in the order they are sent.
+    pub sections: Vec<Section>,
+    /// The tokens the model's context holds.
+    pub budget: usize,
+    /// The first line shown.
+    pub scroll: u16,
+}
+
+impl ContextView {
+    /// Returns the estimated tokens of the whole request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::context_view::{ContextView, Section};
+    ///
+    /// let view = ContextView {
+    ///     sections: vec![
+    ///         Section::new("System prompt", "Be brief.".to_string()),
+    ///         Section::new("Next message", "What is a monad?".to_string()),
+    ///     ],
+    ///     budget: 4096,
+    ///     scroll: 0,
+    /// };
+    /// assert_eq!(view.tokens(), 3 + 6);
+    /// ```
+    #[must_use]
+    pub fn tokens(&self) -> usize {
+        self.sections.iter().map(|section| section.tokens).sum()
+    }
+
+    /// Inserts a section before the next message, or at the end if there
+    /// is none.
+    pub fn insert_before_message(&mut self, section: Section) {
+        let index = self
+            .sections
+            .iter()
+            .position(|section| section.title == NEXT_MESSAGE)
+            .unwrap_or(self.sections.len());
+        self.sections.insert(index, section);
+    }
+
+    /// Scrolls the view up by `lines`.
+    pub fn scroll_up(&mut self, lines: u16) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    /// Scrolls the view down by `lines`, stopping at its last line.
+    pub fn scroll_down(&mut self, lines: u16) {
+        // A heading, the text and a blank line for each section.
+        let last = self
+            .sections
+            .iter()
+            .map(|section| section.text.lines().count() + 2)
+            .sum::<usize>()
+            .saturating_sub(1);
+        self.scroll = self
+            .scroll
+            .saturating_add(lines)
+            .min(u16::try_from(last).unwrap_or(u16::MAX));
+    }
+}
+
+/// The title of the section holding the message being sent.
+pub const NEXT_MESSAGE: &str = "Next message";
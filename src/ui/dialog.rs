@@ -0,0 +1,118 @@
+//! Modal dialogs that ask before destructive actions.
+//!
+//! A dialog is either a yes/no question or a prompt for a line of text,
+//! such as another path to write to. While it is open it takes every key;
+//! `Interface::confirm` and `Interface::prompt` show one and wait for the
+//! answer, so the caller acts only once the user agreed.
+
+use crossterm::event::KeyCode;
+
+/// What a dialog asks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Kind {
+    /// A yes/no question.
+    Confirm,
+    /// A line of text, edited in place.
+    Prompt(String),
+}
+
+/// How the user answered a dialog.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Answer {
+    /// The question was answered with yes.
+    Yes,
+    /// The dialog was declined or cancelled.
+    No,
+    /// The prompt was answered with this text.
+    Text(String),
+}
+
+/// A modal question over the chat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dialog {
+    /// The question, which may span several lines.
+    pub question: String,
+    /// What the dialog asks for.
+    pub kind: Kind,
+}
+
+impl Dialog {
+    /// Creates a yes/no question.
+    #[must_use]
+    pub fn confirm(question: impl Into<String>) -> Self {
+        Dialog {
+            question: question.into(),
+            kind: Kind::Confirm,
+        }
+    }
+
+    /// Creates a prompt for a line of text, starting with `initial`.
+    #[must_use]
+    pub fn prompt(question: impl Into<String>, initial: impl Into<String>) -> Self {
+        Dialog {
+            question: question.into(),
+            kind: Kind::Prompt(initial.into()),
+        }
+    }
+
+    /// Returns the keys that answer the dialog, for its footer.
+    #[must_use]
+    pub fn hint(&self) -> &'static str {
+        match self.kind {
+            Kind::Confirm => "y/Enter: yes   n/Esc: no",
+            Kind::Prompt(_) => "Enter: OK   Esc: cancel",
+        }
+    }
+
+    /// Handles a key while the dialog is open. A question is answered with
+    /// `y` or `Enter` and declined with `n` or `Esc`; a prompt is edited by
+    /// typing, answered with `Enter` unless it is blank and cancelled with
+    /// `Esc`.
+    ///
+    /// # Returns
+    ///
+    /// The answer, once the key gave one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::dialog::{Answer, Dialog};
+    /// use crossterm::event::KeyCode;
+    ///
+    /// let mut dialog = Dialog::prompt("Write to?", "notes.md");
+    /// for _ in 0..2 {
+    ///     dialog.handle_key(KeyCode::Backspace);
+    /// }
+    /// dialog.handle_key(KeyCode::Char('t'));
+    /// dialog.handle_key(KeyCode::Char('x'));
+    /// assert_eq!(dialog.handle_key(KeyCode::Char('t')), None);
+    /// assert_eq!(
+    ///     dialog.handle_key(KeyCode::Enter),
+    ///     Some(Answer::Text(String::from("notes.txt")))
+    /// );
+    /// ```
+    pub fn handle_key(&mut self, key: KeyCode) -> Option<Answer> {
+        match &mut self.kind {
+            Kind::Confirm => match key {
+                KeyCode::Char('y' | 'Y') | KeyCode::Enter => Some(Answer::Yes),
+                KeyCode::Char('n' | 'N') | KeyCode::Esc => Some(Answer::No),
+                _ => None,
+            },
+            Kind::Prompt(text) => match key {
+                KeyCode::Enter if !text.trim().is_empty() => {
+                    Some(Answer::Text(text.trim().to_string()))
+                }
+                KeyCode::Esc => Some(Answer::No),
+                KeyCode::Char(c) => {
+                    text.push(c);
+                    None
+                }
+                KeyCode::Backspace => {
+                    text.pop();
+                    None
+                }
+                _ => None,
+            },
+        }
+    }
+}
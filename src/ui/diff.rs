@@ -0,0 +1,259 @@
+//! Shows what changed between two attempts at an answer.
+//!
+//! Retrying an answer inserts the new attempt below the earlier ones, so
+//! the attempts at an answer sit next to each other in the transcript.
+//! Pressing `d` on an attempt opens a popup with a word-level diff against
+//! the attempt above it: removed words are struck out in red, added words
+//! are green. Attempts too long to diff word by word are diffed line by
+//! line.
+
+/// The most cells of the table of common subsequences built for a diff,
+/// bounding its time and memory; past it, a coarser diff is made.
+pub const MAX_CELLS: usize = 4_000_000;
+
+/// A run of words in a diff.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Text both attempts have.
+    Same(String),
+    /// Text only the earlier attempt has.
+    Removed(String),
+    /// Text only the later attempt has.
+    Added(String),
+}
+
+impl Change {
+    /// Returns the text of the change.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        match self {
+            Change::Same(text) | Change::Removed(text) | Change::Added(text) => text,
+        }
+    }
+}
+
+/// Diffs two texts word by word, keeping whitespace with the words so the
+/// changes put together give back either text. Texts whose differing parts
+/// are too long for that are diffed line by line, or, failing that, shown
+/// as one removal and one addition.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::ui::diff::{diff_words, Change};
+///
+/// assert_eq!(
+///     diff_words("The quick fox", "The slow fox"),
+///     vec![
+///         Change::Same("The ".to_string()),
+///         Change::Removed("quick".to_string()),
+///         Change::Added("slow".to_string()),
+///         Change::Same(" fox".to_string()),
+///     ]
+/// );
+/// ```
+#[must_use]
+pub fn diff_words(old: &str, new: &str) -> Vec<Change> {
+    let changes = diff_tokens(&tokens(old), &tokens(new))
+        .or_else(|| diff_tokens(&lines(old), &lines(new)))
+        .unwrap_or_else(|| {
+            [
+                Change::Removed(old.to_string()),
+                Change::Added(new.to_string()),
+            ]
+            .into_iter()
+            .filter(|change| !change.text().is_empty())
+            .collect()
+        });
+    group(&changes)
+}
+
+/// Diffs two sequences of tokens by their longest common subsequence.
+///
+/// # Returns
+///
+/// The change of each token, or `None` if the parts that differ are too
+/// long to diff within [`MAX_CELLS`].
+fn diff_tokens(old: &[&str], new: &[&str]) -> Option<Vec<Change>> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_middle, new_middle) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+    if (old_middle.len() + 1).saturating_mul(new_middle.len() + 1) > MAX_CELLS {
+        return None;
+    }
+
+    // lengths[i][j] is the length of the longest common subsequence of
+    // old_middle[i..] and new_middle[j..].
+    let width = new_middle.len() + 1;
+    let mut lengths = vec![0u32; (old_middle.len() + 1) * width];
+    for i in (0..old_middle.len()).rev() {
+        for j in (0..new_middle.len()).rev() {
+            lengths[i * width + j] = if old_middle[i] == new_middle[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut changes: Vec<Change> = old[..prefix]
+        .iter()
+        .map(|token| Change::Same((*token).to_string()))
+        .collect();
+    let (mut i, mut j) = (0, 0);
+    while i < old_middle.len() || j < new_middle.len() {
+        if i < old_middle.len() && j < new_middle.len() && old_middle[i] == new_middle[j] {
+            changes.push(Change::Same(old_middle[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if i < old_middle.len()
+            && (j == new_middle.len() || lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+        {
+            changes.push(Change::Removed(old_middle[i].to_string()));
+            i += 1;
+        } else {
+            changes.push(Change::Added(new_middle[j].to_string()));
+            j += 1;
+        }
+    }
+    changes.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|token| Change::Same((*token).to_string())),
+    );
+    Some(changes)
+}
+
+/// Splits text into words and the whitespace between them.
+fn tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut in_space = None;
+    for (index, c) in text.char_indices() {
+        let space = c.is_whitespace();
+        if in_space.is_some_and(|in_space| in_space != space) {
+            tokens.push(&text[start..index]);
+            start = index;
+        }
+        in_space = Some(space);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+/// Splits text into lines, each with its line break.
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+/// Merges the changes of single words into runs, each run of changes as
+/// the removed text followed by the added text. Whitespace between words
+/// that are replaced is made part of the replacement, so a changed phrase
+/// reads as one change instead of being cut at every space.
+fn group(changes: &[Change]) -> Vec<Change> {
+    let mut grouped = Vec::new();
+    let (mut removed, mut added) = (String::new(), String::new());
+    for (index, change) in changes.iter().enumerate() {
+        match change {
+            Change::Removed(text) => removed.push_str(text),
+            Change::Added(text) => added.push_str(text),
+            Change::Same(text) => {
+                let bridges = !removed.is_empty()
+                    && !added.is_empty()
+                    && text.trim().is_empty()
+                    && changes
+                        .get(index + 1)
+                        .is_some_and(|next| !matches!(next, Change::Same(_)));
+                if bridges {
+                    removed.push_str(text);
+                    added.push_str(text);
+                    continue;
+                }
+                flush(&mut grouped, &mut removed, &mut added);
+                match grouped.last_mut() {
+                    Some(Change::Same(last)) => last.push_str(text),
+                    _ => grouped.push(change.clone()),
+                }
+            }
+        }
+    }
+    flush(&mut grouped, &mut removed, &mut added);
+    grouped
+}
+
+/// Ends a run of changes.
+fn flush(grouped: &mut Vec<Change>, removed: &mut String, added: &mut String) {
+    if !removed.is_empty() {
+        grouped.push(Change::Removed(std::mem::take(removed)));
+    }
+    if !added.is_empty() {
+        grouped.push(Change::Added(std::mem::take(added)));
+    }
+}
+
+/// The diff shown in the popup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffView {
+    /// What the earlier attempt is, such as its model and parameters.
+    pub old_label: String,
+    /// What the later attempt is.
+    pub new_label: String,
+    /// The changes from the earlier attempt to the later one.
+    pub changes: Vec<Change>,
+    /// The first line shown.
+    pub scroll: u16,
+}
+
+impl DiffView {
+    /// Diffs two attempts at an answer.
+    #[must_use]
+    pub fn new(old_label: String, old: &str, new_label: String, new: &str) -> Self {
+        DiffView {
+            old_label,
+            new_label,
+            changes: diff_words(old, new),
+            scroll: 0,
+        }
+    }
+
+    /// Scrolls the diff up by `lines`.
+    pub fn scroll_up(&mut self, lines: u16) {
+        self.scroll = self.scroll.saturating_sub(lines);
+    }
+
+    /// Scrolls the diff down by `lines`, stopping at its last line.
+    pub fn scroll_down(&mut self, lines: u16) {
+        let last = self
+            .changes
+            .iter()
+            .map(|change| change.text().matches('\n').count())
+            .sum::<usize>();
+        self.scroll = self
+            .scroll
+            .saturating_add(lines)
+            .min(u16::try_from(last).unwrap_or(u16::MAX));
+    }
+
+    /// Returns the number of words removed and added.
+    #[must_use]
+    pub fn counts(&self) -> (usize, usize) {
+        let words = |text: &str| text.split_whitespace().count();
+        self.changes
+            .iter()
+            .fold((0, 0), |(removed, added), change| match change {
+                Change::Removed(text) => (removed + words(text), added),
+                Change::Added(text) => (removed, added + words(text)),
+                Change::Same(_) => (removed, added),
+            })
+    }
+}
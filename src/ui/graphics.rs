@@ -0,0 +1,121 @@
+//! Draws images inline on terminals with a graphics protocol.
+//!
+//! Images are drawn with the kitty, iTerm2 or sixel protocol, whichever the
+//! terminal supports, into rows the transcript leaves blank below each
+//! image's name and dimensions. On other terminals, and when chatti is
+//! built without the `images` feature, only the name and dimensions are
+//! shown.
+
+use ratatui::{layout::Rect, Frame};
+
+use crate::config::ImageMode;
+use crate::images::Image;
+
+#[cfg(feature = "images")]
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+};
+
+#[cfg(feature = "images")]
+use ratatui_image::{
+    picker::{Picker, ProtocolType},
+    protocol::Protocol,
+    Resize,
+};
+
+/// The rows of the transcript an image drawn inline takes.
+pub const IMAGE_ROWS: u16 = 12;
+
+/// The size of a terminal cell in pixels assumed when the terminal does
+/// not report it.
+#[cfg(feature = "images")]
+const DEFAULT_FONT_SIZE: (u16, u16) = (8, 16);
+
+/// Draws images with the terminal's graphics protocol.
+pub struct Graphics {
+    #[cfg(feature = "images")]
+    picker: Picker,
+    /// The encoded images by image and size, or `None` for those that
+    /// could not be decoded.
+    #[cfg(feature = "images")]
+    encoded: HashMap<(u64, u16, u16), Option<Box<dyn Protocol>>>,
+}
+
+impl std::fmt::Debug for Graphics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("Graphics");
+        #[cfg(feature = "images")]
+        debug.field("protocol", &self.picker.protocol_type);
+        debug.finish_non_exhaustive()
+    }
+}
+
+impl Graphics {
+    /// Returns the rows of the transcript an image drawn inline takes.
+    #[must_use]
+    pub fn rows(&self) -> u16 {
+        IMAGE_ROWS
+    }
+}
+
+#[cfg(feature = "images")]
+impl Graphics {
+    /// Finds the graphics protocol of the terminal. It may ask the
+    /// terminal, so it is called once the interface is shown and before
+    /// events are read.
+    ///
+    /// # Returns
+    ///
+    /// The graphics, or `None` if images are turned off or the terminal
+    /// has no graphics protocol.
+    #[must_use]
+    pub fn detect(mode: ImageMode) -> Option<Self> {
+        #[cfg(unix)]
+        let picker = Picker::from_termios().ok();
+        #[cfg(not(unix))]
+        let picker = None;
+        let mut picker = picker.unwrap_or_else(|| Picker::new(DEFAULT_FONT_SIZE));
+        picker.protocol_type = match mode {
+            ImageMode::Off => return None,
+            ImageMode::Auto => picker.guess_protocol(),
+            ImageMode::Kitty => ProtocolType::Kitty,
+            ImageMode::Iterm2 => ProtocolType::Iterm2,
+            ImageMode::Sixel => ProtocolType::Sixel,
+        };
+        // Half blocks are too coarse to be worth the rows they take.
+        (picker.protocol_type != ProtocolType::Halfblocks).then(|| Graphics {
+            picker,
+            encoded: HashMap::new(),
+        })
+    }
+
+    /// Draws an image fitted into an area, unless it cannot be read or
+    /// decoded.
+    pub fn draw(&mut self, f: &mut Frame, image: &Image, area: Rect) {
+        let mut hasher = DefaultHasher::new();
+        image.hash(&mut hasher);
+        let key = (hasher.finish(), area.width, area.height);
+        let picker = &mut self.picker;
+        let encoded = self.encoded.entry(key).or_insert_with(|| {
+            let decoded = image::load_from_memory(&image.bytes()?).ok()?;
+            picker.new_protocol(decoded, area, Resize::Fit(None)).ok()
+        });
+        if let Some(encoded) = encoded {
+            f.render_widget(ratatui_image::Image::new(encoded.as_ref()), area);
+        }
+    }
+}
+
+#[cfg(not(feature = "images"))]
+impl Graphics {
+    /// Finds no graphics protocol, since chatti was built without the
+    /// `images` feature.
+    #[must_use]
+    pub fn detect(_: ImageMode) -> Option<Self> {
+        None
+    }
+
+    /// Draws nothing, since there is no graphics protocol.
+    pub fn draw(&mut self, _: &mut Frame, _: &Image, _: Rect) {}
+}
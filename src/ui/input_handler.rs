@@ -1,8 +1,29 @@
 //! Handles user input for the chat UI.
 
-use super::state::{InputMode, State};
+use super::pager::MarkCommand;
+use super::palette::Target;
+use super::state::{Action, InputMode, State};
+use super::undo::Edit;
+use crate::{
+    lint::{self, Warning},
+    message::Mark,
+    shell,
+};
 use color_eyre::Result;
-use crossterm::event::KeyCode;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+/// The minimum number of non-whitespace characters in a sendable message.
+pub const MIN_MESSAGE_LENGTH: usize = 1;
+
+/// Enter presses within this interval after a send are ignored.
+pub const SEND_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// How many columns Left/Right pan code blocks in normal mode.
+pub const CODE_PAN_COLUMNS: isize = 8;
 
 /// Handles user input for the chat UI.
 #[derive(Default)]
@@ -22,12 +43,59 @@ impl InputHandler {
     /// * `ui_state` - A mutable reference to the current UI state.
     /// * `key` - The key code of the pressed key.
     pub fn handle_normal_mode(&self, ui_state: &mut State, key: KeyCode) {
+        if ui_state.show_toggle {
+            Self::handle_help(ui_state, key);
+            return;
+        }
         match key {
             KeyCode::Char('q') => ui_state.quit = true,
             KeyCode::Char('?') => ui_state.show_toggle = !ui_state.show_toggle,
             KeyCode::Char('e') => ui_state.input_mode = InputMode::Editing,
+            KeyCode::Char('t') => ui_state.open_pager(),
+            KeyCode::Char('p') => ui_state.open_params(),
+            KeyCode::Char('r') => ui_state.open_retry(),
+            KeyCode::Char('d') => ui_state.open_diff(),
+            KeyCode::Char('i') => ui_state.open_details(),
+            KeyCode::Char('c') => ui_state.request_context(),
+            KeyCode::Char('g') => ui_state.request_diagrams(),
+            KeyCode::Char('m') => ui_state.request_continuation(),
+            KeyCode::Char('s') => ui_state.show_plain(),
+            KeyCode::Char('>') => ui_state.quote_selected(),
+            KeyCode::Char('v') => ui_state.select_in_pager(),
+            KeyCode::Char('+') => ui_state.toggle_mark(Mark::Good),
+            KeyCode::Char('-') => ui_state.toggle_mark(Mark::Bad),
+            KeyCode::Char('*') => ui_state.toggle_mark(Mark::Favorite),
             KeyCode::Up => ui_state.scroll_up(),
             KeyCode::Down => ui_state.scroll_down(),
+            KeyCode::PageUp => ui_state.page_up(),
+            KeyCode::PageDown => ui_state.page_down(),
+            KeyCode::End => ui_state.follow_latest(),
+            KeyCode::Char('x') => ui_state.toggle_error_details(),
+            KeyCode::Char('n') => ui_state.jump_to_unread(),
+            KeyCode::Char('u') if ui_state.can_undo_clear() => ui_state.request_clear_undo(),
+            KeyCode::Char('u') => ui_state.undo_input(),
+            KeyCode::Char('w') => ui_state.toggle_code_wrap(),
+            KeyCode::Char('R') => ui_state.toggle_raw(),
+            KeyCode::Left => ui_state.pan_code(-CODE_PAN_COLUMNS),
+            KeyCode::Right => ui_state.pan_code(CODE_PAN_COLUMNS),
+            _ => {}
+        }
+    }
+
+    /// Handles input while the help popup is open: `Up`/`Down` and
+    /// `PageUp`/`PageDown` scroll it, and `?`, `q` or `Esc` close it.
+    fn handle_help(ui_state: &mut State, key: KeyCode) {
+        let scroll = &mut ui_state.help_scroll;
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => *scroll = scroll.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => *scroll = scroll.saturating_add(1),
+            KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+            KeyCode::PageDown | KeyCode::Char(' ') => *scroll = scroll.saturating_add(10),
+            KeyCode::Home | KeyCode::Char('g') => *scroll = 0,
+            KeyCode::Char('?' | 'q') | KeyCode::Esc => {
+                ui_state.show_toggle = false;
+                ui_state.help_scroll = 0;
+            }
             _ => {}
         }
     }
@@ -51,30 +119,128 @@ impl InputHandler {
         ui_state: &mut State,
         key: KeyCode,
     ) -> Result<Option<String>> {
+        if ui_state.history_search.is_some() {
+            Self::handle_history_search(ui_state, key);
+            return Ok(None);
+        }
         match key {
             KeyCode::Enter => {
-                let message: String = ui_state.input.drain(..).collect();
-                ui_state
-                    .messages
-                    .push(("user".to_string(), message.clone()));
-                ui_state.input_mode = InputMode::Waiting;
-                ui_state
-                    .messages
-                    .push(("system".to_string(), "Generating...".to_string()));
+                let debounced = ui_state
+                    .last_send
+                    .is_some_and(|sent| sent.elapsed() < SEND_DEBOUNCE);
+                if debounced {
+                    return Ok(None);
+                }
+                if ui_state.input.trim().chars().count() < MIN_MESSAGE_LENGTH {
+                    ui_state.flash_input();
+                    return Ok(None);
+                }
+
+                let is_command = ui_state.input.trim_start().starts_with('/');
+                if !is_command
+                    && ui_state.shell_preview.is_none()
+                    && Self::preview_substitutions(ui_state)
+                {
+                    return Ok(None);
+                }
+                if !is_command && ui_state.lint_warnings.is_empty() {
+                    let prompt = ui_state
+                        .shell_preview
+                        .clone()
+                        .unwrap_or_else(|| ui_state.input.clone());
+                    let mut warnings = lint::check(&prompt, Path::new("."));
+                    let credentials = ui_state.scan_outgoing(&prompt);
+                    if !credentials.is_empty() {
+                        warnings.retain(|warning| *warning != Warning::Secret);
+                        warnings.extend(credentials);
+                    }
+                    ui_state.lint_warnings = warnings;
+                    if !ui_state.lint_warnings.is_empty() {
+                        return Ok(None);
+                    }
+                }
+                if !is_command
+                    && !ui_state.awaiting_cost_confirmation
+                    && ui_state.needs_cost_confirmation()
+                {
+                    ui_state.awaiting_cost_confirmation = true;
+                    return Ok(None);
+                }
+                ui_state.awaiting_cost_confirmation = false;
+                ui_state.lint_warnings.clear();
+
+                let message = ui_state
+                    .shell_preview
+                    .take()
+                    .unwrap_or_else(|| ui_state.input.clone());
+                let input = std::mem::take(&mut ui_state.input);
+                ui_state.remember_input(&input);
+                ui_state.completion = None;
+                ui_state.undo.clear();
                 ui_state.horizontal_scroll = 0;
+                ui_state.last_send = Some(Instant::now());
+                if is_command {
+                    ui_state.input_mode = InputMode::Normal;
+                    return Ok(Some(message));
+                }
+                ui_state.submit_prompt(message.clone());
                 ui_state.horizontal_scroll_state = ratatui::widgets::ScrollbarState::default();
                 Ok(Some(message))
             }
             KeyCode::Char(c) => {
+                ui_state.undo.record(&ui_state.input, Edit::Insert);
                 ui_state.input.push(c);
+                if c.is_whitespace() {
+                    ui_state.undo.break_step();
+                }
+                ui_state.awaiting_cost_confirmation = false;
+                ui_state.lint_warnings.clear();
+                ui_state.shell_preview = None;
+                ui_state.update_completion();
                 Ok(None)
             }
             KeyCode::Backspace => {
+                if !ui_state.input.is_empty() {
+                    ui_state.undo.record(&ui_state.input, Edit::Delete);
+                }
                 ui_state.input.pop();
+                ui_state.awaiting_cost_confirmation = false;
+                ui_state.lint_warnings.clear();
+                ui_state.shell_preview = None;
+                ui_state.update_completion();
+                Ok(None)
+            }
+            KeyCode::Tab => {
+                ui_state.accept_completion();
+                Ok(None)
+            }
+            KeyCode::Esc if ui_state.completion.is_some() => {
+                ui_state.completion = None;
+                Ok(None)
+            }
+            KeyCode::Esc if ui_state.shell_preview.is_some() => {
+                ui_state.shell_preview = None;
+                ui_state.lint_warnings.clear();
                 Ok(None)
             }
             KeyCode::Esc => {
                 ui_state.input_mode = InputMode::Normal;
+                ui_state.awaiting_cost_confirmation = false;
+                ui_state.lint_warnings.clear();
+                Ok(None)
+            }
+            KeyCode::Up => {
+                match ui_state.completion.as_mut() {
+                    Some(completion) => completion.select_previous(),
+                    None => ui_state.recall_previous(),
+                }
+                Ok(None)
+            }
+            KeyCode::Down => {
+                match ui_state.completion.as_mut() {
+                    Some(completion) => completion.select_next(),
+                    None => ui_state.recall_next(),
+                }
                 Ok(None)
             }
             KeyCode::Left => {
@@ -92,4 +258,458 @@ impl InputHandler {
             _ => Ok(None),
         }
     }
+
+    /// Handles key combinations with `Ctrl` in editing mode: `Ctrl+Z` undoes
+    /// the last edit of the input, `Ctrl+Y` redoes it and `Ctrl+R` searches
+    /// the history of sent prompts.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui_state` - A mutable reference to the current UI state.
+    /// * `key` - The pressed key.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the key was handled, `false` if it should be handled as
+    /// regular input.
+    pub fn handle_editing_shortcut(&self, ui_state: &mut State, key: KeyEvent) -> bool {
+        if !key.modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+        match key.code {
+            KeyCode::Char('z') => ui_state.undo_input(),
+            KeyCode::Char('y') => ui_state.redo_input(),
+            KeyCode::Char('r') => ui_state.search_history(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Handles `Ctrl+L` in normal mode, which clears the conversation.
+    ///
+    /// # Returns
+    ///
+    /// The `/clear` command, if the key was `Ctrl+L`.
+    pub fn handle_normal_shortcut(&self, key: KeyEvent) -> Option<String> {
+        (key.code == KeyCode::Char('l') && key.modifiers.contains(KeyModifiers::CONTROL))
+            .then(|| String::from("/clear"))
+    }
+
+    /// Handles `Ctrl+P`, which opens the command palette in normal and
+    /// editing mode.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the key was handled.
+    pub fn handle_palette_shortcut(&self, ui_state: &mut State, key: KeyEvent) -> bool {
+        let opens = key.code == KeyCode::Char('p') && key.modifiers.contains(KeyModifiers::CONTROL);
+        if opens {
+            ui_state.open_palette();
+        }
+        opens
+    }
+
+    /// Handles the session keys of normal and editing mode: `Ctrl+N` starts
+    /// a new session and `Ctrl+O` opens the session switcher.
+    ///
+    /// # Returns
+    ///
+    /// The slash command the key runs, if it is one of them.
+    pub fn handle_session_shortcut(&self, key: KeyEvent) -> Option<String> {
+        if !key.modifiers.contains(KeyModifiers::CONTROL) {
+            return None;
+        }
+        match key.code {
+            KeyCode::Char('n') => Some(String::from("/new")),
+            KeyCode::Char('o') => Some(String::from("/sessions")),
+            _ => None,
+        }
+    }
+
+    /// Handles input while the command palette is open: typing filters it,
+    /// `Up`/`Down` choose an entry, `Enter` runs it and `Esc` closes the
+    /// palette. An action is run by pressing its key in normal mode. A
+    /// command without arguments, or with optional ones only, is run right
+    /// away; the others are put in the input to complete, as a step that
+    /// can be undone.
+    ///
+    /// # Returns
+    ///
+    /// The slash command to run, if one was chosen.
+    pub fn handle_palette_mode(&self, ui_state: &mut State, key: KeyCode) -> Option<String> {
+        let palette = ui_state.palette.as_mut()?;
+        match key {
+            KeyCode::Esc => ui_state.close_palette(),
+            KeyCode::Up => palette.select_previous(),
+            KeyCode::Down | KeyCode::Tab => palette.select_next(),
+            KeyCode::Backspace => palette.pop(),
+            KeyCode::Char(c) => palette.push(c),
+            KeyCode::Enter => {
+                let target = palette.chosen()?.target;
+                ui_state.close_palette();
+                match target {
+                    Target::Key(key) => self.handle_normal_mode(ui_state, key),
+                    Target::Command(command)
+                        if !command.args.is_empty() && !command.args.starts_with('[') =>
+                    {
+                        // A draft replaced by the command comes back with undo.
+                        ui_state.set_input(format!("/{} ", command.name));
+                        ui_state.input_mode = InputMode::Editing;
+                    }
+                    Target::Command(command) => return Some(format!("/{}", command.name)),
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Handles input while the session switcher is open: typing filters
+    /// the sessions, `Up`/`Down` choose one, `Enter` opens it and `Esc`
+    /// closes the switcher.
+    pub fn handle_switcher_mode(&self, ui_state: &mut State, key: KeyCode) {
+        let Some(switcher) = ui_state.switcher.as_mut() else {
+            return;
+        };
+        match key {
+            KeyCode::Esc => ui_state.close_switcher(false),
+            KeyCode::Enter => ui_state.close_switcher(true),
+            KeyCode::Up => switcher.select_previous(),
+            KeyCode::Down | KeyCode::Tab => switcher.select_next(),
+            KeyCode::Backspace => switcher.pop(),
+            KeyCode::Char(c) => switcher.push(c),
+            _ => {}
+        }
+    }
+
+    /// Handles input during a history search: typing refines the query,
+    /// `Enter` puts the match in the input and `Esc` leaves the input as it
+    /// was.
+    fn handle_history_search(ui_state: &mut State, key: KeyCode) {
+        match key {
+            KeyCode::Enter => ui_state.finish_history_search(true),
+            KeyCode::Esc => ui_state.finish_history_search(false),
+            KeyCode::Char(c) => ui_state.edit_history_search(Some(c)),
+            KeyCode::Backspace => ui_state.edit_history_search(None),
+            _ => {}
+        }
+    }
+
+    /// Asks for the `$(command)` substitutions in the draft to be expanded
+    /// for preview, once all their commands are allowed. They are run as a
+    /// task outside of the input loop.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the draft had substitutions and sending must wait for the
+    /// preview to be confirmed or the error to be fixed.
+    fn preview_substitutions(ui_state: &mut State) -> bool {
+        if ui_state.shell_allowed.is_empty() {
+            return false;
+        }
+        let checked = shell::substitutions(&ui_state.input).and_then(|found| {
+            for command in &found {
+                shell::check_allowed(command, &ui_state.shell_allowed)?;
+            }
+            Ok(!found.is_empty())
+        });
+        match checked {
+            Ok(false) => false,
+            Ok(true) => {
+                ui_state.substitutions_requested = true;
+                true
+            }
+            Err(err) => {
+                ui_state.add_system_message(format!("Substitution failed: {err}"));
+                ui_state.flash_input();
+                true
+            }
+        }
+    }
+
+    /// Handles input while a request is in flight.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui_state` - A mutable reference to the current UI state.
+    /// * `key` - The pressed key.
+    ///
+    /// # Returns
+    ///
+    /// The `Action` to apply to the in-flight request, if any: `Esc` cancels
+    /// the request and `Ctrl+C` stops generation, keeping the partial answer.
+    /// `Up`/`Down` and `PageUp`/`PageDown` scroll the transcript and `End`
+    /// resumes following the response. Typing writes a follow-up, and
+    /// `Enter` stops generation and sends it right away.
+    pub fn handle_waiting_mode(&self, ui_state: &mut State, key: KeyEvent) -> Option<Action> {
+        match (key.code, key.modifiers) {
+            (KeyCode::Esc, _) => Some(Action::CancelRequest),
+            (KeyCode::Char('c'), KeyModifiers::CONTROL) => Some(Action::StopGeneration),
+            (KeyCode::Enter, _) => ui_state.can_interject().then_some(Action::Interject),
+            // Digits are typed into a follow-up being written, not taken as
+            // cancelling a task.
+            (KeyCode::Char(c @ '1'..='9'), _)
+                if !ui_state.tasks.is_empty() && ui_state.input.is_empty() =>
+            {
+                ui_state.tasks.cancel(c as usize - '1' as usize);
+                None
+            }
+            (KeyCode::Char(c), KeyModifiers::NONE | KeyModifiers::SHIFT) => {
+                ui_state.input.push(c);
+                None
+            }
+            (KeyCode::Backspace, _) => {
+                ui_state.input.pop();
+                None
+            }
+            (KeyCode::Up, _) => {
+                ui_state.scroll_up();
+                None
+            }
+            (KeyCode::Down, _) => {
+                ui_state.scroll_down();
+                None
+            }
+            (KeyCode::PageUp, _) => {
+                ui_state.page_up();
+                None
+            }
+            (KeyCode::PageDown, _) => {
+                ui_state.page_down();
+                None
+            }
+            (KeyCode::End, _) => {
+                ui_state.follow_latest();
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Handles input while choosing between two compared responses.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui_state` - A mutable reference to the current UI state.
+    /// * `key` - The key code of the pressed key.
+    pub fn handle_compare_mode(&self, ui_state: &mut State, key: KeyCode) {
+        match key {
+            KeyCode::Char('1' | 'h') | KeyCode::Left => ui_state.keep_comparison(Some(0)),
+            KeyCode::Char('2' | 'l') | KeyCode::Right => ui_state.keep_comparison(Some(1)),
+            KeyCode::Esc => ui_state.keep_comparison(None),
+            _ => {}
+        }
+    }
+
+    /// Handles input while the parameters popup is open: `Up`/`Down` select
+    /// a row, `Left`/`Right` adjust a parameter, `Backspace` resets it,
+    /// `Enter` applies the changes or starts the retry and `Esc` discards
+    /// them. When retrying, the model row is edited by typing.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui_state` - A mutable reference to the current UI state.
+    /// * `key` - The key code of the pressed key.
+    pub fn handle_params_mode(&self, ui_state: &mut State, key: KeyCode) {
+        match key {
+            KeyCode::Enter => return ui_state.close_params(true),
+            KeyCode::Esc => return ui_state.close_params(false),
+            _ => {}
+        }
+        let Some(editor) = ui_state.params_editor.as_mut() else {
+            return;
+        };
+        if let Some(model) = editor.model_mut() {
+            match key {
+                KeyCode::Char(c) => model.push(c),
+                KeyCode::Backspace => {
+                    model.pop();
+                }
+                _ => {}
+            }
+        }
+        match (key, editor.selected()) {
+            (KeyCode::Up, _) => editor.select_previous(),
+            (KeyCode::Down, _) => editor.select_next(),
+            (KeyCode::Char('q' | 'p'), Some(_)) => ui_state.close_params(false),
+            (KeyCode::Char('k'), Some(_)) => editor.select_previous(),
+            (KeyCode::Char('j'), Some(_)) => editor.select_next(),
+            (KeyCode::Left | KeyCode::Char('h'), Some(param)) => editor.draft.step(param, false),
+            (KeyCode::Right | KeyCode::Char('l'), Some(param)) => editor.draft.step(param, true),
+            (KeyCode::Backspace | KeyCode::Delete, Some(param)) => editor.draft.reset(param),
+            _ => {}
+        }
+    }
+
+    /// Handles input while the session search popup is open: `Up`/`Down`
+    /// highlight a result, `Enter` opens its session and `Esc` closes the
+    /// popup.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui_state` - A mutable reference to the current UI state.
+    /// * `key` - The key code of the pressed key.
+    pub fn handle_search_mode(&self, ui_state: &mut State, key: KeyCode) {
+        let Some(results) = ui_state.search_results.as_mut() else {
+            return;
+        };
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => results.select_previous(),
+            KeyCode::Down | KeyCode::Char('j') => results.select_next(),
+            KeyCode::Enter => ui_state.close_search(true),
+            KeyCode::Esc | KeyCode::Char('q') => ui_state.close_search(false),
+            _ => {}
+        }
+    }
+
+    /// Adds pasted text to the input in editing mode, as one step to undo.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui_state` - A mutable reference to the current UI state.
+    /// * `text` - The pasted text.
+    pub fn handle_paste(&self, ui_state: &mut State, text: &str) {
+        if ui_state.input_mode != InputMode::Editing {
+            return;
+        }
+        ui_state.insert_text(text);
+    }
+
+    /// Handles input while the details popup is open: `Esc` closes it.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui_state` - A mutable reference to the current UI state.
+    /// * `key` - The key code of the pressed key.
+    pub fn handle_details_mode(&self, ui_state: &mut State, key: KeyCode) {
+        if matches!(
+            key,
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q' | 'i')
+        ) {
+            ui_state.close_details();
+        }
+    }
+
+    /// Handles input while the diff popup is open: `Up`/`Down` and
+    /// `PageUp`/`PageDown` scroll, and `Esc` closes the popup.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui_state` - A mutable reference to the current UI state.
+    /// * `key` - The key code of the pressed key.
+    pub fn handle_diff_mode(&self, ui_state: &mut State, key: KeyCode) {
+        let Some(diff) = ui_state.diff.as_mut() else {
+            return;
+        };
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => diff.scroll_up(1),
+            KeyCode::Down | KeyCode::Char('j') => diff.scroll_down(1),
+            KeyCode::PageUp => diff.scroll_up(10),
+            KeyCode::PageDown => diff.scroll_down(10),
+            KeyCode::Esc | KeyCode::Char('q' | 'd') => ui_state.close_diff(),
+            _ => {}
+        }
+    }
+
+    /// Handles input while the context viewer is open: `Up`/`Down` and
+    /// `PageUp`/`PageDown` scroll, and `Esc` closes the popup.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui_state` - A mutable reference to the current UI state.
+    /// * `key` - The key code of the pressed key.
+    pub fn handle_context_mode(&self, ui_state: &mut State, key: KeyCode) {
+        let Some(view) = ui_state.context_view.as_mut() else {
+            return;
+        };
+        match key {
+            KeyCode::Up | KeyCode::Char('k') => view.scroll_up(1),
+            KeyCode::Down | KeyCode::Char('j') => view.scroll_down(1),
+            KeyCode::PageUp => view.scroll_up(10),
+            KeyCode::PageDown => view.scroll_down(10),
+            KeyCode::Esc | KeyCode::Char('q' | 'c') => ui_state.close_context(),
+            _ => {}
+        }
+    }
+
+    /// Handles input in pager mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `ui_state` - A mutable reference to the current UI state.
+    /// * `key` - The key code of the pressed key.
+    pub fn handle_pager_mode(&self, ui_state: &mut State, key: KeyCode) {
+        let pager = &mut ui_state.pager;
+
+        if let Some(query) = pager.search_input.as_mut() {
+            match key {
+                KeyCode::Enter => {
+                    let query = pager.search_input.take().unwrap_or_default();
+                    pager.search(&query);
+                }
+                KeyCode::Esc => pager.search_input = None,
+                KeyCode::Backspace => {
+                    query.pop();
+                }
+                KeyCode::Char(c) => query.push(c),
+                _ => {}
+            }
+            return;
+        }
+
+        pager.notice = None;
+        if pager.selection.is_some() {
+            let page = isize::try_from(pager.height.max(1)).unwrap_or(isize::MAX);
+            match key {
+                KeyCode::Char('j') | KeyCode::Down => pager.move_selection(1),
+                KeyCode::Char('k') | KeyCode::Up => pager.move_selection(-1),
+                KeyCode::Char(' ' | 'f') | KeyCode::PageDown => pager.move_selection(page),
+                KeyCode::Char('b') | KeyCode::PageUp => pager.move_selection(-page),
+                KeyCode::Char('g') | KeyCode::Home => pager.move_selection(isize::MIN),
+                KeyCode::Char('G') | KeyCode::End => pager.move_selection(isize::MAX),
+                KeyCode::Char('y') | KeyCode::Enter => pager.copy_selection(),
+                KeyCode::Char('v') | KeyCode::Esc => pager.selection = None,
+                KeyCode::Char('q') => {
+                    pager.selection = None;
+                    pager.archived = Vec::new();
+                    pager.filter = None;
+                    ui_state.input_mode = InputMode::Normal;
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        if let Some(command) = pager.pending_mark.take() {
+            if let KeyCode::Char(c) = key {
+                match command {
+                    MarkCommand::Set => pager.set_mark(c),
+                    MarkCommand::Jump => pager.jump_to_mark(c),
+                }
+            }
+            return;
+        }
+
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => {
+                pager.archived = Vec::new();
+                pager.filter = None;
+                ui_state.input_mode = InputMode::Normal;
+            }
+            KeyCode::Char('j') | KeyCode::Down => pager.scroll_down(1),
+            KeyCode::Char('k') | KeyCode::Up => pager.scroll_up(1),
+            KeyCode::Char(' ' | 'f') | KeyCode::PageDown => pager.page_down(),
+            KeyCode::Char('b') | KeyCode::PageUp => pager.page_up(),
+            KeyCode::Char('g') | KeyCode::Home => pager.top(),
+            KeyCode::Char('G') | KeyCode::End => pager.bottom(),
+            KeyCode::Char('/') => pager.search_input = Some(String::new()),
+            KeyCode::Char('v') => pager.start_selection(),
+            KeyCode::Char('n') => pager.next_match(),
+            KeyCode::Char('N') => pager.previous_match(),
+            KeyCode::Char('m') => pager.pending_mark = Some(MarkCommand::Set),
+            KeyCode::Char('\'') => pager.pending_mark = Some(MarkCommand::Jump),
+            KeyCode::Char(c @ '1'..='9') => pager.jump_to_footnote(&c.to_string()),
+            _ => {}
+        }
+    }
 }
@@ -0,0 +1,335 @@
+//! Lists the key bindings of every input mode, for the help popup and the
+//! command palette.
+//!
+//! The help popup is generated from this list and the slash commands, so
+//! a binding added to the input handler is documented by adding it here.
+//! Bindings of normal mode with a single key are actions the command
+//! palette can run, by pressing that key.
+
+use crossterm::event::KeyCode;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use unicode_width::UnicodeWidthStr;
+
+use super::state::InputMode;
+use crate::commands::COMMANDS;
+
+/// A key binding of an input mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Binding {
+    /// The mode the keys work in.
+    pub mode: InputMode,
+    /// The keys, as shown in the help.
+    pub keys: &'static str,
+    /// What they do, in a few words.
+    pub action: &'static str,
+    /// The key that runs the action in normal mode, for the command
+    /// palette.
+    pub key: Option<KeyCode>,
+}
+
+impl Binding {
+    const fn new(mode: InputMode, keys: &'static str, action: &'static str) -> Self {
+        Binding {
+            mode,
+            keys,
+            action,
+            key: None,
+        }
+    }
+
+    /// A binding of normal mode the command palette can run.
+    const fn action(key: KeyCode, keys: &'static str, action: &'static str) -> Self {
+        Binding {
+            mode: InputMode::Normal,
+            keys,
+            action,
+            key: Some(key),
+        }
+    }
+}
+
+/// The modes in the order the help lists them, with their headings.
+pub const MODES: &[(InputMode, &str)] = &[
+    (InputMode::Normal, "Normal mode"),
+    (InputMode::Editing, "Editing"),
+    (InputMode::Waiting, "While an answer is generated"),
+    (InputMode::Pager, "Transcript pager"),
+    (InputMode::Compare, "Comparing answers"),
+    (InputMode::Params, "Parameters"),
+    (InputMode::Search, "Session search"),
+    (InputMode::Diff, "Diff and context viewers"),
+    (InputMode::Details, "Answer details"),
+    (InputMode::Palette, "Command palette"),
+    (InputMode::Switcher, "Session switcher"),
+];
+
+/// The key bindings, by mode.
+pub const BINDINGS: &[Binding] = &[
+    Binding::action(KeyCode::Char('?'), "?", "show or hide this help"),
+    Binding::new(InputMode::Normal, "Ctrl+P", "open the command palette"),
+    Binding::new(InputMode::Normal, "Ctrl+N", "start a new session"),
+    Binding::new(InputMode::Normal, "Ctrl+O", "switch to a saved session"),
+    Binding::action(KeyCode::Char('q'), "q", "quit"),
+    Binding::action(KeyCode::Char('e'), "e", "start editing"),
+    Binding::action(KeyCode::Char('t'), "t", "read the transcript in the pager"),
+    Binding::action(
+        KeyCode::Char('p'),
+        "p",
+        "adjust temperature, top_p and max_tokens",
+    ),
+    Binding::action(
+        KeyCode::Char('r'),
+        "r",
+        "retry the selected answer with another model or parameters",
+    ),
+    Binding::action(
+        KeyCode::Char('d'),
+        "d",
+        "diff a retried answer against the attempt before it",
+    ),
+    Binding::action(
+        KeyCode::Char('i'),
+        "i",
+        "see the model, tokens and timing of the selected answer",
+    ),
+    Binding::action(
+        KeyCode::Char('c'),
+        "c",
+        "see what sending the draft would send",
+    ),
+    Binding::action(
+        KeyCode::Char('m'),
+        "m",
+        "continue an answer cut off at the token limit",
+    ),
+    Binding::action(
+        KeyCode::Char('g'),
+        "g",
+        "render the Mermaid and Graphviz diagrams of an answer",
+    ),
+    Binding::action(KeyCode::Char('+'), "+", "mark the selected message good"),
+    Binding::action(KeyCode::Char('-'), "-", "mark the selected message bad"),
+    Binding::action(
+        KeyCode::Char('*'),
+        "*",
+        "mark the selected message favorite",
+    ),
+    Binding::action(
+        KeyCode::Char('s'),
+        "s",
+        "print the selected message as plain text for selecting",
+    ),
+    Binding::action(
+        KeyCode::Char('>'),
+        ">",
+        "reply to the selected message, quoting it",
+    ),
+    Binding::action(
+        KeyCode::Char('v'),
+        "v",
+        "select lines of the selected message to copy",
+    ),
+    Binding::action(
+        KeyCode::Char('x'),
+        "x",
+        "show or hide the details of the selected error",
+    ),
+    Binding::action(
+        KeyCode::Char('w'),
+        "w",
+        "wrap or cut off the selected message's code",
+    ),
+    Binding::new(
+        InputMode::Normal,
+        "Left/Right",
+        "pan the selected message's code",
+    ),
+    Binding::action(
+        KeyCode::Char('R'),
+        "R",
+        "show the selected answer's markdown source, or render it again",
+    ),
+    Binding::action(
+        KeyCode::Char('u'),
+        "u",
+        "undo the last change to the input, or bring back a cleared chat",
+    ),
+    Binding::new(InputMode::Normal, "Ctrl+L", "clear the conversation"),
+    Binding::new(
+        InputMode::Normal,
+        "Up/Down",
+        "select the previous or next message",
+    ),
+    Binding::new(InputMode::Normal, "PageUp/PageDown", "scroll a page"),
+    Binding::action(
+        KeyCode::Char('n'),
+        "n",
+        "jump to the messages new since the session was last open",
+    ),
+    Binding::action(
+        KeyCode::End,
+        "End",
+        "jump to the latest message and follow new output",
+    ),
+    Binding::new(InputMode::Editing, "Enter", "send the message or command"),
+    Binding::new(InputMode::Editing, "Esc", "stop editing"),
+    Binding::new(
+        InputMode::Editing,
+        "Tab",
+        "accept the highlighted completion",
+    ),
+    Binding::new(
+        InputMode::Editing,
+        "Up/Down",
+        "recall sent prompts, or choose a completion",
+    ),
+    Binding::new(InputMode::Editing, "Left/Right", "scroll the input"),
+    Binding::new(InputMode::Editing, "Ctrl+Z/Ctrl+Y", "undo or redo changes"),
+    Binding::new(InputMode::Editing, "Ctrl+R", "search the sent prompts"),
+    Binding::new(InputMode::Editing, "Ctrl+P", "open the command palette"),
+    Binding::new(
+        InputMode::Editing,
+        "Ctrl+N/Ctrl+O",
+        "start or switch sessions",
+    ),
+    Binding::new(
+        InputMode::Waiting,
+        "Esc",
+        "cancel the request, or every running task",
+    ),
+    Binding::new(
+        InputMode::Waiting,
+        "Ctrl+C",
+        "stop generation and keep the partial answer",
+    ),
+    Binding::new(
+        InputMode::Waiting,
+        "Enter",
+        "stop generation and send the typed follow-up",
+    ),
+    Binding::new(InputMode::Waiting, "1-9", "cancel the task in that place"),
+    Binding::new(InputMode::Waiting, "Up/Down, PageUp/PageDown", "scroll"),
+    Binding::new(InputMode::Waiting, "End", "follow the output again"),
+    Binding::new(InputMode::Pager, "q, Esc", "close the pager"),
+    Binding::new(InputMode::Pager, "j/k, Up/Down", "scroll a line"),
+    Binding::new(
+        InputMode::Pager,
+        "Space/b, PageDown/PageUp",
+        "scroll a page",
+    ),
+    Binding::new(InputMode::Pager, "g/G, Home/End", "go to the top or bottom"),
+    Binding::new(InputMode::Pager, "/", "search"),
+    Binding::new(InputMode::Pager, "n/N", "go to the next or previous match"),
+    Binding::new(InputMode::Pager, "m<c>/'<c>", "set or jump to a mark"),
+    Binding::new(InputMode::Pager, "1-9", "jump to a footnote"),
+    Binding::new(
+        InputMode::Pager,
+        "v, then movement",
+        "select lines, starting at the top",
+    ),
+    Binding::new(InputMode::Pager, "y, Enter", "copy the selected lines"),
+    Binding::new(InputMode::Pager, "v, Esc", "cancel the selection"),
+    Binding::new(
+        InputMode::Compare,
+        "1/h/Left, 2/l/Right",
+        "keep the first or second answer",
+    ),
+    Binding::new(InputMode::Compare, "Esc", "discard both answers"),
+    Binding::new(InputMode::Params, "Up/Down, j/k", "select a parameter"),
+    Binding::new(InputMode::Params, "Left/Right, h/l", "adjust it"),
+    Binding::new(InputMode::Params, "Backspace", "reset it"),
+    Binding::new(InputMode::Params, "Enter", "apply the changes, or retry"),
+    Binding::new(InputMode::Params, "Esc", "discard the changes"),
+    Binding::new(InputMode::Search, "Up/Down, j/k", "choose a result"),
+    Binding::new(InputMode::Search, "Enter", "open its session"),
+    Binding::new(InputMode::Search, "Esc, q", "close the search"),
+    Binding::new(
+        InputMode::Diff,
+        "Up/Down, PageUp/PageDown",
+        "scroll the diff or request",
+    ),
+    Binding::new(InputMode::Diff, "Esc, q", "close the viewer"),
+    Binding::new(InputMode::Details, "Esc, Enter, q", "close the details"),
+    Binding::new(
+        InputMode::Palette,
+        "typing",
+        "filter the actions and commands",
+    ),
+    Binding::new(InputMode::Palette, "Up/Down", "choose one"),
+    Binding::new(
+        InputMode::Palette,
+        "Enter",
+        "run it, or start typing a command that needs arguments",
+    ),
+    Binding::new(InputMode::Palette, "Esc", "close the palette"),
+    Binding::new(
+        InputMode::Switcher,
+        "typing",
+        "filter the sessions by title or date",
+    ),
+    Binding::new(InputMode::Switcher, "Up/Down", "choose one"),
+    Binding::new(InputMode::Switcher, "Enter", "open it"),
+    Binding::new(InputMode::Switcher, "Esc", "close the switcher"),
+];
+
+/// Returns the key bindings of a mode.
+pub fn bindings(mode: InputMode) -> impl Iterator<Item = &'static Binding> {
+    BINDINGS.iter().filter(move |binding| binding.mode == mode)
+}
+
+/// Builds the lines of the help popup: the key bindings of every mode,
+/// then the slash commands, each under a heading.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::ui::keymap::help_lines;
+///
+/// let lines: Vec<String> = help_lines().iter().map(ToString::to_string).collect();
+/// assert_eq!(lines[0], "Normal mode");
+/// assert!(lines.iter().any(|line| line.contains("Ctrl+R") && line.contains("search the sent prompts")));
+/// assert!(lines.iter().any(|line| line.starts_with("  /retry [model]")));
+/// ```
+#[must_use]
+pub fn help_lines() -> Vec<Line<'static>> {
+    let heading = Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED);
+    let key = Style::default()
+        .fg(Color::Blue)
+        .add_modifier(Modifier::BOLD);
+    let mut lines = Vec::new();
+    for &(mode, title) in MODES {
+        let width = bindings(mode)
+            .map(|binding| binding.keys.width())
+            .max()
+            .unwrap_or(0);
+        lines.push(Line::styled(title, heading));
+        for binding in bindings(mode) {
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {:<width$}", binding.keys), key),
+                Span::raw(format!("  {}", binding.action)),
+            ]));
+        }
+        lines.push(Line::default());
+    }
+
+    let usages: Vec<String> = COMMANDS
+        .iter()
+        .map(|command| {
+            format!("/{} {}", command.name, command.args)
+                .trim_end()
+                .to_string()
+        })
+        .collect();
+    let width = usages.iter().map(|usage| usage.width()).max().unwrap_or(0);
+    lines.push(Line::styled("Slash commands", heading));
+    for (usage, command) in usages.into_iter().zip(COMMANDS) {
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {usage:<width$}"), key),
+            Span::raw(format!("  {}", command.summary)),
+        ]));
+    }
+    lines
+}
@@ -0,0 +1,223 @@
+//! Caches the rendered lines of each message.
+//!
+//! Rendering markdown and highlighting code on every frame is expensive, and
+//! scrolling needs to know how many lines each message takes up. The cache
+//! keeps the lines and images of every message at the current width,
+//! re-rendering a message only when it changes and everything when the
+//! width changes. Only the messages that may be in view are handed to the
+//! list each frame.
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    ops::Range,
+};
+
+use ratatui::text::Line;
+
+use super::renderer::Renderer;
+use super::theme::Theme;
+use crate::images::{self, Image};
+use crate::message::Message;
+
+/// The rendered lines of the transcript at one width.
+#[derive(Debug, Default, Clone)]
+pub struct LayoutCache {
+    width: usize,
+    theme: Theme,
+    image_rows: u16,
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    fingerprint: u64,
+    start: usize,
+    lines: Vec<Line<'static>>,
+    images: Vec<Image>,
+}
+
+impl LayoutCache {
+    /// Lays out the messages at `width`, reusing the lines of messages that
+    /// have not changed since the last call. A new width or theme lays out
+    /// every message again.
+    ///
+    /// # Arguments
+    ///
+    /// * `messages` - The messages as they are shown.
+    /// * `width` - The available width in columns.
+    /// * `theme` - The role prefixes and colors.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::Message;
+    /// use chatti::ui::layout::LayoutCache;
+    /// use chatti::ui::theme::Theme;
+    ///
+    /// let messages = vec![
+    ///     Message::new("user", "Hello"),
+    ///     Message::new("assistant", "Hi!\n\nHow can I help?"),
+    /// ];
+    /// let mut layout = LayoutCache::default();
+    /// layout.update(&messages, 80, &Theme::default());
+    /// assert_eq!(layout.range(1), Some(1..5));
+    /// assert_eq!(layout.total_lines(), 5);
+    /// assert_eq!(layout.message_at_line(2), Some(1));
+    /// ```
+    pub fn update(&mut self, messages: &[Message], width: usize, theme: &Theme) {
+        if width != self.width {
+            self.width = width;
+            self.entries.clear();
+        }
+        if *theme != self.theme {
+            self.theme = theme.clone();
+            self.entries.clear();
+        }
+        self.entries.truncate(messages.len());
+
+        let mut start = 0;
+        for (index, message) in messages.iter().enumerate() {
+            let fingerprint = fingerprint(message);
+            match self.entries.get_mut(index) {
+                Some(entry) if entry.fingerprint == fingerprint => entry.start = start,
+                slot => {
+                    let entry = Entry {
+                        fingerprint,
+                        start,
+                        lines: Renderer::message_lines_with(message, width, theme, self.image_rows),
+                        images: images::of(message),
+                    };
+                    match slot {
+                        Some(slot) => *slot = entry,
+                        None => self.entries.push(entry),
+                    }
+                }
+            }
+            start += self.entries[index].lines.len();
+        }
+    }
+
+    /// Sets the blank rows left below each image to draw it in, laying out
+    /// every message again if they change.
+    pub fn set_image_rows(&mut self, rows: u16) {
+        if rows != self.image_rows {
+            self.image_rows = rows;
+            self.entries.clear();
+        }
+    }
+
+    /// Forgets the lines of the first `count` messages, after they were
+    /// removed from the transcript.
+    pub fn discard(&mut self, count: usize) {
+        self.entries.drain(..count.min(self.entries.len()));
+    }
+
+    /// Returns the rendered lines of a message.
+    #[must_use]
+    pub fn lines(&self, index: usize) -> &[Line<'static>] {
+        self.entries
+            .get(index)
+            .map_or(&[], |entry| entry.lines.as_slice())
+    }
+
+    /// Returns the images of a message, found when it was laid out.
+    #[must_use]
+    pub fn images(&self, index: usize) -> &[Image] {
+        self.entries
+            .get(index)
+            .map_or(&[], |entry| entry.images.as_slice())
+    }
+
+    /// Returns the transcript lines a message occupies.
+    #[must_use]
+    pub fn range(&self, index: usize) -> Option<Range<usize>> {
+        self.entries
+            .get(index)
+            .map(|entry| entry.start..entry.start + entry.lines.len())
+    }
+
+    /// Returns the number of lines of the whole transcript.
+    #[must_use]
+    pub fn total_lines(&self) -> usize {
+        self.entries
+            .last()
+            .map_or(0, |entry| entry.start + entry.lines.len())
+    }
+
+    /// Returns the messages that may be in view of a list `height` lines
+    /// tall, scrolled to the message at `offset`. The list scrolls to show
+    /// the selected message, so every message between the two may be shown
+    /// as well. The others need not be rendered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::message::Message;
+    /// use chatti::ui::layout::LayoutCache;
+    /// use chatti::ui::theme::Theme;
+    ///
+    /// let messages: Vec<Message> = (0..10)
+    ///     .map(|i| Message::new("user", format!("Message {i}")))
+    ///     .collect();
+    /// let mut layout = LayoutCache::default();
+    /// layout.update(&messages, 80, &Theme::default());
+    /// assert_eq!(layout.visible(2, None, 3), 2..5);
+    /// assert_eq!(layout.visible(2, Some(7), 3), 2..10);
+    /// assert_eq!(layout.visible(8, Some(0), 3), 0..10);
+    /// ```
+    #[must_use]
+    pub fn visible(&self, offset: usize, selected: Option<usize>, height: usize) -> Range<usize> {
+        let selected = selected.unwrap_or(offset);
+        let mut end = offset.max(selected).min(self.entries.len());
+        let mut lines = 0;
+        while end < self.entries.len() && lines < height {
+            lines += self.entries[end].lines.len();
+            end += 1;
+        }
+        offset.min(selected).min(end)..end
+    }
+
+    /// Returns the message shown on a transcript line, or the last message
+    /// if the line is past the end.
+    #[must_use]
+    pub fn message_at_line(&self, line: usize) -> Option<usize> {
+        let index = self
+            .entries
+            .partition_point(|entry| entry.start + entry.lines.len() <= line);
+        (!self.entries.is_empty()).then(|| index.min(self.entries.len() - 1))
+    }
+}
+
+/// Hashes everything about a message that affects how it is rendered.
+fn fingerprint(message: &Message) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    message.role.hash(&mut hasher);
+    message.content.hash(&mut hasher);
+    message.attachment.hash(&mut hasher);
+    message.images.hash(&mut hasher);
+    message.diagrams.hash(&mut hasher);
+    message.sources.hash(&mut hasher);
+    message
+        .generation
+        .as_ref()
+        .map(crate::message::Generation::describe)
+        .hash(&mut hasher);
+    message.compared.hash(&mut hasher);
+    message.json.hash(&mut hasher);
+    message.translation.hash(&mut hasher);
+    message.stopped.hash(&mut hasher);
+    message.pending.hash(&mut hasher);
+    message.cancelled.hash(&mut hasher);
+    message.marks.hash(&mut hasher);
+    message.expanded.hash(&mut hasher);
+    message.code_scroll.hash(&mut hasher);
+    message.raw.hash(&mut hasher);
+    message.first_unread.hash(&mut hasher);
+    message
+        .error
+        .as_ref()
+        .map(crate::error::ErrorDetails::lines)
+        .hash(&mut hasher);
+    hasher.finish()
+}
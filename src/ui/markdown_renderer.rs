@@ -1,16 +1,64 @@
 //! Renders Markdown content for the chat UI.
 
-use pulldown_cmark::{Event as MarkdownEvent, Options, Parser, Tag, TagEnd};
+use super::{code_language, math};
+use crate::diagrams::Kind;
+use pulldown_cmark::{Event as MarkdownEvent, Options, Parser, Tag, TagEnd, TextMergeStream};
 use ratatui::{
     style::{Color, Modifier, Style},
     text::{Line, Span},
 };
+use regex::Regex;
+use std::sync::LazyLock;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::ThemeSet;
 use syntect::parsing::SyntaxSet;
 use syntect::util::LinesWithEndings;
 use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
+/// Matches `[1]`-style citation markers.
+static CITATION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[\d{1,3}\]").expect("valid citation pattern"));
+
+/// The style of footnote and citation markers.
+const CITATION_STYLE: Style = Style::new().fg(Color::Cyan);
+
+/// The style of footnote definitions and source lists.
+const FOOTNOTE_STYLE: Style = Style::new().fg(Color::DarkGray);
+
+/// The style of horizontal rules.
+const RULE_STYLE: Style = Style::new().fg(Color::DarkGray);
+
+/// The style of the placeholder of an image.
+const IMAGE_STYLE: Style = Style::new().fg(Color::Blue);
+
+/// The style of the checkbox of a done task.
+const TASK_DONE_STYLE: Style = Style::new().fg(Color::Green);
+
+/// The style of the title line above code blocks.
+const CODE_TITLE_STYLE: Style = Style::new().fg(Color::DarkGray);
+
+/// The style of inline and display math.
+const MATH_STYLE: Style = Style::new().fg(Color::Magenta);
+
+/// How code blocks are fitted to the available width.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CodeLayout {
+    /// Long code lines wrap onto the next line.
+    #[default]
+    Wrap,
+    /// Long code lines are cut off, showing the columns from the given
+    /// offset onwards.
+    Scroll(usize),
+}
+
+/// A list being rendered.
+struct List {
+    /// The number of the next item, or `None` for a bulleted list.
+    number: Option<u64>,
+    /// How far the text of the current item is indented.
+    indent: usize,
+}
+
 /// Renders Markdown content for the chat UI.
 pub struct MarkdownRenderer;
 
@@ -37,21 +85,56 @@ impl MarkdownRenderer {
     /// assert!(rendered.len() > 0);
     /// ```
     #[must_use]
-    #[allow(clippy::too_many_lines)]
     pub fn render_markdown(content: &str, width: usize) -> Vec<Line<'static>> {
+        Self::render_markdown_with(content, width, CodeLayout::Wrap)
+    }
+
+    /// Renders Markdown content, laying out code blocks as requested.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - A string slice containing the Markdown content to render.
+    /// * `width` - The maximum width for wrapping text.
+    /// * `code` - Whether long code lines wrap or are cut off and panned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::markdown_renderer::{CodeLayout, MarkdownRenderer};
+    ///
+    /// let markdown = "```\nlet answer = 42;\n```";
+    /// let wrapped = MarkdownRenderer::render_markdown_with(markdown, 8, CodeLayout::Wrap);
+    /// let panned = MarkdownRenderer::render_markdown_with(markdown, 8, CodeLayout::Scroll(4));
+    /// assert_eq!(wrapped.len(), 3);
+    /// assert_eq!(panned.len(), 2);
+    /// ```
+    #[must_use]
+    #[allow(clippy::too_many_lines)]
+    pub fn render_markdown_with(
+        content: &str,
+        width: usize,
+        code: CodeLayout,
+    ) -> Vec<Line<'static>> {
         let mut lines = Vec::new();
-        let mut options = Options::empty();
-        options.insert(Options::ENABLE_STRIKETHROUGH);
-        let parser = Parser::new_ext(content, options);
+        let content = math::normalize_delimiters(content);
+        let events = Self::break_before_citations(TextMergeStream::new(Parser::new_ext(
+            &content,
+            Self::options(),
+        )));
+        let mut blocks = 0;
 
         let mut current_line = Vec::new();
         let mut in_code_block = false;
         let mut code_block_lang = String::new();
         let mut code_block_content = String::new();
-        let mut list_level = 0;
+        let mut lists: Vec<List> = Vec::new();
         let mut current_style = Style::default();
+        let mut in_footnotes = false;
+        let mut in_footnote_definition = false;
+        let mut image: Option<(String, String)> = None;
+        let mut table: Option<Vec<Vec<String>>> = None;
 
-        for event in parser {
+        for event in events {
             match event {
                 MarkdownEvent::Start(Tag::CodeBlock(kind)) => {
                     Self::flush_line(&mut lines, &mut current_line);
@@ -62,54 +145,127 @@ impl MarkdownRenderer {
                 }
                 MarkdownEvent::End(TagEnd::CodeBlock) => {
                     in_code_block = false;
+                    blocks += 1;
+                    lines.push(Self::code_title(
+                        &code_block_lang,
+                        &code_block_content,
+                        width,
+                        blocks,
+                    ));
                     let highlighted = Self::highlight_code(&code_block_content, &code_block_lang);
-                    lines.extend(highlighted);
+                    for line in highlighted {
+                        lines.extend(Self::fit_code_line(line, width, code));
+                    }
                     code_block_content.clear();
                     code_block_lang.clear();
                 }
+                MarkdownEvent::Start(Tag::Table(_)) => {
+                    Self::flush_line(&mut lines, &mut current_line);
+                    table = Some(Vec::new());
+                }
+                MarkdownEvent::End(TagEnd::Table) => {
+                    blocks += 1;
+                    lines.push(Self::block_title(" ── table ", width, blocks));
+                    lines.extend(Self::table_lines(&table.take().unwrap_or_default()));
+                }
+                MarkdownEvent::Start(Tag::TableHead | Tag::TableRow) => {
+                    if let Some(rows) = table.as_mut() {
+                        rows.push(Vec::new());
+                    }
+                }
+                MarkdownEvent::Start(Tag::TableCell) => {
+                    if let Some(row) = table.as_mut().and_then(|rows| rows.last_mut()) {
+                        row.push(String::new());
+                    }
+                }
                 MarkdownEvent::Text(text) => {
-                    if in_code_block {
+                    if let Some((_, alt)) = image.as_mut() {
+                        alt.push_str(&text);
+                    } else if in_code_block {
                         code_block_content.push_str(&text);
+                    } else if let Some(cell) = Self::table_cell(&mut table) {
+                        cell.push_str(&text);
                     } else {
                         Self::add_text_to_line(
                             &mut lines,
                             &mut current_line,
                             &text,
                             width,
-                            list_level,
+                            Self::indent(&lists),
                             current_style,
                         );
                     }
                 }
-                MarkdownEvent::SoftBreak => {
-                    if !in_code_block {
-                        Self::add_text_to_line(
-                            &mut lines,
-                            &mut current_line,
-                            " ",
-                            width,
-                            list_level,
-                            current_style,
-                        );
-                    }
+                MarkdownEvent::SoftBreak if !in_code_block => {
+                    Self::add_text_to_line(
+                        &mut lines,
+                        &mut current_line,
+                        " ",
+                        width,
+                        Self::indent(&lists),
+                        current_style,
+                    );
                 }
-                MarkdownEvent::HardBreak => {
-                    if !in_code_block {
-                        Self::flush_line(&mut lines, &mut current_line);
-                    }
+                MarkdownEvent::HardBreak if !in_code_block => {
+                    Self::flush_line(&mut lines, &mut current_line);
+                }
+                // A heading ends its line, rather than running into the
+                // paragraph after it.
+                MarkdownEvent::Start(Tag::Heading { .. })
+                | MarkdownEvent::End(TagEnd::Heading(_)) => {
+                    Self::flush_line(&mut lines, &mut current_line);
                 }
-                MarkdownEvent::Start(Tag::List(..)) => {
+                MarkdownEvent::Start(Tag::List(start)) => {
                     Self::flush_line(&mut lines, &mut current_line);
-                    list_level += 1;
+                    lists.push(List {
+                        number: start,
+                        indent: Self::indent(&lists),
+                    });
                 }
                 MarkdownEvent::End(TagEnd::List(..)) => {
                     Self::flush_line(&mut lines, &mut current_line);
-                    list_level = list_level.saturating_sub(1);
+                    lists.pop();
                 }
                 MarkdownEvent::Start(Tag::Item) => {
                     Self::flush_line(&mut lines, &mut current_line);
-                    let bullet = if list_level % 2 == 1 { "• " } else { "◦ " };
-                    current_line.push(Span::raw("  ".repeat(list_level - 1) + bullet));
+                    let depth = lists.len();
+                    let outer = Self::indent(&lists[..depth.saturating_sub(1)]);
+                    if let Some(list) = lists.last_mut() {
+                        let marker = match list.number.as_mut() {
+                            Some(number) => {
+                                *number += 1;
+                                format!("{}. ", *number - 1)
+                            }
+                            None if depth % 2 == 1 => String::from("• "),
+                            None => String::from("◦ "),
+                        };
+                        // Wrapped lines of the item line up after its marker.
+                        list.indent = outer + marker.width();
+                        current_line.push(Span::raw(format!("{}{marker}", " ".repeat(outer))));
+                    }
+                }
+                MarkdownEvent::TaskListMarker(checked) => {
+                    let (glyph, style) = if checked {
+                        ("☑ ", TASK_DONE_STYLE)
+                    } else {
+                        ("☐ ", Style::default())
+                    };
+                    let depth = lists.len();
+                    let outer = Self::indent(&lists[..depth.saturating_sub(1)]);
+                    if let Some(list) = lists.last_mut() {
+                        // Replaces the bullet the item started with, and
+                        // follows the number of a numbered item.
+                        if list.number.is_some() {
+                            list.indent += glyph.width();
+                            current_line.push(Span::styled(glyph, style));
+                        } else {
+                            current_line.pop();
+                            if outer > 0 {
+                                current_line.push(Span::raw(" ".repeat(outer)));
+                            }
+                            current_line.push(Span::styled(glyph, style));
+                        }
+                    }
                 }
                 MarkdownEvent::End(TagEnd::Item) => {
                     Self::flush_line(&mut lines, &mut current_line);
@@ -126,6 +282,36 @@ impl MarkdownRenderer {
                 MarkdownEvent::End(TagEnd::Strong) => {
                     current_style = current_style.remove_modifier(Modifier::BOLD);
                 }
+                MarkdownEvent::Rule => {
+                    Self::flush_line(&mut lines, &mut current_line);
+                    lines.push(Line::styled("─".repeat(width), RULE_STYLE));
+                }
+                MarkdownEvent::Start(Tag::Image { dest_url, .. }) => {
+                    image = Some((dest_url.into_string(), String::new()));
+                }
+                MarkdownEvent::End(TagEnd::Image) => {
+                    if let Some((url, alt)) = image.take() {
+                        Self::add_text_to_line(
+                            &mut lines,
+                            &mut current_line,
+                            &format!("[image: {alt}]({url})"),
+                            width,
+                            Self::indent(&lists),
+                            IMAGE_STYLE,
+                        );
+                    }
+                }
+                MarkdownEvent::Start(Tag::Strikethrough) => {
+                    current_style = current_style.add_modifier(Modifier::CROSSED_OUT);
+                }
+                MarkdownEvent::End(TagEnd::Strikethrough) => {
+                    current_style = current_style.remove_modifier(Modifier::CROSSED_OUT);
+                }
+                MarkdownEvent::Code(text) if table.is_some() => {
+                    if let Some(cell) = Self::table_cell(&mut table) {
+                        cell.push_str(&format!("`{text}`"));
+                    }
+                }
                 MarkdownEvent::Code(text) => {
                     let code_style = Style::default()
                         .fg(Color::Yellow)
@@ -136,14 +322,66 @@ impl MarkdownRenderer {
                         &mut current_line,
                         &code_span,
                         width,
-                        list_level,
+                        Self::indent(&lists),
                         code_style,
                     );
                 }
-                MarkdownEvent::Start(Tag::Paragraph) => {
-                    if !lines.is_empty() {
+                MarkdownEvent::InlineMath(tex) if table.is_some() => {
+                    if let Some(cell) = Self::table_cell(&mut table) {
+                        cell.push_str(&math::prettify(&tex));
+                    }
+                }
+                MarkdownEvent::InlineMath(tex) => {
+                    Self::add_text_to_line(
+                        &mut lines,
+                        &mut current_line,
+                        &math::prettify(&tex),
+                        width,
+                        Self::indent(&lists),
+                        current_style.patch(MATH_STYLE),
+                    );
+                }
+                MarkdownEvent::DisplayMath(tex) => {
+                    Self::flush_line(&mut lines, &mut current_line);
+                    for line in math::prettify(&tex).lines() {
+                        lines.push(Line::from(vec![
+                            Span::raw("    "),
+                            Span::styled(line.to_string(), MATH_STYLE),
+                        ]));
+                    }
+                }
+                MarkdownEvent::FootnoteReference(label) => {
+                    Self::add_text_to_line(
+                        &mut lines,
+                        &mut current_line,
+                        &format!("[{label}]"),
+                        width,
+                        Self::indent(&lists),
+                        CITATION_STYLE,
+                    );
+                }
+                MarkdownEvent::Start(Tag::FootnoteDefinition(label)) => {
+                    Self::flush_line(&mut lines, &mut current_line);
+                    if !in_footnotes {
+                        in_footnotes = true;
                         lines.push(Line::default());
+                        lines.push(Line::styled(
+                            format!("── Footnotes {}", "─".repeat(width.saturating_sub(13))),
+                            FOOTNOTE_STYLE,
+                        ));
                     }
+                    current_line.push(Span::styled(format!("[{label}] "), CITATION_STYLE));
+                    current_style = current_style.patch(FOOTNOTE_STYLE);
+                    in_footnote_definition = true;
+                }
+                MarkdownEvent::End(TagEnd::FootnoteDefinition) => {
+                    Self::flush_line(&mut lines, &mut current_line);
+                    current_style = Style::default();
+                    in_footnote_definition = false;
+                }
+                MarkdownEvent::Start(Tag::Paragraph) if in_footnote_definition => {}
+                MarkdownEvent::Start(Tag::Paragraph) if !lines.is_empty() => {
+                    lines.push(Line::default());
                 }
                 MarkdownEvent::End(TagEnd::Paragraph) => {
                     Self::flush_line(&mut lines, &mut current_line);
@@ -155,63 +393,226 @@ impl MarkdownRenderer {
 
         Self::flush_line(&mut lines, &mut current_line);
 
-        while lines.last().map_or(false, |line| line.spans.is_empty()) {
+        while lines.last().is_some_and(|line| line.spans.is_empty()) {
             lines.pop();
         }
 
-        lines
+        lines.into_iter().map(Self::style_citations).collect()
     }
 
+    /// Returns the code of each code block and the markdown of each table,
+    /// in the order the blocks are numbered when rendered.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::markdown_renderer::MarkdownRenderer;
+    ///
+    /// let answer = "Build it:\n\n```sh\ncargo build\n```\n\nThen:\n\n    cargo test\n";
+    /// assert_eq!(
+    ///     MarkdownRenderer::code_blocks(answer),
+    ///     vec!["cargo build\n", "cargo test\n"]
+    /// );
+    ///
+    /// let table = "| a | b |\n|---|---|\n| 1 | 2 |\n";
+    /// assert_eq!(MarkdownRenderer::code_blocks(table), vec![table]);
+    /// ```
+    #[must_use]
+    pub fn code_blocks(content: &str) -> Vec<String> {
+        let content = math::normalize_delimiters(content);
+        let mut blocks = Vec::new();
+        let mut current: Option<String> = None;
+        for (event, range) in Parser::new_ext(&content, Self::options()).into_offset_iter() {
+            match event {
+                MarkdownEvent::Start(Tag::CodeBlock(_)) => current = Some(String::new()),
+                MarkdownEvent::Start(Tag::Table(_)) => {
+                    blocks.push(format!("{}\n", content[range].trim_end()));
+                }
+                MarkdownEvent::Text(text) => {
+                    if let Some(code) = current.as_mut() {
+                        code.push_str(&text);
+                    }
+                }
+                MarkdownEvent::End(TagEnd::CodeBlock) => blocks.extend(current.take()),
+                _ => {}
+            }
+        }
+        blocks
+    }
+
+    /// The markdown extensions answers are rendered with.
+    fn options() -> Options {
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_STRIKETHROUGH);
+        options.insert(Options::ENABLE_FOOTNOTES);
+        options.insert(Options::ENABLE_TASKLISTS);
+        options.insert(Options::ENABLE_MATH);
+        options.insert(Options::ENABLE_TABLES);
+        options
+    }
+
+    /// Turns the soft break before a `[n]` source line into a hard break, so
+    /// that source lists keep one entry per line.
+    fn break_before_citations<'a>(
+        events: impl Iterator<Item = MarkdownEvent<'a>>,
+    ) -> Vec<MarkdownEvent<'a>> {
+        let mut events: Vec<MarkdownEvent<'a>> = events.collect();
+        for i in 1..events.len() {
+            if let (MarkdownEvent::SoftBreak, MarkdownEvent::Text(text)) =
+                (&events[i - 1], &events[i])
+            {
+                if CITATION.find(text).is_some_and(|m| m.start() == 0) {
+                    events[i - 1] = MarkdownEvent::HardBreak;
+                }
+            }
+        }
+        events
+    }
+
+    /// Highlights `[n]` citation markers, and dims lines that start with one
+    /// (source list entries).
+    fn style_citations(line: Line<'static>) -> Line<'static> {
+        let is_source_line = line
+            .spans
+            .iter()
+            .map(|span| span.content.trim_start())
+            .find(|content| !content.is_empty())
+            .is_some_and(|content| CITATION.find(content).is_some_and(|m| m.start() == 0));
+
+        if !is_source_line && !line.spans.iter().any(|s| CITATION.is_match(&s.content)) {
+            return line;
+        }
+
+        let mut spans = Vec::new();
+        for span in line.spans {
+            let base = if is_source_line {
+                span.style.patch(FOOTNOTE_STYLE)
+            } else {
+                span.style
+            };
+            let content = span.content.into_owned();
+            let mut last = 0;
+            for m in CITATION.find_iter(&content) {
+                if m.start() > last {
+                    spans.push(Span::styled(content[last..m.start()].to_string(), base));
+                }
+                spans.push(Span::styled(
+                    m.as_str().to_string(),
+                    base.patch(CITATION_STYLE),
+                ));
+                last = m.end();
+            }
+            if last < content.len() {
+                spans.push(Span::styled(content[last..].to_string(), base));
+            }
+        }
+        Line::from(spans)
+    }
+
+    /// Adds text to the current line exactly as written, wrapping at the
+    /// last space that fits. Text is added as runs of spaces and of other
+    /// characters, so a word split across spans, such as `**un**done` or
+    /// streamed chunks, is never joined with a space or broken apart; only
+    /// a word wider than the line is cut.
     fn add_text_to_line(
         lines: &mut Vec<Line<'static>>,
         current_line: &mut Vec<Span<'static>>,
         text: &str,
         width: usize,
-        list_level: usize,
+        indent: usize,
         style: Style,
     ) {
-        let indent = if list_level > 0 { 2 * list_level } else { 0 };
-        let available_width = width.saturating_sub(indent);
+        let width = width.max(indent + 1);
+        let new_line = |lines: &mut Vec<Line<'static>>, current_line: &mut Vec<Span<'static>>| {
+            Self::flush_line(lines, current_line);
+            if indent > 0 {
+                current_line.push(Span::raw(" ".repeat(indent)));
+            }
+        };
 
-        if current_line.is_empty() && list_level > 0 {
+        if current_line.is_empty() && indent > 0 {
             current_line.push(Span::raw(" ".repeat(indent)));
         }
 
-        let mut remaining_text = text;
-        while !remaining_text.is_empty() {
-            let current_line_width = current_line
-                .iter()
-                .map(|span| span.content.width())
-                .sum::<usize>();
-            let space_left = available_width.saturating_sub(current_line_width);
-
-            if space_left == 0 {
-                Self::flush_line(lines, current_line);
-                if list_level > 0 {
-                    current_line.push(Span::raw(" ".repeat(indent)));
+        for run in Self::runs(text) {
+            let line_width: usize = current_line.iter().map(|span| span.content.width()).sum();
+            if run.trim().is_empty() {
+                // Spaces at a wrap are dropped, and never start a line.
+                if !Self::has_words(current_line) {
+                    continue;
+                }
+                if line_width + run.width() <= width {
+                    current_line.push(Span::styled(run.to_string(), style));
+                } else {
+                    new_line(lines, current_line);
                 }
                 continue;
             }
 
-            let (chunk, rest) = Self::split_at_width(remaining_text, space_left);
-            if !chunk.is_empty() {
-                if !current_line.is_empty() && !current_line.last().unwrap().content.ends_with(' ')
-                {
-                    current_line.push(Span::raw(" "));
+            let mut word = run;
+            while !word.is_empty() {
+                let line_width: usize = current_line.iter().map(|span| span.content.width()).sum();
+                if line_width + word.width() <= width {
+                    current_line.push(Span::styled(word.to_string(), style));
+                    break;
+                }
+
+                // Moves the part of the word already on the line, up to the
+                // last space, to the next line along with the rest of it.
+                let space = current_line
+                    .iter()
+                    .rposition(|span| span.content.trim().is_empty());
+                if let Some(at) = space.filter(|&at| Self::has_words(&current_line[..at])) {
+                    let tail = current_line.split_off(at + 1);
+                    let tail_width: usize = tail.iter().map(|span| span.content.width()).sum();
+                    if indent + tail_width + word.width() <= width {
+                        current_line.truncate(at);
+                        new_line(lines, current_line);
+                        current_line.extend(tail);
+                        continue;
+                    }
+                    current_line.extend(tail);
                 }
-                current_line.push(Span::styled(chunk.trim_end().to_string(), style));
-            }
 
-            remaining_text = rest;
-            if !remaining_text.is_empty() {
-                Self::flush_line(lines, current_line);
-                if list_level > 0 {
-                    current_line.push(Span::raw(" ".repeat(indent)));
+                let (mut chunk, mut rest) =
+                    Self::split_at_width(word, width.saturating_sub(line_width));
+                if chunk.is_empty() && !Self::has_words(current_line) {
+                    let first = word.chars().next().map_or(0, char::len_utf8);
+                    (chunk, rest) = word.split_at(first);
+                }
+                if !chunk.is_empty() {
+                    current_line.push(Span::styled(chunk.to_string(), style));
                 }
+                new_line(lines, current_line);
+                word = rest;
             }
         }
     }
 
+    /// Returns how far the text of the innermost list item is indented.
+    fn indent(lists: &[List]) -> usize {
+        lists.last().map_or(0, |list| list.indent)
+    }
+
+    /// Splits text into runs of whitespace and of other characters.
+    fn runs(text: &str) -> impl Iterator<Item = &str> {
+        let mut rest = text;
+        std::iter::from_fn(move || {
+            let first = rest.chars().next()?;
+            let end = rest
+                .find(|c: char| c.is_whitespace() != first.is_whitespace())
+                .unwrap_or(rest.len());
+            let (run, after) = rest.split_at(end);
+            rest = after;
+            Some(run)
+        })
+    }
+
+    /// Returns `true` if any of the spans has more than whitespace.
+    fn has_words(spans: &[Span<'static>]) -> bool {
+        spans.iter().any(|span| !span.content.trim().is_empty())
+    }
+
     fn split_at_width(text: &str, width: usize) -> (&str, &str) {
         let mut total_width = 0;
         let mut split_index = text.len();
@@ -228,18 +629,191 @@ impl MarkdownRenderer {
         text.split_at(split_index)
     }
 
+    /// Ends the current line, dropping the spaces it ends with and joining
+    /// neighbouring spans of the same style.
     fn flush_line(lines: &mut Vec<Line<'static>>, current_line: &mut Vec<Span<'static>>) {
-        if !current_line.is_empty() {
-            lines.push(Line::from(std::mem::take(current_line)));
+        while current_line
+            .last()
+            .is_some_and(|span| span.content.trim().is_empty())
+        {
+            current_line.pop();
+        }
+        if current_line.is_empty() {
+            return;
+        }
+        let mut spans: Vec<Span<'static>> = Vec::new();
+        for span in current_line.drain(..) {
+            match spans.last_mut() {
+                Some(last) if last.style == span.style => {
+                    last.content = format!("{}{}", last.content, span.content).into();
+                }
+                _ => spans.push(span),
+            }
+        }
+        lines.push(Line::from(spans));
+    }
+
+    /// Wraps a highlighted code line at `width` columns, or cuts out the
+    /// visible columns when scrolling. A cut-off line ends with `›`.
+    fn fit_code_line(line: Line<'static>, width: usize, code: CodeLayout) -> Vec<Line<'static>> {
+        let width = width.max(1);
+        let mut spans = line.spans;
+        if let Some(last) = spans.last_mut() {
+            let trimmed = last.content.trim_end_matches(['\n', '\r']).to_string();
+            last.content = trimmed.into();
+        }
+        let total: usize = spans.iter().map(|span| span.content.width()).sum();
+
+        match code {
+            CodeLayout::Wrap if total > width => Self::wrap_spans(&spans, width)
+                .into_iter()
+                .map(Line::from)
+                .collect(),
+            CodeLayout::Wrap => vec![Line::from(spans)],
+            CodeLayout::Scroll(offset) => {
+                let mut visible = Self::slice_spans(&spans, offset, width);
+                if total > offset + width {
+                    visible = Self::slice_spans(&spans, offset, width - 1);
+                    visible.push(Span::styled("›", Style::default().fg(Color::DarkGray)));
+                }
+                vec![Line::from(visible)]
+            }
+        }
+    }
+
+    /// Splits `spans` into rows of at most `width` columns. A double-width
+    /// character that would cross the edge starts the next row instead.
+    fn wrap_spans(spans: &[Span<'static>], width: usize) -> Vec<Vec<Span<'static>>> {
+        let mut rows = Vec::new();
+        let mut row = Vec::new();
+        let mut column = 0;
+        for span in spans {
+            let mut text = String::new();
+            for c in span.content.chars() {
+                let char_width = c.width().unwrap_or(0);
+                if column > 0 && column + char_width > width {
+                    if !text.is_empty() {
+                        row.push(Span::styled(std::mem::take(&mut text), span.style));
+                    }
+                    rows.push(std::mem::take(&mut row));
+                    column = 0;
+                }
+                text.push(c);
+                column += char_width;
+            }
+            if !text.is_empty() {
+                row.push(Span::styled(text, span.style));
+            }
+        }
+        rows.push(row);
+        rows
+    }
+
+    /// Returns the parts of `spans` covering `width` columns from `start`.
+    fn slice_spans(spans: &[Span<'static>], start: usize, width: usize) -> Vec<Span<'static>> {
+        let end = start + width;
+        let mut column = 0;
+        let mut sliced = Vec::new();
+        for span in spans {
+            let mut text = String::new();
+            for c in span.content.chars() {
+                let char_width = c.width().unwrap_or(0);
+                if column >= start && column + char_width <= end {
+                    text.push(c);
+                }
+                column += char_width;
+            }
+            if !text.is_empty() {
+                sliced.push(Span::styled(text, span.style));
+            }
+        }
+        sliced
+    }
+
+    /// Returns the language of a code block: the one its fence is labeled
+    /// with, or else the guessed one, with whether it was guessed.
+    fn code_language<'a>(lang: &'a str, code: &str) -> Option<(&'a str, bool)> {
+        let label = lang
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .next()
+            .unwrap_or_default();
+        if label.is_empty() {
+            code_language::guess(code).map(|language| (language, true))
+        } else {
+            Some((label, false))
+        }
+    }
+
+    /// Builds the line above a code block: its number, for `/copy` and
+    /// `/save`, and its language.
+    fn code_title(lang: &str, code: &str, width: usize, number: usize) -> Line<'static> {
+        let title = match Self::code_language(lang, code) {
+            Some((language, true)) => format!(" ── {language} (guessed) "),
+            Some((language, false)) if Kind::from_language(language).is_some() => {
+                format!(" ── {language} (g to render) ")
+            }
+            Some((language, false)) => format!(" ── {language} "),
+            None => String::from(" "),
+        };
+        Self::block_title(&title, width, number)
+    }
+
+    /// Builds the line above a code block or table: its number, for `/copy`
+    /// and `/save`, and a title ruled out to `width`.
+    fn block_title(title: &str, width: usize, number: usize) -> Line<'static> {
+        let anchor = Span::styled(format!("[{number}]"), CITATION_STYLE);
+        let rule = "─".repeat(width.saturating_sub(anchor.width() + title.width()));
+        Line::from(vec![
+            anchor,
+            Span::styled(format!("{title}{rule}"), CODE_TITLE_STYLE),
+        ])
+    }
+
+    /// Returns the cell of a table that text is being added to, if any.
+    fn table_cell(table: &mut Option<Vec<Vec<String>>>) -> Option<&mut String> {
+        table.as_mut()?.last_mut()?.last_mut()
+    }
+
+    /// Lays out the rows of a table in aligned columns, with a rule below
+    /// the header row.
+    fn table_lines(rows: &[Vec<String>]) -> Vec<Line<'static>> {
+        let mut widths: Vec<usize> = Vec::new();
+        for row in rows {
+            for (column, cell) in row.iter().enumerate() {
+                match widths.get_mut(column) {
+                    Some(width) => *width = (*width).max(cell.width()),
+                    None => widths.push(cell.width()),
+                }
+            }
         }
+        let line = |row: &[String], style: Style| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(&widths)
+                .map(|(cell, &width)| format!("{cell}{}", " ".repeat(width - cell.width())))
+                .collect();
+            Line::styled(cells.join(" │ ").trim_end().to_string(), style)
+        };
+
+        let mut lines = Vec::new();
+        for (index, row) in rows.iter().enumerate() {
+            if index == 0 {
+                lines.push(line(row, Style::default().add_modifier(Modifier::BOLD)));
+                let rule: Vec<String> = widths.iter().map(|&width| "─".repeat(width)).collect();
+                lines.push(Line::styled(rule.join("─┼─"), RULE_STYLE));
+            } else {
+                lines.push(line(row, Style::default()));
+            }
+        }
+        lines
     }
 
     fn highlight_code(code: &str, lang: &str) -> Vec<Line<'static>> {
         let theme_set = ThemeSet::load_defaults();
         let syntax_set = SyntaxSet::load_defaults_newlines();
 
-        let syntax = syntax_set
-            .find_syntax_by_extension(lang)
+        let syntax = Self::code_language(lang, code)
+            .and_then(|(language, _)| syntax_set.find_syntax_by_token(language))
             .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
 
         let mut h = HighlightLines::new(syntax, &theme_set.themes["base16-ocean.dark"]);
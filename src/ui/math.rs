@@ -0,0 +1,454 @@
+//! Converts TeX math into readable unicode text for the terminal.
+//!
+//! This is a best-effort prettifier, not a TeX engine: it maps common
+//! symbols, super- and subscripts, fractions and roots to unicode, and keeps
+//! anything it does not understand readable as plain text.
+
+use regex::Regex;
+use std::{borrow::Cow, iter::Peekable, str::Chars, sync::LazyLock};
+
+static INLINE_PARENS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\\\(\s*(.+?)\s*\\\)").expect("valid inline math pattern"));
+
+static DISPLAY_BRACKETS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?s)\\\[\s*(.+?)\s*\\\]").expect("valid display math pattern"));
+
+/// Rewrites `\( … \)` and `\[ … \]` delimiters to `$ … $` and `$$ … $$`, so
+/// the markdown parser recognises them as math. Fenced code blocks are left
+/// untouched.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::ui::math::normalize_delimiters;
+///
+/// assert_eq!(normalize_delimiters(r"where \( x^2 \) grows"), "where $x^2$ grows");
+/// ```
+#[must_use]
+pub fn normalize_delimiters(content: &str) -> Cow<'_, str> {
+    if !content.contains("\\(") && !content.contains("\\[") {
+        return Cow::Borrowed(content);
+    }
+
+    let mut result = String::with_capacity(content.len());
+    let mut in_fence = false;
+    let mut prose = String::new();
+
+    for line in content.split_inclusive('\n') {
+        let is_fence = line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~");
+        if in_fence || is_fence {
+            result.push_str(&convert_prose(&std::mem::take(&mut prose)));
+            result.push_str(line);
+            if is_fence {
+                in_fence = !in_fence;
+            }
+        } else {
+            prose.push_str(line);
+        }
+    }
+    result.push_str(&convert_prose(&prose));
+
+    Cow::Owned(result)
+}
+
+fn convert_prose(prose: &str) -> String {
+    let display = DISPLAY_BRACKETS.replace_all(prose, "$$$$$1$$$$");
+    INLINE_PARENS.replace_all(&display, "$$$1$$").into_owned()
+}
+
+/// Converts a TeX math expression into unicode text.
+///
+/// # Arguments
+///
+/// * `tex` - The TeX source, without delimiters.
+///
+/// # Returns
+///
+/// A readable unicode rendition of the expression.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::ui::math::prettify;
+///
+/// assert_eq!(prettify(r"e^{i\pi} + 1 = 0"), "e^(iπ) + 1 = 0");
+/// assert_eq!(prettify(r"x_1 \leq \frac{a}{b}"), "x₁ ≤ a/b");
+/// assert_eq!(prettify(r"\sqrt{x^2 + y^2}"), "√(x² + y²)");
+/// ```
+#[must_use]
+pub fn prettify(tex: &str) -> String {
+    let mut parser = TexParser {
+        chars: tex.chars().peekable(),
+    };
+    let output = parser.parse_sequence(false);
+
+    output
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+struct TexParser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl TexParser<'_> {
+    /// Parses atoms until the end of input, or a closing brace if `in_group`.
+    fn parse_sequence(&mut self, in_group: bool) -> String {
+        let mut output = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c == '}' {
+                self.chars.next();
+                if in_group {
+                    break;
+                }
+                continue;
+            }
+            output.push_str(&self.parse_atom());
+        }
+        output
+    }
+
+    /// Parses a single atom: a group, a command, a script or a character.
+    fn parse_atom(&mut self) -> String {
+        let Some(c) = self.chars.next() else {
+            return String::new();
+        };
+
+        match c {
+            '{' => self.parse_sequence(true),
+            '\\' => self.parse_command(),
+            '^' => {
+                let arg = self.parse_argument();
+                script(&arg, superscript, '^')
+            }
+            '_' => {
+                let arg = self.parse_argument();
+                script(&arg, subscript, '_')
+            }
+            '~' | '&' => String::from(" "),
+            '\'' => String::from("′"),
+            c => c.to_string(),
+        }
+    }
+
+    /// Parses the argument of a command or script, skipping leading spaces.
+    fn parse_argument(&mut self) -> String {
+        while self.chars.peek().is_some_and(|c| c.is_whitespace()) {
+            self.chars.next();
+        }
+        self.parse_atom()
+    }
+
+    /// Parses an optional `[...]` argument.
+    fn parse_optional(&mut self) -> Option<String> {
+        if self.chars.peek() != Some(&'[') {
+            return None;
+        }
+        self.chars.next();
+        let mut inner = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c == ']' {
+                self.chars.next();
+                break;
+            }
+            inner.push_str(&self.parse_atom());
+        }
+        Some(inner)
+    }
+
+    fn parse_command(&mut self) -> String {
+        let mut name = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_ascii_alphabetic() {
+                name.push(c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+
+        if name.is_empty() {
+            return match self.chars.next() {
+                Some('\\') => String::from("\n"),
+                Some(',' | ';' | ':' | ' ') => String::from(" "),
+                Some('!') | None => String::new(),
+                Some(c) => c.to_string(),
+            };
+        }
+
+        if let Some(symbol) = symbol(&name) {
+            return symbol.to_string();
+        }
+
+        match name.as_str() {
+            "frac" | "dfrac" | "tfrac" => {
+                let numerator = self.parse_argument();
+                let denominator = self.parse_argument();
+                format!("{}/{}", group(&numerator), group(&denominator))
+            }
+            "sqrt" => {
+                let index = self.parse_optional();
+                let radicand = self.parse_argument();
+                let root = match index.as_deref() {
+                    Some("3") => String::from("∛"),
+                    Some("4") => String::from("∜"),
+                    Some(n) => format!("{}√", script(n, superscript, '^')),
+                    None => String::from("√"),
+                };
+                format!("{root}{}", group(&radicand))
+            }
+            "mathbb" => self.parse_argument().chars().map(double_struck).collect(),
+            "hat" | "widehat" => combine(&self.parse_argument(), '\u{302}'),
+            "bar" | "overline" => combine(&self.parse_argument(), '\u{304}'),
+            "vec" => combine(&self.parse_argument(), '\u{20d7}'),
+            "dot" => combine(&self.parse_argument(), '\u{307}'),
+            "tilde" => combine(&self.parse_argument(), '\u{303}'),
+            "text" | "textrm" | "textbf" | "textit" | "mathrm" | "mathbf" | "mathit"
+            | "mathcal" | "mathsf" | "operatorname" | "boldsymbol" => self.parse_argument(),
+            "left" | "right" | "big" | "Big" | "bigg" | "Bigg" => {
+                if self.chars.peek() == Some(&'.') {
+                    self.chars.next();
+                }
+                String::new()
+            }
+            "quad" | "qquad" => String::from("  "),
+            "begin" | "end" => {
+                self.parse_argument();
+                String::from("\n")
+            }
+            _ => name,
+        }
+    }
+}
+
+/// Wraps compound expressions in parentheses so fractions and roots stay
+/// unambiguous.
+fn group(expr: &str) -> String {
+    let expr = expr.trim();
+    let is_simple = expr.chars().count() == 1
+        || expr.chars().all(|c| c.is_alphanumeric() || c == '.')
+        || (expr.starts_with('(') && expr.ends_with(')'));
+    if is_simple {
+        expr.to_string()
+    } else {
+        format!("({expr})")
+    }
+}
+
+/// Renders a super- or subscript, falling back to `^(...)` notation when a
+/// character has no unicode equivalent.
+fn script(arg: &str, map: fn(char) -> Option<char>, marker: char) -> String {
+    let arg = arg.trim();
+    match arg.chars().map(map).collect::<Option<String>>() {
+        Some(mapped) => mapped,
+        None if arg.chars().count() == 1 => format!("{marker}{arg}"),
+        None => format!("{marker}({arg})"),
+    }
+}
+
+fn combine(base: &str, mark: char) -> String {
+    let mut result = base.to_string();
+    result.push(mark);
+    result
+}
+
+fn symbol(name: &str) -> Option<&'static str> {
+    let symbol = match name {
+        "alpha" => "α",
+        "beta" => "β",
+        "gamma" => "γ",
+        "delta" => "δ",
+        "epsilon" | "varepsilon" => "ε",
+        "zeta" => "ζ",
+        "eta" => "η",
+        "theta" | "vartheta" => "θ",
+        "iota" => "ι",
+        "kappa" => "κ",
+        "lambda" => "λ",
+        "mu" => "μ",
+        "nu" => "ν",
+        "xi" => "ξ",
+        "pi" => "π",
+        "rho" | "varrho" => "ρ",
+        "sigma" => "σ",
+        "tau" => "τ",
+        "upsilon" => "υ",
+        "phi" | "varphi" => "φ",
+        "chi" => "χ",
+        "psi" => "ψ",
+        "omega" => "ω",
+        "Gamma" => "Γ",
+        "Delta" => "Δ",
+        "Theta" => "Θ",
+        "Lambda" => "Λ",
+        "Xi" => "Ξ",
+        "Pi" => "Π",
+        "Sigma" => "Σ",
+        "Phi" => "Φ",
+        "Psi" => "Ψ",
+        "Omega" => "Ω",
+        "times" => "×",
+        "cdot" => "·",
+        "div" => "÷",
+        "pm" => "±",
+        "mp" => "∓",
+        "leq" | "le" => "≤",
+        "geq" | "ge" => "≥",
+        "neq" | "ne" => "≠",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "propto" => "∝",
+        "infty" => "∞",
+        "sum" => "∑",
+        "prod" => "∏",
+        "int" => "∫",
+        "iint" => "∬",
+        "oint" => "∮",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "forall" => "∀",
+        "exists" => "∃",
+        "in" => "∈",
+        "notin" => "∉",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "supset" => "⊃",
+        "cup" => "∪",
+        "cap" => "∩",
+        "emptyset" | "varnothing" => "∅",
+        "to" | "rightarrow" => "→",
+        "leftarrow" | "gets" => "←",
+        "Rightarrow" | "implies" => "⇒",
+        "Leftarrow" => "⇐",
+        "Leftrightarrow" | "iff" => "⇔",
+        "mapsto" => "↦",
+        "land" | "wedge" => "∧",
+        "lor" | "vee" => "∨",
+        "neg" | "lnot" => "¬",
+        "ldots" | "dots" => "…",
+        "cdots" => "⋯",
+        "circ" => "∘",
+        "degree" => "°",
+        "angle" => "∠",
+        "perp" => "⊥",
+        "parallel" => "∥",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "hbar" => "ℏ",
+        "ell" => "ℓ",
+        "log" | "ln" | "exp" | "sin" | "cos" | "tan" | "lim" | "max" | "min" | "det" => {
+            return Some(match name {
+                "log" => "log ",
+                "ln" => "ln ",
+                "exp" => "exp ",
+                "sin" => "sin ",
+                "cos" => "cos ",
+                "tan" => "tan ",
+                "lim" => "lim",
+                "max" => "max",
+                "min" => "min",
+                _ => "det ",
+            })
+        }
+        _ => return None,
+    };
+    Some(symbol)
+}
+
+fn superscript(c: char) -> Option<char> {
+    Some(match c {
+        '0' => '⁰',
+        '1' => '¹',
+        '2' => '²',
+        '3' => '³',
+        '4' => '⁴',
+        '5' => '⁵',
+        '6' => '⁶',
+        '7' => '⁷',
+        '8' => '⁸',
+        '9' => '⁹',
+        '+' => '⁺',
+        '-' | '−' => '⁻',
+        '=' => '⁼',
+        '(' => '⁽',
+        ')' => '⁾',
+        'a' => 'ᵃ',
+        'b' => 'ᵇ',
+        'c' => 'ᶜ',
+        'd' => 'ᵈ',
+        'e' => 'ᵉ',
+        'i' => 'ⁱ',
+        'k' => 'ᵏ',
+        'm' => 'ᵐ',
+        'n' => 'ⁿ',
+        'o' => 'ᵒ',
+        'p' => 'ᵖ',
+        't' => 'ᵗ',
+        'x' => 'ˣ',
+        'y' => 'ʸ',
+        'T' => 'ᵀ',
+        '′' => '′',
+        ' ' => ' ',
+        _ => return None,
+    })
+}
+
+fn subscript(c: char) -> Option<char> {
+    Some(match c {
+        '0' => '₀',
+        '1' => '₁',
+        '2' => '₂',
+        '3' => '₃',
+        '4' => '₄',
+        '5' => '₅',
+        '6' => '₆',
+        '7' => '₇',
+        '8' => '₈',
+        '9' => '₉',
+        '+' => '₊',
+        '-' | '−' => '₋',
+        '=' => '₌',
+        '(' => '₍',
+        ')' => '₎',
+        'a' => 'ₐ',
+        'e' => 'ₑ',
+        'h' => 'ₕ',
+        'i' => 'ᵢ',
+        'j' => 'ⱼ',
+        'k' => 'ₖ',
+        'l' => 'ₗ',
+        'm' => 'ₘ',
+        'n' => 'ₙ',
+        'o' => 'ₒ',
+        'p' => 'ₚ',
+        'r' => 'ᵣ',
+        's' => 'ₛ',
+        't' => 'ₜ',
+        'u' => 'ᵤ',
+        'v' => 'ᵥ',
+        'x' => 'ₓ',
+        ' ' => ' ',
+        _ => return None,
+    })
+}
+
+fn double_struck(c: char) -> char {
+    match c {
+        'R' => 'ℝ',
+        'N' => 'ℕ',
+        'Z' => 'ℤ',
+        'Q' => 'ℚ',
+        'C' => 'ℂ',
+        'P' => 'ℙ',
+        'H' => 'ℍ',
+        c => c,
+    }
+}
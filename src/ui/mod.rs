@@ -13,12 +13,32 @@
 // pub use chat_ui::ChatUI;
 // pub use ui_state::Action;
 
+pub mod capabilities;
 pub mod chat;
+pub mod code_language;
+pub mod compare;
+pub mod completion;
+pub mod context_view;
+pub mod dialog;
+pub mod diff;
+pub mod graphics;
 pub mod input_handler;
+pub mod keymap;
+pub mod layout;
 pub mod markdown_renderer;
+pub mod math;
+pub mod pager;
+pub mod palette;
+pub mod params;
+pub mod picker;
 pub mod renderer;
+pub mod scrollback;
 pub mod spinner;
 pub mod state;
+pub mod switcher;
+pub mod tasks;
+pub mod theme;
+pub mod undo;
 
 pub use chat::Interface;
 pub use state::Action;
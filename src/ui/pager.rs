@@ -0,0 +1,387 @@
+//! Provides a read-only, `less`-like pager over the rendered transcript.
+
+use crate::message::{MarkFilter, Message};
+use ratatui::text::Line;
+use std::collections::HashMap;
+use std::ops::{Range, RangeInclusive};
+
+/// A read-only pager over the fully rendered transcript.
+///
+/// The pager works on rendered lines rather than messages, so long answers
+/// can be read line-by-line independently of the message selection list.
+#[derive(Default, Debug)]
+pub struct Pager {
+    /// The messages paged out of the transcript, loaded while the pager is
+    /// open so they can be read and searched.
+    pub archived: Vec<Message>,
+    /// Which exchanges are shown, by their marks, or `None` for all.
+    pub filter: Option<MarkFilter>,
+    /// The rendered transcript lines, refreshed by the renderer.
+    pub lines: Vec<Line<'static>>,
+    /// Index of the first visible line.
+    pub offset: usize,
+    /// Number of lines visible in the pager viewport.
+    pub height: usize,
+    /// The active search query, if any.
+    pub search: Option<String>,
+    /// The search query being typed, if the search prompt is open.
+    pub search_input: Option<String>,
+    /// Line index of the current search match.
+    pub current_match: Option<usize>,
+    /// Named marks mapping a character to a line index.
+    pub marks: HashMap<char, usize>,
+    /// A pending mark command waiting for its mark character.
+    pub pending_mark: Option<MarkCommand>,
+    /// The lines being selected to copy, if any.
+    pub selection: Option<Selection>,
+    /// The message whose lines to select once the pager is rendered, by
+    /// its index in the transcript.
+    pub select_message: Option<usize>,
+    /// Selected text waiting to be copied to the clipboard.
+    pub copied: Option<String>,
+    /// What happened to the last copy, shown in the title.
+    pub notice: Option<String>,
+}
+
+/// A range of lines selected in the pager, from where the selection
+/// started to the line moved to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Selection {
+    /// The line the selection started on.
+    pub anchor: usize,
+    /// The line the selection extends to.
+    pub cursor: usize,
+}
+
+impl Selection {
+    /// Returns the selected lines, in order.
+    #[must_use]
+    pub fn lines(&self) -> RangeInclusive<usize> {
+        self.anchor.min(self.cursor)..=self.anchor.max(self.cursor)
+    }
+}
+
+/// A mark command waiting for the mark name.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MarkCommand {
+    /// Set a mark at the current position (`m`).
+    Set,
+    /// Jump to a previously set mark (`'`).
+    Jump,
+}
+
+impl Pager {
+    /// Creates a new, empty `Pager`.
+    #[must_use]
+    pub fn new() -> Self {
+        Pager::default()
+    }
+
+    /// Replaces the pager content, keeping the offset within bounds.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - The rendered transcript lines.
+    /// * `height` - The number of lines visible in the viewport.
+    pub fn set_content(&mut self, lines: Vec<Line<'static>>, height: usize) {
+        self.lines = lines;
+        self.height = height;
+        self.offset = self.offset.min(self.max_offset());
+        let count = self.lines.len();
+        self.selection = self
+            .selection
+            .filter(|selection| selection.anchor.max(selection.cursor) < count);
+    }
+
+    /// Returns the largest valid offset for the current content.
+    #[must_use]
+    pub fn max_offset(&self) -> usize {
+        self.lines.len().saturating_sub(self.height.max(1))
+    }
+
+    /// Scrolls down by `n` lines.
+    pub fn scroll_down(&mut self, n: usize) {
+        self.offset = (self.offset + n).min(self.max_offset());
+    }
+
+    /// Scrolls up by `n` lines.
+    pub fn scroll_up(&mut self, n: usize) {
+        self.offset = self.offset.saturating_sub(n);
+    }
+
+    /// Scrolls down by one page.
+    pub fn page_down(&mut self) {
+        self.scroll_down(self.height.max(1));
+    }
+
+    /// Scrolls up by one page.
+    pub fn page_up(&mut self) {
+        self.scroll_up(self.height.max(1));
+    }
+
+    /// Jumps to the first line.
+    pub fn top(&mut self) {
+        self.offset = 0;
+    }
+
+    /// Jumps to the last page.
+    pub fn bottom(&mut self) {
+        self.offset = self.max_offset();
+    }
+
+    /// Returns how far through the transcript the viewport is, in percent.
+    ///
+    /// # Returns
+    ///
+    /// A value between 0 and 100, based on the last visible line.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::pager::Pager;
+    /// use ratatui::text::Line;
+    ///
+    /// let mut pager = Pager::new();
+    /// pager.set_content(vec![Line::from("a"); 10], 5);
+    /// assert_eq!(pager.percentage(), 50);
+    /// pager.bottom();
+    /// assert_eq!(pager.percentage(), 100);
+    /// ```
+    #[must_use]
+    pub fn percentage(&self) -> usize {
+        if self.lines.is_empty() {
+            return 100;
+        }
+        let last_visible = (self.offset + self.height).min(self.lines.len());
+        last_visible * 100 / self.lines.len()
+    }
+
+    /// Sets the search query and jumps to the first match at or after the
+    /// current offset.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - The text to search for (case-insensitive).
+    pub fn search(&mut self, query: &str) {
+        if query.is_empty() {
+            self.search = None;
+            self.current_match = None;
+            return;
+        }
+        self.search = Some(query.to_lowercase());
+        self.current_match = None;
+        self.find_from(self.offset, true);
+    }
+
+    /// Jumps to the next search match.
+    pub fn next_match(&mut self) {
+        let start = self.current_match.map_or(self.offset, |m| m + 1);
+        self.find_from(start, true);
+    }
+
+    /// Jumps to the previous search match.
+    pub fn previous_match(&mut self) {
+        let start = self
+            .current_match
+            .unwrap_or(self.offset)
+            .checked_sub(1)
+            .unwrap_or(self.lines.len().saturating_sub(1));
+        self.find_from(start, false);
+    }
+
+    /// Returns `true` if the given line matches the active search.
+    #[must_use]
+    pub fn is_match(&self, index: usize) -> bool {
+        match (&self.search, self.lines.get(index)) {
+            (Some(query), Some(line)) => line_text(line).to_lowercase().contains(query),
+            _ => false,
+        }
+    }
+
+    /// Sets a mark at the current offset.
+    pub fn set_mark(&mut self, name: char) {
+        self.marks.insert(name, self.offset);
+    }
+
+    /// Jumps to a previously set mark, if it exists.
+    ///
+    /// The position before the jump is remembered as the `'` mark, so `''`
+    /// returns to it.
+    pub fn jump_to_mark(&mut self, name: char) {
+        if let Some(&line) = self.marks.get(&name) {
+            self.marks.insert('\'', self.offset);
+            self.offset = line.min(self.max_offset());
+        }
+    }
+
+    /// Jumps to the definition of footnote or citation `label`, i.e. the next
+    /// line starting with `[label]`, ignoring a leading role prefix span.
+    ///
+    /// The position before the jump is remembered as the `'` mark.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - The footnote label, such as `1`.
+    pub fn jump_to_footnote(&mut self, label: &str) {
+        let marker = format!("[{label}]");
+        let len = self.lines.len();
+        let found = (1..=len).map(|i| (self.offset + i) % len).find(|&i| {
+            let line = &self.lines[i];
+            let without_prefix: String = line
+                .spans
+                .iter()
+                .skip(1)
+                .map(|span| span.content.as_ref())
+                .collect();
+            // The numbers of code blocks and tables look alike, but are followed
+            // by a rule.
+            let defines = |text: &str| {
+                text.trim_start()
+                    .strip_prefix(&marker)
+                    .is_some_and(|rest| !rest.starts_with(" ─"))
+            };
+            defines(&line_text(line)) || (line.spans.len() > 1 && defines(&without_prefix))
+        });
+
+        if let Some(index) = found {
+            self.marks.insert('\'', self.offset);
+            self.offset = index.min(self.max_offset());
+        }
+    }
+
+    /// Starts selecting lines at the first visible line.
+    pub fn start_selection(&mut self) {
+        self.selection = Some(Selection {
+            anchor: self.offset,
+            cursor: self.offset,
+        });
+    }
+
+    /// Selects a range of lines and scrolls to the first of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - The lines to select, such as those of one message.
+    pub fn select_lines(&mut self, lines: Range<usize>) {
+        if lines.is_empty() || lines.end > self.lines.len() {
+            return;
+        }
+        self.selection = Some(Selection {
+            anchor: lines.start,
+            cursor: lines.end - 1,
+        });
+        self.offset = lines.start.min(self.max_offset());
+    }
+
+    /// Moves the end of the selection by `delta` lines, scrolling to keep
+    /// it in view.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::pager::Pager;
+    /// use ratatui::text::Line;
+    ///
+    /// let mut pager = Pager::new();
+    /// pager.set_content(vec![Line::from("a"); 10], 4);
+    /// pager.start_selection();
+    /// pager.move_selection(5);
+    /// assert_eq!(pager.selection.unwrap().lines(), 0..=5);
+    /// assert_eq!(pager.offset, 2);
+    /// ```
+    pub fn move_selection(&mut self, delta: isize) {
+        let Some(selection) = self.selection.as_mut() else {
+            return;
+        };
+        let last = self.lines.len().saturating_sub(1);
+        selection.cursor = selection.cursor.saturating_add_signed(delta).min(last);
+        let cursor = selection.cursor;
+        let height = self.height.max(1);
+        if cursor < self.offset {
+            self.offset = cursor;
+        } else if cursor >= self.offset + height {
+            self.offset = (cursor + 1 - height).min(self.max_offset());
+        }
+    }
+
+    /// Returns `true` if the given line is selected.
+    #[must_use]
+    pub fn is_selected(&self, index: usize) -> bool {
+        self.selection
+            .is_some_and(|selection| selection.lines().contains(&index))
+    }
+
+    /// Returns the text of the selected lines, without the role prefix or
+    /// its indentation, or `None` if nothing is selected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::pager::Pager;
+    /// use ratatui::text::{Line, Span};
+    ///
+    /// let mut pager = Pager::new();
+    /// let line = |lead: &str, text: &str| Line::from(vec![Span::raw(lead.to_string()), Span::raw(text.to_string())]);
+    /// pager.set_content(
+    ///     vec![line("Bot: ", "First"), line("     ", "second"), line("     ", "third")],
+    ///     3,
+    /// );
+    /// pager.start_selection();
+    /// pager.move_selection(1);
+    /// assert_eq!(pager.selected_text().unwrap(), "First\nsecond");
+    /// ```
+    #[must_use]
+    pub fn selected_text(&self) -> Option<String> {
+        let selection = self.selection?;
+        let lines: Vec<String> = self.lines[selection.lines()]
+            .iter()
+            .map(|line| {
+                let text: String = line
+                    .spans
+                    .iter()
+                    .skip(usize::from(line.spans.len() > 1))
+                    .map(|span| span.content.as_ref())
+                    .collect();
+                text.trim_end().to_string()
+            })
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    /// Ends the selection, leaving its text to be copied.
+    pub fn copy_selection(&mut self) {
+        self.copied = self.selected_text();
+        self.selection = None;
+    }
+
+    fn find_from(&mut self, start: usize, forward: bool) {
+        let len = self.lines.len();
+        if len == 0 {
+            return;
+        }
+        let start = start % len;
+        let found = (0..len)
+            .map(|i| {
+                if forward {
+                    (start + i) % len
+                } else {
+                    (start + len - i) % len
+                }
+            })
+            .find(|&i| self.is_match(i));
+
+        if let Some(index) = found {
+            self.current_match = Some(index);
+            self.offset = index.min(self.max_offset());
+        }
+    }
+}
+
+/// Returns the plain text content of a rendered line.
+#[must_use]
+pub fn line_text(line: &Line) -> String {
+    line.spans
+        .iter()
+        .map(|span| span.content.as_ref())
+        .collect()
+}
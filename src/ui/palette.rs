@@ -0,0 +1,100 @@
+//! Finds and runs actions and slash commands by name.
+//!
+//! `Ctrl+P` opens the palette over the transcript. It lists every action of
+//! normal mode that a key runs, from the key bindings, and every slash
+//! command. Typing filters them fuzzily by what they do and their keys or
+//! name, `Up`/`Down` choose one and `Enter` runs it.
+
+use crossterm::event::KeyCode;
+
+use super::completion::fuzzy_score;
+use super::keymap::BINDINGS;
+use super::picker::{Item, Picker};
+use crate::commands::{CommandInfo, COMMANDS};
+
+/// What choosing an entry of the palette does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    /// Presses a key in normal mode.
+    Key(KeyCode),
+    /// Runs a slash command, or starts typing it if it needs arguments.
+    Command(&'static CommandInfo),
+}
+
+/// An action or command listed in the palette.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// What it does, or the command's usage.
+    pub label: String,
+    /// Its key, or what the command does.
+    pub detail: String,
+    /// What choosing it does.
+    pub target: Target,
+}
+
+impl Entry {
+    /// Returns every action a key runs in normal mode, then every slash
+    /// command.
+    #[must_use]
+    pub fn all() -> Vec<Self> {
+        let actions = BINDINGS.iter().filter_map(|binding| {
+            Some(Entry {
+                label: binding.action.to_string(),
+                detail: binding.keys.to_string(),
+                target: Target::Key(binding.key?),
+            })
+        });
+        let commands = COMMANDS.iter().map(|command| Entry {
+            label: format!("/{} {}", command.name, command.args)
+                .trim_end()
+                .to_string(),
+            detail: command.summary.to_string(),
+            target: Target::Command(command),
+        });
+        actions.chain(commands).collect()
+    }
+}
+
+impl Item for Entry {
+    /// Scores the entry by what it does, or half as well by its key or
+    /// what the command does.
+    fn score(&self, query: &str) -> Option<usize> {
+        let label = fuzzy_score(query, &self.label);
+        let detail = fuzzy_score(query, &self.detail).map(|score| score / 2);
+        label.max(detail)
+    }
+
+    fn label(&self) -> &str {
+        &self.label
+    }
+}
+
+/// The command palette: the query and the entries matching it.
+pub type Palette = Picker<Entry>;
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Palette {
+    /// Opens the palette with every entry listed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::palette::Palette;
+    ///
+    /// let mut palette = Palette::new();
+    /// for c in "transcr".chars() {
+    ///     palette.push(c);
+    /// }
+    /// assert_eq!(palette.matches[0].label, "read the transcript in the pager");
+    /// assert_eq!(palette.matches[0].detail, "t");
+    /// ```
+    #[must_use]
+    pub fn new() -> Self {
+        Picker::with_items(Entry::all())
+    }
+}
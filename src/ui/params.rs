@@ -0,0 +1,300 @@
+//! A popup for adjusting the sampling parameters of later requests.
+//!
+//! The popup is opened with `p` in normal mode. `Up`/`Down` select a
+//! parameter, `Left`/`Right` step its value, `Backspace` resets an optional
+//! parameter to the server's default, `Enter` applies the changes and `Esc`
+//! discards them.
+//!
+//! With `r` on a selected answer, the same popup retries that answer with
+//! another model or other parameters instead.
+
+use crate::config::Config;
+use crate::message::Generation;
+
+/// The largest temperature the popup allows.
+pub const MAX_TEMPERATURE: f32 = 2.0;
+
+/// How much `Left`/`Right` change the temperature.
+pub const TEMPERATURE_STEP: f32 = 0.1;
+
+/// How much `Left`/`Right` change `top_p`.
+pub const TOP_P_STEP: f32 = 0.05;
+
+/// How much `Left`/`Right` change the response token limit.
+pub const MAX_TOKENS_STEP: u32 = 256;
+
+/// The sampling parameters sent with each request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Params {
+    /// The temperature.
+    pub temperature: f32,
+    /// The nucleus sampling threshold, or `None` for the server's default.
+    pub top_p: Option<f32>,
+    /// The response token limit, or `None` for no limit.
+    pub max_tokens: Option<u32>,
+}
+
+impl Default for Params {
+    fn default() -> Self {
+        Params {
+            temperature: 0.7,
+            top_p: None,
+            max_tokens: None,
+        }
+    }
+}
+
+impl Params {
+    /// Returns the parameters set in the configuration.
+    #[must_use]
+    pub fn from_config(config: &Config) -> Self {
+        Params {
+            temperature: config.temperature,
+            top_p: config.top_p,
+            max_tokens: config.max_tokens,
+        }
+    }
+
+    /// Returns the parameters that produced an answer.
+    #[must_use]
+    pub fn from_generation(generation: &Generation) -> Self {
+        Params {
+            temperature: generation.temperature,
+            top_p: generation.top_p,
+            max_tokens: generation.max_tokens,
+        }
+    }
+
+    /// Sets the parameters in the configuration used for requests.
+    pub fn apply(&self, config: &mut Config) {
+        config.temperature = self.temperature;
+        config.top_p = self.top_p;
+        config.max_tokens = self.max_tokens;
+    }
+
+    /// Describes the parameters in one line, as shown after applying them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::params::Params;
+    ///
+    /// let params = Params {
+    ///     temperature: 0.9,
+    ///     top_p: None,
+    ///     max_tokens: Some(512),
+    /// };
+    /// assert_eq!(
+    ///     params.describe(),
+    ///     "temperature 0.90, top_p default, max_tokens 512"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn describe(&self) -> String {
+        Param::ALL
+            .iter()
+            .map(|param| format!("{} {}", param.key(), self.value(*param)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Formats the value of a parameter.
+    #[must_use]
+    pub fn value(&self, param: Param) -> String {
+        match param {
+            Param::Temperature => format!("{:.2}", self.temperature),
+            Param::TopP => self
+                .top_p
+                .map_or_else(|| String::from("default"), |top_p| format!("{top_p:.2}")),
+            Param::MaxTokens => self
+                .max_tokens
+                .map_or_else(|| String::from("unlimited"), |max| max.to_string()),
+        }
+    }
+
+    /// Returns where the value of a parameter lies in its range, for the
+    /// slider, or `None` if it has no range.
+    #[must_use]
+    pub fn fraction(&self, param: Param) -> Option<f32> {
+        match param {
+            Param::Temperature => Some(self.temperature / MAX_TEMPERATURE),
+            Param::TopP => Some(self.top_p.unwrap_or(1.0)),
+            Param::MaxTokens => None,
+        }
+    }
+
+    /// Steps a parameter up or down.
+    ///
+    /// An unset `top_p` starts from 1.0. The token limit is removed when it
+    /// is stepped below one step, and set to one step when it is stepped up
+    /// from unlimited.
+    ///
+    /// # Arguments
+    ///
+    /// * `param` - The parameter to change.
+    /// * `up` - Whether to increase the value.
+    pub fn step(&mut self, param: Param, up: bool) {
+        let sign = if up { 1.0 } else { -1.0 };
+        match param {
+            Param::Temperature => {
+                self.temperature =
+                    round(self.temperature + sign * TEMPERATURE_STEP).clamp(0.0, MAX_TEMPERATURE);
+            }
+            Param::TopP => {
+                let top_p = self.top_p.unwrap_or(1.0);
+                self.top_p = Some(round(top_p + sign * TOP_P_STEP).clamp(TOP_P_STEP, 1.0));
+            }
+            Param::MaxTokens => {
+                self.max_tokens = match (self.max_tokens, up) {
+                    (None, true) => Some(MAX_TOKENS_STEP),
+                    (None, false) => None,
+                    (Some(max), true) => Some(max.saturating_add(MAX_TOKENS_STEP)),
+                    (Some(max), false) => {
+                        Some(max.saturating_sub(MAX_TOKENS_STEP)).filter(|max| *max > 0)
+                    }
+                };
+            }
+        }
+    }
+
+    /// Resets an optional parameter to the server's default.
+    pub fn reset(&mut self, param: Param) {
+        match param {
+            Param::Temperature => {}
+            Param::TopP => self.top_p = None,
+            Param::MaxTokens => self.max_tokens = None,
+        }
+    }
+}
+
+/// Rounds away the error accumulated by repeated float steps.
+fn round(value: f32) -> f32 {
+    (value * 100.0).round() / 100.0
+}
+
+/// A parameter adjustable in the popup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Param {
+    /// The temperature.
+    Temperature,
+    /// The nucleus sampling threshold.
+    TopP,
+    /// The response token limit.
+    MaxTokens,
+}
+
+impl Param {
+    /// The parameters, in the order they are shown.
+    pub const ALL: [Param; 3] = [Param::Temperature, Param::TopP, Param::MaxTokens];
+
+    /// Returns the name the parameter is sent and configured under.
+    #[must_use]
+    pub fn key(self) -> &'static str {
+        match self {
+            Param::Temperature => "temperature",
+            Param::TopP => "top_p",
+            Param::MaxTokens => "max_tokens",
+        }
+    }
+}
+
+/// An answer to send again with other parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Retry {
+    /// The index of the answer in the transcript.
+    pub message: usize,
+    /// The model to send the prompt to, or an empty string for the current
+    /// model.
+    pub model: String,
+    /// The parameters to send it with.
+    pub params: Params,
+}
+
+/// The parameters being adjusted in the open popup.
+///
+/// When retrying an answer, the popup also has a model field above the
+/// parameters.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamsEditor {
+    /// The adjusted parameters, applied when the popup is confirmed.
+    pub draft: Params,
+    /// The index of the selected row.
+    pub selected: usize,
+    /// The answer being retried and the model to retry it with, or `None`
+    /// when adjusting the parameters of the following requests.
+    pub retry: Option<(usize, String)>,
+}
+
+impl ParamsEditor {
+    /// Opens the popup on the current parameters.
+    #[must_use]
+    pub fn new(params: Params) -> Self {
+        ParamsEditor {
+            draft: params,
+            selected: 0,
+            retry: None,
+        }
+    }
+
+    /// Opens the popup for retrying an answer.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The index of the answer in the transcript.
+    /// * `model` - The model to start from.
+    /// * `params` - The parameters to start from.
+    #[must_use]
+    pub fn retry(message: usize, model: String, params: Params) -> Self {
+        ParamsEditor {
+            draft: params,
+            selected: 0,
+            retry: Some((message, model)),
+        }
+    }
+
+    /// Returns the number of rows: the model field when retrying, and the
+    /// parameters.
+    #[must_use]
+    pub fn rows(&self) -> usize {
+        Param::ALL.len() + usize::from(self.retry.is_some())
+    }
+
+    /// Returns the parameter of the selected row, or `None` if the model
+    /// field is selected.
+    #[must_use]
+    pub fn selected(&self) -> Option<Param> {
+        let offset = usize::from(self.retry.is_some());
+        self.selected
+            .checked_sub(offset)
+            .map(|index| Param::ALL[index])
+    }
+
+    /// Returns the model field while the model row is selected.
+    pub fn model_mut(&mut self) -> Option<&mut String> {
+        if self.selected().is_some() {
+            return None;
+        }
+        self.retry.as_mut().map(|(_, model)| model)
+    }
+
+    /// Selects the previous row, wrapping around.
+    pub fn select_previous(&mut self) {
+        self.selected = (self.selected + self.rows() - 1) % self.rows();
+    }
+
+    /// Selects the next row, wrapping around.
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1) % self.rows();
+    }
+
+    /// Returns the retry the popup was confirmed for, or `None` if it
+    /// adjusts the parameters of the following requests.
+    #[must_use]
+    pub fn into_retry(self) -> Option<Retry> {
+        self.retry.map(|(message, model)| Retry {
+            message,
+            model: model.trim().to_string(),
+            params: self.draft,
+        })
+    }
+}
@@ -0,0 +1,94 @@
+//! A list filtered fuzzily by what is typed, to choose one item from.
+//!
+//! The command palette and the session switcher are pickers: typing adds to
+//! the query and narrows the list, best matches first, `Up`/`Down` choose an
+//! item and `Enter` takes it.
+
+use std::cmp::Reverse;
+
+/// Something a picker lists.
+pub trait Item: Clone {
+    /// Scores how well the item matches a query, higher being better, or
+    /// returns `None` if it does not match.
+    fn score(&self, query: &str) -> Option<usize>;
+
+    /// Returns the name the item is listed by.
+    fn label(&self) -> &str;
+}
+
+/// A picker: the query and the items matching it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Picker<T> {
+    /// What was typed to filter the items.
+    pub query: String,
+    /// The items matching the query, best first.
+    pub matches: Vec<T>,
+    /// The index of the chosen item.
+    pub selected: usize,
+    items: Vec<T>,
+}
+
+impl<T: Item> Picker<T> {
+    /// Opens a picker with every item listed, in the order given.
+    #[must_use]
+    pub fn with_items(items: Vec<T>) -> Self {
+        Picker {
+            query: String::new(),
+            matches: items.clone(),
+            selected: 0,
+            items,
+        }
+    }
+
+    /// Adds a character to the query.
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.filter();
+    }
+
+    /// Removes the last character of the query.
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.filter();
+    }
+
+    /// Lists the items matching the query, best first, keeping the order
+    /// of equally good ones.
+    fn filter(&mut self) {
+        let query = self.query.trim();
+        let mut scored: Vec<(usize, &T)> = self
+            .items
+            .iter()
+            .filter_map(|item| Some((item.score(query)?, item)))
+            .collect();
+        scored.sort_by_key(|(score, _)| Reverse(*score));
+        self.matches = scored.into_iter().map(|(_, item)| item.clone()).collect();
+        self.selected = 0;
+    }
+
+    /// Chooses the previous item, wrapping around.
+    pub fn select_previous(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected + self.matches.len() - 1) % self.matches.len();
+        }
+    }
+
+    /// Chooses the next item, wrapping around.
+    pub fn select_next(&mut self) {
+        if !self.matches.is_empty() {
+            self.selected = (self.selected + 1) % self.matches.len();
+        }
+    }
+
+    /// Returns the number of items, matching the query or not.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns the chosen item, if any matches the query.
+    #[must_use]
+    pub fn chosen(&self) -> Option<&T> {
+        self.matches.get(self.selected)
+    }
+}
@@ -5,13 +5,31 @@ use ratatui::{
     style::{Color, Modifier, Style, Stylize},
     text::{Line, Span, Text},
     widgets::{
-        Block, Borders, Clear, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation, Wrap,
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Row, Scrollbar,
+        ScrollbarOrientation, ScrollbarState, Table, Wrap,
     },
     Frame,
 };
 
-use super::markdown_renderer::MarkdownRenderer;
+use super::compare::Comparison;
+use super::completion::Completion;
+use super::context_view::ContextView;
+use super::dialog::{Dialog, Kind};
+use super::diff::{Change, DiffView};
+use super::graphics::Graphics;
+use super::keymap;
+use super::markdown_renderer::{CodeLayout, MarkdownRenderer};
+use super::params::{Param, ParamsEditor};
+use super::picker::{Item, Picker};
 use super::state::{InputMode, State};
+use super::switcher;
+use super::tasks::MAX_VISIBLE;
+use super::theme::Theme;
+use crate::{
+    api::EndpointStatus, error::ErrorDetails, images, lint::Warning, message::Message,
+    search::Results, structured, tables::Sample, tokens::DraftStats,
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 /// Renders the user interface for the chat application.
 #[derive(Default)]
@@ -39,104 +57,185 @@ impl Renderer {
             .constraints([Constraint::Min(0), Constraint::Length(3)].as_ref())
             .split(f.area());
 
-        let messages_area = chunks[0];
+        let task_rows = ui_state.tasks.len().min(MAX_VISIBLE) as u16;
+        let [messages_area, tasks_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(task_rows)]).areas(chunks[0]);
         let messages_inner_area = messages_area.inner(Margin::new(1, 1));
+        let width = messages_inner_area.width as usize;
+        ui_state.messages_height = messages_inner_area.height;
+        let image_rows = ui_state.graphics.as_ref().map_or(0, Graphics::rows);
+        ui_state.layout.set_image_rows(image_rows);
+        ui_state
+            .layout
+            .update(&ui_state.messages, width, &ui_state.theme);
+        let visible = ui_state.layout.visible(
+            ui_state.list_state.offset(),
+            ui_state.list_state.selected(),
+            messages_inner_area.height as usize,
+        );
         let processed_messages: Vec<ListItem> = ui_state
             .messages
             .iter()
-            .map(|(role, content)| {
-                let (style, prefix) = match role.as_str() {
-                    "user" => (Style::default().fg(Color::Blue), "You: "),
-                    "assistant" => (Style::default().fg(Color::Green), "AI: "),
-                    "system" => (Style::default().fg(Color::Yellow), ""),
-                    _ => (Style::default(), ""),
-                };
-
-                let content = if role == "system" && ui_state.input_mode == InputMode::Waiting {
-                    format!("{} {}", ui_state.spinner.next_frame(), content)
-                } else {
-                    content.clone()
-                };
-
-                let available_width = messages_inner_area.width as usize - prefix.len();
-
-                let lines: Vec<_> = if role == "system" || role == "user" {
-                    Self::wrap_text(&content, available_width)
-                        .into_iter()
-                        .enumerate()
-                        .map(|(i, line)| {
-                            if i == 0 {
-                                Line::from(vec![
-                                    Span::styled(prefix.to_string(), style),
-                                    Span::raw(line),
-                                ])
-                            } else {
-                                Line::from(vec![
-                                    Span::raw(" ".repeat(prefix.len())),
-                                    Span::raw(line),
-                                ])
-                            }
-                        })
-                        .collect()
+            .enumerate()
+            .map(|(index, message)| {
+                // Messages out of view keep their height but not their text.
+                if !visible.contains(&index) {
+                    let rows = ui_state.layout.lines(index).len();
+                    return ListItem::new(vec![Line::default(); rows]);
+                }
+                // A notice such as a model pull spins while it is the last
+                // message; responses show the indicator below instead.
+                let busy = ui_state.input_mode == InputMode::Waiting
+                    && ui_state.progress.is_none()
+                    && ui_state.tasks.is_empty()
+                    && index + 1 == ui_state.messages.len();
+                if message.role == "system" && busy {
+                    let content = format!("{} {}", ui_state.spinner.next_frame(), message.content);
+                    ListItem::new(Self::message_lines(
+                        &Message::new("system", content),
+                        width,
+                        &ui_state.theme,
+                    ))
                 } else {
-                    let markdown_lines =
-                        MarkdownRenderer::render_markdown(&content, available_width);
-                    markdown_lines
-                        .into_iter()
-                        .enumerate()
-                        .map(|(i, line)| {
-                            if i == 0 {
-                                Line::from(vec![
-                                    Span::styled(prefix.to_string(), style),
-                                    Span::raw(line.to_string()),
-                                ])
-                            } else {
-                                Line::from(vec![
-                                    Span::raw(" ".repeat(prefix.len())),
-                                    Span::raw(line.to_string()),
-                                ])
-                            }
-                        })
-                        .collect()
-                };
-
-                ListItem::new(lines)
+                    ListItem::new(ui_state.layout.lines(index).to_vec())
+                }
             })
             .collect();
 
+        let mut block = Block::default()
+            .title("Chatti")
+            .title(Self::endpoint_indicator(ui_state).right_aligned())
+            .borders(Borders::ALL);
+        if let Some(indicator) = Self::generation_indicator(ui_state) {
+            block = block.title_bottom(indicator);
+        }
         let messages = List::new(processed_messages)
-            .block(Block::default().title("Chatti").borders(Borders::ALL))
+            .block(block)
             .highlight_style(Style::default().bg(Color::DarkGray));
 
-        ui_state.vertical_scroll_state = ui_state
-            .vertical_scroll_state
-            .content_length(ui_state.messages.len())
-            .viewport_content_length(messages_area.height as usize);
+        if ui_state.input_mode == InputMode::Pager {
+            Self::render_pager(f, messages_area, ui_state);
+        } else if let Some(comparison) = &ui_state.comparison {
+            Self::render_comparison(f, messages_area, comparison);
+        } else {
+            f.render_stateful_widget(messages, messages_area, &mut ui_state.list_state);
+            Self::render_images(f, messages_inner_area, ui_state);
 
-        f.render_stateful_widget(messages, messages_area, &mut ui_state.list_state);
+            // The scrollbar tracks the first visible line, so it reaches the
+            // bottom when the last line is in view.
+            let viewport = messages_inner_area.height as usize;
+            let top = ui_state
+                .layout
+                .range(ui_state.list_state.offset())
+                .map_or(0, |range| range.start);
+            ui_state.vertical_scroll_state = ui_state
+                .vertical_scroll_state
+                .content_length(ui_state.layout.total_lines().saturating_sub(viewport) + 1)
+                .viewport_content_length(viewport)
+                .position(top);
 
-        f.render_stateful_widget(
-            Scrollbar::new(ScrollbarOrientation::VerticalRight)
-                .begin_symbol(None)
-                .end_symbol(None),
-            messages_area.inner(Margin::new(0, 1)),
-            &mut ui_state.vertical_scroll_state,
-        );
+            f.render_stateful_widget(
+                Self::scrollbar(
+                    ScrollbarOrientation::VerticalRight,
+                    ui_state.capabilities.unicode,
+                ),
+                messages_area.inner(Margin::new(0, 1)),
+                &mut ui_state.vertical_scroll_state,
+            );
+        }
+
+        Self::render_tasks(f, tasks_area, ui_state);
 
         ui_state.input_width = chunks[1].width.saturating_sub(2);
 
-        let input = Paragraph::new(ui_state.input.as_str())
+        let draft = DraftStats::of(&ui_state.input);
+        let prompt_tokens = ui_state.estimated_prompt_tokens();
+        let counter_style = if prompt_tokens > ui_state.context_budget {
+            Style::default().fg(Color::Red)
+        } else if prompt_tokens * 5 > ui_state.context_budget * 4 {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        let cost = ui_state
+            .estimated_prompt_cost()
+            .map(|cost| format!(" | ~${cost:.4}"))
+            .unwrap_or_default();
+        let counter = if ui_state.input.is_empty() {
+            Line::default()
+        } else {
+            Line::styled(
+                format!(
+                    " {} chars | {} words | ~{} tokens ({}/{} ctx){cost} ",
+                    draft.chars, draft.words, draft.tokens, prompt_tokens, ui_state.context_budget
+                ),
+                counter_style,
+            )
+            .right_aligned()
+        };
+
+        // Only the line being typed fits, such as the reply after a quote.
+        let (above, typed) = ui_state
+            .input
+            .rsplit_once('\n')
+            .map_or((0, ui_state.input.as_str()), |(above, typed)| {
+                (above.lines().count() + 1, typed)
+            });
+        let (input_text, input_title) = match &ui_state.history_search {
+            Some(search) => (
+                search
+                    .found
+                    .and_then(|index| ui_state.history.get(index))
+                    .unwrap_or_default(),
+                Line::styled(
+                    format!(" reverse search: {} ", search.query),
+                    Style::default().fg(Color::Cyan),
+                ),
+            ),
+            None => {
+                let attached = Self::context_indicator(ui_state);
+                if above > 0 && attached.spans.is_empty() {
+                    let above = format!(" {above} line(s) above ");
+                    (
+                        typed,
+                        Line::styled(above, Style::default().fg(Color::DarkGray)),
+                    )
+                } else {
+                    (typed, attached)
+                }
+            }
+        };
+        let input = Paragraph::new(input_text)
             .style(match ui_state.input_mode {
                 InputMode::Normal => Style::default(),
                 InputMode::Editing => Style::default().fg(Color::Yellow),
-                InputMode::Waiting => Style::default().fg(Color::DarkGray),
+                InputMode::Waiting
+                | InputMode::Pager
+                | InputMode::Compare
+                | InputMode::Params
+                | InputMode::Search
+                | InputMode::Diff
+                | InputMode::Details
+                | InputMode::Context
+                | InputMode::Palette
+                | InputMode::Switcher => Style::default().fg(Color::DarkGray),
             })
-            .block(Block::default().borders(Borders::ALL))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(input_title)
+                    .title_bottom(counter)
+                    .border_style(if ui_state.is_input_flashing() {
+                        Style::default().fg(Color::Red)
+                    } else {
+                        Style::default()
+                    }),
+            )
             .scroll((0, ui_state.horizontal_scroll as u16));
         f.render_widget(input, chunks[1]);
 
-        if ui_state.input.len() as u16 > ui_state.input_width {
-            let content_length = ui_state.input.len();
+        if typed.len() as u16 > ui_state.input_width {
+            let content_length = typed.len();
             let viewport_content_length = ui_state.input_width as usize;
 
             ui_state.horizontal_scroll_state = ui_state
@@ -146,10 +245,10 @@ impl Renderer {
                 .position(ui_state.horizontal_scroll);
 
             f.render_stateful_widget(
-                Scrollbar::new(ScrollbarOrientation::HorizontalBottom)
-                    .thumb_symbol("🬋")
-                    .begin_symbol(None)
-                    .end_symbol(None),
+                Self::scrollbar(
+                    ScrollbarOrientation::HorizontalBottom,
+                    ui_state.capabilities.unicode,
+                ),
                 chunks[1].inner(Margin {
                     vertical: 0,
                     horizontal: 1,
@@ -160,12 +259,31 @@ impl Renderer {
 
         if ui_state.input_mode == InputMode::Editing {
             f.set_cursor_position(Position::new(
-                chunks[1].x + ui_state.input.len() as u16 + 1,
+                chunks[1].x + typed.len() as u16 + 1,
                 chunks[1].y + 1,
             ));
         }
 
+        let pager_search = ui_state.pager.search_input.clone();
         let (msg, style) = match ui_state.input_mode {
+            InputMode::Normal if ui_state.has_pending() => (
+                vec![format!(
+                    "Endpoint unreachable: {} message(s) pending, retrying automatically",
+                    ui_state.messages.iter().filter(|m| m.pending).count()
+                )
+                .into()],
+                Style::default().fg(Color::Yellow),
+            ),
+            InputMode::Normal if ui_state.cut_off_answer().is_some() => (
+                vec![
+                    "The answer stopped at the token limit. Press ".into(),
+                    "m".bold(),
+                    " to continue it, ".into(),
+                    "?".bold(),
+                    " to show help menu".into(),
+                ],
+                Style::default().fg(Color::Yellow),
+            ),
             InputMode::Normal => (
                 vec![
                     "Press ".into(),
@@ -173,11 +291,48 @@ impl Renderer {
                     " to exit, ".into(),
                     "e".bold(),
                     " to start editing, ".into(),
+                    "t".bold(),
+                    " to read the transcript, ".into(),
                     "?".bold(),
                     " to show help menu".into(),
                 ],
                 Style::default(),
             ),
+            InputMode::Editing if !ui_state.lint_warnings.is_empty() => (
+                vec![
+                    format!("{} warning(s) above. ", ui_state.lint_warnings.len()).into(),
+                    "Enter".bold(),
+                    " to send anyway, or keep editing".into(),
+                ],
+                if ui_state.lint_warnings.iter().any(Warning::is_credential) {
+                    Style::default().fg(Color::Red)
+                } else {
+                    Style::default().fg(Color::Yellow)
+                },
+            ),
+            InputMode::Editing if ui_state.shell_preview.is_some() => (
+                vec![
+                    "Press ".into(),
+                    "Enter".bold(),
+                    " to send the expanded prompt, ".into(),
+                    "Esc".bold(),
+                    " to keep editing".into(),
+                ],
+                Style::default().fg(Color::Yellow),
+            ),
+            InputMode::Editing if ui_state.awaiting_cost_confirmation => (
+                vec![
+                    format!(
+                        "Estimated cost ${:.4} exceeds ${:.4}. ",
+                        ui_state.estimated_prompt_cost().unwrap_or_default(),
+                        ui_state.cost_confirm_threshold.unwrap_or_default()
+                    )
+                    .into(),
+                    "Enter".bold(),
+                    " to send anyway, or keep editing".into(),
+                ],
+                Style::default().fg(Color::Red),
+            ),
             InputMode::Editing => (
                 vec![
                     "Press ".into(),
@@ -189,9 +344,117 @@ impl Renderer {
                 Style::default(),
             ),
             InputMode::Waiting => (
-                vec!["Press ".into(), "Esc".bold(), " to cancel request".into()],
+                vec![
+                    "Press ".into(),
+                    "Esc".bold(),
+                    " to cancel request, ".into(),
+                    "Ctrl+C".bold(),
+                    " to stop and keep the partial answer, type and ".into(),
+                    "Enter".bold(),
+                    " to interrupt with a follow-up".into(),
+                    if ui_state.follow {
+                        " (following output)".into()
+                    } else {
+                        ", End to follow output".into()
+                    },
+                ],
                 Style::default(),
             ),
+            InputMode::Compare => (
+                vec![
+                    "Press ".into(),
+                    "1".bold(),
+                    " or ".into(),
+                    "2".bold(),
+                    " to keep that answer, ".into(),
+                    "Esc".bold(),
+                    " to discard both".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Params => (
+                vec![
+                    "Press ".into(),
+                    "Left/Right".bold(),
+                    " to adjust, ".into(),
+                    "Backspace".bold(),
+                    " to reset, ".into(),
+                    "Enter".bold(),
+                    " to apply, ".into(),
+                    "Esc".bold(),
+                    " to cancel".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Details => (
+                vec!["Press ".into(), "Esc".bold(), " to close".into()],
+                Style::default(),
+            ),
+            InputMode::Palette => (
+                vec![
+                    "Type to filter, ".into(),
+                    "Up/Down".bold(),
+                    " to choose, ".into(),
+                    "Enter".bold(),
+                    " to run, ".into(),
+                    "Esc".bold(),
+                    " to close".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Switcher => (
+                vec![
+                    "Type a title or date, ".into(),
+                    "Up/Down".bold(),
+                    " to choose, ".into(),
+                    "Enter".bold(),
+                    " to open, ".into(),
+                    "Esc".bold(),
+                    " to close".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Diff | InputMode::Context => (
+                vec![
+                    "Press ".into(),
+                    "Up/Down".bold(),
+                    " to scroll, ".into(),
+                    "Esc".bold(),
+                    " to close".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Search => (
+                vec![
+                    "Press ".into(),
+                    "Up/Down".bold(),
+                    " to choose, ".into(),
+                    "Enter".bold(),
+                    " to open the session, ".into(),
+                    "Esc".bold(),
+                    " to close".into(),
+                ],
+                Style::default(),
+            ),
+            InputMode::Pager => match pager_search {
+                Some(query) => (vec!["/".bold(), query.into()], Style::default()),
+                None => (
+                    vec![
+                        "Press ".into(),
+                        "q".bold(),
+                        " to close, ".into(),
+                        "/".bold(),
+                        " to search, ".into(),
+                        "n/N".bold(),
+                        " for next/previous match, ".into(),
+                        "m/'".bold(),
+                        " to set/jump to a mark, ".into(),
+                        "1-9".bold(),
+                        " to jump to a footnote".into(),
+                    ],
+                    Style::default(),
+                ),
+            },
         };
 
         let text = Text::from(Line::from(msg)).patch_style(style);
@@ -199,78 +462,1154 @@ impl Renderer {
 
         f.render_widget(help_message, chunks[1]);
 
+        if let Some(completion) = ui_state
+            .completion
+            .as_ref()
+            .filter(|_| ui_state.input_mode == InputMode::Editing)
+        {
+            Self::render_completion(f, chunks[1], completion);
+        }
+
+        if ui_state.input_mode == InputMode::Editing && !ui_state.lint_warnings.is_empty() {
+            Self::render_lint_warnings(f, chunks[1], &ui_state.lint_warnings);
+        }
+
         if ui_state.show_toggle {
-            Self::render_help(f);
+            Self::render_help(f, ui_state);
         }
+
+        if let Some(preview) = &ui_state.shell_preview {
+            Self::render_shell_preview(f, preview);
+        }
+
+        if let Some(editor) = &ui_state.params_editor {
+            Self::render_params(f, editor, ui_state.capabilities.unicode);
+        }
+
+        if let Some(details) = &ui_state.details {
+            Self::render_details(f, details);
+        }
+
+        if let Some(view) = &ui_state.context_view {
+            Self::render_context(f, view);
+        }
+
+        if let Some(diff) = &ui_state.diff {
+            Self::render_diff(f, diff);
+        }
+
+        if let Some(results) = &ui_state.search_results {
+            Self::render_search(f, results);
+        }
+
+        if let Some(palette) = &ui_state.palette {
+            Self::render_picker(f, "Command palette", palette, |entry| entry.detail.clone());
+        }
+
+        if let Some(switcher) = &ui_state.switcher {
+            let current = ui_state.session_id.as_deref();
+            Self::render_picker(f, "Sessions", switcher, |session| {
+                let open = if current == Some(session.id.as_str()) {
+                    " (open)"
+                } else {
+                    ""
+                };
+                format!(
+                    "{} · {} message(s){open}",
+                    switcher::date(session.updated_at),
+                    session.messages
+                )
+            });
+        }
+
+        if let Some(dialog) = &ui_state.dialog {
+            let top = ui_state.table_preview.as_ref().map_or(0, |(name, sample)| {
+                Self::render_table_preview(f, name, sample)
+            });
+            Self::render_dialog(f, dialog, top);
+        }
+
+        ui_state.capabilities.apply(f.buffer_mut());
     }
 
-    fn render_help(f: &mut Frame) {
-        let area = f.area();
-        let help_area = Rect::new(
-            area.width / 4,
-            area.height / 5,
-            area.width / 2,
-            area.height / 2,
-        );
+    /// Builds the indicator of clipboard context and images attached to the
+    /// next message.
+    fn context_indicator(ui_state: &State) -> Line<'static> {
+        let mut attached = Vec::new();
+        if let Some(context) = &ui_state.pending_context {
+            attached.push(format!("context: {} line(s)", context.lines().count()));
+        }
+        if !ui_state.pending_images.is_empty() {
+            attached.push(format!("{} image(s)", ui_state.pending_images.len()));
+        }
+        if attached.is_empty() {
+            return Line::default();
+        }
+        Line::styled(
+            format!(" + {}, sent with the next message ", attached.join(", ")),
+            Style::default().fg(Color::Cyan),
+        )
+    }
 
-        f.render_widget(Clear, help_area);
+    /// Builds the endpoint readiness indicator shown in the title bar.
+    fn endpoint_indicator(ui_state: &State) -> Line<'static> {
+        let Some(status) = ui_state.endpoint_status() else {
+            return Line::default();
+        };
+        let (label, color) = match status {
+            EndpointStatus::Checking => ("connecting", Color::DarkGray),
+            EndpointStatus::WarmingUp => ("loading model", Color::Yellow),
+            EndpointStatus::Ready => ("ready", Color::Green),
+            EndpointStatus::Unreachable => ("unreachable", Color::Red),
+        };
+        let dot = if ui_state.capabilities.unicode {
+            "●"
+        } else {
+            "*"
+        };
+        Line::styled(format!(" {dot} {label} "), Style::default().fg(color))
+    }
 
-        let help_text = vec![
-            Line::from("Shortcut Information"),
-            Line::from(""),
-            Line::from(vec![
-                Span::styled(
-                    "?",
-                    Style::default()
-                        .fg(Color::Blue)
-                        .add_modifier(Modifier::BOLD),
+    /// Builds the live indicator shown below the transcript while a response
+    /// is generating, or `None` if none is.
+    fn generation_indicator(ui_state: &mut State) -> Option<Line<'static>> {
+        let progress = ui_state.progress.as_ref()?;
+        let frame = ui_state.spinner.next_frame();
+        // Until the first token, the request may still wait for its turn.
+        let queued = ui_state
+            .limiter
+            .as_ref()
+            .filter(|limiter| progress.tokens() == 0 && limiter.queued() > 0);
+        let mut text = match queued {
+            Some(_) => format!(" {frame} Queued"),
+            None => format!(" {frame} Generating"),
+        };
+        if ui_state.show_progress {
+            text.push_str(&format!(" {}", progress.describe()));
+        }
+        if let Some(limiter) = queued {
+            text.push_str(&format!(" · {} waiting", limiter.queued()));
+            if let Some(max) = limiter.max_concurrent() {
+                text.push_str(&format!(", {}/{max} in flight", limiter.in_flight()));
+            }
+        }
+        text.push(' ');
+        Some(Line::styled(text, Style::default().fg(Color::DarkGray)))
+    }
+
+    /// Renders the running tasks, one per row with a spinner and the key
+    /// that cancels it.
+    fn render_tasks(f: &mut Frame, area: Rect, ui_state: &mut State) {
+        if area.height == 0 {
+            return;
+        }
+        let frame = ui_state.spinner.next_frame();
+        let unicode = ui_state.capabilities.unicode;
+        let lines: Vec<Line> = ui_state
+            .tasks
+            .iter()
+            .take(MAX_VISIBLE)
+            .enumerate()
+            .map(|(position, task)| {
+                Line::from(vec![
+                    Span::raw(format!(" {frame} {} ", task.describe(unicode))),
+                    Span::styled(
+                        format!("({} to cancel)", position + 1),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ])
+            })
+            .collect();
+        f.render_widget(Paragraph::new(lines), area);
+    }
+
+    /// Builds a scrollbar, using ASCII symbols if unicode is unsupported.
+    fn scrollbar(orientation: ScrollbarOrientation, unicode: bool) -> Scrollbar<'static> {
+        let horizontal = orientation == ScrollbarOrientation::HorizontalBottom;
+        let scrollbar = Scrollbar::new(orientation)
+            .begin_symbol(None)
+            .end_symbol(None);
+        match (unicode, horizontal) {
+            (true, true) => scrollbar.thumb_symbol("🬋"),
+            (true, false) => scrollbar,
+            (false, true) => scrollbar.track_symbol(Some("-")).thumb_symbol("="),
+            (false, false) => scrollbar.track_symbol(Some("|")).thumb_symbol("#"),
+        }
+    }
+
+    /// Renders a single message into styled lines with its role prefix.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The message to render.
+    /// * `width` - The available width, including the prefix.
+    /// * `theme` - The role prefixes and colors.
+    ///
+    /// # Returns
+    ///
+    /// A vector of `Line`s representing the rendered message.
+    #[must_use]
+    pub fn message_lines(message: &Message, width: usize, theme: &Theme) -> Vec<Line<'static>> {
+        Self::message_lines_with(message, width, theme, 0)
+    }
+
+    /// Renders a single message like [`Renderer::message_lines`], leaving
+    /// `image_rows` blank rows below the name of each image to draw it in.
+    #[must_use]
+    pub fn message_lines_with(
+        message: &Message,
+        width: usize,
+        theme: &Theme,
+        image_rows: u16,
+    ) -> Vec<Line<'static>> {
+        if let Some(details) = &message.error {
+            return Self::error_lines(message, details, width);
+        }
+
+        let role = message.role.as_str();
+        let pretty = (message.json && !message.raw)
+            .then(|| structured::check(&message.content, None).ok())
+            .flatten();
+        let content = pretty
+            .as_ref()
+            .map_or(message.content.as_str(), |checked| checked.content.as_str());
+        let style = theme.role(role).map_or(Style::default(), |r| r.style);
+        let prefix = theme.prefix(message);
+        let prefix_width = prefix.width();
+
+        let available_width = width.saturating_sub(prefix_width);
+
+        let body: Vec<Line<'static>> = if message.raw {
+            Self::raw_lines(content, available_width)
+        } else if role == "system" || role == "user" {
+            Self::wrap_text(content, available_width)
+                .into_iter()
+                .map(Line::from)
+                .collect()
+        } else {
+            let code = message
+                .code_scroll
+                .map_or(CodeLayout::Wrap, CodeLayout::Scroll);
+            MarkdownRenderer::render_markdown_with(content, available_width, code)
+        };
+
+        let mut lines: Vec<Line<'static>> = body
+            .into_iter()
+            .enumerate()
+            .map(|(i, line)| {
+                let lead = if i == 0 {
+                    Span::styled(prefix.clone(), style)
+                } else {
+                    Span::raw(" ".repeat(prefix_width))
+                };
+                let mut spans = vec![lead];
+                spans.extend(line.spans);
+                Line::from(spans)
+            })
+            .collect();
+        if message.first_unread {
+            lines.insert(
+                0,
+                Line::styled(
+                    format!("{:─^width$}", " new "),
+                    Style::default().fg(Color::Yellow),
                 ),
-                Span::raw(" to toggle/untoggle this help menu"),
-            ]),
-            Line::from(vec![
+            );
+        }
+
+        let mut notes = Vec::new();
+        if let Some(attachment) = &message.attachment {
+            notes.push(format!(
+                "[+ attached context: {} line(s)]",
+                attachment.lines().count()
+            ));
+        }
+        if !message.sources.is_empty() {
+            notes.push(format!("[sources: {}]", message.sources.join(", ")));
+        }
+        if let Some(generation) = message.generation.as_ref().filter(|_| message.compared) {
+            notes.push(format!("[{}]", generation.describe()));
+        }
+        if let Some(language) = &message.translation {
+            notes.push(format!("[translated to {language}]"));
+        }
+        if !message.marks.is_empty() {
+            let marks: Vec<String> = message
+                .marks
+                .iter()
+                .map(|mark| format!("{} {}", mark.symbol(), mark.name()))
+                .collect();
+            notes.push(format!("[{}]", marks.join(", ")));
+        }
+        if message.stopped {
+            notes.push(String::from("(stopped)"));
+        } else if message.pending {
+            notes.push(String::from("(pending send)"));
+        } else if message.cancelled {
+            notes.push(String::from("(cancelled, not sent again)"));
+        }
+        let note_style = Style::default()
+            .fg(Color::DarkGray)
+            .add_modifier(Modifier::ITALIC);
+        for note in notes {
+            lines.push(Line::from(vec![
+                Span::raw(" ".repeat(prefix_width)),
+                Span::styled(note, note_style),
+            ]));
+        }
+        // Images come last, so their rows can be found from the end.
+        for image in images::of(message) {
+            lines.push(Line::from(vec![
+                Span::raw(" ".repeat(prefix_width)),
+                Span::styled(image.placeholder(), note_style),
+            ]));
+            lines.extend((0..image_rows).map(|_| Line::default()));
+        }
+
+        lines
+    }
+
+    /// Draws the images of the messages in view into the rows left for
+    /// them, skipping those not entirely in view.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - The frame.
+    /// * `area` - The area of the transcript inside its border.
+    /// * `ui_state` - The messages, their layout and the graphics.
+    fn render_images(f: &mut Frame, area: Rect, ui_state: &mut State) {
+        let Some(graphics) = ui_state.graphics.as_mut() else {
+            return;
+        };
+        let rows = usize::from(graphics.rows());
+        let bottom = usize::from(area.bottom());
+        let mut top = usize::from(area.y);
+        for (index, message) in ui_state
+            .messages
+            .iter()
+            .enumerate()
+            .skip(ui_state.list_state.offset())
+        {
+            if top >= bottom {
+                break;
+            }
+            let height = ui_state.layout.lines(index).len();
+            let images = ui_state.layout.images(index);
+            let prefix = ui_state.theme.prefix(message).width();
+            for (position, image) in images.iter().enumerate() {
+                let from_end = (images.len() - position) * (rows + 1);
+                let y = top + height.saturating_sub(from_end) + 1;
+                if y + rows > bottom {
+                    continue;
+                }
+                #[allow(clippy::cast_possible_truncation)]
+                let image_area = Rect::new(
+                    area.x + prefix as u16,
+                    y as u16,
+                    area.width.saturating_sub(prefix as u16 + 1),
+                    rows as u16,
+                );
+                graphics.draw(f, image, image_area);
+            }
+            top += height;
+        }
+    }
+
+    /// Renders an error card: the friendly message, followed by the
+    /// technical details when expanded.
+    fn error_lines(message: &Message, details: &ErrorDetails, width: usize) -> Vec<Line<'static>> {
+        const PREFIX: &str = "Error: ";
+        let error_style = Style::default().fg(Color::Red);
+        let detail_style = Style::default().fg(Color::DarkGray);
+        let available_width = width.saturating_sub(PREFIX.len());
+        let indent = || Span::raw(" ".repeat(PREFIX.len()));
+
+        let mut lines: Vec<Line<'static>> = Self::wrap_text(&message.content, available_width)
+            .into_iter()
+            .enumerate()
+            .map(|(i, text)| {
+                let lead = if i == 0 {
+                    Span::styled(PREFIX, error_style.add_modifier(Modifier::BOLD))
+                } else {
+                    indent()
+                };
+                Line::from(vec![lead, Span::styled(text, error_style)])
+            })
+            .collect();
+
+        if message.expanded {
+            for detail in details.lines() {
+                for text in Self::wrap_text(&detail, available_width) {
+                    lines.push(Line::from(vec![indent(), Span::styled(text, detail_style)]));
+                }
+            }
+        } else {
+            lines.push(Line::from(vec![
+                indent(),
                 Span::styled(
-                    "q",
-                    Style::default()
-                        .fg(Color::Blue)
-                        .add_modifier(Modifier::BOLD),
+                    "press x to show details",
+                    detail_style.add_modifier(Modifier::ITALIC),
                 ),
-                Span::raw(" to quit the application"),
-            ]),
-            Line::from(vec![
-                Span::styled(
-                    "Esc",
+            ]));
+        }
+
+        lines
+    }
+
+    /// Renders two compared responses side by side, each scrolled to its
+    /// latest output.
+    fn render_comparison(f: &mut Frame, area: Rect, comparison: &Comparison) {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(area);
+
+        for (side, column) in columns.iter().enumerate() {
+            let inner = column.inner(Margin::new(1, 1));
+            let lines = MarkdownRenderer::render_markdown(
+                &comparison.responses[side],
+                inner.width as usize,
+            );
+            let skip = lines.len().saturating_sub(inner.height as usize);
+            let state = if comparison.done[side] {
+                "done"
+            } else {
+                "streaming"
+            };
+            let block = Block::default()
+                .title(format!(
+                    " [{}] {} ({state}) ",
+                    side + 1,
+                    comparison.models[side]
+                ))
+                .borders(Borders::ALL);
+            f.render_widget(
+                Paragraph::new(lines.into_iter().skip(skip).collect::<Vec<_>>()).block(block),
+                *column,
+            );
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn render_pager(f: &mut Frame, area: Rect, ui_state: &mut State) {
+        let inner = area.inner(Margin::new(1, 1));
+        let mut lines = Vec::new();
+        let mut messages: Vec<&Message> = ui_state
+            .pager
+            .archived
+            .iter()
+            .chain(&ui_state.messages)
+            .collect();
+        if let Some(filter) = &ui_state.pager.filter {
+            messages = filter.apply(&messages);
+        }
+        let mut ranges = Vec::new();
+        for message in messages {
+            let start = lines.len();
+            lines.extend(Self::message_lines(
+                message,
+                inner.width as usize,
+                &ui_state.theme,
+            ));
+            ranges.push(start..lines.len());
+            lines.push(Line::default());
+        }
+        lines.pop();
+
+        let pager = &mut ui_state.pager;
+        pager.set_content(lines, inner.height as usize);
+        if let Some(range) = pager
+            .select_message
+            .take()
+            .and_then(|index| ranges.get(index).cloned())
+        {
+            pager.select_lines(range);
+        }
+
+        let visible: Vec<Line> = pager
+            .lines
+            .iter()
+            .enumerate()
+            .skip(pager.offset)
+            .take(pager.height)
+            .map(|(i, line)| {
+                if pager.is_selected(i) {
+                    line.clone()
+                        .patch_style(Style::default().add_modifier(Modifier::REVERSED))
+                } else if pager.current_match == Some(i) {
+                    line.clone()
+                        .patch_style(Style::default().bg(Color::DarkGray))
+                } else if pager.is_match(i) {
+                    line.clone()
+                        .patch_style(Style::default().add_modifier(Modifier::UNDERLINED))
+                } else {
+                    line.clone()
+                }
+            })
+            .collect();
+
+        let filter = pager
+            .filter
+            .as_ref()
+            .map(|filter| format!(", {}", filter.describe()))
+            .unwrap_or_default();
+        let status = match (&pager.selection, &pager.notice) {
+            (Some(selection), _) => format!(
+                " | {} line(s) selected, y: copy, Esc: cancel",
+                selection.lines().count()
+            ),
+            (None, Some(notice)) => format!(" | {notice}"),
+            (None, None) => String::new(),
+        };
+        let title = format!(
+            "Transcript{filter} {}-{}/{} ({}%){status}",
+            (pager.offset + 1).min(pager.lines.len()),
+            (pager.offset + pager.height).min(pager.lines.len()),
+            pager.lines.len(),
+            pager.percentage()
+        );
+
+        f.render_widget(
+            Paragraph::new(visible).block(Block::default().title(title).borders(Borders::ALL)),
+            area,
+        );
+    }
+
+    /// Renders the prompt with its `$(command)` substitutions expanded.
+    fn render_shell_preview(f: &mut Frame, preview: &str) {
+        let area = f.area();
+        let preview_area = Rect::new(
+            area.width / 8,
+            area.height / 8,
+            area.width * 3 / 4,
+            area.height * 3 / 4,
+        );
+        let lines = Self::wrap_text(preview, preview_area.width.saturating_sub(2) as usize);
+
+        f.render_widget(Clear, preview_area);
+        f.render_widget(
+            Paragraph::new(lines.into_iter().map(Line::from).collect::<Vec<_>>()).block(
+                Block::default()
+                    .title(" Expanded prompt ")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            ),
+            preview_area,
+        );
+    }
+
+    /// Renders the completion suggestions in a popup above the input.
+    fn render_completion(f: &mut Frame, input_area: Rect, completion: &Completion) {
+        const MAX_ROWS: usize = 8;
+
+        let text_width = completion
+            .suggestions
+            .iter()
+            .map(|suggestion| suggestion.text.width())
+            .max()
+            .unwrap_or_default();
+        let items: Vec<ListItem> = completion
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!(" {:<text_width$} ", suggestion.text)),
+                    Span::styled(
+                        format!("{} ", suggestion.detail),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                ]))
+            })
+            .collect();
+        let content_width = items.iter().map(ListItem::width).max().unwrap_or_default();
+
+        let height = (completion.suggestions.len().min(MAX_ROWS) as u16 + 2).min(input_area.y);
+        let width = (content_width as u16 + 2).min(input_area.width);
+        let popup = Rect::new(input_area.x, input_area.y - height, width, height);
+
+        let mut state = ListState::default().with_selected(Some(completion.selected));
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(
+            List::new(items)
+                .block(Block::default().borders(Borders::ALL))
+                .highlight_style(
                     Style::default()
-                        .fg(Color::Blue)
+                        .fg(Color::Yellow)
                         .add_modifier(Modifier::BOLD),
                 ),
-                Span::raw(" to exit from Editing Mode"),
-            ]),
-            Line::from(vec![
-                Span::styled(
-                    "Left/Right key",
-                    Style::default()
-                        .fg(Color::Blue)
-                        .add_modifier(Modifier::BOLD),
+            popup,
+            &mut state,
+        );
+    }
+
+    /// Renders the warnings about the draft in a box above the input.
+    fn render_lint_warnings(f: &mut Frame, input_area: Rect, warnings: &[Warning]) {
+        let width = input_area.width.saturating_sub(2) as usize;
+        let lines: Vec<Line> = warnings
+            .iter()
+            .flat_map(|warning| Self::wrap_text(&format!("- {warning}"), width))
+            .map(Line::from)
+            .collect();
+        let height = (lines.len() as u16 + 2).min(input_area.y);
+        let popup = Rect::new(
+            input_area.x,
+            input_area.y - height,
+            input_area.width,
+            height,
+        );
+        let color = if warnings.iter().any(Warning::is_credential) {
+            Color::Red
+        } else {
+            Color::Yellow
+        };
+
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .title(" Send anyway? ")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(color)),
+            ),
+            popup,
+        );
+    }
+
+    /// Renders a dialog in a popup centered below `top`: the question, the
+    /// text of a prompt and the keys that answer it.
+    fn render_dialog(f: &mut Frame, dialog: &Dialog, top: u16) {
+        let area = f.area();
+        let below = area.height.saturating_sub(top);
+        let width = area.width * 3 / 5;
+        let lines = Self::wrap_text(&dialog.question, width.saturating_sub(2) as usize);
+        let input_rows = if matches!(dialog.kind, Kind::Prompt(_)) {
+            2
+        } else {
+            0
+        };
+        let height = (lines.len() as u16 + input_rows + 4).min(below);
+        let popup = Rect::new(
+            (area.width - width) / 2,
+            top + below.saturating_sub(height) / 2,
+            width,
+            height,
+        );
+
+        let mut text: Vec<Line> = lines.into_iter().map(Line::from).collect();
+        if let Kind::Prompt(input) = &dialog.kind {
+            text.push(Line::default());
+            text.push(Line::from(vec![
+                Span::styled("> ", Style::default().fg(Color::Yellow)),
+                Span::raw(input.clone()),
+            ]));
+            let row = popup.y + text.len() as u16;
+            if row < popup.bottom().saturating_sub(1) {
+                f.set_cursor_position(Position::new(
+                    (popup.x + 3 + input.width() as u16).min(popup.right().saturating_sub(2)),
+                    row,
+                ));
+            }
+        }
+        text.push(Line::default());
+        text.push(Line::styled(
+            dialog.hint(),
+            Style::default().fg(Color::DarkGray),
+        ));
+
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(text).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            ),
+            popup,
+        );
+    }
+
+    /// Renders the rows of a table sample in a popup at the top of the
+    /// screen, above the confirmation to attach it.
+    ///
+    /// # Returns
+    ///
+    /// The row below the popup.
+    fn render_table_preview(f: &mut Frame, name: &str, sample: &Sample) -> u16 {
+        const MAX_COLUMN_WIDTH: usize = 20;
+
+        let area = f.area();
+        let width = area.width * 4 / 5;
+        let height = (sample.rows.len() as u16 + 3).min(area.height / 2);
+        let popup = Rect::new((area.width - width) / 2, 1.min(area.height), width, height);
+
+        let numbers: Vec<String> = sample.rows.iter().map(|(n, _)| n.to_string()).collect();
+        let mut widths = vec![numbers.iter().map(String::len).max().unwrap_or(0).max(3)];
+        for (index, name) in sample.header.iter().enumerate() {
+            let longest = sample
+                .rows
+                .iter()
+                .filter_map(|(_, row)| row.get(index))
+                .chain(std::iter::once(name))
+                .map(|value| value.width())
+                .max()
+                .unwrap_or(0);
+            widths.push(longest.min(MAX_COLUMN_WIDTH));
+        }
+        let header =
+            Row::new(std::iter::once("row".to_string()).chain(sample.header.iter().cloned()))
+                .style(Style::default().add_modifier(Modifier::BOLD));
+        let rows = sample.rows.iter().zip(&numbers).map(|((_, row), number)| {
+            Row::new(
+                std::iter::once(Span::styled(
+                    number.clone(),
+                    Style::default().fg(Color::DarkGray),
+                ))
+                .chain(row.iter().map(|value| Span::raw(value.clone()))),
+            )
+        });
+        let widths = widths
+            .into_iter()
+            .map(|width| Constraint::Length(width as u16));
+        let shown = if sample.is_complete() {
+            format!("all {} row(s)", sample.total_rows)
+        } else {
+            format!("{} of {} rows", sample.rows.len(), sample.total_rows)
+        };
+
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Table::new(rows, widths).header(header).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow))
+                    .title(format!(" {} preview: {name} ", sample.kind()))
+                    .title_bottom(Line::from(format!(" {shown} ")).right_aligned()),
+            ),
+            popup,
+        );
+        popup.bottom()
+    }
+
+    /// Renders the sampling parameters popup, with a slider for the
+    /// parameters that have a range.
+    fn render_params(f: &mut Frame, editor: &ParamsEditor, unicode: bool) {
+        const SLIDER_WIDTH: usize = 20;
+
+        let area = f.area();
+        let width = 52.min(area.width);
+        let height = (editor.rows() as u16 + 2).min(area.height);
+        let popup = Rect::new(
+            (area.width - width) / 2,
+            area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        );
+
+        let (full, empty) = if unicode { ('█', '░') } else { ('#', '-') };
+        let model = editor.retry.as_ref().map(|(_, model)| {
+            let model = if model.is_empty() {
+                "(current)"
+            } else {
+                model.as_str()
+            };
+            format!(" {:<12} {model}", "model")
+        });
+        let params = Param::ALL.iter().map(|param| {
+            #[allow(
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss,
+                clippy::cast_precision_loss
+            )]
+            let slider = editor.draft.fraction(*param).map_or_else(
+                || " ".repeat(SLIDER_WIDTH),
+                |fraction| {
+                    let filled = ((fraction.clamp(0.0, 1.0) * SLIDER_WIDTH as f32).round()
+                        as usize)
+                        .min(SLIDER_WIDTH);
+                    std::iter::repeat_n(full, filled)
+                        .chain(std::iter::repeat_n(empty, SLIDER_WIDTH - filled))
+                        .collect()
+                },
+            );
+            format!(
+                " {:<12} {slider} {:>9} ",
+                param.key(),
+                editor.draft.value(*param)
+            )
+        });
+        let lines: Vec<Line> = model
+            .into_iter()
+            .chain(params)
+            .enumerate()
+            .map(|(index, line)| {
+                if index == editor.selected {
+                    Line::styled(
+                        line,
+                        Style::default()
+                            .fg(Color::Yellow)
+                            .add_modifier(Modifier::BOLD),
+                    )
+                } else {
+                    Line::from(line)
+                }
+            })
+            .collect();
+        let title = if editor.retry.is_some() {
+            " Retry with "
+        } else {
+            " Parameters "
+        };
+
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .title(title)
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            ),
+            popup,
+        );
+    }
+
+    /// Renders the request the next prompt would make in a centered popup,
+    /// each section under a heading with its estimated tokens.
+    fn render_context(f: &mut Frame, view: &ContextView) {
+        let area = f.area();
+        let width = area.width * 4 / 5;
+        let height = area.height * 4 / 5;
+        let popup = Rect::new(
+            (area.width - width) / 2,
+            (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let mut lines = Vec::new();
+        for section in &view.sections {
+            lines.push(Line::styled(
+                format!("{} (~{} tokens)", section.title, section.tokens),
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            ));
+            lines.extend(section.text.lines().map(|line| Line::raw(line.to_string())));
+            lines.push(Line::default());
+        }
+        if view.sections.is_empty() {
+            lines.push(Line::styled(
+                "Nothing would be sent: there is no system prompt, history or draft",
+                Style::default().fg(Color::DarkGray),
+            ));
+        }
+
+        let tokens = view.tokens();
+        let total_style = if tokens > view.budget {
+            Style::default().fg(Color::Red)
+        } else {
+            Style::default()
+        };
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .scroll((view.scroll, 0))
+                .block(
+                    Block::default()
+                        .title(" Next request ")
+                        .title_bottom(Line::styled(
+                            format!(" ~{tokens} of {} tokens ", view.budget),
+                            total_style,
+                        ))
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(Color::Yellow)),
                 ),
-                Span::raw(" to scrolling horizontally"),
-            ]),
-            Line::from(vec![
-                Span::styled(
-                    "Up/Down key",
-                    Style::default()
-                        .fg(Color::Blue)
-                        .add_modifier(Modifier::BOLD),
+            popup,
+        );
+    }
+
+    /// Renders how an answer was generated in a centered popup, one label
+    /// and value per line.
+    fn render_details(f: &mut Frame, details: &[(&str, String)]) {
+        let area = f.area();
+        let label_width = details
+            .iter()
+            .map(|(label, _)| label.len())
+            .max()
+            .unwrap_or_default();
+        let lines: Vec<Line> = details
+            .iter()
+            .map(|(label, value)| {
+                Line::from(vec![
+                    Span::styled(
+                        format!(" {label:label_width$}  "),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::raw(value.as_str()),
+                ])
+            })
+            .collect();
+        let content_width = lines.iter().map(Line::width).max().unwrap_or_default();
+        let width = (u16::try_from(content_width).unwrap_or(u16::MAX) + 3)
+            .max(30)
+            .min(area.width);
+        let height = (u16::try_from(lines.len()).unwrap_or(u16::MAX) + 2).min(area.height);
+        let popup = Rect::new(
+            (area.width - width) / 2,
+            (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .title(" Details ")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            ),
+            popup,
+        );
+    }
+
+    /// Renders the diff between two attempts at an answer in a centered
+    /// popup, with removed words struck out in red and added words green.
+    fn render_diff(f: &mut Frame, diff: &DiffView) {
+        let area = f.area();
+        let width = area.width * 4 / 5;
+        let height = area.height * 4 / 5;
+        let popup = Rect::new(
+            (area.width - width) / 2,
+            (area.height - height) / 2,
+            width,
+            height,
+        );
+
+        let mut lines = vec![Line::default()];
+        for change in &diff.changes {
+            let style = match change {
+                Change::Same(_) => Style::default(),
+                Change::Removed(_) => Style::default()
+                    .fg(Color::Red)
+                    .add_modifier(Modifier::CROSSED_OUT),
+                Change::Added(_) => Style::default().fg(Color::Green),
+            };
+            for (index, part) in change.text().split('\n').enumerate() {
+                if index > 0 {
+                    lines.push(Line::default());
+                }
+                if !part.is_empty() {
+                    if let Some(line) = lines.last_mut() {
+                        line.push_span(Span::styled(part.to_string(), style));
+                    }
+                }
+            }
+        }
+
+        let (removed, added) = diff.counts();
+        f.render_widget(Clear, popup);
+        f.render_widget(
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .scroll((diff.scroll, 0))
+                .block(
+                    Block::default()
+                        .title(Line::from(vec![
+                            Span::raw(" "),
+                            Span::styled(diff.old_label.as_str(), Style::default().fg(Color::Red)),
+                            Span::raw(" → "),
+                            Span::styled(
+                                diff.new_label.as_str(),
+                                Style::default().fg(Color::Green),
+                            ),
+                            Span::raw(" "),
+                        ]))
+                        .title_bottom(format!(" -{removed} +{added} word(s) "))
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(Color::Yellow)),
                 ),
-                Span::raw(" to scrolling vertically"),
-            ]),
-        ];
+            popup,
+        );
+    }
 
-        let help_paragraph = Paragraph::new(help_text)
-            .block(Block::default().title("Help").borders(Borders::ALL))
-            .alignment(ratatui::layout::Alignment::Center)
-            .wrap(Wrap { trim: true });
+    /// Renders a picker near the top of the screen: the query, then the
+    /// matching items by label, each followed by its detail.
+    fn render_picker<T: Item>(
+        f: &mut Frame,
+        title: &str,
+        picker: &Picker<T>,
+        detail: impl Fn(&T) -> String,
+    ) {
+        let area = f.area();
+        let width = (area.width * 3 / 5).max(40).min(area.width);
+        let height = (area.height * 3 / 5).max(5).min(area.height);
+        let popup = Rect::new((area.width - width) / 2, area.height / 8, width, height);
+
+        let label_width = picker
+            .matches
+            .iter()
+            .map(|item| item.label().width())
+            .max()
+            .unwrap_or(0)
+            .min(width as usize / 2);
+        let items: Vec<ListItem> = picker
+            .matches
+            .iter()
+            .map(|item| {
+                ListItem::new(Line::from(vec![
+                    Span::raw(format!(" {:<label_width$}  ", item.label())),
+                    Span::styled(detail(item), Style::default().fg(Color::DarkGray)),
+                ]))
+            })
+            .collect();
+
+        f.render_widget(Clear, popup);
+        let block = Block::default()
+            .title(format!(" {title} "))
+            .title_bottom(
+                Line::from(format!(" {}/{} ", picker.matches.len(), picker.total()))
+                    .right_aligned(),
+            )
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan));
+        let inner = block.inner(popup);
+        f.render_widget(block, popup);
+        let [query_area, list_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(0)]).areas(inner);
+        f.render_widget(
+            Paragraph::new(Line::from(vec![
+                "> ".bold(),
+                Span::raw(picker.query.clone()),
+            ])),
+            query_area,
+        );
+        #[allow(clippy::cast_possible_truncation)]
+        f.set_cursor_position(Position::new(
+            query_area.x + 2 + picker.query.width() as u16,
+            query_area.y,
+        ));
+        let mut state = ListState::default().with_selected(Some(picker.selected));
+        f.render_stateful_widget(
+            List::new(items).highlight_style(Style::default().add_modifier(Modifier::REVERSED)),
+            list_area,
+            &mut state,
+        );
+    }
+
+    /// Renders the results of a session search in a centered popup, each
+    /// as the session title above a snippet with the match highlighted.
+    fn render_search(f: &mut Frame, results: &Results) {
+        let area = f.area();
+        let width = (area.width * 4 / 5).max(20).min(area.width);
+        let height = (results.hits.len() as u16 * 2 + 2).min(area.height * 4 / 5);
+        let popup = Rect::new(
+            (area.width - width) / 2,
+            area.height.saturating_sub(height) / 2,
+            width,
+            height,
+        );
+
+        let items: Vec<ListItem> = results
+            .hits
+            .iter()
+            .map(|hit| {
+                let snippet = &hit.snippet;
+                ListItem::new(vec![
+                    Line::styled(
+                        format!(" {}", hit.title),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Line::from(vec![
+                        Span::raw(format!(" {}", &snippet[..hit.matched.start])),
+                        Span::styled(
+                            &snippet[hit.matched.clone()],
+                            Style::default().fg(Color::Black).bg(Color::Yellow),
+                        ),
+                        Span::raw(&snippet[hit.matched.end..]),
+                    ]),
+                ])
+            })
+            .collect();
 
+        let mut state = ListState::default().with_selected(Some(results.selected));
+        f.render_widget(Clear, popup);
+        f.render_stateful_widget(
+            List::new(items)
+                .block(
+                    Block::default()
+                        .title(format!(" Sessions mentioning \"{}\" ", results.query))
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(Color::Yellow)),
+                )
+                .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+                .highlight_symbol(">"),
+            popup,
+            &mut state,
+        );
+    }
+
+    /// Renders the help popup, generated from the key bindings and slash
+    /// commands, scrolled to `help_scroll`.
+    #[allow(clippy::cast_possible_truncation)]
+    fn render_help(f: &mut Frame, ui_state: &mut State) {
+        let area = f.area();
+        let help_area = Rect::new(
+            area.width / 8,
+            area.height / 10,
+            area.width * 3 / 4,
+            area.height * 4 / 5,
+        );
+
+        f.render_widget(Clear, help_area);
+
+        let lines = keymap::help_lines();
+        let viewport = help_area.height.saturating_sub(2);
+        let max_scroll = (lines.len() as u16).saturating_sub(viewport);
+        ui_state.help_scroll = ui_state.help_scroll.min(max_scroll);
+
+        let help_paragraph = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .title("Help")
+                    .title_bottom(Line::from(" Up/Down to scroll, ? to close ").right_aligned())
+                    .borders(Borders::ALL),
+            )
+            .scroll((ui_state.help_scroll, 0));
         f.render_widget(help_paragraph, help_area);
+
+        let mut scroll_state = ScrollbarState::new(max_scroll as usize + 1)
+            .viewport_content_length(viewport as usize)
+            .position(ui_state.help_scroll as usize);
+        f.render_stateful_widget(
+            Self::scrollbar(
+                ScrollbarOrientation::VerticalRight,
+                ui_state.capabilities.unicode,
+            ),
+            help_area.inner(Margin::new(0, 1)),
+            &mut scroll_state,
+        );
+    }
+
+    /// Lays out text exactly as written, keeping its indentation and
+    /// spaces, and cutting lines longer than `max_width` columns.
+    fn raw_lines(text: &str, max_width: usize) -> Vec<Line<'static>> {
+        let max_width = max_width.max(1);
+        let mut lines = Vec::new();
+        for line in text.lines() {
+            let mut part = String::new();
+            let mut part_width = 0;
+            for c in line.chars() {
+                let width = c.width().unwrap_or(0);
+                if part_width + width > max_width && !part.is_empty() {
+                    lines.push(Line::raw(std::mem::take(&mut part)));
+                    part_width = 0;
+                }
+                part.push(c);
+                part_width += width;
+            }
+            lines.push(Line::raw(part));
+        }
+        lines
     }
 
     fn wrap_text(text: &str, max_width: usize) -> Vec<String> {
@@ -0,0 +1,193 @@
+//! Pages the oldest messages of long sessions out to disk.
+//!
+//! With `max_scrollback` set, the transcript keeps at most that many
+//! messages in memory. Older ones are appended to a file in
+//! `~/.config/chatti/scrollback`, one JSON message per line, and read back
+//! only when they are needed: when the pager is opened, when the session is
+//! saved and when a prompt is sent. The file is created fresh, readable only
+//! by the user, and removed when the transcript is replaced or the
+//! application exits.
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    process,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{message::Message, tokens::estimate_tokens};
+
+/// The messages paged out of the transcript.
+#[derive(Debug)]
+pub struct Scrollback {
+    limit: Option<usize>,
+    path: PathBuf,
+    archived: usize,
+    tokens: usize,
+}
+
+impl Default for Scrollback {
+    fn default() -> Self {
+        Scrollback::new(None)
+    }
+}
+
+impl Scrollback {
+    /// Creates an empty scrollback kept in [`default_directory`].
+    ///
+    /// # Arguments
+    ///
+    /// * `limit` - How many messages the transcript keeps in memory, or
+    ///   `None` to keep them all.
+    #[must_use]
+    pub fn new(limit: Option<usize>) -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.subsec_nanos());
+        let path = default_directory().join(format!("{}-{nanos}.jsonl", process::id()));
+        Scrollback::with_path(limit, path)
+    }
+
+    /// Creates an empty scrollback kept in the given file, which must not
+    /// exist yet when the first messages are paged out.
+    #[must_use]
+    pub fn with_path(limit: Option<usize>, path: PathBuf) -> Self {
+        Scrollback {
+            limit,
+            path,
+            archived: 0,
+            tokens: 0,
+        }
+    }
+
+    /// Returns the file the messages are kept in.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns how many of `live` messages in memory are beyond the limit.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::scrollback::Scrollback;
+    ///
+    /// assert_eq!(Scrollback::new(Some(100)).excess(130), 30);
+    /// assert_eq!(Scrollback::new(Some(100)).excess(80), 0);
+    /// assert_eq!(Scrollback::new(None).excess(10_000), 0);
+    /// ```
+    #[must_use]
+    pub fn excess(&self, live: usize) -> usize {
+        self.limit.map_or(0, |limit| live.saturating_sub(limit))
+    }
+
+    /// Returns the number of messages paged out.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.archived
+    }
+
+    /// Returns `true` if no messages were paged out.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.archived == 0
+    }
+
+    /// Returns the estimated tokens of the paged-out messages that are sent
+    /// with prompts.
+    #[must_use]
+    pub fn tokens(&self) -> usize {
+        self.tokens
+    }
+
+    /// Appends messages, oldest first, to the file, creating it for the
+    /// first ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written, or if it already
+    /// exists when the first messages are paged out.
+    pub fn archive(&mut self, messages: &[Message]) -> io::Result<()> {
+        if messages.is_empty() {
+            return Ok(());
+        }
+        let mut text = String::new();
+        for message in messages {
+            text.push_str(&serde_json::to_string(message)?);
+            text.push('\n');
+        }
+        let mut options = fs::OpenOptions::new();
+        if self.is_empty() {
+            if let Some(parent) = self.path.parent() {
+                create_private_dir(parent)?;
+            }
+            options.create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        }
+        options
+            .append(true)
+            .open(&self.path)?
+            .write_all(text.as_bytes())?;
+        self.archived += messages.len();
+        self.tokens += messages
+            .iter()
+            .filter(|message| message.is_sent())
+            .map(|message| estimate_tokens(&message.request_content()))
+            .sum::<usize>();
+        Ok(())
+    }
+
+    /// Reads the paged-out messages back, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or holds a message that
+    /// cannot be parsed.
+    pub fn load(&self) -> io::Result<Vec<Message>> {
+        if self.is_empty() {
+            return Ok(Vec::new());
+        }
+        BufReader::new(fs::File::open(&self.path)?)
+            .lines()
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect()
+    }
+
+    /// Forgets the paged-out messages and removes the file.
+    pub fn clear(&mut self) {
+        if !self.is_empty() {
+            let _ = fs::remove_file(&self.path);
+        }
+        self.archived = 0;
+        self.tokens = 0;
+    }
+}
+
+/// Returns the directory paged-out messages are kept in,
+/// `~/.config/chatti/scrollback`.
+#[must_use]
+pub fn default_directory() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(".config")
+        .join("chatti")
+        .join("scrollback")
+}
+
+/// Creates a directory, and its parents, that only the user can enter.
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+impl Drop for Scrollback {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
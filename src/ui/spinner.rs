@@ -1,9 +1,14 @@
 //! Provides a simple text-based spinner for indicating progress.
 
+use std::time::{Duration, Instant};
+
+use crate::config::{SpinnerConfig, SpinnerStyle};
+use crate::tokens::estimate_tokens;
+
 /// A simple text-based spinner for indicating progress
 #[derive(Default, Debug)]
 pub struct Spinner {
-    frames: Vec<char>,
+    frames: Vec<String>,
     current: usize,
 }
 
@@ -23,17 +28,83 @@ impl Spinner {
     /// ```
     #[must_use]
     pub fn new() -> Self {
+        Spinner::with_style(SpinnerStyle::Braille)
+    }
+
+    /// Creates a spinner that only uses ASCII characters, for terminals that
+    /// cannot display braille glyphs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::spinner::Spinner;
+    ///
+    /// let mut spinner = Spinner::ascii();
+    /// assert!(spinner.next_frame().is_ascii());
+    /// ```
+    #[must_use]
+    pub fn ascii() -> Self {
+        Spinner::with_style(SpinnerStyle::Ascii)
+    }
+
+    /// Creates a spinner with a built-in frame set.
+    #[must_use]
+    pub fn with_style(style: SpinnerStyle) -> Self {
+        let frames: &[&str] = match style {
+            SpinnerStyle::Braille => &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+            SpinnerStyle::Dots => &["·  ", "·· ", "···", " ··", "  ·", "   "],
+            SpinnerStyle::Ascii => &["|", "/", "-", "\\"],
+        };
         Spinner {
-            frames: vec!['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'],
+            frames: frames.iter().map(ToString::to_string).collect(),
             current: 0,
         }
     }
 
+    /// Creates the configured spinner.
+    ///
+    /// Custom frames are used as they are; a built-in style other than
+    /// ASCII falls back to ASCII if the terminal cannot display unicode.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The spinner configuration.
+    /// * `unicode` - Whether the terminal can display unicode glyphs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::config::SpinnerConfig;
+    /// use chatti::ui::spinner::Spinner;
+    ///
+    /// let config = SpinnerConfig {
+    ///     frames: vec!["[=  ]".to_string(), "[ = ]".to_string(), "[  =]".to_string()],
+    ///     ..SpinnerConfig::default()
+    /// };
+    /// let mut spinner = Spinner::from_config(&config, false);
+    /// assert_eq!(spinner.next_frame(), "[=  ]");
+    /// assert_eq!(spinner.next_frame(), "[ = ]");
+    /// ```
+    #[must_use]
+    pub fn from_config(config: &SpinnerConfig, unicode: bool) -> Self {
+        if !config.frames.is_empty() {
+            return Spinner {
+                frames: config.frames.clone(),
+                current: 0,
+            };
+        }
+        if unicode {
+            Spinner::with_style(config.style)
+        } else {
+            Spinner::ascii()
+        }
+    }
+
     /// Returns the next frame of the spinner.
     ///
     /// # Returns
     ///
-    /// A `char` representing the next frame of the spinner.
+    /// The text of the next frame of the spinner.
     ///
     /// # Examples
     ///
@@ -45,9 +116,93 @@ impl Spinner {
     /// let frame2 = spinner.next_frame();
     /// assert_ne!(frame1, frame2);
     /// ```
-    pub fn next_frame(&mut self) -> char {
-        let char = self.frames[self.current];
+    pub fn next_frame(&mut self) -> String {
+        let frame = self.frames[self.current].clone();
         self.current = (self.current + 1) % self.frames.len();
-        char
+        frame
+    }
+}
+
+/// Tracks how long a response has been generating and how much of it has
+/// arrived, for display next to the spinner.
+#[derive(Debug, Clone)]
+pub struct Progress {
+    started: Instant,
+    first_token: Option<Duration>,
+    received: String,
+    tokens: usize,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Progress::start()
+    }
+}
+
+impl Progress {
+    /// Starts tracking a response.
+    #[must_use]
+    pub fn start() -> Self {
+        Progress {
+            started: Instant::now(),
+            first_token: None,
+            received: String::new(),
+            tokens: 0,
+        }
+    }
+
+    /// Records a received piece of the response. The tokens are estimated
+    /// from all of it received so far, as pieces split words.
+    pub fn record(&mut self, content: &str) {
+        self.first_token
+            .get_or_insert_with(|| self.started.elapsed());
+        self.received.push_str(content);
+        self.tokens = estimate_tokens(&self.received);
+    }
+
+    /// Returns the estimated number of tokens received.
+    #[must_use]
+    pub fn tokens(&self) -> usize {
+        self.tokens
+    }
+
+    /// Returns the time since the response was requested.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Returns the time from the request to the first piece of the
+    /// response, if one arrived.
+    #[must_use]
+    pub fn first_token(&self) -> Option<Duration> {
+        self.first_token
+    }
+
+    /// Returns the estimated number of tokens received per second so far.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn tokens_per_second(&self) -> f64 {
+        let elapsed = self.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.tokens as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    /// Describes the progress, such as `3.2s · 45 tokens · 14.1 tok/s`. The
+    /// speed is left out until the first token arrives.
+    #[must_use]
+    pub fn describe(&self) -> String {
+        let elapsed = format!("{:.1}s", self.elapsed().as_secs_f64());
+        if self.tokens == 0 {
+            return elapsed;
+        }
+        format!(
+            "{elapsed} · {} tokens · {:.1} tok/s",
+            self.tokens,
+            self.tokens_per_second()
+        )
     }
 }
@@ -1,21 +1,149 @@
 //! Manages the state of the user interface.
 
-use super::spinner::Spinner;
+use super::capabilities::Capabilities;
+use super::compare::Comparison;
+use super::completion::Completion;
+use super::context_view::{ContextView, Section, NEXT_MESSAGE};
+use super::dialog::Dialog;
+use super::diff::DiffView;
+use super::graphics::Graphics;
+use super::layout::LayoutCache;
+use super::markdown_renderer::MarkdownRenderer;
+use super::pager::Pager;
+use super::palette::Palette;
+use super::params::{Params, ParamsEditor, Retry};
+use super::scrollback::Scrollback;
+use super::spinner::{Progress, Spinner};
+use super::switcher::Switcher;
+use super::tasks::Tasks;
+use super::theme::Theme;
+use super::undo::{Edit, UndoStack};
+use crate::{
+    api::EndpointStatus,
+    continuation,
+    diagrams::{self, Diagram},
+    error::ErrorDetails,
+    history::{HistorySearch, InputHistory},
+    lint::Warning,
+    mentions,
+    message::{Generation, Mark, MarkFilter, Message, Metadata},
+    plugins::{self, PluginError, Reply},
+    ratelimit::RateLimiter,
+    redact::Redactor,
+    search::{Hit, Results},
+    session::{Recovery, Session},
+    speech::Speaker,
+    store::SessionInfo,
+    structured::{self, JsonOutput},
+    tables::{Sample, Sampling},
+    tokens::{estimate_cost, estimate_tokens},
+    translate,
+};
 use ratatui::widgets::{ListState, ScrollbarState};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::sync::{mpsc, watch};
+use tracing::error;
+use unicode_width::UnicodeWidthStr;
+
+/// How long the input border flashes after an invalid send.
+pub const INPUT_FLASH_DURATION: Duration = Duration::from_millis(600);
+
+/// How long `u` and `/clear undo` bring back a cleared conversation.
+pub const CLEAR_UNDO_WINDOW: Duration = Duration::from_secs(10);
+
+/// How many lines of a message a quoted reply quotes at most.
+pub const QUOTE_LINES: usize = 4;
+
+/// The instruction sent to continue an answer cut off at the token limit.
+pub const CONTINUE_PROMPT: &str = "Continue your last answer exactly where it stopped, \
+     without repeating any of it or adding an introduction.";
 
 /// Represents the current state of the user interface.
 #[derive(Default, Debug)]
 pub struct State {
+    pub answer_language: Option<String>,
+    pub awaiting_cost_confirmation: bool,
+    pub capabilities: Capabilities,
+    pub clear_undo_requested: bool,
+    pub cleared: Option<Cleared>,
+    pub compare_mode: bool,
+    pub compare_models: Option<[String; 2]>,
+    pub comparison: Option<Comparison>,
+    pub completion: Option<Completion>,
+    pub connectivity: Option<watch::Receiver<bool>>,
+    pub context_budget: usize,
+    pub context_requested: bool,
+    pub context_view: Option<ContextView>,
+    pub continue_requested: bool,
+    pub control: Option<mpsc::Receiver<String>>,
+    pub cost_confirm_threshold: Option<f64>,
     pub current_response: String,
+    pub default_system_prompt: Option<String>,
+    pub details: Option<Vec<(&'static str, String)>>,
+    pub diagrams_requested: bool,
+    pub dialog: Option<Dialog>,
+    pub diff: Option<DiffView>,
+    pub dry_run: bool,
+    pub endpoint: Option<watch::Receiver<EndpointStatus>>,
+    pub follow: bool,
+    pub graphics: Option<Graphics>,
+    pub help_scroll: u16,
+    pub history: InputHistory,
+    pub history_draft: String,
+    pub history_file: Option<PathBuf>,
+    pub history_index: Option<usize>,
+    pub history_search: Option<HistorySearch>,
     pub horizontal_scroll_state: ScrollbarState,
     pub horizontal_scroll: usize,
     pub input: String,
+    pub input_flash: Option<Instant>,
     pub input_mode: InputMode,
     pub input_width: u16,
+    pub interjection: Option<String>,
+    pub json_output: Option<JsonOutput>,
+    pub last_read: usize,
+    pub last_send: Option<Instant>,
+    pub layout: LayoutCache,
+    pub limiter: Option<RateLimiter>,
+    pub lint_warnings: Vec<Warning>,
     pub list_state: ListState,
-    pub messages: Vec<(String, String)>,
+    pub messages: Vec<Message>,
+    pub messages_height: u16,
+    pub pager: Pager,
+    pub palette: Option<Palette>,
+    pub params: Params,
+    pub params_editor: Option<ParamsEditor>,
+    pub pending_context: Option<String>,
+    pub pending_images: Vec<PathBuf>,
+    pub plain_view: bool,
+    pub plugin_replies: Option<mpsc::UnboundedReceiver<Result<Reply, PluginError>>>,
+    pub progress: Option<Progress>,
+    pub prompt_price: Option<f64>,
+    pub queue_offline: bool,
+    pub retry: Option<Retry>,
+    pub sampling: Sampling,
+    pub scrollback: Scrollback,
+    pub search_hit: Option<Hit>,
+    pub search_results: Option<Results>,
+    pub secret_scan: Option<Redactor>,
+    pub session_id: Option<String>,
+    pub shell_allowed: Vec<String>,
+    pub shell_preview: Option<String>,
+    pub show_progress: bool,
     pub show_toggle: bool,
+    pub speaker: Option<Speaker>,
     pub spinner: Spinner,
+    pub substitutions_requested: bool,
+    pub switch_to: Option<SessionInfo>,
+    pub switcher: Option<Switcher>,
+    pub system_prompt: Option<String>,
+    pub table_preview: Option<(String, Sample)>,
+    pub tasks: Tasks,
+    pub theme: Theme,
+    pub undo: UndoStack,
     pub vertical_scroll_state: ScrollbarState,
     pub quit: bool,
 }
@@ -32,35 +160,182 @@ impl State {
         list_state.select(Some(0));
 
         State {
+            answer_language: None,
+            awaiting_cost_confirmation: false,
+            capabilities: Capabilities::default(),
+            clear_undo_requested: false,
+            cleared: None,
+            compare_mode: false,
+            compare_models: None,
+            comparison: None,
+            completion: None,
+            connectivity: None,
+            context_budget: 4096,
+            context_requested: false,
+            context_view: None,
+            continue_requested: false,
+            control: None,
+            cost_confirm_threshold: None,
             current_response: String::new(),
+            default_system_prompt: None,
+            details: None,
+            diagrams_requested: false,
+            dialog: None,
+            diff: None,
+            dry_run: false,
+            endpoint: None,
+            follow: true,
+            graphics: None,
+            help_scroll: 0,
+            history: InputHistory::default(),
+            history_draft: String::new(),
+            history_file: None,
+            history_index: None,
+            history_search: None,
             horizontal_scroll_state: ScrollbarState::default(),
             horizontal_scroll: 0,
             input: String::new(),
+            input_flash: None,
             input_mode: InputMode::Normal,
             input_width: 0,
+            interjection: None,
+            json_output: None,
+            last_read: 0,
+            last_send: None,
+            layout: LayoutCache::default(),
+            limiter: None,
+            lint_warnings: Vec::new(),
             list_state,
             messages: Vec::new(),
+            messages_height: 0,
+            pager: Pager::new(),
+            palette: None,
+            params: Params::default(),
+            params_editor: None,
+            pending_context: None,
+            pending_images: Vec::new(),
+            plain_view: false,
+            plugin_replies: None,
+            progress: None,
+            prompt_price: None,
+            queue_offline: false,
+            retry: None,
+            sampling: Sampling::default(),
+            scrollback: Scrollback::default(),
+            search_hit: None,
+            search_results: None,
+            secret_scan: None,
+            session_id: None,
+            shell_allowed: Vec::new(),
+            shell_preview: None,
+            show_progress: true,
             show_toggle: false,
+            speaker: None,
             spinner: Spinner::new(),
+            substitutions_requested: false,
+            switch_to: None,
+            switcher: None,
+            system_prompt: None,
+            table_preview: None,
+            tasks: Tasks::default(),
+            theme: Theme::default(),
+            undo: UndoStack::default(),
             vertical_scroll_state: ScrollbarState::default(),
             quit: false,
         }
     }
 
-    /// Scrolls the message list up by one item.
+    /// Flashes the input border to signal an invalid send.
+    pub fn flash_input(&mut self) {
+        self.input_flash = Some(Instant::now());
+    }
+
+    /// Returns `true` while the input border should be shown as invalid.
+    #[must_use]
+    pub fn is_input_flashing(&self) -> bool {
+        self.input_flash
+            .is_some_and(|flash| flash.elapsed() < INPUT_FLASH_DURATION)
+    }
+
+    /// Scrolls the message list up by one item and stops following new
+    /// output.
     pub fn scroll_up(&mut self) {
         let current = self.list_state.selected().unwrap_or(0);
         let next = current.saturating_sub(1);
-        self.list_state.select(Some(next));
-        self.vertical_scroll_state = self.vertical_scroll_state.position(next);
+        self.select(next);
+        self.follow = false;
     }
 
-    /// Scrolls the message list down by one item.
+    /// Scrolls the message list down by one item. Reaching the last message
+    /// resumes following new output.
     pub fn scroll_down(&mut self) {
+        let last = self.messages.len().saturating_sub(1);
+        let current = self.list_state.selected().unwrap_or(0);
+        let next = (current + 1).min(last);
+        self.select(next);
+        self.follow = next == last;
+    }
+
+    /// Jumps to the last message and resumes following new output.
+    pub fn follow_latest(&mut self) {
+        self.follow = true;
+        self.select(self.messages.len().saturating_sub(1));
+    }
+
+    /// Selects the last message if following new output.
+    fn follow_tail(&mut self) {
+        if self.follow {
+            self.select(self.messages.len().saturating_sub(1));
+        }
+    }
+
+    /// Scrolls the message list up by about one screen of rendered lines.
+    pub fn page_up(&mut self) {
+        let current = self.list_state.selected().unwrap_or(0);
+        let target = self
+            .layout
+            .range(current)
+            .map(|range| range.start.saturating_sub(self.messages_height as usize))
+            .and_then(|line| self.layout.message_at_line(line))
+            .unwrap_or(current);
+        self.select(if target == current {
+            current.saturating_sub(1)
+        } else {
+            target
+        });
+        self.follow = false;
+    }
+
+    /// Scrolls the message list down by about one screen of rendered lines.
+    /// Reaching the last message resumes following new output.
+    pub fn page_down(&mut self) {
+        let last = self.messages.len().saturating_sub(1);
         let current = self.list_state.selected().unwrap_or(0);
-        let next = (current + 1).min(self.messages.len().saturating_sub(1));
-        self.list_state.select(Some(next));
-        self.vertical_scroll_state = self.vertical_scroll_state.position(next);
+        let target = self
+            .layout
+            .range(current)
+            .map(|range| range.start + self.messages_height as usize)
+            .and_then(|line| self.layout.message_at_line(line))
+            .unwrap_or(current);
+        let next = if target == current {
+            current + 1
+        } else {
+            target
+        }
+        .min(last);
+        self.select(next);
+        self.follow = next == last;
+    }
+
+    fn select(&mut self, index: usize) {
+        self.list_state.select(Some(index));
+        let read = self
+            .messages
+            .iter()
+            .take(index + 1)
+            .filter(|m| in_conversation(m))
+            .count();
+        self.last_read = self.last_read.max(self.scrollback.len() + read);
     }
 
     /// Updates the current response with new content.
@@ -71,26 +346,20 @@ impl State {
     pub fn update_response(&mut self, new_content: &str) {
         if self.input_mode == InputMode::Waiting {
             self.current_response.push_str(new_content);
+            if let Some(progress) = self.progress.as_mut() {
+                progress.record(new_content);
+            }
 
-            if let Some((role, content)) = self.messages.last_mut() {
-                if role == "assistant" {
-                    content.clone_from(&self.current_response);
-                } else {
-                    self.messages
-                        .push(("assistant".to_string(), self.current_response.clone()));
+            match self.messages.last_mut() {
+                Some(message) if message.role == "assistant" => {
+                    message.content.clone_from(&self.current_response);
                 }
-            } else {
-                self.messages
-                    .push(("assistant".to_string(), self.current_response.clone()));
+                _ => self
+                    .messages
+                    .push(Message::new("assistant", self.current_response.clone())),
             }
 
-            let is_at_bottom = self.list_state.selected() == Some(self.messages.len() - 1);
-
-            if is_at_bottom {
-                self.list_state.select(Some(self.messages.len() - 1));
-                self.vertical_scroll_state =
-                    self.vertical_scroll_state.position(self.messages.len() - 1);
-            }
+            self.follow_tail();
         }
     }
 
@@ -100,49 +369,1805 @@ impl State {
     ///
     /// * `response` - A string containing the complete response to be added.
     pub fn add_response(&mut self, response: String) {
+        self.finish_response(Message::new("assistant", response));
+    }
+
+    /// Records the model and parameters that produced the latest response.
+    ///
+    /// # Arguments
+    ///
+    /// * `generation` - The model and parameters of the request.
+    pub fn annotate_generation(&mut self, generation: Generation) {
+        if let Some(message) = self
+            .messages
+            .iter_mut()
+            .rev()
+            .find(|m| m.role == "assistant")
+        {
+            message.generation = Some(generation);
+        }
+    }
+
+    /// Records what the server reported about the latest response.
+    ///
+    /// # Arguments
+    ///
+    /// * `metadata` - The token counts, finish reason and request id.
+    pub fn annotate_metadata(&mut self, metadata: Metadata) {
+        if let Some(index) = self.messages.iter().rposition(|m| m.role == "assistant") {
+            self.annotate_metadata_at(index, metadata);
+        }
+    }
+
+    /// Records what the server reported about the response in a message
+    /// inserted by a retry or a translation.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The index of the message.
+    /// * `metadata` - The token counts, finish reason and request id.
+    pub fn annotate_metadata_at(&mut self, slot: usize, metadata: Metadata) {
+        if let Some(message) = self.messages.get_mut(slot) {
+            message
+                .metadata
+                .get_or_insert_with(Metadata::default)
+                .merge(metadata);
+        }
+    }
+
+    /// Records the excerpts retrieved as context on the latest response.
+    ///
+    /// # Arguments
+    ///
+    /// * `sources` - The files and lines of the excerpts.
+    pub fn annotate_sources(&mut self, sources: Vec<String>) {
+        if let Some(message) = self
+            .messages
+            .iter_mut()
+            .rev()
+            .find(|m| m.role == "assistant")
+        {
+            message.sources = sources;
+        }
+    }
+
+    /// Stops the current generation, keeping the partial response as a
+    /// normal assistant message marked as stopped.
+    ///
+    /// In a comparison, both partial responses are kept to choose from.
+    pub fn stop_response(&mut self) {
+        if self.input_mode != InputMode::Waiting {
+            return;
+        }
+        if self.comparison.is_some() {
+            self.input_mode = InputMode::Compare;
+            return;
+        }
+
+        let partial = std::mem::take(&mut self.current_response);
+        if partial.is_empty() {
+            self.finish_response(Message::new(
+                "system",
+                "Generation stopped before any output",
+            ));
+        } else {
+            self.finish_response(Message {
+                stopped: true,
+                ..Message::new("assistant", partial)
+            });
+        }
+    }
+
+    /// Returns `true` if a follow-up was typed while a single response
+    /// streams, so it can interrupt the response.
+    #[must_use]
+    pub fn can_interject(&self) -> bool {
+        let follow_up = self.input.trim();
+        self.input_mode == InputMode::Waiting
+            && self.comparison.is_none()
+            && !follow_up.is_empty()
+            && !follow_up.starts_with('/')
+    }
+
+    /// Queues the follow-up typed while the response streamed, to be sent
+    /// once the response is stopped.
+    pub fn queue_interjection(&mut self) {
+        let input = std::mem::take(&mut self.input);
+        if !input.trim().is_empty() {
+            self.remember_input(&input);
+            self.interjection = Some(input.trim().to_string());
+        }
+    }
+
+    /// Submits the queued follow-up as a prompt, with the stopped answer
+    /// before it in the conversation.
+    ///
+    /// # Returns
+    ///
+    /// The follow-up to send, or `None` if none is queued.
+    pub fn take_interjection(&mut self) -> Option<String> {
+        let prompt = self.interjection.take()?;
+        self.submit_prompt(prompt.clone());
+        self.follow_latest();
+        Some(prompt)
+    }
+
+    /// Cancels the current request, discarding any partial response and
+    /// leaving its prompt out of later requests.
+    pub fn cancel_response(&mut self) {
+        // Left in, the unanswered prompt would be followed by the next one
+        // in the conversation sent.
         if self.input_mode == InputMode::Waiting {
-            self.messages.pop();
+            if let Some(prompt) = self.messages.iter_mut().rev().find(|m| m.role == "user") {
+                prompt.cancelled = true;
+            }
+        }
+        self.comparison = None;
+        self.finish_response(Message::new("system", "Request cancelled"));
+    }
 
-            if let Some((role, content)) = self.messages.last() {
-                if role == "system" && content == "Generating..." {
-                    self.messages.pop();
-                }
+    /// Starts comparing the responses of the configured models to the
+    /// prompt just sent.
+    ///
+    /// # Returns
+    ///
+    /// The two models to send the prompt to, or `None` if compare mode is off
+    /// or fewer than two models are configured.
+    pub fn start_comparison(&mut self) -> Option<[String; 2]> {
+        if !self.compare_mode {
+            return None;
+        }
+        let models = self.compare_models.clone()?;
+        self.comparison = Some(Comparison::new(models.clone()));
+        self.input_mode = InputMode::Waiting;
+        self.progress = Some(Progress::start());
+        Some(models)
+    }
+
+    /// Appends streamed content to one side of the comparison.
+    ///
+    /// # Arguments
+    ///
+    /// * `side` - `0` for the left model, `1` for the right one.
+    /// * `content` - The new content.
+    pub fn update_comparison(&mut self, side: usize, content: &str) {
+        if let Some(comparison) = self.comparison.as_mut() {
+            comparison.push(side, content);
+        }
+        if let Some(progress) = self.progress.as_mut() {
+            progress.record(content);
+        }
+    }
+
+    /// Marks one side of the comparison as finished. Once both are, the user
+    /// chooses which answer to keep.
+    pub fn finish_comparison(&mut self, side: usize) {
+        if let Some(comparison) = self.comparison.as_mut() {
+            comparison.finish(side);
+            if comparison.is_done() && self.input_mode == InputMode::Waiting {
+                self.input_mode = InputMode::Compare;
             }
         }
-        self.messages.push(("assistant".to_string(), response));
+    }
+
+    /// Keeps one side of the comparison as the answer in the transcript.
+    ///
+    /// # Arguments
+    ///
+    /// * `side` - `0` for the left model, `1` for the right one, or `None` to
+    ///   discard both answers.
+    pub fn keep_comparison(&mut self, side: Option<usize>) {
+        let Some(comparison) = self.comparison.take() else {
+            return;
+        };
         self.input_mode = InputMode::Normal;
-        self.current_response.clear();
 
-        self.list_state.select(Some(self.messages.len() - 1));
-        self.vertical_scroll_state = self.vertical_scroll_state.position(self.messages.len() - 1);
+        let kept = side.and_then(|side| {
+            let response = comparison.responses.get(side)?;
+            (!response.is_empty()).then(|| Message::new("assistant", response.clone()))
+        });
+        self.finish_response(
+            kept.unwrap_or_else(|| Message::new("system", "Comparison discarded")),
+        );
+    }
+
+    /// Opens the popup for adjusting the sampling parameters.
+    pub fn open_params(&mut self) {
+        self.params_editor = Some(ParamsEditor::new(self.params));
+        self.input_mode = InputMode::Params;
+    }
 
-        self.horizontal_scroll = 0;
-        self.horizontal_scroll_state = ScrollbarState::default();
+    /// Opens the popup for retrying the selected answer, starting from the
+    /// model and parameters that produced it.
+    pub fn open_retry(&mut self) {
+        let selected = self.list_state.selected().filter(|&index| {
+            self.messages
+                .get(index)
+                .is_some_and(|m| m.role == "assistant" && !m.content.is_empty())
+        });
+        let Some(index) = selected else {
+            self.add_system_message(String::from("Select an answer to retry it"));
+            return;
+        };
+        let (model, params) = self.messages[index].generation.as_ref().map_or(
+            (String::new(), self.params),
+            |generation| {
+                (
+                    generation.model.clone(),
+                    Params::from_generation(generation),
+                )
+            },
+        );
+        self.params_editor = Some(ParamsEditor::retry(index, model, params));
+        self.input_mode = InputMode::Params;
     }
 
-    /// Prepares the UI state for a new response.
-    pub fn start_new_response(&mut self) {
+    /// Closes the parameters popup.
+    ///
+    /// # Arguments
+    ///
+    /// * `apply` - Whether the adjusted parameters are used for the
+    ///   following requests, or the answer is retried with them; otherwise
+    ///   they are discarded.
+    pub fn close_params(&mut self, apply: bool) {
+        let Some(editor) = self.params_editor.take() else {
+            return;
+        };
+        self.input_mode = InputMode::Normal;
+        if editor.retry.is_some() {
+            if apply {
+                self.retry = editor.into_retry();
+            }
+            return;
+        }
+        if apply && editor.draft != self.params {
+            self.params = editor.draft;
+            self.add_system_message(format!("Using {}", self.params.describe()));
+        }
+    }
+
+    /// Opens the popup listing the results of a session search.
+    ///
+    /// # Arguments
+    ///
+    /// * `results` - The matching messages of the saved sessions.
+    pub fn open_search(&mut self, results: Results) {
+        self.search_results = Some(results);
+        self.input_mode = InputMode::Search;
+    }
+
+    /// Closes the session search popup.
+    ///
+    /// # Arguments
+    ///
+    /// * `open` - Whether the session of the highlighted result is opened.
+    pub fn close_search(&mut self, open: bool) {
+        let Some(results) = self.search_results.take() else {
+            return;
+        };
+        self.input_mode = InputMode::Normal;
+        if open {
+            self.search_hit = results.selected().cloned();
+        }
+    }
+
+    /// Selects a message of a freshly loaded session by its position in
+    /// the session, and stops following new output.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The position of the message, counting the messages paged
+    ///   out to disk.
+    pub fn jump_to_message(&mut self, index: usize) {
+        let Some(shown) = index.checked_sub(self.scrollback.len()) else {
+            self.add_system_message(String::from(
+                "The message was paged out of the transcript to disk",
+            ));
+            return;
+        };
+        // Saved sessions only hold the conversation, so notices shown
+        // between its messages are not counted.
+        let found = self
+            .messages
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| in_conversation(m))
+            .nth(shown);
+        if let Some((position, _)) = found {
+            self.select(position);
+            self.follow = false;
+        }
+    }
+
+    /// Marks the messages of a freshly loaded session added since it was
+    /// last read as new, showing a separator above the first of them.
+    ///
+    /// # Arguments
+    ///
+    /// * `last_read` - How many messages had been read when the session
+    ///   was last open.
+    ///
+    /// # Returns
+    ///
+    /// The number of new messages.
+    pub fn mark_unread(&mut self, last_read: Option<usize>) -> usize {
+        let total =
+            self.scrollback.len() + self.messages.iter().filter(|m| in_conversation(m)).count();
+        let Some(read) = last_read.filter(|&read| read < total) else {
+            return 0;
+        };
+        let first = self
+            .messages
+            .iter_mut()
+            .filter(|m| in_conversation(m))
+            .nth(read.saturating_sub(self.scrollback.len()));
+        if let Some(message) = first {
+            message.first_unread = true;
+        }
+        total - read
+    }
+
+    /// Selects the first message added since the session was last read.
+    pub fn jump_to_unread(&mut self) {
+        match self.messages.iter().position(|m| m.first_unread) {
+            Some(index) => {
+                self.select(index);
+                self.follow = false;
+            }
+            None => self.add_system_message(String::from("There are no new messages")),
+        }
+    }
+
+    /// Returns the attempt at an answer made before the given one by a
+    /// retry: the answer right above it, as retries are inserted below the
+    /// answers they retry.
+    #[must_use]
+    pub fn previous_attempt(&self, index: usize) -> Option<usize> {
+        let is_attempt = |m: &Message| m.role == "assistant" && m.compared;
+        let previous = index.checked_sub(1)?;
+        (self.messages.get(index).is_some_and(is_attempt)
+            && self.messages.get(previous).is_some_and(is_attempt))
+        .then_some(previous)
+    }
+
+    /// Opens the popup diffing the selected answer against the attempt
+    /// before it.
+    pub fn open_diff(&mut self) {
+        let selected = self.list_state.selected().unwrap_or(0);
+        let Some(previous) = self.previous_attempt(selected) else {
+            self.add_system_message(String::from(
+                "Select a retried answer to see what changed from the attempt before it",
+            ));
+            return;
+        };
+        let label = |index: usize, fallback: &str| {
+            self.messages[index]
+                .generation
+                .as_ref()
+                .map_or_else(|| fallback.to_string(), Generation::describe)
+        };
+        self.diff = Some(DiffView::new(
+            label(previous, "previous attempt"),
+            &self.messages[previous].content,
+            label(selected, "this attempt"),
+            &self.messages[selected].content,
+        ));
+        self.input_mode = InputMode::Diff;
+    }
+
+    /// Opens the popup with the model, parameters, token counts and timing
+    /// of the selected answer.
+    pub fn open_details(&mut self) {
+        let selected = self.list_state.selected().unwrap_or(0);
+        match self.messages.get(selected) {
+            Some(message) if message.role == "assistant" => {
+                self.details = Some(message.details());
+                self.input_mode = InputMode::Details;
+            }
+            _ => self
+                .add_system_message(String::from("Select an answer to see how it was generated")),
+        }
+    }
+
+    /// Asks for the diagrams of the selected answer, or of the latest
+    /// answer with diagrams, to be rendered. Rendering runs external
+    /// commands, so the request is handled outside of the input loop.
+    pub fn request_diagrams(&mut self) {
+        if self.diagram_answer().is_some() {
+            self.diagrams_requested = true;
+        } else {
+            self.add_system_message(String::from(
+                "There is no answer with a Mermaid or Graphviz diagram",
+            ));
+        }
+    }
+
+    /// Takes a request to render diagrams, if one was made.
+    pub fn take_diagrams_request(&mut self) -> bool {
+        std::mem::take(&mut self.diagrams_requested)
+    }
+
+    /// Returns the index and the diagrams of the selected answer, or of the
+    /// latest answer with diagrams if the selected message has none.
+    #[must_use]
+    pub fn diagram_answer(&self) -> Option<(usize, Vec<Diagram>)> {
+        let diagrams_of = |index: usize| {
+            let message = self.messages.get(index)?;
+            let found = diagrams::find(&message.content);
+            (message.role == "assistant" && !found.is_empty()).then_some((index, found))
+        };
+        self.list_state
+            .selected()
+            .and_then(diagrams_of)
+            .or_else(|| (0..self.messages.len()).rev().find_map(diagrams_of))
+    }
+
+    /// Returns the code blocks and tables of the selected answer, or of the
+    /// latest answer with any if the selected message has none, numbered
+    /// from 1 as they are rendered.
+    #[must_use]
+    pub fn code_block_answer(&self) -> Option<Vec<String>> {
+        let blocks_of = |index: usize| {
+            let message: &Message = self.messages.get(index)?;
+            let blocks = MarkdownRenderer::code_blocks(&message.content);
+            (message.role == "assistant" && !blocks.is_empty()).then_some(blocks)
+        };
+        self.list_state
+            .selected()
+            .and_then(blocks_of)
+            .or_else(|| (0..self.messages.len()).rev().find_map(blocks_of))
+    }
+
+    /// Shows the images the diagrams of an answer were rendered into below
+    /// it.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the answer.
+    /// * `images` - The rendered images, in the order of the diagrams.
+    pub fn show_diagrams(&mut self, index: usize, images: Vec<PathBuf>) {
+        if let Some(message) = self.messages.get_mut(index) {
+            message.diagrams = images;
+        }
+    }
+
+    /// Asks for the context viewer to be opened on the draft. Opening it
+    /// may need the server, to retrieve excerpts from the index, so the
+    /// request is handled outside of the input loop.
+    pub fn request_context(&mut self) {
+        self.context_requested = true;
+    }
+
+    /// Takes a request to open the context viewer, if one was made.
+    pub fn take_context_request(&mut self) -> bool {
+        std::mem::take(&mut self.context_requested)
+    }
+
+    /// Empties the transcript, keeping the conversation it showed for
+    /// [`CLEAR_UNDO_WINDOW`] so it can be brought back.
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - The session the transcript showed, as last saved.
+    pub fn clear_conversation(&mut self, session: Session) {
+        self.cleared = Some(Cleared {
+            session,
+            at: Instant::now(),
+        });
+        self.session_id = None;
+        self.load_messages(Vec::new());
+    }
+
+    /// Returns `true` while a cleared conversation can be brought back.
+    #[must_use]
+    pub fn can_undo_clear(&self) -> bool {
+        self.cleared
+            .as_ref()
+            .is_some_and(|cleared| cleared.at.elapsed() < CLEAR_UNDO_WINDOW)
+    }
+
+    /// Requests bringing back the cleared conversation, which needs the
+    /// session store and is done by `/clear undo`.
+    pub fn request_clear_undo(&mut self) {
+        self.clear_undo_requested = true;
+    }
+
+    /// Takes a request to bring back the cleared conversation, if one was
+    /// made.
+    pub fn take_clear_undo_request(&mut self) -> bool {
+        std::mem::take(&mut self.clear_undo_requested)
+    }
+
+    /// Takes the cleared conversation, if it can still be brought back.
+    pub fn take_cleared(&mut self) -> Option<Session> {
+        let cleared = self.cleared.take()?;
+        (cleared.at.elapsed() < CLEAR_UNDO_WINDOW).then_some(cleared.session)
+    }
+
+    /// Assembles the request that sending `prompt` would make: the system
+    /// prompt, the history, the attached files and the message, each with
+    /// its estimated tokens. Excerpts retrieved from the index are added by
+    /// the caller, as retrieving them needs the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The message that would be sent.
+    #[must_use]
+    pub fn next_request(&self, prompt: &str) -> ContextView {
+        let mut sections = Vec::new();
+        let mut messages = self.request_messages();
+        if messages.first().is_some_and(|m| m.role == "system") {
+            let system = messages.remove(0);
+            sections.push(Section::new("System prompt", system.content));
+        }
+        if !messages.is_empty() {
+            let history: Vec<String> = messages
+                .iter()
+                .map(|m| {
+                    format!(
+                        "[{}, ~{} tokens]\n{}",
+                        m.role,
+                        estimate_tokens(&m.content),
+                        m.content
+                    )
+                })
+                .collect();
+            sections.push(Section::new(
+                format!("History, {} message(s)", messages.len()),
+                history.join("\n\n"),
+            ));
+        }
+        let mentioned = mentions::attach(prompt, Path::new("."), self.sampling);
+        let attached: Vec<String> = self
+            .pending_context
+            .iter()
+            .cloned()
+            .chain(mentioned)
+            .collect();
+        if !attached.is_empty() {
+            sections.push(Section::new("Attachments", attached.join("\n\n")));
+        }
+        if !prompt.trim().is_empty() {
+            sections.push(Section::new(NEXT_MESSAGE, prompt.to_string()));
+        }
+        ContextView {
+            sections,
+            budget: self.context_budget,
+            scroll: 0,
+        }
+    }
+
+    /// Opens the context viewer popup.
+    ///
+    /// # Arguments
+    ///
+    /// * `view` - The request to show.
+    pub fn open_context(&mut self, view: ContextView) {
+        self.context_view = Some(view);
+        self.input_mode = InputMode::Context;
+    }
+
+    /// Closes the context viewer popup.
+    pub fn close_context(&mut self) {
+        self.context_view = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Opens the command palette.
+    pub fn open_palette(&mut self) {
+        self.palette = Some(Palette::new());
+        self.show_toggle = false;
+        self.completion = None;
+        self.input_mode = InputMode::Palette;
+    }
+
+    /// Closes the command palette.
+    pub fn close_palette(&mut self) {
+        self.palette = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Opens the session switcher.
+    ///
+    /// # Arguments
+    ///
+    /// * `switcher` - The saved sessions to choose from.
+    pub fn open_switcher(&mut self, switcher: Switcher) {
+        self.switcher = Some(switcher);
+        self.show_toggle = false;
+        self.completion = None;
+        self.input_mode = InputMode::Switcher;
+    }
+
+    /// Closes the session switcher.
+    ///
+    /// # Arguments
+    ///
+    /// * `open` - Whether the chosen session is opened.
+    pub fn close_switcher(&mut self, open: bool) {
+        let Some(switcher) = self.switcher.take() else {
+            return;
+        };
+        self.input_mode = InputMode::Normal;
+        if open {
+            self.switch_to = switcher.chosen().cloned();
+        }
+    }
+
+    /// Closes the details popup.
+    pub fn close_details(&mut self) {
+        self.details = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Closes the diff popup.
+    pub fn close_diff(&mut self) {
+        self.diff = None;
+        self.input_mode = InputMode::Normal;
+    }
+
+    /// Returns a retry of the latest answer.
+    ///
+    /// # Arguments
+    ///
+    /// * `model` - The model to retry with, or `None` for the current model.
+    #[must_use]
+    pub fn retry_latest(&self, model: Option<String>) -> Option<Retry> {
+        let message = self.messages.iter().rposition(|m| m.role == "assistant")?;
+        Some(Retry {
+            message,
+            model: model.unwrap_or_default(),
+            params: self.params,
+        })
+    }
+
+    /// Inserts an empty answer below the retried one, and below earlier
+    /// retries of it, to stream the new answer into.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The index of the retried answer.
+    /// * `generation` - The model and parameters of the retry.
+    ///
+    /// # Returns
+    ///
+    /// The messages to send and the index of the new answer, or `None` if
+    /// the message is not an answer to a prompt.
+    pub fn start_retry(
+        &mut self,
+        message: usize,
+        generation: Generation,
+    ) -> Option<(Vec<Message>, usize)> {
+        if self.messages.get(message)?.role != "assistant" {
+            return None;
+        }
+        let prompt = self.messages[..message]
+            .iter()
+            .rposition(|m| m.role == "user")?;
+        let history = self.request_messages_until(prompt + 1);
+
+        self.messages[message].compared = true;
+        let slot = message
+            + 1
+            + self.messages[message + 1..]
+                .iter()
+                .take_while(|m| m.role == "assistant" && m.compared)
+                .count();
+        self.messages.insert(
+            slot,
+            Message {
+                generation: Some(generation),
+                compared: true,
+                ..Message::new("assistant", String::new())
+            },
+        );
         self.input_mode = InputMode::Waiting;
-        self.current_response.clear();
-        self.messages.push(("assistant".to_string(), String::new()));
+        self.progress = Some(Progress::start());
+        self.select(slot);
+        self.follow = false;
+        Some((history, slot))
+    }
+
+    /// Inserts an empty message below an answer, and below earlier
+    /// translations of it, to stream its translation into.
+    ///
+    /// The selected answer is translated, or the latest one if no answer is
+    /// selected.
+    ///
+    /// # Arguments
+    ///
+    /// * `language` - The language to translate into.
+    ///
+    /// # Returns
+    ///
+    /// The messages to send and the index of the new message, or `None` if
+    /// there is no answer to translate.
+    pub fn start_translation(&mut self, language: &str) -> Option<(Vec<Message>, usize)> {
+        let is_answer = |m: &Message| m.role == "assistant" && !m.content.is_empty();
+        let message = self
+            .list_state
+            .selected()
+            .filter(|&index| self.messages.get(index).is_some_and(is_answer))
+            .or_else(|| self.messages.iter().rposition(is_answer))?;
+        let request = vec![Message::new(
+            "user",
+            translate::prompt(language, &self.messages[message].content),
+        )];
+
+        let slot = message
+            + 1
+            + self.messages[message + 1..]
+                .iter()
+                .take_while(|m| m.role == "assistant" && m.translation.is_some())
+                .count();
+        self.messages.insert(
+            slot,
+            Message {
+                translation: Some(language.to_string()),
+                ..Message::new("assistant", String::new())
+            },
+        );
+        self.input_mode = InputMode::Waiting;
+        self.progress = Some(Progress::start());
+        self.select(slot);
+        self.follow = false;
+        Some((request, slot))
     }
+
+    /// Appends streamed content to a message inserted by a retry or a
+    /// translation.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The index of the new answer.
+    /// * `content` - The new content.
+    pub fn update_retry(&mut self, slot: usize, content: &str) {
+        if let Some(message) = self.messages.get_mut(slot) {
+            message.content.push_str(content);
+        }
+        if let Some(progress) = self.progress.as_mut() {
+            progress.record(content);
+        }
+    }
+
+    /// Ends a retry, removing the new answer if it stayed empty and
+    /// reporting the error if it failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The index of the new answer.
+    /// * `error` - Why the retry failed, if it did.
+    pub fn finish_retry(&mut self, slot: usize, error: Option<String>) {
+        self.finish_inserted(slot);
+        if let Some(error) = error {
+            self.add_system_message(format!("Retry failed: {error}"));
+        }
+    }
+
+    /// Cancels a retry, removing the attempt streamed so far.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The index of the attempt.
+    pub fn cancel_retry(&mut self, slot: usize) {
+        self.input_mode = InputMode::Normal;
+        self.progress = None;
+        if slot < self.messages.len() {
+            self.messages.remove(slot);
+        }
+        self.add_system_message(String::from("Retry cancelled"));
+    }
+
+    /// Returns the answer to continue: the selected answer if it was cut
+    /// off at the token limit, or else the latest answer if it was.
+    #[must_use]
+    pub fn cut_off_answer(&self) -> Option<usize> {
+        let selected = self
+            .list_state
+            .selected()
+            .filter(|&index| self.messages.get(index).is_some_and(Message::is_cut_off));
+        selected.or_else(|| {
+            let latest = self.messages.iter().rposition(|m| m.role == "assistant")?;
+            self.messages[latest].is_cut_off().then_some(latest)
+        })
+    }
+
+    /// Asks for the answer cut off at the token limit to be continued. The
+    /// request is sent outside of the input loop.
+    pub fn request_continuation(&mut self) {
+        if self.cut_off_answer().is_some() {
+            self.continue_requested = true;
+        } else {
+            self.add_system_message(String::from(
+                "Only an answer cut off at the token limit can be continued",
+            ));
+        }
+    }
+
+    /// Takes a request to continue an answer, if one was made.
+    pub fn take_continuation_request(&mut self) -> bool {
+        std::mem::take(&mut self.continue_requested)
+    }
+
+    /// Starts continuing an answer cut off at the token limit. The
+    /// continuation is streamed onto the end of the answer.
+    ///
+    /// # Returns
+    ///
+    /// The messages to send, ending with the instruction to continue, and
+    /// the answer being continued; or `None` if no answer was cut off.
+    pub fn start_continuation(&mut self) -> Option<(Vec<Message>, Continuation)> {
+        let slot = self.cut_off_answer()?;
+        let mut history = self.request_messages_until(slot + 1);
+        history.push(Message::new("user", CONTINUE_PROMPT));
+        let continuation = Continuation {
+            slot,
+            metadata: self.messages[slot].metadata.take().unwrap_or_default(),
+            length: self.messages[slot].content.len(),
+        };
+        self.input_mode = InputMode::Waiting;
+        self.progress = Some(Progress::start());
+        self.select(slot);
+        self.follow = slot + 1 == self.messages.len();
+        Some((history, continuation))
+    }
+
+    /// Ends the continuation of an answer: stitches what was streamed onto
+    /// the answer without a code fence reopened or a line repeated, adds
+    /// its tokens and time to what was recorded about the answer, and
+    /// reports the error if it failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `continuation` - The answer being continued.
+    /// * `error` - Why the continuation failed, if it did.
+    pub fn finish_continuation(&mut self, continuation: Continuation, error: Option<String>) {
+        self.input_mode = InputMode::Normal;
+        let elapsed = self
+            .progress
+            .take()
+            .map(|progress| u64::try_from(progress.elapsed().as_millis()).unwrap_or(u64::MAX));
+        let Continuation {
+            slot,
+            mut metadata,
+            length,
+        } = continuation;
+        if let Some(message) = self.messages.get_mut(slot) {
+            if let Some(streamed) = message.content.get(length..) {
+                let stitched = continuation::stitch(&message.content[..length], streamed);
+                message.content = format!("{}{stitched}", &message.content[..length]);
+            }
+            let reported = message.metadata.take().unwrap_or_default();
+            if error.is_none() {
+                metadata.extend(Metadata {
+                    total_ms: elapsed,
+                    ..reported
+                });
+            }
+            message.metadata = Some(metadata);
+        }
+        if let Some(error) = error {
+            self.add_system_message(format!("Continuing failed: {error}"));
+        }
+    }
+
+    /// Ends a translation, removing the new message if it stayed empty and
+    /// reporting the error if it failed.
+    ///
+    /// # Arguments
+    ///
+    /// * `slot` - The index of the new message.
+    /// * `error` - Why the translation failed, if it did.
+    pub fn finish_translation(&mut self, slot: usize, error: Option<String>) {
+        self.finish_inserted(slot);
+        if let Some(error) = error {
+            self.add_system_message(format!("Translation failed: {error}"));
+        }
+    }
+
+    fn finish_inserted(&mut self, slot: usize) {
+        self.input_mode = InputMode::Normal;
+        if let (Some(progress), Some(message)) = (self.progress.take(), self.messages.get_mut(slot))
+        {
+            record_timing(message, &progress);
+        }
+        let empty = self
+            .messages
+            .get(slot)
+            .is_some_and(|m| m.content.is_empty());
+        if empty {
+            self.messages.remove(slot);
+        }
+    }
+
+    /// Ends the current request with an error card.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - The user-friendly error message.
+    /// * `details` - The technical details shown when the card is expanded.
+    pub fn add_error(&mut self, message: String, details: ErrorDetails) {
+        self.finish_response(Message {
+            error: Some(details),
+            ..Message::new("error", message)
+        });
+    }
+
+    /// Expands or collapses the details of the selected error card.
+    pub fn toggle_error_details(&mut self) {
+        if let Some(message) = self
+            .list_state
+            .selected()
+            .and_then(|index| self.messages.get_mut(index))
+            .filter(|message| message.error.is_some())
+        {
+            message.expanded = !message.expanded;
+        }
+    }
+
+    /// Switches the code blocks of the selected message between wrapping
+    /// and cutting off long lines.
+    pub fn toggle_code_wrap(&mut self) {
+        if let Some(message) = self.selected_message_mut() {
+            message.code_scroll = match message.code_scroll {
+                Some(_) => None,
+                None => Some(0),
+            };
+        }
+    }
+
+    /// Switches the selected answer between rendered markdown and its
+    /// source, as written.
+    pub fn toggle_raw(&mut self) {
+        if let Some(message) = self
+            .selected_message_mut()
+            .filter(|message| message.role == "assistant")
+        {
+            message.raw = !message.raw;
+        }
+    }
+
+    /// Pans the code blocks of the selected message horizontally, cutting
+    /// off long lines instead of wrapping them.
+    ///
+    /// # Arguments
+    ///
+    /// * `columns` - How far to pan; negative values pan left.
+    pub fn pan_code(&mut self, columns: isize) {
+        if let Some(message) = self.selected_message_mut() {
+            let longest = message
+                .content
+                .lines()
+                .map(UnicodeWidthStr::width)
+                .max()
+                .unwrap_or(0);
+            let offset = message
+                .code_scroll
+                .unwrap_or(0)
+                .saturating_add_signed(columns)
+                .min(longest.saturating_sub(1));
+            message.code_scroll = Some(offset);
+        }
+    }
+
+    /// Starts a reply to the selected message, with its first paragraph
+    /// quoted in the input, if it has text.
+    pub fn quote_selected(&mut self) {
+        let Some(content) = self.plain_text() else {
+            return;
+        };
+        let reply = quote(content);
+        self.set_input(reply);
+        self.input_mode = InputMode::Editing;
+    }
+
+    /// Asks for the selected message to be printed as plain text outside
+    /// the alternate screen, if one is selected.
+    pub fn show_plain(&mut self) {
+        self.plain_view = self.plain_text().is_some();
+    }
+
+    /// Returns the text of the selected message as written, without the
+    /// markdown rendering, or `None` if no message with text is selected.
+    #[must_use]
+    pub fn plain_text(&self) -> Option<&str> {
+        self.list_state
+            .selected()
+            .and_then(|index| self.messages.get(index))
+            .map(|message| message.content.as_str())
+            .filter(|content| !content.is_empty())
+    }
+
+    fn selected_message_mut(&mut self) -> Option<&mut Message> {
+        self.list_state
+            .selected()
+            .and_then(|index| self.messages.get_mut(index))
+    }
+
+    /// Returns `true` unless the latest health check failed to reach the
+    /// endpoint.
+    #[must_use]
+    pub fn is_online(&self) -> bool {
+        self.connectivity.as_ref().is_none_or(|rx| *rx.borrow())
+    }
+
+    /// Returns the readiness of the endpoint, combining the startup check
+    /// with the latest health check, or `None` if neither is running.
+    #[must_use]
+    pub fn endpoint_status(&self) -> Option<EndpointStatus> {
+        let startup = self.endpoint.as_ref().map(|rx| *rx.borrow());
+        match (startup, self.connectivity.is_some(), self.is_online()) {
+            (_, true, false) => Some(EndpointStatus::Unreachable),
+            (Some(EndpointStatus::Unreachable), true, true) => Some(EndpointStatus::Ready),
+            (None, true, true) => Some(EndpointStatus::Ready),
+            (status, ..) => status,
+        }
+    }
+
+    /// Returns `true` if messages are waiting to be sent.
+    #[must_use]
+    pub fn has_pending(&self) -> bool {
+        self.messages.iter().any(|m| m.pending)
+    }
+
+    /// Returns `true` if messages are queued and a health check completed
+    /// since they were queued has reached the endpoint.
+    pub fn should_resend(&mut self) -> bool {
+        if !self.has_pending() {
+            return false;
+        }
+        self.connectivity
+            .as_mut()
+            .is_some_and(|rx| rx.has_changed().unwrap_or(false) && *rx.borrow_and_update())
+    }
+
+    /// Queues the message being sent until the endpoint is reachable again,
+    /// removing the empty response.
+    pub fn mark_pending(&mut self) {
+        if let Some(rx) = self.connectivity.as_mut() {
+            rx.borrow_and_update();
+        }
+        self.abandon_response();
+        if let Some(message) = self.messages.iter_mut().rev().find(|m| m.role == "user") {
+            message.pending = true;
+        }
+        self.follow_tail();
+    }
+
+    /// Ends a dry run: the prompt is taken back out of the conversation and
+    /// into the input, and the request it would have been sent with is
+    /// shown instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `preview` - The description of the request.
+    pub fn finish_dry_run(&mut self, preview: String) {
+        self.abandon_response();
+        let prompt = self
+            .messages
+            .iter()
+            .rposition(|m| m.role == "user")
+            .map(|index| self.messages.remove(index));
+        if let Some(prompt) = prompt.filter(|_| self.input.is_empty()) {
+            self.input = prompt.content;
+        }
+        self.add_system_message(preview);
+    }
+
+    /// Shows the latest answer pretty-printed as JSON while JSON mode is on,
+    /// and reports below it where it does not match the schema or is not
+    /// JSON at all. The answer itself is kept as it was sent.
+    pub fn check_json_answer(&mut self) {
+        let Some(output) = &self.json_output else {
+            return;
+        };
+        let Some(message) = self
+            .messages
+            .iter_mut()
+            .rev()
+            .find(|m| m.role == "assistant")
+        else {
+            return;
+        };
+        match structured::check(&message.content, output.schema.as_ref()) {
+            Ok(checked) => {
+                message.json = true;
+                if !checked.problems.is_empty() {
+                    let problems: Vec<String> = checked
+                        .problems
+                        .iter()
+                        .map(|problem| format!("- {problem}"))
+                        .collect();
+                    self.add_system_message(format!(
+                        "The answer does not match the schema:\n{}",
+                        problems.join("\n")
+                    ));
+                }
+            }
+            Err(err) => self.add_system_message(format!("The answer is not valid JSON: {err}")),
+        }
+    }
+
+    /// Restores the input buffer to before the last edit.
+    pub fn undo_input(&mut self) {
+        if let Some(previous) = self.undo.undo(&self.input) {
+            self.replace_input(previous);
+        }
+    }
+
+    /// Reapplies the last undone edit to the input buffer.
+    pub fn redo_input(&mut self) {
+        if let Some(next) = self.undo.redo(&self.input) {
+            self.replace_input(next);
+        }
+    }
+
+    /// Updates the completion popup for the edited input.
+    pub fn update_completion(&mut self) {
+        self.completion = Completion::for_input(&self.input);
+    }
+
+    /// Replaces the completed part of the input with the selected
+    /// suggestion, then offers the completions of the result.
+    ///
+    /// # Returns
+    ///
+    /// `false` if no completion popup was shown.
+    pub fn accept_completion(&mut self) -> bool {
+        let Some(completion) = self.completion.take() else {
+            return false;
+        };
+        self.undo.record(&self.input, Edit::Insert);
+        self.undo.break_step();
+        let input = completion.apply(&self.input);
+        self.replace_input(input);
+        self.update_completion();
+        true
+    }
+
+    /// Adds the sent input to the history and stops recalling earlier
+    /// prompts.
+    pub fn remember_input(&mut self, input: &str) {
+        self.history.push(input);
+        self.history_index = None;
+        self.history_search = None;
+        self.history_draft.clear();
+    }
+
+    /// Replaces the input with the previous prompt of the history, keeping
+    /// the draft so [`recall_next`](Self::recall_next) can return to it.
+    pub fn recall_previous(&mut self) {
+        let index = match self.history_index {
+            None if self.history.is_empty() => return,
+            None => {
+                self.history_draft = self.input.clone();
+                self.history.len() - 1
+            }
+            Some(0) => return,
+            Some(index) => index - 1,
+        };
+        self.history_index = Some(index);
+        let entry = self.history.get(index).unwrap_or_default().to_string();
+        self.replace_input(entry);
+    }
+
+    /// Replaces the input with the next prompt of the history, or with the
+    /// draft after the newest one.
+    pub fn recall_next(&mut self) {
+        let Some(index) = self.history_index else {
+            return;
+        };
+        if index + 1 < self.history.len() {
+            self.history_index = Some(index + 1);
+            let entry = self.history.get(index + 1).unwrap_or_default().to_string();
+            self.replace_input(entry);
+        } else {
+            self.history_index = None;
+            let draft = std::mem::take(&mut self.history_draft);
+            self.replace_input(draft);
+        }
+    }
+
+    /// Starts a reverse search through the history, or moves an ongoing one
+    /// to the next older match.
+    pub fn search_history(&mut self) {
+        let Some(search) = self.history_search.as_mut() else {
+            self.history_search = Some(HistorySearch::default());
+            return;
+        };
+        let Some(found) = search.found else {
+            return;
+        };
+        match self.history.search(&search.query, found) {
+            Some(older) => search.found = Some(older),
+            None => self.flash_input(),
+        }
+    }
+
+    /// Changes the query of the ongoing history search and finds the newest
+    /// prompt matching it.
+    ///
+    /// # Arguments
+    ///
+    /// * `c` - The character typed, or `None` to remove the last one.
+    pub fn edit_history_search(&mut self, c: Option<char>) {
+        let Some(search) = self.history_search.as_mut() else {
+            return;
+        };
+        match c {
+            Some(c) => search.query.push(c),
+            None => {
+                search.query.pop();
+            }
+        }
+        search.found = if search.query.is_empty() {
+            None
+        } else {
+            self.history.search(&search.query, self.history.len())
+        };
+    }
+
+    /// Ends the history search, replacing the input with the match if
+    /// `accept` is set.
+    pub fn finish_history_search(&mut self, accept: bool) {
+        let Some(found) = self.history_search.take().and_then(|search| search.found) else {
+            return;
+        };
+        if accept {
+            if self.history_index.is_none() {
+                self.history_draft = self.input.clone();
+            }
+            self.history_index = Some(found);
+            let entry = self.history.get(found).unwrap_or_default().to_string();
+            self.replace_input(entry);
+        }
+    }
+
+    /// Adds context to the context attached to the next message.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The text to attach.
+    pub fn attach_context(&mut self, context: String) {
+        self.pending_context = Some(match self.pending_context.take() {
+            Some(pending) => format!("{pending}\n\n{context}"),
+            None => context,
+        });
+    }
+
+    /// Attaches an image file to the next message.
+    pub fn attach_image(&mut self, path: PathBuf) {
+        if !self.pending_images.contains(&path) {
+            self.pending_images.push(path);
+        }
+    }
+
+    /// Scans a prompt about to be sent, and what would be attached to it,
+    /// for credentials, when prompts are scanned.
+    ///
+    /// # Returns
+    ///
+    /// A warning for each kind of credential found in the prompt and in its
+    /// attachments, or none if prompts are not scanned.
+    #[must_use]
+    pub fn scan_outgoing(&self, prompt: &str) -> Vec<Warning> {
+        let Some(scanner) = &self.secret_scan else {
+            return Vec::new();
+        };
+        let mentioned = mentions::attach(prompt, Path::new("."), self.sampling);
+        let attached: Vec<String> = self
+            .pending_context
+            .iter()
+            .cloned()
+            .chain(mentioned)
+            .collect();
+        let found = |text: &str, place: &'static str| {
+            scanner
+                .secrets_in(text)
+                .into_iter()
+                .map(move |kind| Warning::Credential { kind, place })
+        };
+        found(prompt, "the prompt")
+            .chain(found(&attached.join("\n\n"), "the attachments"))
+            .collect()
+    }
+
+    fn replace_input(&mut self, input: String) {
+        self.input = input;
+        self.horizontal_scroll = 0;
+        self.awaiting_cost_confirmation = false;
+        self.lint_warnings.clear();
+        self.shell_preview = None;
+    }
+
+    /// Replaces the input, as one step to undo.
+    pub fn set_input(&mut self, input: String) {
+        self.undo.record(&self.input, Edit::Insert);
+        self.undo.break_step();
+        self.replace_input(input);
+        self.update_completion();
+    }
+
+    /// Replaces the text of the latest message with a role, such as a
+    /// prompt or answer a script rewrote.
+    ///
+    /// # Arguments
+    ///
+    /// * `role` - The role of the message, `user` or `assistant`.
+    /// * `content` - The new text.
+    pub fn rewrite_latest(&mut self, role: &str, content: String) {
+        if let Some(message) = self
+            .messages
+            .iter_mut()
+            .rev()
+            .find(|message| message.role == role)
+        {
+            message.content = content;
+        }
+    }
+
+    /// Adds a user message, with any pending context and the files mentioned
+    /// as `@path` attached, and waits for the response to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - The text of the message.
+    pub fn submit_prompt(&mut self, prompt: String) {
+        let mentioned = mentions::attach(&prompt, Path::new("."), self.sampling);
+        let mut message = Message::new("user", prompt);
+        message.attachment = match (self.pending_context.take(), mentioned) {
+            (Some(context), Some(files)) => Some(format!("{context}\n\n{files}")),
+            (context, files) => context.or(files),
+        };
+        message.images = std::mem::take(&mut self.pending_images);
+        self.messages.push(message);
+        self.input_mode = InputMode::Waiting;
+        self.progress = Some(Progress::start());
+    }
+
+    /// Takes the next input received on the control socket, submitting it
+    /// as a prompt unless it is a command.
+    ///
+    /// # Returns
+    ///
+    /// The input to handle, or `None` if nothing was received.
+    pub fn take_control_input(&mut self) -> Option<String> {
+        let input = self.control.as_mut()?.try_recv().ok()?;
+        if !input.trim_start().starts_with('/') {
+            self.submit_prompt(input.clone());
+            self.follow_latest();
+        }
+        Some(input)
+    }
+
+    /// Carries out the actions plugins asked for since the last call, and
+    /// shows the failures of their commands.
+    pub fn apply_plugin_replies(&mut self) {
+        let Some(rx) = self.plugin_replies.as_mut() else {
+            return;
+        };
+        let mut replies = Vec::new();
+        while let Ok(reply) = rx.try_recv() {
+            replies.push(reply);
+        }
+        for reply in replies {
+            match reply {
+                Ok(Reply {
+                    action: plugins::Action::InsertText { text },
+                    ..
+                }) => self.insert_text(&text),
+                Ok(Reply {
+                    plugin,
+                    action: plugins::Action::AddMessage { content },
+                }) => self.add_system_message(format!("{plugin}: {content}")),
+                Ok(Reply {
+                    action: plugins::Action::RegisterCommand { .. },
+                    ..
+                }) => {}
+                Err(err) => self.add_system_message(err.to_string()),
+            }
+        }
+    }
+
+    /// Adds text to the end of the input, as one step to undo.
+    pub fn insert_text(&mut self, text: &str) {
+        self.undo.record(&self.input, Edit::Insert);
+        self.undo.break_step();
+        self.input
+            .push_str(&text.replace("\r\n", "\n").replace('\r', "\n"));
+        self.awaiting_cost_confirmation = false;
+        self.lint_warnings.clear();
+        self.shell_preview = None;
+        self.update_completion();
+    }
+
+    /// Sends the queued messages, if any.
+    ///
+    /// # Returns
+    ///
+    /// The text of the last queued message, or `None` if nothing was queued.
+    pub fn resend_pending(&mut self) -> Option<String> {
+        let mut last = None;
+        for message in self.messages.iter_mut().filter(|m| m.pending) {
+            message.pending = false;
+            last = Some(message.content.clone());
+        }
+        if last.is_some() {
+            self.input_mode = InputMode::Waiting;
+            self.progress = Some(Progress::start());
+            self.follow_latest();
+        }
+        last
+    }
+
+    /// Leaves waiting for a response without keeping anything of it,
+    /// removing the response being streamed.
+    pub fn abandon_response(&mut self) {
+        if self.input_mode == InputMode::Waiting
+            && self.messages.last().is_some_and(|m| m.role == "assistant")
+        {
+            self.messages.pop();
+        }
+        self.input_mode = InputMode::Normal;
+        self.current_response.clear();
+        self.progress = None;
+    }
+
+    /// Removes the response being streamed and appends the final message.
+    fn finish_response(&mut self, mut message: Message) {
+        let responding = self.input_mode == InputMode::Waiting;
+        if responding && self.messages.last().is_some_and(|m| m.role == "assistant") {
+            self.messages.pop();
+        }
+        // A comparison is timed as a whole, so its kept answer is not.
+        if let Some(progress) = self.progress.take() {
+            if responding && message.role == "assistant" {
+                record_timing(&mut message, &progress);
+            }
+        }
+        self.messages.push(message);
+        self.input_mode = InputMode::Normal;
+        self.current_response.clear();
+        self.follow_tail();
+
+        self.horizontal_scroll = 0;
+        self.horizontal_scroll_state = ScrollbarState::default();
+    }
+
+    /// Replaces the transcript with the given messages.
+    ///
+    /// # Arguments
+    ///
+    /// * `messages` - The messages to show.
+    pub fn load_messages(&mut self, messages: Vec<Message>) {
+        self.scrollback.clear();
+        self.messages = messages;
+        self.last_read = 0;
+        self.current_response.clear();
+        self.input_mode = InputMode::Normal;
+        self.follow_latest();
+        self.trim_scrollback();
+    }
+
+    /// Pages the oldest messages out to disk while the transcript holds more
+    /// than `max_scrollback` of them. Nothing is paged out while a response
+    /// is streaming or a popup refers to a message by its position.
+    pub fn trim_scrollback(&mut self) {
+        let excess = self.scrollback.excess(self.messages.len());
+        let busy = matches!(self.input_mode, InputMode::Waiting | InputMode::Compare)
+            || self.retry.is_some()
+            || self.params_editor.is_some();
+        if excess == 0 || busy {
+            return;
+        }
+        if let Err(err) = self.scrollback.archive(&self.messages[..excess]) {
+            error!(?err, "failed to page out older messages");
+            return;
+        }
+        self.messages.drain(..excess);
+        self.layout.discard(excess);
+        let selected = self.list_state.selected().unwrap_or(0);
+        self.select(selected.saturating_sub(excess));
+        self.follow_tail();
+    }
+
+    /// Returns the messages paged out of the transcript, oldest first.
+    fn archived_messages(&self) -> Vec<Message> {
+        self.scrollback.load().unwrap_or_else(|err| {
+            error!(?err, "failed to read the paged-out messages");
+            Vec::new()
+        })
+    }
+
+    /// Opens the pager over the whole transcript, including the messages
+    /// paged out to disk.
+    pub fn open_pager(&mut self) {
+        self.pager.archived = self.archived_messages();
+        self.input_mode = InputMode::Pager;
+    }
+
+    /// Opens the pager with the lines of the selected message selected, to
+    /// copy part of them.
+    pub fn select_in_pager(&mut self) {
+        let Some(index) = self.list_state.selected() else {
+            return;
+        };
+        self.open_pager();
+        self.pager.filter = None;
+        self.pager.select_message = Some(self.pager.archived.len() + index);
+    }
+
+    /// Opens the transcript pager on the exchanges a filter keeps, or
+    /// says so if it keeps none.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter` - Which exchanges are shown, by their marks.
+    pub fn open_marked(&mut self, filter: MarkFilter) {
+        let mut conversation = self.archived_messages();
+        conversation.extend_from_slice(&self.messages);
+        if filter.apply(&conversation).is_empty() {
+            self.add_system_message(format!(
+                "No exchange is {}; select a prompt or answer and press +, - or * to mark it",
+                filter.describe()
+            ));
+            return;
+        }
+        self.pager.filter = Some(filter);
+        self.open_pager();
+    }
+
+    /// Marks the selected prompt or answer, or removes the mark if it has
+    /// it.
+    pub fn toggle_mark(&mut self, mark: Mark) {
+        let selected = self.list_state.selected().unwrap_or(0);
+        match self.messages.get_mut(selected) {
+            Some(message) if message.role == "user" || message.role == "assistant" => {
+                message.toggle_mark(mark);
+            }
+            _ => self.add_system_message(String::from("Select a prompt or answer to mark it")),
+        }
+    }
+
+    /// Adds a system notice to the transcript.
+    ///
+    /// # Arguments
+    ///
+    /// * `notice` - The text of the notice.
+    pub fn add_system_message(&mut self, notice: String) {
+        self.messages.push(Message::new("system", notice));
+        self.follow_tail();
+    }
+
+    /// Replaces the text of the last system notice, such as a progress
+    /// report, or adds a new notice if the last message is not one.
+    ///
+    /// # Arguments
+    ///
+    /// * `notice` - The new text of the notice.
+    pub fn update_system_message(&mut self, notice: String) {
+        match self.messages.last_mut() {
+            Some(message) if message.role == "system" => message.content = notice,
+            _ => self.messages.push(Message::new("system", notice)),
+        }
+        self.follow_tail();
+    }
+
+    /// Returns the user and assistant messages that make up the conversation,
+    /// skipping system notices and empty placeholders.
+    #[must_use]
+    pub fn conversation(&self) -> Vec<Message> {
+        self.archived_messages()
+            .iter()
+            .chain(&self.messages)
+            .filter(|m| in_conversation(m))
+            .cloned()
+            .collect()
+    }
+
+    /// Captures the conversation and draft for crash recovery. A response
+    /// that is still streaming is kept and marked as stopped.
+    #[must_use]
+    pub fn recovery(&self) -> Recovery {
+        let mut messages = self.conversation();
+        if self.input_mode == InputMode::Waiting {
+            if let Some(partial) = messages.last_mut().filter(|m| m.role == "assistant") {
+                partial.stopped = true;
+            }
+        }
+        Recovery {
+            session_id: self.session_id.clone(),
+            system_prompt: self.system_prompt.clone(),
+            messages,
+            draft: self.input.clone(),
+        }
+    }
+
+    /// Returns the messages to send to the API: the system prompt, if any,
+    /// followed by the conversation.
+    #[must_use]
+    pub fn request_messages(&self) -> Vec<Message> {
+        self.request_messages_until(self.messages.len())
+    }
+
+    /// Returns the messages to send for the transcript up to, but not
+    /// including, the message at `end`.
+    fn request_messages_until(&self, end: usize) -> Vec<Message> {
+        let archived = self.archived_messages();
+        self.request_messages_of(archived.iter().chain(&self.messages[..end]))
+    }
+
+    /// Returns the system prompt followed by the given messages that are
+    /// sent, as they are sent. Of the attempts at an answer made by
+    /// retrying it, only the latest is sent.
+    fn request_messages_of<'a>(&self, messages: impl Iterator<Item = &'a Message>) -> Vec<Message> {
+        let is_attempt = |m: &Message| m.role == "assistant" && m.compared;
+        let sent: Vec<&Message> = messages.filter(|message| message.is_sent()).collect();
+        let kept = sent.iter().enumerate().filter(|&(index, message)| {
+            !(is_attempt(message) && sent.get(index + 1).is_some_and(|next| is_attempt(next)))
+        });
+        translate::with_language(
+            self.system_prompt.as_deref(),
+            self.answer_language.as_deref(),
+        )
+        .into_iter()
+        .map(|prompt| Message::new("system", prompt))
+        .chain(kept.map(|(_, message)| Message {
+            content: message.request_content(),
+            attachment: None,
+            ..(*message).clone()
+        }))
+        .collect()
+    }
+
+    /// Estimates the tokens that sending the current draft would use,
+    /// including the system prompt and conversation history.
+    #[must_use]
+    pub fn estimated_prompt_tokens(&self) -> usize {
+        self.request_messages_of(self.messages.iter())
+            .iter()
+            .map(|m| estimate_tokens(&m.content))
+            .sum::<usize>()
+            + self.scrollback.tokens()
+            + estimate_tokens(&self.input)
+            + self.pending_context.as_deref().map_or(0, estimate_tokens)
+    }
+
+    /// Estimates the cost of sending the current draft, if a price is known.
+    #[must_use]
+    pub fn estimated_prompt_cost(&self) -> Option<f64> {
+        self.prompt_price
+            .map(|price| estimate_cost(self.estimated_prompt_tokens(), price))
+    }
+
+    /// Returns `true` if sending the draft needs an explicit confirmation
+    /// because its estimated cost is above the configured threshold.
+    #[must_use]
+    pub fn needs_cost_confirmation(&self) -> bool {
+        match (self.estimated_prompt_cost(), self.cost_confirm_threshold) {
+            (Some(cost), Some(threshold)) => cost > threshold,
+            _ => false,
+        }
+    }
+
+    /// Prepares the UI state for a new response. Sending a message resumes
+    /// following new output.
+    pub fn start_new_response(&mut self) {
+        self.input_mode = InputMode::Waiting;
+        self.current_response.clear();
+        self.progress.get_or_insert_with(Progress::start);
+        self.messages.push(Message::new("assistant", String::new()));
+        self.follow_latest();
+    }
+}
+
+/// Records how long a response took on its message.
+fn record_timing(message: &mut Message, progress: &Progress) {
+    let millis = |duration: Duration| u64::try_from(duration.as_millis()).unwrap_or(u64::MAX);
+    message
+        .metadata
+        .get_or_insert_with(Metadata::default)
+        .merge(Metadata {
+            first_token_ms: progress.first_token().map(millis),
+            total_ms: Some(millis(progress.elapsed())),
+            ..Metadata::default()
+        });
+}
+
+/// Quotes the first paragraph of a message as markdown, at most
+/// [`QUOTE_LINES`] lines of it, for a reply to follow.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::ui::state::quote;
+///
+/// assert_eq!(quote("Use a `Vec`.\n\nOr a `VecDeque`."), "> Use a `Vec`.\n> …\n\n");
+/// assert_eq!(quote("\nShort answer."), "> Short answer.\n\n");
+/// ```
+#[must_use]
+pub fn quote(content: &str) -> String {
+    let mut lines = content.lines().skip_while(|line| line.trim().is_empty());
+    let excerpt: Vec<&str> = lines
+        .by_ref()
+        .take_while(|line| !line.trim().is_empty())
+        .take(QUOTE_LINES)
+        .collect();
+    let mut quoted: String = excerpt
+        .iter()
+        .map(|line| format!("> {}\n", line.trim_end()))
+        .collect();
+    if lines.any(|line| !line.trim().is_empty()) {
+        quoted.push_str("> …\n");
+    }
+    quoted.push('\n');
+    quoted
+}
+
+/// Whether a message is part of the conversation sent and saved, rather
+/// than a notice or an answer still empty.
+fn in_conversation(message: &Message) -> bool {
+    (message.role == "user" || message.role == "assistant") && !message.content.is_empty()
+}
+
+/// An answer being continued after the token limit cut it off.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Continuation {
+    /// The index of the answer.
+    pub slot: usize,
+    /// What was recorded about the answer before it was continued.
+    pub metadata: Metadata,
+    /// The length of the answer before it was continued.
+    pub length: usize,
+}
+
+/// A conversation `/clear` removed from the transcript.
+#[derive(Debug, Clone)]
+pub struct Cleared {
+    /// The session as it was before it was cleared.
+    pub session: Session,
+    /// When it was cleared.
+    pub at: Instant,
 }
 
 /// Represents the different input modes of the UI.
-#[derive(Default, Debug, PartialEq, Clone, Copy)]
+#[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum InputMode {
-    /// No rmal mode for navigation and command input.
+    /// Normal mode for navigation and command input.
     #[default]
     Normal,
     /// Editing mode for text input.
     Editing,
     /// Waiting mode while processing a request.
     Waiting,
+    /// Read-only pager over the rendered transcript.
+    Pager,
+    /// Choosing which of two compared responses to keep.
+    Compare,
+    /// Adjusting the sampling parameters in a popup.
+    Params,
+    /// Choosing a result of a session search in a popup.
+    Search,
+    /// Reading the diff between two attempts at an answer in a popup.
+    Diff,
+    /// Reading how an answer was generated in a popup.
+    Details,
+    /// Reading what the next request would send in a popup.
+    Context,
+    /// Finding an action or command to run in the command palette.
+    Palette,
+    /// Choosing a saved session to switch to.
+    Switcher,
 }
 
 /// Represents possible actions that can be taken in the UI.
-#[derive(PartialEq)]
+#[derive(Debug, PartialEq)]
 pub enum Action {
-    /// Action to cancel the current request.
+    /// Action to cancel the current request, discarding the partial response.
     CancelRequest,
+    /// Action to stop generation, keeping the partial response.
+    StopGeneration,
+    /// Action to stop generation, keeping the partial response, and send
+    /// the follow-up typed meanwhile.
+    Interject,
 }
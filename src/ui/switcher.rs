@@ -0,0 +1,93 @@
+//! Switches to a saved session by its title or date.
+//!
+//! `Ctrl+O` or `/sessions` opens the switcher over the saved sessions, the
+//! most recently modified first. Typing filters them fuzzily by title, or
+//! by the start of the date they were last modified, such as `2024-05`;
+//! `Up`/`Down` choose one and `Enter` opens it.
+
+use super::completion::fuzzy_score;
+use super::picker::{Item, Picker};
+use crate::store::SessionInfo;
+
+/// The seconds of a day.
+const DAY: u64 = 24 * 60 * 60;
+
+/// Formats a time in seconds since the Unix epoch as a UTC date.
+///
+/// # Examples
+///
+/// ```
+/// use chatti::ui::switcher::date;
+///
+/// assert_eq!(date(0), "1970-01-01");
+/// assert_eq!(date(1_709_251_200), "2024-03-01");
+/// ```
+#[must_use]
+pub fn date(secs: u64) -> String {
+    // Converts days to a civil date, counting in 400-year eras from
+    // 0000-03-01 so leap days end each year.
+    let days = secs / DAY + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+impl Item for SessionInfo {
+    /// Scores the session by its title, or above any title if the date it
+    /// was last modified starts with the query.
+    fn score(&self, query: &str) -> Option<usize> {
+        let title = fuzzy_score(query, &self.title);
+        let date = date(self.updated_at)
+            .starts_with(query)
+            .then_some(usize::MAX);
+        title.max(date)
+    }
+
+    fn label(&self) -> &str {
+        &self.title
+    }
+}
+
+/// The session switcher: the query and the sessions matching it.
+pub type Switcher = Picker<SessionInfo>;
+
+impl Switcher {
+    /// Opens the switcher with every session listed, in the order given.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::store::SessionInfo;
+    /// use chatti::ui::switcher::Switcher;
+    ///
+    /// let session = |id: &str, title: &str, updated_at| SessionInfo {
+    ///     id: id.to_string(),
+    ///     title: title.to_string(),
+    ///     updated_at,
+    ///     messages: 2,
+    /// };
+    /// let mut switcher = Switcher::new(vec![
+    ///     session("b", "Rust lifetimes", 1_709_251_200),
+    ///     session("a", "Trip to Lisbon", 1_704_067_200),
+    /// ]);
+    /// for c in "lisb".chars() {
+    ///     switcher.push(c);
+    /// }
+    /// assert_eq!(switcher.chosen().unwrap().id, "a");
+    /// ```
+    #[must_use]
+    pub fn new(sessions: Vec<SessionInfo>) -> Self {
+        Picker::with_items(sessions)
+    }
+}
@@ -0,0 +1,171 @@
+//! Tracks long-running tasks, such as model pulls and diagram renders, for
+//! the progress area above the input.
+//!
+//! Each task is listed with a spinner, its progress and how long it has
+//! run. While tasks run and nothing is typed in the input, the number keys
+//! cancel the task in that place of the list, and Esc cancels them all;
+//! the code running a task checks whether it was cancelled and stops it.
+
+use std::time::{Duration, Instant};
+
+/// The most tasks the progress area lists; it takes one row each.
+pub const MAX_VISIBLE: usize = 4;
+
+/// The width of a task's progress bar in cells.
+const PROGRESS_BAR_WIDTH: usize = 20;
+
+/// Identifies a task while it runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TaskId(usize);
+
+/// A running task.
+#[derive(Debug, Clone)]
+pub struct Task {
+    id: TaskId,
+    /// What the task does, such as `Pulling llama3.2`.
+    pub label: String,
+    /// What the task is doing at the moment, if it says.
+    pub status: String,
+    /// How much of the task is done, if known.
+    pub fraction: Option<f64>,
+    started: Instant,
+    cancelled: bool,
+}
+
+impl Task {
+    /// Returns the time since the task started.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Describes the task as a line of the progress area: its label and
+    /// status, a progress bar if its progress is known, and how long it
+    /// has run.
+    ///
+    /// # Arguments
+    ///
+    /// * `unicode` - Whether the bar may use block characters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::tasks::Tasks;
+    ///
+    /// let mut tasks = Tasks::default();
+    /// let id = tasks.start("Pulling llama3");
+    /// tasks.update(id, "pulling 6a0746a1ec1a", Some(0.25));
+    /// assert_eq!(
+    ///     tasks.iter().next().unwrap().describe(false),
+    ///     "Pulling llama3: pulling 6a0746a1ec1a [#####---------------]  25% · 0s"
+    /// );
+    /// ```
+    #[must_use]
+    pub fn describe(&self, unicode: bool) -> String {
+        let mut line = self.label.clone();
+        if !self.status.is_empty() {
+            line.push_str(&format!(": {}", self.status));
+        }
+        if let Some(fraction) = self.fraction {
+            #[allow(
+                clippy::cast_possible_truncation,
+                clippy::cast_sign_loss,
+                clippy::cast_precision_loss
+            )]
+            let filled = (fraction.clamp(0.0, 1.0) * PROGRESS_BAR_WIDTH as f64).round() as usize;
+            let (full, empty) = if unicode { ('█', '░') } else { ('#', '-') };
+            let bar: String = std::iter::repeat_n(full, filled)
+                .chain(std::iter::repeat_n(empty, PROGRESS_BAR_WIDTH - filled))
+                .collect();
+            line.push_str(&format!(" [{bar}] {:>3.0}%", fraction * 100.0));
+        }
+        line.push_str(&format!(" · {}s", self.elapsed().as_secs()));
+        line
+    }
+}
+
+/// The running tasks, in the order they started.
+#[derive(Debug, Default)]
+pub struct Tasks {
+    tasks: Vec<Task>,
+    next_id: usize,
+}
+
+impl Tasks {
+    /// Starts tracking a task.
+    ///
+    /// # Returns
+    ///
+    /// The id that updates and finishes the task.
+    pub fn start(&mut self, label: impl Into<String>) -> TaskId {
+        let id = TaskId(self.next_id);
+        self.next_id += 1;
+        self.tasks.push(Task {
+            id,
+            label: label.into(),
+            status: String::new(),
+            fraction: None,
+            started: Instant::now(),
+            cancelled: false,
+        });
+        id
+    }
+
+    /// Updates what a task is doing and how much of it is done.
+    pub fn update(&mut self, id: TaskId, status: impl Into<String>, fraction: Option<f64>) {
+        if let Some(task) = self.tasks.iter_mut().find(|task| task.id == id) {
+            task.status = status.into();
+            task.fraction = fraction;
+        }
+    }
+
+    /// Stops tracking a task that ended or was stopped.
+    pub fn finish(&mut self, id: TaskId) {
+        self.tasks.retain(|task| task.id != id);
+    }
+
+    /// Cancels the task in a place of the list, counting from zero.
+    ///
+    /// # Returns
+    ///
+    /// `true` if there is a task in that place.
+    pub fn cancel(&mut self, position: usize) -> bool {
+        self.tasks
+            .get_mut(position)
+            .map(|task| task.cancelled = true)
+            .is_some()
+    }
+
+    /// Cancels every task.
+    pub fn cancel_all(&mut self) {
+        for task in &mut self.tasks {
+            task.cancelled = true;
+        }
+    }
+
+    /// Returns `true` if a task was cancelled, or has ended.
+    #[must_use]
+    pub fn is_cancelled(&self, id: TaskId) -> bool {
+        self.tasks
+            .iter()
+            .find(|task| task.id == id)
+            .is_none_or(|task| task.cancelled)
+    }
+
+    /// Returns the running tasks, in the order they started.
+    pub fn iter(&self) -> impl Iterator<Item = &Task> {
+        self.tasks.iter()
+    }
+
+    /// Returns the number of running tasks.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    /// Returns `true` if no task is running.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
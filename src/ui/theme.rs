@@ -0,0 +1,141 @@
+//! Resolves the configured role prefixes and colors for rendering.
+
+use std::str::FromStr;
+
+use ratatui::style::{Color, Style};
+use tracing::warn;
+
+use crate::config::ThemeConfig;
+use crate::message::Message;
+
+/// The prefix and style of one message role.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoleStyle {
+    /// Text shown before the first line of a message, where `{model}`
+    /// stands for the model that answered it.
+    pub prefix: String,
+    /// Style of the prefix.
+    pub style: Style,
+}
+
+/// Prefixes and colors for each message role.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    /// Style of user messages.
+    pub user: RoleStyle,
+    /// Style of assistant messages.
+    pub assistant: RoleStyle,
+    /// Style of system notices.
+    pub system: RoleStyle,
+    /// The model named in prefixes of messages that do not record one.
+    pub model: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::from_config(&ThemeConfig::default(), "")
+    }
+}
+
+impl Theme {
+    /// Builds a theme from the configuration.
+    ///
+    /// Colors that cannot be parsed fall back to the defaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The theme section of the configuration.
+    /// * `model` - The name of the configured model, for prefixes of
+    ///   messages that do not record the model that answered them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::config::ThemeConfig;
+    /// use chatti::ui::theme::Theme;
+    /// use ratatui::style::Color;
+    ///
+    /// let config = ThemeConfig {
+    ///     assistant_prefix: "{model}> ".to_string(),
+    ///     assistant_color: "#ff8800".to_string(),
+    ///     ..ThemeConfig::default()
+    /// };
+    /// let theme = Theme::from_config(&config, "llama3");
+    /// assert_eq!(theme.assistant.prefix, "{model}> ");
+    /// assert_eq!(theme.assistant.style.fg, Some(Color::Rgb(0xff, 0x88, 0x00)));
+    /// ```
+    #[must_use]
+    pub fn from_config(config: &ThemeConfig, model: &str) -> Self {
+        let role = |prefix: &str, color: &str, fallback: Color| RoleStyle {
+            prefix: prefix.to_string(),
+            style: Style::default().fg(parse_color(color, fallback)),
+        };
+
+        Theme {
+            user: role(&config.user_prefix, &config.user_color, Color::Blue),
+            assistant: role(
+                &config.assistant_prefix,
+                &config.assistant_color,
+                Color::Green,
+            ),
+            system: role(&config.system_prefix, &config.system_color, Color::Yellow),
+            model: model.to_string(),
+        }
+    }
+
+    /// Returns the style of `role`, if it is a known role.
+    #[must_use]
+    pub fn role(&self, role: &str) -> Option<&RoleStyle> {
+        match role {
+            "user" => Some(&self.user),
+            "assistant" => Some(&self.assistant),
+            "system" => Some(&self.system),
+            _ => None,
+        }
+    }
+
+    /// Returns the prefix shown before a message, with `{model}` replaced
+    /// by the model that answered it, or by the configured model if the
+    /// message does not record one. Unknown roles have no prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::config::ThemeConfig;
+    /// use chatti::message::{Generation, Message};
+    /// use chatti::ui::theme::Theme;
+    ///
+    /// let config = ThemeConfig {
+    ///     assistant_prefix: "{model}> ".to_string(),
+    ///     ..ThemeConfig::default()
+    /// };
+    /// let theme = Theme::from_config(&config, "mistral");
+    /// let mut message = Message::new("assistant", "Hi!");
+    /// assert_eq!(theme.prefix(&message), "mistral> ");
+    ///
+    /// message.generation = Some(Generation {
+    ///     model: "llama3".to_string(),
+    ///     temperature: 0.7,
+    ///     top_p: None,
+    ///     max_tokens: None,
+    /// });
+    /// assert_eq!(theme.prefix(&message), "llama3> ");
+    /// ```
+    #[must_use]
+    pub fn prefix(&self, message: &Message) -> String {
+        let model = message
+            .generation
+            .as_ref()
+            .map_or(self.model.as_str(), |generation| generation.model.as_str());
+        self.role(&message.role)
+            .map(|role| role.prefix.replace("{model}", model))
+            .unwrap_or_default()
+    }
+}
+
+fn parse_color(value: &str, fallback: Color) -> Color {
+    Color::from_str(value).unwrap_or_else(|_| {
+        warn!(color = value, "invalid theme color, using default");
+        fallback
+    })
+}
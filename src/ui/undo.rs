@@ -0,0 +1,89 @@
+//! Keeps undo and redo history for the input buffer.
+
+/// The maximum number of undo steps kept.
+const MAX_STEPS: usize = 100;
+
+/// The kind of change made to the input buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    /// Characters were typed.
+    Insert,
+    /// Characters were deleted.
+    Delete,
+}
+
+/// Undo and redo history of the input buffer.
+///
+/// Consecutive edits of the same kind are grouped into one step, and typing
+/// whitespace ends the current step, so undo removes a word at a time.
+#[derive(Debug, Default, Clone)]
+pub struct UndoStack {
+    undo: Vec<String>,
+    redo: Vec<String>,
+    last_edit: Option<Edit>,
+}
+
+impl UndoStack {
+    /// Records the buffer before an edit.
+    ///
+    /// # Arguments
+    ///
+    /// * `before` - The buffer as it was before the edit.
+    /// * `edit` - The kind of edit about to be made.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use chatti::ui::undo::{Edit, UndoStack};
+    ///
+    /// let mut history = UndoStack::default();
+    /// history.record("", Edit::Insert);
+    /// history.record("h", Edit::Insert);
+    /// assert_eq!(history.undo("hi"), Some(String::new()));
+    /// assert_eq!(history.redo(""), Some("hi".to_string()));
+    /// ```
+    pub fn record(&mut self, before: &str, edit: Edit) {
+        if self.last_edit != Some(edit) {
+            self.undo.push(before.to_string());
+            if self.undo.len() > MAX_STEPS {
+                self.undo.remove(0);
+            }
+        }
+        self.last_edit = Some(edit);
+        self.redo.clear();
+    }
+
+    /// Ends the current step, so the next edit starts a new one.
+    pub fn break_step(&mut self) {
+        self.last_edit = None;
+    }
+
+    /// Returns the buffer before the last step, if there is one.
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - The current buffer, kept for redo.
+    pub fn undo(&mut self, current: &str) -> Option<String> {
+        let previous = self.undo.pop()?;
+        self.redo.push(current.to_string());
+        self.last_edit = None;
+        Some(previous)
+    }
+
+    /// Returns the buffer after the last undone step, if there is one.
+    ///
+    /// # Arguments
+    ///
+    /// * `current` - The current buffer, kept for undo.
+    pub fn redo(&mut self, current: &str) -> Option<String> {
+        let next = self.redo.pop()?;
+        self.undo.push(current.to_string());
+        self.last_edit = None;
+        Some(next)
+    }
+
+    /// Forgets all history, for example after the draft was sent.
+    pub fn clear(&mut self) {
+        *self = UndoStack::default();
+    }
+}
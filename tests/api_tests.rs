@@ -0,0 +1,212 @@
+// tests/api_tests.rs
+use chatti::api::{
+    self, check_endpoint, is_local_endpoint, parse_error_message, JsonLines, ServerSentEvents,
+};
+use chatti::config::Config;
+
+#[test]
+fn test_parse_provider_error_shapes() {
+    let anthropic =
+        r#"{"type":"error","error":{"type":"authentication_error","message":"invalid x-api-key"}}"#;
+    assert_eq!(
+        parse_error_message(anthropic).as_deref(),
+        Some("invalid x-api-key [authentication_error]")
+    );
+
+    let openai = r#"{"error":{"message":"You exceeded your current quota","type":"insufficient_quota","param":null,"code":null}}"#;
+    assert_eq!(
+        parse_error_message(openai).as_deref(),
+        Some("You exceeded your current quota [insufficient_quota]")
+    );
+
+    assert_eq!(
+        parse_error_message(r#"{"detail":"Not Found"}"#).as_deref(),
+        Some("Not Found")
+    );
+    assert_eq!(
+        parse_error_message(r#"{"errors":[{"message":"rate limited"}]}"#).as_deref(),
+        Some("rate limited")
+    );
+}
+
+#[test]
+fn test_parse_error_without_message() {
+    assert_eq!(parse_error_message(""), None);
+    assert_eq!(parse_error_message(r#"{"error":""}"#), None);
+    assert_eq!(parse_error_message(r#"{"error":{"code":500}}"#), None);
+}
+
+#[tokio::test]
+async fn test_check_endpoint() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = [0; 1024];
+        let _ = socket.read(&mut request).await;
+        let _ = socket
+            .write_all(b"HTTP/1.1 404 Not Found\r\ncontent-length: 0\r\n\r\n")
+            .await;
+    });
+
+    let client = reqwest::Client::new();
+    assert!(
+        check_endpoint(&client, &format!("http://{address}/api/chat")).await,
+        "any HTTP response means the endpoint is reachable"
+    );
+    // The listener is closed once the server is done.
+    server.await.unwrap();
+    assert!(
+        !check_endpoint(
+            &reqwest::Client::new(),
+            &format!("http://{address}/api/chat")
+        )
+        .await
+    );
+    assert!(!check_endpoint(&client, "not a url").await);
+}
+
+#[tokio::test]
+async fn test_check_endpoint_times_out() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    // The server accepts the connection and never answers.
+    tokio::spawn(async move {
+        let (_socket, _) = listener.accept().await.unwrap();
+        std::future::pending::<()>().await;
+    });
+
+    // The clock skips ahead to the deadline instead of waiting for it.
+    tokio::time::pause();
+    assert!(!check_endpoint(&reqwest::Client::new(), &format!("http://{address}/")).await);
+}
+
+#[tokio::test]
+async fn test_client_sends_the_key_with_every_request() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = [0; 1024];
+        let read = socket.read(&mut request).await.unwrap();
+        let _ = socket
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+            .await;
+        String::from_utf8_lossy(&request[..read]).to_lowercase()
+    });
+
+    let client = api::client(Some("sk-test")).unwrap();
+    assert!(check_endpoint(&client, &format!("http://{address}/api/chat")).await);
+    let request = server.await.unwrap();
+    assert!(
+        request.contains("authorization: bearer sk-test"),
+        "{request}"
+    );
+}
+
+#[test]
+fn test_local_only_endpoints() {
+    assert!(is_local_endpoint("http://127.0.0.2:8080/completion"));
+    assert!(is_local_endpoint("http://ollama.localhost/api/chat"));
+    assert!(!is_local_endpoint("http://192.168.1.20:11434/api/chat"));
+    assert!(!is_local_endpoint("not a url"));
+
+    let mut config: Config = toml::from_str(
+        "model = \"llama3\"\nstream = true\ntemperature = 0.7\nallowed_hosts = [\"192.168.1.20\"]",
+    )
+    .unwrap();
+    let remote = "https://api.example.com/v1/chat/completions";
+    assert!(config.check_endpoint(remote).is_ok(), "off by default");
+
+    config.local_only = true;
+    assert!(config
+        .check_endpoint("http://192.168.1.20:11434/api/chat")
+        .is_ok());
+    let error = config.check_endpoint(remote).unwrap_err();
+    assert_eq!(
+        error.display_message(),
+        "Local-only mode: not sending a request to api.example.com, which is neither this machine nor in `allowed_hosts`"
+    );
+}
+
+fn contents(values: Vec<Result<serde_json::Value, serde_json::Error>>) -> Vec<String> {
+    values
+        .into_iter()
+        .map(|value| match value {
+            Ok(json) => json["message"]["content"]
+                .as_str()
+                .unwrap_or_default()
+                .to_string(),
+            Err(_) => String::from("<error>"),
+        })
+        .collect()
+}
+
+#[test]
+fn test_json_lines_split_across_chunks() {
+    let stream = "{\"message\":{\"content\":\"caf\u{e9} \\\"quoted\\\"\\nline\"}}\n{\"message\":{\"content\":\"\u{1f980}\"},\"done\":true}\n";
+    // Every split point, including inside multi-byte characters and escapes.
+    for split in 1..stream.len() {
+        let mut lines = JsonLines::new();
+        let (head, tail) = stream.as_bytes().split_at(split);
+        let mut values = contents(lines.push(head));
+        values.extend(contents(lines.push(tail)));
+        assert_eq!(
+            values,
+            vec!["caf\u{e9} \"quoted\"\nline", "\u{1f980}"],
+            "split at byte {split}"
+        );
+        assert!(lines.finish().is_none());
+    }
+}
+
+#[test]
+fn test_json_lines_keeps_values_around_malformed_lines() {
+    let mut lines = JsonLines::new();
+    let values = contents(lines.push(
+        b"{\"message\":{\"content\":\"a\"}}\nnot json\n{\"message\":\n  {\"content\":\"b\"}}\n",
+    ));
+    assert_eq!(values, vec!["a", "<error>", "b"]);
+
+    assert!(lines.push(b"{\"message\":{\"content\":\"c\"}}").len() == 1);
+    assert!(lines.push(b"{\"message\":{\"content\":").is_empty());
+    assert!(matches!(lines.finish(), Some(Err(err)) if err.is_eof()));
+}
+
+#[test]
+fn test_server_sent_events_split_across_chunks() {
+    let stream = "data: {\"content\":\"caf\u{e9}\",\"stop\":false}\n\n: keep-alive\n\ndata: {\"content\":\"\u{1f980}\",\"stop\":true}\r\n\r\ndata: [DONE]\n\n";
+    for split in 1..stream.len() {
+        let mut events = ServerSentEvents::new();
+        let (head, tail) = stream.as_bytes().split_at(split);
+        let mut values = events.push(head);
+        values.extend(events.push(tail));
+        let contents: Vec<_> = values
+            .into_iter()
+            .map(|value| value.unwrap()["content"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(
+            contents,
+            vec!["caf\u{e9}", "\u{1f980}"],
+            "split at byte {split}"
+        );
+        assert!(events.finish().is_none());
+    }
+}
+
+#[test]
+fn test_server_sent_events_without_final_blank_line() {
+    let mut events = ServerSentEvents::new();
+    assert!(
+        events
+            .push(b"data: not json\n\ndata: {\"content\":\"a\"}")
+            .len()
+            == 1
+    );
+    let last = events.finish().unwrap().unwrap();
+    assert_eq!(last["content"], "a");
+}
@@ -0,0 +1,233 @@
+// tests/attachments_tests.rs
+use chatti::attachments::{
+    dropped_files, head_and_tail, Attachment, AttachmentError, Batch, MAX_BYTES, MAX_WHOLE_BYTES,
+};
+use chatti::commands::Command;
+use chatti::tables::Sampling;
+use chatti::ui::input_handler::InputHandler;
+use chatti::ui::state::{InputMode, State};
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("chatti-attach-{name}-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_dropped_files() {
+    let dir = temp_dir("drop");
+    let spaced = dir.join("meeting notes.txt");
+    let plain = dir.join("main.rs");
+    fs::write(&spaced, "notes").unwrap();
+    fs::write(&plain, "fn main() {}").unwrap();
+    let (spaced_text, plain_text) = (spaced.display().to_string(), plain.display().to_string());
+
+    // Quoted, escaped, unescaped, and as a file URL.
+    assert_eq!(
+        dropped_files(&format!("'{spaced_text}'")),
+        vec![spaced.clone()]
+    );
+    assert_eq!(
+        dropped_files(&spaced_text.replace(' ', "\\ ")),
+        vec![spaced.clone()]
+    );
+    assert_eq!(dropped_files(&spaced_text), vec![spaced.clone()]);
+    assert_eq!(
+        dropped_files(&format!("file://{}", spaced_text.replace(' ', "%20"))),
+        vec![spaced.clone()]
+    );
+
+    // Several files at once.
+    assert_eq!(
+        dropped_files(&format!("\"{spaced_text}\" {plain_text}\n")),
+        vec![spaced.clone(), plain.clone()]
+    );
+    assert_eq!(
+        dropped_files(&format!("{plain_text}\n{spaced_text}")),
+        vec![plain.clone(), spaced]
+    );
+
+    // Anything that is not only paths to existing files is ordinary text.
+    assert!(dropped_files(&format!("look at {plain_text}")).is_empty());
+    assert!(dropped_files(&format!("{plain_text} {}", dir.join("gone.txt").display())).is_empty());
+    assert!(dropped_files(&dir.display().to_string()).is_empty());
+    assert!(dropped_files("main.rs").is_empty());
+    assert!(dropped_files("  ").is_empty());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_attachment_guardrails() {
+    let dir = temp_dir("guard");
+    let small = dir.join("main.rs");
+    fs::write(&small, "fn main() {}\n").unwrap();
+    let attachment = Attachment::load(&small, Sampling::default()).unwrap();
+    assert!(!attachment.truncated);
+    assert_eq!(
+        attachment.context(),
+        format!("File `{}`:\nfn main() {{}}", small.display())
+    );
+    assert_eq!(attachment.summary(), "main.rs: 13 B, ~4 tokens");
+
+    // Binary files are refused, even when they happen to be valid UTF-8.
+    let binary = dir.join("image.png");
+    fs::write(&binary, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+    assert!(matches!(
+        Attachment::load(&binary, Sampling::default()),
+        Err(AttachmentError::Binary { .. })
+    ));
+    let nul = dir.join("data.bin");
+    fs::write(&nul, b"abc\0def").unwrap();
+    let err = Attachment::load(&nul, Sampling::default()).unwrap_err();
+    assert_eq!(err.to_string(), "data.bin is a binary file (7 B), not text");
+
+    // Large files keep their head and tail, and say what was left out.
+    let large = dir.join("server.log");
+    let log: String = (0..20_000).map(|n| format!("request {n} ok ✓\n")).collect();
+    fs::write(&large, &log).unwrap();
+    let attachment = Attachment::load(&large, Sampling::default()).unwrap();
+    assert!(attachment.truncated);
+    assert_eq!(attachment.size, log.len() as u64);
+    assert!(attachment.text.len() <= MAX_BYTES + 100);
+    assert!(attachment.text.starts_with("request 0 ok ✓\n"));
+    assert!(attachment.text.ends_with("request 19999 ok ✓\n"));
+    assert!(log.starts_with(attachment.text.split("[...").next().unwrap()));
+    assert!(attachment.text.contains(" left out ...]\n"));
+    assert!(attachment.full_tokens > attachment.tokens());
+    assert!(attachment.summary().contains("too large to attach whole"));
+
+    // Only the head and tail of large files are read, so size alone does
+    // not refuse them, but documents are read whole and have a limit.
+    let sparse = dir.join("zeros.log");
+    fs::File::create(&sparse)
+        .unwrap()
+        .set_len(MAX_WHOLE_BYTES + 1)
+        .unwrap();
+    assert!(matches!(
+        Attachment::load(&sparse, Sampling::default()),
+        Err(AttachmentError::Binary { .. })
+    ));
+    let document = dir.join("huge.pdf");
+    fs::rename(&sparse, &document).unwrap();
+    assert!(matches!(
+        Attachment::load(&document, Sampling::default()),
+        Err(AttachmentError::TooLarge { .. })
+    ));
+
+    // A single line longer than the budget is cut inside the line.
+    let line = "x".repeat(1000);
+    let cut = head_and_tail(&line, 100);
+    assert!(cut.starts_with(&"x".repeat(50)));
+    assert!(cut.contains("[... 1 line(s), 900 B left out ...]"));
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn test_paste_and_attach() {
+    let mut ui_state = State::new();
+    let handler = InputHandler::new();
+    handler.handle_paste(&mut ui_state, "ignored");
+    assert!(ui_state.input.is_empty());
+
+    ui_state.input_mode = InputMode::Editing;
+    ui_state.input.push_str("Fix ");
+    handler.handle_paste(&mut ui_state, "this\r\nbug");
+    assert_eq!(ui_state.input, "Fix this\nbug");
+    ui_state.undo_input();
+    assert_eq!(ui_state.input, "Fix ");
+
+    ui_state.attach_context(String::from("File `a.rs`:\na"));
+    ui_state.attach_context(String::from("File `b.rs`:\nb"));
+    assert_eq!(
+        ui_state.pending_context.as_deref(),
+        Some("File `a.rs`:\na\n\nFile `b.rs`:\nb")
+    );
+}
+
+#[test]
+fn test_attach_matching_files() {
+    assert_eq!(
+        Command::parse("/attach src/**/*.rs docs"),
+        Some(Command::Attach(vec![
+            String::from("src/**/*.rs"),
+            String::from("docs")
+        ]))
+    );
+    assert!(matches!(
+        Command::parse("/attach"),
+        Some(Command::Invalid(_))
+    ));
+
+    let dir = temp_dir("glob");
+    let git = |args: &[&str]| {
+        let status = process::Command::new("git")
+            .args(args)
+            .current_dir(&dir)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {args:?} failed");
+    };
+    git(&["init", "-q"]);
+    fs::create_dir_all(dir.join("src/ui")).unwrap();
+    fs::create_dir_all(dir.join("docs")).unwrap();
+    fs::write(dir.join(".gitignore"), "generated.rs\n").unwrap();
+    fs::write(dir.join("src/lib.rs"), "pub mod ui;\n").unwrap();
+    fs::write(dir.join("src/ui/mod.rs"), "pub fn draw() {}\n").unwrap();
+    fs::write(dir.join("src/generated.rs"), "// generated\n").unwrap();
+    fs::write(dir.join("src/.scratch.rs"), "// hidden\n").unwrap();
+    fs::write(dir.join("docs/guide.md"), "# Guide\n".repeat(20)).unwrap();
+    fs::create_dir_all(dir.join("src/target/debug")).unwrap();
+    fs::write(dir.join("src/target/debug/build.rs"), "// built\n").unwrap();
+
+    let patterns = [String::from("src/**/*.rs"), String::from("docs")];
+    let batch = Batch::collect(&patterns, &dir, 1024, Sampling::default()).unwrap();
+    let paths: Vec<PathBuf> = batch.attached.iter().map(|a| a.path.clone()).collect();
+    assert_eq!(
+        paths,
+        vec![
+            PathBuf::from("src/lib.rs"),
+            PathBuf::from("src/ui/mod.rs"),
+            PathBuf::from("docs/guide.md"),
+        ]
+    );
+    assert_eq!(batch.ignored, 1);
+    assert_eq!(
+        batch.context(),
+        "File `src/lib.rs`:\npub mod ui;\n\nFile `src/ui/mod.rs`:\npub fn draw() {}\n\nFile `docs/guide.md`:\n"
+            .to_string()
+            + "# Guide\n".repeat(20).trim_end()
+    );
+    assert!(batch
+        .describe(1024)
+        .ends_with("\n\nSkipped 1 file(s) ignored by git"));
+
+    // Files that do not fit in the budget are left out.
+    let batch = Batch::collect(&patterns, &dir, 100, Sampling::default()).unwrap();
+    assert_eq!(batch.attached.len(), 2);
+    assert_eq!(batch.over_budget, vec![PathBuf::from("docs/guide.md")]);
+    assert!(batch
+        .describe(100)
+        .contains("\n\nLeft out to stay within 100 B: docs/guide.md"));
+
+    // Skipped directories are searched only when a pattern names them.
+    let named = [String::from("src/target")];
+    let batch = Batch::collect(&named, &dir, 1024, Sampling::default()).unwrap();
+    assert_eq!(
+        batch.attached[0].path,
+        PathBuf::from("src/target/debug/build.rs")
+    );
+
+    let batch = Batch::collect(&[String::from("*.py")], &dir, 1024, Sampling::default()).unwrap();
+    assert!(batch.is_empty());
+    assert!(matches!(
+        Batch::collect(&[String::from("src/[")], &dir, 1024, Sampling::default()),
+        Err(AttachmentError::Pattern(_))
+    ));
+
+    fs::remove_dir_all(dir).unwrap();
+}
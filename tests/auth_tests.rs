@@ -0,0 +1,25 @@
+// tests/auth_tests.rs
+use chatti::auth::{choose_key, run_command, AuthError};
+
+#[test]
+fn test_key_falls_back_to_env_then_config() {
+    let unavailable = || Err(AuthError::Usage("no keyring".to_string()));
+
+    assert_eq!(
+        choose_key(Ok(None), Some("env-key".to_string()), Some("config-key")),
+        Some("env-key".to_string())
+    );
+    assert_eq!(
+        choose_key(unavailable(), None, Some("config-key")),
+        Some("config-key".to_string())
+    );
+    assert_eq!(choose_key(unavailable(), None, Some("  ")), None);
+}
+
+#[test]
+fn test_auth_command_usage() {
+    for args in [vec![], vec!["rotate"], vec!["set", "work", "extra"]] {
+        let args: Vec<String> = args.into_iter().map(String::from).collect();
+        assert!(matches!(run_command(&args), Err(AuthError::Usage(_))));
+    }
+}
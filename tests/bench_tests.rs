@@ -0,0 +1,100 @@
+// tests/bench_tests.rs
+use chatti::bench::{self, report, Summary};
+use chatti::cli::{Mode, UsageError};
+use chatti::script::Answer;
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| (*arg).to_string()).collect()
+}
+
+fn answer(prompt: &str, model: &str, response: Result<&str, &str>) -> Answer {
+    Answer {
+        prompt: prompt.to_string(),
+        model: model.to_string(),
+        response: response.map(str::to_string).map_err(str::to_string),
+        prompt_tokens: 3,
+        first_token: Some(Duration::from_millis(250)),
+        elapsed: Duration::from_secs(2),
+    }
+}
+
+#[test]
+fn test_parse_bench() {
+    assert_eq!(
+        Mode::parse(&args(&[
+            "bench",
+            "--models",
+            "llama3, mistral",
+            "-p",
+            "prompts.txt"
+        ])),
+        Ok(Mode::Bench(bench::Options {
+            models: args(&["llama3", "mistral"]),
+            prompts: PathBuf::from("prompts.txt"),
+            report: PathBuf::from(bench::DEFAULT_REPORT),
+        }))
+    );
+    assert_eq!(
+        Mode::parse(&args(&["bench", "-m", "a", "-p", "p.txt", "-r", "out.md"])),
+        Ok(Mode::Bench(bench::Options {
+            models: args(&["a"]),
+            prompts: PathBuf::from("p.txt"),
+            report: PathBuf::from("out.md"),
+        }))
+    );
+    for invalid in [
+        &["bench"][..],
+        &["bench", "--models", "a"],
+        &["bench", "--prompts", "p.txt"],
+        &["bench", "--models", ",", "--prompts", "p.txt"],
+        &["bench", "-m", "a", "-p", "p.txt", "--plain"],
+    ] {
+        assert!(
+            matches!(Mode::parse(&args(invalid)), Err(UsageError(_))),
+            "{invalid:?}"
+        );
+    }
+}
+
+#[test]
+fn test_tokens_per_second() {
+    let answer = answer(
+        "Hi",
+        "llama3",
+        Ok("one two three four five six seven eight"),
+    );
+    assert!(answer.response_tokens() > 0);
+    let expected = answer.response_tokens() as f64 / 2.0;
+    assert!((answer.tokens_per_second() - expected).abs() < f64::EPSILON);
+    let failed = self::answer("Hi", "llama3", Err("timeout"));
+    assert_eq!(failed.tokens_per_second(), 0.0);
+}
+
+#[test]
+fn test_report_lists_answers_by_prompt() {
+    let answers = [
+        answer("What is Rust?", "llama3", Ok("A language.")),
+        answer("Why?", "llama3", Ok("Safety.")),
+        answer("What is Rust?", "mistral", Ok("A metal oxide.")),
+        answer("Why?", "mistral", Err("timeout")),
+    ];
+    let summaries: Vec<Summary> = ["llama3", "mistral"]
+        .iter()
+        .map(|model| Summary::of(model, &answers))
+        .collect();
+    assert_eq!(summaries[1].answered, 1);
+    assert_eq!(summaries[1].prompts, 2);
+
+    let prompts = vec![String::from("What is Rust?"), String::from("Why?")];
+    let report = report(&prompts, &summaries, &answers);
+    assert!(report.starts_with(
+        "# Benchmark\n\n| Model | Answered | First token | Total | Tok/s |\n|---|---|---|---|---|\n| llama3 | 2/2 | 0.25s | 2.00s |"
+    ));
+    assert!(report.contains("| mistral | 1/2 | 0.25s | 2.00s |"));
+    let why = report.find("## 2. Why?").unwrap();
+    assert!(report[..why].contains("### mistral\n\n_0.25s to first token, 2.00s total,"));
+    assert!(report[..why].contains("A metal oxide."));
+    assert!(report[why..].ends_with("### mistral\n\n**Error:** timeout\n"));
+}
@@ -0,0 +1,66 @@
+// tests/capabilities_tests.rs
+use chatti::config::TerminalConfig;
+use chatti::ui::capabilities::{unicode_from_env, Capabilities, ColorSupport};
+use ratatui::{buffer::Buffer, layout::Rect, style::Color};
+
+#[test]
+fn test_color_support_detection_and_mapping() {
+    assert_eq!(
+        ColorSupport::from_env(Some("24bit"), Some("xterm")),
+        ColorSupport::TrueColor
+    );
+    assert_eq!(
+        ColorSupport::from_env(None, Some("screen-256color")),
+        ColorSupport::Ansi256
+    );
+    assert_eq!(ColorSupport::from_env(None, None), ColorSupport::Ansi16);
+
+    assert_eq!(
+        ColorSupport::Ansi256.adapt(Color::Rgb(128, 128, 128)),
+        Color::Indexed(244)
+    );
+    assert_eq!(
+        ColorSupport::Ansi16.adapt(Color::Indexed(196)),
+        Color::LightRed
+    );
+    assert_eq!(
+        ColorSupport::TrueColor.adapt(Color::Rgb(1, 2, 3)),
+        Color::Rgb(1, 2, 3)
+    );
+}
+
+#[test]
+fn test_capabilities_apply_to_buffer() {
+    let mut buffer = Buffer::empty(Rect::new(0, 0, 2, 1));
+    buffer.content[0].fg = Color::Rgb(0, 0, 0);
+    buffer.content[1].bg = Color::Rgb(255, 255, 255);
+
+    Capabilities {
+        colors: ColorSupport::Ansi16,
+        unicode: false,
+    }
+    .apply(&mut buffer);
+
+    assert_eq!(buffer.content[0].fg, Color::Black);
+    assert_eq!(buffer.content[1].bg, Color::White);
+    assert_eq!(buffer.content[1].fg, Color::Reset);
+}
+
+#[test]
+fn test_unicode_detection_from_locale() {
+    assert!(unicode_from_env(Some(""), Some("de_DE.utf8"), None));
+    assert!(!unicode_from_env(None, None, None));
+    assert!(!unicode_from_env(None, None, Some("POSIX")));
+}
+
+#[test]
+fn test_frame_settings() {
+    let config = TerminalConfig::default();
+    assert!(config.synchronized_output);
+    assert_eq!(config.max_fps, 30);
+
+    let config: TerminalConfig =
+        toml::from_str("colors = \"16\"\nsynchronized_output = false\n").unwrap();
+    assert!(!config.synchronized_output);
+    assert_eq!(config.max_fps, 30);
+}
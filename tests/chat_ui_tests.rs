@@ -11,8 +11,8 @@ fn test_chat_ui_new_response() {
     chat_ui.start_new_response();
     assert_eq!(chat_ui.ui_state.input_mode, InputMode::Waiting);
     assert_eq!(chat_ui.ui_state.current_response, "");
-    assert_eq!(chat_ui.ui_state.messages.last().unwrap().0, "assistant");
-    assert_eq!(chat_ui.ui_state.messages.last().unwrap().1, "");
+    assert_eq!(chat_ui.ui_state.messages.last().unwrap().role, "assistant");
+    assert_eq!(chat_ui.ui_state.messages.last().unwrap().content, "");
 }
 
 #[test]
@@ -23,7 +23,10 @@ fn test_chat_ui_update_response() {
     chat_ui.update_response("Hello");
     chat_ui.update_response(", world!");
     assert_eq!(chat_ui.ui_state.current_response, "Hello, world!");
-    assert_eq!(chat_ui.ui_state.messages.last().unwrap().1, "Hello, world!");
+    assert_eq!(
+        chat_ui.ui_state.messages.last().unwrap().content,
+        "Hello, world!"
+    );
 }
 
 #[test]
@@ -32,6 +35,9 @@ fn test_chat_ui_add_response() {
     let mut chat_ui = create_mock_chat_ui();
     chat_ui.add_response("Test response".to_string());
     assert_eq!(chat_ui.ui_state.input_mode, InputMode::Normal);
-    assert_eq!(chat_ui.ui_state.messages.last().unwrap().0, "assistant");
-    assert_eq!(chat_ui.ui_state.messages.last().unwrap().1, "Test response");
+    assert_eq!(chat_ui.ui_state.messages.last().unwrap().role, "assistant");
+    assert_eq!(
+        chat_ui.ui_state.messages.last().unwrap().content,
+        "Test response"
+    );
 }
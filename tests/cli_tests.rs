@@ -0,0 +1,96 @@
+// tests/cli_tests.rs
+use chatti::cli::{take_trace_requests, InteractiveOptions, Mode, UsageError, USAGE};
+use chatti::script::{Options, OutputFormat};
+use chatti::session::TranscriptFormat;
+use std::path::PathBuf;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| (*arg).to_string()).collect()
+}
+
+#[test]
+fn test_parse_rejects_unknown_or_incomplete_arguments() {
+    for invalid in [
+        &["--script"][..],
+        &["--output", "out.md"],
+        &["--verbose"],
+        &["--script", "p.txt", "--format", "xml"],
+        &["--format", "json"],
+        &["--template"],
+        &["-t", "fix-grammar", "-s", "p.txt"],
+        &["-t", "fix-grammar", "-o", "out.md"],
+        &["--plain", "-s", "p.txt"],
+        &["--print-on-exit"],
+        &["--print-on-exit", "html"],
+        &["-t", "fix-grammar", "--print-on-exit", "plain"],
+    ] {
+        assert!(
+            matches!(Mode::parse(&args(invalid)), Err(UsageError(_))),
+            "{invalid:?}"
+        );
+    }
+}
+
+#[test]
+fn test_parse_modes() {
+    assert_eq!(
+        Mode::parse(&[]),
+        Ok(Mode::Interactive(InteractiveOptions::default()))
+    );
+    assert_eq!(
+        Mode::parse(&args(&["--plain"])),
+        Ok(Mode::Interactive(InteractiveOptions {
+            plain: true,
+            print_on_exit: None,
+        }))
+    );
+    assert_eq!(
+        Mode::parse(&args(&["--print-on-exit", "plain"])),
+        Ok(Mode::Interactive(InteractiveOptions {
+            plain: false,
+            print_on_exit: Some(TranscriptFormat::Plain),
+        }))
+    );
+    assert_eq!(
+        Mode::parse(&args(&["auth", "set", "work"])),
+        Ok(Mode::Auth(args(&["set", "work"])))
+    );
+    assert_eq!(
+        Mode::parse(&args(&["-s", "prompts.yaml"])),
+        Ok(Mode::Script(Options {
+            script: PathBuf::from("prompts.yaml"),
+            output: None,
+            format: OutputFormat::Markdown,
+        }))
+    );
+    assert!(matches!(
+        Mode::parse(&args(&["--format", "json", "--script", "p.txt"])),
+        Ok(Mode::Script(Options {
+            format: OutputFormat::Json,
+            ..
+        }))
+    ));
+    assert_eq!(
+        Mode::parse(&args(&["--template", "fix-grammar"])),
+        Ok(Mode::Filter("fix-grammar".to_string()))
+    );
+}
+
+#[test]
+fn test_usage_error_shows_usage() {
+    let err = Mode::parse(&args(&["--verbose"])).unwrap_err();
+    let text = err.to_string();
+    assert!(text.starts_with("unknown option: --verbose\n"));
+    assert!(text.contains("chatti --template <name>"));
+}
+
+#[test]
+fn test_trace_requests_goes_with_every_mode() {
+    let mut given = args(&["--trace-requests", "-t", "fix-grammar"]);
+    assert!(take_trace_requests(&mut given));
+    assert_eq!(
+        Mode::parse(&given),
+        Ok(Mode::Filter("fix-grammar".to_string()))
+    );
+    assert!(USAGE.contains("--trace-requests"));
+}
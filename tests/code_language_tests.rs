@@ -0,0 +1,72 @@
+// tests/code_language_tests.rs
+use chatti::ui::code_language::guess;
+
+#[test]
+fn test_guess_from_first_line() {
+    assert_eq!(guess("#!/bin/bash -e\nls\n"), Some("bash"));
+    assert_eq!(guess("#!/usr/bin/env node\nrun()\n"), Some("javascript"));
+    assert_eq!(guess("<?php\necho 'hi';\n"), Some("php"));
+    assert_eq!(guess("<!DOCTYPE html>\n<html></html>\n"), Some("html"));
+    assert_eq!(guess("[1, 2, 3]"), Some("json"));
+    assert_eq!(guess("#!/usr/bin/perl\n"), None);
+}
+
+#[test]
+fn test_guess_from_keywords() {
+    let samples = [
+        (
+            "def greet(name):\n    if name is None:\n        return\n    print(name)\n",
+            "python",
+        ),
+        (
+            "const add = (a, b) => a + b;\nconsole.log(add(1, 2));\n",
+            "javascript",
+        ),
+        (
+            "package main\n\nfunc main() {\n\tx := 1\n\tfmt.Println(x)\n}\n",
+            "go",
+        ),
+        (
+            "#include <stdio.h>\n\nint main(void) {\n    printf(\"hi\\n\");\n}\n",
+            "c",
+        ),
+        (
+            "#include <iostream>\n\nint main() {\n    std::cout << \"hi\" << std::endl;\n}\n",
+            "cpp",
+        ),
+        (
+            "public class Main {\n    public static void main(String[] args) {\n        System.out.println(\"hi\");\n    }\n}\n",
+            "java",
+        ),
+        (
+            "cd project\ncargo build --release\nls target | grep chatti\n",
+            "bash",
+        ),
+        (
+            "SELECT name, count(*)\nFROM users\nWHERE active\nGROUP BY name;\n",
+            "sql",
+        ),
+        (
+            "body {\n    margin: 0;\n    padding: 8px;\n}\n",
+            "css",
+        ),
+        (
+            "services:\n  web:\n    image: nginx\n    ports:\n      - \"80:80\"\n",
+            "yaml",
+        ),
+        (
+            "impl Point {\n    fn norm(&self) -> f64 {\n        (self.x * self.x).sqrt()\n    }\n}\n",
+            "rust",
+        ),
+    ];
+    for (code, language) in samples {
+        assert_eq!(guess(code), Some(language), "{code}");
+    }
+}
+
+#[test]
+fn test_ambiguous_code_is_not_guessed() {
+    assert_eq!(guess("x = 1"), None);
+    assert_eq!(guess("hello world"), None);
+    assert_eq!(guess(""), None);
+}
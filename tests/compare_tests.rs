@@ -0,0 +1,79 @@
+// tests/compare_tests.rs
+use chatti::commands::Command;
+use chatti::config::CompareConfig;
+use chatti::message::Message;
+use chatti::ui::input_handler::InputHandler;
+use chatti::ui::state::{InputMode, State};
+use crossterm::event::KeyCode;
+
+fn comparing_state() -> State {
+    let mut ui_state = State::new();
+    ui_state.compare_mode = true;
+    ui_state.compare_models = CompareConfig {
+        models: vec!["left".to_string(), "right".to_string()],
+    }
+    .pair();
+    ui_state.messages.push(Message::new("user", "Hi"));
+    ui_state.input_mode = InputMode::Waiting;
+    ui_state
+}
+
+#[test]
+fn test_compare_keeps_chosen_answer() {
+    assert_eq!(Command::parse("/compare"), Some(Command::Compare));
+
+    let mut ui_state = comparing_state();
+    assert_eq!(
+        ui_state.start_comparison(),
+        Some(["left".to_string(), "right".to_string()])
+    );
+
+    ui_state.update_comparison(0, "from left");
+    ui_state.update_comparison(1, "from right");
+    ui_state.finish_comparison(0);
+    assert_eq!(ui_state.input_mode, InputMode::Waiting);
+    ui_state.finish_comparison(1);
+    assert_eq!(ui_state.input_mode, InputMode::Compare);
+
+    InputHandler::new().handle_compare_mode(&mut ui_state, KeyCode::Char('2'));
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+    assert!(ui_state.comparison.is_none());
+    assert_eq!(
+        ui_state.conversation(),
+        vec![
+            Message::new("user", "Hi"),
+            Message::new("assistant", "from right")
+        ]
+    );
+}
+
+#[test]
+fn test_compare_stop_and_discard() {
+    let mut ui_state = comparing_state();
+    ui_state.start_comparison();
+    ui_state.update_comparison(0, "partial");
+
+    ui_state.stop_response();
+    assert_eq!(
+        ui_state.input_mode,
+        InputMode::Compare,
+        "stopping keeps both partial answers to choose from"
+    );
+
+    InputHandler::new().handle_compare_mode(&mut ui_state, KeyCode::Esc);
+    assert_eq!(ui_state.conversation().len(), 1);
+    assert_eq!(
+        ui_state.messages.last().unwrap().content,
+        "Comparison discarded"
+    );
+}
+
+#[test]
+fn test_compare_needs_two_models() {
+    let mut ui_state = comparing_state();
+    ui_state.compare_models = CompareConfig {
+        models: vec!["only".to_string()],
+    }
+    .pair();
+    assert_eq!(ui_state.start_comparison(), None);
+}
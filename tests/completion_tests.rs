@@ -0,0 +1,123 @@
+// tests/completion_tests.rs
+use chatti::ui::completion::{complete_command, complete_mention, complete_path, Completion};
+use chatti::ui::input_handler::InputHandler;
+use chatti::ui::state::{InputMode, State};
+use crossterm::event::KeyCode;
+
+#[test]
+fn test_complete_command_lists_matching_commands() {
+    let all = complete_command("");
+    assert!(all.iter().any(|s| s.text == "/compare"));
+    assert!(all
+        .iter()
+        .any(|s| s.text == "/model " && s.detail.starts_with("<name>")));
+
+    let texts: Vec<_> = complete_command("mo").into_iter().map(|s| s.text).collect();
+    assert_eq!(texts, vec!["/model ", "/models "]);
+    assert!(complete_command("nope").is_empty());
+
+    assert!(
+        Completion::for_input("/compare").is_none(),
+        "a complete command needs no popup"
+    );
+    assert!(
+        Completion::for_input("/model ll").is_none(),
+        "model names are not paths"
+    );
+}
+
+#[test]
+fn test_complete_path_lists_directory_entries() {
+    let dir = std::env::temp_dir().join(format!("chatti-complete-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("notes")).unwrap();
+    std::fs::write(dir.join("notes.md"), "").unwrap();
+    std::fs::write(dir.join(".hidden"), "").unwrap();
+    let base = format!("{}/", dir.display());
+
+    let texts: Vec<_> = complete_path(&format!("{base}no"))
+        .into_iter()
+        .map(|s| s.text)
+        .collect();
+    assert_eq!(
+        texts,
+        vec![format!("{base}notes.md"), format!("{base}notes/")]
+    );
+    assert_eq!(complete_path(&base).len(), 2, "hidden files are skipped");
+    assert_eq!(complete_path(&format!("{base}.")).len(), 1);
+
+    let completion = Completion::for_input(&format!("/export {base}notes.")).unwrap();
+    assert_eq!(completion.start, "/export ".len());
+    assert_eq!(
+        completion.apply(&format!("/export {base}notes.")),
+        format!("/export {base}notes.md")
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_tab_accepts_selected_completion() {
+    let input_handler = InputHandler::new();
+    let mut ui_state = State::new();
+    ui_state.input_mode = InputMode::Editing;
+
+    for c in "/mo".chars() {
+        input_handler
+            .handle_editing_mode(&mut ui_state, KeyCode::Char(c))
+            .unwrap();
+    }
+    assert_eq!(ui_state.completion.as_ref().unwrap().suggestions.len(), 2);
+    input_handler
+        .handle_editing_mode(&mut ui_state, KeyCode::Down)
+        .unwrap();
+    input_handler
+        .handle_editing_mode(&mut ui_state, KeyCode::Tab)
+        .unwrap();
+    assert_eq!(ui_state.input, "/models ");
+
+    input_handler
+        .handle_editing_mode(&mut ui_state, KeyCode::Char('x'))
+        .unwrap();
+    assert!(ui_state.completion.is_none());
+    input_handler
+        .handle_editing_mode(&mut ui_state, KeyCode::Esc)
+        .unwrap();
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+}
+
+#[test]
+fn test_mentions_complete_files_fuzzily() {
+    let dir = std::env::temp_dir().join(format!("chatti-mention-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("src/ui")).unwrap();
+    std::fs::create_dir_all(dir.join("target")).unwrap();
+    std::fs::write(dir.join("src/ui/state.rs"), "").unwrap();
+    std::fs::write(dir.join("src/main.rs"), "").unwrap();
+    std::fs::write(dir.join("target/state.rs"), "").unwrap();
+
+    let texts: Vec<_> = complete_mention("state", &dir)
+        .into_iter()
+        .map(|s| s.text)
+        .collect();
+    assert_eq!(texts, vec!["@src/ui/state.rs "]);
+    let texts: Vec<_> = complete_mention("srs", &dir)
+        .into_iter()
+        .map(|s| s.text)
+        .collect();
+    assert_eq!(texts.len(), 2);
+    std::fs::write(dir.join("src/lib.rs"), "").unwrap();
+    assert_eq!(
+        complete_mention("srs", &dir).len(),
+        2,
+        "the files listed a moment ago are reused"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let completion = Completion::for_input("Explain @Cargo.to").unwrap();
+    assert_eq!(completion.start, "Explain ".len());
+    assert_eq!(
+        completion.apply("Explain @Cargo.to"),
+        "Explain @Cargo.toml ",
+        "the best match comes first"
+    );
+    assert!(Completion::for_input("Explain @Cargo.toml ").is_none());
+    assert!(Completion::for_input("mail me@").is_none());
+}
@@ -0,0 +1,44 @@
+// tests/continuation_tests.rs
+use chatti::continuation::stitch;
+
+#[test]
+fn test_stitch_into_an_open_code_block() {
+    let earlier = "Here:\n\n```python\ndef area(r):\n    return 3.14";
+
+    // The reopened fence and the repeated start of the line are dropped.
+    assert_eq!(
+        stitch(earlier, "\n```python\n    return 3.14 * r * r\n```\n"),
+        " * r * r\n```\n"
+    );
+    // A continuation picking up where the answer stopped is kept.
+    assert_eq!(stitch(earlier, "159 * r * r\n```"), "159 * r * r\n```");
+
+    // A complete last line written again is dropped with its newline.
+    let earlier = "~~~\nfirst();\nsecond();\n";
+    assert_eq!(
+        stitch(earlier, "~~~\nsecond();\nthird();\n~~~"),
+        "third();\n~~~"
+    );
+
+    // A bare fence with no other after it closes the block.
+    assert_eq!(
+        stitch("```\nlet x = 1;\n", "```\nThat is all."),
+        "```\nThat is all."
+    );
+    // A bare fence followed by code and a closing fence reopens it.
+    assert_eq!(
+        stitch("```\nlet x = 1;\n", "```\nlet y = 2;\n```"),
+        "let y = 2;\n```"
+    );
+}
+
+#[test]
+fn test_stitch_outside_a_code_block() {
+    let earlier = "```rust\nfn main() {}\n```\nThe function does";
+    assert_eq!(stitch(earlier, " nothing."), " nothing.");
+    // A fence opening a new block is kept.
+    assert_eq!(
+        stitch("As an example:\n", "```rust\nfn main() {}\n```"),
+        "```rust\nfn main() {}\n```"
+    );
+}
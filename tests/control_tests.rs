@@ -0,0 +1,101 @@
+// tests/control_tests.rs
+#![cfg(unix)]
+use chatti::commands::Command;
+use chatti::control::{self, ControlCommand};
+use chatti::import::parse_markdown;
+use chatti::message::{MarkFilter, Message};
+use chatti::session::Session;
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+#[test]
+fn test_parse_control_commands() {
+    assert_eq!(
+        ControlCommand::parse("send hello there"),
+        Ok(ControlCommand::Send("hello there".to_string()))
+    );
+    assert_eq!(
+        ControlCommand::parse("export ~/chat.md"),
+        Ok(ControlCommand::Export(Some("~/chat.md".to_string())))
+    );
+    assert_eq!(
+        ControlCommand::parse("export"),
+        Ok(ControlCommand::Export(None))
+    );
+    assert!(ControlCommand::parse("send").is_err());
+    assert!(ControlCommand::parse(r#"send "unclosed"#).is_err());
+    assert!(ControlCommand::parse(r#"send "a" b"#).is_err());
+    assert!(ControlCommand::parse("switch-model").is_err());
+}
+
+#[test]
+fn test_export_and_model_commands() {
+    assert_eq!(
+        Command::parse("/export"),
+        Some(Command::Export(None, MarkFilter::default()))
+    );
+    assert_eq!(
+        Command::parse("/export chat.md"),
+        Some(Command::Export(
+            Some(PathBuf::from("chat.md")),
+            MarkFilter::default()
+        ))
+    );
+    assert_eq!(
+        Command::parse("/model mistral"),
+        Some(Command::Model("mistral".to_string()))
+    );
+    assert!(matches!(
+        Command::parse("/model"),
+        Some(Command::Invalid(_))
+    ));
+}
+
+#[test]
+fn test_exported_markdown_can_be_imported() {
+    let messages = vec![
+        Message::new("user", "What is Rust?"),
+        Message::new("assistant", "A language.\n\n## Features\n\n- Fast"),
+        Message::new("user", "Thanks"),
+    ];
+    let mut session = Session::from_messages(messages.clone());
+    session.system_prompt = Some("Be brief.".to_string());
+
+    let imported = parse_markdown(&session.to_markdown());
+    assert_eq!(imported[0], Message::new("system", "Be brief."));
+    assert_eq!(imported[1], messages[0]);
+    assert_eq!(imported.last(), messages.last());
+}
+
+#[tokio::test]
+async fn test_socket_forwards_commands() {
+    let path = std::env::temp_dir().join(format!("chatti-control-{}.sock", std::process::id()));
+    let (socket, mut rx) = control::listen(&path).await.unwrap();
+    assert_eq!(socket.path(), path);
+    let mode = std::os::unix::fs::PermissionsExt::mode(&path.metadata().unwrap().permissions());
+    assert_eq!(mode & 0o777, 0o600, "only the user can connect");
+
+    let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+    let (reader, mut writer) = stream.into_split();
+    let mut replies = BufReader::new(reader).lines();
+
+    writer
+        .write_all(b"send \"two\\nlines\"\nswitch-model mistral\nfly\n")
+        .await
+        .unwrap();
+    assert_eq!(replies.next_line().await.unwrap().unwrap(), "ok");
+    assert_eq!(replies.next_line().await.unwrap().unwrap(), "ok");
+    assert_eq!(
+        replies.next_line().await.unwrap().unwrap(),
+        "error: unknown command: fly"
+    );
+    assert_eq!(rx.recv().await.unwrap(), "two\nlines");
+    assert_eq!(rx.recv().await.unwrap(), "/model mistral");
+
+    let (second, _rx) = control::listen(&path).await.unwrap();
+    assert_ne!(second.path(), path, "a live socket is not replaced");
+    drop(second);
+
+    drop(socket);
+    assert!(!path.exists(), "the socket is removed when dropped");
+}
@@ -0,0 +1,115 @@
+// tests/dataset_tests.rs
+use chatti::commands::Command;
+use chatti::dataset::{pairs, to_jsonl, DatasetFormat};
+use chatti::message::{Mark, MarkFilter, Message};
+use std::path::PathBuf;
+
+#[test]
+fn test_dataset_pairs() {
+    let mut prompt = Message::new("user", "Explain this");
+    prompt.attachment = Some("fn main() {}".to_string());
+    let mut translated = Message::new("assistant", "Explication");
+    translated.translation = Some("French".to_string());
+    let mut messages = vec![
+        prompt,
+        Message::new("assistant", "It does nothing."),
+        Message::new("assistant", "An empty program."),
+        translated,
+        Message::new("user", "Thanks"),
+        Message::new("assistant", "You're welcome."),
+        Message::new("user", "Unanswered"),
+    ];
+    messages[1].toggle_mark(Mark::Favorite);
+    messages[4].toggle_mark(Mark::Favorite);
+    messages[6].toggle_mark(Mark::Favorite);
+
+    // The marked answer is kept over later ones, and the prompt keeps its
+    // context; an exchange without an answer is left out.
+    let favorites = MarkFilter {
+        only: vec![Mark::Favorite],
+        skip: Vec::new(),
+    };
+    assert_eq!(
+        pairs(&messages, &favorites),
+        vec![
+            (
+                "Explain this\n\nContext:\n```\nfn main() {}\n```".to_string(),
+                "It does nothing.".to_string()
+            ),
+            ("Thanks".to_string(), "You're welcome.".to_string()),
+        ]
+    );
+
+    // Without an answer with a mark asked for, the last answer sent, not a
+    // translation, is kept.
+    let all = pairs(&messages, &MarkFilter::default());
+    assert_eq!(all.len(), 2);
+    assert_eq!(all[0].1, "An empty program.");
+}
+
+#[test]
+fn test_dataset_lines() {
+    let pairs = vec![
+        ("Hi".to_string(), "Hello!".to_string()),
+        ("Say \"bye\"".to_string(), "Bye\nnow".to_string()),
+    ];
+    let lines = to_jsonl(DatasetFormat::OpenAi, Some("Be brief."), &pairs);
+    let lines: Vec<&str> = lines.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(
+        lines[0],
+        r#"{"messages":[{"content":"Be brief.","role":"system"},{"content":"Hi","role":"user"},{"content":"Hello!","role":"assistant"}]}"#
+    );
+    let line: serde_json::Value = serde_json::from_str(lines[1]).unwrap();
+    assert_eq!(line["messages"][1]["content"], "Say \"bye\"");
+    assert_eq!(line["messages"][2]["content"], "Bye\nnow");
+
+    let lines = to_jsonl(DatasetFormat::ShareGpt, Some("Be brief."), &pairs[..1]);
+    let line: serde_json::Value = serde_json::from_str(lines.trim_end()).unwrap();
+    assert_eq!(line["conversations"][0]["from"], "system");
+    assert_eq!(line["conversations"][1]["from"], "human");
+    assert_eq!(line["conversations"][2]["from"], "gpt");
+    assert_eq!(line["conversations"][2]["value"], "Hello!");
+}
+
+#[test]
+fn test_dataset_command() {
+    let favorites = MarkFilter {
+        only: vec![Mark::Favorite],
+        skip: Vec::new(),
+    };
+    assert_eq!(
+        Command::parse("/dataset"),
+        Some(Command::Dataset(
+            DatasetFormat::OpenAi,
+            favorites.clone(),
+            None
+        ))
+    );
+    assert_eq!(
+        Command::parse("/dataset sharegpt -bad set.jsonl"),
+        Some(Command::Dataset(
+            DatasetFormat::ShareGpt,
+            MarkFilter {
+                skip: vec![Mark::Bad],
+                ..favorites
+            },
+            Some(PathBuf::from("set.jsonl"))
+        ))
+    );
+    assert_eq!(
+        Command::parse("/dataset +good"),
+        Some(Command::Dataset(
+            DatasetFormat::OpenAi,
+            MarkFilter {
+                only: vec![Mark::Good],
+                skip: Vec::new(),
+            },
+            None
+        ))
+    );
+    assert!(matches!(
+        Command::parse("/dataset +great"),
+        Some(Command::Invalid(_))
+    ));
+}
@@ -0,0 +1,105 @@
+// tests/diagrams_tests.rs
+use chatti::commands::Command;
+use chatti::config::DiagramsConfig;
+use chatti::diagrams::{self, Diagram, DiagramError, Kind};
+use chatti::images;
+use chatti::message::Message;
+use chatti::ui::state::State;
+use std::fs;
+use std::sync::atomic::AtomicBool;
+
+fn settings(name: &str, graphviz: &str) -> DiagramsConfig {
+    let directory =
+        std::env::temp_dir().join(format!("chatti-diagrams-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    DiagramsConfig {
+        graphviz: graphviz.to_string(),
+        directory: Some(directory),
+        ..DiagramsConfig::default()
+    }
+}
+
+#[test]
+fn test_render_diagram_once() {
+    // Counts the renders next to the images, to see that they are reused.
+    let config = settings(
+        "once",
+        "cp {input} {output} && echo >> \"$(dirname {output})/renders\"",
+    );
+    let diagram = Diagram {
+        kind: Kind::Graphviz,
+        source: String::from("digraph { a -> b }\n"),
+    };
+
+    let image = diagrams::render(&diagram, &config, &AtomicBool::default()).unwrap();
+    assert_eq!(image.extension().unwrap(), "png");
+    assert_eq!(fs::read_to_string(&image).unwrap(), diagram.source);
+    assert_eq!(
+        diagrams::render(&diagram, &config, &AtomicBool::default()).unwrap(),
+        image
+    );
+    let directory = config.directory.unwrap();
+    assert_eq!(fs::read_to_string(directory.join("renders")).unwrap(), "\n");
+
+    fs::remove_dir_all(directory).unwrap();
+}
+
+#[test]
+fn test_render_diagram_failures() {
+    let diagram = Diagram {
+        kind: Kind::Graphviz,
+        source: String::from("digraph {"),
+    };
+
+    let config = settings("failed", "echo 'syntax error' >&2; exit 1");
+    let err = diagrams::render(&diagram, &config, &AtomicBool::default()).unwrap_err();
+    assert!(matches!(err, DiagramError::Failed { ref stderr, .. } if stderr == "syntax error"));
+    fs::remove_dir_all(config.directory.unwrap()).unwrap();
+
+    let config = settings("missing", "true");
+    let err = diagrams::render(&diagram, &config, &AtomicBool::default()).unwrap_err();
+    assert!(matches!(err, DiagramError::NoOutput { .. }));
+    fs::remove_dir_all(config.directory.unwrap()).unwrap();
+
+    let mut config = settings("slow", "sleep 30");
+    config.timeout_secs = 0;
+    let err = diagrams::render(&diagram, &config, &AtomicBool::default()).unwrap_err();
+    assert!(matches!(err, DiagramError::TimedOut { seconds: 0, .. }));
+    fs::remove_dir_all(config.directory.unwrap()).unwrap();
+
+    let config = settings("cancelled", "sleep 30");
+    let err = diagrams::render(&diagram, &config, &AtomicBool::new(true)).unwrap_err();
+    assert!(matches!(err, DiagramError::Cancelled { .. }));
+    fs::remove_dir_all(config.directory.unwrap()).unwrap();
+}
+
+#[test]
+fn test_diagrams_of_the_selected_answer() {
+    assert_eq!(Command::parse("/diagrams"), Some(Command::Diagrams));
+
+    let mut ui_state = State::new();
+    ui_state.messages = vec![
+        Message::new("user", "Draw it"),
+        Message::new("assistant", "```dot\ndigraph { a -> b }\n```"),
+        Message::new("user", "```mermaid\ngraph TD; A-->B;\n```"),
+        Message::new("assistant", "No diagram here"),
+    ];
+    // Falls back to the latest answer with diagrams; prompts have none.
+    ui_state.list_state.select(Some(3));
+    let (index, found) = ui_state.diagram_answer().unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(found[0].kind, Kind::Graphviz);
+    ui_state.request_diagrams();
+    assert!(ui_state.take_diagrams_request());
+
+    let image = std::env::temp_dir().join("chatti-diagram.png");
+    ui_state.show_diagrams(index, vec![image.clone()]);
+    let shown = images::of(&ui_state.messages[1]);
+    assert_eq!(shown.len(), 1);
+    assert_eq!(shown[0].label, "diagram 1");
+    assert_eq!(shown[0].source, images::Source::File(image));
+
+    ui_state.messages.truncate(1);
+    ui_state.request_diagrams();
+    assert!(!ui_state.take_diagrams_request());
+}
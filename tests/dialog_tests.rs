@@ -0,0 +1,36 @@
+// tests/dialog_tests.rs
+use chatti::ui::dialog::{Answer, Dialog, Kind};
+use crossterm::event::KeyCode;
+
+#[test]
+fn test_confirm_dialog_answers() {
+    let mut dialog = Dialog::confirm("Delete the model llama3?");
+    assert_eq!(dialog.hint(), "y/Enter: yes   n/Esc: no");
+    assert_eq!(dialog.handle_key(KeyCode::Char('x')), None);
+    assert_eq!(dialog.handle_key(KeyCode::Char('Y')), Some(Answer::Yes));
+    assert_eq!(dialog.handle_key(KeyCode::Enter), Some(Answer::Yes));
+    assert_eq!(dialog.handle_key(KeyCode::Char('n')), Some(Answer::No));
+    assert_eq!(dialog.handle_key(KeyCode::Esc), Some(Answer::No));
+}
+
+#[test]
+fn test_prompt_dialog_edits_its_text() {
+    let mut dialog = Dialog::prompt("notes.md already exists.", "notes.md");
+    assert_eq!(dialog.hint(), "Enter: OK   Esc: cancel");
+    // Letters are typed, not answers.
+    assert_eq!(dialog.handle_key(KeyCode::Char('y')), None);
+    assert_eq!(dialog.kind, Kind::Prompt(String::from("notes.mdy")));
+    for _ in 0.."notes.mdy".len() {
+        dialog.handle_key(KeyCode::Backspace);
+    }
+    // A blank answer is not taken.
+    assert_eq!(dialog.handle_key(KeyCode::Enter), None);
+    for c in " other.md ".chars() {
+        dialog.handle_key(KeyCode::Char(c));
+    }
+    assert_eq!(
+        dialog.handle_key(KeyCode::Enter),
+        Some(Answer::Text(String::from("other.md")))
+    );
+    assert_eq!(dialog.handle_key(KeyCode::Esc), Some(Answer::No));
+}
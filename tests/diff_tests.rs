@@ -0,0 +1,122 @@
+// tests/diff_tests.rs
+use chatti::message::{Generation, Message};
+use chatti::ui::diff::{diff_words, Change};
+use chatti::ui::input_handler::InputHandler;
+use chatti::ui::state::{InputMode, State};
+use crossterm::event::KeyCode;
+
+fn generation(model: &str) -> Generation {
+    Generation {
+        model: model.to_string(),
+        temperature: 0.7,
+        top_p: None,
+        max_tokens: None,
+    }
+}
+
+#[test]
+fn test_diff_words() {
+    assert_eq!(
+        diff_words("same text", "same text"),
+        vec![Change::Same("same text".to_string())]
+    );
+    assert_eq!(
+        diff_words("", "new answer"),
+        vec![Change::Added("new answer".to_string())]
+    );
+    assert_eq!(
+        diff_words("Rust is fast and safe.", "Rust is very fast and safe."),
+        vec![
+            Change::Same("Rust is ".to_string()),
+            Change::Added("very ".to_string()),
+            Change::Same("fast and safe.".to_string()),
+        ]
+    );
+
+    // A replaced phrase is one change rather than one per word.
+    let changes = diff_words("It is a small cat.", "It is one big dog.");
+    assert_eq!(
+        changes,
+        vec![
+            Change::Same("It is ".to_string()),
+            Change::Removed("a small cat.".to_string()),
+            Change::Added("one big dog.".to_string()),
+        ]
+    );
+
+    // The changes give back both texts.
+    let (old, new) = ("one two\nthree four", "one 2\nthree four five");
+    let changes = diff_words(old, new);
+    let text = |keep: fn(&Change) -> bool| -> String {
+        changes
+            .iter()
+            .filter(|change| keep(change))
+            .map(Change::text)
+            .collect()
+    };
+    assert_eq!(text(|c| !matches!(c, Change::Added(_))), old);
+    assert_eq!(text(|c| !matches!(c, Change::Removed(_))), new);
+}
+
+#[test]
+fn test_long_attempts_are_diffed_by_line() {
+    // Different words on every line, too many to diff word by word.
+    let old: String = (0..1000).map(|n| format!("old words {n}\n")).collect();
+    let mut new: String = (0..1000).map(|n| format!("new words {n}\n")).collect();
+    new.insert_str(0, "kept\n");
+    let old = format!("kept\n{old}");
+    let changes = diff_words(&old, &new);
+    assert_eq!(changes[0], Change::Same("kept\n".to_string()));
+    let removed: String = changes
+        .iter()
+        .filter(|change| !matches!(change, Change::Added(_)))
+        .map(Change::text)
+        .collect();
+    assert_eq!(removed, old);
+    let added: String = changes
+        .iter()
+        .filter(|change| !matches!(change, Change::Removed(_)))
+        .map(Change::text)
+        .collect();
+    assert_eq!(added, new);
+}
+
+#[test]
+fn test_open_diff_between_attempts() {
+    let mut ui_state = State::new();
+    ui_state.messages.push(Message::new("user", "Hi"));
+    ui_state.messages.push(Message {
+        generation: Some(generation("llama3")),
+        ..Message::new("assistant", "Hello there")
+    });
+
+    // An answer that was never retried has nothing to compare with.
+    ui_state.list_state.select(Some(1));
+    ui_state.open_diff();
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+    assert!(ui_state.diff.is_none());
+    assert_eq!(
+        ui_state.messages.last().map(|m| m.role.as_str()),
+        Some("system")
+    );
+    ui_state.messages.pop();
+
+    let (_, slot) = ui_state.start_retry(1, generation("mistral")).unwrap();
+    ui_state.update_retry(slot, "Hello friend");
+    ui_state.finish_retry(slot, None);
+    ui_state.input_mode = InputMode::Normal;
+    assert_eq!(ui_state.previous_attempt(slot), Some(1));
+    assert_eq!(ui_state.previous_attempt(1), None);
+
+    ui_state.list_state.select(Some(slot));
+    InputHandler::new().handle_normal_mode(&mut ui_state, KeyCode::Char('d'));
+    assert_eq!(ui_state.input_mode, InputMode::Diff);
+    let diff = ui_state.diff.as_ref().unwrap();
+    assert!(diff.old_label.contains("llama3"));
+    assert!(diff.new_label.contains("mistral"));
+    assert_eq!(diff.counts(), (1, 1));
+
+    InputHandler::new().handle_diff_mode(&mut ui_state, KeyCode::Esc);
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+    assert!(ui_state.diff.is_none());
+}
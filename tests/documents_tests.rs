@@ -0,0 +1,145 @@
+// tests/documents_tests.rs
+#![cfg(feature = "documents")]
+
+use chatti::attachments::{Attachment, AttachmentError};
+use chatti::documents::{extract, DocumentError, Kind};
+use chatti::tables::Sampling;
+use lopdf::content::{Content, Operation};
+use lopdf::{dictionary, Document, Object, Stream};
+use std::io::{Cursor, Write};
+use zip::write::SimpleFileOptions;
+
+/// Builds a PDF with one page per text.
+fn pdf(pages: &[&str]) -> Vec<u8> {
+    let mut document = Document::with_version("1.5");
+    let pages_id = document.new_object_id();
+    let font_id = document.add_object(dictionary! {
+        "Type" => "Font",
+        "Subtype" => "Type1",
+        "BaseFont" => "Courier",
+    });
+    let resources_id = document.add_object(dictionary! {
+        "Font" => dictionary! { "F1" => font_id },
+    });
+    let kids: Vec<Object> = pages
+        .iter()
+        .map(|text| {
+            let content = Content {
+                operations: vec![
+                    Operation::new("BT", vec![]),
+                    Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                    Operation::new("Td", vec![72.into(), 720.into()]),
+                    Operation::new("Tj", vec![Object::string_literal(*text)]),
+                    Operation::new("ET", vec![]),
+                ],
+            };
+            let content_id =
+                document.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+            document
+                .add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                    "Resources" => resources_id,
+                    "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+                })
+                .into()
+        })
+        .collect();
+    document.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Count" => i64::try_from(kids.len()).unwrap(),
+            "Kids" => kids,
+        }),
+    );
+    let catalog_id = document.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+    });
+    document.trailer.set("Root", catalog_id);
+    let mut bytes = Vec::new();
+    document.save_to(&mut bytes).unwrap();
+    bytes
+}
+
+/// Builds a DOCX file around the given body XML.
+fn docx(body: &str) -> Vec<u8> {
+    let mut archive = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    archive
+        .start_file("word/document.xml", SimpleFileOptions::default())
+        .unwrap();
+    write!(
+        archive,
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:document xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main"><w:body>{body}</w:body></w:document>"#
+    )
+    .unwrap();
+    archive.finish().unwrap().into_inner()
+}
+
+fn paragraph(style: Option<&str>, text: &str) -> String {
+    let style = style.map_or_else(String::new, |style| {
+        format!(r#"<w:pPr><w:pStyle w:val="{style}"/></w:pPr>"#)
+    });
+    format!("<w:p>{style}<w:r><w:t>{text}</w:t></w:r></w:p>")
+}
+
+#[test]
+fn test_extract_pdf_pages() {
+    let text = extract(Kind::Pdf, &pdf(&["Quarterly results", "Outlook"])).unwrap();
+    assert!(text.starts_with("[Page 1]\nQuarterly results"));
+    assert!(text.contains("\n\n[Page 2]\nOutlook"));
+
+    assert!(matches!(
+        extract(Kind::Pdf, &pdf(&[""])),
+        Err(DocumentError::NoText)
+    ));
+    assert!(extract(Kind::Pdf, b"not a pdf").is_err());
+}
+
+#[test]
+fn test_extract_docx_sections() {
+    let body = [
+        paragraph(Some("Title"), "Design notes"),
+        paragraph(Some("Heading1"), "Goals"),
+        paragraph(None, "Keep it small."),
+        "<w:p><w:r><w:t>Line one</w:t><w:br/><w:t>line two</w:t><w:tab/><w:t>end</w:t></w:r></w:p>"
+            .to_string(),
+        paragraph(None, "  "),
+        paragraph(Some("Heading2"), "Non-goals"),
+    ]
+    .concat();
+    assert_eq!(
+        extract(Kind::Docx, &docx(&body)).unwrap(),
+        "# Design notes\n\n## Goals\n\nKeep it small.\n\nLine one\nline two\tend\n\n### Non-goals"
+    );
+    assert!(matches!(
+        extract(Kind::Docx, b"PK not really"),
+        Err(DocumentError::Zip(_))
+    ));
+}
+
+#[test]
+fn test_attach_documents() {
+    let dir = std::env::temp_dir().join(format!("chatti-documents-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let report = dir.join("report.pdf");
+    std::fs::write(&report, pdf(&["Revenue grew"])).unwrap();
+    let attachment = Attachment::load(&report, Sampling::default()).unwrap();
+    assert_eq!(
+        attachment.context(),
+        format!("File `{}`:\n[Page 1]\nRevenue grew", report.display())
+    );
+
+    let broken = dir.join("broken.docx");
+    std::fs::write(&broken, b"PK\x03\x04").unwrap();
+    let err = Attachment::load(&broken, Sampling::default()).unwrap_err();
+    assert!(matches!(err, AttachmentError::Document { .. }));
+    assert!(err
+        .to_string()
+        .starts_with("broken.docx: cannot read the DOCX archive"));
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
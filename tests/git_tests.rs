@@ -0,0 +1,72 @@
+// tests/git_tests.rs
+use chatti::commands::Command;
+use chatti::git::{GitError, GitPrompt};
+use std::fs;
+use std::path::Path;
+use std::process;
+
+fn git(dir: &Path, args: &[&str]) {
+    let status = process::Command::new("git")
+        .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap()
+        .status;
+    assert!(status.success(), "git {args:?} failed");
+}
+
+fn repository(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("chatti-git-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    git(&dir, &["init", "-q"]);
+    fs::write(dir.join("lib.rs"), "fn one() {}\n").unwrap();
+    git(&dir, &["add", "lib.rs"]);
+    git(&dir, &["commit", "-q", "-m", "Add one"]);
+    dir
+}
+
+#[test]
+fn test_git_prompts_include_changes_and_log() {
+    let dir = repository("prompts");
+    fs::write(dir.join("lib.rs"), "fn one() {}\nfn two() {}\n").unwrap();
+    git(&dir, &["add", "lib.rs"]);
+    fs::write(
+        dir.join("lib.rs"),
+        "fn one() {}\nfn two() {}\nfn three() {}\n",
+    )
+    .unwrap();
+
+    let diff = GitPrompt::Diff.build(&dir).unwrap();
+    let commit = GitPrompt::CommitMessage.build(&dir).unwrap();
+    let explain = GitPrompt::ExplainStaged.build(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(diff.contains("+fn two() {}") && diff.contains("+fn three() {}"));
+    assert!(commit.contains("Add one"));
+    assert!(commit.contains("+fn two() {}") && !commit.contains("three"));
+    assert!(explain.contains("+fn two() {}") && !explain.contains("three"));
+}
+
+#[test]
+fn test_git_prompt_without_changes() {
+    let dir = repository("clean");
+    let result = GitPrompt::ExplainStaged.build(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(matches!(result, Err(GitError::NoChanges("staged changes"))));
+}
+
+#[test]
+fn test_parse_git_commands() {
+    assert_eq!(Command::parse("/diff"), Some(Command::Git(GitPrompt::Diff)));
+    assert_eq!(
+        Command::parse("/commitmsg"),
+        Some(Command::Git(GitPrompt::CommitMessage))
+    );
+    assert_eq!(
+        Command::parse("/explain-staged"),
+        Some(Command::Git(GitPrompt::ExplainStaged))
+    );
+}
@@ -0,0 +1,25 @@
+// tests/history_tests.rs
+use chatti::history::InputHistory;
+
+#[test]
+fn test_history_round_trips_multiline_prompts() {
+    let path = std::env::temp_dir().join(format!("chatti-history-{}", std::process::id()));
+    let mut history = InputHistory::new(10);
+    history.push("first line\nsecond line");
+    history.push(r"C:\path\to\file");
+    history.push("   ");
+    history.save(&path).unwrap();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(text.lines().count(), 2, "each prompt is one line");
+
+    let loaded = InputHistory::load(&path, 10).unwrap();
+    assert_eq!(loaded, history);
+
+    let truncated = InputHistory::load(&path, 1).unwrap();
+    assert_eq!(truncated.entries(), [r"C:\path\to\file"]);
+    std::fs::remove_file(&path).unwrap();
+
+    let missing = InputHistory::load(&path, 10).unwrap();
+    assert!(missing.is_empty());
+}
@@ -0,0 +1,25 @@
+// tests/hooks_tests.rs
+use chatti::hooks::{run, HookError};
+use std::fs;
+
+#[test]
+fn test_hook_receives_response_and_model() {
+    let path = std::env::temp_dir().join(format!("chatti-hook-{}.txt", std::process::id()));
+    let command = format!("{{ echo \"$CHATTI_MODEL\"; cat; }} > '{}'", path.display());
+
+    run(&command, "The answer is 42.", "llama3.2").unwrap();
+    let written = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(written, "llama3.2\nThe answer is 42.");
+}
+
+#[test]
+fn test_hook_failure_and_ignored_input() {
+    assert!(run("true", &"long response ".repeat(10_000), "llama3.2").is_ok());
+
+    match run("echo broken >&2; exit 2", "response", "llama3.2") {
+        Err(HookError::Failed { stderr, .. }) => assert_eq!(stderr, "broken"),
+        other => panic!("expected a failed hook, got {other:?}"),
+    }
+}
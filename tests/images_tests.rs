@@ -0,0 +1,109 @@
+// tests/images_tests.rs
+use chatti::config::Config;
+use chatti::images::{self, Image, Source};
+use chatti::message::Message;
+use chatti::provider::Provider;
+use chatti::ui::renderer::Renderer;
+use chatti::ui::theme::Theme;
+use std::fs;
+use std::path::PathBuf;
+
+/// Writes the header of a PNG image, which is all that is read of it.
+fn write_png(name: &str, width: u32, height: u32) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("chatti-{name}-{}.png", std::process::id()));
+    let mut bytes = b"\x89PNG\r\n\x1a\n\x00\x00\x00\x0dIHDR".to_vec();
+    bytes.extend_from_slice(&width.to_be_bytes());
+    bytes.extend_from_slice(&height.to_be_bytes());
+    bytes.extend_from_slice(&[8, 6, 0, 0, 0, 0, 0, 0, 0]);
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+fn config(provider: &str) -> Config {
+    toml::from_str(&format!(
+        "provider = \"{provider}\"\napi_endpoint = \"http://localhost:11434/api/chat\"\nmodel = \"llava\"\nstream = true\ntemperature = 0.5\n"
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_images_of_messages() {
+    let path = write_png("chart", 640, 480);
+    let image = Image::file(&path);
+    assert!(image.placeholder().ends_with(".png, 640x480]"));
+
+    let mut prompt = Message::new("user", "What is in ![this](/tmp/ignored.png)?");
+    prompt.images = vec![path.clone()];
+    // Markdown in prompts is not read for images.
+    assert_eq!(images::of(&prompt), vec![image]);
+
+    // Answers may only show files in the working directory.
+    let local = PathBuf::from(format!("target/chatti-chart-{}.png", std::process::id()));
+    fs::copy(&path, &local).unwrap();
+    let answer = Message::new(
+        "assistant",
+        format!(
+            "Three charts:\n\n![Sales](file://{})\n\n![](https://example.com/a.png)\n\n![Costs]({})",
+            local.display(),
+            path.display()
+        ),
+    );
+    let found = images::of(&answer);
+    assert_eq!(found.len(), 3);
+    assert_eq!(found[0].placeholder(), "[image: Sales, 640x480]");
+    assert_eq!(
+        found[1].source,
+        Source::Remote(String::from("https://example.com/a.png"))
+    );
+    assert_eq!(found[2].source, Source::Outside(path.clone()));
+    assert!(found[2].bytes().is_none());
+
+    fs::remove_file(local).unwrap();
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_image_rows_are_left_below_placeholders() {
+    let path = write_png("rows", 32, 16);
+    let mut message = Message::new("user", "Look");
+    message.images = vec![path.clone()];
+    let theme = Theme::default();
+
+    let lines = Renderer::message_lines(&message, 80, &theme);
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].to_string().contains("32x16]"));
+
+    let lines = Renderer::message_lines_with(&message, 80, &theme, 3);
+    assert_eq!(lines.len(), 5);
+    assert!(lines[1].to_string().contains("32x16]"));
+    assert!(lines[2..].iter().all(|line| line.width() == 0));
+
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_images_are_sent_with_messages() {
+    let path = write_png("send", 1, 1);
+    let encoded = images::encode(&path).unwrap();
+    let mut message = Message::new("user", "Describe it");
+    message.images = vec![path.clone()];
+    let messages = [message];
+
+    let body = Provider::Ollama.request_body(&config("ollama"), &messages);
+    assert_eq!(body["messages"][0]["content"], "Describe it");
+    assert_eq!(body["messages"][0]["images"][0], encoded.as_str());
+
+    let body = Provider::OpenAi.request_body(&config("openai"), &messages);
+    let parts = &body["messages"][0]["content"];
+    assert_eq!(parts[0]["text"], "Describe it");
+    assert_eq!(
+        parts[1]["image_url"]["url"],
+        format!("data:image/png;base64,{encoded}").as_str()
+    );
+
+    // Messages without images keep plain text content.
+    let body = Provider::OpenAi.request_body(&config("openai"), &[Message::new("user", "Hi")]);
+    assert_eq!(body["messages"][0]["content"], "Hi");
+
+    fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,61 @@
+// tests/import_tests.rs
+use chatti::commands::Command;
+use chatti::import::{import_str, parse_markdown, ImportError};
+use chatti::message::Message;
+use std::path::Path;
+
+#[test]
+fn test_import_openai_json() {
+    let json = r#"{"title": "Greetings", "messages": [
+        {"role": "system", "content": "Be brief"},
+        {"role": "user", "content": "Hi"},
+        {"role": "assistant", "content": [{"type": "text", "text": "Hello!"}]}
+    ]}"#;
+
+    let sessions = import_str(Path::new("export.json"), json).unwrap();
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].title, "Greetings");
+    assert_eq!(sessions[0].messages.len(), 3);
+    assert_eq!(sessions[0].messages[2], Message::new("assistant", "Hello!"));
+}
+
+#[test]
+fn test_import_chatgpt_mapping() {
+    let json = r#"[{"title": "Old chat", "current_node": "c", "mapping": {
+        "a": {"message": null, "parent": null, "children": ["b"]},
+        "b": {"message": {"author": {"role": "user"}, "content": {"parts": ["Question"]}}, "parent": "a", "children": ["c"]},
+        "c": {"message": {"author": {"role": "assistant"}, "content": {"parts": ["Answer"]}}, "parent": "b", "children": []}
+    }}]"#;
+
+    let sessions = import_str(Path::new("conversations.json"), json).unwrap();
+    assert_eq!(sessions[0].title, "Old chat");
+    assert_eq!(
+        sessions[0].messages,
+        vec![
+            Message::new("user", "Question"),
+            Message::new("assistant", "Answer"),
+        ]
+    );
+}
+
+#[test]
+fn test_import_markdown_and_errors() {
+    let markdown = "# Chat log\n\n## User\nWhat is Rust?\n\n## Assistant\nA language.\n\nIt is fast.\n\n**You:** Thanks";
+    let messages = parse_markdown(markdown);
+    assert_eq!(messages.len(), 3);
+    assert_eq!(messages[1].content, "A language.\n\nIt is fast.");
+    assert_eq!(messages[2], Message::new("user", "Thanks"));
+
+    assert!(matches!(
+        import_str(Path::new("notes.md"), "just some notes"),
+        Err(ImportError::Empty)
+    ));
+    assert!(matches!(
+        import_str(Path::new("x.json"), r#"{"foo": 1}"#),
+        Err(ImportError::UnknownFormat)
+    ));
+    assert_eq!(
+        Command::parse("/import"),
+        Some(Command::Invalid("Usage: /import <path>".to_string()))
+    );
+}
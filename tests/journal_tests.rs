@@ -0,0 +1,95 @@
+// tests/journal_tests.rs
+use chatti::journal::{self, Journal, Partial};
+use chatti::message::Message;
+use chatti::session::Recovery;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir()
+        .join(format!("chatti-journal-{name}-{}", std::process::id()))
+        .join("journal.jsonl")
+}
+
+#[test]
+fn test_replay_collects_the_streamed_chunks() {
+    let path = temp_path("replay");
+    let prompt = Message::new("user", "Tell me a story");
+    let mut journal = Journal::begin(path.clone(), Some(String::from("42")), &prompt).unwrap();
+    journal.append("Once upon");
+    journal.append(" a time\n");
+
+    let partial = journal::replay(&path).unwrap().unwrap();
+    assert_eq!(
+        partial,
+        Partial {
+            session_id: Some(String::from("42")),
+            prompt,
+            response: String::from("Once upon a time\n"),
+        }
+    );
+
+    journal.finish().unwrap();
+    assert!(!path.exists());
+    assert_eq!(journal::replay(&path).unwrap(), None);
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn test_replay_ignores_a_torn_last_line() {
+    let path = temp_path("torn");
+    let mut journal = Journal::begin(path.clone(), None, &Message::new("user", "Hi")).unwrap();
+    journal.append("Hello");
+    fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(b"{\"chunk\":\" the")
+        .unwrap();
+
+    let partial = journal::replay(&path).unwrap().unwrap();
+    assert_eq!(partial.response, "Hello");
+    journal::clear(&path).unwrap();
+    journal::clear(&path).unwrap();
+    fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn test_restore_adds_the_prompt_the_recovery_file_missed() {
+    let mut recovery = Recovery {
+        messages: vec![
+            Message::new("user", "First"),
+            Message::new("assistant", "Answer"),
+        ],
+        ..Recovery::default()
+    };
+    Partial {
+        session_id: Some(String::from("7")),
+        prompt: Message::new("user", "Second"),
+        response: String::from("Part"),
+    }
+    .restore(&mut recovery);
+
+    let contents: Vec<&str> = recovery
+        .messages
+        .iter()
+        .map(|m| m.content.as_str())
+        .collect();
+    assert_eq!(contents, ["First", "Answer", "Second", "Part"]);
+    assert!(recovery.messages[3].stopped);
+    assert_eq!(recovery.session_id.as_deref(), Some("7"));
+}
+
+#[test]
+fn test_restore_without_recovery_or_answer() {
+    let mut recovery = Recovery::default();
+    Partial {
+        session_id: None,
+        prompt: Message::new("user", "Hi"),
+        response: String::new(),
+    }
+    .restore(&mut recovery);
+    assert_eq!(recovery.messages, vec![Message::new("user", "Hi")]);
+    assert!(!recovery.is_empty());
+}
@@ -0,0 +1,54 @@
+// tests/keymap_tests.rs
+use chatti::commands::COMMANDS;
+use chatti::ui::input_handler::InputHandler;
+use chatti::ui::keymap::{bindings, help_lines, MODES};
+use chatti::ui::state::State;
+use crossterm::event::KeyCode;
+
+#[test]
+fn test_help_lists_every_mode_and_command() {
+    let lines: Vec<String> = help_lines().iter().map(ToString::to_string).collect();
+    for (mode, title) in MODES {
+        assert!(bindings(*mode).next().is_some(), "{title} has no bindings");
+        assert!(lines.iter().any(|line| line == title));
+    }
+    assert!(lines.iter().any(|line| line == "Slash commands"));
+    for command in COMMANDS {
+        let usage = format!("  /{} ", command.name);
+        assert!(
+            lines
+                .iter()
+                .any(|line| line.starts_with(&usage) && line.ends_with(command.summary)),
+            "/{} is missing",
+            command.name
+        );
+    }
+    // Keys are padded into one column per section.
+    let first = lines
+        .iter()
+        .position(|line| line.contains("show or hide this help"));
+    let second = lines.iter().position(|line| line.ends_with("  quit"));
+    assert_eq!(
+        lines[first.unwrap()].find("show"),
+        lines[second.unwrap()].find("quit")
+    );
+}
+
+#[test]
+fn test_help_scrolls_and_closes() {
+    let input_handler = InputHandler::new();
+    let mut ui_state = State::new();
+
+    input_handler.handle_normal_mode(&mut ui_state, KeyCode::Char('?'));
+    assert!(ui_state.show_toggle);
+    input_handler.handle_normal_mode(&mut ui_state, KeyCode::PageDown);
+    input_handler.handle_normal_mode(&mut ui_state, KeyCode::Up);
+    assert_eq!(ui_state.help_scroll, 9);
+
+    // Keys go to the help while it is open.
+    input_handler.handle_normal_mode(&mut ui_state, KeyCode::Char('e'));
+    input_handler.handle_normal_mode(&mut ui_state, KeyCode::Char('q'));
+    assert!(!ui_state.quit);
+    assert!(!ui_state.show_toggle);
+    assert_eq!(ui_state.help_scroll, 0);
+}
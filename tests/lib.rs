@@ -1,6 +1,63 @@
+mod api_tests;
+mod attachments_tests;
+mod auth_tests;
+mod bench_tests;
+mod capabilities_tests;
+mod cli_tests;
+mod code_language_tests;
+mod compare_tests;
+mod completion_tests;
+mod continuation_tests;
+mod control_tests;
+mod dataset_tests;
+mod diagrams_tests;
+mod dialog_tests;
+mod diff_tests;
+mod documents_tests;
+mod git_tests;
+mod history_tests;
+mod hooks_tests;
+mod images_tests;
+mod import_tests;
 mod input_handler_tests;
+mod journal_tests;
+mod keymap_tests;
+mod lint_tests;
 mod markdown_renderer_tests;
+mod math_tests;
+mod mentions_tests;
+mod models_tests;
+mod pager_tests;
+mod palette_tests;
+mod params_tests;
+mod plugins_tests;
+mod project_tests;
+mod provider_tests;
+mod ratelimit_tests;
+mod redact_tests;
+mod renderer_tests;
+mod retention_tests;
+mod retrieval_tests;
+mod script_tests;
+mod scripting_tests;
+mod scrollback_tests;
+mod search_tests;
+mod shell_tests;
+mod speech_tests;
 mod spinner_tests;
+mod stats_tests;
+mod store_tests;
+mod stream_tests;
+mod structured_tests;
+mod switcher_tests;
+mod tables_tests;
+mod tasks_tests;
+mod telemetry_tests;
+mod templates_tests;
+mod theme_tests;
+mod tokens_tests;
+mod translate_tests;
+mod transport_tests;
 mod ui_state_tests;
 
 pub mod mock;
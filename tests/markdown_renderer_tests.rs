@@ -1,5 +1,5 @@
 // tests/markdown_renderer_tests.rs
-use chatti::ui::markdown_renderer::MarkdownRenderer;
+use chatti::ui::markdown_renderer::{CodeLayout, MarkdownRenderer};
 
 #[test]
 fn test_markdown_renderer() {
@@ -8,11 +8,12 @@ fn test_markdown_renderer() {
 
     assert!(!rendered.is_empty());
 
-    // Check if the first span of the first line contains "Hello"
+    // Check if the first line is the heading "Hello" alone
     assert_eq!(rendered[0].spans[0].content, "Hello");
+    assert_eq!(rendered[0].spans.len(), 1);
 
     // Check if the rendered content includes bold and italic text
-    let full_content: String = rendered[0]
+    let full_content: String = rendered[2]
         .spans
         .iter()
         .map(|span| span.content.clone())
@@ -21,12 +22,293 @@ fn test_markdown_renderer() {
     assert!(full_content.contains("italic"));
 
     // Check for styling (this might need adjustment based on your exact implementation)
-    assert!(rendered[0].spans.iter().any(|span| span
+    assert!(rendered[2].spans.iter().any(|span| span
         .style
         .add_modifier
         .contains(ratatui::style::Modifier::BOLD)));
-    assert!(rendered[0].spans.iter().any(|span| span
+    assert!(rendered[2].spans.iter().any(|span| span
         .style
         .add_modifier
         .contains(ratatui::style::Modifier::ITALIC)));
 }
+
+#[test]
+fn test_markdown_footnotes_and_citations() {
+    let markdown = "Rust is safe [1] and fast[^perf].\n\n[^perf]: See benchmarks.\n\nSources:\n[1] https://rust-lang.org";
+    let rendered = MarkdownRenderer::render_markdown(markdown, 80);
+    let texts: Vec<String> = rendered
+        .iter()
+        .map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
+        .collect();
+
+    assert!(texts.iter().any(|t| t.starts_with("── Footnotes")));
+    assert!(texts.contains(&"[perf] See benchmarks.".to_string()));
+    assert!(texts.contains(&"[1] https://rust-lang.org".to_string()));
+
+    let citation = rendered[0]
+        .spans
+        .iter()
+        .find(|s| s.content == "[1]")
+        .expect("inline citation is its own span");
+    assert_eq!(citation.style.fg, Some(ratatui::style::Color::Cyan));
+}
+
+#[test]
+fn test_math_is_prettified() {
+    let rendered =
+        MarkdownRenderer::render_markdown("Euler: $e^{i\\pi} = -1$\n\n$$\n\\frac{a}{b}\n$$", 80);
+    let text: Vec<String> = rendered
+        .iter()
+        .map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
+        .collect();
+
+    assert!(text[0].contains("e^(iπ) = -1"));
+    assert!(text.iter().any(|line| line.trim() == "a/b"));
+    assert!(!text.iter().any(|line| line.contains('$')));
+}
+
+#[test]
+fn test_dollar_amounts_are_not_math() {
+    let rendered = MarkdownRenderer::render_markdown("It costs $5 and $6.", 80);
+    let text: String = rendered[0]
+        .spans
+        .iter()
+        .map(|s| s.content.as_ref())
+        .collect();
+    assert!(text.contains("$5"));
+}
+
+#[test]
+fn test_code_block_wrap_and_scroll() {
+    let markdown = "```\nabcdefghijkl\nshort\n```";
+    let text = |lines: Vec<ratatui::text::Line>| -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
+            .collect()
+    };
+
+    let wrapped = text(MarkdownRenderer::render_markdown_with(
+        markdown,
+        5,
+        CodeLayout::Wrap,
+    ));
+    assert_eq!(wrapped, vec!["[1] ─", "abcde", "fghij", "kl", "short"]);
+
+    let panned = text(MarkdownRenderer::render_markdown_with(
+        markdown,
+        5,
+        CodeLayout::Scroll(4),
+    ));
+    assert_eq!(panned, vec!["[1] ─", "efgh›", "t"]);
+}
+
+#[test]
+fn test_code_block_wrap_keeps_wide_characters() {
+    let rendered =
+        MarkdownRenderer::render_markdown_with("```\nabcd漢字\n```", 5, CodeLayout::Wrap);
+    let wrapped: Vec<String> = rendered
+        .iter()
+        .map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
+        .collect();
+    assert_eq!(wrapped, vec!["[1] ─", "abcd", "漢字"]);
+}
+
+#[test]
+fn test_code_block_titles() {
+    let title = |markdown: &str| -> String {
+        let rendered = MarkdownRenderer::render_markdown(markdown, 30);
+        rendered[0]
+            .spans
+            .iter()
+            .map(|s| s.content.as_ref())
+            .collect()
+    };
+
+    assert_eq!(
+        title("```rust,ignore\nlet x = 1;\n```"),
+        format!("[1] ── rust {}", "─".repeat(18))
+    );
+    assert!(title("```\nfn main() {\n    println!(\"hi\");\n}\n```")
+        .starts_with("[1] ── rust (guessed) ─"));
+    assert_eq!(
+        title("```\nhello world\n```"),
+        format!("[1] {}", "─".repeat(26))
+    );
+}
+
+/// Renders markdown and returns the text of each line.
+fn rendered_text(markdown: &str, width: usize) -> Vec<String> {
+    MarkdownRenderer::render_markdown(markdown, width)
+        .iter()
+        .map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
+        .collect()
+}
+
+#[test]
+fn test_spans_join_exactly_as_written() {
+    assert_eq!(
+        rendered_text("This is **bold**, *un*done and `code`.", 80),
+        vec!["This is bold, undone and `code`."]
+    );
+    assert_eq!(
+        rendered_text("Rust is fast[^1].\n\n[^1]: Mostly.", 80)[0],
+        "Rust is fast[1]."
+    );
+    assert_eq!(rendered_text("a\nb", 80), vec!["a b"]);
+}
+
+#[test]
+fn test_streamed_chunk_seams() {
+    // Tokens streamed without leading spaces, split mid-word and inside
+    // markdown, render the same as the whole answer at every seam.
+    let chunks = [
+        "Hel",
+        "lo, wor",
+        "ld! Use **Vec",
+        "Deque** or `Vec",
+        "<T>`",
+        " in*stead*.",
+    ];
+    let mut streamed = String::new();
+    for chunk in chunks {
+        streamed.push_str(chunk);
+        let text = rendered_text(&streamed, 80).concat();
+        assert!(
+            !text.contains("Hel lo") && !text.contains("wor ld") && !text.contains("Vec Deque"),
+            "a seam added a space: {text:?}"
+        );
+    }
+    assert_eq!(
+        rendered_text(&streamed, 80),
+        vec!["Hello, world! Use VecDeque or `Vec<T>` instead."]
+    );
+}
+
+#[test]
+fn test_wrapping_keeps_words_whole() {
+    assert_eq!(
+        rendered_text("one **two**three four", 10),
+        vec!["one", "twothree", "four"]
+    );
+    assert_eq!(
+        rendered_text("a abcdefghijkl", 5),
+        vec!["a abc", "defgh", "ijkl"]
+    );
+    assert_eq!(
+        rendered_text("- first item that wraps", 12),
+        vec!["• first item", "  that wraps"]
+    );
+}
+
+#[test]
+fn test_strikethrough_task_lists_and_footnotes() {
+    let rendered = MarkdownRenderer::render_markdown(
+        "Use ~~Vec~~ VecDeque[^why].\n\n- [x] push\n- [ ] pop\n\n[^why]: Both ends.",
+        80,
+    );
+    let struck = rendered[0]
+        .spans
+        .iter()
+        .find(|s| s.content == "Vec")
+        .expect("struck text is its own span");
+    assert!(struck
+        .style
+        .add_modifier
+        .contains(ratatui::style::Modifier::CROSSED_OUT));
+    assert!(!rendered[0].spans.iter().any(|s| s.content.contains('~')));
+
+    let text = rendered_text(
+        "Use ~~Vec~~ VecDeque[^why].\n\n- [x] push\n- [ ] pop\n  - [ ] nested\n\n[^why]: Both ends.",
+        80,
+    );
+    assert_eq!(text[0], "Use Vec VecDeque[why].");
+    assert!(text.contains(&"☑ push".to_string()));
+    assert!(text.contains(&"☐ pop".to_string()));
+    assert!(text.contains(&"  ☐ nested".to_string()));
+    assert!(text.contains(&"[why] Both ends.".to_string()));
+}
+
+#[test]
+fn test_ordered_lists_are_numbered() {
+    assert_eq!(
+        rendered_text(
+            "3. three\n4. four is long enough to wrap\n   1. nested\n   2. again\n      - deep\n5. [x] done",
+            20
+        ),
+        vec![
+            "3. three",
+            "4. four is long",
+            "   enough to wrap",
+            "   1. nested",
+            "   2. again",
+            "      • deep",
+            "5. ☑ done",
+        ]
+    );
+    assert_eq!(
+        rendered_text("- bullet\n  1. first\n  2. second", 80),
+        vec!["• bullet", "  1. first", "  2. second"]
+    );
+}
+
+#[test]
+fn test_rules_and_images() {
+    let rendered = MarkdownRenderer::render_markdown("Above\n\n---\n\nBelow", 10);
+    let text: Vec<String> = rendered
+        .iter()
+        .map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
+        .collect();
+    assert_eq!(
+        text,
+        vec!["Above", "", "─".repeat(10).as_str(), "", "Below"]
+    );
+    assert_eq!(rendered[2].style.fg, Some(ratatui::style::Color::DarkGray));
+
+    let image = MarkdownRenderer::render_markdown("See ![a *red* crab](crab.png).", 80);
+    let text: String = image[0].spans.iter().map(|s| s.content.as_ref()).collect();
+    assert_eq!(text, "See [image: a red crab](crab.png).");
+    let placeholder = image[0]
+        .spans
+        .iter()
+        .find(|s| s.content.starts_with("[image:"))
+        .expect("the placeholder is its own span");
+    assert_eq!(placeholder.style.fg, Some(ratatui::style::Color::Blue));
+}
+
+#[test]
+fn test_code_blocks_are_numbered() {
+    let answer = "First:\n\n```sh\ncargo build\n```\n\nThen:\n\n```\ncargo test\n```";
+    let text = rendered_text(answer, 20);
+    assert!(text[2].starts_with("[1] ── sh"));
+    assert!(text[7].starts_with("[2] ───"));
+    assert_eq!(text[8], "cargo test");
+    assert_eq!(
+        MarkdownRenderer::code_blocks(answer),
+        vec!["cargo build\n", "cargo test\n"]
+    );
+}
+
+#[test]
+fn test_tables_are_numbered_with_code_blocks() {
+    let answer = "```sh\ncargo build\n```\n\n| Name | Size |\n|------|-----:|\n| `a.rs` | 12 |\n| b.rs | 3 |";
+    let text = rendered_text(answer, 20);
+    assert!(text[0].starts_with("[1] ── sh"));
+    assert_eq!(text[2], format!("[2] ── table {}", "─".repeat(7)));
+    assert_eq!(
+        &text[3..],
+        [
+            "Name   │ Size",
+            "───────┼─────",
+            "`a.rs` │ 12",
+            "b.rs   │ 3"
+        ]
+    );
+    assert_eq!(
+        MarkdownRenderer::code_blocks(answer),
+        vec![
+            "cargo build\n",
+            "| Name | Size |\n|------|-----:|\n| `a.rs` | 12 |\n| b.rs | 3 |\n"
+        ]
+    );
+}
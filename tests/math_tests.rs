@@ -0,0 +1,30 @@
+// tests/math_tests.rs
+use chatti::ui::math::{normalize_delimiters, prettify};
+
+#[test]
+fn test_prettify_symbols_and_scripts() {
+    assert_eq!(prettify(r"\alpha + \beta \leq \infty"), "α + β ≤ ∞");
+    assert_eq!(prettify(r"x^2 + y_{10}"), "x² + y₁₀");
+    assert_eq!(prettify(r"\sum_{i=1}^{n} i"), "∑ᵢ₌₁ⁿ i");
+    assert_eq!(prettify(r"x^{\alpha}"), "x^α");
+    assert_eq!(prettify(r"e^{2\pi}"), "e^(2π)");
+    assert_eq!(prettify(r"\mathbb{R}^3"), "ℝ³");
+}
+
+#[test]
+fn test_prettify_fractions_and_roots() {
+    assert_eq!(prettify(r"\frac{1}{2}"), "1/2");
+    assert_eq!(prettify(r"\frac{a+b}{c}"), "(a+b)/c");
+    assert_eq!(prettify(r"\sqrt{2}"), "√2");
+    assert_eq!(prettify(r"\sqrt[3]{x+1}"), "∛(x+1)");
+    assert_eq!(prettify(r"\left( \frac{x}{y} \right)"), "( x/y )");
+}
+
+#[test]
+fn test_normalize_delimiters_skips_code_fences() {
+    let content = "Inline \\(a^2\\) and\n\\[\nb\n\\]\n```\n\\(raw\\)\n```\n";
+    assert_eq!(
+        normalize_delimiters(content),
+        "Inline $a^2$ and\n$$b$$\n```\n\\(raw\\)\n```\n"
+    );
+}
@@ -0,0 +1,41 @@
+// tests/mentions_tests.rs
+use chatti::mentions::{attach, find};
+use chatti::tables::Sampling;
+use chatti::ui::state::State;
+
+#[test]
+fn test_mentions_attach_existing_files() {
+    let dir = std::env::temp_dir().join(format!("chatti-mentions-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("src")).unwrap();
+    std::fs::write(dir.join("src/lib.rs"), "pub fn answer() -> u32 { 42 }\n").unwrap();
+    std::fs::write(dir.join("notes.md"), "- remember the milk\n").unwrap();
+    std::fs::write(dir.join("image.bin"), [0xff, 0xfe, 0x00]).unwrap();
+
+    let prompt = "Does @src/lib.rs match @notes.md? Ask @someone, not @image.bin";
+    assert_eq!(
+        find(prompt, &dir),
+        vec!["src/lib.rs", "notes.md", "image.bin"]
+    );
+
+    let context = attach(prompt, &dir, Sampling::default()).unwrap();
+    assert_eq!(
+        context,
+        "File `src/lib.rs`:\npub fn answer() -> u32 { 42 }\n\nFile `notes.md`:\n- remember the milk"
+    );
+    assert_eq!(
+        attach("No files here, @you", &dir, Sampling::default()),
+        None
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_submit_prompt_attaches_mentioned_files() {
+    let mut ui_state = State::new();
+    ui_state.pending_context = Some(String::from("clipboard text"));
+    ui_state.submit_prompt(String::from("Explain @Cargo.toml"));
+
+    let attachment = ui_state.messages[0].attachment.as_deref().unwrap();
+    assert!(attachment.starts_with("clipboard text\n\nFile `Cargo.toml`:\n[package]"));
+    assert_eq!(ui_state.messages[0].content, "Explain @Cargo.toml");
+}
@@ -1,10 +1,12 @@
 // tests/mock/mod.rs
+use chatti::session::Recovery;
 use chatti::ui::chat::Interface;
 use chatti::ui::input_handler::InputHandler;
 use chatti::ui::renderer::Renderer;
 use chatti::ui::state::State;
 use ratatui::backend::CrosstermBackend;
 use std::io::stdout;
+use std::time::{Duration, Instant};
 
 pub fn create_mock_chat_ui() -> Interface {
     let backend = CrosstermBackend::new(stdout());
@@ -16,5 +18,11 @@ pub fn create_mock_chat_ui() -> Interface {
         input_handler: InputHandler::new(),
         ui_renderer,
         terminal,
+        last_autosave: Instant::now(),
+        saved_recovery: Recovery::default(),
+        alternate_screen: false,
+        synchronized_output: false,
+        frame_interval: Duration::ZERO,
+        last_frame: None,
     }
 }
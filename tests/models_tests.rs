@@ -0,0 +1,150 @@
+// tests/models_tests.rs
+use chatti::commands::Command;
+use chatti::models::{self, ModelCommand, PullProgress};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+/// Serves a single HTTP response and returns the address and the request.
+async fn serve_once(
+    response: impl Into<String>,
+) -> (std::net::SocketAddr, tokio::task::JoinHandle<String>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let response = response.into();
+    let request = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = vec![0; 4096];
+        let read = socket.read(&mut request).await.unwrap();
+        socket.write_all(response.as_bytes()).await.unwrap();
+        String::from_utf8_lossy(&request[..read]).into_owned()
+    });
+    (address, request)
+}
+
+#[test]
+fn test_parse_models_commands() {
+    assert_eq!(
+        Command::parse("/models pull llama3:8b"),
+        Some(Command::Models(ModelCommand::Pull("llama3:8b".to_string())))
+    );
+    assert_eq!(
+        Command::parse("/models rm llama3"),
+        Some(Command::Models(ModelCommand::Remove("llama3".to_string())))
+    );
+    assert_eq!(
+        Command::parse("/models ps"),
+        Some(Command::Models(ModelCommand::Running))
+    );
+    for invalid in [
+        "/models",
+        "/models pull",
+        "/models ps extra",
+        "/models list",
+    ] {
+        assert!(
+            matches!(Command::parse(invalid), Some(Command::Invalid(_))),
+            "{invalid}"
+        );
+    }
+}
+
+#[test]
+fn test_pull_progress_without_sizes() {
+    let progress = PullProgress {
+        status: "pulling manifest".to_string(),
+        ..PullProgress::default()
+    };
+    assert_eq!(progress.fraction(), None);
+    assert_eq!(progress.describe(), "pulling manifest");
+}
+
+#[tokio::test]
+async fn test_pull_streams_progress() {
+    let (address, request) = serve_once(
+        "HTTP/1.1 200 OK\r\ncontent-type: application/x-ndjson\r\nconnection: close\r\n\r\n\
+         {\"status\":\"pulling manifest\"}\n\
+         {\"status\":\"pulling abc\",\"total\":100,\"completed\":40}\n\
+         {\"status\":\"success\"}\n",
+    )
+    .await;
+
+    let (tx, mut rx) = mpsc::channel(10);
+    let client = reqwest::Client::new();
+    models::pull(&client, &format!("http://{address}/api/chat"), "llama3", tx)
+        .await
+        .unwrap();
+
+    let request = request.await.unwrap();
+    assert!(request.starts_with("POST /api/pull "), "{request}");
+    assert!(request.contains(r#""model":"llama3""#), "{request}");
+
+    let mut updates = Vec::new();
+    while let Some(update) = rx.recv().await {
+        updates.push(update);
+    }
+    assert_eq!(updates.len(), 3);
+    assert_eq!(updates[1].fraction(), Some(0.4));
+    assert_eq!(updates[2].status, "success");
+}
+
+#[tokio::test]
+async fn test_pull_reports_streamed_error() {
+    let (address, _request) = serve_once(
+        "HTTP/1.1 200 OK\r\nconnection: close\r\n\r\n{\"error\":\"pull model manifest: file does not exist\"}\n",
+    )
+    .await;
+
+    let (tx, _rx) = mpsc::channel(10);
+    let err = models::pull(
+        &reqwest::Client::new(),
+        &format!("http://{address}/"),
+        "nope",
+        tx,
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.to_string(), "pull model manifest: file does not exist");
+}
+
+#[tokio::test]
+async fn test_delete_missing_model() {
+    let body = r#"{"error":"model 'nope' not found"}"#;
+    let response = format!(
+        "HTTP/1.1 404 Not Found\r\ncontent-length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    let (address, request) = serve_once(response).await;
+
+    let err = models::delete(
+        &reqwest::Client::new(),
+        &format!("http://{address}/api/chat"),
+        "nope",
+    )
+    .await
+    .unwrap_err();
+    assert!(request.await.unwrap().starts_with("DELETE /api/delete "));
+    assert_eq!(err.to_string(), "model 'nope' not found");
+}
+
+#[tokio::test]
+async fn test_running_models() {
+    let (address, request) = serve_once(
+        "HTTP/1.1 200 OK\r\nconnection: close\r\n\r\n\
+         {\"models\":[{\"name\":\"llama3:8b\",\"model\":\"llama3:8b\",\"size\":2000,\"size_vram\":2000,\"expires_at\":\"2024-06-04T14:38:31Z\"}]}",
+    )
+    .await;
+
+    let running = models::running(
+        &reqwest::Client::new(),
+        &format!("http://{address}/api/chat"),
+    )
+    .await
+    .unwrap();
+    assert!(request.await.unwrap().starts_with("GET /api/ps "));
+    assert_eq!(running.len(), 1);
+    assert_eq!(running[0].name, "llama3:8b");
+    assert_eq!(
+        models::describe_running(&running),
+        "Loaded models:\n\n- **llama3:8b**: 2.0 KB, 100% GPU, until 2024-06-04T14:38:31Z"
+    );
+}
@@ -0,0 +1,121 @@
+// tests/pager_tests.rs
+use chatti::ui::pager::Pager;
+use ratatui::text::Line;
+
+fn pager_with(lines: &[&str], height: usize) -> Pager {
+    let mut pager = Pager::new();
+    pager.set_content(
+        lines.iter().map(|l| Line::from((*l).to_string())).collect(),
+        height,
+    );
+    pager
+}
+
+#[test]
+fn test_pager_navigation() {
+    let mut pager = pager_with(&["a"; 10], 4);
+
+    pager.page_down();
+    assert_eq!(pager.offset, 4);
+    pager.page_down();
+    assert_eq!(pager.offset, 6, "offset should stop at the last page");
+    assert_eq!(pager.percentage(), 100);
+
+    pager.top();
+    assert_eq!(pager.offset, 0);
+    assert_eq!(pager.percentage(), 40);
+}
+
+#[test]
+fn test_pager_search_and_marks() {
+    let mut pager = pager_with(
+        &[
+            "intro",
+            "Rust is fast",
+            "filler",
+            "filler",
+            "more rust",
+            "end",
+        ],
+        2,
+    );
+
+    pager.search("rust");
+    assert_eq!(pager.current_match, Some(1));
+    pager.next_match();
+    assert_eq!(pager.current_match, Some(4));
+    pager.next_match();
+    assert_eq!(pager.current_match, Some(1), "search should wrap around");
+    pager.previous_match();
+    assert_eq!(pager.current_match, Some(4));
+
+    pager.top();
+    pager.set_mark('a');
+    pager.bottom();
+    pager.jump_to_mark('a');
+    assert_eq!(pager.offset, 0);
+}
+
+#[test]
+fn test_pager_footnote_jump() {
+    let mut pager = pager_with(
+        &[
+            "AI: see [2]",
+            "text",
+            "text",
+            "[1] first",
+            "[2] second",
+            "end",
+        ],
+        2,
+    );
+
+    pager.jump_to_footnote("2");
+    assert_eq!(pager.offset, 4);
+    pager.jump_to_mark('\'');
+    assert_eq!(
+        pager.offset, 0,
+        "'' returns to the position before the jump"
+    );
+}
+
+#[test]
+fn test_pager_footnote_jump_skips_custom_prefix() {
+    let mut pager = Pager::new();
+    pager.set_content(
+        vec![
+            Line::from("» see [1]"),
+            Line::from("text"),
+            Line::from(vec!["» ".into(), "[1] source".into()]),
+        ],
+        1,
+    );
+
+    pager.jump_to_footnote("1");
+    assert_eq!(pager.offset, 2);
+}
+
+#[test]
+fn test_pager_copies_a_selection() {
+    let mut pager = pager_with(&["one", "two  ", "three", "four", "five"], 2);
+    pager.scroll_down(1);
+    pager.start_selection();
+    pager.move_selection(2);
+    assert_eq!(pager.offset, 2, "the end of the selection stays in view");
+    assert!(pager.is_selected(1) && pager.is_selected(3) && !pager.is_selected(4));
+    pager.move_selection(-3);
+    assert_eq!(pager.selection.unwrap().lines(), 0..=1);
+    assert_eq!(pager.offset, 0);
+
+    pager.copy_selection();
+    assert!(pager.selection.is_none());
+    assert_eq!(pager.copied.as_deref(), Some("one\ntwo"));
+
+    pager.select_lines(3..5);
+    assert_eq!(pager.selected_text().as_deref(), Some("four\nfive"));
+    pager.set_content(vec![Line::from("one")], 2);
+    assert!(
+        pager.selection.is_none(),
+        "a selection past the content ends"
+    );
+}
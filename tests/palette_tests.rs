@@ -0,0 +1,85 @@
+// tests/palette_tests.rs
+use chatti::commands::COMMANDS;
+use chatti::ui::input_handler::InputHandler;
+use chatti::ui::keymap::BINDINGS;
+use chatti::ui::palette::{Palette, Target};
+use chatti::ui::state::{InputMode, State};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+fn type_query(input_handler: &InputHandler, ui_state: &mut State, query: &str) {
+    for c in query.chars() {
+        input_handler.handle_palette_mode(ui_state, KeyCode::Char(c));
+    }
+}
+
+#[test]
+fn test_palette_lists_actions_and_commands() {
+    let palette = Palette::new();
+    let actions = BINDINGS
+        .iter()
+        .filter(|binding| binding.key.is_some())
+        .count();
+    assert_eq!(palette.total(), actions + COMMANDS.len());
+    assert_eq!(palette.matches.len(), palette.total());
+    assert!(palette
+        .matches
+        .iter()
+        .any(|entry| entry.target == Target::Key(KeyCode::Char('t'))));
+
+    let mut palette = Palette::new();
+    for c in "xyzzy".chars() {
+        palette.push(c);
+    }
+    assert!(palette.chosen().is_none());
+    for _ in 0..5 {
+        palette.pop();
+    }
+    assert_eq!(palette.matches.len(), palette.total());
+}
+
+#[test]
+fn test_palette_runs_the_chosen_entry() {
+    let input_handler = InputHandler::new();
+    let mut ui_state = State::new();
+    let ctrl_p = KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL);
+    assert!(
+        !input_handler.handle_palette_shortcut(&mut ui_state, KeyEvent::from(KeyCode::Char('p')))
+    );
+    assert!(input_handler.handle_palette_shortcut(&mut ui_state, ctrl_p));
+    assert_eq!(ui_state.input_mode, InputMode::Palette);
+
+    // An action presses its key in normal mode.
+    type_query(&input_handler, &mut ui_state, "start editing");
+    assert_eq!(
+        input_handler.handle_palette_mode(&mut ui_state, KeyCode::Enter),
+        None
+    );
+    assert_eq!(ui_state.input_mode, InputMode::Editing);
+    assert!(ui_state.palette.is_none());
+
+    // A command without arguments is run.
+    ui_state.open_palette();
+    type_query(&input_handler, &mut ui_state, "/dryrun");
+    assert_eq!(
+        input_handler.handle_palette_mode(&mut ui_state, KeyCode::Enter),
+        Some(String::from("/dryrun"))
+    );
+
+    // A command that needs arguments is put in the input.
+    ui_state.input = String::from("my draft");
+    ui_state.open_palette();
+    type_query(&input_handler, &mut ui_state, "/model ");
+    assert_eq!(
+        input_handler.handle_palette_mode(&mut ui_state, KeyCode::Enter),
+        None
+    );
+    assert_eq!(ui_state.input, "/model ");
+    assert_eq!(ui_state.input_mode, InputMode::Editing);
+    ui_state.undo_input();
+    assert_eq!(ui_state.input, "my draft", "the draft comes back with undo");
+
+    ui_state.open_palette();
+    input_handler.handle_palette_mode(&mut ui_state, KeyCode::Esc);
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+    assert!(ui_state.palette.is_none());
+}
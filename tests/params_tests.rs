@@ -0,0 +1,62 @@
+// tests/params_tests.rs
+use chatti::ui::params::{Param, Params, ParamsEditor};
+
+#[test]
+fn test_params_step_within_bounds() {
+    let mut params = Params {
+        temperature: 1.95,
+        top_p: None,
+        max_tokens: None,
+    };
+    params.step(Param::Temperature, true);
+    assert!((params.temperature - 2.0).abs() < f32::EPSILON);
+    for _ in 0..30 {
+        params.step(Param::Temperature, false);
+    }
+    assert!(params.temperature.abs() < f32::EPSILON);
+
+    params.step(Param::TopP, true);
+    assert_eq!(params.top_p, Some(1.0), "an unset top_p starts from 1.0");
+    params.step(Param::TopP, false);
+    assert_eq!(params.top_p, Some(0.95));
+    params.reset(Param::TopP);
+    assert_eq!(params.top_p, None);
+
+    params.step(Param::MaxTokens, true);
+    params.step(Param::MaxTokens, true);
+    assert_eq!(params.max_tokens, Some(512));
+    params.step(Param::MaxTokens, false);
+    params.step(Param::MaxTokens, false);
+    assert_eq!(
+        params.max_tokens, None,
+        "stepping below one step removes the limit"
+    );
+}
+
+#[test]
+fn test_params_editor_selection_wraps() {
+    let mut editor = ParamsEditor::new(Params::default());
+    assert_eq!(editor.selected(), Some(Param::Temperature));
+    editor.select_previous();
+    assert_eq!(editor.selected(), Some(Param::MaxTokens));
+    editor.select_next();
+    editor.select_next();
+    assert_eq!(editor.selected(), Some(Param::TopP));
+}
+
+#[test]
+fn test_retry_editor_has_a_model_row() {
+    let mut editor = ParamsEditor::retry(3, "llama3".to_string(), Params::default());
+    assert_eq!(editor.rows(), 4);
+    assert_eq!(editor.selected(), None);
+    editor.model_mut().unwrap().push_str(":70b ");
+    editor.select_next();
+    assert_eq!(editor.selected(), Some(Param::Temperature));
+    assert!(editor.model_mut().is_none());
+    editor.draft.step(Param::Temperature, true);
+
+    let retry = editor.into_retry().unwrap();
+    assert_eq!(retry.message, 3);
+    assert_eq!(retry.model, "llama3:70b");
+    assert!((retry.params.temperature - 0.8).abs() < f32::EPSILON);
+}
@@ -0,0 +1,154 @@
+// tests/plugins_tests.rs
+#![cfg(unix)]
+use chatti::plugins::{find, Action, Event, Plugin, PluginError, Plugins, Reply};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Writes an executable shell script.
+fn write_plugin(dir: &Path, name: &str, script: &str) -> PathBuf {
+    let path = dir.join(name);
+    fs::write(&path, format!("#!/bin/sh\n{script}")).unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+fn plugin(path: PathBuf) -> Plugin {
+    Plugin {
+        name: path.file_name().unwrap().to_string_lossy().into_owned(),
+        path,
+        commands: Vec::new(),
+    }
+}
+
+#[test]
+fn test_plugin_receives_events_and_replies() {
+    let dir = std::env::temp_dir().join(format!("chatti-plugin-run-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let timeout = Duration::from_secs(5);
+
+    // The event arrives as one line of JSON, and each line written back is
+    // an action.
+    let echo = plugin(write_plugin(
+        &dir,
+        "echo",
+        r#"read event
+printf '{"action": "add_message", "content": "%s"}\n\n' "$(echo "$event" | sed 's/"/'"'"'/g')"
+echo '{"action": "insert_text", "text": "done"}'
+"#,
+    ));
+    let event = Event::MessageSent {
+        content: "Hi".to_string(),
+        model: "llama3.2".to_string(),
+    };
+    assert_eq!(
+        echo.run(&event, timeout).unwrap(),
+        vec![
+            Action::AddMessage {
+                content: "{'event':'message_sent','content':'Hi','model':'llama3.2'}".to_string()
+            },
+            Action::InsertText {
+                text: "done".to_string()
+            },
+        ]
+    );
+
+    let failing = plugin(write_plugin(&dir, "failing", "echo broken >&2; exit 3\n"));
+    match failing.run(&Event::Load, timeout) {
+        Err(PluginError::Failed { stderr, .. }) => assert_eq!(stderr, "broken"),
+        other => panic!("expected a failed plugin, got {other:?}"),
+    }
+    let invalid = plugin(write_plugin(
+        &dir,
+        "invalid",
+        "echo '{\"action\": \"fly\"}'\n",
+    ));
+    assert!(matches!(
+        invalid.run(&Event::Load, timeout),
+        Err(PluginError::Action { .. })
+    ));
+    let slow = plugin(write_plugin(&dir, "slow", "exec sleep 5\n"));
+    assert!(matches!(
+        slow.run(&Event::Load, Duration::from_millis(100)),
+        Err(PluginError::TimedOut { .. })
+    ));
+    // An event larger than the pipe is not written before the deadline
+    // starts, so a plugin that never reads it is still stopped.
+    let large = Event::MessageSent {
+        content: "x".repeat(1024 * 1024),
+        model: "llama3.2".to_string(),
+    };
+    assert!(matches!(
+        slow.run(&large, Duration::from_millis(100)),
+        Err(PluginError::TimedOut { .. })
+    ));
+    assert!(!chatti::config::PluginsConfig::default().enabled);
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_plugins_register_and_run_commands() {
+    let dir = std::env::temp_dir().join(format!("chatti-plugin-load-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    write_plugin(
+        &dir,
+        "weather",
+        r#"read event
+case "$event" in
+*'"load"'*)
+    echo '{"action": "register_command", "name": "weather", "description": "<city>"}'
+    echo '{"action": "register_command", "name": "export"}'
+    echo '{"action": "add_message", "content": "ready"}'
+    ;;
+*'"command"'*)
+    echo '{"action": "insert_text", "text": "Forecast for Oslo"}'
+    ;;
+esac
+"#,
+    );
+    fs::write(dir.join("README.txt"), "not a plugin").unwrap();
+    assert_eq!(find(&dir).unwrap(), vec![dir.join("weather")]);
+    assert!(find(&dir.join("missing")).unwrap().is_empty());
+
+    let (mut plugins, mut rx) = Plugins::new(Duration::from_secs(5));
+    let errors = plugins.load(&dir);
+    // Built-in commands cannot be taken over.
+    assert!(matches!(
+        errors.as_slice(),
+        [PluginError::Command { name, .. }] if name == "export"
+    ));
+    assert_eq!(plugins.plugins.len(), 1);
+    assert_eq!(plugins.plugins[0].commands.len(), 1);
+    assert_eq!(
+        plugins.describe(&dir),
+        format!(
+            "Plugins in {}:\n- weather\n  /weather <city>",
+            dir.display()
+        )
+    );
+    assert_eq!(
+        rx.recv().await.unwrap().unwrap(),
+        Reply {
+            plugin: "weather".to_string(),
+            action: Action::AddMessage {
+                content: "ready".to_string()
+            },
+        }
+    );
+
+    assert!(!plugins.invoke("/forecast Oslo"));
+    assert!(!plugins.invoke("weather Oslo"));
+    assert!(plugins.invoke("/weather Oslo"));
+    assert_eq!(
+        rx.recv().await.unwrap().unwrap().action,
+        Action::InsertText {
+            text: "Forecast for Oslo".to_string()
+        }
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(plugins.load(&dir).is_empty());
+    assert!(plugins.plugins.is_empty());
+}
@@ -0,0 +1,46 @@
+// tests/project_tests.rs
+use chatti::config::Config;
+use chatti::project::{find_root, Project};
+
+#[test]
+fn test_project_settings_from_repository_root() {
+    let root = std::env::temp_dir().join(format!("chatti-project-{}", std::process::id()));
+    let nested = root.join("src").join("ui");
+    std::fs::create_dir_all(&nested).unwrap();
+    let root = root.canonicalize().unwrap();
+    std::fs::create_dir_all(root.join(".git")).unwrap();
+    assert_eq!(find_root(&nested).as_deref(), Some(root.as_path()));
+    assert_eq!(
+        Project::detect(&nested).unwrap(),
+        None,
+        "repositories without settings are not projects"
+    );
+
+    std::fs::write(root.join("README.md"), "# Demo\n").unwrap();
+    std::fs::write(
+        root.join(".chatti.toml"),
+        "model = \"coder\"\nsystem = \"Be brief.\"\nattach = [\"README.md\"]\n",
+    )
+    .unwrap();
+    let project = Project::detect(&nested).unwrap().unwrap();
+    assert_eq!(project.root, root);
+    assert!(project.name().starts_with("chatti-project-"));
+
+    let mut config: Config = toml::from_str(
+        "api_endpoint = \"http://localhost:11434/api/chat\"\nmodel = \"llama3\"\nstream = true\ntemperature = 0.7\n",
+    )
+    .unwrap();
+    project.apply(&mut config);
+    assert_eq!(config.model, "coder");
+
+    let started = project.start().unwrap();
+    assert_eq!(started.files, 1);
+    let system = started.system_prompt.unwrap();
+    assert!(system.starts_with("Be brief."));
+    assert!(system.contains("# Demo"));
+
+    let sessions = project.sessions_dir().unwrap();
+    assert!(sessions.ends_with("sessions"));
+    assert!(sessions.to_string_lossy().contains("/projects/"));
+    std::fs::remove_dir_all(&root).unwrap();
+}
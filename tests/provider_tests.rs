@@ -0,0 +1,176 @@
+// tests/provider_tests.rs
+use chatti::config::Config;
+use chatti::message::{Message, Metadata};
+use chatti::provider::{dry_run, ChatTemplate, Provider, TemplateName};
+use serde_json::json;
+
+fn config(extra: &str) -> Config {
+    toml::from_str(&format!(
+        "api_endpoint = \"http://localhost:8080/completion\"\nmodel = \"qwen\"\nstream = true\ntemperature = 0.5\nmax_tokens = 256\n{extra}"
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_llamacpp_request_renders_the_template() {
+    let config = config("provider = \"llamacpp\"\n\n[llamacpp]\ntemplate = \"llama3\"\n");
+    assert_eq!(config.provider, Provider::LlamaCpp);
+
+    let messages = [
+        Message::new("system", "Be brief."),
+        Message::new("user", "Hi"),
+        Message::new("assistant", "Hello!"),
+        Message::new("user", "Bye"),
+    ];
+    let body = config.provider.request_body(&config, &messages);
+    assert_eq!(
+        body["prompt"],
+        "<|begin_of_text|><|start_header_id|>system<|end_header_id|>\n\nBe brief.<|eot_id|>\
+         <|start_header_id|>user<|end_header_id|>\n\nHi<|eot_id|>\
+         <|start_header_id|>assistant<|end_header_id|>\n\nHello!<|eot_id|>\
+         <|start_header_id|>user<|end_header_id|>\n\nBye<|eot_id|>\
+         <|start_header_id|>assistant<|end_header_id|>\n\n"
+    );
+    assert_eq!(body["stop"][0], "<|eot_id|>");
+    assert_eq!(body["n_predict"], 256);
+    assert_eq!(body["stream"], true);
+    assert!(body.get("messages").is_none());
+}
+
+#[test]
+fn test_custom_template() {
+    let config = config(
+        "provider = \"llamacpp\"\n\n[llamacpp]\ntemplate = \"custom\"\n\n[llamacpp.custom]\nuser = \"### Instruction:\\n{content}\\n\\n\"\ngeneration = \"### Response:\\n\"\nstop = [\"###\"]\n",
+    );
+    let template = config.llamacpp.chat_template();
+    assert_eq!(
+        template.render(&[Message::new("user", "Hi")]),
+        "### Instruction:\nHi\n\n### Response:\n"
+    );
+    assert_eq!(template.stop, vec!["###"]);
+    assert!(ChatTemplate::preset(TemplateName::Custom).is_none());
+}
+
+#[test]
+fn test_ollama_is_the_default() {
+    let config = config("top_p = 0.9\n");
+    assert_eq!(config.provider, Provider::Ollama);
+
+    let body = config
+        .provider
+        .request_body(&config, &[Message::new("user", "Hi")]);
+    assert_eq!(body["model"], "qwen");
+    assert_eq!(body["messages"][0]["content"], "Hi");
+    assert_eq!(body["max_tokens"], 256);
+    assert!(body.get("stream_options").is_none());
+    assert!(Provider::Ollama.can_warm_up());
+    assert!(!Provider::LlamaCpp.can_warm_up());
+}
+
+#[test]
+fn test_unknown_template_is_a_configuration_error() {
+    let result: Result<Config, _> = toml::from_str(
+        "api_endpoint = \"\"\nmodel = \"\"\nstream = true\ntemperature = 0.5\n\n[llamacpp]\ntemplate = \"vicuna\"\n",
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_lmstudio_preset_defaults() {
+    let mut config: Config = toml::from_str(
+        "provider = \"lmstudio\"\nmodel = \"qwen\"\nstream = true\ntemperature = 0.5\n",
+    )
+    .unwrap();
+    config.apply_provider_defaults();
+    assert_eq!(config.provider, Provider::LmStudio);
+    assert_eq!(
+        config.api_endpoint,
+        "http://localhost:1234/v1/chat/completions"
+    );
+    assert!(config.api_key.is_none());
+    assert!(config.provider.streams_events());
+
+    let mut custom = config.clone();
+    custom.api_endpoint = String::from("http://gpu-box:1234/v1/chat/completions");
+    custom.apply_provider_defaults();
+    assert_eq!(
+        custom.api_endpoint,
+        "http://gpu-box:1234/v1/chat/completions"
+    );
+}
+
+#[test]
+fn test_openai_compatible_quirks() {
+    let provider = Provider::OpenAi;
+    let usage_only = json!({"choices": [], "usage": {"total_tokens": 12}});
+    assert_eq!(provider.content(&usage_only), None);
+    assert!(!provider.is_done(&usage_only));
+
+    let no_usage = json!({"choices": [{"message": {"content": "Hi"}, "finish_reason": "stop"}]});
+    assert_eq!(provider.content(&no_usage), Some("Hi"));
+    assert!(provider.is_done(&no_usage));
+
+    let legacy = json!({"choices": [{"text": "Hi", "finish_reason": "eos"}]});
+    assert_eq!(provider.content(&legacy), Some("Hi"));
+    assert!(provider.is_done(&legacy));
+
+    for reason in [json!(""), json!("null"), json!(null)] {
+        let part = json!({"choices": [{"delta": {"content": null}, "finish_reason": reason}]});
+        assert_eq!(provider.content(&part), None);
+        assert!(!provider.is_done(&part), "{reason}");
+    }
+
+    // Streamed requests ask for the usage, sent after the finish reason.
+    let mut config = config("provider = \"openai\"\n");
+    let body = provider.request_body(&config, &[Message::new("user", "Hi")]);
+    assert_eq!(body["stream_options"], json!({"include_usage": true}));
+    assert!(provider.ends_with_marker());
+    config.stream = false;
+    let body = provider.request_body(&config, &[Message::new("user", "Hi")]);
+    assert!(body.get("stream_options").is_none());
+}
+
+#[test]
+fn test_record_metadata() {
+    let mut metadata = Metadata::default();
+    let last = json!({"content": "", "stop": true, "stopped_limit": true, "stopped_eos": false,
+        "tokens_evaluated": 40, "tokens_predicted": 256});
+    Provider::LlamaCpp.record(&last, &mut metadata);
+    assert_eq!(metadata.prompt_tokens, Some(40));
+    assert_eq!(metadata.completion_tokens, Some(256));
+    assert_eq!(metadata.finish_reason.as_deref(), Some("limit"));
+
+    // The id comes with every part, the finish reason with the last one and
+    // the usage, if requested, after it.
+    let mut metadata = Metadata::default();
+    let parts = [
+        json!({"id": "chatcmpl-1", "choices": [{"delta": {"content": "Hi"}, "finish_reason": null}]}),
+        json!({"id": "chatcmpl-1", "choices": [{"delta": {}, "finish_reason": "stop"}]}),
+        json!({"id": "chatcmpl-1", "choices": [], "usage": {"prompt_tokens": 9, "completion_tokens": 1}}),
+    ];
+    for part in &parts {
+        Provider::OpenAi.record(part, &mut metadata);
+    }
+    assert_eq!(
+        metadata,
+        Metadata {
+            prompt_tokens: Some(9),
+            completion_tokens: Some(1),
+            finish_reason: Some("stop".to_string()),
+            request_id: Some("chatcmpl-1".to_string()),
+            ..Metadata::default()
+        }
+    );
+}
+
+#[test]
+fn test_dry_run_hides_the_api_key() {
+    let mut config = config("provider = \"llamacpp\"\n");
+    config.api_key = Some(String::from("sk-secret"));
+    let preview = dry_run(&config, &[Message::new("user", "Hi")]);
+    assert!(preview.starts_with("Dry run, nothing was sent:"));
+    assert!(preview.contains("POST http://localhost:8080/completion\n"));
+    assert!(preview.contains("Authorization: Bearer <hidden>"));
+    assert!(!preview.contains("sk-secret"));
+    assert!(preview.contains("\"n_predict\": 256"));
+}
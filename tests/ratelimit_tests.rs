@@ -0,0 +1,109 @@
+// tests/ratelimit_tests.rs
+use chatti::config::{RateLimit, RateLimitConfig};
+use chatti::ratelimit::RateLimiter;
+use std::time::Duration;
+
+#[test]
+fn test_profile_limits_override_general_ones() {
+    let config: RateLimitConfig = toml::from_str(
+        r"
+        requests_per_minute = 20
+        max_concurrent = 2
+
+        [profiles.paid]
+        requests_per_minute = 5
+        ",
+    )
+    .unwrap();
+    assert_eq!(
+        config.for_profile("paid"),
+        RateLimit {
+            requests_per_minute: Some(5),
+            max_concurrent: Some(2),
+        }
+    );
+    assert_eq!(config.for_profile("default").requests_per_minute, Some(20));
+}
+
+#[tokio::test]
+async fn test_requests_per_minute() {
+    let limiter = RateLimiter::new(RateLimit {
+        requests_per_minute: Some(2),
+        max_concurrent: None,
+    });
+    let _first = limiter.acquire().await;
+    assert_eq!(limiter.delay(), None);
+    let _second = limiter.clone().acquire().await;
+    let delay = limiter.delay().expect("the third request should wait");
+    assert!(delay > Duration::from_secs(55) && delay <= Duration::from_secs(60));
+    assert!(
+        tokio::time::timeout(Duration::from_millis(50), limiter.acquire())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_zero_requests_per_minute_is_no_limit() {
+    let limiter = RateLimiter::new(RateLimit {
+        requests_per_minute: Some(0),
+        max_concurrent: None,
+    });
+    let permit = tokio::time::timeout(Duration::from_millis(50), limiter.acquire()).await;
+    assert!(permit.is_ok(), "a limit of 0 should not block requests");
+    assert_eq!(limiter.delay(), None);
+}
+
+#[tokio::test]
+async fn test_concurrent_requests() {
+    let limiter = RateLimiter::new(RateLimit {
+        requests_per_minute: None,
+        max_concurrent: Some(1),
+    });
+    let first = limiter.acquire().await;
+    assert!(
+        tokio::time::timeout(Duration::from_millis(50), limiter.acquire())
+            .await
+            .is_err(),
+        "a second request should wait for the first"
+    );
+    drop(first);
+    assert!(
+        tokio::time::timeout(Duration::from_millis(50), limiter.acquire())
+            .await
+            .is_ok()
+    );
+}
+
+#[tokio::test]
+async fn test_queued_and_in_flight_requests() {
+    let limiter = RateLimiter::new(RateLimit {
+        requests_per_minute: None,
+        max_concurrent: Some(1),
+    });
+    assert_eq!(limiter.max_concurrent(), Some(1));
+    let first = limiter.acquire().await;
+    assert_eq!((limiter.queued(), limiter.in_flight()), (0, 1));
+
+    let waiting = tokio::spawn({
+        let limiter = limiter.clone();
+        async move { limiter.acquire().await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!((limiter.queued(), limiter.in_flight()), (1, 1));
+
+    drop(first);
+    let second = waiting.await.unwrap();
+    assert_eq!((limiter.queued(), limiter.in_flight()), (0, 1));
+    drop(second);
+    assert_eq!(limiter.in_flight(), 0);
+
+    // A request dropped while it waits is no longer counted.
+    let _first = limiter.acquire().await;
+    assert!(
+        tokio::time::timeout(Duration::from_millis(50), limiter.acquire())
+            .await
+            .is_err()
+    );
+    assert_eq!(limiter.queued(), 0);
+}
@@ -0,0 +1,98 @@
+// tests/renderer_tests.rs
+use chatti::error::ErrorDetails;
+use chatti::message::Message;
+use chatti::ui::renderer::Renderer;
+use chatti::ui::theme::Theme;
+
+fn text(lines: &[ratatui::text::Line]) -> Vec<String> {
+    lines
+        .iter()
+        .map(|line| line.spans.iter().map(|s| s.content.as_ref()).collect())
+        .collect()
+}
+
+#[test]
+fn test_error_card_expands_details() {
+    let mut message = Message {
+        error: Some(ErrorDetails {
+            status: Some(500),
+            request_id: Some("req-42".to_string()),
+            body: Some("internal".to_string()),
+            source: "API error (500): boom".to_string(),
+        }),
+        ..Message::new("error", "boom")
+    };
+
+    let collapsed = text(&Renderer::message_lines(&message, 80, &Theme::default()));
+    assert_eq!(collapsed[0], "Error: boom");
+    assert!(collapsed[1].contains("press x"));
+    assert!(!collapsed.iter().any(|line| line.contains("req-42")));
+
+    message.expanded = true;
+    let expanded = text(&Renderer::message_lines(&message, 80, &Theme::default()));
+    assert!(expanded.iter().any(|line| line.trim() == "status: 500"));
+    assert!(expanded
+        .iter()
+        .any(|line| line.trim() == "request id: req-42"));
+    assert!(expanded.iter().any(|line| line.trim() == "body: internal"));
+}
+
+#[test]
+fn test_attachment_is_collapsed() {
+    let message = Message {
+        attachment: Some("line one\nline two\nline three".to_string()),
+        ..Message::new("user", "What is this?")
+    };
+
+    let lines = text(&Renderer::message_lines(&message, 80, &Theme::default()));
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].contains("attached context: 3 line(s)"));
+    assert!(!lines.iter().any(|line| line.contains("line two")));
+}
+
+#[test]
+fn test_sources_are_listed_under_the_answer() {
+    let message = Message {
+        sources: vec!["notes.md:1-12".to_string(), "todo.md:4-9".to_string()],
+        ..Message::new("assistant", "Rotate it monthly [1].")
+    };
+
+    let lines = text(&Renderer::message_lines(&message, 80, &Theme::default()));
+    assert!(lines
+        .last()
+        .unwrap()
+        .contains("[sources: notes.md:1-12, todo.md:4-9]"));
+}
+
+#[test]
+fn test_raw_toggle_shows_the_markdown_source() {
+    use chatti::ui::input_handler::InputHandler;
+    use chatti::ui::state::State;
+    use crossterm::event::KeyCode;
+
+    let mut ui_state = State::new();
+    ui_state.load_messages(vec![
+        Message::new("user", "**Hi**"),
+        Message::new("assistant", "**Bold** and\n\n    indented code"),
+    ]);
+    let rendered = text(&Renderer::message_lines(
+        &ui_state.messages[1],
+        80,
+        &Theme::default(),
+    ));
+    assert!(!rendered.iter().any(|line| line.contains("**")));
+
+    InputHandler::new().handle_normal_mode(&mut ui_state, KeyCode::Char('R'));
+    assert!(ui_state.messages[1].raw);
+    let raw = text(&Renderer::message_lines(
+        &ui_state.messages[1],
+        80,
+        &Theme::default(),
+    ));
+    assert!(raw[0].ends_with("**Bold** and"));
+    assert!(raw[2].ends_with("    indented code"));
+
+    ui_state.scroll_up();
+    InputHandler::new().handle_normal_mode(&mut ui_state, KeyCode::Char('R'));
+    assert!(!ui_state.messages[0].raw, "only answers are toggled");
+}
@@ -0,0 +1,102 @@
+// tests/retention_tests.rs
+use chatti::commands::Command;
+use chatti::config::Config;
+use chatti::message::Message;
+use chatti::retention::{cutoff, sweep, Action, Archive};
+use chatti::session::Session;
+use chatti::store::{JsonStore, SessionStore};
+use std::fs;
+use std::path::PathBuf;
+
+fn temp_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("chatti-retention-{name}-{}", std::process::id()))
+}
+
+fn session(id: &str, updated_at: u64) -> Session {
+    let mut session = Session::from_messages(vec![Message::new("user", format!("Prompt {id}"))]);
+    session.id = id.to_string();
+    session.updated_at = updated_at;
+    session
+}
+
+fn stored_ids(store: &JsonStore) -> Vec<String> {
+    store
+        .list()
+        .unwrap()
+        .into_iter()
+        .map(|info| info.id)
+        .collect()
+}
+
+#[test]
+fn test_sweep_archives_old_sessions() {
+    let dir = temp_dir("archive");
+    let store = JsonStore::new(dir.join("sessions"));
+    let archive = Archive::new(dir.join("archive"));
+    let old = session("old", 10);
+    store.save(&old).unwrap();
+    for (id, updated_at) in [("open", 20), ("new", 300)] {
+        store.save(&session(id, updated_at)).unwrap();
+    }
+
+    let swept = sweep(&store, &archive, 100, Action::Archive, Some("open")).unwrap();
+    assert_eq!(swept, 1);
+    assert_eq!(stored_ids(&store), ["new", "open"]);
+    assert!(archive.dir().join("old.json.gz").exists());
+    assert_eq!(archive.load("old").unwrap(), old);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_sweep_deletes_old_sessions() {
+    let dir = temp_dir("delete");
+    let store = JsonStore::new(dir.join("sessions"));
+    let archive = Archive::new(dir.join("archive"));
+    store.save(&session("old", 10)).unwrap();
+    store.save(&session("new", 300)).unwrap();
+
+    assert_eq!(
+        sweep(&store, &archive, 100, Action::Delete, None).unwrap(),
+        1
+    );
+    assert_eq!(stored_ids(&store), ["new"]);
+    assert!(!archive.dir().exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_cutoff_counts_days_back_from_now() {
+    let now = cutoff(0);
+    assert_eq!(now - cutoff(2), 2 * 24 * 60 * 60);
+    assert_eq!(cutoff(u64::MAX), 0);
+}
+
+#[test]
+fn test_parse_retention_config() {
+    let config: Config = toml::from_str(
+        "api_endpoint = \"http://localhost:11434/api/chat\"\nmodel = \"llama3\"\nstream = true\ntemperature = 0.7\n[retention]\ndays = 90\naction = \"delete\"\n",
+    )
+    .unwrap();
+    assert_eq!(config.retention.days, Some(90));
+    assert_eq!(config.retention.action, Action::Delete);
+
+    let config: Config = toml::from_str(
+        "api_endpoint = \"http://localhost:11434/api/chat\"\nmodel = \"llama3\"\nstream = true\ntemperature = 0.7\n",
+    )
+    .unwrap();
+    assert_eq!(config.retention.days, None);
+    assert_eq!(config.retention.action, Action::Archive);
+}
+
+#[test]
+fn test_parse_archive_command() {
+    assert_eq!(Command::parse("/archive"), Some(Command::Archive(None)));
+    assert_eq!(
+        Command::parse("/archive 30"),
+        Some(Command::Archive(Some(30)))
+    );
+    assert_eq!(
+        Command::parse("/archive soon"),
+        Some(Command::Invalid(String::from("Usage: /archive [days]")))
+    );
+}
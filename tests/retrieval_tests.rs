@@ -0,0 +1,167 @@
+// tests/retrieval_tests.rs
+use chatti::config::RetrievalConfig;
+use chatti::retrieval::{self, split, Chunk, Index};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+fn chunk(source: &str, embedding: Vec<f32>) -> Chunk {
+    Chunk {
+        source: source.to_string(),
+        start_line: 1,
+        end_line: 1,
+        text: source.to_string(),
+        embedding,
+    }
+}
+
+/// Serves embeddings that count the occurrences of "apple" and "pear" in
+/// each input, and counts the inputs embedded.
+async fn serve_embeddings(embedded: Arc<AtomicUsize>) -> std::net::SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 4096];
+            let body = loop {
+                let read = socket.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..read]);
+                let text = String::from_utf8_lossy(&request).into_owned();
+                if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                    let length: usize = head
+                        .lines()
+                        .find_map(|line| line.strip_prefix("content-length: "))
+                        .and_then(|value| value.trim().parse().ok())
+                        .unwrap_or(0);
+                    if body.len() >= length {
+                        break body.to_string();
+                    }
+                }
+            };
+            let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+            let embeddings: Vec<Vec<f32>> = json["input"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|input| {
+                    let input = input.as_str().unwrap();
+                    #[allow(clippy::cast_precision_loss)]
+                    let count = |word| input.matches(word).count() as f32;
+                    vec![count("apple"), count("pear")]
+                })
+                .collect();
+            embedded.fetch_add(embeddings.len(), Ordering::SeqCst);
+            let body = serde_json::json!({ "embeddings": embeddings }).to_string();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+    });
+    address
+}
+
+#[test]
+fn test_split_keeps_long_lines_whole() {
+    let long = "x".repeat(20);
+    let chunks = split(&format!("short\n{long}\nend"), 10);
+    assert_eq!(chunks.len(), 3);
+    assert_eq!(chunks[1], (2, 2, long));
+    assert_eq!(chunks[2], (3, 3, "end".to_string()));
+    assert!(split("\n\n  \n", 10).is_empty());
+}
+
+#[test]
+fn test_split_counts_characters() {
+    // Five characters but fifteen bytes per line.
+    let chunks = split("ääääå\nööööø\nüüüüù", 12);
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(chunks[0], (1, 2, "ääääå\nööööø".to_string()));
+}
+
+#[test]
+fn test_search_orders_by_similarity() {
+    let mut index = Index::default();
+    index.chunks = vec![
+        chunk("pears.md", vec![0.0, 1.0]),
+        chunk("apples.md", vec![1.0, 0.0]),
+        chunk("both.md", vec![1.0, 1.0]),
+    ];
+    let found: Vec<_> = index
+        .search(&[1.0, 0.2], 2)
+        .into_iter()
+        .map(|chunk| chunk.source.as_str())
+        .collect();
+    assert_eq!(found, vec!["apples.md", "both.md"]);
+}
+
+#[test]
+fn test_embeddings_endpoint_defaults_to_api_host() {
+    let config = RetrievalConfig::default();
+    assert_eq!(
+        retrieval::embeddings_endpoint(&config, "http://localhost:11434/api/chat"),
+        "http://localhost:11434/api/embed"
+    );
+    let config = RetrievalConfig {
+        endpoint: Some("http://embed.local/v1/embed".to_string()),
+        ..RetrievalConfig::default()
+    };
+    assert_eq!(
+        retrieval::embeddings_endpoint(&config, "http://localhost:11434/api/chat"),
+        "http://embed.local/v1/embed"
+    );
+}
+
+#[tokio::test]
+async fn test_refresh_embeds_only_changed_files_and_retrieves() {
+    let dir = std::env::temp_dir().join(format!("chatti-retrieval-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("fruit")).unwrap();
+    std::fs::create_dir_all(dir.join(".git")).unwrap();
+    std::fs::write(dir.join("fruit/apple.md"), "An apple a day.").unwrap();
+    std::fs::write(dir.join("pear.md"), "A pear, a pear.").unwrap();
+    std::fs::write(dir.join(".git/HEAD"), "apple apple apple").unwrap();
+
+    let embedded = Arc::new(AtomicUsize::new(0));
+    let address = serve_embeddings(embedded.clone()).await;
+    let endpoint = format!("http://{address}/api/embed");
+    let client = reqwest::Client::new();
+    let config = RetrievalConfig {
+        top_k: 1,
+        ..RetrievalConfig::default()
+    };
+
+    let index = Index::default()
+        .refresh(&client, &endpoint, &dir, &config)
+        .await
+        .unwrap();
+    assert_eq!(index.chunks.len(), 2, "hidden directories are skipped");
+    assert_eq!(embedded.load(Ordering::SeqCst), 2);
+
+    let index = index
+        .refresh(&client, &endpoint, &dir, &config)
+        .await
+        .unwrap();
+    assert_eq!(embedded.load(Ordering::SeqCst), 2, "nothing changed");
+
+    std::fs::write(dir.join("pear.md"), "Pears and more pears.").unwrap();
+    let index = index
+        .refresh(&client, &endpoint, &dir, &config)
+        .await
+        .unwrap();
+    assert_eq!(embedded.load(Ordering::SeqCst), 3);
+    assert_eq!(index.chunks.len(), 2);
+
+    let found = retrieval::retrieve(&client, &endpoint, &index, &config, "which apple?")
+        .await
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(
+        found[0].label(),
+        format!("fruit{}apple.md:1-1", std::path::MAIN_SEPARATOR)
+    );
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,77 @@
+// tests/script_tests.rs
+use chatti::message::Message;
+use chatti::script::{Answer, Script, ScriptError};
+use std::time::Duration;
+
+#[test]
+fn test_answer_json_lines() {
+    let mut answer = Answer {
+        prompt: "Hello".to_string(),
+        model: "llama3".to_string(),
+        response: Ok("Hi there!".to_string()),
+        prompt_tokens: 12,
+        first_token: Some(Duration::from_millis(250)),
+        elapsed: Duration::from_millis(1300),
+    };
+    let line = answer.to_json_line(2);
+    assert!(!line.contains('\n'));
+    let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(json["step"], 2);
+    assert_eq!(json["model"], "llama3");
+    assert_eq!(json["response"], "Hi there!");
+    assert!(json["error"].is_null());
+    assert_eq!(json["tokens"]["prompt"], 12);
+    assert!(json["tokens"]["response"].as_u64().unwrap() > 0);
+    assert_eq!(json["timings"]["first_token_ms"], 250);
+    assert_eq!(json["timings"]["total_ms"], 1300);
+
+    answer.response = Err("Network error: connection refused".to_string());
+    answer.first_token = None;
+    let json: serde_json::Value = serde_json::from_str(&answer.to_json_line(1)).unwrap();
+    assert!(json["response"].is_null());
+    assert_eq!(json["error"], "Network error: connection refused");
+    assert_eq!(json["tokens"]["response"], 0);
+    assert!(json["timings"]["first_token_ms"].is_null());
+}
+
+#[test]
+fn test_load_yaml_script_with_overrides() {
+    let path = std::env::temp_dir().join(format!("chatti-script-{}.yaml", std::process::id()));
+    std::fs::write(
+        &path,
+        "model: llama3\nsystem: Be brief.\nsteps:\n  - prompt: First\n  - prompt: Second\n    model: mistral\n    system: Be verbose.\n",
+    )
+    .unwrap();
+    let script = Script::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(script.steps.len(), 2);
+    assert_eq!(script.model_for(&script.steps[0], "default"), "llama3");
+    assert_eq!(script.model_for(&script.steps[1], "default"), "mistral");
+
+    let history = vec![
+        Message::new("user", "First"),
+        Message::new("assistant", "One"),
+    ];
+    let request = script.request(&script.steps[1], &history);
+    let roles: Vec<_> = request.iter().map(|m| m.role.as_str()).collect();
+    assert_eq!(roles, vec!["system", "user", "assistant", "user"]);
+    assert_eq!(request[0].content, "Be verbose.");
+    assert_eq!(request[3].content, "Second");
+}
+
+#[test]
+fn test_load_rejects_empty_script() {
+    let path = std::env::temp_dir().join(format!("chatti-script-{}.txt", std::process::id()));
+    std::fs::write(&path, "# only a comment\n\n").unwrap();
+    let result = Script::load(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(ScriptError::Empty)));
+
+    let script = Script::from_lines("Hello");
+    assert_eq!(
+        script.model_for(&script.steps[0], "configured"),
+        "configured"
+    );
+    assert_eq!(script.request(&script.steps[0], &[]).len(), 1);
+}
@@ -0,0 +1,188 @@
+// tests/scripting_tests.rs
+use chatti::message::Message;
+use chatti::scripting::{find, ScriptingError, Scripts, Snapshot};
+use std::fs;
+use std::path::Path;
+
+fn write_scripts(dir: &Path, scripts: &[(&str, &str)]) {
+    fs::create_dir_all(dir).unwrap();
+    for (name, source) in scripts {
+        fs::write(dir.join(name), source).unwrap();
+    }
+}
+
+#[cfg(feature = "scripting")]
+#[test]
+fn test_scripts_transform_prompts_and_answers() {
+    let dir = std::env::temp_dir().join(format!("chatti-scripts-{}", std::process::id()));
+    write_scripts(
+        &dir,
+        &[
+            (
+                "1-prefix.rhai",
+                r#"
+                fn on_prompt(prompt) { "Answer briefly. " + prompt }
+                fn on_response(response) { response.trim(); response }
+                "#,
+            ),
+            (
+                "2-model.rhai",
+                r#"
+                fn on_prompt(prompt) {
+                    notify("sending to " + model());
+                    prompt + " (" + messages().len() + " messages)"
+                }
+                "#,
+            ),
+            ("3-broken.rhai", "fn on_prompt(prompt) { prompt +"),
+            ("notes.txt", "not a script"),
+        ],
+    );
+    assert_eq!(find(&dir).unwrap().len(), 3);
+
+    let (scripts, errors) = Scripts::load(&dir);
+    assert!(matches!(
+        errors.as_slice(),
+        [ScriptingError::Compile { script, .. }] if script == "3-broken.rhai"
+    ));
+    assert_eq!(scripts.names(), vec!["1-prefix.rhai", "2-model.rhai"]);
+
+    let snapshot = Snapshot {
+        model: String::from("llama3.2"),
+        messages: vec![Message::new("user", "What is Rust?")],
+        input: String::new(),
+    };
+    let outcome = scripts.on_prompt(&snapshot, "What is Rust?");
+    assert_eq!(
+        outcome.text.as_deref(),
+        Some("Answer briefly. What is Rust? (1 messages)")
+    );
+    assert_eq!(outcome.effects.notices, vec!["sending to llama3.2"]);
+    assert!(outcome.errors.is_empty());
+
+    let outcome = scripts.on_response(&snapshot, "  A language.\n");
+    assert_eq!(outcome.text.as_deref(), Some("A language."));
+    // Unchanged text is not returned.
+    assert!(scripts.on_response(&snapshot, "Done").text.is_none());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "scripting")]
+#[test]
+fn test_script_commands() {
+    let dir = std::env::temp_dir().join(format!("chatti-script-commands-{}", std::process::id()));
+    write_scripts(
+        &dir,
+        &[(
+            "tools.rhai",
+            r#"
+            fn command_shout(args) { set_input(input() + args.to_upper()); }
+            fn command_ask(args) { send("Explain " + args); "asking" }
+            fn command_export(args) { }
+            fn command_fail(args) { throw "no luck"; }
+            fn command_spin(args) { loop { } }
+            "#,
+        )],
+    );
+    let (scripts, errors) = Scripts::load(&dir);
+    // Built-in commands cannot be taken over.
+    assert!(matches!(
+        errors.as_slice(),
+        [ScriptingError::Command { name, .. }] if name == "export"
+    ));
+    assert_eq!(
+        scripts.describe(&dir),
+        format!(
+            "Scripts in {}:\n- tools.rhai\n  /ask\n  /fail\n  /shout\n  /spin",
+            dir.display()
+        )
+    );
+
+    let snapshot = Snapshot {
+        input: String::from("Say "),
+        ..Snapshot::default()
+    };
+    let outcome = scripts.command(&snapshot, "/shout hello").unwrap();
+    assert_eq!(outcome.effects.input.as_deref(), Some("Say HELLO"));
+    assert!(outcome.effects.notices.is_empty());
+
+    let outcome = scripts.command(&snapshot, "/ask monads").unwrap();
+    assert_eq!(outcome.effects.prompt.as_deref(), Some("Explain monads"));
+    assert_eq!(outcome.effects.notices, vec!["asking"]);
+
+    let outcome = scripts.command(&snapshot, "/fail").unwrap();
+    assert!(matches!(
+        outcome.errors.as_slice(),
+        [ScriptingError::Run { message, .. }] if message.contains("no luck")
+    ));
+    // A script running too long is stopped.
+    let outcome = scripts.command(&snapshot, "/spin").unwrap();
+    assert_eq!(outcome.errors.len(), 1);
+
+    assert!(scripts.command(&snapshot, "/other").is_none());
+    assert!(scripts.command(&snapshot, "shout hello").is_none());
+
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[cfg(feature = "scripting")]
+#[test]
+fn test_scripts_cannot_import_or_grow_without_bound() {
+    let dir = std::env::temp_dir().join(format!("chatti-script-limits-{}", std::process::id()));
+    let module = dir.with_extension("module");
+    fs::create_dir_all(&module).unwrap();
+    fs::write(module.join("helper.rhai"), "fn answer() { 42 }").unwrap();
+    let source = format!(
+        r#"
+        fn command_imports(args) {{ import "{}" as helper; helper::answer().to_string() }}
+        fn command_grow(args) {{ let text = "x"; loop {{ text += text; }} }}
+        fn command_fill(args) {{ let items = []; loop {{ items.push(1); }} }}
+        "#,
+        module.join("helper").display()
+    );
+    write_scripts(&dir, &[("limits.rhai", &source)]);
+    let (scripts, errors) = Scripts::load(&dir);
+    assert!(errors.is_empty());
+
+    let snapshot = Snapshot::default();
+    for (command, error) in [
+        ("/imports", "Module not found"),
+        ("/grow", "too large"),
+        ("/fill", "too large"),
+    ] {
+        let outcome = scripts.command(&snapshot, command).unwrap();
+        assert!(
+            matches!(
+                outcome.errors.as_slice(),
+                [ScriptingError::Run { message, .. }] if message.contains(error)
+            ),
+            "{command} should be stopped"
+        );
+        assert!(outcome.effects.notices.is_empty());
+    }
+
+    fs::remove_dir_all(dir).unwrap();
+    fs::remove_dir_all(module).unwrap();
+}
+
+#[cfg(not(feature = "scripting"))]
+#[test]
+fn test_scripts_need_feature() {
+    let dir = std::env::temp_dir().join(format!("chatti-scripts-off-{}", std::process::id()));
+    write_scripts(&dir, &[("prefix.rhai", "fn on_prompt(p) { p }")]);
+    assert_eq!(find(&dir).unwrap(), vec![dir.join("prefix.rhai")]);
+
+    let (scripts, errors) = Scripts::load(&dir);
+    assert!(matches!(errors.as_slice(), [ScriptingError::Unsupported]));
+    assert!(scripts.is_empty());
+    let snapshot = Snapshot {
+        messages: vec![Message::new("user", "Hi")],
+        ..Snapshot::default()
+    };
+    assert!(scripts.on_prompt(&snapshot, "Hi").text.is_none());
+    assert!(scripts.command(&snapshot, "/prefix").is_none());
+    assert!(Scripts::load(&dir.join("missing")).1.is_empty());
+
+    fs::remove_dir_all(dir).unwrap();
+}
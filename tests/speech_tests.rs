@@ -0,0 +1,25 @@
+// tests/speech_tests.rs
+use chatti::speech::SentenceSplitter;
+
+#[test]
+fn test_sentences_split_across_chunks() {
+    let mut splitter = SentenceSplitter::new();
+    let mut sentences = Vec::new();
+    for chunk in ["Pi is 3.", "14 roughly! Isn't", " it neat? Yes", " it is"] {
+        sentences.extend(splitter.push(chunk));
+    }
+    assert_eq!(sentences, vec!["Pi is 3.14 roughly!", "Isn't it neat?"]);
+    assert_eq!(splitter.finish().as_deref(), Some("Yes it is"));
+}
+
+#[test]
+fn test_code_blocks_and_markup_are_skipped() {
+    let mut splitter = SentenceSplitter::new();
+    let sentences = splitter.push(
+        "## Example\nRun this:\n```sh\necho done. really\n```\n- Then **check** the `output`.\n",
+    );
+    assert_eq!(
+        sentences,
+        vec!["Example", "Run this:", "Then check the output."]
+    );
+}
@@ -1,5 +1,7 @@
 // tests/spinner_tests.rs
-use chatti::ui::spinner::Spinner;
+use chatti::config::{SpinnerConfig, SpinnerStyle};
+use chatti::tokens::estimate_tokens;
+use chatti::ui::spinner::{Progress, Spinner};
 
 #[test]
 fn test_spinner() {
@@ -8,3 +10,42 @@ fn test_spinner() {
     let second_frame = spinner.next_frame();
     assert_ne!(first_frame, second_frame);
 }
+
+#[test]
+fn test_ascii_spinner() {
+    let mut spinner = Spinner::ascii();
+    for _ in 0..8 {
+        assert!(spinner.next_frame().is_ascii());
+    }
+}
+
+#[test]
+fn test_spinner_from_config() {
+    let config: SpinnerConfig = toml::from_str("style = \"dots\"").unwrap();
+    assert_eq!(config.style, SpinnerStyle::Dots);
+    let mut dots = Spinner::from_config(&config, true);
+    assert_eq!(dots.next_frame(), "·  ");
+    assert!(
+        Spinner::from_config(&config, false).next_frame().is_ascii(),
+        "built-in styles fall back to ASCII without unicode"
+    );
+}
+
+#[test]
+fn test_progress_counts_received_tokens() {
+    let mut progress = Progress::start();
+    assert!(
+        progress.describe().ends_with('s'),
+        "only the elapsed time is shown before the first token"
+    );
+    progress.record("Hello");
+    progress.record(" world, how are you?");
+    assert_eq!(
+        progress.tokens(),
+        estimate_tokens("Hello world, how are you?")
+    );
+    let description = progress.describe();
+    assert!(description.contains(&format!("s · {} tokens · ", progress.tokens())));
+    assert!(description.ends_with(" tok/s"));
+    assert!(progress.tokens_per_second() >= 0.0);
+}
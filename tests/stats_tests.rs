@@ -0,0 +1,72 @@
+// tests/stats_tests.rs
+use chatti::commands::Command;
+use chatti::message::{Message, Metadata};
+use chatti::stats::Stats;
+use chatti::tokens::estimate_tokens;
+use std::time::Duration;
+
+fn answer(content: &str, metadata: Option<Metadata>) -> Message {
+    Message {
+        metadata,
+        ..Message::new("assistant", content)
+    }
+}
+
+#[test]
+fn test_summary_stats() {
+    assert_eq!(
+        Command::parse("/summary-stats"),
+        Some(Command::SummaryStats)
+    );
+
+    let reported = |prompt, completion, ms| Metadata {
+        prompt_tokens: Some(prompt),
+        completion_tokens: Some(completion),
+        total_ms: Some(ms),
+        ..Metadata::default()
+    };
+    let messages = vec![
+        Message::new("system", "Started a new chat"),
+        Message::new("user", "Explain ownership"),
+        answer("Each value has one owner.", Some(reported(20, 400, 6000))),
+        Message::new("user", "Shorter"),
+        answer("One owner per value.", Some(reported(430, 6, 1000))),
+        Message {
+            translation: Some("French".to_string()),
+            ..answer("Un propriétaire par valeur.", None)
+        },
+    ];
+    let stats = Stats::of(&messages);
+    assert_eq!(stats.turns, 2);
+    assert_eq!(stats.answers, 2);
+    assert_eq!(stats.prompt_tokens, 450);
+    assert_eq!(stats.completion_tokens, 406);
+    assert!(!stats.estimated);
+    assert_eq!(stats.mean_response, Some(Duration::from_millis(3500)));
+    assert_eq!(
+        stats.longest,
+        Some((1, 400, Some(Duration::from_millis(6000))))
+    );
+    assert!(!stats.describe().contains('~'));
+}
+
+#[test]
+fn test_summary_stats_estimates_unreported_tokens() {
+    let messages = vec![
+        Message::new("user", "Hi there"),
+        answer("Hello! How can I help you today?", None),
+    ];
+    let stats = Stats::of(&messages);
+    assert!(stats.estimated);
+    assert_eq!(stats.prompt_tokens, estimate_tokens("Hi there"));
+    assert_eq!(
+        stats.completion_tokens,
+        estimate_tokens("Hello! How can I help you today?")
+    );
+    assert_eq!(stats.mean_response, None);
+    let text = stats.describe();
+    assert!(text.contains("- Tokens: ~"));
+    assert!(!text.contains("Average response time"));
+
+    assert_eq!(Stats::of(&[]).longest, None);
+}
@@ -0,0 +1,127 @@
+// tests/store_tests.rs
+use chatti::config::{Config, FSError};
+use chatti::message::Message;
+use chatti::session::Session;
+use chatti::store::{Backend, JsonStore, SessionInfo, SessionStore};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+fn temp_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("chatti-store-{name}-{}", std::process::id()))
+}
+
+fn session(id: &str, updated_at: u64, prompt: &str) -> Session {
+    let mut session = Session::from_messages(vec![
+        Message::new("user", prompt),
+        Message::new("assistant", "Sure."),
+    ]);
+    session.id = id.to_string();
+    session.updated_at = updated_at;
+    session
+}
+
+/// Checks the behavior every store shares.
+fn exercise(store: &dyn SessionStore) {
+    store.save(&session("a", 10, "Explain lifetimes")).unwrap();
+    store.save(&session("b", 30, "Write a haiku")).unwrap();
+    store.save(&session("c", 20, "Lifetimes again")).unwrap();
+    store
+        .save(&session("a", 40, "Explain LIFETIMES in Rust"))
+        .unwrap();
+
+    let loaded = store.load("a").unwrap();
+    assert_eq!(loaded.messages[0].content, "Explain LIFETIMES in Rust");
+    assert!(store.load("missing").is_err());
+
+    let ids =
+        |infos: Vec<SessionInfo>| -> Vec<String> { infos.into_iter().map(|i| i.id).collect() };
+    assert_eq!(ids(store.list().unwrap()), ["a", "b", "c"]);
+    assert_eq!(ids(store.search("lifetimes").unwrap()), ["a", "c"]);
+    assert_eq!(ids(store.search("sure").unwrap()), ["a", "b", "c"]);
+    assert!(store.search("100%").unwrap().is_empty());
+
+    let info = &store.list().unwrap()[0];
+    assert_eq!(info.title, "Explain LIFETIMES in Rust");
+    assert_eq!(info.messages, 2);
+
+    store.delete("c").unwrap();
+    store.delete("missing").unwrap();
+    assert!(store.load("c").is_err());
+    assert_eq!(ids(store.search("lifetimes").unwrap()), ["a"]);
+}
+
+#[test]
+fn test_json_store() {
+    let dir = temp_dir("json");
+    let store = JsonStore::new(dir.clone());
+    assert!(store.list().unwrap().is_empty());
+    exercise(&store);
+    assert!(dir.join("a.json").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+/// A store kept in memory, relying on the default search.
+#[derive(Default)]
+struct MemoryStore {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl SessionStore for MemoryStore {
+    fn save(&self, session: &Session) -> Result<(), FSError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.insert(session.id.clone(), session.clone());
+        Ok(())
+    }
+
+    fn load(&self, id: &str) -> Result<Session, FSError> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .get(id)
+            .cloned()
+            .ok_or_else(|| FSError::IoError(std::io::Error::from(std::io::ErrorKind::NotFound)))
+    }
+
+    fn delete(&self, id: &str) -> Result<(), FSError> {
+        self.sessions.lock().unwrap().remove(id);
+        Ok(())
+    }
+
+    fn list(&self) -> Result<Vec<SessionInfo>, FSError> {
+        let sessions = self.sessions.lock().unwrap();
+        let mut infos: Vec<SessionInfo> = sessions.values().map(SessionInfo::from).collect();
+        infos.sort_by_key(|info| std::cmp::Reverse(info.updated_at));
+        Ok(infos)
+    }
+}
+
+#[test]
+fn test_custom_store_gets_default_search() {
+    exercise(&MemoryStore::default());
+}
+
+#[cfg(feature = "sqlite")]
+#[test]
+fn test_sqlite_store() {
+    let dir = temp_dir("sqlite");
+    let store = chatti::store::SqliteStore::open(&dir.join("sessions.db")).unwrap();
+    assert!(store.has_full_text());
+    exercise(&store);
+    drop(store);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_session_store_is_configurable() {
+    let config: Config = toml::from_str(
+        "api_endpoint = \"http://localhost:11434/api/chat\"\nmodel = \"llama3\"\nstream = true\ntemperature = 0.7\nsession_store = \"sqlite\"\n",
+    )
+    .unwrap();
+    assert_eq!(config.session_store, Backend::Sqlite);
+    #[cfg(not(feature = "sqlite"))]
+    assert!(matches!(
+        chatti::store::open(Backend::Sqlite),
+        Err(FSError::Unsupported(_))
+    ));
+}
@@ -0,0 +1,185 @@
+// tests/stream_tests.rs
+use chatti::provider::Provider;
+use chatti::stream;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+/// Serves a response whose body is written in separate pieces.
+async fn serve_in_pieces(pieces: Vec<Vec<u8>>) -> std::net::SocketAddr {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = vec![0; 4096];
+        let _ = socket.read(&mut request).await.unwrap();
+        socket
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        for piece in pieces {
+            socket.write_all(&piece).await.unwrap();
+            socket.flush().await.unwrap();
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    });
+    address
+}
+
+#[tokio::test]
+async fn test_forward_keeps_characters_split_between_chunks() {
+    let body = "{\"message\":{\"content\":\"日本語\"}}\n{\"message\":{\"content\":\"🦀 ok\"},\"done\":true}\n";
+    let bytes = body.as_bytes();
+    // Split inside "本" and inside the crab emoji.
+    let first = body.find('本').unwrap() + 1;
+    let second = body.find('🦀').unwrap() + 2;
+    let pieces = vec![
+        bytes[..first].to_vec(),
+        bytes[first..second].to_vec(),
+        bytes[second..].to_vec(),
+    ];
+    let address = serve_in_pieces(pieces).await;
+
+    let response = reqwest::get(format!("http://{address}/api/chat"))
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::channel(10);
+    stream::forward(response, Provider::Ollama, &tx)
+        .await
+        .unwrap();
+    drop(tx);
+
+    let mut content = Vec::new();
+    while let Some(piece) = rx.recv().await {
+        content.push(piece.unwrap());
+    }
+    assert_eq!(content, vec!["日本語", "🦀 ok"]);
+}
+
+#[tokio::test]
+async fn test_forward_reads_llamacpp_events() {
+    let pieces = vec![
+        b"data: {\"content\":\"Hel\",\"stop\":false}\n\n".to_vec(),
+        b"data: {\"content\":\"lo\",\"stop\":false}\n".to_vec(),
+        b"\ndata: {\"content\":\"\",\"stop\":true}\n\n".to_vec(),
+    ];
+    let address = serve_in_pieces(pieces).await;
+
+    let response = reqwest::get(format!("http://{address}/completion"))
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::channel(10);
+    stream::forward(response, Provider::LlamaCpp, &tx)
+        .await
+        .unwrap();
+    drop(tx);
+
+    let mut content = String::new();
+    while let Some(piece) = rx.recv().await {
+        content.push_str(&piece.unwrap());
+    }
+    assert_eq!(content, "Hello");
+}
+
+#[tokio::test]
+async fn test_forward_reads_openai_compatible_events() {
+    let pieces = vec![
+        b"data: {\"choices\":[{\"delta\":{\"role\":\"assistant\"},\"finish_reason\":\"\"}]}\n\n"
+            .to_vec(),
+        b"data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"},\"finish_reason\":null}]}\n\n"
+            .to_vec(),
+        b"data: {\"choices\":[{\"delta\":{\"content\":\"!\"},\"finish_reason\":\"eos\"}]}\n\n"
+            .to_vec(),
+        b"data: [DONE]\n\n".to_vec(),
+    ];
+    let address = serve_in_pieces(pieces).await;
+
+    let response = reqwest::get(format!("http://{address}/v1/chat/completions"))
+        .await
+        .unwrap();
+    let (tx, mut rx) = mpsc::channel(10);
+    stream::forward(response, Provider::LmStudio, &tx)
+        .await
+        .unwrap();
+    drop(tx);
+
+    let mut content = String::new();
+    while let Some(piece) = rx.recv().await {
+        content.push_str(&piece.unwrap());
+    }
+    assert_eq!(content, "Hi!");
+}
+
+#[tokio::test]
+async fn test_forward_reads_usage_after_the_finish_reason() {
+    let pieces = vec![
+        b"data: {\"id\":\"chatcmpl-1\",\"choices\":[{\"delta\":{\"content\":\"Hi\"},\"finish_reason\":null}]}\n\n"
+            .to_vec(),
+        b"data: {\"id\":\"chatcmpl-1\",\"choices\":[{\"delta\":{},\"finish_reason\":\"stop\"}]}\n\n"
+            .to_vec(),
+        b"data: {\"id\":\"chatcmpl-1\",\"choices\":[],\"usage\":{\"prompt_tokens\":12,\"completion_tokens\":3}}\n\n"
+            .to_vec(),
+        b"data: [DONE]\n\n".to_vec(),
+    ];
+    let address = serve_in_pieces(pieces).await;
+
+    let response = reqwest::get(format!("http://{address}/v1/chat/completions"))
+        .await
+        .unwrap();
+    let (tx, _rx) = mpsc::channel(10);
+    let metadata = stream::forward(response, Provider::OpenAi, &tx)
+        .await
+        .unwrap();
+    assert_eq!(metadata.finish_reason.as_deref(), Some("stop"));
+    assert_eq!(metadata.prompt_tokens, Some(12));
+    assert_eq!(metadata.completion_tokens, Some(3));
+}
+
+/// Collects what a subscriber writes, to read the traced fields back.
+#[derive(Clone, Default)]
+struct Captured(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl std::io::Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn test_forward_traces_chunks_and_malformed_parts() {
+    let address = serve_in_pieces(vec![
+        b"{\"message\":{\"content\":\"Hi\"}}\n".to_vec(),
+        b"not json\n{\"message\":{\"content\":\"!\"},\"done\":true}\n".to_vec(),
+    ])
+    .await;
+    let captured = Captured::default();
+    let writer = captured.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(format!("{}=trace", chatti::logging::API_TARGET))
+        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    let _default = tracing::subscriber::set_default(subscriber);
+
+    let response = reqwest::get(format!("http://{address}/api/chat"))
+        .await
+        .unwrap();
+    let (tx, _rx) = mpsc::channel(10);
+    stream::forward(response, Provider::Ollama, &tx)
+        .await
+        .unwrap();
+
+    let log = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+    assert!(log.contains("received a chunk"), "{log}");
+    assert!(log.contains("skipping a malformed part"), "{log}");
+    assert!(
+        log.contains("chunks=2 bytes=78 parts=3 malformed=1"),
+        "{log}"
+    );
+}
@@ -0,0 +1,156 @@
+// tests/structured_tests.rs
+use chatti::commands::Command;
+use chatti::config::{Config, TemplateConfig};
+use chatti::message::Message;
+use chatti::provider::Provider;
+use chatti::structured::{check, JsonOutput};
+use chatti::ui::state::State;
+use serde_json::json;
+
+fn config(extra: &str) -> Config {
+    toml::from_str(&format!(
+        "api_endpoint = \"http://localhost:11434/api/chat\"\nmodel = \"llama3\"\nstream = true\ntemperature = 0.7\n{extra}"
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_json_command_parses() {
+    assert_eq!(Command::parse("/json"), Some(Command::Json));
+}
+
+#[test]
+fn test_requests_ask_for_json() {
+    let messages = [Message::new("user", "List three colors")];
+    let schema = json!({"type": "array", "items": {"type": "string"}});
+    let mut config = config("");
+
+    config.json_output = Some(JsonOutput::default());
+    let body = Provider::Ollama.request_body(&config, &messages);
+    assert_eq!(body["format"], "json");
+    let body = Provider::OpenAi.request_body(&config, &messages);
+    assert_eq!(body["response_format"], json!({"type": "json_object"}));
+    let body = Provider::LlamaCpp.request_body(&config, &messages);
+    assert_eq!(body["json_schema"], json!({}));
+
+    config.json_output = Some(JsonOutput {
+        schema: Some(schema.clone()),
+    });
+    let body = Provider::Ollama.request_body(&config, &messages);
+    assert_eq!(body["format"], schema);
+    let body = Provider::LmStudio.request_body(&config, &messages);
+    assert_eq!(body["response_format"]["type"], "json_schema");
+    assert_eq!(body["response_format"]["json_schema"]["schema"], schema);
+    assert_eq!(body["response_format"]["json_schema"]["strict"], true);
+    let body = Provider::LlamaCpp.request_body(&config, &messages);
+    assert_eq!(body["json_schema"], schema);
+
+    config.json_output = Some(JsonOutput {
+        schema: Some(json!({"type": "object", "properties": {"name": {}}})),
+    });
+    let body = Provider::OpenAi.request_body(&config, &messages);
+    assert_eq!(
+        body["response_format"]["json_schema"]["strict"], false,
+        "optional properties cannot be enforced strictly"
+    );
+
+    config.json_output = None;
+    let body = Provider::Ollama.request_body(&config, &messages);
+    assert!(body.get("format").is_none());
+}
+
+#[test]
+fn test_json_output_and_template_schema_are_configurable() {
+    let config = config(
+        "[json_output.schema]\ntype = \"object\"\n\n[templates.extract.schema]\ntype = \"object\"\nrequired = [\"name\"]\n\n[templates.extract.schema.properties.name]\ntype = \"string\"\n",
+    );
+    assert_eq!(
+        config.json_output,
+        Some(JsonOutput {
+            schema: Some(json!({"type": "object"}))
+        })
+    );
+    let template: &TemplateConfig = &config.templates["extract"];
+    assert_eq!(
+        template.schema,
+        Some(json!({
+            "type": "object",
+            "required": ["name"],
+            "properties": {"name": {"type": "string"}},
+        }))
+    );
+}
+
+#[test]
+fn test_check_reports_nested_problems() {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "status": {"enum": ["open", "closed"]},
+            "tags": {
+                "type": "array",
+                "maxItems": 2,
+                "items": {"type": "string", "minLength": 2},
+            },
+            "owner": {
+                "type": "object",
+                "properties": {"age": {"type": "integer", "minimum": 0}},
+                "additionalProperties": false,
+            },
+        },
+    });
+    let answer = r#"{"status": "done", "tags": ["a", 3, "ok"], "owner": {"age": -1, "nick": "x"}}"#;
+    let checked = check(answer, Some(&schema)).unwrap();
+    assert_eq!(
+        checked.problems,
+        vec![
+            "`$.owner.age`: -1 is less than 0",
+            "`$.owner`: unexpected property \"nick\"",
+            "`$.status`: \"done\" is not one of \"open\", \"closed\"",
+            "`$.tags`: 3 item(s), more than 2",
+            "`$.tags[0]`: 1 character(s), fewer than 2",
+            "`$.tags[1]`: expected string, found integer",
+        ]
+    );
+}
+
+#[test]
+fn test_check_accepts_fenced_and_matching_answers() {
+    let schema = json!({"type": ["number", "null"]});
+    let checked = check("```json\n2.5\n```", Some(&schema)).unwrap();
+    assert_eq!(checked.content, "```json\n2.5\n```");
+    assert!(checked.problems.is_empty());
+    assert!(check("null", Some(&schema)).unwrap().problems.is_empty());
+    assert!(check("4", Some(&json!({"type": "number"})))
+        .unwrap()
+        .problems
+        .is_empty());
+}
+
+#[test]
+fn test_check_json_answer_formats_and_annotates() {
+    let mut state = State::new();
+    state.messages.push(Message::new("assistant", "{\"a\":1}"));
+    state.check_json_answer();
+    assert_eq!(state.messages.len(), 1, "JSON mode is off");
+
+    state.json_output = Some(JsonOutput {
+        schema: Some(json!({"required": ["b"]})),
+    });
+    state.check_json_answer();
+    assert_eq!(state.messages[0].content, "{\"a\":1}", "the answer is kept");
+    assert!(state.messages[0].json);
+    assert_eq!(
+        state.messages[1].content,
+        "The answer does not match the schema:\n- `$`: missing required property \"b\""
+    );
+
+    state
+        .messages
+        .push(Message::new("assistant", "Sure! Here it is"));
+    state.check_json_answer();
+    assert!(state.messages[3]
+        .content
+        .starts_with("The answer is not valid JSON:"));
+    assert!(!state.messages[2].json);
+}
@@ -0,0 +1,86 @@
+// tests/switcher_tests.rs
+use chatti::commands::Command;
+use chatti::store::SessionInfo;
+use chatti::ui::input_handler::InputHandler;
+use chatti::ui::state::{InputMode, State};
+use chatti::ui::switcher::{date, Switcher};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+fn sessions() -> Vec<SessionInfo> {
+    [
+        ("c", "Borrow checker questions", 1_709_251_200),
+        ("b", "Leap day plans", 1_709_164_800),
+        ("a", "Trip to Lisbon", 1_672_531_200),
+    ]
+    .into_iter()
+    .map(|(id, title, updated_at)| SessionInfo {
+        id: id.to_string(),
+        title: title.to_string(),
+        updated_at,
+        messages: 4,
+    })
+    .collect()
+}
+
+#[test]
+fn test_date() {
+    assert_eq!(date(1_709_164_800), "2024-02-29");
+    assert_eq!(date(1_672_531_199), "2022-12-31");
+    assert_eq!(date(951_782_400), "2000-02-29");
+}
+
+#[test]
+fn test_switcher_filters_by_title_and_date() {
+    let mut switcher = Switcher::new(sessions());
+    assert_eq!(switcher.total(), 3);
+    assert_eq!(switcher.chosen().unwrap().id, "c");
+
+    for c in "2023".chars() {
+        switcher.push(c);
+    }
+    let ids: Vec<&str> = switcher.matches.iter().map(|s| s.id.as_str()).collect();
+    assert_eq!(ids, vec!["a"]);
+
+    for _ in 0..4 {
+        switcher.pop();
+    }
+    assert_eq!(switcher.matches.len(), 3);
+    switcher.select_previous();
+    assert_eq!(switcher.chosen().unwrap().id, "a");
+    switcher.select_next();
+    assert_eq!(switcher.chosen().unwrap().id, "c");
+}
+
+#[test]
+fn test_switcher_keys() {
+    let input_handler = InputHandler::new();
+    let ctrl = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL);
+    assert_eq!(
+        input_handler.handle_session_shortcut(ctrl('n')),
+        Some(String::from("/new"))
+    );
+    assert_eq!(
+        input_handler.handle_session_shortcut(ctrl('o')),
+        Some(String::from("/sessions"))
+    );
+    assert_eq!(
+        input_handler.handle_session_shortcut(KeyEvent::from(KeyCode::Char('o'))),
+        None
+    );
+    assert_eq!(Command::parse("/sessions"), Some(Command::Sessions));
+
+    let mut ui_state = State::new();
+    ui_state.open_switcher(Switcher::new(sessions()));
+    assert_eq!(ui_state.input_mode, InputMode::Switcher);
+    input_handler.handle_switcher_mode(&mut ui_state, KeyCode::Esc);
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+    assert!(ui_state.switch_to.is_none());
+
+    ui_state.open_switcher(Switcher::new(sessions()));
+    for c in "leap".chars() {
+        input_handler.handle_switcher_mode(&mut ui_state, KeyCode::Char(c));
+    }
+    input_handler.handle_switcher_mode(&mut ui_state, KeyCode::Enter);
+    assert!(ui_state.switcher.is_none());
+    assert_eq!(ui_state.switch_to.unwrap().id, "b");
+}
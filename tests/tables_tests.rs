@@ -0,0 +1,116 @@
+// tests/tables_tests.rs
+use chatti::attachments::Attachment;
+use chatti::config::AttachmentsConfig;
+use chatti::tables::{parse, Sample, Sampling, Strategy};
+use std::fs;
+
+#[test]
+fn test_parse_tables() {
+    let csv = "id,name,notes\r\n1,\"Smith, Ann\",\"two\nlines\"\n\n2,Bob,\n";
+    assert_eq!(
+        parse(csv, ','),
+        vec![
+            vec!["id", "name", "notes"],
+            vec!["1", "Smith, Ann", "two\nlines"],
+            vec!["2", "Bob", ""],
+        ]
+    );
+    assert_eq!(
+        parse("a\tb\n1\t2", '\t'),
+        vec![vec!["a", "b"], vec!["1", "2"]]
+    );
+    assert!(parse("", ',').is_empty());
+}
+
+#[test]
+fn test_sample_rows_and_columns() {
+    let mut text = String::from("id,city,price\n");
+    for n in 1..=100 {
+        let price = if n % 10 == 0 {
+            String::new()
+        } else {
+            format!("{}.5", n)
+        };
+        text.push_str(&format!("{n},{},{price}\n", ["Oslo", "Lima"][n % 2]));
+    }
+
+    let head = Sampling {
+        rows: 4,
+        strategy: Strategy::Head,
+    };
+    let sample = Sample::of(&text, ',', head).unwrap();
+    let numbers: Vec<usize> = sample.rows.iter().map(|(n, _)| *n).collect();
+    assert_eq!(numbers, vec![1, 2, 3, 4]);
+    assert_eq!(sample.total_rows, 100);
+    assert!(!sample.is_complete());
+    assert_eq!(
+        sample.columns,
+        vec![
+            "id: numbers from 1 to 100, mean 50.50",
+            "city: text, 2 distinct value(s)",
+            "price: numbers from 1.5 to 99.5, mean 50.50, 10 empty",
+        ]
+    );
+
+    let even = Sampling {
+        rows: 4,
+        strategy: Strategy::Even,
+    };
+    let sample = Sample::of(&text, ',', even).unwrap();
+    let numbers: Vec<usize> = sample.rows.iter().map(|(n, _)| *n).collect();
+    assert_eq!(numbers, vec![1, 34, 67, 100]);
+
+    let text = sample.text();
+    assert!(text.starts_with(
+        "CSV sample: the header and 4 of 100 rows, spread evenly from the first to the last."
+    ));
+    assert!(text.contains("\nrow,id,city,price\n1,1,Lima,1.5\n34,34,Oslo,34.5\n"));
+    assert!(text.contains("\nColumns, over all 100 row(s):\n- id: "));
+
+    // Fields holding the delimiter are quoted again.
+    let sample = Sample::of("a,b\n\"x,y\",\"say \"\"hi\"\"\"\n", ',', even).unwrap();
+    assert!(sample.is_complete());
+    assert!(sample.text().contains("\n1,\"x,y\",\"say \"\"hi\"\"\"\n"));
+
+    // The configuration sets the sampling.
+    let config: AttachmentsConfig = toml::from_str("sample_rows = 5\nsampling = \"head\"").unwrap();
+    assert_eq!(config.sampling(), Sampling { rows: 5, ..head });
+    assert_eq!(AttachmentsConfig::default().sampling(), Sampling::default());
+}
+
+#[test]
+fn test_attach_tables() {
+    let dir = std::env::temp_dir().join(format!("chatti-tables-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let large = dir.join("readings.tsv");
+    let text: String = std::iter::once("sensor\tvalue\n".to_string())
+        .chain((1..=500).map(|n| format!("s{}\t{n}\n", n % 7)))
+        .collect();
+    fs::write(&large, &text).unwrap();
+    let attachment = Attachment::load(&large, Sampling::default()).unwrap();
+    let sample = attachment.table.as_ref().unwrap();
+    assert_eq!(sample.kind(), "TSV");
+    assert_eq!(sample.rows.len(), 20);
+    assert!(!attachment.truncated);
+    assert!(attachment
+        .text
+        .starts_with("TSV sample: the header and 20 of 500 rows"));
+    assert!(attachment.text.contains("\nrow\tsensor\tvalue\n1\ts1\t1\n"));
+    assert!(attachment
+        .text
+        .contains("- value: numbers from 1 to 500, mean 250.50"));
+    assert!(attachment.full_tokens > attachment.tokens());
+    assert!(attachment
+        .summary()
+        .contains("the header and 20 of 500 rows, spread evenly from the first to the last would be attached with statistics of each column"));
+
+    // A table within the sample is attached as it is.
+    let small = dir.join("small.csv");
+    fs::write(&small, "a,b\n1,2\n").unwrap();
+    let attachment = Attachment::load(&small, Sampling::default()).unwrap();
+    assert!(attachment.table.unwrap().is_complete());
+    assert_eq!(attachment.text, "a,b\n1,2\n");
+
+    fs::remove_dir_all(dir).unwrap();
+}
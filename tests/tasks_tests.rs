@@ -0,0 +1,70 @@
+// tests/tasks_tests.rs
+use chatti::ui::input_handler::InputHandler;
+use chatti::ui::state::State;
+use chatti::ui::tasks::Tasks;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[test]
+fn test_task_lifecycle() {
+    let mut tasks = Tasks::default();
+    assert!(tasks.is_empty());
+    let pull = tasks.start("Pulling llama3");
+    let render = tasks.start("Rendering Mermaid diagram 1");
+    assert_eq!(tasks.len(), 2);
+
+    tasks.update(pull, "pulling manifest", None);
+    let first = tasks.iter().next().unwrap();
+    assert!(first
+        .describe(true)
+        .starts_with("Pulling llama3: pulling manifest · "));
+    tasks.update(pull, "pulling abc", Some(0.5));
+    assert!(tasks
+        .iter()
+        .next()
+        .unwrap()
+        .describe(true)
+        .contains("██████████░░░░░░░░░░]  50%"));
+
+    assert!(!tasks.is_cancelled(render));
+    assert!(tasks.cancel(1));
+    assert!(!tasks.cancel(2));
+    assert!(tasks.is_cancelled(render));
+    assert!(!tasks.is_cancelled(pull));
+
+    tasks.finish(render);
+    assert_eq!(tasks.len(), 1);
+    tasks.cancel_all();
+    assert!(tasks.is_cancelled(pull));
+    tasks.finish(pull);
+    // Ended tasks count as cancelled, so their runners stop waiting.
+    assert!(tasks.is_cancelled(pull));
+    assert!(tasks.is_empty());
+}
+
+#[test]
+fn test_number_keys_cancel_tasks() {
+    let input_handler = InputHandler::new();
+    let mut ui_state = State::new();
+    let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+
+    // Without tasks, numbers are typed as a follow-up.
+    input_handler.handle_waiting_mode(&mut ui_state, key('2'));
+    assert_eq!(ui_state.input, "2");
+
+    ui_state.input.clear();
+    let first = ui_state.tasks.start("Rendering Graphviz diagram 1");
+    let second = ui_state.tasks.start("Rendering Graphviz diagram 2");
+    assert_eq!(
+        input_handler.handle_waiting_mode(&mut ui_state, key('2')),
+        None
+    );
+    assert!(ui_state.input.is_empty());
+    assert!(ui_state.tasks.is_cancelled(second));
+    assert!(!ui_state.tasks.is_cancelled(first));
+
+    // While a follow-up is being typed, numbers are part of it.
+    ui_state.input = String::from("Try ");
+    input_handler.handle_waiting_mode(&mut ui_state, key('1'));
+    assert_eq!(ui_state.input, "Try 1");
+    assert!(!ui_state.tasks.is_cancelled(first));
+}
@@ -0,0 +1,116 @@
+// tests/telemetry_tests.rs
+use chatti::config::{Config, TelemetryConfig};
+use chatti::message::Metadata;
+use chatti::telemetry::{self, signal_endpoint, Telemetry};
+use std::time::Duration;
+
+#[test]
+fn test_telemetry_config() {
+    let config: Config = toml::from_str(
+        "model = \"llama3\"\nstream = true\ntemperature = 0.5\n\n[telemetry]\nenabled = true\nendpoint = \"https://otlp.example.com/otlp\"\n\n[telemetry.headers]\nAuthorization = \"Basic abc\"\n",
+    )
+    .unwrap();
+    assert!(config.telemetry.enabled);
+    assert_eq!(config.telemetry.service_name, "chatti");
+    assert_eq!(config.telemetry.export_interval_secs, 60);
+    assert_eq!(config.telemetry.headers["Authorization"], "Basic abc");
+    assert_eq!(
+        signal_endpoint(&config.telemetry.endpoint, "metrics"),
+        "https://otlp.example.com/otlp/v1/metrics"
+    );
+}
+
+#[test]
+fn test_telemetry_is_off_by_default() {
+    let config = TelemetryConfig::default();
+    assert!(!config.enabled);
+    assert!(Telemetry::start(&config).unwrap().is_none());
+    // Recording without an exporter does nothing.
+    telemetry::record_request(
+        "llama3",
+        "ollama",
+        200,
+        Duration::from_secs(1),
+        &Metadata::default(),
+    );
+}
+
+#[cfg(not(feature = "telemetry"))]
+#[test]
+fn test_telemetry_needs_the_feature() {
+    let config = TelemetryConfig {
+        enabled: true,
+        ..TelemetryConfig::default()
+    };
+    assert!(matches!(
+        Telemetry::start(&config),
+        Err(telemetry::TelemetryError::Unsupported)
+    ));
+}
+
+#[cfg(feature = "telemetry")]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_telemetry_starts_exporters() {
+    let config = TelemetryConfig {
+        enabled: true,
+        // Nothing listens here; exports fail quietly in the background.
+        endpoint: String::from("http://127.0.0.1:9"),
+        ..TelemetryConfig::default()
+    };
+    let telemetry = Telemetry::start(&config).unwrap();
+    assert!(telemetry.is_some());
+}
+
+/// Collects the names and fields of the spans and events a layer sees.
+#[derive(Clone, Default)]
+struct Seen(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+impl tracing::field::Visit for Seen {
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("{}={value:?}", field.name()));
+    }
+}
+
+impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for Seen {
+    fn on_new_span(
+        &self,
+        attrs: &tracing::span::Attributes<'_>,
+        _: &tracing::span::Id,
+        _: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(attrs.metadata().name().to_string());
+        attrs.record(&mut self.clone());
+    }
+
+    fn on_event(&self, event: &tracing::Event<'_>, _: tracing_subscriber::layer::Context<'_, S>) {
+        event.record(&mut self.clone());
+    }
+}
+
+#[test]
+fn test_only_request_spans_are_exported() {
+    use tracing_subscriber::prelude::*;
+
+    let seen = Seen::default();
+    let subscriber = tracing_subscriber::registry().with(seen.clone().with_filter(
+        tracing_subscriber::filter::filter_fn(telemetry::is_exported),
+    ));
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!(target: "chatti::api", "process_message", messages = 2);
+        let _entered = span.enter();
+        let messages = ["my key is sk-abcdefghijklmnopqrstuvwxyz123456"];
+        tracing::info!(?messages, "sending prompt");
+        tracing::info!(target: "chatti::api", ?messages, "sending prompt");
+        tracing::info_span!("unrelated", secret = messages[0]).in_scope(|| {});
+    });
+
+    let seen = seen.0.lock().unwrap();
+    assert_eq!(*seen, ["process_message", "messages=2"]);
+    assert!(!seen.iter().any(|line| line.contains("sk-")));
+}
@@ -0,0 +1,85 @@
+// tests/templates_tests.rs
+use chatti::commands::Command;
+use chatti::config::TemplateConfig;
+use chatti::message::Message;
+use chatti::templates::{apply_to, start, start_cancellable, Started, TemplateError};
+use std::fs;
+use std::sync::atomic::AtomicBool;
+
+#[test]
+fn test_template_attaches_matching_files() {
+    let dir = std::env::temp_dir().join(format!("chatti-template-{}", std::process::id()));
+    fs::create_dir_all(dir.join("src")).unwrap();
+    fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+    fs::write(dir.join("notes.txt"), "not attached").unwrap();
+
+    let template = TemplateConfig {
+        system: Some("Review this code.".to_string()),
+        messages: vec![Message::new("assistant", "Ready to review.")],
+        attach: vec!["src/*.rs".to_string()],
+        command: None,
+        schema: None,
+    };
+    let started = start(&template, &dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let prompt = started.system_prompt.unwrap();
+    assert!(prompt.starts_with("Review this code."));
+    assert!(prompt.contains("File `src/main.rs`"));
+    assert!(prompt.contains("fn main() {}"));
+    assert!(!prompt.contains("not attached"));
+    assert_eq!(started.files, 1);
+    assert_eq!(started.messages, template.messages);
+}
+
+#[test]
+fn test_template_command_failure() {
+    let template = TemplateConfig {
+        command: Some("echo oops >&2; exit 3".to_string()),
+        ..TemplateConfig::default()
+    };
+    match start(&template, std::path::Path::new(".")) {
+        Err(TemplateError::Command { stderr, .. }) => assert_eq!(stderr, "oops"),
+        other => panic!("expected a command error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_template_command_cancelled() {
+    let template = TemplateConfig {
+        command: Some("sleep 5".to_string()),
+        ..TemplateConfig::default()
+    };
+    let cancelled = AtomicBool::new(true);
+    match start_cancellable(&template, std::path::Path::new("."), &cancelled) {
+        Err(TemplateError::Cancelled(command)) => assert_eq!(command, "sleep 5"),
+        other => panic!("expected a cancelled command, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_parse_new_command() {
+    assert_eq!(Command::parse("/new"), Some(Command::New(None)));
+    assert_eq!(
+        Command::parse("/new code-review"),
+        Some(Command::New(Some("code-review".to_string())))
+    );
+}
+
+#[test]
+fn test_apply_to_sends_input_as_user_message() {
+    let started = Started {
+        system_prompt: None,
+        messages: vec![Message::new("assistant", "Send me the text.")],
+        files: 0,
+        schema: None,
+    };
+    let request = apply_to(started, "teh text\n");
+    assert_eq!(
+        request,
+        vec![
+            Message::new("assistant", "Send me the text."),
+            Message::new("user", "teh text"),
+        ]
+    );
+}
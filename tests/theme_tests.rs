@@ -0,0 +1,83 @@
+// tests/theme_tests.rs
+use chatti::config::ThemeConfig;
+use chatti::message::{Generation, Message};
+use chatti::ui::layout::LayoutCache;
+use chatti::ui::renderer::Renderer;
+use chatti::ui::theme::Theme;
+use ratatui::style::Color;
+
+#[test]
+fn test_theme_prefixes_and_fallback_colors() {
+    let config = ThemeConfig {
+        user_prefix: "me ".to_string(),
+        assistant_prefix: "[{model}] ".to_string(),
+        user_color: "not-a-color".to_string(),
+        ..ThemeConfig::default()
+    };
+    let theme = Theme::from_config(&config, "mistral");
+
+    assert_eq!(theme.prefix(&Message::new("user", "")), "me ");
+    assert_eq!(theme.user.style.fg, Some(Color::Blue));
+    assert_eq!(theme.prefix(&Message::new("assistant", "")), "[mistral] ");
+    assert_eq!(theme.assistant.style.fg, Some(Color::Green));
+}
+
+#[test]
+fn test_message_lines_use_theme_prefix() {
+    let config = ThemeConfig {
+        user_prefix: "★ ".to_string(),
+        user_color: "magenta".to_string(),
+        ..ThemeConfig::default()
+    };
+    let theme = Theme::from_config(&config, "");
+    let lines = Renderer::message_lines(&Message::new("user", "hello world again"), 8, &theme);
+
+    assert_eq!(lines[0].spans[0].content, "★ ");
+    assert_eq!(lines[0].spans[0].style.fg, Some(Color::Magenta));
+    assert_eq!(
+        lines[1].spans[0].content, "  ",
+        "continuation lines align with the prefix"
+    );
+}
+
+#[test]
+fn test_layout_is_redone_when_the_theme_changes() {
+    let theme = |prefix: &str| {
+        let config = ThemeConfig {
+            assistant_prefix: prefix.to_string(),
+            ..ThemeConfig::default()
+        };
+        Theme::from_config(&config, "")
+    };
+    let messages = vec![Message::new("assistant", "Hi!")];
+    let mut layout = LayoutCache::default();
+
+    layout.update(&messages, 80, &theme("old> "));
+    assert_eq!(layout.lines(0)[0].spans[0].content, "old> ");
+
+    layout.update(&messages, 80, &theme("new> "));
+    assert_eq!(layout.lines(0)[0].spans[0].content, "new> ");
+}
+
+#[test]
+fn test_answers_keep_the_model_that_wrote_them() {
+    let config = ThemeConfig {
+        assistant_prefix: "{model}> ".to_string(),
+        ..ThemeConfig::default()
+    };
+    let mut earlier = Message::new("assistant", "Hi!");
+    earlier.generation = Some(Generation {
+        model: "llama3".to_string(),
+        temperature: 0.7,
+        top_p: None,
+        max_tokens: None,
+    });
+    let messages = vec![earlier, Message::new("assistant", "Hello!")];
+
+    // Switching models with `/model` rebuilds the theme.
+    let theme = Theme::from_config(&config, "mistral");
+    let mut layout = LayoutCache::default();
+    layout.update(&messages, 80, &theme);
+    assert_eq!(layout.lines(0)[0].spans[0].content, "llama3> ");
+    assert_eq!(layout.lines(1)[0].spans[0].content, "mistral> ");
+}
@@ -0,0 +1,34 @@
+// tests/tokens_tests.rs
+use chatti::message::Message;
+use chatti::tokens::{estimate_tokens, DraftStats};
+use chatti::ui::state::State;
+
+#[test]
+fn test_draft_stats() {
+    let stats = DraftStats::of("Explain borrow checking in Rust");
+    assert_eq!(stats.chars, 31);
+    assert_eq!(stats.words, 5);
+    assert_eq!(
+        stats.tokens,
+        estimate_tokens("Explain borrow checking in Rust")
+    );
+    assert!(stats.tokens >= 7);
+}
+
+#[test]
+fn test_prompt_tokens_include_history() {
+    let mut ui_state = State::new();
+    ui_state.input = "and now?".to_string();
+    let draft_only = ui_state.estimated_prompt_tokens();
+
+    ui_state
+        .messages
+        .push(Message::new("user", "a long question ".repeat(50)));
+    ui_state
+        .messages
+        .push(Message::new("system", "ignored notice ".repeat(50)));
+    assert_eq!(
+        ui_state.estimated_prompt_tokens(),
+        draft_only + estimate_tokens(&"a long question ".repeat(50))
+    );
+}
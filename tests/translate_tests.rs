@@ -0,0 +1,87 @@
+// tests/translate_tests.rs
+use chatti::commands::Command;
+use chatti::message::Message;
+use chatti::ui::state::{InputMode, State};
+
+#[test]
+fn test_parse_translate_command() {
+    assert_eq!(
+        Command::parse("/translate Brazilian Portuguese"),
+        Some(Command::Translate("Brazilian Portuguese".to_string()))
+    );
+    assert!(matches!(
+        Command::parse("/translate"),
+        Some(Command::Invalid(_))
+    ));
+}
+
+#[test]
+fn test_translation_goes_below_the_selected_answer() {
+    let mut ui_state = State::new();
+    ui_state.messages = vec![
+        Message::new("user", "Say hello"),
+        Message::new("assistant", "Hello"),
+        Message::new("user", "Say goodbye"),
+        Message::new("assistant", "Goodbye"),
+    ];
+    ui_state.list_state.select(Some(1));
+
+    let (request, slot) = ui_state.start_translation("French").unwrap();
+    assert_eq!(slot, 2);
+    assert_eq!(request.len(), 1);
+    assert!(request[0].content.contains("into French"));
+    assert!(request[0].content.ends_with("\n\nHello"));
+    assert_eq!(ui_state.input_mode, InputMode::Waiting);
+    ui_state.update_retry(slot, "Bonjour");
+    ui_state.finish_translation(slot, None);
+    assert_eq!(ui_state.messages[2].translation.as_deref(), Some("French"));
+
+    ui_state.list_state.select(Some(1));
+    let (_, second) = ui_state.start_translation("German").unwrap();
+    assert_eq!(second, 3, "a second translation goes below the first");
+    ui_state.finish_translation(second, Some("timed out".to_string()));
+
+    let contents: Vec<_> = ui_state
+        .messages
+        .iter()
+        .map(|m| m.content.as_str())
+        .collect();
+    assert_eq!(
+        contents,
+        vec![
+            "Say hello",
+            "Hello",
+            "Bonjour",
+            "Say goodbye",
+            "Goodbye",
+            "Translation failed: timed out"
+        ]
+    );
+}
+
+#[test]
+fn test_translation_defaults_to_the_latest_answer() {
+    let mut ui_state = State::new();
+    assert!(ui_state.start_translation("French").is_none());
+
+    ui_state.messages = vec![
+        Message::new("user", "Say hello"),
+        Message::new("assistant", "Hello"),
+    ];
+    ui_state.list_state.select(Some(0));
+    let (request, slot) = ui_state.start_translation("French").unwrap();
+    assert_eq!(slot, 2);
+    assert!(request[0].content.ends_with("\n\nHello"));
+}
+
+#[test]
+fn test_answer_language_extends_the_system_prompt() {
+    let mut ui_state = State::new();
+    ui_state.answer_language = Some("German".to_string());
+    ui_state.submit_prompt("Hi".to_string());
+
+    let sent = ui_state.request_messages();
+    assert_eq!(sent[0].role, "system");
+    assert!(sent[0].content.contains("Always answer in German"));
+    assert!(ui_state.messages.iter().all(|m| m.role != "system"));
+}
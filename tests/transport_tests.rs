@@ -0,0 +1,124 @@
+// tests/transport_tests.rs
+use chatti::config::TransportConfig;
+use chatti::error::Application;
+use chatti::provider::Provider;
+use chatti::transport::{send_websocket, Transport};
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::Message as Frame;
+
+#[test]
+fn test_transport_per_profile() {
+    let config: TransportConfig =
+        toml::from_str("protocol = \"http\"\n\n[profiles]\ngateway = \"websocket\"\n").unwrap();
+    assert_eq!(config.for_profile("gateway"), Transport::WebSocket);
+    assert_eq!(config.for_profile("default"), Transport::Http);
+    assert_eq!(
+        TransportConfig::default().for_profile("gateway"),
+        Transport::Http
+    );
+}
+
+// The handshake callback's error type is set by tungstenite.
+#[allow(clippy::result_large_err)]
+#[tokio::test]
+async fn test_websocket_streams_the_answer() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (received, request) = oneshot::channel();
+    tokio::spawn(async move {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut authorization = None;
+        let mut socket =
+            tokio_tungstenite::accept_hdr_async(socket, |request: &Request, response: Response| {
+                authorization = request
+                    .headers()
+                    .get("authorization")
+                    .map(|value| value.to_str().unwrap().to_string());
+                Ok(response)
+            })
+            .await
+            .unwrap();
+        let Some(Ok(Frame::Text(body))) = socket.next().await else {
+            panic!("expected the request body");
+        };
+        received.send((authorization, body)).unwrap();
+        for part in [
+            r#"{"message":{"content":"Hel"},"done":false}"#,
+            "",
+            r#"{"message":{"content":"lo"},"done":false}"#,
+            r#"{"message":{"content":""},"done":true,"done_reason":"stop","eval_count":2}"#,
+        ] {
+            socket.send(Frame::text(part)).await.unwrap();
+        }
+        // The client closes the connection once the answer is done.
+        while let Some(Ok(_)) = socket.next().await {}
+    });
+
+    let (tx, mut rx) = mpsc::channel(10);
+    let body = json!({"model": "llama3.2", "messages": [], "stream": true});
+    let metadata = send_websocket(
+        &format!("http://{address}/api/chat"),
+        Some("secret"),
+        &body,
+        Provider::Ollama,
+        &tx,
+    )
+    .await
+    .unwrap();
+    drop(tx);
+
+    let (authorization, sent) = request.await.unwrap();
+    assert_eq!(authorization.as_deref(), Some("Bearer secret"));
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&sent).unwrap(),
+        body
+    );
+    let mut content = String::new();
+    while let Some(piece) = rx.recv().await {
+        content.push_str(&piece.unwrap());
+    }
+    assert_eq!(content, "Hello");
+    assert_eq!(metadata.finish_reason.as_deref(), Some("stop"));
+    assert_eq!(metadata.completion_tokens, Some(2));
+}
+
+#[tokio::test]
+async fn test_websocket_refused_by_the_server() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut request = vec![0; 4096];
+        let _ = socket.read(&mut request).await.unwrap();
+        let body = r#"{"error": "invalid token"}"#;
+        socket
+            .write_all(
+                format!(
+                    "HTTP/1.1 401 Unauthorized\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+    });
+
+    let (tx, _rx) = mpsc::channel(10);
+    let err = send_websocket(
+        &format!("ws://{address}/chat"),
+        None,
+        &json!({}),
+        Provider::OpenAi,
+        &tx,
+    )
+    .await
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        Application::ApiError { status: 401, ref message, .. } if message == "invalid token"
+    ));
+}
@@ -1,5 +1,15 @@
 // tests/ui_state_tests.rs
-use chatti::ui::state::{InputMode, State};
+use chatti::api::EndpointStatus;
+use chatti::commands::Command;
+use chatti::error::ErrorDetails;
+use chatti::message::{Generation, Mark, MarkFilter, Message, Metadata};
+use chatti::plugins::{Action, PluginError, Reply};
+use chatti::session::Session;
+use chatti::ui::context_view::Section;
+use chatti::ui::input_handler::InputHandler;
+use chatti::ui::state::{InputMode, State, CLEAR_UNDO_WINDOW, CONTINUE_PROMPT};
+use chatti::ui::undo::Edit;
+use crossterm::event::KeyCode;
 
 #[test]
 fn test_ui_state() {
@@ -8,12 +18,8 @@ fn test_ui_state() {
     ui_state.scroll_down();
     assert_eq!(ui_state.list_state.selected(), Some(0));
 
-    ui_state
-        .messages
-        .push(("user".to_string(), "Hello".to_string()));
-    ui_state
-        .messages
-        .push(("assistant".to_string(), "Hi".to_string()));
+    ui_state.messages.push(Message::new("user", "Hello"));
+    ui_state.messages.push(Message::new("assistant", "Hi"));
 
     ui_state.scroll_down();
     assert_eq!(ui_state.list_state.selected(), Some(1));
@@ -30,6 +36,832 @@ fn test_ui_state() {
     );
 
     ui_state.add_response("Final response".to_string());
-    assert_eq!(ui_state.messages.last().unwrap().1, "Final response");
+    assert_eq!(ui_state.messages.last().unwrap().content, "Final response");
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+}
+
+#[test]
+fn test_ui_state_stop_and_cancel() {
+    let mut ui_state = State::new();
+    ui_state
+        .messages
+        .push(Message::new("user", "Tell me a story"));
+    ui_state.start_new_response();
+    ui_state.update_response("Once upon");
+
+    ui_state.stop_response();
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+    assert_eq!(ui_state.messages.len(), 2);
+    let last = ui_state.messages.last().unwrap();
+    assert_eq!(last.content, "Once upon");
+    assert!(last.stopped);
+    assert_eq!(ui_state.conversation().len(), 2);
+
+    ui_state.messages.push(Message::new("user", "Another"));
+    ui_state.start_new_response();
+    ui_state.update_response("partial");
+
+    ui_state.cancel_response();
+    assert_eq!(ui_state.messages.last().unwrap().role, "system");
+    assert!(!ui_state.messages.iter().any(|m| m.content == "partial"));
+    assert!(ui_state.progress.is_none());
+    assert_eq!(ui_state.conversation().len(), 3);
+    assert!(ui_state.messages[2].cancelled);
+
+    ui_state.messages.push(Message::new("user", "Once more"));
+    let sent: Vec<String> = ui_state
+        .request_messages()
+        .into_iter()
+        .map(|m| m.content)
+        .collect();
+    assert_eq!(sent, ["Tell me a story", "Once upon", "Once more"]);
+}
+
+#[test]
+fn test_ui_state_follow_mode() {
+    let mut ui_state = State::new();
+    assert!(ui_state.follow);
+
+    ui_state.scroll_up();
+    ui_state.scroll_down();
+    assert_eq!(ui_state.list_state.selected(), Some(0));
+    assert!(
+        ui_state.follow,
+        "scrolling an empty transcript keeps following"
+    );
+
+    ui_state.messages.push(Message::new("user", "First"));
+    ui_state.start_new_response();
+    ui_state.update_response("streamed");
+    assert_eq!(
+        ui_state.list_state.selected(),
+        Some(1),
+        "following selects new output"
+    );
+
+    ui_state.scroll_up();
+    assert!(!ui_state.follow);
+    ui_state.update_response(" more");
+    assert_eq!(
+        ui_state.list_state.selected(),
+        Some(0),
+        "streaming does not move the selection after scrolling away"
+    );
+    ui_state.add_response("done".to_string());
+    assert_eq!(ui_state.list_state.selected(), Some(0));
+
+    ui_state.scroll_down();
+    assert!(
+        ui_state.follow,
+        "scrolling back to the last message resumes following"
+    );
+    ui_state.add_system_message("notice".to_string());
+    assert_eq!(ui_state.list_state.selected(), Some(2));
+}
+
+#[test]
+fn test_ui_state_sending_resumes_following() {
+    let mut ui_state = State::new();
+    ui_state.load_messages(vec![
+        Message::new("user", "a"),
+        Message::new("assistant", "b"),
+    ]);
+    ui_state.scroll_up();
+    assert!(!ui_state.follow);
+
+    ui_state.messages.push(Message::new("user", "c"));
+    ui_state.start_new_response();
+    assert!(ui_state.follow);
+    assert_eq!(ui_state.list_state.selected(), Some(3));
+}
+
+#[test]
+fn test_ui_state_error_card() {
+    let mut ui_state = State::new();
+    ui_state.messages.push(Message::new("user", "Hi"));
+    ui_state.start_new_response();
+
+    ui_state.add_error(
+        "model not found".to_string(),
+        ErrorDetails {
+            status: Some(404),
+            ..ErrorDetails::default()
+        },
+    );
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+    assert_eq!(ui_state.messages.len(), 2);
+    let card = ui_state.messages.last().unwrap();
+    assert_eq!(card.role, "error");
+    assert!(!card.expanded);
+    assert_eq!(
+        ui_state.conversation().len(),
+        1,
+        "errors are not sent as context"
+    );
+
+    ui_state.toggle_error_details();
+    assert!(ui_state.messages[1].expanded);
+
+    ui_state.scroll_up();
+    ui_state.toggle_error_details();
+    assert!(!ui_state.messages[0].expanded, "only error cards expand");
+}
+
+#[test]
+fn test_ui_state_offline_queue() {
+    let mut ui_state = State::new();
+    assert!(
+        ui_state.is_online(),
+        "without a monitor the endpoint is assumed reachable"
+    );
+
+    let (tx, rx) = tokio::sync::watch::channel(false);
+    ui_state.connectivity = Some(rx);
+    assert!(!ui_state.is_online());
+
+    ui_state.messages.push(Message::new("user", "Hello"));
+    ui_state.input_mode = InputMode::Waiting;
+    ui_state.mark_pending();
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+    assert_eq!(ui_state.messages.len(), 1);
+    assert!(ui_state.has_pending());
+    assert_eq!(
+        ui_state.conversation().len(),
+        1,
+        "pending messages stay in the conversation"
+    );
+
+    assert!(!ui_state.should_resend());
+    tx.send(false).unwrap();
+    assert!(
+        !ui_state.should_resend(),
+        "the endpoint is still unreachable"
+    );
+    tx.send(true).unwrap();
+    assert!(ui_state.is_online());
+    assert!(ui_state.should_resend());
+    assert_eq!(ui_state.resend_pending().as_deref(), Some("Hello"));
+    assert!(!ui_state.has_pending());
+    assert_eq!(ui_state.input_mode, InputMode::Waiting);
+    assert_eq!(ui_state.messages.last().unwrap().content, "Hello");
+    assert!(
+        ui_state.progress.is_some(),
+        "the indicator is shown instead of a notice"
+    );
+    assert_eq!(ui_state.resend_pending(), None);
+}
+
+#[test]
+fn test_ui_state_endpoint_status() {
+    let mut ui_state = State::new();
+    assert_eq!(ui_state.endpoint_status(), None);
+
+    let (startup_tx, startup_rx) = tokio::sync::watch::channel(EndpointStatus::Checking);
+    ui_state.endpoint = Some(startup_rx);
+    assert_eq!(ui_state.endpoint_status(), Some(EndpointStatus::Checking));
+    startup_tx.send(EndpointStatus::Unreachable).unwrap();
+    assert_eq!(
+        ui_state.endpoint_status(),
+        Some(EndpointStatus::Unreachable)
+    );
+
+    let (health_tx, health_rx) = tokio::sync::watch::channel(true);
+    ui_state.connectivity = Some(health_rx);
+    assert_eq!(
+        ui_state.endpoint_status(),
+        Some(EndpointStatus::Ready),
+        "a later successful health check overrides the startup result"
+    );
+    health_tx.send(false).unwrap();
+    assert_eq!(
+        ui_state.endpoint_status(),
+        Some(EndpointStatus::Unreachable)
+    );
+}
+
+#[test]
+fn test_ui_state_pasted_context() {
+    let mut state = State::new();
+    state.pending_context = Some("fn main() {}".to_string());
+    state.submit_prompt("Explain this".to_string());
+
+    assert_eq!(state.pending_context, None);
+    assert_eq!(state.input_mode, InputMode::Waiting);
+    let sent = state.request_messages();
+    assert_eq!(
+        sent[0].content,
+        "Explain this\n\nContext:\n```\nfn main() {}\n```"
+    );
+    assert_eq!(sent[0].attachment, None);
+    assert_eq!(
+        state.messages[0].attachment.as_deref(),
+        Some("fn main() {}")
+    );
+}
+
+#[test]
+fn test_ui_state_recovery_keeps_partial_response() {
+    let mut state = State::new();
+    state.session_id = Some("session-1".to_string());
+    state.submit_prompt("Tell me a story".to_string());
+    state.update_response("Once upon");
+    state.input = "and then?".to_string();
+
+    let recovery = state.recovery();
+    assert_eq!(recovery.session_id.as_deref(), Some("session-1"));
+    assert_eq!(recovery.draft, "and then?");
+    assert_eq!(recovery.messages.len(), 2);
+    assert_eq!(recovery.messages[1].content, "Once upon");
+    assert!(recovery.messages[1].stopped);
+    assert!(!recovery.is_empty());
+}
+
+#[test]
+fn test_ui_state_page_navigation_uses_rendered_lines() {
+    let mut state = State::new();
+    let long = (1..=12)
+        .map(|n| format!("Paragraph {n}."))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    state.load_messages(vec![
+        Message::new("user", "Hi"),
+        Message::new("assistant", long),
+        Message::new("user", "Thanks"),
+        Message::new("assistant", "You're welcome."),
+    ]);
+    state.layout.update(&state.messages, 80, &state.theme);
+    state.messages_height = 10;
+    assert_eq!(state.layout.total_lines(), 37);
+
+    state.list_state.select(Some(0));
+    state.page_down();
+    assert_eq!(state.list_state.selected(), Some(1));
+    state.page_down();
+    assert_eq!(state.list_state.selected(), Some(2));
+    assert!(!state.follow);
+    state.page_down();
+    assert_eq!(state.list_state.selected(), Some(3));
+    assert!(state.follow);
+
+    state.page_up();
+    assert_eq!(state.list_state.selected(), Some(1));
+    assert!(!state.follow);
+}
+
+#[test]
+fn test_retry_inserts_answer_below_original() {
+    let mut ui_state = State::new();
+    ui_state.messages = vec![
+        Message::new("user", "Name a color"),
+        Message::new("assistant", "Blue"),
+        Message::new("user", "Another one"),
+        Message::new("assistant", "Red"),
+    ];
+    let generation = |model: &str| Generation {
+        model: model.to_string(),
+        temperature: 1.2,
+        top_p: None,
+        max_tokens: None,
+    };
+
+    let (history, slot) = ui_state.start_retry(1, generation("mistral")).unwrap();
+    assert_eq!(slot, 2);
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].content, "Name a color");
+    assert_eq!(ui_state.input_mode, InputMode::Waiting);
+    ui_state.update_retry(slot, "Gr");
+    ui_state.update_retry(slot, "een");
+    ui_state.finish_retry(slot, None);
+
+    let (_, second) = ui_state.start_retry(1, generation("llama3")).unwrap();
+    assert_eq!(second, 3, "a second retry goes below the first");
+    ui_state.finish_retry(second, Some("connection refused".to_string()));
+
+    let contents: Vec<_> = ui_state
+        .messages
+        .iter()
+        .map(|m| m.content.as_str())
+        .collect();
+    assert_eq!(
+        contents,
+        vec![
+            "Name a color",
+            "Blue",
+            "Green",
+            "Another one",
+            "Red",
+            "Retry failed: connection refused"
+        ]
+    );
+    assert!(ui_state.messages[1].compared);
+    assert_eq!(
+        ui_state.messages[2]
+            .generation
+            .as_ref()
+            .map(|g| g.model.as_str()),
+        Some("mistral")
+    );
+    assert!(ui_state.start_retry(0, generation("mistral")).is_none());
+    assert_eq!(
+        ui_state
+            .retry_latest(Some("phi3".to_string()))
+            .map(|r| (r.message, r.model)),
+        Some((4, "phi3".to_string()))
+    );
+
+    let sent: Vec<String> = ui_state
+        .request_messages()
+        .into_iter()
+        .map(|m| m.content)
+        .collect();
+    assert_eq!(
+        sent,
+        ["Name a color", "Green", "Another one", "Red"],
+        "only the latest attempt is sent"
+    );
+
+    let (_, third) = ui_state.start_retry(1, generation("phi3")).unwrap();
+    ui_state.update_retry(third, "Yel");
+    ui_state.cancel_retry(third);
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+    assert!(!ui_state.messages.iter().any(|m| m.content == "Yel"));
+    assert_eq!(ui_state.messages.last().unwrap().content, "Retry cancelled");
+}
+
+#[test]
+fn test_dry_run_returns_the_prompt_to_the_input() {
+    let mut state = State::new();
+    state.messages.push(Message::new("user", "Earlier"));
+    state.messages.push(Message::new("assistant", "Answer"));
+    state.submit_prompt("Try this".to_string());
+
+    state.finish_dry_run(String::from("Dry run, nothing was sent"));
+    assert_eq!(state.input_mode, InputMode::Normal);
+    assert_eq!(state.input, "Try this");
+    let contents: Vec<_> = state.messages.iter().map(|m| m.content.as_str()).collect();
+    assert_eq!(
+        contents,
+        vec!["Earlier", "Answer", "Dry run, nothing was sent"]
+    );
+}
+
+#[test]
+fn test_details_of_an_answer() {
+    let mut ui_state = State::new();
+    ui_state.messages.push(Message::new("user", "Hi"));
+    ui_state.start_new_response();
+    ui_state.update_response("Hello");
+    ui_state.add_response(String::from("Hello"));
+    ui_state.annotate_generation(Generation {
+        model: "llama3".to_string(),
+        temperature: 0.7,
+        top_p: None,
+        max_tokens: Some(128),
+    });
+    ui_state.annotate_metadata(Metadata {
+        completion_tokens: Some(2),
+        finish_reason: Some("stop".to_string()),
+        ..Metadata::default()
+    });
+
+    // The time the answer took is recorded when it ends.
+    let metadata = ui_state.messages[1].metadata.clone().unwrap();
+    assert!(metadata.total_ms.is_some());
+    assert!(metadata.first_token_ms <= metadata.total_ms);
+    assert_eq!(metadata.completion_tokens, Some(2));
+
+    ui_state.list_state.select(Some(0));
+    ui_state.open_details();
+    assert!(ui_state.details.is_none());
+    assert_eq!(ui_state.messages.last().unwrap().role, "system");
+
+    ui_state.list_state.select(Some(1));
+    ui_state.open_details();
+    assert_eq!(ui_state.input_mode, InputMode::Details);
+    let details = ui_state.details.clone().unwrap();
+    assert!(details.contains(&("Model", "llama3".to_string())));
+    assert!(details.contains(&("max_tokens", "128".to_string())));
+    assert!(details.contains(&("Finish reason", "stop".to_string())));
+    assert!(details.contains(&("Request id", "-".to_string())));
+
+    ui_state.close_details();
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+    assert!(ui_state.details.is_none());
+}
+
+#[test]
+fn test_context_of_the_next_request() {
+    let mut ui_state = State::new();
+    ui_state.system_prompt = Some(String::from("Be brief."));
+    ui_state.messages.push(Message::new("user", "Hi"));
+    ui_state.messages.push(Message::new("assistant", "Hello!"));
+    ui_state.attach_context(String::from("File `notes.md`:\n- milk"));
+
+    let mut view = ui_state.next_request("What do I need?");
+    let titles: Vec<&str> = view.sections.iter().map(|s| s.title.as_str()).collect();
+    assert_eq!(
+        titles,
+        vec![
+            "System prompt",
+            "History, 2 message(s)",
+            "Attachments",
+            "Next message"
+        ]
+    );
+    assert_eq!(view.sections[0].text, "Be brief.");
+    assert_eq!(
+        view.sections[1].text,
+        "[user, ~2 tokens]\nHi\n\n[assistant, ~2 tokens]\nHello!"
+    );
+    assert_eq!(view.budget, 4096);
+    assert_eq!(
+        view.tokens(),
+        view.sections.iter().map(|s| s.tokens).sum::<usize>()
+    );
+
+    // Retrieved excerpts go before the message.
+    view.insert_before_message(Section::new("Retrieved excerpts", String::from("a")));
+    assert_eq!(view.sections[3].title, "Retrieved excerpts");
+    assert_eq!(view.sections[4].title, "Next message");
+
+    // Without a draft, only what is already queued is shown.
+    let empty = State::new().next_request("");
+    assert!(empty.sections.is_empty());
+
+    // `c` asks for the viewer, which is opened with the request.
+    let handler = InputHandler::new();
+    handler.handle_normal_mode(&mut ui_state, KeyCode::Char('c'));
+    assert!(ui_state.take_context_request());
+    assert!(!ui_state.take_context_request());
+    ui_state.open_context(view);
+    assert_eq!(ui_state.input_mode, InputMode::Context);
+    handler.handle_context_mode(&mut ui_state, KeyCode::PageDown);
+    assert_eq!(ui_state.context_view.as_ref().unwrap().scroll, 10);
+    handler.handle_context_mode(&mut ui_state, KeyCode::Esc);
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+    assert!(ui_state.context_view.is_none());
+
+    assert_eq!(
+        Command::parse("/context why?"),
+        Some(Command::Context(String::from("why?")))
+    );
+}
+
+#[test]
+fn test_continue_an_answer_cut_off() {
+    let mut ui_state = State::new();
+    ui_state.messages.push(Message::new("user", "Count to ten"));
+    ui_state.messages.push(Message {
+        metadata: Some(Metadata {
+            completion_tokens: Some(8),
+            total_ms: Some(1000),
+            finish_reason: Some("length".to_string()),
+            ..Metadata::default()
+        }),
+        ..Message::new("assistant", "1, 2, 3, 4,")
+    });
+    assert_eq!(ui_state.cut_off_answer(), Some(1));
+
+    // An answer the user stopped is not offered to be continued.
+    ui_state.messages[1].stopped = true;
+    assert_eq!(ui_state.cut_off_answer(), None);
+    let handler = InputHandler::new();
+    handler.handle_normal_mode(&mut ui_state, KeyCode::Char('m'));
+    assert!(!ui_state.take_continuation_request());
+    assert_eq!(ui_state.messages.pop().unwrap().role, "system");
+    ui_state.messages[1].stopped = false;
+
+    handler.handle_normal_mode(&mut ui_state, KeyCode::Char('m'));
+    assert!(ui_state.take_continuation_request());
+
+    let (history, continuation) = ui_state.start_continuation().unwrap();
+    let slot = continuation.slot;
+    assert_eq!(slot, 1);
+    assert_eq!(ui_state.input_mode, InputMode::Waiting);
+    assert_eq!(history.len(), 3);
+    assert_eq!(history[1].content, "1, 2, 3, 4,");
+    assert_eq!(history[2].content, CONTINUE_PROMPT);
+
+    ui_state.update_retry(slot, " 5, 6, 7, 8, 9, 10");
+    ui_state.annotate_metadata_at(
+        slot,
+        Metadata {
+            completion_tokens: Some(12),
+            finish_reason: Some("stop".to_string()),
+            ..Metadata::default()
+        },
+    );
+    ui_state.finish_continuation(continuation, None);
     assert_eq!(ui_state.input_mode, InputMode::Normal);
+    assert_eq!(ui_state.messages.len(), 2);
+    assert_eq!(
+        ui_state.messages[1].content,
+        "1, 2, 3, 4, 5, 6, 7, 8, 9, 10"
+    );
+    let metadata = ui_state.messages[1].metadata.clone().unwrap();
+    assert_eq!(metadata.completion_tokens, Some(20));
+    assert!(metadata.total_ms >= Some(1000));
+    assert_eq!(ui_state.cut_off_answer(), None);
+
+    // A continuation reopening the code block it stopped in is stitched
+    // into it.
+    ui_state.messages.push(Message {
+        metadata: Some(Metadata {
+            finish_reason: Some("length".to_string()),
+            ..Metadata::default()
+        }),
+        ..Message::new(
+            "assistant",
+            "```rust
+fn main() {
+    println!(\"hi",
+        )
+    });
+    let (_, continuation) = ui_state.start_continuation().unwrap();
+    let slot = continuation.slot;
+    ui_state.update_retry(
+        slot,
+        "```rust
+    println!(\"hi\");
+}
+```",
+    );
+    ui_state.finish_continuation(continuation, None);
+    assert_eq!(
+        ui_state.messages[slot].content,
+        "```rust
+fn main() {
+    println!(\"hi\");
+}
+```"
+    );
+
+    assert_eq!(Command::parse("/continue"), Some(Command::Continue));
+}
+
+#[test]
+fn test_mark_messages() {
+    let mut ui_state = State::new();
+    ui_state.messages = vec![
+        Message::new("user", "Name a prime"),
+        Message::new("assistant", "7"),
+        Message::new("user", "Name an even prime"),
+        Message::new("assistant", "4"),
+        Message::new("user", "Name a square"),
+        Message::new("assistant", "9"),
+    ];
+
+    let handler = InputHandler::new();
+    ui_state.list_state.select(Some(1));
+    handler.handle_normal_mode(&mut ui_state, KeyCode::Char('+'));
+    handler.handle_normal_mode(&mut ui_state, KeyCode::Char('*'));
+    ui_state.list_state.select(Some(3));
+    handler.handle_normal_mode(&mut ui_state, KeyCode::Char('-'));
+    assert_eq!(ui_state.messages[1].marks, vec![Mark::Good, Mark::Favorite]);
+    assert_eq!(ui_state.messages[3].marks, vec![Mark::Bad]);
+
+    // Exchanges are kept or left out with the prompt and its answers.
+    let good = MarkFilter {
+        only: vec![Mark::Good],
+        skip: Vec::new(),
+    };
+    let kept: Vec<String> = good
+        .apply(&ui_state.messages)
+        .into_iter()
+        .map(|m| m.content)
+        .collect();
+    assert_eq!(kept, vec!["Name a prime", "7"]);
+    let not_bad = MarkFilter {
+        only: Vec::new(),
+        skip: vec![Mark::Bad],
+    };
+    assert_eq!(not_bad.apply(&ui_state.messages).len(), 4);
+
+    ui_state.open_marked(good);
+    assert_eq!(ui_state.input_mode, InputMode::Pager);
+    handler.handle_pager_mode(&mut ui_state, KeyCode::Char('q'));
+    assert!(ui_state.pager.filter.is_none());
+
+    // System notices cannot be marked, and no exchange is opened if none
+    // has the mark.
+    ui_state.add_system_message("Switched model".to_string());
+    ui_state.list_state.select(Some(6));
+    handler.handle_normal_mode(&mut ui_state, KeyCode::Char('+'));
+    assert!(ui_state.messages[6].marks.is_empty());
+    ui_state.messages[1].toggle_mark(Mark::Favorite);
+    ui_state.open_marked(MarkFilter {
+        only: vec![Mark::Favorite],
+        skip: Vec::new(),
+    });
+    assert_eq!(ui_state.input_mode, InputMode::Normal);
+    assert!(ui_state
+        .messages
+        .last()
+        .unwrap()
+        .content
+        .starts_with("No exchange is marked ★ favorite"));
+
+    assert_eq!(
+        Command::parse("/export +good -bad ~/good.md"),
+        Some(Command::Export(
+            Some(chatti::commands::expand_home("~/good.md")),
+            MarkFilter {
+                only: vec![Mark::Good],
+                skip: vec![Mark::Bad],
+            }
+        ))
+    );
+    assert_eq!(
+        Command::parse("/export -bad -- -draft.md"),
+        Some(Command::Export(
+            Some(std::path::PathBuf::from("-draft.md")),
+            MarkFilter {
+                only: Vec::new(),
+                skip: vec![Mark::Bad],
+            }
+        ))
+    );
+    assert_eq!(
+        Command::parse("/marked"),
+        Some(Command::Marked(MarkFilter {
+            only: Mark::ALL.to_vec(),
+            skip: Vec::new(),
+        }))
+    );
+    assert!(matches!(
+        Command::parse("/marked great"),
+        Some(Command::Invalid(_))
+    ));
+}
+
+#[test]
+fn test_apply_plugin_replies() {
+    let mut ui_state = State::new();
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+    ui_state.plugin_replies = Some(rx);
+    ui_state.input = String::from("Weather: ");
+
+    let reply = |action| {
+        Ok(Reply {
+            plugin: String::from("weather"),
+            action,
+        })
+    };
+    tx.send(reply(Action::InsertText {
+        text: String::from("sunny\r\n"),
+    }))
+    .unwrap();
+    tx.send(reply(Action::AddMessage {
+        content: String::from("Fetched the forecast"),
+    }))
+    .unwrap();
+    tx.send(Err(PluginError::TimedOut {
+        plugin: String::from("slow"),
+        seconds: 5,
+    }))
+    .unwrap();
+    ui_state.apply_plugin_replies();
+
+    assert_eq!(ui_state.input, "Weather: sunny\n");
+    let notices: Vec<&str> = ui_state
+        .messages
+        .iter()
+        .map(|message| message.content.as_str())
+        .collect();
+    assert_eq!(
+        notices,
+        vec![
+            "weather: Fetched the forecast",
+            "plugin `slow` took longer than 5s and was stopped"
+        ]
+    );
+    // The inserted text is one step to undo.
+    ui_state.undo_input();
+    assert_eq!(ui_state.input, "Weather: ");
+}
+
+#[test]
+fn test_clear_conversation_can_be_undone() {
+    let input_handler = InputHandler::new();
+    let mut ui_state = State::new();
+    ui_state.input = String::from("draft");
+    ui_state.undo.record("draf", Edit::Insert);
+    ui_state.session_id = Some(String::from("abc"));
+    let mut session = Session::new();
+    session.messages = vec![
+        Message::new("user", "Hi"),
+        Message::new("assistant", "Hello"),
+    ];
+    ui_state.load_messages(session.messages.clone());
+
+    ui_state.clear_conversation(session.clone());
+    assert!(ui_state.messages.is_empty());
+    assert_eq!(ui_state.session_id, None);
+    assert_eq!(Command::parse("/clear all"), Some(Command::Clear(true)));
+    assert_eq!(Command::parse("/clear undo"), Some(Command::UndoClear));
+    assert!(matches!(
+        Command::parse("/clear now"),
+        Some(Command::Invalid(_))
+    ));
+
+    // Within the window, u brings the conversation back instead of undoing
+    // the input.
+    input_handler.handle_normal_mode(&mut ui_state, KeyCode::Char('u'));
+    assert_eq!(ui_state.input, "draft");
+    assert!(ui_state.take_clear_undo_request());
+    assert_eq!(ui_state.take_cleared().unwrap().messages, session.messages);
+    assert!(ui_state.take_cleared().is_none());
+
+    ui_state.clear_conversation(session);
+    let cleared = ui_state.cleared.as_mut().unwrap();
+    cleared.at = cleared.at.checked_sub(CLEAR_UNDO_WINDOW).unwrap();
+    assert!(!ui_state.can_undo_clear());
+    input_handler.handle_normal_mode(&mut ui_state, KeyCode::Char('u'));
+    assert_eq!(ui_state.input, "draf");
+    assert!(ui_state.take_cleared().is_none());
+}
+
+#[test]
+fn test_jump_to_messages_new_since_last_read() {
+    let input_handler = InputHandler::new();
+    let mut ui_state = State::new();
+    ui_state.load_messages(vec![
+        Message::new("user", "Hello"),
+        Message::new("assistant", "Hi"),
+        Message::new("user", "How are you?"),
+        Message::new("assistant", "Fine"),
+    ]);
+    assert_eq!(ui_state.last_read, 4);
+
+    assert_eq!(ui_state.mark_unread(Some(4)), 0);
+    assert_eq!(ui_state.mark_unread(None), 0);
+    assert_eq!(ui_state.mark_unread(Some(2)), 2);
+    assert!(ui_state.messages[2].first_unread);
+
+    input_handler.handle_normal_mode(&mut ui_state, KeyCode::Char('n'));
+    assert_eq!(ui_state.list_state.selected(), Some(2));
+    assert!(!ui_state.follow);
+
+    let mut session = Session::from_messages(ui_state.conversation());
+    session.last_read = Some(2);
+    let json = serde_json::to_string(&session).unwrap();
+    let loaded: Session = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.last_read, Some(2));
+    assert!(loaded.messages.iter().all(|m| !m.first_unread));
+}
+
+#[test]
+fn test_reply_quoting_the_selected_message() {
+    let input_handler = InputHandler::new();
+    let mut ui_state = State::new();
+    ui_state.load_messages(vec![
+        Message::new("user", "Which collection?"),
+        Message::new(
+            "assistant",
+            "Use a `VecDeque`:\nit pops from both ends\nin constant time\nand grows as needed\nwithout moving.\n\nA `Vec` works too.",
+        ),
+    ]);
+
+    input_handler.handle_normal_mode(&mut ui_state, KeyCode::Char('>'));
+    assert_eq!(ui_state.input_mode, InputMode::Editing);
+    assert_eq!(
+        ui_state.input,
+        "> Use a `VecDeque`:\n> it pops from both ends\n> in constant time\n> and grows as needed\n> …\n\n"
+    );
+
+    ui_state.insert_text("Why not a list?");
+    assert!(ui_state.input.ends_with("\n\nWhy not a list?"));
+    ui_state.undo_input();
+    assert!(ui_state.input.ends_with("> …\n\n"));
+}
+
+#[test]
+fn test_code_blocks_by_number() {
+    assert_eq!(Command::parse("/copy 2"), Some(Command::Copy(2)));
+    assert_eq!(
+        Command::parse("/save 1 build.sh"),
+        Some(Command::Save(1, std::path::PathBuf::from("build.sh")))
+    );
+    assert!(matches!(
+        Command::parse("/copy 0"),
+        Some(Command::Invalid(_))
+    ));
+    assert!(matches!(
+        Command::parse("/save 1"),
+        Some(Command::Invalid(_))
+    ));
+
+    let mut ui_state = State::new();
+    assert_eq!(ui_state.code_block_answer(), None);
+    ui_state.load_messages(vec![
+        Message::new("user", "Build and test?"),
+        Message::new(
+            "assistant",
+            "```sh\ncargo build\n```\n\n```sh\ncargo test\n```",
+        ),
+        Message::new("user", "Thanks"),
+        Message::new("assistant", "You're welcome."),
+    ]);
+    let blocks = ui_state.code_block_answer().unwrap();
+    assert_eq!(blocks, vec!["cargo build\n", "cargo test\n"]);
 }